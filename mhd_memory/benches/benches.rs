@@ -52,8 +52,102 @@ fn bench_mhd_memory_sizes(c: &mut Criterion) {
     group.finish();
 } // end bench_mhd_memory_sizes
 
+// Row-wise vs. transposed (bit sliced) reads, see BitSlices
+fn bench_bit_slices(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bit_slices");
+    group.sampling_mode(SamplingMode::Flat);
+
+    const WIDTH: usize = 256;
+    for height in [1024, 16384].iter() {
+        let mut mem = MhdMemory::new(WIDTH);
+        mem.write_n_random_samples(*height);
+        let mask = Sample::random(WIDTH);
+        let query = Sample::random(WIDTH);
+        for bit_sliced in [false, true].iter() {
+            mem.use_bit_slices(*bit_sliced);
+            let name = if *bit_sliced { "columns" } else { "rows" };
+            group.bench_function(BenchmarkId::new(name, *height), |b| {
+                b.iter(|| mem.read_2_priorities(&mask.bytes, &query.bytes, 0))
+            });
+        }
+    } // end for heights
+
+    group.finish();
+} // end bench_bit_slices
+
+// The masked hamming distance, computed by each backend the CPU supports, see SimdBackend
+fn bench_simd_backends(c: &mut Criterion) {
+    let mut group = c.benchmark_group("simd_backends");
+
+    for num_bytes in [32, 256, 4096].iter() {
+        let mask = Sample::random(8 * num_bytes);
+        let x = Sample::random(8 * num_bytes);
+        let y = Sample::random(8 * num_bytes);
+        for backend in SimdBackend::available() {
+            group.bench_function(BenchmarkId::new(backend.name(), *num_bytes), |b| {
+                b.iter(|| backend.distance(&mask.bytes, &x.bytes, &y.bytes))
+            });
+        }
+    } // end for sizes
+
+    group.finish();
+} // end bench_simd_backends
+
+// The population counts (plain and masked) and the scalar masked hamming distance
+fn bench_popcount(c: &mut Criterion) {
+    let mut group = c.benchmark_group("popcount");
+
+    for num_bytes in [32, 256, 4096].iter() {
+        let mask = Sample::random(8 * num_bytes);
+        let x = Sample::random(8 * num_bytes);
+        let y = Sample::random(8 * num_bytes);
+        group.bench_function(BenchmarkId::new("weight", *num_bytes), |b| {
+            b.iter(|| weight(&x.bytes))
+        });
+        group.bench_function(BenchmarkId::new("masked_weight", *num_bytes), |b| {
+            b.iter(|| masked_weight(&mask.bytes, &x.bytes))
+        });
+        group.bench_function(BenchmarkId::new("distance_fast", *num_bytes), |b| {
+            b.iter(|| distance_fast(&mask.bytes, &x.bytes, &y.bytes))
+        });
+    } // end for sizes
+
+    group.finish();
+} // end bench_popcount
+
+// One query against many rows: one distance call per row, or distance_many
+fn bench_distance_many(c: &mut Criterion) {
+    let mut group = c.benchmark_group("distance_many");
+
+    const WIDTH: usize = 256;
+    for height in [1024, 16384].iter() {
+        let rows: Vec<Sample> = (0..*height).map(|_| Sample::random(WIDTH)).collect();
+        let mask = Sample::random(WIDTH);
+        let query = Sample::random(WIDTH);
+        group.bench_function(BenchmarkId::new("one_by_one", *height), |b| {
+            b.iter(|| {
+                rows.iter()
+                    .map(|row| distance(&mask.bytes, &query.bytes, &row.bytes))
+                    .collect::<Vec<u64>>()
+            })
+        });
+        group.bench_function(BenchmarkId::new("distance_many", *height), |b| {
+            b.iter(|| distance_many(&mask.bytes, &query.bytes, &rows))
+        });
+    } // end for heights
+
+    group.finish();
+} // end bench_distance_many
+
 // criterion_group!(randomBenches, );
-criterion_group!(benches, bench_mhd_memory_sizes,);
+criterion_group!(
+    benches,
+    bench_mhd_memory_sizes,
+    bench_bit_slices,
+    bench_simd_backends,
+    bench_popcount,
+    bench_distance_many,
+);
 criterion_main!(benches);
 
 /************* obsolete benchmarks ************
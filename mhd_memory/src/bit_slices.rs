@@ -0,0 +1,242 @@
+/// # Bit Slices -- a transposed layout of the samples
+///
+/// An `MhdMemory` stores its samples row by row. For reads which need the distance from a
+/// query to *every* sample, the transposed layout is better: one bitset ("column") per
+/// bit position, holding that bit of every sample, 64 samples per word.
+/// Then the distances to 64 samples at once are a handful of wide XOR and AND operations
+/// per (masked) bit position, counted in bit-sliced counters -- instead of one
+/// masked hamming distance per sample.
+///
+/// Bit positions here are the raw positions in the sample's bytes (bit `p % 8` of byte `p / 8`),
+/// so that distances are exactly those of `distance(mask, query, &sample.bytes)`.
+///
+/// ```rust
+/// use mhd_memory::{distance, BitSlices, Sample};
+///
+/// let samples: Vec<Sample> = (0..100).map(|_| Sample::random(40)).collect();
+/// let mut slices = BitSlices::new(5); // 40 bits = 5 bytes
+/// for sample in samples.iter() {
+///     slices.push(&sample.bytes);
+/// }
+/// let mask = vec![0xF0, 0xFF, 0x00, 0x0F, 0xFF];
+/// let query = Sample::random(40);
+/// let distances = slices.distances(&mask, &query.bytes);
+/// for (sample, dist) in samples.iter().zip(distances) {
+///     assert_eq!(dist, distance(&mask, &query.bytes, &sample.bytes));
+/// }
+/// ```
+use rayon::prelude::*;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BitSlices {
+    num_bytes: usize,
+    num_samples: usize,
+    columns: Vec<Vec<u64>>, // columns[position][word]: bit (sample % 64) of word (sample / 64)
+}
+
+impl BitSlices {
+    /// Bit slices for samples of `num_bytes` bytes each
+    pub fn new(num_bytes: usize) -> Self {
+        Self {
+            num_bytes,
+            num_samples: 0,
+            columns: vec![Vec::new(); 8 * num_bytes],
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.num_samples
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        0 == self.num_samples
+    }
+
+    /// Approximate number of bytes held (the columns, on the heap)
+    pub fn memory_bytes(&self) -> usize {
+        self.columns.capacity() * std::mem::size_of::<Vec<u64>>()
+            + self
+                .columns
+                .iter()
+                .map(|column| column.capacity() * std::mem::size_of::<u64>())
+                .sum::<usize>()
+    }
+
+    pub fn clear(&mut self) {
+        for column in self.columns.iter_mut() {
+            column.clear();
+        }
+        self.num_samples = 0;
+    }
+
+    /// Append one more sample's bytes
+    pub fn push(&mut self, bytes: &[u8]) {
+        assert_eq!(bytes.len(), self.num_bytes);
+        let word = self.num_samples / 64;
+        let bit = 1u64 << (self.num_samples % 64);
+        for (position, column) in self.columns.iter_mut().enumerate() {
+            if column.len() <= word {
+                column.push(0);
+            };
+            if 0 != bytes[position / 8] & (1 << (position % 8)) {
+                column[word] |= bit;
+            };
+        }
+        self.num_samples += 1;
+    }
+
+    /// Remove sample number `sample` (later samples move up one, as in `Vec::remove`)
+    pub fn remove(&mut self, sample: usize) {
+        assert!(sample < self.num_samples);
+        let first_word = sample / 64;
+        let below = (1u64 << (sample % 64)) - 1; // the bits which stay where they are
+        let shrink = (self.num_samples - 1).is_multiple_of(64); // the last word becomes empty
+        for column in self.columns.iter_mut() {
+            let word = column[first_word];
+            column[first_word] = (word & below) | ((word >> 1) & !below);
+            for index in first_word + 1..column.len() {
+                column[index - 1] |= (column[index] & 1) << 63;
+                column[index] >>= 1;
+            }
+            if shrink {
+                column.pop();
+            };
+        }
+        self.num_samples -= 1;
+    }
+
+    /// Raw bit `position` of sample number `sample`
+    #[inline]
+    pub fn get(&self, position: usize, sample: usize) -> bool {
+        assert!(sample < self.num_samples);
+        0 != self.columns[position][sample / 64] & (1u64 << (sample % 64))
+    }
+
+    /// The raw position (as in `get`) of bit `bit_index` as `Sample::get_bit` counts them
+    /// (the most significant bit of each byte first)
+    #[inline]
+    pub fn position_of_bit(bit_index: usize) -> usize {
+        8 * (bit_index / 8) + 7 - bit_index % 8
+    }
+
+    /// The column of raw bit `position`: that bit of every sample, 64 samples per word
+    /// (sample `s` is bit `s % 64` of word `s / 64`; the bits after the last sample are zero)
+    #[inline]
+    pub fn column(&self, position: usize) -> &[u64] {
+        &self.columns[position]
+    }
+
+    /// The masked hamming distance from `query` to every sample (in the order they were pushed)
+    pub fn distances(&self, mask: &[u8], query: &[u8]) -> Vec<u64> {
+        assert_eq!(mask.len(), self.num_bytes);
+        assert_eq!(query.len(), self.num_bytes);
+        // The (masked) positions to compare, and the query's bit at each
+        let positions: Vec<(usize, bool)> = (0..8 * self.num_bytes)
+            .filter(|position| 0 != mask[position / 8] & (1 << (position % 8)))
+            .map(|position| (position, 0 != query[position / 8] & (1 << (position % 8))))
+            .collect();
+        // Enough bit planes to count up to the number of positions
+        let num_planes = (usize::BITS - positions.len().leading_zeros()) as usize;
+        let num_words = self.num_samples.div_ceil(64);
+
+        let mut result: Vec<u64> = (0..num_words)
+            .into_par_iter() // RAYON!
+            .flat_map_iter(|word| {
+                // Bit-sliced counters: planes[k] holds bit k of 64 distances
+                let mut planes = vec![0u64; num_planes];
+                for &(position, query_bit) in positions.iter() {
+                    let column = self.columns[position][word];
+                    let mut carry = if query_bit { !column } else { column };
+                    for plane in planes.iter_mut() {
+                        if 0 == carry {
+                            break;
+                        };
+                        let next_carry = *plane & carry;
+                        *plane ^= carry;
+                        carry = next_carry;
+                    }
+                }
+                (0..64).map(move |bit| {
+                    planes
+                        .iter()
+                        .enumerate()
+                        .map(|(k, plane)| ((plane >> bit) & 1) << k)
+                        .sum::<u64>()
+                })
+            })
+            .collect();
+        result.truncate(self.num_samples);
+        result
+    } // end distances
+} // end impl BitSlices
+
+///////////////////// TESTs for BitSlices /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use distance_::distance;
+    use sample::{Sample, ZERO_SCORE};
+
+    #[test]
+    fn test_bit_slices() {
+        const NUM_BITS: usize = 20; // not a multiple of 8, on purpose
+        let num_bytes = Sample::new(NUM_BITS, ZERO_SCORE).size_in_bytes();
+        let mut slices = BitSlices::new(num_bytes);
+        assert!(slices.is_empty());
+        assert!(slices
+            .distances(&vec![0xFF; num_bytes], &vec![0; num_bytes])
+            .is_empty());
+
+        let samples: Vec<Sample> = (0..150).map(|_| Sample::random(NUM_BITS)).collect();
+        for sample in samples.iter() {
+            slices.push(&sample.bytes);
+        }
+        assert_eq!(slices.len(), 150);
+        assert_eq!(slices.get(3, 149), 0 != samples[149].bytes[0] & (1 << 3));
+        for bit_index in 0..NUM_BITS {
+            let column = slices.column(BitSlices::position_of_bit(bit_index));
+            for (index, sample) in samples.iter().enumerate() {
+                let bit = 0 != column[index / 64] & (1u64 << (index % 64));
+                assert_eq!(bit, sample.get_bit(bit_index));
+            }
+        }
+
+        for _ in 0..10 {
+            let mask = Sample::random(NUM_BITS).bytes;
+            let query = Sample::random(NUM_BITS).bytes;
+            let distances = slices.distances(&mask, &query);
+            assert_eq!(distances.len(), samples.len());
+            for (sample, dist) in samples.iter().zip(distances) {
+                assert_eq!(dist, distance(&mask, &query, &sample.bytes));
+            }
+        }
+        // An all-ones mask counts every differing bit (even the padding)
+        let all = vec![0xFF; num_bytes];
+        let distances = slices.distances(&all, &samples[7].bytes);
+        assert_eq!(distances[7], 0);
+        assert_eq!(
+            distances[8],
+            distance(&all, &samples[7].bytes, &samples[8].bytes)
+        );
+
+        // Removing samples (across word boundaries, too)
+        let mut samples = samples;
+        for index in [149, 128, 63, 64, 0, 70].iter().cloned() {
+            slices.remove(index);
+            samples.remove(index);
+        }
+        assert_eq!(slices.len(), samples.len());
+        let query = Sample::random(NUM_BITS).bytes;
+        let distances = slices.distances(&all, &query);
+        for (sample, dist) in samples.iter().zip(distances) {
+            assert_eq!(dist, distance(&all, &query, &sample.bytes));
+        }
+        slices.push(&samples[0].bytes);
+        assert_eq!(slices.distances(&all, &samples[0].bytes)[samples.len()], 0);
+
+        slices.clear();
+        assert!(slices.is_empty());
+    }
+}
@@ -0,0 +1,317 @@
+/// # Building a memory
+///
+/// A memory has quite a few knobs (see `MhdMemoryConfig`, `DistanceKernel` and
+/// `SampleWeighting`), and not every setting of them makes sense: a threshold wider than the
+/// samples, a negative kernel exponent... `MhdMemoryBuilder` sets them one by one, and `build`
+/// checks them all, before there is a memory to misbehave.
+///
+/// ```rust
+/// use mhd_memory::{ConfigError, EvictionPolicy, KernelShape, MhdMemoryBuilder};
+///
+/// let mut memory = MhdMemoryBuilder::new(64)
+///     .max_samples(1000)
+///     .eviction(EvictionPolicy::LowestScore)
+///     .threshold(8)
+///     .kernel_shape(KernelShape::Power)
+///     .kernel_exponent(2.0)
+///     .ucb_constant(2.0)
+///     .seed(42)
+///     .build()
+///     .expect("a valid configuration");
+/// memory.write_n_random_samples(10);
+/// assert_eq!(memory.num_samples(), 10);
+///
+/// assert_eq!(
+///     MhdMemoryBuilder::new(64).threshold(65).build().err(),
+///     Some(ConfigError::Threshold(65))
+/// );
+/// ```
+use std::error::Error;
+use std::fmt;
+
+use mhdmemory::{
+    DistanceKernel, EvictionPolicy, KernelShape, MhdMemory, MhdMemoryConfig, SampleWeighting,
+};
+use sample::Sample;
+
+/// What `MhdMemoryBuilder::build` found wrong
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigError {
+    /// Samples can't be this wide (or narrow)
+    Width(usize),
+    /// A memory has to keep at least one sample
+    MaxSamples,
+    /// The threshold is wider than the samples
+    Threshold(u64),
+    /// The kernel exponent has to be positive (and finite)
+    KernelExponent(f64),
+    /// The UCB constant can't be negative (or infinite)
+    UcbConstant(f64),
+    /// The recency half life has to be positive
+    HalfLife(f64),
+    /// Approximate reads have to visit at least one sample
+    ReadSubsample,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Width(width) => write!(f, "illegal sample width {}", width),
+            ConfigError::MaxSamples => write!(f, "max_samples must be at least 1"),
+            ConfigError::Threshold(threshold) => {
+                write!(f, "threshold {} is wider than the samples", threshold)
+            }
+            ConfigError::KernelExponent(exponent) => {
+                write!(f, "kernel exponent {} is not positive", exponent)
+            }
+            ConfigError::UcbConstant(constant) => write!(f, "illegal UCB constant {}", constant),
+            ConfigError::HalfLife(half_life) => {
+                write!(f, "recency half life {} is not positive", half_life)
+            }
+            ConfigError::ReadSubsample => write!(f, "read_subsample must be at least 1"),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MhdMemoryBuilder {
+    width: usize,
+    config: MhdMemoryConfig,
+    kernel: DistanceKernel,
+    weighting: SampleWeighting,
+}
+
+impl MhdMemoryBuilder {
+    /// A builder for a memory of samples `width` bits wide, with all the defaults
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            config: MhdMemoryConfig::default(),
+            kernel: DistanceKernel::default(),
+            weighting: SampleWeighting::default(),
+        }
+    }
+
+    /// See `MhdMemoryConfig::max_samples`
+    pub fn max_samples(mut self, max_samples: usize) -> Self {
+        self.config.max_samples = Some(max_samples);
+        self
+    }
+
+    /// See `MhdMemoryConfig::eviction`
+    pub fn eviction(mut self, eviction: EvictionPolicy) -> Self {
+        self.config.eviction = eviction;
+        self
+    }
+
+    /// See `MhdMemoryConfig::parallel_threshold`
+    pub fn parallel_threshold(mut self, parallel_threshold: usize) -> Self {
+        self.config.parallel_threshold = parallel_threshold;
+        self
+    }
+
+    /// See `DistanceKernel::threshold`
+    pub fn threshold(mut self, threshold: u64) -> Self {
+        self.kernel.threshold = Some(threshold);
+        self
+    }
+
+    /// See `DistanceKernel::shape`
+    pub fn kernel_shape(mut self, shape: KernelShape) -> Self {
+        self.kernel.shape = shape;
+        self
+    }
+
+    /// See `DistanceKernel::exponent`
+    pub fn kernel_exponent(mut self, exponent: f64) -> Self {
+        self.kernel.exponent = exponent;
+        self
+    }
+
+    /// See `DistanceKernel::refit_interval`
+    pub fn refit_interval(mut self, refit_interval: usize) -> Self {
+        self.kernel.refit_interval = Some(refit_interval);
+        self
+    }
+
+    /// See `SampleWeighting`
+    pub fn weighting(mut self, weighting: SampleWeighting) -> Self {
+        self.weighting = weighting;
+        self
+    }
+
+    /// See `MhdMemoryConfig::ucb_constant`
+    pub fn ucb_constant(mut self, ucb_constant: f64) -> Self {
+        self.config.ucb_constant = Some(ucb_constant);
+        self
+    }
+
+    /// See `MhdMemoryConfig::read_subsample`
+    pub fn read_subsample(mut self, read_subsample: usize) -> Self {
+        self.config.read_subsample = Some(read_subsample);
+        self
+    }
+
+    /// See `MhdMemoryConfig::normalize_scores`
+    pub fn normalize_scores(mut self, normalize_scores: bool) -> Self {
+        self.config.normalize_scores = normalize_scores;
+        self
+    }
+
+    /// See `MhdMemoryConfig::overwrite_dominated`
+    pub fn overwrite_dominated(mut self, overwrite_dominated: bool) -> Self {
+        self.config.overwrite_dominated = overwrite_dominated;
+        self
+    }
+
+    /// See `MhdMemoryConfig::seed`
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    /// Check the configuration
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !Sample::size_is_legal(self.width) {
+            return Err(ConfigError::Width(self.width));
+        };
+        if Some(0) == self.config.max_samples {
+            return Err(ConfigError::MaxSamples);
+        };
+        if Some(0) == self.config.read_subsample {
+            return Err(ConfigError::ReadSubsample);
+        };
+        if let Some(threshold) = self.kernel.threshold {
+            if (self.width as u64) < threshold {
+                return Err(ConfigError::Threshold(threshold));
+            };
+        };
+        let exponent = self.kernel.exponent;
+        if !(exponent.is_finite() && 0.0 < exponent) {
+            return Err(ConfigError::KernelExponent(exponent));
+        };
+        if let Some(constant) = self.config.ucb_constant {
+            if !(constant.is_finite() && 0.0 <= constant) {
+                return Err(ConfigError::UcbConstant(constant));
+            };
+        };
+        if let Some(half_life) = self.weighting.recency_half_life {
+            if half_life.is_nan() || half_life <= 0.0 {
+                return Err(ConfigError::HalfLife(half_life));
+            };
+        };
+        Ok(())
+    }
+
+    /// The memory, if the configuration is valid
+    pub fn build(self) -> Result<MhdMemory, ConfigError> {
+        self.validate()?;
+        let mut memory = MhdMemory::with_config(self.width, self.config);
+        memory.kernel = self.kernel;
+        memory.weighting = self.weighting;
+        Ok(memory)
+    }
+} // end impl MhdMemoryBuilder
+
+///////////////////// TESTs for MhdMemoryBuilder /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mhdmemory::PrioritySums;
+    use sample::ScoreType;
+
+    #[test]
+    fn test_builder() {
+        let memory = MhdMemoryBuilder::new(32)
+            .max_samples(10)
+            .threshold(4)
+            .kernel_exponent(0.5)
+            .refit_interval(100)
+            .parallel_threshold(64)
+            .build()
+            .unwrap();
+        assert_eq!(memory.width(), 32);
+        assert_eq!(memory.config.max_samples, Some(10));
+        assert_eq!(memory.config.parallel_threshold, 64);
+        assert_eq!(memory.kernel.threshold, Some(4));
+        assert_eq!(memory.kernel.exponent, 0.5);
+        assert_eq!(memory.kernel.refit_interval, Some(100));
+
+        // Everything that can go wrong
+        let errors = vec![
+            (MhdMemoryBuilder::new(2), ConfigError::Width(2)),
+            (
+                MhdMemoryBuilder::new(32).max_samples(0),
+                ConfigError::MaxSamples,
+            ),
+            (
+                MhdMemoryBuilder::new(32).threshold(33),
+                ConfigError::Threshold(33),
+            ),
+            (
+                MhdMemoryBuilder::new(32).kernel_exponent(-1.0),
+                ConfigError::KernelExponent(-1.0),
+            ),
+            (
+                MhdMemoryBuilder::new(32).ucb_constant(f64::INFINITY),
+                ConfigError::UcbConstant(f64::INFINITY),
+            ),
+            (
+                MhdMemoryBuilder::new(32).weighting(SampleWeighting {
+                    recency_half_life: Some(0.0),
+                    score_importance: 0.0,
+                }),
+                ConfigError::HalfLife(0.0),
+            ),
+            (
+                MhdMemoryBuilder::new(32).read_subsample(0),
+                ConfigError::ReadSubsample,
+            ),
+        ];
+        for (builder, error) in errors {
+            assert_eq!(builder.build().err(), Some(error));
+            assert!(!error.to_string().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_seed() {
+        // The same seed, the same random samples
+        let build = |seed| {
+            let mut memory = MhdMemoryBuilder::new(64).seed(seed).build().unwrap();
+            memory.write_n_random_samples(20);
+            memory.samples
+        };
+        assert_eq!(build(7), build(7));
+        assert_ne!(build(7), build(8));
+        // ...and so does reseeding
+        let mut memory = MhdMemoryBuilder::new(64).build().unwrap();
+        memory.reseed(7);
+        memory.write_n_random_samples(20);
+        assert_eq!(memory.samples, build(7));
+    }
+
+    #[test]
+    fn test_ucb_constant() {
+        let sums = PrioritySums {
+            score_false: 50.0,
+            score_true: 50.0,
+            weight_false: 1.0,
+            weight_true: 1.0,
+            hits_false: 1,
+            hits_true: 3,
+        };
+        let max_score = 100 as ScoreType;
+        // Without exploration, only the scores count
+        assert_eq!(sums.priorities_with_ucb(max_score, Some(0.0)), (0.5, 0.5));
+        // With it, the alternative with fewer hits is preferred
+        let (prio_false, prio_true) = sums.priorities_with_ucb(max_score, Some(2.0));
+        assert!(prio_true < prio_false);
+        assert_eq!(
+            sums.priorities_with_ucb(max_score, None),
+            sums.priorities(max_score)
+        );
+    }
+}
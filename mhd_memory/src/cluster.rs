@@ -0,0 +1,362 @@
+/// # Clusters -- another index for large memories
+///
+/// Like an `LshIndex`, a `ClusterIndex` lets a read visit only some of the samples: those
+/// close to the query. It groups the samples into clusters, each around a medoid (one of its
+/// samples), by k-medoids under the hamming distance: starting with medoids far apart,
+/// every sample belongs to the cluster of its nearest medoid, and every medoid is the member
+/// with the smallest total distance to the others (both steps repeated until nothing
+/// changes, or `MAX_ITERATIONS` times).
+/// A read then measures the query's (masked) distance to each medoid only, and visits the
+/// members of the `num_probes` nearest clusters -- for `k` clusters of `n` samples, about
+/// `k + num_probes * n / k` distances instead of `n`, which is least for `k` around
+/// `sqrt(num_probes * n)` (see `MhdMemory::use_clusters`).
+///
+/// Samples inserted later join the cluster of their nearest medoid (the first few found new
+/// clusters, until there are `num_clusters`), but the medoids stay where they are: rebuild
+/// the index now and then, when many samples were added (see `MhdMemory::use_clusters`).
+/// Like LSH, this is approximate: close samples in other clusters are missed.
+///
+/// ```rust
+/// use mhd_memory::cluster::ClusterIndex;
+/// use mhd_memory::Sample;
+///
+/// let samples: Vec<Sample> = (0..1000).map(|_| Sample::random(64)).collect();
+/// let rows: Vec<&[u8]> = samples.iter().map(|s| &s.bytes[..]).collect();
+/// let index = ClusterIndex::build(&rows, 32, 2);
+/// assert_eq!((index.len(), index.num_clusters()), (1000, 32));
+///
+/// // A sample is always found (with a full mask), among a fraction of the others
+/// let full_mask = vec![0xFF; 8];
+/// let candidates = index.candidates(&full_mask, &samples[42].bytes).unwrap();
+/// assert!(candidates.contains(&42));
+/// assert!(candidates.len() < 500);
+///
+/// // With nothing in the mask, the index can't help
+/// assert!(index.candidates(&[0x00; 8], &samples[42].bytes).is_none());
+/// ```
+use rand::seq::index::sample;
+use rand::Rng;
+use rayon::prelude::*;
+use std::mem::size_of;
+
+use distance_::distance;
+use weight_::weight;
+
+/// Default number of clusters probed by a read, see `MhdMemory::use_clusters`
+pub const DEFAULT_CLUSTER_PROBES: usize = 3;
+/// The most rounds of k-medoids `ClusterIndex::build` does
+pub const MAX_ITERATIONS: usize = 10;
+/// When a cluster chooses its medoid, it tries at most this many of its members...
+pub const MAX_MEDOID_CANDIDATES: usize = 32;
+/// ... against at most this many others (chosen at random, for large clusters)
+pub const MAX_MEDOID_PEERS: usize = 256;
+
+#[derive(Debug, Clone)]
+struct Cluster {
+    medoid: Vec<u8>,
+    members: Vec<usize>, // indices of the samples, ascending
+}
+
+#[derive(Debug, Clone)]
+pub struct ClusterIndex {
+    clusters: Vec<Cluster>,
+    assignments: Vec<usize>, // the cluster of every sample
+    num_clusters: usize,     // the most clusters there may be
+    num_probes: usize,
+}
+
+impl ClusterIndex {
+    /// An empty index, for up to `num_clusters` clusters (at least one), of which a read
+    /// visits the `num_probes` (at least one) nearest
+    pub fn new(num_clusters: usize, num_probes: usize) -> Self {
+        Self {
+            clusters: Vec::new(),
+            assignments: Vec::new(),
+            num_clusters: num_clusters.max(1),
+            num_probes: num_probes.max(1),
+        }
+    }
+
+    /// An index of `rows` (the samples' bytes, all the same length), clustered by k-medoids
+    pub fn build<R: AsRef<[u8]> + Sync>(
+        rows: &[R],
+        num_clusters: usize,
+        num_probes: usize,
+    ) -> Self {
+        let mut index = Self::new(num_clusters, num_probes);
+        if rows.is_empty() {
+            return index;
+        };
+        let full_mask = vec![0xFF; rows[0].as_ref().len()];
+        let nearest = |medoids: &[&[u8]], row: &[u8]| -> usize {
+            (0..medoids.len())
+                .min_by_key(|c| distance(&full_mask, row, medoids[*c]))
+                .expect("Some medoids")
+        };
+
+        // Start with medoids far apart: one at random, then always the sample farthest
+        // from all medoids so far
+        let k = index.num_clusters.min(rows.len());
+        let mut medoids = vec![rand::thread_rng().gen_range(0..rows.len())];
+        let mut gaps: Vec<u64> = vec![u64::MAX; rows.len()]; // distance to the nearest medoid
+        while medoids.len() < k {
+            let last = rows[*medoids.last().expect("Some medoids")].as_ref();
+            gaps.par_iter_mut() // RAYON!
+                .zip(rows.par_iter())
+                .for_each(|(gap, row)| *gap = (*gap).min(distance(&full_mask, row.as_ref(), last)));
+            let farthest = (0..rows.len())
+                .max_by_key(|row| gaps[*row])
+                .expect("Some rows");
+            if 0 == gaps[farthest] {
+                break; // (fewer distinct samples than clusters)
+            };
+            medoids.push(farthest);
+        }
+        for _ in 0..MAX_ITERATIONS {
+            // Assign every sample to its nearest medoid...
+            let medoid_rows: Vec<&[u8]> = medoids.iter().map(|m| rows[*m].as_ref()).collect();
+            let assignments: Vec<usize> = rows
+                .par_iter() // RAYON!
+                .map(|row| nearest(&medoid_rows, row.as_ref()))
+                .collect();
+            let mut members = vec![Vec::new(); medoids.len()];
+            for (row, cluster) in assignments.iter().enumerate() {
+                members[*cluster].push(row);
+            }
+            // ... and move every medoid to the member closest to all the others
+            let new_medoids: Vec<usize> = members
+                .par_iter() // RAYON!
+                .zip(medoids.par_iter())
+                .map(|(members, medoid)| {
+                    let mut rng = rand::thread_rng();
+                    let pick = |rng: &mut _, most| -> Vec<usize> {
+                        if members.len() <= most {
+                            members.clone()
+                        } else {
+                            sample(rng, members.len(), most)
+                                .iter()
+                                .map(|m| members[m])
+                                .collect()
+                        }
+                    };
+                    let peers = pick(&mut rng, MAX_MEDOID_PEERS);
+                    let mut candidates = pick(&mut rng, MAX_MEDOID_CANDIDATES);
+                    candidates.push(*medoid); // (so it never gets worse)
+                    let total = |candidate: &usize| -> u64 {
+                        let row = rows[*candidate].as_ref();
+                        peers
+                            .iter()
+                            .map(|peer| distance(&full_mask, row, rows[*peer].as_ref()))
+                            .sum()
+                    };
+                    *candidates
+                        .iter()
+                        .min_by_key(|c| (total(c), **c != *medoid))
+                        .expect("Some candidates")
+                })
+                .collect();
+            if new_medoids == medoids {
+                break;
+            };
+            medoids = new_medoids;
+        } // end for all iterations
+
+        // The clusters around the final medoids
+        let medoid_rows: Vec<&[u8]> = medoids.iter().map(|m| rows[*m].as_ref()).collect();
+        index.assignments = rows
+            .par_iter() // RAYON!
+            .map(|row| nearest(&medoid_rows, row.as_ref()))
+            .collect();
+        index.clusters = medoids
+            .iter()
+            .map(|m| Cluster {
+                medoid: rows[*m].as_ref().to_vec(),
+                members: Vec::new(),
+            })
+            .collect();
+        for (row, cluster) in index.assignments.iter().enumerate() {
+            index.clusters[*cluster].members.push(row);
+        }
+        index
+    } // end build
+
+    /// Number of samples indexed
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.assignments.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.assignments.is_empty()
+    }
+
+    /// Number of clusters so far (at most the `num_clusters` the index was made with)
+    #[inline]
+    pub fn num_clusters(&self) -> usize {
+        self.clusters.len()
+    }
+
+    /// The most clusters there may be
+    #[inline]
+    pub fn max_clusters(&self) -> usize {
+        self.num_clusters
+    }
+
+    #[inline]
+    pub fn num_probes(&self) -> usize {
+        self.num_probes
+    }
+
+    /// The sizes of the clusters
+    pub fn cluster_sizes(&self) -> Vec<usize> {
+        self.clusters.iter().map(|c| c.members.len()).collect()
+    }
+
+    /// Forget all samples (but keep the medoids)
+    pub fn clear(&mut self) {
+        for cluster in self.clusters.iter_mut() {
+            cluster.members.clear();
+        }
+        self.assignments.clear();
+    }
+
+    /// Index a sample's bytes, as sample number `len()`: in the cluster of the nearest
+    /// medoid, or as the medoid of a new cluster, while there are fewer than `num_clusters`
+    pub fn insert(&mut self, bytes: &[u8]) {
+        let row = self.len();
+        let cluster = if self.clusters.len() < self.num_clusters {
+            self.clusters.push(Cluster {
+                medoid: bytes.to_vec(),
+                members: Vec::new(),
+            });
+            self.clusters.len() - 1
+        } else {
+            let full_mask = vec![0xFF; bytes.len()];
+            (0..self.clusters.len())
+                .min_by_key(|c| distance(&full_mask, bytes, &self.clusters[*c].medoid))
+                .expect("Some clusters")
+        };
+        self.clusters[cluster].members.push(row);
+        self.assignments.push(cluster);
+    }
+
+    /// Forget sample number `index`; later samples move up one, as in
+    /// `MhdMemory::remove_sample` (its cluster keeps its medoid, even if that was the sample)
+    pub fn remove(&mut self, index: usize) {
+        let cluster = self.assignments.remove(index);
+        self.clusters[cluster]
+            .members
+            .retain(|member| *member != index);
+        for cluster in self.clusters.iter_mut() {
+            for member in cluster.members.iter_mut().filter(|m| index < **m) {
+                *member -= 1;
+            }
+        }
+    }
+
+    /// The indices (in ascending order) of the samples in the `num_probes` clusters whose
+    /// medoids are closest to `query` (under `mask`), or `None` if the mask is empty
+    /// (then every medoid is as close as any other)
+    pub fn candidates(&self, mask: &[u8], query: &[u8]) -> Option<Vec<usize>> {
+        if 0 == weight(mask) {
+            return None;
+        };
+        let mut nearest: Vec<(u64, usize)> = self
+            .clusters
+            .iter()
+            .enumerate()
+            .map(|(c, cluster)| (distance(mask, query, &cluster.medoid), c))
+            .collect();
+        nearest.sort_unstable();
+        let mut result: Vec<usize> = nearest
+            .iter()
+            .take(self.num_probes)
+            .flat_map(|(_, c)| self.clusters[*c].members.iter().cloned())
+            .collect();
+        result.sort_unstable();
+        Some(result)
+    }
+
+    /// Approximate number of bytes this index holds
+    pub fn memory_bytes(&self) -> usize {
+        let clusters: usize = self
+            .clusters
+            .iter()
+            .map(|c| {
+                size_of::<Cluster>()
+                    + c.medoid.capacity()
+                    + c.members.capacity() * size_of::<usize>()
+            })
+            .sum();
+        size_of::<Self>() + clusters + self.assignments.capacity() * size_of::<usize>()
+    }
+} // end impl ClusterIndex
+
+///////////////////// TESTs for ClusterIndex /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sample::Sample;
+
+    #[test]
+    fn test_cluster_index() {
+        const NUM_BITS: usize = 48;
+        // Four groups of samples, each close to a center of its own
+        let centers: Vec<Sample> = (0..4).map(|_| Sample::random(NUM_BITS)).collect();
+        let samples: Vec<Sample> = (0..400)
+            .map(|i| {
+                let mut sample = centers[i % 4].clone();
+                for bit in 0..3 {
+                    let position = (7 * i + 13 * bit) % NUM_BITS;
+                    sample.set_bit(position, !sample.get_bit(position));
+                }
+                sample
+            })
+            .collect();
+        let rows: Vec<&[u8]> = samples.iter().map(|s| &s.bytes[..]).collect();
+        let index = ClusterIndex::build(&rows, 4, 1);
+        assert_eq!(
+            (index.len(), index.num_clusters(), index.num_probes()),
+            (400, 4, 1)
+        );
+        assert_eq!(index.cluster_sizes().iter().sum::<usize>(), 400);
+
+        // Every sample finds itself, and (most of) its group, but little else
+        let full_mask = vec![0xFF; 6];
+        for (i, sample) in samples.iter().enumerate().take(40) {
+            let candidates = index.candidates(&full_mask, &sample.bytes).unwrap();
+            assert!(candidates.binary_search(&i).is_ok());
+            assert!(candidates.windows(2).all(|pair| pair[0] < pair[1]));
+            let same_group = candidates.iter().filter(|c| *c % 4 == i % 4).count();
+            assert!(50 < same_group && same_group * 2 > candidates.len());
+        }
+        assert!(index.candidates(&[0x00; 6], &samples[0].bytes).is_none());
+
+        // Inserting and removing, as the memory does
+        let mut index = index;
+        index.insert(&samples[5].bytes);
+        assert_eq!(index.len(), 401);
+        let candidates = index.candidates(&full_mask, &samples[5].bytes).unwrap();
+        assert!(candidates.contains(&400));
+        index.remove(3);
+        assert_eq!(index.len(), 400);
+        let candidates = index.candidates(&full_mask, &samples[5].bytes).unwrap();
+        assert!(candidates.contains(&4) && candidates.contains(&399));
+        assert!(index.memory_bytes() > 400 * size_of::<usize>());
+
+        // An empty index finds its clusters as samples come
+        let mut index = ClusterIndex::new(2, 1);
+        assert!(index
+            .candidates(&full_mask, &samples[0].bytes)
+            .unwrap()
+            .is_empty());
+        for row in rows.iter().take(10) {
+            index.insert(row);
+        }
+        assert_eq!((index.len(), index.num_clusters()), (10, 2));
+        index.clear();
+        assert!(index.is_empty());
+        assert_eq!(index.num_clusters(), 2);
+    }
+}
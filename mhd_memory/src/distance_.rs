@@ -1,7 +1,12 @@
 /// # The Masked Hamming Distance Functions
 ///
+use rayon::prelude::*;
 
-fn naive(mask: &[u8], x: &[u8], y: &[u8]) -> u64 {
+use simd::SimdBackend;
+use util::as_words;
+use weight_::{thirties, weight_of_thirty};
+
+pub(crate) fn naive(mask: &[u8], x: &[u8], y: &[u8]) -> u64 {
     assert_eq!(mask.len(), x.len());
     assert_eq!(x.len(), y.len());
     mask.iter()
@@ -37,7 +42,8 @@ pub struct DistanceError {
 ///
 /// It is essentially guaranteed that `x` and `y` will have the same
 /// 8-byte alignment if they are both just `Vec<u8>`s of non-trivial
-/// length (e.g. larger than 8) as in the example below.
+/// length (e.g. larger than 8) as in the example below. The bytes of a `Sample`
+/// are always word aligned (see `SampleBytes`), whatever their length.
 ///
 /// This is implemented using the same tree-merging approach as
 /// `weight`, see there for details.
@@ -77,17 +83,9 @@ pub fn distance_fast(mask: &[u8], x: &[u8], y: &[u8]) -> Result<u64, DistanceErr
     assert_eq!(x.len(), y.len());
     assert_eq!(mask.len(), y.len());
 
-    const M1: u64 = 0x5555555555555555;
-    const M2: u64 = 0x3333333333333333;
-    const M4: u64 = 0x0F0F0F0F0F0F0F0F;
-    const M8: u64 = 0x00FF00FF00FF00FF;
-
-    type T30 = [u64; 30];
-
-    // can't fit a single T30 in
-    let (head0, thirty0, tail0) = unsafe { super::util::align_to::<_, T30>(mask) };
-    let (head1, thirty1, tail1) = unsafe { super::util::align_to::<_, T30>(x) };
-    let (head2, thirty2, tail2) = unsafe { super::util::align_to::<_, T30>(y) };
+    let (head0, words0, tail0) = as_words(mask);
+    let (head1, words1, tail1) = as_words(x);
+    let (head2, words2, tail2) = as_words(y);
 
     if (head1.len() != head2.len()) || (head0.len() != head1.len()) {
         // The arrays required different shift amounts, so we can't
@@ -95,36 +93,28 @@ pub fn distance_fast(mask: &[u8], x: &[u8], y: &[u8]) -> Result<u64, DistanceErr
         return Err(DistanceError { _x: () });
     }
 
-    debug_assert_eq!(thirty1.len(), thirty2.len());
-    debug_assert_eq!(thirty0.len(), thirty1.len());
+    debug_assert_eq!(words1.len(), words2.len());
+    debug_assert_eq!(words0.len(), words1.len());
+    let (thirty0, rest0) = thirties(words0);
+    let (thirty1, rest1) = thirties(words1);
+    let (thirty2, rest2) = thirties(words2);
 
     // do the nonaligned stuff at the head and tail the hard way...
     let mut count = naive(head0, head1, head2) + naive(tail0, tail1, tail2);
+    // ... and the words which don't make a whole thirty, word by word...
+    count += distance_words(rest0, rest1, rest2);
 
     // now do the aligned stuff in the middle...
-    for (mask_array, (array1, array2)) in thirty0.iter().zip(thirty1.iter().zip(thirty2)) {
-        let mut acc = 0;
-        for j_ in 0..10 {
-            let j = j_ * 3;
-            // Next 3 lines were all we had to modify for masking!
-            let mut count1 = mask_array[j] & (array1[j] ^ array2[j]);
-            let mut count2 = mask_array[j + 1] & (array1[j + 1] ^ array2[j + 1]);
-            let mut half1 = mask_array[j + 1] & (array1[j + 2] ^ array2[j + 2]);
-            let mut half2 = half1;
-            half1 &= M1;
-            half2 = (half2 >> 1) & M1;
-            count1 -= (count1 >> 1) & M1;
-            count2 -= (count2 >> 1) & M1;
-            count1 += half1;
-            count2 += half2;
-            count1 = (count1 & M2) + ((count1 >> 2) & M2);
-            count1 += (count2 & M2) + ((count2 >> 2) & M2);
-            acc += (count1 & M4) + ((count1 >> 4) & M4);
+    for (mask_array, (array1, array2)) in thirty0.zip(thirty1.zip(thirty2)) {
+        // (this was all we had to modify for masking!)
+        let mut masked = [0u64; 30];
+        for (word, (m, (a, b))) in masked
+            .iter_mut()
+            .zip(mask_array.iter().zip(array1.iter().zip(array2)))
+        {
+            *word = m & (a ^ b);
         }
-        acc = (acc & M8) + ((acc >> 8) & M8);
-        acc = acc + (acc >> 16);
-        acc = acc + (acc >> 32);
-        count += acc & 0xFFFF;
+        count += weight_of_thirty(|j| masked[j]);
     }
     Ok(count)
 }
@@ -134,9 +124,10 @@ pub fn distance_fast(mask: &[u8], x: &[u8], y: &[u8]) -> Result<u64, DistanceErr
 /// or, the number of set bits in the xor of `x` and `y`
 ///  -- whereby only the bits which are set in the mask are counted!
 ///
-/// When `mask`, `x` and `y` have the same 8-byte alignment, this uses
-/// `distance_fast`, a highly optimised version of the following naive
-/// version:
+/// If the CPU has vector units we know how to use (AVX2, AVX-512 or NEON),
+/// this uses them, see `SimdBackend`. Otherwise, when `mask`, `x` and `y` have
+/// the same 8-byte alignment, this uses `distance_fast`, a highly optimised
+/// version of the following naive version:
 ///
 /// ```rust
 /// fn naive(mask: &[u8], x: &[u8], y: &[u8]) -> u64 {
@@ -179,11 +170,72 @@ pub fn distance_fast(mask: &[u8], x: &[u8], y: &[u8]) -> Result<u64, DistanceErr
 /// assert_eq!( mhd_memory::distance(&mask, &x, &y), 4 * 1000);
 /// ```
 pub fn distance(mask: &[u8], x: &[u8], y: &[u8]) -> u64 {
+    SimdBackend::best().distance(mask, x, y)
+}
+
+// `distance` without SIMD, see `SimdBackend::Scalar`
+pub(crate) fn scalar_distance(mask: &[u8], x: &[u8], y: &[u8]) -> u64 {
     distance_fast(mask, x, y)
         .ok()
         .unwrap_or_else(|| naive(mask, x, y))
 }
 
+/// **Masked Hamming distance** between bit vectors stored in words, e.g. `SampleBytes::words`:
+/// no alignment to check, and nothing left over at either end.
+///
+/// # Examples
+///
+/// ```rust
+/// use mhd_memory::{distance_words, Sample, ZERO_SCORE};
+///
+/// let mask = Sample::new_ones(100, ZERO_SCORE);
+/// let x = Sample::new_ones(100, ZERO_SCORE);
+/// let y = Sample::new(100, ZERO_SCORE);
+/// assert_eq!(distance_words(mask.bytes.words(), x.bytes.words(), y.bytes.words()), 13 * 8);
+/// ```
+pub fn distance_words(mask: &[u64], x: &[u64], y: &[u64]) -> u64 {
+    assert_eq!(x.len(), y.len());
+    assert_eq!(mask.len(), y.len());
+    mask.iter()
+        .zip(x.iter().zip(y))
+        .map(|(m, (a, b))| (m & (a ^ b)).count_ones() as u64)
+        .sum()
+}
+
+/// Rows per rayon task in `distance_many` (fewer rows are compared sequentially)
+pub const DISTANCE_MANY_MIN_ROWS: usize = 1024;
+
+/// Computes the **Masked Hamming distance** between `query` and each of `rows`
+/// (e.g. a memory's samples, see `MhdMemory::distances`), in one pass: the SIMD backend is
+/// chosen only once, the mask and query stay in the cache, and the rows are split among
+/// threads, `DISTANCE_MANY_MIN_ROWS` at least at a time.
+///
+/// # Examples
+///
+/// ```rust
+/// let mask = vec![0x0F; 3];
+/// let query = vec![0x00; 3];
+/// let rows = vec![vec![0x00; 3], vec![0xFF; 3], vec![0x01, 0x10, 0x03]];
+/// assert_eq!(mhd_memory::distance_many(&mask, &query, &rows), vec![0, 12, 3]);
+/// ```
+pub fn distance_many<R: AsRef<[u8]> + Sync>(mask: &[u8], query: &[u8], rows: &[R]) -> Vec<u64> {
+    distance_many_in_chunks(mask, query, rows, DISTANCE_MANY_MIN_ROWS)
+}
+
+// `distance_many`, with at least `min_rows` rows per rayon task
+pub(crate) fn distance_many_in_chunks<R: AsRef<[u8]> + Sync>(
+    mask: &[u8],
+    query: &[u8],
+    rows: &[R],
+    min_rows: usize,
+) -> Vec<u64> {
+    let backend = SimdBackend::best();
+    rows.par_iter() // RAYON!
+        .with_min_len(min_rows.max(1))
+        .map(|row| backend.distance(mask, query, row.as_ref()))
+        .collect()
+}
+
 ////////////////////////////////////////////
 ///
 /// **Truncated Hamming Distance**
@@ -235,6 +287,61 @@ pub fn truncated_distance(masked_bits: u64, left: &[u8], right: &[u8]) -> u64 {
     subtotal + (mask & (left[num_mask_bytes] ^ right[num_mask_bytes])).count_ones() as u64
 }
 
+////////////////////////////////////////////
+///
+/// **Ranged Hamming Distance**
+///
+/// Another special case of the masked hamming distance, where the ones in the mask are
+/// contiguous: only the bits from `start_bit` up to (but not including) `end_bit` count.
+/// (So `truncated_distance(n, ...)` is `ranged_distance(0, n, ...)`.)
+/// Partial solutions often have their open decisions in a suffix, and their decided ones
+/// in a range. Bits are numbered as in `truncated_distance`, from left to right;
+/// whole bytes in the range are compared byte-wise.
+///
+/// # Examples:
+/// ```rust
+/// let lvec  = vec![0xF0; 4]; // l ^ r = 0xFF
+/// let rvec  = vec![0x0F; 4]; // i.e.  8 different bits / byte
+///
+/// assert_eq!( 14, mhd_memory::ranged_distance( 3, 17, &lvec, &rvec ) );
+/// assert_eq!( mhd_memory::truncated_distance( 20, &lvec, &rvec ),
+///             mhd_memory::ranged_distance( 0, 20, &lvec, &rvec ) );
+/// ```
+pub fn ranged_distance(start_bit: u64, end_bit: u64, left: &[u8], right: &[u8]) -> u64 {
+    assert_eq!(left.len(), right.len());
+    assert!(end_bit <= 8 * left.len() as u64);
+    if end_bit <= start_bit {
+        return 0;
+    };
+
+    let first_byte = (start_bit / 8) as usize;
+    let last_byte = (end_bit / 8) as usize; // the byte after the last whole one
+
+    // (bit 0 is 128, see truncated_distance)
+    let head_mask: u8 = 0xFF >> (start_bit % 8);
+    let tail_mask: u8 = ((0xFF00 >> (end_bit % 8)) & 0xFF) as u8;
+    let differing =
+        |index: usize, mask: u8| (mask & (left[index] ^ right[index])).count_ones() as u64;
+
+    if first_byte == last_byte {
+        // the whole range is within one byte
+        return differing(first_byte, head_mask & tail_mask);
+    };
+
+    // The first (partial) byte, then whole bytes, byte-wise, then the last (partial) byte
+    let head = differing(first_byte, head_mask);
+    let middle = hamming::distance(
+        &left[first_byte + 1..last_byte],
+        &right[first_byte + 1..last_byte],
+    );
+    let tail = if 0 == tail_mask {
+        0 // (and there may be no last byte)
+    } else {
+        differing(last_byte, tail_mask)
+    };
+    head + middle + tail
+}
+
 // TESTS TESTS TESTS TESTS TESTS TESTS TESTS TESTS TESTS TESTS TESTS TESTS
 
 #[cfg(test)]
@@ -294,6 +401,39 @@ mod tests {
         );
     }
     #[test]
+    fn distance_fast_on_samples() {
+        use sample::Sample;
+        // Sample bytes are word aligned, so distance_fast never gives up on them
+        for width in (4..100).chain(vec![1000, 2048, 4097]) {
+            let mask = Sample::random(width);
+            let (x, y) = (Sample::random(width), Sample::random(width));
+            let naive = super::naive(&mask.bytes, &x.bytes, &y.bytes);
+            assert_eq!(
+                super::distance_fast(&mask.bytes, &x.bytes, &y.bytes),
+                Ok(naive)
+            );
+            assert_eq!(
+                super::distance_words(mask.bytes.words(), x.bytes.words(), y.bytes.words()),
+                naive
+            );
+        }
+    }
+    #[test]
+    fn distance_fast_random_masks() {
+        use rand::prelude::*;
+        let mut rng = thread_rng();
+        let mut random_bytes = |len: usize| -> Vec<u8> { (0..len).map(|_| rng.gen()).collect() };
+        // long enough for the aligned middle part, with every word masked differently
+        let (m, x, y) = (random_bytes(3000), random_bytes(3000), random_bytes(3000));
+        for offset in 0..8 {
+            let (m, x, y) = (&m[offset..], &x[offset..], &y[offset..]);
+            if let Ok(fast) = super::distance_fast(m, x, y) {
+                assert_eq!(fast, super::naive(m, x, y));
+            };
+            assert_eq!(super::distance(m, x, y), super::naive(m, x, y));
+        }
+    }
+    #[test]
     fn distance_smoke() {
         let m = vec![0xFF; 10000];
         let v = vec![0; m.len()];
@@ -311,6 +451,45 @@ mod tests {
         }
     }
     #[test]
+    fn distance_many_random() {
+        use rand::prelude::*;
+        let mut rng = thread_rng();
+        let mut random_bytes = |len: usize| -> Vec<u8> { (0..len).map(|_| rng.gen()).collect() };
+        let (mask, query) = (random_bytes(100), random_bytes(100));
+        let rows: Vec<Vec<u8>> = (0..3000).map(|_| random_bytes(100)).collect();
+        let distances = super::distance_many(&mask, &query, &rows);
+        assert_eq!(distances.len(), rows.len());
+        for (row, distance) in rows.iter().zip(distances) {
+            assert_eq!(distance, super::naive(&mask, &query, row));
+        }
+        let no_rows: Vec<Vec<u8>> = Vec::new();
+        assert!(super::distance_many(&mask, &query, &no_rows).is_empty());
+    }
+    #[test]
+    fn ranged_distance_random() {
+        use rand::prelude::*;
+        let mut rng = thread_rng();
+        let x: Vec<u8> = (0..40).map(|_| rng.gen()).collect();
+        let y: Vec<u8> = (0..40).map(|_| rng.gen()).collect();
+        for _ in 0..1000 {
+            let start = rng.gen_range(0..=320);
+            let end = rng.gen_range(0..=320);
+            // the same range as a mask
+            let mut mask = vec![0u8; 40];
+            for bit in start..end {
+                mask[bit / 8] |= 128 >> (bit % 8);
+            }
+            assert_eq!(
+                super::ranged_distance(start as u64, end as u64, &x, &y),
+                super::naive(&mask, &x, &y)
+            );
+        }
+        assert_eq!(
+            super::ranged_distance(0, 320, &x, &y),
+            super::truncated_distance(320, &x, &y)
+        );
+    }
+    #[test]
     fn truncate_distance_smoke() {
         let size: usize = 4 * 1024 * 1024; // number of bytes in vectors
         let v1 = vec![0xF0; size];
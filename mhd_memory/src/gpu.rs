@@ -0,0 +1,463 @@
+/// # Memory reads on a GPU
+///
+/// With the feature "gpu", the distances of a read -- the hot loop, one masked hamming distance
+/// per sample -- can be computed by a compute shader (with wgpu, so on Vulkan, Metal, DX12 or
+/// OpenGL). `GpuSamples` uploads the samples of a memory once; each read then uploads just
+/// the mask and the query, and reads back one distance per sample. The rest of the read --
+/// kernel, sample weighting, sums -- is the memory's own, as on the CPU, so
+/// `masked_read_gpu` and `read_2_priorities_gpu` read just what `masked_read` and
+/// `read_2_priorities` read (over all samples: no LSH index, clusters or subsample).
+///
+/// That pays off for large memories -- millions of samples, hundreds of bits -- where the
+/// CPU spends its time computing distances. Samples written after the upload are not on
+/// the GPU: `upload` them again (reads fail with `GpuError::OutOfDate` until then).
+/// Partial samples and custom metrics are not supported.
+///
+/// ```rust
+/// use mhd_memory::{GpuSamples, MhdMemory, Sample};
+///
+/// let mut memory = MhdMemory::new(128);
+/// memory.write_n_random_samples(1000);
+/// let (mask, query) = (Sample::random(128), Sample::random(128));
+/// match GpuSamples::new(&memory) {
+///     Ok(gpu) => {
+///         let read = memory.masked_read_gpu(&gpu, &mask.bytes, &query.bytes).unwrap();
+///         assert_eq!(read, memory.masked_read(&mask.bytes, &query.bytes));
+///     }
+///     Err(error) => println!("no GPU here: {}", error),
+/// };
+/// ```
+use std::error::Error;
+use std::fmt;
+use std::sync::{mpsc, Mutex};
+
+use wgpu::util::DeviceExt;
+
+use metric::{MaskedHamming, Metric};
+use mhdmemory::MhdMemory;
+use sample::ScoreType;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+// One invocation per sample: the masked hamming distance of its row to the query
+const SHADER: &str = r#"
+struct Params {
+    words_per_row: u32,
+    num_rows: u32,
+    row_stride: u32, // rows per workgroup row (y) of the dispatch
+    unused: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> rows: array<u32>;
+@group(0) @binding(2) var<storage, read> mask_and_query: array<u32>;
+@group(0) @binding(3) var<storage, read_write> distances: array<u32>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let row = id.y * params.row_stride + id.x;
+    if (row >= params.num_rows) {
+        return;
+    }
+    let base = row * params.words_per_row;
+    var count = 0u;
+    for (var word = 0u; word < params.words_per_row; word++) {
+        let mask = mask_and_query[word];
+        let query = mask_and_query[params.words_per_row + word];
+        count += countOneBits(mask & (query ^ rows[base + word]));
+    }
+    distances[row] = count;
+}
+"#;
+
+/// What went wrong with a `GpuSamples`
+#[derive(Debug, Clone, PartialEq)]
+pub enum GpuError {
+    /// No GPU (or none wgpu can use)
+    NoAdapter(String),
+    /// The GPU wouldn't give us a device, or failed a read
+    Device(String),
+    /// The memory holds partial samples, see `write_masked_sample`
+    PartialSamples,
+    /// The memory measures distances with a metric of its own, see `with_metric`
+    CustomMetric(&'static str),
+    /// The memory has a different number of samples than were uploaded, see `upload`
+    OutOfDate { uploaded: usize, in_memory: usize },
+}
+
+impl fmt::Display for GpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GpuError::NoAdapter(message) => write!(f, "no GPU adapter: {}", message),
+            GpuError::Device(message) => write!(f, "GPU device error: {}", message),
+            GpuError::PartialSamples => write!(f, "partial samples can't be read on a GPU"),
+            GpuError::CustomMetric(name) => write!(f, "the metric {} can't run on a GPU", name),
+            GpuError::OutOfDate {
+                uploaded,
+                in_memory,
+            } => write!(
+                f,
+                "{} samples uploaded, but {} in the memory",
+                uploaded, in_memory
+            ),
+        }
+    }
+}
+
+impl Error for GpuError {}
+
+// The buffers of one upload (the parameters and the samples are only in the bind group)
+struct Rows {
+    mask_and_query: wgpu::Buffer,
+    distances: wgpu::Buffer,
+    staging: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    num_rows: usize,
+    words_per_row: usize,
+    workgroups: (u32, u32),
+}
+
+pub struct GpuSamples {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    rows: Rows,
+    reading: Mutex<()>, // one read at a time (they share the buffers)
+}
+
+impl GpuSamples {
+    /// Find a GPU, and upload the samples of `memory` to it
+    pub fn new(memory: &MhdMemory) -> Result<Self, GpuError> {
+        Self::check(memory)?;
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .map_err(|e| GpuError::NoAdapter(e.to_string()))?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("mhd_memory"),
+            required_limits: adapter.limits(), // as large a sample matrix as it can hold
+            ..Default::default()
+        }))
+        .map_err(|e| GpuError::Device(e.to_string()))?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("masked hamming distances"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("masked hamming distances"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let rows = Self::create_rows(&device, &pipeline, memory)?;
+        Ok(Self {
+            device,
+            queue,
+            pipeline,
+            rows,
+            reading: Mutex::new(()),
+        })
+    }
+
+    fn check(memory: &MhdMemory) -> Result<(), GpuError> {
+        if memory.has_partial_samples() {
+            return Err(GpuError::PartialSamples);
+        };
+        if memory.metric_name() != MaskedHamming.name() {
+            return Err(GpuError::CustomMetric(memory.metric_name()));
+        };
+        Ok(())
+    }
+
+    /// Upload the samples of `memory` again (e.g. after writing some), replacing the old ones
+    pub fn upload(&mut self, memory: &MhdMemory) -> Result<(), GpuError> {
+        Self::check(memory)?;
+        self.rows = Self::create_rows(&self.device, &self.pipeline, memory)?;
+        Ok(())
+    }
+
+    fn create_rows(
+        device: &wgpu::Device,
+        pipeline: &wgpu::ComputePipeline,
+        memory: &MhdMemory,
+    ) -> Result<Rows, GpuError> {
+        let words_per_row = memory.width().div_ceil(32).max(1);
+        let num_rows = memory.num_samples();
+        let mut matrix = Vec::with_capacity(4 * words_per_row * num_rows.max(1));
+        for sample in memory.iter() {
+            matrix.extend_from_slice(&sample.bytes);
+            matrix.resize(matrix.len() + 4 * words_per_row - sample.bytes.len(), 0);
+        }
+        matrix.resize(matrix.len().max(4), 0); // (no empty buffers)
+
+        // Up to `max_compute_workgroups_per_dimension` workgroups in x, as many rows in y
+        let num_groups = (num_rows as u32).div_ceil(WORKGROUP_SIZE).max(1);
+        let max_x = device.limits().max_compute_workgroups_per_dimension.max(1);
+        let workgroups = (num_groups.min(max_x), num_groups.div_ceil(max_x));
+        let params = [
+            words_per_row as u32,
+            num_rows as u32,
+            workgroups.0 * WORKGROUP_SIZE,
+            0,
+        ];
+        let params_bytes: Vec<u8> = params.iter().flat_map(|p| p.to_le_bytes()).collect();
+
+        let buffer_limit = device.limits().max_storage_buffer_binding_size as usize;
+        if buffer_limit < matrix.len() {
+            return Err(GpuError::Device(format!(
+                "{} bytes of samples, but buffers hold at most {}",
+                matrix.len(),
+                buffer_limit
+            )));
+        };
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("params"),
+            contents: &params_bytes,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let matrix = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("samples"),
+            contents: &matrix,
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let mask_and_query = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("mask and query"),
+            size: (8 * words_per_row) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let distances_size = (4 * num_rows.max(1)) as u64;
+        let distances = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("distances"),
+            size: distances_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("distances read back"),
+            size: distances_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("masked hamming distances"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: matrix.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: mask_and_query.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: distances.as_entire_binding(),
+                },
+            ],
+        });
+        Ok(Rows {
+            mask_and_query,
+            distances,
+            staging,
+            bind_group,
+            num_rows,
+            words_per_row,
+            workgroups,
+        })
+    }
+
+    /// The number of samples uploaded
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.rows.num_rows
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        0 == self.len()
+    }
+
+    /// The (masked hamming) distance from `query` to every sample uploaded,
+    /// as `MhdMemory::distances` computes them
+    pub fn distances(&self, mask: &[u8], query: &[u8]) -> Result<Vec<u64>, GpuError> {
+        let rows = &self.rows;
+        if 0 == rows.num_rows {
+            return Ok(Vec::new());
+        };
+        let row_bytes = 4 * rows.words_per_row;
+        let mut mask_and_query = vec![0u8; 2 * row_bytes];
+        let (mask_part, query_part) = mask_and_query.split_at_mut(row_bytes);
+        for (part, bytes) in [(mask_part, mask), (query_part, query)] {
+            let num_bytes = bytes.len().min(row_bytes);
+            part[..num_bytes].copy_from_slice(&bytes[..num_bytes]);
+        }
+
+        let _reading = self.reading.lock().unwrap_or_else(|e| e.into_inner());
+        self.queue
+            .write_buffer(&rows.mask_and_query, 0, &mask_and_query);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &rows.bind_group, &[]);
+            pass.dispatch_workgroups(rows.workgroups.0, rows.workgroups.1, 1);
+        }
+        let size = rows.staging.size();
+        encoder.copy_buffer_to_buffer(&rows.distances, 0, &rows.staging, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = rows.staging.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device
+            .poll(wgpu::PollType::Wait)
+            .map_err(|e| GpuError::Device(e.to_string()))?;
+        receiver
+            .recv()
+            .map_err(|e| GpuError::Device(e.to_string()))?
+            .map_err(|e| GpuError::Device(e.to_string()))?;
+        let distances = {
+            let view = slice.get_mapped_range();
+            view.chunks_exact(4)
+                .take(rows.num_rows)
+                .map(|d| u32::from_le_bytes([d[0], d[1], d[2], d[3]]) as u64)
+                .collect()
+        };
+        rows.staging.unmap();
+        Ok(distances)
+    }
+} // end impl GpuSamples
+
+impl MhdMemory {
+    // The distances of all samples, from the GPU (if it holds all of them)
+    fn gpu_distances(
+        &self,
+        gpu: &GpuSamples,
+        mask: &[u8],
+        query: &[u8],
+    ) -> Result<Vec<u64>, GpuError> {
+        assert!(self.width <= 8 * mask.len());
+        assert!(self.width <= 8 * query.len());
+        if gpu.len() != self.num_samples() {
+            return Err(GpuError::OutOfDate {
+                uploaded: gpu.len(),
+                in_memory: self.num_samples(),
+            });
+        };
+        gpu.distances(mask, query)
+    }
+
+    /// `masked_read`, with the distances computed on the GPU (see `GpuSamples`)
+    pub fn masked_read_gpu(
+        &self,
+        gpu: &GpuSamples,
+        mask: &[u8],
+        query: &[u8],
+    ) -> Result<ScoreType, GpuError> {
+        let distances = self.gpu_distances(gpu, mask, query)?;
+        Ok(self.read_from_distances(&None, &distances).score)
+    }
+
+    /// `read_2_priorities`, with the distances computed on the GPU (see `GpuSamples`)
+    pub fn read_2_priorities_gpu(
+        &self,
+        gpu: &GpuSamples,
+        mask: &[u8],
+        query: &[u8],
+        index: usize,
+    ) -> Result<(f64, f64), GpuError> {
+        let distances = self.gpu_distances(gpu, mask, query)?;
+        let sums = self.sums_from_distances(mask, index, &None, &distances);
+        Ok(self.priorities_from(&sums))
+    }
+} // end impl MhdMemory
+
+///////////////////// TESTs for GpuSamples /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metric::MaskedJaccard;
+    use sample::Sample;
+
+    #[test]
+    fn test_gpu_reads() {
+        const NUM_BITS: usize = 100;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_n_random_samples(500);
+        let mut gpu = match GpuSamples::new(&memory) {
+            Ok(gpu) => gpu,
+            Err(GpuError::NoAdapter(message)) => {
+                println!("No GPU, nothing to test: {}", message);
+                return;
+            }
+            Err(error) => panic!("{}", error),
+        };
+        assert_eq!(gpu.len(), 500);
+        for _ in 0..10 {
+            let (mask, query) = (Sample::random(NUM_BITS), Sample::random(NUM_BITS));
+            assert_eq!(
+                gpu.distances(&mask.bytes, &query.bytes).unwrap(),
+                memory.distances(&mask.bytes, &query.bytes)
+            );
+            assert_eq!(
+                memory
+                    .masked_read_gpu(&gpu, &mask.bytes, &query.bytes)
+                    .unwrap(),
+                memory.masked_read(&mask.bytes, &query.bytes)
+            );
+            assert_eq!(
+                memory
+                    .read_2_priorities_gpu(&gpu, &mask.bytes, &query.bytes, 7)
+                    .unwrap(),
+                memory.read_2_priorities(&mask.bytes, &query.bytes, 7)
+            );
+        }
+
+        // New samples have to be uploaded
+        memory.write_random_sample();
+        let (mask, query) = ([0xFF; 13], [0x00; 13]);
+        assert!(memory.masked_read_gpu(&gpu, &mask, &query).is_err());
+        gpu.upload(&memory).unwrap();
+        assert_eq!(gpu.len(), 501);
+        assert_eq!(
+            memory.masked_read_gpu(&gpu, &mask, &query).unwrap(),
+            memory.masked_read(&mask, &query)
+        );
+    }
+
+    #[test]
+    fn test_gpu_unsupported() {
+        const NUM_BITS: usize = 16;
+        let mut partial = MhdMemory::new(NUM_BITS);
+        partial.write_masked_sample(&[0xFF, 0x00], &[0x12, 0x34], 7);
+        assert_eq!(
+            GpuSamples::new(&partial).err(),
+            Some(GpuError::PartialSamples)
+        );
+        let jaccard = MhdMemory::with_metric(NUM_BITS, MaskedJaccard);
+        assert!(matches!(
+            GpuSamples::new(&jaccard).err(),
+            Some(GpuError::CustomMetric(_))
+        ));
+        let error = GpuError::OutOfDate {
+            uploaded: 1,
+            in_memory: 2,
+        };
+        assert_eq!(error.to_string(), "1 samples uploaded, but 2 in the memory");
+    }
+}
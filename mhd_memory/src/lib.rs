@@ -33,16 +33,77 @@ extern crate rand;
 extern crate rand_distr;
 extern crate rayon;
 
+#[cfg(feature = "mmap")]
+extern crate memmap2;
+#[cfg(feature = "gpu")]
+extern crate pollster;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(feature = "serde", test))]
+extern crate serde_json;
+#[cfg(feature = "gpu")]
+extern crate wgpu;
+
 pub mod util;
 
 pub mod weight_;
-pub use self::weight_::weight;
+pub use self::weight_::{masked_weight, weight};
 
 pub mod distance_;
-pub use self::distance_::{distance, distance_fast, truncated_distance};
+pub use self::distance_::{
+    distance, distance_fast, distance_many, distance_words, ranged_distance, truncated_distance,
+};
+
+pub mod simd;
+pub use self::simd::SimdBackend;
+
+pub mod score;
+pub use self::score::Score;
 
 pub mod sample;
 pub use self::sample::{Sample, ScoreType, ZERO_SCORE};
 
+pub mod sample_bytes;
+pub use self::sample_bytes::SampleBytes;
+
+pub mod bit_slices;
+pub use self::bit_slices::BitSlices;
+
+pub mod sketch;
+
+pub mod lsh;
+
+pub mod cluster;
+
+pub mod metric;
+pub use self::metric::{MaskedHamming, MaskedJaccard, Metric, WeightedHamming};
+
+pub mod policy;
+pub use self::policy::{DecisionPolicy, EpsilonGreedy, Softmax, ThompsonSampling, UcbPolicy};
+
 pub mod mhdmemory;
-pub use self::mhdmemory::MhdMemory;
+pub use self::mhdmemory::{
+    DistanceKernel, EvictionPolicy, KernelShape, MaskedRead, MhdMemory, MhdMemoryConfig,
+    PrioritySums, SampleWeighting, SelfScore,
+};
+
+pub mod builder;
+pub use self::builder::{ConfigError, MhdMemoryBuilder};
+
+pub mod stats;
+pub use self::stats::MemoryStats;
+
+pub mod sharded;
+pub use self::sharded::ShardedMemory;
+
+pub mod persistence;
+
+#[cfg(feature = "mmap")]
+pub mod mapped;
+#[cfg(feature = "mmap")]
+pub use self::mapped::MappedSamples;
+
+#[cfg(feature = "gpu")]
+pub mod gpu;
+#[cfg(feature = "gpu")]
+pub use self::gpu::{GpuError, GpuSamples};
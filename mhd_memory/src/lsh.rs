@@ -0,0 +1,268 @@
+/// # Locality Sensitive Hashing -- an index for large memories
+///
+/// Every read visits every sample, which is prohibitive for large memories. An `LshIndex`
+/// keeps `num_tables` hash tables, each of which files the samples under `bits_per_table` of
+/// their bits, chosen at random (bit sampling LSH). A sample `d` bits away from a query (out
+/// of the `n` bits in the mask) agrees with it on a sampled bit with probability `1 - d/n`,
+/// so close samples share a bucket with the query in some table much more often than far
+/// ones do -- and a read only needs to visit the samples in the query's buckets
+/// (see `MhdMemory::use_lsh`). The chance to find a sample at all is
+/// `1 - (1 - (1 - d/n)^bits_per_table)^num_tables`: more bits per table mean fewer (and
+/// closer) candidates, more tables mean fewer close samples missed.
+///
+/// Masks make this a little trickier: where a table samples a bit outside the mask, any
+/// value matches, so the index probes the buckets for both values -- for up to
+/// `MAX_WILDCARDS` such bits per table. Tables with more are skipped, and if no table is
+/// left, `candidates` returns `None`: the read has to scan all samples, as usual.
+/// (So the index helps most once a good part of the bits is decided.)
+///
+/// Bit positions are numbered as in `Sample::get_bit`.
+///
+/// ```rust
+/// use mhd_memory::lsh::LshIndex;
+/// use mhd_memory::Sample;
+///
+/// let mut index = LshIndex::new(64, 8, 12);
+/// let samples: Vec<Sample> = (0..1000).map(|_| Sample::random(64)).collect();
+/// for sample in samples.iter() {
+///     index.insert(&sample.bytes);
+/// }
+/// assert_eq!(index.len(), 1000);
+///
+/// // A sample always finds itself (with a full mask), and (almost) none of the others
+/// let full_mask = vec![0xFF; 8];
+/// let candidates = index.candidates(&full_mask, &samples[42].bytes).unwrap();
+/// assert!(candidates.contains(&42));
+/// assert!(candidates.len() < 100);
+///
+/// // With (almost) nothing in the mask, the index can't help
+/// let empty_mask = vec![0x00; 8];
+/// assert!(index.candidates(&empty_mask, &samples[42].bytes).is_none());
+/// ```
+use rand::seq::index::sample;
+use std::collections::HashMap;
+use std::mem::size_of;
+
+/// Default number of hash tables, see `MhdMemory::use_lsh`
+pub const DEFAULT_LSH_TABLES: usize = 16;
+/// Default number of bits per hash table, see `MhdMemory::use_lsh`
+pub const DEFAULT_BITS_PER_TABLE: usize = 12;
+/// The most bits outside the mask a table may sample, and still be probed
+/// (each one doubles the number of buckets to probe)
+pub const MAX_WILDCARDS: usize = 4;
+
+#[inline]
+fn bit_at(bytes: &[u8], position: usize) -> bool {
+    0 != bytes[position / 8] & (128 >> (position % 8))
+}
+
+#[derive(Debug, Clone)]
+struct LshTable {
+    positions: Vec<usize>, // the sampled bits; bit k of a key is positions[k]
+    buckets: HashMap<u64, Vec<usize>>, // key -> indices of the samples with that key
+}
+
+impl LshTable {
+    #[inline]
+    fn key(&self, bytes: &[u8]) -> u64 {
+        self.positions
+            .iter()
+            .enumerate()
+            .filter(|(_, position)| bit_at(bytes, **position))
+            .fold(0, |key, (k, _)| key | (1 << k))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LshIndex {
+    tables: Vec<LshTable>,
+    len: usize,
+}
+
+impl LshIndex {
+    /// An empty index for samples of `width` bits, with `num_tables` tables
+    /// of `bits_per_table` (at most 64, and at most `width`) random bits each
+    pub fn new(width: usize, num_tables: usize, bits_per_table: usize) -> Self {
+        let mut rng = rand::thread_rng();
+        let bits_per_table = bits_per_table.min(64).min(width);
+        Self {
+            tables: (0..num_tables)
+                .map(|_| LshTable {
+                    positions: sample(&mut rng, width, bits_per_table).into_vec(),
+                    buckets: HashMap::new(),
+                })
+                .collect(),
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub fn num_tables(&self) -> usize {
+        self.tables.len()
+    }
+
+    #[inline]
+    pub fn bits_per_table(&self) -> usize {
+        self.tables.first().map_or(0, |table| table.positions.len())
+    }
+
+    /// Number of samples indexed
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        0 == self.len
+    }
+
+    /// Forget all samples (but keep the sampled bits)
+    pub fn clear(&mut self) {
+        for table in self.tables.iter_mut() {
+            table.buckets.clear();
+        }
+        self.len = 0;
+    }
+
+    /// Index a sample's bytes, as sample number `len()`
+    pub fn insert(&mut self, bytes: &[u8]) {
+        for table in self.tables.iter_mut() {
+            let key = table.key(bytes);
+            table.buckets.entry(key).or_default().push(self.len);
+        }
+        self.len += 1;
+    }
+
+    /// Forget sample number `index` (with these bytes); later samples move up one,
+    /// as in `MhdMemory::remove_sample`
+    pub fn remove(&mut self, index: usize, bytes: &[u8]) {
+        assert!(index < self.len);
+        for table in self.tables.iter_mut() {
+            let key = table.key(bytes);
+            if let Some(bucket) = table.buckets.get_mut(&key) {
+                bucket.retain(|entry| *entry != index);
+                if bucket.is_empty() {
+                    table.buckets.remove(&key);
+                };
+            };
+            for bucket in table.buckets.values_mut() {
+                for entry in bucket.iter_mut().filter(|entry| index < **entry) {
+                    *entry -= 1;
+                }
+            }
+        }
+        self.len -= 1;
+    }
+
+    /// The indices (in ascending order) of the samples which share a bucket with `query`
+    /// (under `mask`) in some table, or `None` if no table can be used with this mask
+    pub fn candidates(&self, mask: &[u8], query: &[u8]) -> Option<Vec<usize>> {
+        let mut seen = vec![false; self.len];
+        let mut result = Vec::new();
+        let mut usable = false;
+        for table in self.tables.iter() {
+            let wildcards: Vec<usize> = (0..table.positions.len())
+                .filter(|k| !bit_at(mask, table.positions[*k]))
+                .collect();
+            if MAX_WILDCARDS < wildcards.len() {
+                continue;
+            };
+            usable = true;
+            let wildcard_bits = wildcards.iter().fold(0u64, |bits, k| bits | (1 << k));
+            let key = table.key(query) & !wildcard_bits;
+            // probe every combination of values of the wildcards
+            for combination in 0..(1u64 << wildcards.len()) {
+                let probe = wildcards
+                    .iter()
+                    .enumerate()
+                    .filter(|(w, _)| 0 != combination & (1 << w))
+                    .fold(key, |probe, (_, k)| probe | (1 << k));
+                for index in table.buckets.get(&probe).into_iter().flatten() {
+                    if !seen[*index] {
+                        seen[*index] = true;
+                        result.push(*index);
+                    };
+                }
+            } // end for all combinations
+        } // end for all tables
+        if !usable {
+            return None;
+        };
+        result.sort_unstable();
+        Some(result)
+    }
+
+    /// Approximate number of bytes this index holds
+    pub fn memory_bytes(&self) -> usize {
+        let tables: usize = self
+            .tables
+            .iter()
+            .map(|table| {
+                size_of::<LshTable>()
+                    + table.positions.capacity() * size_of::<usize>()
+                    + table.buckets.capacity() * (size_of::<u64>() + size_of::<Vec<usize>>())
+            })
+            .sum();
+        // (every sample is in one bucket per table)
+        size_of::<Self>() + tables + self.len * self.num_tables() * size_of::<usize>()
+    }
+} // end impl LshIndex
+
+///////////////////// TESTs for LshIndex /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use distance_::distance;
+    use sample::Sample;
+
+    #[test]
+    fn test_lsh_index() {
+        const NUM_BITS: usize = 48;
+        let mut index = LshIndex::new(NUM_BITS, 10, 8);
+        assert_eq!((index.num_tables(), index.bits_per_table()), (10, 8));
+        let samples: Vec<Sample> = (0..500).map(|_| Sample::random(NUM_BITS)).collect();
+        for sample in samples.iter() {
+            index.insert(&sample.bytes);
+        }
+
+        // A close neighbour (two bits off) is found nearly always, far samples rarely
+        let full_mask = vec![0xFF; 6];
+        let mut close_found = 0;
+        for (position, sample) in samples.iter().enumerate().take(100) {
+            let mut query = sample.clone();
+            query.set_bit(position % NUM_BITS, !sample.get_bit(position % NUM_BITS));
+            query.set_bit(47 - position % 20, !sample.get_bit(47 - position % 20));
+            let candidates = index.candidates(&full_mask, &query.bytes).unwrap();
+            assert!(candidates.windows(2).all(|pair| pair[0] < pair[1]));
+            if candidates.binary_search(&position).is_ok() {
+                close_found += 1;
+            };
+            assert!(candidates.len() < 100);
+        }
+        assert!(90 < close_found);
+
+        // Bits outside the mask don't matter (as long as there are few of them)
+        let mut mask = full_mask.clone();
+        mask[5] = 0xF0; // four bits less
+        let mut query = samples[7].clone();
+        query.bytes[5] ^= 0x0F; // ...which differ
+        assert_eq!(distance(&mask, &query.bytes, &samples[7].bytes), 0);
+        let candidates = index.candidates(&mask, &query.bytes).unwrap();
+        assert!(candidates.contains(&7));
+
+        // Removing sample 3 moves the later ones up
+        index.remove(3, &samples[3].bytes);
+        assert_eq!(index.len(), 499);
+        let candidates = index.candidates(&full_mask, &samples[10].bytes).unwrap();
+        assert!(candidates.contains(&9));
+        assert!(index.memory_bytes() > 499 * 10 * size_of::<usize>());
+
+        index.clear();
+        assert!(index.is_empty());
+        assert_eq!(
+            index.candidates(&full_mask, &samples[10].bytes),
+            Some(vec![])
+        );
+    }
+}
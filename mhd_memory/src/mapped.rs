@@ -0,0 +1,323 @@
+/// # Memory-mapped samples
+///
+/// With the feature "mmap", samples can live in a file instead of RAM: `MappedSamples` appends
+/// them to a file and maps it into memory, so the operating system pages them in (and out)
+/// as needed. That allows for far more samples than fit in RAM, and reopening a file
+/// is instant -- nothing is parsed, as it would be by `import_csv` or serde.
+///
+/// The file is append-only: a header (the magic bytes `MHDMMAP1` and the width in bits),
+/// then one record per sample, each the score (as a little endian `f64`) followed by the
+/// sample's bytes, padded to whole words, so the bytes are word aligned just like
+/// a `Sample`'s (see `SampleBytes`).
+///
+/// `masked_read` reads just as an `MhdMemory` does (with its kernel and weighting), but
+/// without the extras (LSH, bit slices...); for those, load the samples into a memory
+/// with `MhdMemory::from_mapped`.
+/// The in-RAM memory stays the default.
+///
+/// ```rust
+/// use mhd_memory::{MappedSamples, MhdMemory, Sample, ScoreType};
+///
+/// let path = std::env::temp_dir().join(format!("mapped_doc_{}.mhd", std::process::id()));
+/// let mut mapped = MappedSamples::create(&path, 24).unwrap();
+/// mapped.append(&Sample::new(24, 10 as ScoreType)).unwrap();
+/// mapped.append(&Sample::new_ones(24, 30 as ScoreType)).unwrap();
+/// assert_eq!(mapped.len(), 2);
+///
+/// let reopened = MappedSamples::open(&path).unwrap();
+/// assert_eq!(reopened.get(1), Sample::new_ones(24, 30 as ScoreType));
+/// let mask = vec![0xFF; 3];
+/// assert_eq!(reopened.distances(&mask, &[0x00; 3]), vec![0, 24]);
+///
+/// let memory = MhdMemory::from_mapped(&reopened);
+/// assert_eq!(memory.num_samples(), 2);
+/// assert_eq!(
+///     reopened.masked_read(&mask, &[0x00; 3], &memory.kernel, &memory.weighting),
+///     memory.masked_read(&mask, &[0x00; 3])
+/// );
+/// std::fs::remove_file(&path).unwrap();
+/// ```
+use memmap2::Mmap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use distance_::distance_many;
+use mhdmemory::{
+    DistanceKernel, MaskedRead, MhdMemory, SampleWeighting, DEFAULT_PARALLEL_THRESHOLD,
+};
+use sample::{Sample, ScoreType, ZERO_SCORE};
+use sample_bytes::SampleBytes;
+use score::Score;
+
+const MAGIC: &[u8; 8] = b"MHDMMAP1";
+const HEADER_BYTES: usize = 16; // the magic bytes, and the width as a u64
+const WORD_BYTES: usize = 8;
+
+fn invalid(kind: io::ErrorKind, message: String) -> io::Error {
+    io::Error::new(kind, message)
+}
+
+pub struct MappedSamples {
+    file: File, // opened for appending
+    map: Mmap,
+    width: usize,
+    record_bytes: usize, // the score, and the bytes padded to whole words
+}
+
+impl MappedSamples {
+    /// A new, empty file (replacing any old one) for samples `width` bits wide
+    pub fn create<P: AsRef<Path>>(path: P, width: usize) -> io::Result<Self> {
+        if !Sample::size_is_legal(width) {
+            return Err(invalid(
+                io::ErrorKind::InvalidInput,
+                format!("illegal sample width {}", width),
+            ));
+        };
+        let mut file = File::create(&path)?;
+        file.write_all(MAGIC)?;
+        file.write_all(&(width as u64).to_le_bytes())?;
+        file.sync_all()?;
+        Self::open(path)
+    }
+
+    /// Reopen a file written by `create` and `append`
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).append(true).open(path)?;
+        let map = Self::map(&file)?;
+        if map.len() < HEADER_BYTES || &map[..MAGIC.len()] != MAGIC {
+            return Err(invalid(
+                io::ErrorKind::InvalidData,
+                "not a file of mapped samples".to_string(),
+            ));
+        };
+        let mut width_bytes = [0; 8];
+        width_bytes.copy_from_slice(&map[MAGIC.len()..HEADER_BYTES]);
+        let width = u64::from_le_bytes(width_bytes) as usize;
+        if !Sample::size_is_legal(width) {
+            return Err(invalid(
+                io::ErrorKind::InvalidData,
+                format!("illegal sample width {}", width),
+            ));
+        };
+        let record_bytes = WORD_BYTES + WORD_BYTES * width.div_ceil(8 * WORD_BYTES);
+        if !(map.len() - HEADER_BYTES).is_multiple_of(record_bytes) {
+            return Err(invalid(
+                io::ErrorKind::InvalidData,
+                "the last sample is incomplete".to_string(),
+            ));
+        };
+        Ok(Self {
+            file,
+            map,
+            width,
+            record_bytes,
+        })
+    }
+
+    fn map(file: &File) -> io::Result<Mmap> {
+        // Safe as long as no one else truncates the file while it is mapped;
+        // we only ever append to it (and map it again, see append_all)
+        unsafe { Mmap::map(file) }
+    }
+
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        (self.map.len() - HEADER_BYTES) / self.record_bytes
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        0 == self.len()
+    }
+
+    /// Append a sample to the file
+    pub fn append(&mut self, sample: &Sample) -> io::Result<()> {
+        self.append_all(std::iter::once(sample))
+    }
+
+    /// Append some samples to the file (and map it again only once)
+    pub fn append_all<'a, I: IntoIterator<Item = &'a Sample>>(
+        &mut self,
+        samples: I,
+    ) -> io::Result<()> {
+        let mut buffer = Vec::new();
+        for sample in samples {
+            if sample.size() != self.width {
+                return Err(invalid(
+                    io::ErrorKind::InvalidInput,
+                    format!("sample width {} instead of {}", sample.size(), self.width),
+                ));
+            };
+            if sample.is_partial() {
+                return Err(invalid(
+                    io::ErrorKind::InvalidInput,
+                    "partial samples can't be mapped".to_string(),
+                ));
+            };
+            buffer.extend_from_slice(&sample.score.to_f64().to_le_bytes());
+            buffer.extend_from_slice(&sample.bytes);
+            buffer.resize(
+                buffer.len() + self.record_bytes - WORD_BYTES - sample.bytes.len(),
+                0,
+            );
+        }
+        self.file.write_all(&buffer)?;
+        self.map = Self::map(&self.file)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn record(&self, index: usize) -> &[u8] {
+        let start = HEADER_BYTES + index * self.record_bytes;
+        &self.map[start..start + self.record_bytes]
+    }
+
+    /// The bytes of sample number `index` (without the padding)
+    #[inline]
+    pub fn bytes(&self, index: usize) -> &[u8] {
+        &self.record(index)[WORD_BYTES..WORD_BYTES + self.width.div_ceil(8)]
+    }
+
+    /// The score of sample number `index`
+    #[inline]
+    pub fn score(&self, index: usize) -> ScoreType {
+        let mut score_bytes = [0; 8];
+        score_bytes.copy_from_slice(&self.record(index)[..WORD_BYTES]);
+        ScoreType::from_f64(f64::from_le_bytes(score_bytes))
+    }
+
+    /// A copy of sample number `index`, in RAM
+    pub fn get(&self, index: usize) -> Sample {
+        let mut sample = Sample::new(self.width, self.score(index));
+        sample.bytes = SampleBytes::from(self.bytes(index));
+        sample
+    }
+
+    /// Copies of all samples, one by one
+    pub fn iter(&self) -> impl Iterator<Item = Sample> + '_ {
+        (0..self.len()).map(move |index| self.get(index))
+    }
+
+    /// The (masked hamming) distance from `query` to every sample, see `distance_many`
+    pub fn distances(&self, mask: &[u8], query: &[u8]) -> Vec<u64> {
+        let rows: Vec<&[u8]> = (0..self.len()).map(|index| self.bytes(index)).collect();
+        distance_many(mask, query, &rows)
+    }
+
+    /// The weighted average of the scores, as `MhdMemory::masked_read` computes it
+    /// for a memory with these samples (written in this order), `kernel` and `weighting`;
+    /// zero if there are no samples
+    pub fn masked_read(
+        &self,
+        mask: &[u8],
+        query: &[u8],
+        kernel: &DistanceKernel,
+        weighting: &SampleWeighting,
+    ) -> ScoreType {
+        if self.is_empty() {
+            return ZERO_SCORE;
+        };
+        let distances = self.distances(mask, query);
+        let scores: Vec<ScoreType> = (0..self.len()).map(|index| self.score(index)).collect();
+        let total = scores
+            .iter()
+            .fold(ZERO_SCORE, |total, score| total + *score);
+        let min = ScoreType::min_of(scores.iter().cloned()).unwrap_or(ZERO_SCORE);
+        let max = ScoreType::max_of(scores.iter().cloned()).unwrap_or(ZERO_SCORE);
+        let floating_avg = (total / self.len() as ScoreType).to_f64();
+        let newest = self.len() - 1;
+        let visit = |index: usize| {
+            let weight = kernel.masked_read_weight(distances[index])
+                * weighting.recency((newest - index) as f64)
+                * weighting.importance(scores[index], min, max);
+            (scores[index].to_f64(), weight)
+        };
+        MaskedRead::weighted(floating_avg, self.len(), DEFAULT_PARALLEL_THRESHOLD, visit).score
+    }
+} // end impl MappedSamples
+
+impl MhdMemory {
+    /// A memory (in RAM) with all the samples of `mapped`, written one by one
+    /// (so duplicates are skipped, and `max_samples` applies)
+    pub fn from_mapped(mapped: &MappedSamples) -> Self {
+        let mut memory = MhdMemory::new(mapped.width());
+        for sample in mapped.iter() {
+            memory.write_sample(&sample);
+        }
+        memory
+    }
+
+    /// Append all samples to `mapped`, e.g. to reopen them in a later run
+    pub fn append_to_mapped(&self, mapped: &mut MappedSamples) -> io::Result<()> {
+        mapped.append_all(self.samples.iter())
+    }
+} // end impl MhdMemory
+
+///////////////////// TESTs for MappedSamples /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}_{}.mhd", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_mapped_samples() {
+        const NUM_BITS: usize = 100;
+        let path = temp_path("test_mapped_samples");
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_n_random_samples(50);
+
+        let mut mapped = MappedSamples::create(&path, NUM_BITS).unwrap();
+        assert!(mapped.is_empty());
+        let (mask, query) = ([0xFF; 13], [0x00; 13]);
+        let read = mapped.masked_read(&mask, &query, &memory.kernel, &memory.weighting);
+        assert_eq!(read, ZERO_SCORE);
+        memory.append_to_mapped(&mut mapped).unwrap();
+        assert_eq!(mapped.len(), 50);
+
+        // Reopened: the same samples, distances and reads
+        let reopened = MappedSamples::open(&path).unwrap();
+        assert_eq!(reopened.width(), NUM_BITS);
+        assert!(reopened.iter().eq(memory.samples.iter().cloned()));
+        let mask = Sample::random(NUM_BITS);
+        let query = Sample::random(NUM_BITS);
+        assert_eq!(
+            reopened.distances(&mask.bytes, &query.bytes),
+            memory.distances(&mask.bytes, &query.bytes)
+        );
+        let mut from_mapped = MhdMemory::from_mapped(&reopened);
+        from_mapped.weighting = SampleWeighting {
+            recency_half_life: Some(20.0),
+            score_importance: 1.0,
+        };
+        from_mapped.kernel.exponent = 2.0;
+        from_mapped.kernel.threshold = Some(10); // (which masked_read ignores)
+        let (kernel, weighting) = (from_mapped.kernel, from_mapped.weighting);
+        assert_eq!(
+            reopened.masked_read(&mask.bytes, &query.bytes, &kernel, &weighting),
+            from_mapped.masked_read(&mask.bytes, &query.bytes)
+        );
+
+        // Appending a duplicate: the file keeps it, a memory doesn't
+        mapped.append(&memory.samples[0]).unwrap();
+        assert_eq!(mapped.len(), 51);
+        assert_eq!(MhdMemory::from_mapped(&mapped).samples, memory.samples);
+
+        // Wrong widths, wrong files
+        assert!(mapped.append(&Sample::new(NUM_BITS + 1, ZERO_SCORE)).is_err());
+        assert!(MappedSamples::create(&path, 2).is_err());
+        drop((mapped, reopened));
+        std::fs::write(&path, b"not mapped samples").unwrap();
+        assert!(MappedSamples::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+/// # Metrics -- how far is a sample from a query?
+///
+/// An `MhdMemory` weighs its samples by their distance to the query (see `DistanceKernel`).
+/// By default, that is the masked hamming distance, but any `Metric` can be chosen when the
+/// memory is built (see `MhdMemory::with_metric`), to experiment with others.
+///
+/// Distances are measured "in bits": zero for a perfect match, and (about) the number of
+/// masked bits for a perfect mismatch -- the memory ignores samples more than half of that away.
+/// Bit positions are the raw positions in the bytes (bit `p % 8` of byte `p / 8`),
+/// as in `distance` and in the solutions of the optimization crate.
+///
+/// ```rust
+/// use mhd_memory::{MaskedHamming, MaskedJaccard, Metric, WeightedHamming};
+///
+/// let mask = [0xFF];
+/// let query = [0b0000_0111];
+/// let sample = [0b0000_0110];
+/// assert_eq!(MaskedHamming.distance(&mask, &query, &sample), 1);
+///
+/// // Two of three set bits shared: Jaccard distance 1/3, times 8 masked bits
+/// assert_eq!(MaskedJaccard.distance(&mask, &query, &sample), 3);
+///
+/// // Bit 0 counts four times as much as the others
+/// let weighted = WeightedHamming::new(vec![4.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0]);
+/// assert_eq!(weighted.distance(&mask, &query, &sample), 4);
+/// ```
+use std::fmt::Debug;
+
+use distance_::distance;
+use weight_::weight;
+
+pub trait Metric: Debug + Send + Sync {
+    /// A short name, for traces and reports
+    fn name(&self) -> &'static str;
+
+    /// The distance from `query` to `sample`, counting only the bits set in `mask`
+    fn distance(&self, mask: &[u8], query: &[u8], sample: &[u8]) -> u64;
+}
+
+/// The masked hamming distance (the default): the number of masked bits which differ
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MaskedHamming;
+
+impl Metric for MaskedHamming {
+    fn name(&self) -> &'static str {
+        "MaskedHamming"
+    }
+
+    #[inline]
+    fn distance(&self, mask: &[u8], query: &[u8], sample: &[u8]) -> u64 {
+        distance(mask, query, sample)
+    }
+}
+
+/// A masked hamming distance where each differing bit counts its own weight
+/// (so some decisions can matter more than others). Weights are in bits: 1.0 is "normal".
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightedHamming {
+    pub weights: Vec<f64>, // one per bit position
+}
+
+impl WeightedHamming {
+    pub fn new(weights: Vec<f64>) -> Self {
+        assert!(
+            weights.iter().all(|w| 0.0 <= *w),
+            "Weights must not be negative"
+        );
+        Self { weights }
+    }
+}
+
+impl Metric for WeightedHamming {
+    fn name(&self) -> &'static str {
+        "WeightedHamming"
+    }
+
+    fn distance(&self, mask: &[u8], query: &[u8], sample: &[u8]) -> u64 {
+        let mut result = 0.0;
+        for (byte_index, (m, (q, s))) in mask.iter().zip(query.iter().zip(sample)).enumerate() {
+            let mut differences = m & (q ^ s);
+            while 0 != differences {
+                let bit = differences.trailing_zeros() as usize;
+                result += self.weights.get(8 * byte_index + bit).unwrap_or(&1.0);
+                differences &= differences - 1;
+            }
+        }
+        result.round() as u64
+    }
+}
+
+/// The masked Jaccard distance: one minus the fraction of (masked) set bits which are set in
+/// both, i.e. `1 - |q & s| / |q | s|` -- scaled to the number of masked bits.
+/// Bits which are clear in both don't count, which suits sparse samples (few items chosen).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MaskedJaccard;
+
+impl Metric for MaskedJaccard {
+    fn name(&self) -> &'static str {
+        "MaskedJaccard"
+    }
+
+    fn distance(&self, mask: &[u8], query: &[u8], sample: &[u8]) -> u64 {
+        let (mut both, mut either) = (0u64, 0u64);
+        for (m, (q, s)) in mask.iter().zip(query.iter().zip(sample)) {
+            both += (m & q & s).count_ones() as u64;
+            either += (m & (q | s)).count_ones() as u64;
+        }
+        if 0 == either {
+            return 0; // nothing set anywhere: identical
+        };
+        let jaccard_distance = 1.0 - both as f64 / either as f64;
+        (jaccard_distance * weight(mask) as f64).round() as u64
+    }
+}
+
+///////////////////// TESTs for Metric /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sample::Sample;
+
+    #[test]
+    fn test_metrics() {
+        const NUM_BITS: usize = 40;
+        let ones = vec![1.0; NUM_BITS];
+        let weighted = WeightedHamming::new(ones);
+        for _ in 0..20 {
+            let mask = Sample::random(NUM_BITS).bytes;
+            let query = Sample::random(NUM_BITS).bytes;
+            let sample = Sample::random(NUM_BITS).bytes;
+            let hamming = MaskedHamming.distance(&mask, &query, &sample);
+            assert_eq!(hamming, distance(&mask, &query, &sample));
+            // All weights one: the same as the plain masked hamming distance
+            assert_eq!(weighted.distance(&mask, &query, &sample), hamming);
+            // Every metric: zero from itself, and at most all masked bits
+            let metrics: [&dyn Metric; 3] = [&MaskedHamming, &weighted, &MaskedJaccard];
+            for metric in metrics.iter() {
+                assert_eq!(metric.distance(&mask, &query, &query), 0);
+                assert!(metric.distance(&mask, &query, &sample) <= weight(&mask));
+            }
+        }
+        // Jaccard ignores bits clear in both, hamming doesn't
+        let mask = [0xFF, 0xFF];
+        let (query, sample) = ([0b1, 0x00], [0b11, 0x00]);
+        assert_eq!(MaskedHamming.distance(&mask, &query, &sample), 1);
+        assert_eq!(MaskedJaccard.distance(&mask, &query, &sample), 8); // 1/2 of 16 bits
+        assert_eq!(MaskedJaccard.distance(&mask, &[0, 0], &[0, 0]), 0);
+        assert_eq!(MaskedJaccard.distance(&mask, &[0xF0, 0], &[0x0F, 0]), 16);
+    }
+}
@@ -1,10 +1,501 @@
 use log::*;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
 use rayon::prelude::*;
-
-use distance_::distance;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use bit_slices::BitSlices;
+use distance_::{distance, distance_many_in_chunks};
+use cluster::{ClusterIndex, DEFAULT_CLUSTER_PROBES};
+use lsh::{LshIndex, DEFAULT_BITS_PER_TABLE, DEFAULT_LSH_TABLES};
+use metric::{MaskedHamming, Metric};
+use policy::{DecisionPolicy, UcbPolicy};
+use sketch::{simhash, sketch_distance, sketch_radius};
 use weight_::weight;
 use sample::*;
+use sample_bytes::SampleBytes;
+use util::{get_bit, put_bit};
+
+/// # How well does a memory predict its own samples?
+///
+/// The result of `MhdMemory::self_score` (leave-one-out evaluation).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SelfScore {
+    pub num_samples: usize,
+    /// Mean absolute error of the predictions
+    pub mae: f64,
+    /// Mean absolute error of always predicting the average score -- the mae to beat
+    pub baseline_mae: f64,
+    /// Pearson correlation between real and predicted scores (0.0 if either is constant)
+    pub correlation: f64,
+}
+
+/// # A masked read, with its confidence
+///
+/// The result of `MhdMemory::masked_read_with_confidence`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MaskedRead {
+    /// What `masked_read` returns
+    pub score: ScoreType,
+    /// The weighted variance of the scores read (infinite if nothing was read)
+    pub variance: f64,
+    /// How many samples the read effectively averages, (sum of weights)^2 / sum of weights^2:
+    /// 1.0 if one sample outweighs all others, the number of samples if all weigh the same
+    pub effective_samples: f64,
+}
+
+impl MaskedRead {
+    /// The standard error of the weighted mean, sqrt( variance / effective_samples )
+    pub fn standard_error(&self) -> f64 {
+        (self.variance / self.effective_samples).sqrt()
+    }
+
+    // The read of `num_visits` samples, where `visit` gives the score and weight of each,
+    // around the average score `floating_avg` (for MhdMemory and MappedSamples alike)
+    pub(crate) fn weighted<F>(
+        floating_avg: f64,
+        num_visits: usize,
+        min_chunk: usize,
+        visit: F,
+    ) -> Self
+    where
+        F: Fn(usize) -> (f64, f64) + Sync + Send,
+    {
+        let (read_sum, weight_sum, score_sum, square_sum, square_weight_sum) = (0..num_visits)
+            .into_par_iter() // RAYON!!
+            .with_min_len(min_chunk)
+            .map(|index| {
+                let (score, weight) = visit(index);
+                let delta_score = score - floating_avg;
+                let weighted_delta = delta_score * weight;
+                let weighted_score = floating_avg + weighted_delta;
+                let weighted = weight * score;
+                (weighted_score, weight, weighted, weighted * score, weight * weight)
+            })
+            // For this Rayon version,
+            // see https://docs.rs/rayon/1.5.1/rayon/iter/trait.ParallelIterator.html#method.reduce
+            .reduce(
+                || (0.0, 0.0, 0.0, 0.0, 0.0), // identity element
+                |a, b| (a.0 + b.0, a.1 + b.1, a.2 + b.2, a.3 + b.3, a.4 + b.4),
+            );
+        let result = read_sum / weight_sum;
+        trace!(
+            "sum of scores = {}, sum of weights =  {}, result = {}",
+            read_sum,
+            weight_sum,
+            result
+        );
+        if 0.0 == weight_sum {
+            return Self {
+                score: result as ScoreType,
+                variance: f64::INFINITY,
+                effective_samples: 0.0,
+            };
+        };
+        let mean = score_sum / weight_sum;
+        Self {
+            score: result as ScoreType,
+            variance: (square_sum / weight_sum - mean * mean).max(0.0),
+            effective_samples: weight_sum * weight_sum / square_weight_sum,
+        }
+    }
+}
+
+impl SelfScore {
+    /// Summarize (real score, predicted score) pairs
+    pub fn from_predictions(pairs: &[(f64, f64)]) -> Self {
+        if pairs.is_empty() {
+            return Self::default();
+        };
+        let n = pairs.len() as f64;
+        let real_mean = pairs.iter().map(|p| p.0).sum::<f64>() / n;
+        let predicted_mean = pairs.iter().map(|p| p.1).sum::<f64>() / n;
+        let mae = pairs.iter().map(|p| (p.0 - p.1).abs()).sum::<f64>() / n;
+        let baseline_mae = pairs.iter().map(|p| (p.0 - real_mean).abs()).sum::<f64>() / n;
+
+        let covariance: f64 = pairs
+            .iter()
+            .map(|p| (p.0 - real_mean) * (p.1 - predicted_mean))
+            .sum();
+        let real_variance: f64 = pairs.iter().map(|p| (p.0 - real_mean).powi(2)).sum();
+        let predicted_variance: f64 = pairs.iter().map(|p| (p.1 - predicted_mean).powi(2)).sum();
+        let correlation = if 0.0 == real_variance || 0.0 == predicted_variance {
+            0.0
+        } else {
+            covariance / (real_variance * predicted_variance).sqrt()
+        };
+        Self {
+            num_samples: pairs.len(),
+            mae,
+            baseline_mae,
+            correlation,
+        }
+    }
+
+    /// Do the predictions beat guessing the average?
+    #[inline]
+    pub fn has_learned(&self) -> bool {
+        self.mae < self.baseline_mae
+    }
+} // end impl SelfScore
+
+/// # How much does a sample count when reading?
+///
+/// Every read (`masked_read`, `read_2_priorities`, `predict_score`) weighs each sample by
+/// the distance kernel, multiplied by a recency factor and a score factor configured here.
+/// Without them, a mediocre old sample at distance 1 beats an excellent new one at distance 2
+/// forever. The default (no decay, no score importance) leaves only the distance kernel.
+/// Samples which have decayed to (nearly) nothing can be dropped with `prune_stale`.
+///
+/// ```rust
+/// use mhd_memory::{MhdMemory, SampleWeighting};
+/// let mut memory = MhdMemory::new(64);
+/// memory.weighting = SampleWeighting {
+///     recency_half_life: Some(1000.0), // a sample counts half after 1000 newer ones
+///     score_importance: 1.0,           // the best sample counts twice as much as the worst
+/// };
+/// memory.write_n_random_samples(32);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SampleWeighting {
+    /// Number of later writes after which a sample counts half as much (None = never decay)
+    pub recency_half_life: Option<f64>,
+    /// Exponent for (1 + normalized score), so 0.0 means all scores count the same
+    pub score_importance: f64,
+}
+
+impl SampleWeighting {
+    /// The recency factor of a sample written `age` writes ago (see `MhdMemory::recency_factor`)
+    #[inline]
+    pub fn recency(&self, age: f64) -> f64 {
+        match self.recency_half_life {
+            None => 1.0,
+            Some(half_life) => 0.5f64.powf(age / half_life),
+        }
+    }
+
+    /// The score factor of a sample with `score`, if the scores range from `min` to `max`
+    pub fn importance(&self, score: ScoreType, min: ScoreType, max: ScoreType) -> f64 {
+        if 0.0 == self.score_importance {
+            return 1.0;
+        };
+        let range = (max - min).to_f64();
+        let normalized = if 0.0 == range {
+            1.0
+        } else {
+            (score - min).to_f64() / range
+        };
+        (1.0 + normalized).powf(self.score_importance)
+    }
+}
+
+/// # The distance kernel
+///
+/// How fast the weight of a sample falls off with its (masked hamming) distance to a query:
+/// `masked_read` weighs a sample by `1 / (distance + 1) ^ exponent`, the other reads
+/// (`read_2_priorities`, `predict_score`...) by the kernel's `shape`, and not at all beyond
+/// the `threshold`. So a larger exponent means only close samples count; the default (1.0)
+/// was chosen by hand, as was the default threshold, half the bits in the mask.
+/// Instead of guessing, the memory can fit the exponent itself (`fit_kernel_exponent`),
+/// and -- if `refit_interval` is set -- refit it every so many new samples.
+///
+/// ```rust
+/// use mhd_memory::{KernelShape, MhdMemory};
+/// let mut memory = MhdMemory::new(32);
+/// memory.kernel.refit_interval = Some(50); // refit every 50 new samples
+/// memory.write_n_random_samples(100);
+/// assert!(memory.kernel.exponent > 0.0);
+///
+/// // 1 / (distance + 1)^2, for samples at most 4 bits away
+/// memory.kernel.shape = KernelShape::Power;
+/// memory.kernel.exponent = 2.0;
+/// memory.kernel.threshold = Some(4);
+/// assert_eq!(memory.kernel.weight(4, 3), 1.0 / 16.0);
+/// assert_eq!(memory.kernel.weight(4, 5), 0.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DistanceKernel {
+    /// The exponent (see above)
+    pub exponent: f64,
+    /// Refit the exponent whenever the number of samples is a multiple of this (None = never).
+    /// Note each refit costs a leave-one-out evaluation (`self_score`) per candidate exponent.
+    pub refit_interval: Option<usize>,
+    /// The kernel of the reads other than `masked_read`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub shape: KernelShape,
+    /// Samples farther away than this don't count in those reads (None = half the masked bits)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub threshold: Option<u64>,
+}
+
+impl Default for DistanceKernel {
+    fn default() -> Self {
+        Self {
+            exponent: 1.0,
+            refit_interval: None,
+            shape: KernelShape::default(),
+            threshold: None,
+        }
+    }
+}
+
+/// The shape of a `DistanceKernel` (`d` is the distance, `t` the threshold)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum KernelShape {
+    /// `1 / (d + 1) ^ exponent`, as in `masked_read` (so exponent 2.0 means 1 / (d + 1)²)
+    Power,
+    /// `(1 - d / t) ^ (exponent / (d + 1))`, which falls to zero at the threshold
+    #[default]
+    Binomial,
+}
+
+impl DistanceKernel {
+    /// The threshold for reads with `mask`
+    #[inline]
+    pub fn threshold(&self, mask: &[u8]) -> u64 {
+        self.threshold.unwrap_or(weight(mask) / 2)
+    }
+
+    /// The weight of a sample at `distance`, given the `threshold` (see `threshold`)
+    pub fn weight(&self, threshold: u64, distance: u64) -> f64 {
+        if 0 == distance {
+            return 1.0;
+        };
+        // Now assume 0 < distance
+        let dist_plus_1 = (distance + 1) as f64; // prevents division by zero later
+        match self.shape {
+            KernelShape::Power => {
+                if threshold < distance {
+                    0.0
+                } else {
+                    1.0 / dist_plus_1.powf(self.exponent)
+                }
+            }
+            KernelShape::Binomial => {
+                // approximate 1 - (2 * cumulative binomial distribution)
+                if threshold <= distance {
+                    0.0 // too far out
+                } else {
+                    let exponent = self.exponent / dist_plus_1;
+                    let base = 1.0 - (distance as f64) / (threshold as f64);
+                    let result = base.powf(exponent);
+                    debug_assert!((0.0..=1.0).contains(&result));
+                    result
+                }
+            }
+        }
+    }
+
+    /// The weight of a sample at `distance` in `masked_read`: `1 / (distance + 1) ^ exponent`,
+    /// whatever the shape and threshold
+    #[inline]
+    pub fn masked_read_weight(&self, distance: u64) -> f64 {
+        let dist_plus_1 = (distance + 1) as f64; // adding one prevents division by zero later
+        1.0 / dist_plus_1.powf(self.exponent)
+    }
+}
+
+/// # Capacity and eviction
+///
+/// By default, a memory keeps every sample it is given -- which, in a long run, can take all
+/// the RAM there is. With `max_samples`, a full memory forgets one sample for every new one,
+/// chosen by the `eviction` policy. (The new sample itself is always written.)
+///
+/// ```rust
+/// use mhd_memory::{EvictionPolicy, MhdMemory, MhdMemoryConfig};
+///
+/// let config = MhdMemoryConfig {
+///     max_samples: Some(100),
+///     eviction: EvictionPolicy::LowestScore,
+///     ..MhdMemoryConfig::default()
+/// };
+/// let mut memory = MhdMemory::with_config(32, config);
+/// memory.write_n_random_samples(500);
+/// assert_eq!(memory.num_samples(), 100);
+/// assert_eq!(memory.num_evictions(), 400);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EvictionPolicy {
+    /// Forget the sample written (or written again) longest ago
+    #[default]
+    LeastRecentlyUsed,
+    /// Forget the sample with the lowest score (the oldest one, if there are several)
+    LowestScore,
+    /// Forget any sample, at random
+    Random,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MhdMemoryConfig {
+    /// The most samples the memory keeps (None = no limit)
+    pub max_samples: Option<usize>,
+    /// Which sample to forget when the memory is full
+    pub eviction: EvictionPolicy,
+    /// Reads (`masked_read`, `read_2_priorities`, `predict_score`, ...) visit the samples in
+    /// parallel, in chunks of at least this many samples -- so a memory with fewer samples
+    /// is read sequentially, where rayon's overhead would cost more than it saves.
+    pub parallel_threshold: usize,
+    /// With a constant, `read_2_priorities` explores by the UCB formula,
+    /// `sqrt(ln(total hits) / hits) * ucb_constant`, instead of by the hits alone (None)
+    pub ucb_constant: Option<f64>,
+    /// Seed for the memory's own random numbers (random samples, random eviction,
+    /// `read_subsample`), so that runs can be repeated (None = a new seed every time)
+    pub seed: Option<u64>,
+    /// Approximate reads: `masked_read`, `read_2_priorities` (and the other reads built on the
+    /// same scan) visit at most this many samples, chosen uniformly at random for every read,
+    /// instead of all of them (None = exact reads). This bounds the time per decision for
+    /// very large memories (say, more than 100k samples), at the price of some noise.
+    pub read_subsample: Option<usize>,
+    /// Turn scores into priorities by their z-scores (how many standard deviations above or
+    /// below the average score they are, squashed into 0..1 by the logistic function) instead
+    /// of as fractions of the best score -- so priorities tell good from bad just as well
+    /// when all scores are large and close together (e.g. 1000000 and 1000100), or when
+    /// problems with scores of very different magnitudes share a memory.
+    pub normalize_scores: bool,
+    /// Writing a sample which is already stored (e.g. the same partial sample, see
+    /// `write_masked_sample`) with a better score replaces the stored score, instead of
+    /// being ignored -- so that later reads see the best score known for those bits.
+    /// (Worse scores are still ignored; and complete samples don't need to score the same.)
+    pub overwrite_dominated: bool,
+}
+
+/// Default for `MhdMemoryConfig::parallel_threshold`
+pub const DEFAULT_PARALLEL_THRESHOLD: usize = 1024;
+
+/// A good `MhdMemoryConfig::ucb_constant` (80 * sqrt(2), chosen by hand)
+pub const DEFAULT_UCB_CONSTANT: f64 = 113.13708499;
+
+impl Default for MhdMemoryConfig {
+    fn default() -> Self {
+        Self {
+            max_samples: None,
+            eviction: EvictionPolicy::default(),
+            parallel_threshold: DEFAULT_PARALLEL_THRESHOLD,
+            ucb_constant: None,
+            seed: None,
+            read_subsample: None,
+            normalize_scores: false,
+            overwrite_dominated: false,
+        }
+    }
+}
+
+/// The exponents `fit_kernel_exponent` chooses from
+pub const KERNEL_EXPONENTS: [f64; 7] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+
+/// # The weighted sums behind a read
+///
+/// `MhdMemory::read_2_sums` adds up, over all samples close enough to a query, the weighted
+/// scores and weights of the samples with the bit at some index false, and of those with it
+/// true -- and counts the exact hits. `priorities` turns the sums into a pair of priorities.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PrioritySums {
+    pub score_false: f64,
+    pub score_true: f64,
+    pub weight_false: f64,
+    pub weight_true: f64,
+    pub hits_false: usize,
+    pub hits_true: usize,
+}
+
+impl std::ops::Add for PrioritySums {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            score_false: self.score_false + other.score_false,
+            score_true: self.score_true + other.score_true,
+            weight_false: self.weight_false + other.weight_false,
+            weight_true: self.weight_true + other.weight_true,
+            hits_false: self.hits_false + other.hits_false,
+            hits_true: self.hits_true + other.hits_true,
+        }
+    }
+}
+
+impl PrioritySums {
+    /// Convert the sums into `( prio_false, prio_true )`, relative to `max_score`
+    /// (the best score in the memory, or memories, summed up)
+    pub fn priorities(&self, max_score: ScoreType) -> (f64, f64) {
+        self.priorities_with_ucb(max_score, None)
+    }
+
+    /// `priorities`, exploring by the UCB formula with `ucb_constant`, if there is one
+    /// (see `MhdMemoryConfig::ucb_constant`)
+    pub fn priorities_with_ucb(
+        &self,
+        max_score: ScoreType,
+        ucb_constant: Option<f64>,
+    ) -> (f64, f64) {
+        let total_hits = self.hits_false + self.hits_true;
+        let result = (
+            MhdMemory::calculate_priority(
+                max_score,
+                self.hits_false,
+                total_hits,
+                self.score_false,
+                self.weight_false,
+                self.weight_true,
+                ucb_constant,
+            ),
+            MhdMemory::calculate_priority(
+                max_score,
+                self.hits_true,
+                total_hits,
+                self.score_true,
+                self.weight_true,
+                self.weight_false,
+                ucb_constant,
+            ),
+        );
+        trace!(
+            "MHD MEM: hits = ({},{}), scores = ({}, {}), weights =  ({}, {}), result = ({},{})",
+            self.hits_false,
+            self.hits_true,
+            self.score_false,
+            self.score_true,
+            self.weight_false,
+            self.weight_true,
+            result.0,
+            result.1,
+        );
+        result
+    }
+
+    /// The sums with every alternative's average score replaced by its z-score (relative to
+    /// `mean` and `std_dev`), squashed into 0..1 by the logistic function -- to be turned
+    /// into priorities relative to a best score of one (see `MhdMemoryConfig::normalize_scores`)
+    pub fn normalized(&self, mean: f64, std_dev: f64) -> Self {
+        let squash = |score: f64, weight: f64| {
+            if 0.0 < weight {
+                let z = if 0.0 < std_dev {
+                    (score / weight - mean) / std_dev
+                } else {
+                    0.0
+                };
+                weight / (1.0 + (-z).exp())
+            } else {
+                score
+            }
+        };
+        Self {
+            score_false: squash(self.score_false, self.weight_false),
+            score_true: squash(self.score_true, self.weight_true),
+            ..*self
+        }
+    }
+} // end impl PrioritySums
 
 /// # The MHD Memory Struct
 /// Formally, the memory consists of a collection of `samples`, and various `read` and `write` operations.
@@ -38,23 +529,88 @@ use sample::*;
 /// assert!( ! test_mem.is_empty() );
 /// assert_eq!( 3, test_mem.num_samples() );
 ///
-/// let target_total : ScoreType = 3 + 33 + 333; // == 369 right?
+/// let target_total = (3 + 33 + 333) as ScoreType; // == 369 right?
 /// assert_eq!( test_mem.total_score, target_total );
-/// assert_eq!( test_mem.min_score, 3 );
-/// assert_eq!( test_mem.max_score, 333 );
+/// assert_eq!( test_mem.min_score, 3 as ScoreType );
+/// assert_eq!( test_mem.max_score, 333 as ScoreType );
 /// let target_avg : ScoreType = target_total / (3 as ScoreType); // == 123 ?
 /// assert_eq!( test_mem.avg_score(), target_avg );
 /// ```
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MhdMemory {
     pub width: usize,
     pub total_score: ScoreType,
     pub max_score: ScoreType,
     pub min_score: ScoreType,
     pub samples: Vec<Sample>, // initially empty
+    pub weighting: SampleWeighting,
+    pub kernel: DistanceKernel,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub config: MhdMemoryConfig,
+    // The sum of the squares of all scores minus score_shift (the first score written), which
+    // is close enough to the scores to keep the variance accurate (see score_std_dev)
+    #[cfg_attr(feature = "serde", serde(default))]
+    shifted_squares: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    score_shift: f64,
+    write_count: u64,
+    write_stamps: Vec<u64>, // write_count when samples[i] was written
+    #[cfg_attr(feature = "serde", serde(default))]
+    use_stamps: Vec<u64>, // write_count when samples[i] was last written (again)
+    #[cfg_attr(feature = "serde", serde(default))]
+    num_evictions: u64,
+    // (The next seven are not serialized, see persistence)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    bit_slices: Option<BitSlices>, // the samples again, transposed (see use_bit_slices)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    metric: Option<Arc<dyn Metric>>, // None = the masked hamming distance (see with_metric)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    sketches: Option<Vec<u64>>, // a SimHash sketch per sample (see use_sketches)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    lsh: Option<LshIndex>, // an index for approximate reads (see use_lsh)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    clusters: Option<ClusterIndex>, // another one (see use_clusters)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    positions: HashMap<PositionKey, usize>, // bits -> index of the sample (see search_index)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    rng: Option<SeededRng>, // seeded from config.seed (None = thread_rng)
 } // end struct Sample
 
+// The memory's own random numbers, behind a lock, so that reads (which only borrow the memory)
+// can draw them, too. A clone goes on with a copy of them.
+#[derive(Debug)]
+struct SeededRng(Mutex<StdRng>);
+
+impl SeededRng {
+    fn new(seed: u64) -> Self {
+        SeededRng(Mutex::new(StdRng::seed_from_u64(seed)))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, StdRng> {
+        // (a panic while drawing a number can't leave the generator inconsistent)
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Clone for SeededRng {
+    fn clone(&self) -> Self {
+        SeededRng(Mutex::new(self.lock().clone()))
+    }
+}
+
+// What makes two samples the same (see search_index): their bits, and which of them are known
+type PositionKey = (SampleBytes, Option<SampleBytes>);
+
+fn position_key(sample: &Sample) -> PositionKey {
+    (sample.bytes.clone(), sample.known.clone())
+}
+
+fn same_bits(left: &Sample, right: &Sample) -> bool {
+    left.bytes == right.bytes && left.known == right.known
+}
+
 impl MhdMemory {
     #[inline]
     pub fn default() -> Self {
@@ -64,6 +620,22 @@ impl MhdMemory {
             max_score: ZERO_SCORE,
             min_score: ZERO_SCORE,
             samples: vec![], // start with an empty vector of samples
+            weighting: SampleWeighting::default(),
+            kernel: DistanceKernel::default(),
+            config: MhdMemoryConfig::default(),
+            shifted_squares: 0.0,
+            score_shift: 0.0,
+            write_count: 0,
+            write_stamps: vec![],
+            use_stamps: vec![],
+            num_evictions: 0,
+            bit_slices: None,
+            metric: None,
+            sketches: None,
+            lsh: None,
+            clusters: None,
+            positions: HashMap::new(),
+            rng: None,
         }
     }
 
@@ -76,6 +648,75 @@ impl MhdMemory {
         }
     }
 
+    /// A memory which measures distances with `metric` instead of the masked hamming distance
+    /// (in every read, see `Metric`).
+    pub fn with_metric<M: Metric + 'static>(width: usize, metric: M) -> Self {
+        Self {
+            metric: Some(Arc::new(metric)),
+            ..Self::new(width)
+        }
+    }
+
+    /// A memory with a capacity limit (and eviction policy), see `MhdMemoryConfig`
+    pub fn with_config(width: usize, config: MhdMemoryConfig) -> Self {
+        Self {
+            config,
+            rng: config.seed.map(SeededRng::new),
+            ..Self::new(width)
+        }
+    }
+
+    /// Seed the memory's own random numbers anew (see `MhdMemoryConfig::seed`),
+    /// e.g. to repeat a run with a memory which already holds samples
+    pub fn reseed(&mut self, seed: u64) {
+        self.config.seed = Some(seed);
+        self.rng = Some(SeededRng::new(seed));
+    }
+
+    // Call `f` with the memory's own random numbers (see `MhdMemoryConfig::seed`),
+    // or, without a seed, with the thread's
+    fn with_rng<T, F: FnOnce(&mut dyn RngCore) -> T>(&self, f: F) -> T {
+        match self.rng.as_ref() {
+            Some(rng) => f(&mut *rng.lock()),
+            None => f(&mut rand::thread_rng()),
+        }
+    }
+
+    /// The name of the metric this memory measures distances with
+    pub fn metric_name(&self) -> &'static str {
+        match &self.metric {
+            None => MaskedHamming.name(),
+            Some(metric) => metric.name(),
+        }
+    }
+
+    // The distance from `query` to a sample's bytes, in this memory's metric
+    #[inline]
+    pub(crate) fn metric_distance(&self, mask: &[u8], query: &[u8], bytes: &[u8]) -> u64 {
+        match &self.metric {
+            None => distance(mask, query, bytes),
+            Some(metric) => metric.distance(mask, query, bytes),
+        }
+    }
+
+    // The distance from `query` to a sample, in this memory's metric,
+    // counting only the bits of a partial sample which are known (see write_masked_sample)
+    #[inline]
+    fn sample_distance(&self, mask: &[u8], query: &[u8], sample: &Sample) -> u64 {
+        match &sample.known {
+            None => self.metric_distance(mask, query, &sample.bytes),
+            Some(known) => {
+                let both: Vec<u8> = mask.iter().zip(known.iter()).map(|(m, k)| m & k).collect();
+                self.metric_distance(&both, query, &sample.bytes)
+            }
+        }
+    }
+
+    /// Are some of the samples partial, see `write_masked_sample`?
+    pub fn has_partial_samples(&self) -> bool {
+        self.samples.par_iter().any(|s| s.is_partial()) // RAYON!
+    }
+
     #[inline]
     pub fn width(&self) -> usize {
         self.width
@@ -91,6 +732,46 @@ impl MhdMemory {
         self.samples.is_empty()
     }
 
+    /// The samples, in the order they are stored (so `for sample in &memory` works, too)
+    #[inline]
+    pub fn iter(&self) -> std::slice::Iter<'_, Sample> {
+        self.samples.iter()
+    }
+
+    /// The samples, in parallel (RAYON)
+    #[inline]
+    pub fn par_iter(&self) -> rayon::slice::Iter<'_, Sample> {
+        self.samples.par_iter()
+    }
+
+    /// Approximate number of bytes this memory holds: the samples (and their bytes),
+    /// their positions, and the bit slices, sketches and LSH index, if used
+    pub fn memory_bytes(&self) -> usize {
+        // (every sample holds the same number of bytes -- no need to visit them all)
+        let bytes_per_sample = self.samples.first().map_or(0, |s| s.bytes.capacity());
+        let samples = self.samples.capacity() * std::mem::size_of::<Sample>()
+            + self.num_samples() * bytes_per_sample;
+        let write_stamps = (self.write_stamps.capacity() + self.use_stamps.capacity())
+            * std::mem::size_of::<u64>();
+        let bit_slices = self.bit_slices.as_ref().map_or(0, |slices| slices.memory_bytes());
+        let sketches = self
+            .sketches
+            .as_ref()
+            .map_or(0, |sketches| sketches.capacity() * std::mem::size_of::<u64>());
+        let lsh = self.lsh.as_ref().map_or(0, |lsh| lsh.memory_bytes());
+        let clusters = self.clusters.as_ref().map_or(0, |c| c.memory_bytes());
+        let positions = self.positions.capacity()
+            * (std::mem::size_of::<(PositionKey, usize)>() + bytes_per_sample);
+        std::mem::size_of::<Self>()
+            + samples
+            + write_stamps
+            + positions
+            + bit_slices
+            + sketches
+            + lsh
+            + clusters
+    }
+
     #[inline]
     pub fn avg_score(&self) -> ScoreType {
         if self.is_empty() {
@@ -104,17 +785,187 @@ impl MhdMemory {
     #[inline]
     pub fn clear(&mut self) {
         let old_width = self.width;
+        let old_weighting = self.weighting;
+        let old_kernel = self.kernel;
+        let old_config = self.config;
+        let used_bit_slices = self.uses_bit_slices();
+        let used_sketches = self.uses_sketches();
+        let old_metric = self.metric.take();
+        let old_lsh = self.lsh.take();
+        let old_clusters = self.clusters.take();
+        let old_rng = self.rng.take();
         self.samples.clear();
         *self = Self::new(old_width);
+        self.weighting = old_weighting;
+        self.kernel = old_kernel;
+        self.config = old_config;
+        self.metric = old_metric;
+        self.rng = old_rng;
+        self.use_bit_slices(used_bit_slices);
+        self.use_sketches(used_sketches);
+        if let Some(mut lsh) = old_lsh {
+            lsh.clear(); // (keeping the sampled bits)
+            self.lsh = Some(lsh);
+        };
+        if let Some(mut clusters) = old_clusters {
+            clusters.clear(); // (keeping the medoids)
+            self.clusters = Some(clusters);
+        };
     }
 
     // search for a sample with a patter -- return true iff the query is already stored
     #[inline]
     pub fn search(&self, query: &Sample) -> Option<&Sample> {
+        self.search_index(query).map(|index| &self.samples[index])
+    } // end sample_present
+
+    /// All samples which agree with `query` on every bit set in `mask` (a partial sample, see
+    /// `write_masked_sample`, only on the bits it knows), in the order they were written.
+    /// Unlike `search`, the other bits don't matter -- so this is how to look up a partial
+    /// solution, e.g. to find out if an equivalent one has been seen before.
+    /// Unlike `within(mask, query, 0)`, it doesn't depend on the metric.
+    ///
+    /// ```rust
+    /// use mhd_memory::{MhdMemory, Sample, ScoreType};
+    ///
+    /// let mut memory = MhdMemory::new(16);
+    /// memory.write_sample(&Sample::new(16, 1 as ScoreType));
+    /// memory.write_sample(&Sample::new_ones(16, 2 as ScoreType));
+    /// let mut sample = Sample::new(16, 3 as ScoreType);
+    /// sample.set_bit(15, true);
+    /// memory.write_sample(&sample);
+    ///
+    /// let mask = [0xFF, 0x00]; // only the first byte counts
+    /// let scores: Vec<_> = memory.search_masked(&mask, &[0x00, 0xFF]).map(|s| s.score).collect();
+    /// assert_eq!(scores, vec![1 as ScoreType, 3 as ScoreType]);
+    /// assert_eq!(memory.search_masked(&[0x00, 0x00], &[0x00, 0x00]).count(), 3);
+    /// ```
+    pub fn search_masked<'a>(
+        &'a self,
+        mask: &'a [u8],
+        query: &'a [u8],
+    ) -> impl Iterator<Item = &'a Sample> + 'a {
+        self.samples.iter().filter(move |sample| {
+            let agrees = |(index, (m, (q, s))): (usize, (&u8, (&u8, &u8)))| {
+                let known = sample.known.as_ref().map_or(0xFF, |known| known[index]);
+                0 == (q ^ s) & m & known
+            };
+            mask.iter()
+                .zip(query.iter().zip(sample.bytes.iter()))
+                .enumerate()
+                .all(agrees)
+        })
+    }
+
+    // The index of the sample with the same bits as `query`, if there is one.
+    // A hash map lookup, unless samples were pushed directly into `samples`
+    // (or the memory was deserialized): then the positions are incomplete, and we scan.
+    fn search_index(&self, query: &Sample) -> Option<usize> {
+        if self.positions.len() == self.num_samples() {
+            return self.positions.get(&position_key(query)).cloned();
+        };
+        if let Some(sketches) = self.current_sketches() {
+            // only samples with the same sketch can be the same
+            let sketch = simhash(&query.bytes, self.width);
+            return sketches
+                .par_iter() // RAYON!
+                .zip(self.samples.par_iter())
+                .position_any(|(s_sketch, s_in_mem)| {
+                    *s_sketch == sketch && same_bits(s_in_mem, query)
+                });
+        };
         self.samples
             .par_iter() // RAYON!
-            .find_any(|s_in_mem| s_in_mem.bytes == query.bytes)
-    } // end sample_present
+            .position_any(|s_in_mem| same_bits(s_in_mem, query))
+    }
+
+    /// All samples whose (masked) distance from `query` is at most `radius`, in this memory's
+    /// metric, in the order they were written. The distances come from the bit slices if they
+    /// are used; if the radius covers every masked bit, no distances are computed at all.
+    /// With sketches (see `use_sketches`) and a full mask, only samples with a close sketch
+    /// are measured -- so, rarely, a sample within the radius may be missed.
+    ///
+    /// ```rust
+    /// use mhd_memory::{MhdMemory, Sample};
+    ///
+    /// let mut memory = MhdMemory::new(32);
+    /// memory.write_n_random_samples(100);
+    /// let query = Sample::random(32);
+    /// let mask = [0xFF, 0x00, 0x00, 0x00]; // only the first byte counts
+    /// for sample in memory.within(&mask, &query.bytes, 2) {
+    ///     assert!((sample.bytes[0] ^ query.bytes[0]).count_ones() <= 2);
+    /// }
+    /// assert_eq!(memory.within(&mask, &query.bytes, 8).count(), memory.num_samples());
+    /// ```
+    pub fn within<'a>(
+        &'a self,
+        mask: &[u8],
+        query: &[u8],
+        radius: u64,
+    ) -> impl Iterator<Item = &'a Sample> + 'a {
+        // Cheap prefilter: no masked hamming distance exceeds the number of masked bits
+        let distances = if self.metric.is_none() && weight(mask) <= radius {
+            None
+        } else if let Some(sketches) = self
+            .current_sketches()
+            .filter(|_| self.is_full_mask(mask) && !self.has_partial_samples())
+        {
+            let sketch = simhash(query, self.width);
+            let max_sketch_distance = sketch_radius(radius, self.width);
+            Some(
+                self.samples
+                    .par_iter() // RAYON!
+                    .zip(sketches.par_iter())
+                    .map(|(s, s_sketch)| {
+                        if max_sketch_distance < sketch_distance(sketch, *s_sketch) {
+                            u64::MAX // (very probably) too far
+                        } else {
+                            self.sample_distance(mask, query, s)
+                        }
+                    })
+                    .collect(),
+            )
+        } else {
+            Some(self.distances(mask, query))
+        };
+        self.samples
+            .iter()
+            .enumerate()
+            .filter(move |(index, _)| distances.as_ref().is_none_or(|d| d[*index] <= radius))
+            .map(|(_, sample)| sample)
+    }
+
+    /// All samples with their (masked) distances from `query`, in this memory's metric,
+    /// closest first (and, at the same distance, in the order they were written).
+    /// The distances are all computed at once, but sorted lazily -- so taking the closest
+    /// few costs little more than computing the distances.
+    ///
+    /// ```rust
+    /// use mhd_memory::{MhdMemory, Sample};
+    ///
+    /// let mut memory = MhdMemory::new(32);
+    /// memory.write_n_random_samples(100);
+    /// let query = Sample::random(32);
+    /// let mask = [0xFF; 4];
+    /// let closest: Vec<(u64, &Sample)> =
+    ///     memory.iter_by_distance(&mask, &query.bytes).take(5).collect();
+    /// assert_eq!(closest.len(), 5);
+    /// assert!(closest.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    /// ```
+    pub fn iter_by_distance<'a>(
+        &'a self,
+        mask: &[u8],
+        query: &[u8],
+    ) -> impl Iterator<Item = (u64, &'a Sample)> + 'a {
+        let mut heap: BinaryHeap<Reverse<(u64, usize)>> = self
+            .distances(mask, query)
+            .into_iter()
+            .enumerate()
+            .map(|(index, distance)| Reverse((distance, index)))
+            .collect(); // (heapifies in linear time)
+        std::iter::from_fn(move || heap.pop())
+            .map(move |Reverse((distance, index))| (distance, &self.samples[index]))
+    }
 
     /// returns true iff new_sample not yet in memory (returns false if already there)
     pub fn write_sample(&mut self, new_sample: &Sample) -> bool {
@@ -123,19 +974,41 @@ impl MhdMemory {
         // First take care of the scores
         if self.is_empty() {
             self.total_score += new_sample.score;
+            self.score_shift = new_sample.score.to_f64();
+            self.shifted_squares = 0.0;
             self.max_score = new_sample.score;
             self.min_score = new_sample.score;
-            self.samples.push(new_sample.clone());
+            self.push_sample(new_sample);
             true
         } else {
-            match self.search(&new_sample) {
-                Some(elder_sample) => {
-                    // Check that the scores match TOO, which they must...
-                    assert_eq!(elder_sample.score, new_sample.score);
-                    // But otherwise do nothingm, but return false
+            match self.search_index(new_sample) {
+                Some(elder_index) => {
+                    let elder_score = self.samples[elder_index].score;
+                    if self.config.overwrite_dominated {
+                        // A better score for the same bits replaces the old one
+                        if new_sample.score.cmp_scores(&elder_score).is_gt() {
+                            self.rescore_sample(elder_index, new_sample);
+                        };
+                    } else if !new_sample.is_partial() {
+                        // Check that the scores match TOO, which they must...
+                        // (unless the sample is partial: then it has many completions)
+                        assert_eq!(elder_score, new_sample.score);
+                    };
+                    // But otherwise do nothingm (but note the use), and return false
+                    if let Some(stamp) = self.use_stamps.get_mut(elder_index) {
+                        *stamp = self.write_count;
+                    };
                     false
                 }
                 None => {
+                    // Make room first, if full
+                    let max_samples = self.config.max_samples;
+                    if max_samples.is_some_and(|max| max <= self.num_samples()) {
+                        self.evict_one();
+                        if self.is_empty() {
+                            return self.write_sample(new_sample);
+                        };
+                    };
                     // if not empty, and query not found in memory:
                     // I wanted to use ::std::cmp::max and min here, but...
                     // the trait `Ord` is not implemented for `f32`
@@ -146,7 +1019,8 @@ impl MhdMemory {
                         self.min_score = new_sample.score
                     };
                     self.total_score += new_sample.score;
-                    self.samples.push(new_sample.clone());
+                    self.shifted_squares += (new_sample.score.to_f64() - self.score_shift).powi(2);
+                    self.push_sample(new_sample);
                     // return...
                     true
                 } // end case None
@@ -154,56 +1028,680 @@ impl MhdMemory {
         } // end if not empty
     } // end write_sample
 
+    /// Write a partial sample: only the bits set in `mask` are known (e.g. the decisions
+    /// of a promising partial solution, and its best score so far). Reads ignore the unknown
+    /// bits, on the stored side, just as they ignore the bits outside their own masks.
+    /// A partial sample written twice is stored once, with the score it was first written with.
+    /// (With a full mask, this is just `write_sample`.)
+    /// The sketches and the LSH index (see `use_sketches`, `use_lsh`) don't know about
+    /// unknown bits; sketches aren't used while there are partial samples.
+    /// Neither `export_csv` nor `MappedSamples` store partial samples.
+    ///
+    /// ```rust
+    /// use mhd_memory::{MhdMemory, Sample, ScoreType};
+    ///
+    /// let mut memory = MhdMemory::new(16);
+    /// memory.write_sample(&Sample::new_ones(16, 100 as ScoreType));
+    /// // Only the first byte is known: all zeros
+    /// assert!(memory.write_masked_sample(&[0xFF, 0x00], &[0x00, 0xFF], 10 as ScoreType));
+    /// assert!(memory.samples[1].is_partial());
+    ///
+    /// // The second byte of the partial sample doesn't count
+    /// assert_eq!(memory.distances(&[0xFF, 0xFF], &[0x00, 0xFF]), vec![8, 0]);
+    /// ```
+    pub fn write_masked_sample(&mut self, mask: &[u8], bytes: &[u8], score: ScoreType) -> bool {
+        let mut sample = Sample::new(self.width, score);
+        sample.bytes.copy_from_slice(bytes);
+        if self.is_full_mask(mask) {
+            return self.write_sample(&sample);
+        };
+        self.write_sample(&sample.with_known(mask))
+    }
+
+    /// Forget sample number `index` (later samples move up one)
+    pub fn remove_sample(&mut self, index: usize) -> Sample {
+        let removed = self.samples.remove(index);
+        // (samples pushed directly into `samples` have no stamps, bit slices, sketches...)
+        if index < self.write_stamps.len() {
+            self.write_stamps.remove(index);
+            self.use_stamps.remove(index);
+        };
+        if let Some(slices) = self.bit_slices.as_mut() {
+            if index < slices.len() {
+                slices.remove(index);
+            };
+        };
+        if let Some(sketches) = self.sketches.as_mut() {
+            if index < sketches.len() {
+                sketches.remove(index);
+            };
+        };
+        if let Some(lsh) = self.lsh.as_mut() {
+            if index < lsh.len() {
+                lsh.remove(index, &removed.bytes);
+            };
+        };
+        if let Some(clusters) = self.clusters.as_mut() {
+            if index < clusters.len() {
+                clusters.remove(index);
+            };
+        };
+        let removed_key = position_key(&removed);
+        if self.positions.get(&removed_key) == Some(&index) {
+            self.positions.remove(&removed_key);
+        };
+        for position in self.positions.values_mut().filter(|position| index < **position) {
+            *position -= 1;
+        }
+        // Fix the scores
+        self.total_score -= removed.score;
+        self.shifted_squares -= (removed.score.to_f64() - self.score_shift).powi(2);
+        if removed.score == self.min_score || removed.score == self.max_score {
+            let scores = self.samples.iter().map(|sample| sample.score);
+            self.min_score = Score::min_of(scores.clone()).unwrap_or(ZERO_SCORE);
+            self.max_score = Score::max_of(scores).unwrap_or(ZERO_SCORE);
+        };
+        removed
+    }
+
+    // Give sample number `index` the (better) score (and objectives) of `better`
+    fn rescore_sample(&mut self, index: usize, better: &Sample) {
+        let old_score = self.samples[index].score;
+        self.samples[index].score = better.score;
+        self.samples[index].objectives = better.objectives.clone();
+        self.total_score -= old_score;
+        self.total_score += better.score;
+        self.shifted_squares += (better.score.to_f64() - self.score_shift).powi(2)
+            - (old_score.to_f64() - self.score_shift).powi(2);
+        if self.max_score < better.score {
+            self.max_score = better.score;
+        };
+        if old_score == self.min_score {
+            let scores = self.samples.iter().map(|sample| sample.score);
+            self.min_score = Score::min_of(scores).unwrap_or(ZERO_SCORE);
+        };
+    }
+
+    /// How many samples were forgotten to make room for new ones (see `MhdMemoryConfig`)
+    #[inline]
+    pub fn num_evictions(&self) -> u64 {
+        self.num_evictions
+    }
+
+    // Forget one sample, chosen by the eviction policy
+    fn evict_one(&mut self) {
+        let victim = match self.config.eviction {
+            EvictionPolicy::LeastRecentlyUsed => (0..self.num_samples())
+                .min_by_key(|index| self.use_stamps.get(*index).cloned().unwrap_or(u64::MAX)),
+            EvictionPolicy::LowestScore => {
+                (0..self.num_samples()).min_by(|a, b| {
+                    self.samples[*a].score.cmp_scores(&self.samples[*b].score)
+                })
+            }
+            EvictionPolicy::Random => {
+                let num_samples = self.num_samples();
+                Some(self.with_rng(|rng| rng.gen_range(0..num_samples)))
+            }
+        };
+        if let Some(index) = victim {
+            self.remove_sample(index);
+            self.num_evictions += 1;
+        };
+    }
+
+    /// Write a whole batch of samples (e.g. all complete children of a search node),
+    /// returns how many of them were new
+    pub fn write_samples<'a, I: IntoIterator<Item = &'a Sample>>(&mut self, samples: I) -> usize {
+        samples
+            .into_iter()
+            .filter(|sample| self.write_sample(sample))
+            .count()
+    }
+
+    /// A new memory, for another problem instance, with what this one learned: bit `b` of
+    /// every new sample is bit `mapping[b]` of the old one -- so the new width is
+    /// `mapping.len()` -- or, if that is None (a decision the old instance didn't have),
+    /// unknown: the sample is written as a partial sample (see `write_masked_sample`).
+    /// The scores stay as they were. If several samples map to the same new one, the first
+    /// is kept. The new memory has this one's config, kernel and weighting (but no metric,
+    /// bit slices or indexes, see `use_bit_slices` etc.).
+    ///
+    /// ```rust
+    /// use mhd_memory::util::{get_bit, put_bit};
+    /// use mhd_memory::{MhdMemory, Sample, ScoreType};
+    ///
+    /// let mut memory = MhdMemory::new(16);
+    /// let mut sample = Sample::new(16, 42 as ScoreType);
+    /// put_bit(&mut sample.bytes, 3, true); // (numbered as the solutions number them)
+    /// memory.write_sample(&sample);
+    ///
+    /// // The new instance has the old bits 0 to 7 (in reverse), and a new last bit
+    /// let mapping: Vec<Option<usize>> = (0..8).rev().map(Some).chain(Some(None)).collect();
+    /// let remapped = memory.remap(&mapping);
+    /// assert_eq!((remapped.width(), remapped.num_samples()), (9, 1));
+    /// let new_sample = &remapped.samples[0];
+    /// assert!(get_bit(&new_sample.bytes, 4) && new_sample.is_partial());
+    /// assert_eq!(new_sample.score, 42 as ScoreType);
+    /// ```
+    pub fn remap(&self, mapping: &[Option<usize>]) -> MhdMemory {
+        assert!(mapping.iter().flatten().all(|old| *old < self.width));
+        let mut result = MhdMemory::with_config(mapping.len(), self.config);
+        result.kernel = self.kernel;
+        result.weighting = self.weighting;
+        // Samples hold a solution's query bytes, so use the solutions' bit order
+        let remapped = |bytes: &[u8]| -> Vec<bool> {
+            mapping.iter().map(|m| m.is_some_and(|old| get_bit(bytes, old))).collect()
+        };
+        let known: Vec<bool> = mapping.iter().map(|m| m.is_some()).collect();
+        let known = Sample::from_solution_bits(&known, ZERO_SCORE);
+        for sample in self.samples.iter() {
+            let mut new_sample = Sample::from_solution_bits(&remapped(&sample.bytes), sample.score);
+            new_sample.objectives = sample.objectives.clone();
+            if let Some(old_known) = sample.known.as_ref() {
+                let new_known = Sample::from_solution_bits(&remapped(old_known), ZERO_SCORE);
+                new_sample = new_sample.with_known(&new_known.bytes);
+            } else if mapping.iter().any(|m| m.is_none()) {
+                new_sample = new_sample.with_known(&known.bytes);
+            };
+            if result.search(&new_sample).is_none() {
+                result.write_sample(&new_sample);
+            };
+        }
+        result
+    } // end remap
+
+    /// Keep (or stop keeping) a transposed copy of the samples, see `BitSlices`.
+    /// With it, `read_2_priorities` computes all distances column-wise, which is much faster
+    /// for large memories -- at the price of storing every sample twice -- and also takes
+    /// the bit it decides from its column (see `bit_column`).
+    /// The samples (rows) stay what is written; each new one is appended to the columns, too.
+    pub fn use_bit_slices(&mut self, on: bool) {
+        self.bit_slices = if on {
+            let num_bytes = Sample::new(self.width, ZERO_SCORE).size_in_bytes();
+            let mut slices = BitSlices::new(num_bytes);
+            for sample in self.samples.iter() {
+                slices.push(&sample.bytes);
+            }
+            Some(slices)
+        } else {
+            None
+        };
+    }
+
+    #[inline]
+    pub fn uses_bit_slices(&self) -> bool {
+        self.bit_slices.is_some()
+    }
+
+    // The bit slices, if they are used, and hold every sample
+    // (samples pushed directly into `samples` are missing, see `distances`)
+    #[inline]
+    fn current_bit_slices(&self) -> Option<&BitSlices> {
+        self.bit_slices
+            .as_ref()
+            .filter(|slices| slices.len() == self.num_samples())
+    }
+
+    /// Bit `index` (as in `Sample::get_bit`) of every sample, 64 samples per word,
+    /// see `BitSlices::column` -- if bit slices are used (see `use_bit_slices`)
+    ///
+    /// ```rust
+    /// use mhd_memory::MhdMemory;
+    ///
+    /// let mut memory = MhdMemory::new(32);
+    /// memory.write_n_random_samples(100);
+    /// assert!(memory.bit_column(3).is_none());
+    /// memory.use_bit_slices(true);
+    /// let column = memory.bit_column(3).unwrap();
+    /// let set = column.iter().map(|word| word.count_ones() as usize).sum::<usize>();
+    /// assert_eq!(set, memory.samples.iter().filter(|s| s.get_bit(3)).count());
+    /// ```
+    pub fn bit_column(&self, index: usize) -> Option<&[u64]> {
+        assert!(index < self.width);
+        self.current_bit_slices()
+            .map(|slices| slices.column(BitSlices::position_of_bit(index)))
+    }
+
+    // Bit `index` of sample number `s_index`: from its column, if there is one
+    #[inline]
+    fn column_or_row_bit(&self, column: Option<&[u64]>, s_index: usize, index: usize) -> bool {
+        match column {
+            Some(column) => 0 != column[s_index / 64] & (1u64 << (s_index % 64)),
+            None => self.samples[s_index].get_bit(index),
+        }
+    }
+
+    /// Keep (or stop keeping) a SimHash sketch of every sample, see `sketch`.
+    /// With them, `search` (and so `write_sample`) and `within` (with a full mask) compare
+    /// sketches first, and only compute exact distances for samples with close sketches
+    /// -- which pays off for large memories, at the price of 8 bytes per sample.
+    pub fn use_sketches(&mut self, on: bool) {
+        self.sketches = if on {
+            Some(
+                self.samples
+                    .par_iter() // RAYON!
+                    .map(|s| simhash(&s.bytes, self.width))
+                    .collect(),
+            )
+        } else {
+            None
+        };
+    }
+
+    #[inline]
+    pub fn uses_sketches(&self) -> bool {
+        self.sketches.is_some()
+    }
+
+    // The sketches, if they are used and complete
+    // (samples pushed directly into `samples` are missing in the sketches)
+    fn current_sketches(&self) -> Option<&Vec<u64>> {
+        self.sketches
+            .as_ref()
+            .filter(|sketches| sketches.len() == self.num_samples())
+    }
+
+    /// Keep (or stop keeping) a locality sensitive hashing index of the samples, with
+    /// `DEFAULT_LSH_TABLES` tables of `DEFAULT_BITS_PER_TABLE` bits, see `LshIndex`.
+    /// With it, `masked_read` and `read_2_priorities` only visit the samples the index finds
+    /// for the query, instead of all of them -- much faster for large memories, but
+    /// approximate: far samples are ignored, and a few close ones can be missed.
+    /// (If the index can't help with a mask, or finds nothing, a read visits all samples.)
+    ///
+    /// ```rust
+    /// use mhd_memory::{MhdMemory, Sample};
+    /// let mut memory = MhdMemory::new(64);
+    /// memory.use_lsh(true);
+    /// memory.write_n_random_samples(1000);
+    ///
+    /// let mask = vec![0xFF; 8];
+    /// let query = memory.samples[42].clone();
+    /// let (prio_false, prio_true) = memory.read_2_priorities(&mask, &query.bytes, 0);
+    /// assert!(0.0 < prio_false && 0.0 < prio_true);
+    /// ```
+    pub fn use_lsh(&mut self, on: bool) {
+        if on {
+            let index = LshIndex::new(self.width, DEFAULT_LSH_TABLES, DEFAULT_BITS_PER_TABLE);
+            self.use_lsh_index(index);
+        } else {
+            self.lsh = None;
+        };
+    }
+
+    /// Like `use_lsh(true)`, but with an index of your own (e.g. with more tables);
+    /// whatever it held is replaced by this memory's samples
+    pub fn use_lsh_index(&mut self, mut index: LshIndex) {
+        index.clear();
+        for sample in self.samples.iter() {
+            index.insert(&sample.bytes);
+        }
+        self.lsh = Some(index);
+    }
+
+    #[inline]
+    pub fn uses_lsh(&self) -> bool {
+        self.lsh.is_some()
+    }
+
+    // The samples a read should visit, if the LSH index is used (and complete),
+    // can be used with this mask, and finds any
+    fn lsh_candidates(&self, mask: &[u8], query: &[u8]) -> Option<Vec<usize>> {
+        self.lsh
+            .as_ref()
+            .filter(|lsh| lsh.len() == self.num_samples())
+            .and_then(|lsh| lsh.candidates(mask, query))
+            .filter(|candidates| !candidates.is_empty())
+    }
+
+    /// Keep (or stop keeping) a `ClusterIndex` of the samples: about `sqrt(3 * n)` clusters
+    /// (for `n` samples, now), of which a read visits the `DEFAULT_CLUSTER_PROBES` nearest.
+    /// With it (and unless an LSH index finds the candidates, see `use_lsh`), `masked_read`
+    /// and `read_2_priorities` only visit the members of those clusters -- much faster for
+    /// large memories, but approximate: close samples in other clusters are ignored.
+    /// Later samples join the nearest cluster, but the clusters are only found anew by calling
+    /// this again: best once the memory holds most of its samples, and again now and then.
+    ///
+    /// ```rust
+    /// use mhd_memory::MhdMemory;
+    /// let mut memory = MhdMemory::new(64);
+    /// memory.write_n_random_samples(1000);
+    /// memory.use_clusters(true);
+    /// assert!(memory.uses_clusters());
+    ///
+    /// let mask = vec![0xFF; 8];
+    /// let query = memory.samples[42].clone();
+    /// let (prio_false, prio_true) = memory.read_2_priorities(&mask, &query.bytes, 0);
+    /// assert!(0.0 < prio_false && 0.0 < prio_true);
+    /// ```
+    pub fn use_clusters(&mut self, on: bool) {
+        if on {
+            let num_probes = DEFAULT_CLUSTER_PROBES;
+            let num_clusters = ((num_probes * self.num_samples()) as f64).sqrt().ceil() as usize;
+            self.use_cluster_index(ClusterIndex::new(num_clusters, num_probes));
+        } else {
+            self.clusters = None;
+        };
+    }
+
+    /// Like `use_clusters(true)`, but with as many clusters and probes as `index` has;
+    /// whatever it held is replaced by this memory's samples, clustered anew
+    pub fn use_cluster_index(&mut self, index: ClusterIndex) {
+        let rows: Vec<&[u8]> = self.samples.iter().map(|s| &s.bytes[..]).collect();
+        let index = ClusterIndex::build(&rows, index.max_clusters(), index.num_probes());
+        self.clusters = Some(index);
+    }
+
+    #[inline]
+    pub fn uses_clusters(&self) -> bool {
+        self.clusters.is_some()
+    }
+
+    // The samples a read should visit, if the cluster index is used (and complete),
+    // can be used with this mask, and finds any
+    fn cluster_candidates(&self, mask: &[u8], query: &[u8]) -> Option<Vec<usize>> {
+        self.clusters
+            .as_ref()
+            .filter(|clusters| clusters.len() == self.num_samples())
+            .and_then(|clusters| clusters.candidates(mask, query))
+            .filter(|candidates| !candidates.is_empty())
+    }
+
+    // Does `mask` cover every bit of a sample?
+    fn is_full_mask(&self, mask: &[u8]) -> bool {
+        (0..self.width).all(|position| 0 != mask[position / 8] & (1 << (position % 8)))
+    }
+
+    // Parallel reads split the samples into chunks of at least this many
+    // (see MhdMemoryConfig::parallel_threshold)
+    #[inline]
+    fn min_chunk(&self) -> usize {
+        self.config.parallel_threshold.max(1)
+    }
+
+    /// The distance from `query` to every sample, in this memory's metric: from the bit slices
+    /// if they are used, otherwise with `distance_many` (for the masked hamming distance).
+    /// The unknown bits of partial samples don't count (see `write_masked_sample`).
+    pub fn distances(&self, mask: &[u8], query: &[u8]) -> Vec<u64> {
+        if self.has_partial_samples() {
+            // (each partial sample with a mask of its own)
+            return self
+                .samples
+                .par_iter() // RAYON!
+                .with_min_len(self.min_chunk())
+                .map(|s| self.sample_distance(mask, query, s))
+                .collect();
+        };
+        match (&self.bit_slices, &self.metric) {
+            // (samples pushed directly into `samples` are missing in the bit slices)
+            (Some(slices), None) if slices.len() == self.num_samples() => {
+                slices.distances(mask, query)
+            }
+            (_, None) => distance_many_in_chunks(mask, query, &self.samples, self.min_chunk()),
+            (_, Some(metric)) => self
+                .samples
+                .par_iter() // RAYON!
+                .with_min_len(self.min_chunk())
+                .map(|s| metric.distance(mask, query, &s.bytes))
+                .collect(),
+        }
+    }
+
+    // The samples a read should visit: those the LSH index or the clusters find
+    // (see lsh_candidates, cluster_candidates), or all -- or, with `config.read_subsample`,
+    // at most that many of them, chosen at random (in the order they were written).
+    // None = all samples.
+    fn read_candidates(&self, mask: &[u8], query: &[u8]) -> Option<Vec<usize>> {
+        let candidates = self
+            .lsh_candidates(mask, query)
+            .or_else(|| self.cluster_candidates(mask, query));
+        let num_candidates = candidates.as_ref().map_or(self.num_samples(), |c| c.len());
+        match self.config.read_subsample {
+            Some(limit) if limit < num_candidates => {
+                let mut chosen = self
+                    .with_rng(|rng| rand::seq::index::sample(rng, num_candidates, limit))
+                    .into_vec();
+                chosen.sort_unstable();
+                Some(match candidates {
+                    Some(candidates) => chosen.iter().map(|c| candidates[*c]).collect(),
+                    None => chosen,
+                })
+            }
+            _ => candidates,
+        }
+    }
+
+    // The distances of the samples a read visits: the candidates, if there are any
+    // (see read_candidates), otherwise all
+    fn visit_distances(
+        &self,
+        mask: &[u8],
+        query: &[u8],
+        candidates: &Option<Vec<usize>>,
+    ) -> Vec<u64> {
+        match candidates {
+            None => self.distances(mask, query),
+            Some(indices) => indices
+                .par_iter() // RAYON!
+                .with_min_len(self.min_chunk())
+                .map(|index| self.sample_distance(mask, query, &self.samples[*index]))
+                .collect(),
+        }
+    }
+
+    // Store a (new) sample, and remember when it was written
+    fn push_sample(&mut self, new_sample: &Sample) {
+        if let Some(slices) = self.bit_slices.as_mut() {
+            slices.push(&new_sample.bytes);
+        };
+        if let Some(sketches) = self.sketches.as_mut() {
+            sketches.push(simhash(&new_sample.bytes, self.width));
+        };
+        if let Some(lsh) = self.lsh.as_mut() {
+            lsh.insert(&new_sample.bytes);
+        };
+        if let Some(clusters) = self.clusters.as_mut() {
+            clusters.insert(&new_sample.bytes);
+        };
+        self.positions.insert(position_key(new_sample), self.num_samples());
+        self.samples.push(new_sample.clone());
+        self.write_stamps.push(self.write_count);
+        self.use_stamps.push(self.write_count);
+        self.write_count += 1;
+        if let Some(interval) = self.kernel.refit_interval {
+            if self.num_samples().is_multiple_of(interval.max(1)) {
+                self.fit_kernel_exponent();
+            };
+        };
+    }
+
+    /// Choose the exponent of the distance kernel (from `KERNEL_EXPONENTS`) which predicts
+    /// the memory's own samples best, i.e. with the smallest leave-one-out error (`self_score`).
+    /// The current exponent is kept unless another one is strictly better. Returns the exponent.
+    pub fn fit_kernel_exponent(&mut self) -> f64 {
+        let mut best_exponent = self.kernel.exponent;
+        let mut best_mae = self.self_score().mae;
+        for &exponent in KERNEL_EXPONENTS.iter() {
+            self.kernel.exponent = exponent;
+            let mae = self.self_score().mae;
+            if mae < best_mae {
+                best_mae = mae;
+                best_exponent = exponent;
+            };
+        }
+        self.kernel.exponent = best_exponent;
+        debug!(
+            "Kernel exponent fitted to {} (mae {}) on {} samples",
+            best_exponent,
+            best_mae,
+            self.num_samples()
+        );
+        best_exponent
+    }
+
+    /// How much the sample at `index` still counts, by its age alone:
+    /// `0.5 ^ (newer writes / recency_half_life)`, or 1.0 without a half life
+    pub fn recency_factor(&self, index: usize) -> f64 {
+        if self.weighting.recency_half_life.is_none() {
+            return 1.0;
+        };
+        // samples pushed directly into `samples` have no stamp; treat them as brand new
+        let newest = self.write_count.saturating_sub(1);
+        let stamp = self.write_stamps.get(index).cloned().unwrap_or(newest);
+        self.weighting.recency(newest.saturating_sub(stamp) as f64)
+    }
+
+    /// Forget all samples whose `recency_factor` has decayed below `min_factor`
+    /// (e.g. the random samples of the bootstrap, long after better ones were written),
+    /// returns how many. Without a `recency_half_life`, nothing is stale.
+    pub fn prune_stale(&mut self, min_factor: f64) -> usize {
+        let stale: Vec<usize> = (0..self.num_samples())
+            .filter(|index| self.recency_factor(*index) < min_factor)
+            .collect();
+        // back to front, so the indices stay valid
+        for index in stale.iter().rev() {
+            self.remove_sample(*index);
+        }
+        stale.len()
+    }
+
+    /// The weight of the sample at `index` in any read: the distance kernel's weight `kernel`,
+    /// times the recency and score factors from `self.weighting`.
+    pub fn sample_weight(&self, index: usize, kernel: f64) -> f64 {
+        if 0.0 == kernel {
+            return 0.0;
+        };
+        let score = self.samples[index].score;
+        kernel
+            * self.recency_factor(index)
+            * self.weighting.importance(score, self.min_score, self.max_score)
+    } // end sample_weight
+
     /// Calculate the weighted sum of all the samples in the memory,
-    /// where the weight of each sample is the inverse of the squared masked hamming distance to
-    /// the query, i.e. 1 / (mhd * mhd)
+    /// where the weight of each sample is the inverse of the masked hamming distance to
+    /// the query, to the power of the kernel exponent, i.e. 1 / (mhd + 1) ^ exponent
+    /// (With an LSH index, only over the samples it finds, see `use_lsh`.)
     /// **This is not a maximum function (yet).**
     pub fn masked_read(&self, mask: &[u8], query: &[u8]) -> ScoreType {
+        self.masked_read_with_confidence(mask, query).score
+    } // end maked_read
+
+    /// `masked_read`, and how far to trust it: the weighted variance of the scores it averages,
+    /// and how many samples it effectively averages (see `MaskedRead`). A mean computed from
+    /// one distant sample is no better than a guess -- better explore, then.
+    ///
+    /// ```rust
+    /// use mhd_memory::{MhdMemory, Sample};
+    ///
+    /// let mut memory = MhdMemory::new(32);
+    /// memory.write_n_random_samples(50);
+    /// let (mask, query) = ([0xFF; 4], Sample::random(32));
+    /// let read = memory.masked_read_with_confidence(&mask, &query.bytes);
+    /// assert_eq!(read.score, memory.masked_read(&mask, &query.bytes));
+    /// assert!(1.0 <= read.effective_samples && read.effective_samples <= 50.0);
+    /// assert!(0.0 < read.variance);
+    /// ```
+    pub fn masked_read_with_confidence(&self, mask: &[u8], query: &[u8]) -> MaskedRead {
         assert!(self.width <= 8 * mask.len());
         assert!(self.width <= 8 * query.len());
-        let (score_sum, weight_sum) = self
-            .samples
-            .par_iter() // RAYON!!
-            .map(|s| {
-                // use a closure here to capture query and mask
-                let dist = distance(mask, query, &s.bytes);
-                let dist_plus_1 = (dist + 1) as f64; // adding one prevents division by zero later
-                                                     // let weight = 1.0 / (dist_plus_1 * dist_plus_1);
-                let weight = 1.0 / dist_plus_1; // TODO DECIDE! Squared or not!!!
-                let floating_avg = self.avg_score() as f64;
-                let delta_score = s.score as f64 - floating_avg;
-                let weighted_delta = delta_score * weight;
-                let weighted_score = floating_avg + weighted_delta;
-                (weighted_score, weight) // return score
+        let candidates = self.read_candidates(mask, query);
+        let distances = self.visit_distances(mask, query, &candidates);
+        self.read_from_distances(&candidates, &distances)
+    }
+
+    // `masked_read_with_confidence` of the samples at `distances` (all of them, or the
+    // candidates), however those were computed (e.g. by a GPU, see `GpuSamples`)
+    pub(crate) fn read_from_distances(
+        &self,
+        candidates: &Option<Vec<usize>>,
+        distances: &[u64],
+    ) -> MaskedRead {
+        let floating_avg = self.avg_score().to_f64();
+        MaskedRead::weighted(floating_avg, distances.len(), self.min_chunk(), |visit| {
+            let index = candidates.as_ref().map_or(visit, |indices| indices[visit]);
+            let weight = self.read_weight(index, distances[visit]);
+            (self.samples[index].score.to_f64(), weight)
+        })
+    }
+
+    // The weight of sample number `index`, at distance `dist`, in masked_read
+    // (and masked_read_multi)
+    #[inline]
+    fn read_weight(&self, index: usize, dist: u64) -> f64 {
+        self.sample_weight(index, self.kernel.masked_read_weight(dist))
+    }
+
+    /// The largest number of objectives of any sample (see `Sample::objectives`)
+    pub fn num_objectives(&self) -> usize {
+        self.samples
+            .iter()
+            .map(|sample| sample.objectives().len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// `masked_read` for multi-objective samples: one prediction per objective (see
+    /// `Sample::with_objectives`), all with the weights `masked_read` uses. A sample with
+    /// fewer objectives than `num_objectives` only counts for the objectives it has.
+    pub fn masked_read_multi(&self, mask: &[u8], query: &[u8]) -> Vec<ScoreType> {
+        assert!(self.width <= 8 * mask.len());
+        assert!(self.width <= 8 * query.len());
+        let num_objectives = self.num_objectives();
+        // The average of each objective (as masked_read uses avg_score)
+        let mut totals = vec![ZERO_SCORE; num_objectives];
+        let mut counts = vec![0usize; num_objectives];
+        for sample in self.samples.iter() {
+            for (k, objective) in sample.objectives().iter().enumerate() {
+                totals[k] += *objective;
+                counts[k] += 1;
+            }
+        }
+        let averages: Vec<f64> = totals
+            .iter()
+            .zip(counts.iter())
+            .map(|(total, count)| (*total / (*count).max(1) as ScoreType).to_f64())
+            .collect();
+
+        let candidates = self.read_candidates(mask, query);
+        let distances = self.visit_distances(mask, query, &candidates);
+        let zeros = || (vec![0.0; num_objectives], vec![0.0; num_objectives]);
+        let (score_sums, weight_sums) = (0..distances.len())
+            .into_par_iter() // RAYON!!
+            .with_min_len(self.min_chunk())
+            .fold(zeros, |(mut score_sums, mut weight_sums), visit| {
+                let index = candidates.as_ref().map_or(visit, |indices| indices[visit]);
+                let weight = self.read_weight(index, distances[visit]);
+                for (k, objective) in self.samples[index].objectives().iter().enumerate() {
+                    let delta = objective.to_f64() - averages[k];
+                    score_sums[k] += averages[k] + delta * weight;
+                    weight_sums[k] += weight;
+                }
+                (score_sums, weight_sums)
             })
-            // .fold( (0.0, 0.0), |(s0, w0), (s1, w1)| (s0 + s1, w0 + w1)); <- use without rayon
-            // For this Rayon version,
-            // see https://docs.rs/rayon/1.5.1/rayon/iter/trait.ParallelIterator.html#method.reduce
-            // .cloned() // iterating over ( f64, f64 )
-            .reduce(
-                || (0.0, 0.0), // identity element
-                |a, b| (a.0 + b.0, a.1 + b.1),
-            );
-        let result = score_sum / weight_sum;
-        trace!(
-            "sum of scores = {}, sum of weights =  {}, result = {}",
-            score_sum,
-            weight_sum,
-            result
-        );
-        result as ScoreType
-    } // end maked_read
+            .reduce(zeros, |(mut score_sums, mut weight_sums), (scores, weights)| {
+                for k in 0..num_objectives {
+                    score_sums[k] += scores[k];
+                    weight_sums[k] += weights[k];
+                }
+                (score_sums, weight_sums)
+            });
+        score_sums
+            .iter()
+            .zip(weight_sums.iter())
+            .map(|(score_sum, weight_sum)| (score_sum / weight_sum) as ScoreType)
+            .collect()
+    } // end masked_read_multi
 
     // Utility DRY function, used only in read_2_scores, below
     fn calculate_priority(
-        &self,
+        max_score: ScoreType,
         hits_count: usize,
         total_hits: usize,
         score: f64,
         weight: f64,
         other_weight: f64,
+        ucb_constant: Option<f64>,
     ) -> f64 {
-        let max_score = self.max_score as f64;
+        let max_score = max_score.to_f64();
         if 0 == hits_count {
             max_score * 1024.0 // a.k.a. infinity
         } else {
@@ -216,10 +1714,13 @@ impl MhdMemory {
             // exploration -- trickier...
             let ln_total_hits = (total_hits as f64).ln();
             const UCB_METHOD: u8 = 3;  // 0 == close to UCB, 1 = not quite, 2 = weight ratios, 3 = hits
-            let exploration = match UCB_METHOD {
+            // (a UCB constant from the config means method 0)
+            let method = if ucb_constant.is_some() { 0 } else { UCB_METHOD };
+            let exploration = match method {
                 0 => {  // This is roughly the UCBT Formula...
-                    const UCB_CONSTANT: f64 = 113.13708499; // = 80 * sqrt(2) ; or 5.65685425; or 2.828427125...
-                    (ln_total_hits / hits_count as f64).sqrt() * UCB_CONSTANT
+                    // DEFAULT_UCB_CONSTANT = 80 * sqrt(2) ; or 5.65685425; or 2.828427125...
+                    let constant = ucb_constant.unwrap_or(DEFAULT_UCB_CONSTANT);
+                    (ln_total_hits / hits_count as f64).sqrt() * constant
                 },
                 1 => { // First Approximation to the UCBT
                     // return the ratio other_weight / weight ...
@@ -289,65 +1790,92 @@ impl MhdMemory {
         }
     }
 
-    fn distance_multiplier( threshold : u64, distance : u64 ) -> f64 {
-        if 0 == distance { return 1.0 };
-        // Now assume 0 < distance
-        let dist_plus_1 = (distance + 1) as f64; // prevents division by zero later
-        const DISTANCE_WEIGHT_METHOD : u8 = 2;
-        match DISTANCE_WEIGHT_METHOD {
-            0 => { // 1 over distance+1
-                1.0 / dist_plus_1
-            } // end method 0 --
-            1 => { // 1 over distance+1 squared
-                1.0 / (dist_plus_1 * dist_plus_1)
-            } // end method 0 --
-            2 => { // approximate 1 - (2 * cumulative binomial distribution)
-                if threshold <= distance { 0.0 } // too far out
-                else { // if 0 < distance < num_bits / 2
-                    let exponent = 1.0 / dist_plus_1;
-                    let base = 1.0 - (distance as f64)/(threshold as f64); // 1 -  distance /half-of-num-bits
-                    // return
-                    let result = base.powf( exponent );
-                    assert!( 0.0 <= result );
-                    assert!( result <= 1.0 );
-                    result
-                }
-            } // end method 0 --
-            _ => {
-                error!( "Unknown Method {}", DISTANCE_WEIGHT_METHOD );
-                -1.0
-            }
-        }
-    }
-
     /// This method evaluates what happens if we take the solution implied by `mask` and `query`,
     /// set the bit at `index` to be true, and to be false, and return the results as a pair of
     /// floats `(f64,f64) == ( prio_false, prio_true )`
     /// (so that `result.0` is `prio_false` and `prio.1` is `score_true`).
     pub fn read_2_priorities(&self, mask: &[u8], query: &[u8], index: usize) -> (f64, f64) {
+        self.priorities_from(&self.read_2_sums(mask, query, index))
+    }
+
+    /// The sums, and the best score, to turn into priorities: as they are, or normalized
+    /// (see `MhdMemoryConfig::normalize_scores`, `PrioritySums::normalized`)
+    pub fn scaled_sums(&self, sums: &PrioritySums) -> (PrioritySums, ScoreType) {
+        if self.config.normalize_scores {
+            let one = ScoreType::from_f64(1.0);
+            (sums.normalized(self.score_mean(), self.score_std_dev()), one)
+        } else {
+            (*sums, self.max_score)
+        }
+    }
+
+    /// The priorities of the sums, as `read_2_priorities` computes them: scaled (see
+    /// `scaled_sums`), with the exploration the config asks for
+    pub fn priorities_from(&self, sums: &PrioritySums) -> (f64, f64) {
+        let (sums, max_score) = self.scaled_sums(sums);
+        sums.priorities_with_ucb(max_score, self.config.ucb_constant)
+    }
+
+    /// The average score
+    #[inline]
+    pub fn score_mean(&self) -> f64 {
+        self.total_score.to_f64() / self.num_samples().max(1) as f64
+    }
+
+    /// The standard deviation of the scores (kept up to date as samples come and go)
+    pub fn score_std_dev(&self) -> f64 {
+        let num_samples = self.num_samples().max(1) as f64;
+        let shifted_mean = self.score_mean() - self.score_shift;
+        (self.shifted_squares / num_samples - shifted_mean * shifted_mean)
+            .max(0.0)
+            .sqrt()
+    }
+
+    /// The first step of `read_2_priorities`: the weighted sums over all samples, which
+    /// `PrioritySums::priorities` then turns into priorities. Sums of several memories
+    /// (e.g. the shards of a `ShardedMemory`) can simply be added up first.
+    pub fn read_2_sums(&self, mask: &[u8], query: &[u8], index: usize) -> PrioritySums {
         assert!(self.width <= 8 * mask.len());
         assert!(self.width <= 8 * query.len());
 
+        // With an LSH index, visit only the samples it finds (see use_lsh), otherwise all
+        let candidates = self.read_candidates(mask, query);
+        let distances = self.visit_distances(mask, query, &candidates);
+        self.sums_from_distances(mask, index, &candidates, &distances)
+    } // end read_2_sums
+
+    // `read_2_sums` of the samples at `distances` (all of them, or the candidates),
+    // however those were computed (e.g. by a GPU, see `GpuSamples`)
+    pub(crate) fn sums_from_distances(
+        &self,
+        mask: &[u8],
+        index: usize,
+        candidates: &Option<Vec<usize>>,
+        distances: &[u64],
+    ) -> PrioritySums {
         // STEP 1: Calculate (score_false, score_true, weight_false, weight_true)
 
         // let threshold = std::cmp::max( 8,std::cmp::min( 4, mask.iter().count_ones() ) );
-        let threshold = weight( mask ) / 2; // distances beyond that are meaningless
-        // assert!( 0 <= threshold ); tautological - according to compiler...
-        assert!( threshold <= self.width() as u64 / 2 );
-        let (score_false, score_true, weight_false, weight_true, hits_false, hits_true) = self
-            .samples
-            .par_iter() // RAYON!
-            .map(|s| {
-                // use a closure here to capture query and mask
-                let dist = distance(mask, query, &s.bytes);
+        let threshold = self.kernel.threshold(mask); // distances beyond that are meaningless
+        let column = self.bit_column(index); // (None without bit slices)
+        let visits = 0..distances.len();
+        let (score_false, score_true, weight_false, weight_true, hits_false, hits_true) = visits
+            .into_par_iter() // RAYON!
+            .with_min_len(self.min_chunk())
+            .map(|visit| {
+                // use a closure here to capture the distances
+                let s_index = candidates.as_ref().map_or(visit, |indices| indices[visit]);
+                let s = &self.samples[s_index];
+                let dist = distances[visit];
                 if threshold < dist {
                     (0.0f64, 0.0f64, 0.0f64, 0.0f64, 0, 0)
                 } else {
                     // if dist <= THRESHOLD
-                    let weight = Self::distance_multiplier( threshold, dist );
+                    let kernel = self.kernel.weight(threshold, dist);
+                    let weight = self.sample_weight( s_index, kernel );
                     let mut hits_on_0: usize = 0;
                     let mut hits_on_1: usize = 0;
-                    let s_at_index = s.get_bit(index);
+                    let s_at_index = self.column_or_row_bit(column, s_index, index);
                     if s_at_index {
                         if 0 == dist {
                             hits_on_1 = 1;
@@ -355,7 +1883,7 @@ impl MhdMemory {
                         // return 6tuple (score0, score1, weight0, weight1, hits0, hits1 )
                         (
                             0.0f64,
-                            weight * s.score as f64,
+                            weight * s.score.to_f64(),
                             0.0f64,
                             weight,
                             hits_on_0,
@@ -368,7 +1896,7 @@ impl MhdMemory {
                         };
                         // return 6tuple (score0, score1, weight0, weight1, hits0, hits1 )
                         (
-                            weight * s.score as f64,
+                            weight * s.score.to_f64(),
                             0.0f64,
                             weight,
                             0.0f64,
@@ -401,35 +1929,73 @@ impl MhdMemory {
             );
         // RAYON VERSION 2 - Won't work without the trait `Sum<(f64, f64, f64, f64, usize, usize)>`
         // .sum();
-
-        // STEP 2: Convert (score_false, score_true, weight_false, weight_true) into 2 scores
-        // and return those scores
-        let total_hits = hits_false + hits_true;
-        let result = (
-            self.calculate_priority(
-                hits_false,
-                total_hits,
-                score_false,
-                weight_false,
-                weight_true,
-            ),
-            self.calculate_priority(hits_true, total_hits, score_true, weight_true, weight_false),
-        );
-        trace!(
-            "MHD MEM: hits = ({},{}), scores = ({}, {}), weights =  ({}, {}), result = ({},{})",
-            hits_false,
-            hits_true,
+        PrioritySums {
             score_false,
             score_true,
             weight_false,
             weight_true,
-            result.0,
-            result.1,
-        );
+            hits_false,
+            hits_true,
+        }
+    } // end sums_from_distances
+
+    /// `read_2_priorities` for several open decisions `indices` at once, one pair per index:
+    /// the memory is scanned (and the distances computed) only once, instead of once per index.
+    ///
+    /// ```rust
+    /// use mhd_memory::MhdMemory;
+    ///
+    /// let mut memory = MhdMemory::new(32);
+    /// memory.write_n_random_samples(100);
+    /// let (mask, query) = ([0xFF, 0x00, 0x00, 0x00], [0x5A; 4]);
+    /// let priorities = memory.read_priorities(&mask, &query, &[8, 9, 20]);
+    /// assert_eq!(priorities.len(), 3);
+    /// assert_eq!(priorities[2], memory.read_2_priorities(&mask, &query, 20));
+    /// ```
+    pub fn read_priorities(&self, mask: &[u8], query: &[u8], indices: &[usize]) -> Vec<(f64, f64)> {
+        self.read_sums(mask, query, indices)
+            .iter()
+            .map(|sums| self.priorities_from(sums))
+            .collect()
+    }
 
-        // Return...
-        result
-    } // end maked_read
+    /// `read_2_sums` for several indices at once, see `read_priorities`
+    pub fn read_sums(&self, mask: &[u8], query: &[u8], indices: &[usize]) -> Vec<PrioritySums> {
+        assert!(self.width <= 8 * mask.len());
+        assert!(self.width <= 8 * query.len());
+        let threshold = self.kernel.threshold(mask); // as in read_2_sums
+        let candidates = self.read_candidates(mask, query);
+        let distances = self.visit_distances(mask, query, &candidates);
+        let columns: Vec<Option<&[u64]>> = indices.iter().map(|i| self.bit_column(*i)).collect();
+        let no_sums = || vec![PrioritySums::default(); indices.len()];
+        (0..distances.len())
+            .into_par_iter() // RAYON!
+            .with_min_len(self.min_chunk())
+            .fold(no_sums, |mut sums, visit| {
+                let dist = distances[visit];
+                if dist <= threshold {
+                    let s_index = candidates.as_ref().map_or(visit, |indices| indices[visit]);
+                    let s = &self.samples[s_index];
+                    let weight = self.sample_weight(s_index, self.kernel.weight(threshold, dist));
+                    let score = weight * s.score.to_f64();
+                    let hit = (0 == dist) as usize;
+                    let bits = indices.iter().zip(columns.iter());
+                    for ((index, column), sum) in bits.zip(sums.iter_mut()) {
+                        if self.column_or_row_bit(*column, s_index, *index) {
+                            sum.score_true += score;
+                            sum.weight_true += weight;
+                            sum.hits_true += hit;
+                        } else {
+                            sum.score_false += score;
+                            sum.weight_false += weight;
+                            sum.hits_false += hit;
+                        };
+                    }
+                };
+                sums
+            })
+            .reduce(no_sums, |a, b| a.into_iter().zip(b).map(|(a, b)| a + b).collect())
+    }
 
     #[inline]
     pub fn read_and_decide(
@@ -439,8 +2005,23 @@ impl MhdMemory {
         index: usize,
         full_monte: bool,
     ) -> bool {
-        let priorities = self.read_2_priorities(mask, query, index);
+        self.read_and_decide_with(&UcbPolicy { full_monte }, mask, query, index)
+    }
+
+    /// Read the memory for decision `index` (see `read_2_sums`), and let `policy` decide
+    pub fn read_and_decide_with(
+        &self,
+        policy: &dyn DecisionPolicy,
+        mask: &[u8],
+        query: &[u8],
+        index: usize,
+    ) -> bool {
+        policy.decide(self, &self.read_2_sums(mask, query, index))
+    }
 
+    /// Decide for true or false, given `( prio_false, prio_true )` (see `read_2_priorities`):
+    /// with `full_monte`, at random in proportion to the priorities, otherwise for the higher.
+    pub fn decide(priorities: (f64, f64), full_monte: bool) -> bool {
         // Are probablistic decisions too flaky?
         assert!(0.0 <= priorities.0);
         assert!(0.0 <= priorities.1);
@@ -464,9 +2045,289 @@ impl MhdMemory {
         } // end if NOT full_monte
     }
 
+    /// A mask with exactly the first `width` bits set, i.e. with every decision "made"
+    /// (numbered as the solutions number them, see `util::put_bit`).
+    fn full_mask(&self) -> SampleBytes {
+        let mut mask = SampleBytes::from_elem(0x0, self.width.div_ceil(8));
+        for position in 0..self.width {
+            put_bit(&mut mask, position, true);
+        }
+        mask
+    }
+
+    /// Predict the score of a complete assignment `bits` (all `width` bits count, no mask),
+    /// e.g. to screen candidate solutions produced by other heuristics.
+    /// Returns the kernel-weighted estimate (same kernel and `weighting` as `read_2_priorities`),
+    /// and an uncertainty: the standard error of that estimate, where the memory's overall
+    /// variance serves as a prior (worth one sample), so few neighbours mean high uncertainty.
+    /// A sample stored with exactly these bits is returned with uncertainty 0.0,
+    /// and if no sample is close enough (or the memory is empty), the uncertainty is infinite.
+    ///
+    /// ```rust
+    /// use mhd_memory::{MhdMemory, Sample};
+    /// let mut memory = MhdMemory::new(64);
+    /// memory.write_n_random_samples(32);
+    ///
+    /// let known = memory.samples[7].clone();
+    /// assert_eq!(memory.predict_score(&known.bytes), (known.score, 0.0));
+    ///
+    /// let (score, uncertainty) = memory.predict_score(&Sample::random(64).bytes);
+    /// assert!(memory.min_score <= score && score <= memory.max_score);
+    /// assert!(0.0 < uncertainty);
+    /// ```
+    pub fn predict_score(&self, bits: &[u8]) -> (ScoreType, f64) {
+        self.predict_score_without(bits, None)
+    }
+
+    // predict_score, but ignoring the sample with index `excluded` (if any) -- see self_score
+    fn predict_score_without(&self, bits: &[u8], excluded: Option<usize>) -> (ScoreType, f64) {
+        assert!(self.width <= 8 * bits.len());
+        if self.is_empty() {
+            return (ZERO_SCORE, f64::INFINITY);
+        };
+        let mask = self.full_mask();
+        if let Some((_, hit)) = self
+            .samples
+            .par_iter() // RAYON!
+            .with_min_len(self.min_chunk())
+            .enumerate()
+            .filter(|(index, _)| Some(*index) != excluded)
+            .find_any(|(_, s)| !s.is_partial() && 0 == distance(&mask, bits, &s.bytes))
+        {
+            return (hit.score, 0.0);
+        };
+        self.kernel_estimate(&mask, bits, excluded)
+    } // end predict_score_without
+
+    /// Like `predict_score`, but for a partial assignment: only the bits set in `mask` count.
+    /// So this predicts the score of a typical completion of `query`, e.g. of a partial
+    /// solution, with the same uncertainty measure as `predict_score`.
+    /// (Even a sample matching `query` exactly under the mask is just one completion,
+    /// so there is no "exact hit" with uncertainty 0.0 here, unless the mask is full.)
+    ///
+    /// ```rust
+    /// use mhd_memory::{MhdMemory, Sample, ZERO_SCORE};
+    /// let mut memory = MhdMemory::new(64);
+    /// memory.write_n_random_samples(32);
+    ///
+    /// // With nothing decided, we can only predict a typical sample
+    /// let nothing = Sample::new(64, ZERO_SCORE);
+    /// let (score, uncertainty) = memory.predict_masked_score(&nothing.bytes, &nothing.bytes);
+    /// assert!(memory.min_score <= score && score <= memory.max_score);
+    /// assert!(0.0 < uncertainty && uncertainty.is_finite());
+    /// ```
+    pub fn predict_masked_score(&self, mask: &[u8], query: &[u8]) -> (ScoreType, f64) {
+        assert!(self.width <= 8 * mask.len());
+        assert!(self.width <= 8 * query.len());
+        if self.is_empty() {
+            return (ZERO_SCORE, f64::INFINITY);
+        };
+        self.kernel_estimate(mask, query, None)
+    } // end predict_masked_score
+
+    // The kernel-weighted estimate and its uncertainty, for predict_score & predict_masked_score
+    fn kernel_estimate(&self, mask: &[u8], query: &[u8], excluded: Option<usize>) -> (ScoreType, f64) {
+        debug_assert!(!self.is_empty());
+        let threshold = self.kernel.threshold(mask); // as in read_2_priorities
+        let (score_sum, square_sum, weight_sum, square_weight_sum) = self
+            .samples
+            .par_iter() // RAYON!
+            .with_min_len(self.min_chunk())
+            .enumerate()
+            .filter(|(index, _)| Some(*index) != excluded)
+            .map(|(index, s)| {
+                let dist = self.sample_distance(mask, query, s);
+                let kernel = self.kernel.weight(threshold, dist);
+                let weight = self.sample_weight(index, kernel);
+                let score = s.score.to_f64();
+                (weight * score, weight * score * score, weight, weight * weight)
+            })
+            .reduce(
+                || (0.0, 0.0, 0.0, 0.0), // identity element
+                |a, b| (a.0 + b.0, a.1 + b.1, a.2 + b.2, a.3 + b.3),
+            );
+        if 0.0 == weight_sum {
+            return (self.avg_score(), f64::INFINITY);
+        };
+
+        let estimate = score_sum / weight_sum;
+        let local_variance = (square_sum / weight_sum - estimate * estimate).max(0.0);
+        // the memory's overall variance, as prior
+        let avg = self.avg_score().to_f64();
+        let global_variance = self
+            .samples
+            .par_iter() // RAYON!
+            .with_min_len(self.min_chunk())
+            .map(|s| (s.score.to_f64() - avg) * (s.score.to_f64() - avg))
+            .sum::<f64>()
+            / self.num_samples() as f64;
+        let variance = (weight_sum * local_variance + global_variance) / (weight_sum + 1.0);
+        let effective_samples = weight_sum * weight_sum / square_weight_sum;
+        let uncertainty = (variance / effective_samples).sqrt();
+        trace!(
+            "kernel_estimate: estimate {}, weights {}, effective samples {}, uncertainty {}",
+            estimate,
+            weight_sum,
+            effective_samples,
+            uncertainty
+        );
+        (estimate.round() as ScoreType, uncertainty)
+    } // end kernel_estimate
+
+    /// Leave-one-out self evaluation: predict the score of every stored sample from all
+    /// the *other* samples (with `predict_score`), and compare with its real score.
+    /// If the memory's predictions are no better than just guessing the average score
+    /// (see `SelfScore::baseline_mae`), it has not learned anything (yet), and should not be
+    /// trusted to guide branching.
+    ///
+    /// ```rust
+    /// use mhd_memory::MhdMemory;
+    /// let mut memory = MhdMemory::new(32);
+    /// memory.write_n_random_samples(50);
+    /// let report = memory.self_score();
+    /// assert_eq!(report.num_samples, 50);
+    /// assert!(0.0 < report.mae);
+    /// assert!(-1.0 <= report.correlation && report.correlation <= 1.0);
+    /// ```
+    pub fn self_score(&self) -> SelfScore {
+        let predictions: Vec<(f64, f64)> = (0..self.num_samples())
+            .into_par_iter() // RAYON!
+            .map(|index| {
+                let sample = &self.samples[index];
+                let (predicted, _) = self.predict_score_without(&sample.bytes, Some(index));
+                (sample.score.to_f64(), predicted.to_f64())
+            })
+            .collect();
+        SelfScore::from_predictions(&predictions)
+    } // end self_score
+
+    /// The (up to) `k` samples with the best scores, best first, see `top_k_by_score`
+    #[inline]
+    pub fn top_samples(&self, k: usize) -> Vec<&Sample> {
+        self.top_k_by_score(k)
+    } // end top_samples
+
+    /// The (up to) `k` samples with the best scores, best first (samples with the same score
+    /// in the order they were written), e.g. to seed crossover or a local search.
+    /// Only the best `k` are sorted, not all samples.
+    ///
+    /// ```rust
+    /// use mhd_memory::{MhdMemory, Sample};
+    ///
+    /// let mut memory = MhdMemory::new(32);
+    /// memory.write_n_random_samples(100);
+    /// let top = memory.top_k_by_score(5);
+    /// assert_eq!(top.len(), 5);
+    /// assert_eq!(top[0].score, memory.max_score);
+    /// assert!(top.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    /// ```
+    pub fn top_k_by_score(&self, k: usize) -> Vec<&Sample> {
+        let better = |a: &(usize, &Sample), b: &(usize, &Sample)| {
+            b.1.score.cmp_scores(&a.1.score).then(a.0.cmp(&b.0))
+        };
+        let mut result: Vec<(usize, &Sample)> = self.samples.iter().enumerate().collect();
+        if 0 == k {
+            return Vec::new();
+        };
+        if k < result.len() {
+            result.select_nth_unstable_by(k - 1, better);
+            result.truncate(k);
+        };
+        result.sort_by(better);
+        result.into_iter().map(|(_, sample)| sample).collect()
+    } // end top_k_by_score
+
+    /// The samples with scores from `low` to `high` (both included), in the order they were
+    /// written
+    ///
+    /// ```rust
+    /// use mhd_memory::{MhdMemory, Sample, ScoreType};
+    ///
+    /// let mut memory = MhdMemory::new(8);
+    /// for score in 0..10 {
+    ///     let mut sample = Sample::new(8, score as ScoreType);
+    ///     sample.bytes[0] = score as u8; // (all different)
+    ///     memory.write_sample(&sample);
+    /// }
+    /// let (three, five) = (3 as ScoreType, 5 as ScoreType);
+    /// assert_eq!(memory.samples_in_score_range(three, five).len(), 3);
+    /// assert!(memory.samples_in_score_range(five, three).is_empty());
+    /// ```
+    pub fn samples_in_score_range(&self, low: ScoreType, high: ScoreType) -> Vec<&Sample> {
+        self.samples
+            .iter()
+            .filter(|sample| low <= sample.score && sample.score <= high)
+            .collect()
+    } // end samples_in_score_range
+
+    /// A sample drawn at random, with a probability in proportion to its score over the
+    /// lowest score (so the worst sample is never drawn, unless all score the same: then
+    /// every sample is equally likely), e.g. to pick parents for crossover.
+    /// Panics if the memory is empty.
+    ///
+    /// ```rust
+    /// use mhd_memory::{MhdMemory, Sample, ScoreType};
+    ///
+    /// let mut memory = MhdMemory::new(8);
+    /// memory.write_sample(&Sample::new(8, 10 as ScoreType));
+    /// memory.write_sample(&Sample::new_ones(8, 20 as ScoreType));
+    /// let mut rng = rand::thread_rng();
+    /// assert_eq!(memory.sample_weighted(&mut rng).score, 20 as ScoreType);
+    /// ```
+    pub fn sample_weighted<R: Rng + ?Sized>(&self, rng: &mut R) -> &Sample {
+        assert!(!self.is_empty(), "can't draw from an empty memory");
+        let excess = |sample: &Sample| (sample.score - self.min_score).to_f64();
+        let total: f64 = self.samples.iter().map(excess).sum();
+        if total <= 0.0 || !total.is_finite() {
+            return &self.samples[rng.gen_range(0..self.num_samples())];
+        };
+        let mut remaining = rng.gen_range(0.0..total);
+        for sample in self.samples.iter() {
+            remaining -= excess(sample);
+            if remaining < 0.0 {
+                return sample;
+            };
+        }
+        // (only by rounding errors)
+        self.samples
+            .iter()
+            .rev()
+            .find(|sample| 0.0 < excess(sample))
+            .unwrap_or(&self.samples[0])
+    } // end sample_weighted
+
+    /// How much does each bit matter? For every bit (as in `util::get_bit`), the average
+    /// score of the samples with that bit set, minus the average score of those without it
+    /// (zero, if either is missing). Positive means good samples tend to have that bit set.
+    pub fn bit_importance(&self) -> Vec<f64> {
+        (0..self.width)
+            .into_par_iter() // RAYON!
+            .map(|bit| {
+                let (mut sum_set, mut num_set) = (0.0, 0usize);
+                let (mut sum_clear, mut num_clear) = (0.0, 0usize);
+                for sample in self.samples.iter() {
+                    if get_bit(&sample.bytes, bit) {
+                        sum_set += sample.score.to_f64();
+                        num_set += 1;
+                    } else {
+                        sum_clear += sample.score.to_f64();
+                        num_clear += 1;
+                    };
+                }
+                if 0 == num_set || 0 == num_clear {
+                    0.0
+                } else {
+                    sum_set / num_set as f64 - sum_clear / num_clear as f64
+                }
+            })
+            .collect()
+    } // end bit_importance
+
     #[inline]
     pub fn write_random_sample(&mut self) {
-        self.write_sample(&Sample::random(self.width));
+        let mut sample = Sample::new(self.width, ZERO_SCORE);
+        self.with_rng(|rng| sample.randomize_with(rng));
+        self.write_sample(&sample);
     } // end write_sample
 
     #[inline]
@@ -477,6 +2338,40 @@ impl MhdMemory {
     }
 } // more coming up below
 
+impl<'a> IntoIterator for &'a MhdMemory {
+    type Item = &'a Sample;
+    type IntoIter = std::slice::Iter<'a, Sample>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A memory as wide as the first sample, with all the samples written one by one
+/// (so duplicates are skipped); an empty iterator gives an empty memory of width 0
+///
+/// ```rust
+/// use mhd_memory::{MhdMemory, Sample, ScoreType};
+///
+/// let samples = vec![Sample::new(16, 1 as ScoreType), Sample::new_ones(16, 2 as ScoreType)];
+/// let memory: MhdMemory = samples.into_iter().collect();
+/// assert_eq!(memory.width(), 16);
+/// assert_eq!(memory.iter().map(|sample| sample.score).sum::<ScoreType>(), 3 as ScoreType);
+/// ```
+impl std::iter::FromIterator<Sample> for MhdMemory {
+    fn from_iter<I: IntoIterator<Item = Sample>>(samples: I) -> Self {
+        let mut samples = samples.into_iter().peekable();
+        let mut memory = match samples.peek() {
+            Some(first) => MhdMemory::new(first.size()),
+            None => MhdMemory::default(),
+        };
+        for sample in samples {
+            memory.write_sample(&sample);
+        }
+        memory
+    }
+}
+
 ///////////////////////// TESTS TESTS TESTS TESTS TESTS TESTS /////////////////////////
 
 #[cfg(test)]
@@ -690,4 +2585,1128 @@ mod tests {
     fn test_read_for_decision_not_monte() {
         test_read_for_decision(false);
     }
+
+    #[test]
+    fn test_self_score_summary() {
+        let perfect = SelfScore::from_predictions(&[(1.0, 1.0), (2.0, 2.0), (3.0, 3.0)]);
+        assert_eq!(perfect.num_samples, 3);
+        assert_eq!(perfect.mae, 0.0);
+        assert!((perfect.correlation - 1.0).abs() < 1e-9);
+        assert!(perfect.has_learned());
+
+        let constant = SelfScore::from_predictions(&[(1.0, 2.0), (3.0, 2.0)]);
+        assert_eq!(constant.mae, 1.0);
+        assert_eq!(constant.baseline_mae, 1.0);
+        assert_eq!(constant.correlation, 0.0);
+        assert!(!constant.has_learned());
+
+        assert_eq!(SelfScore::from_predictions(&[]), SelfScore::default());
+    }
+
+    #[test]
+    fn test_self_score_learns_structure() {
+        // Score = number of ones in the first byte: a memory should be able to learn that
+        const NUM_BITS: usize = 16;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        for _ in 0..200 {
+            let mut sample = Sample::random(NUM_BITS);
+            sample.score = (sample.bytes[0].count_ones() * 100) as ScoreType;
+            memory.write_sample(&sample);
+        }
+        let report = memory.self_score();
+        assert_eq!(report.num_samples, memory.num_samples());
+        assert!(0.0 < report.correlation);
+        assert!(report.has_learned());
+    }
+
+    #[test]
+    fn test_predict_score() {
+        const NUM_BITS: usize = 20; // not a multiple of 8, on purpose
+        let mut memory = MhdMemory::new(NUM_BITS);
+        let query = Sample::random(NUM_BITS);
+        assert_eq!(memory.predict_score(&query.bytes), (ZERO_SCORE, f64::INFINITY));
+
+        memory.write_n_random_samples(64);
+        for row in 0..8 {
+            let sample = memory.samples[row].clone();
+            assert_eq!(memory.predict_score(&sample.bytes), (sample.score, 0.0));
+        }
+
+        let (score, uncertainty) = memory.predict_score(&query.bytes);
+        if memory.search(&query).is_none() {
+            assert!(memory.min_score <= score);
+            assert!(score <= memory.max_score);
+            assert!(0.0 < uncertainty);
+        };
+
+        // A lone neighbour at distance one says more than nothing, but not much
+        let mut lonely = MhdMemory::new(NUM_BITS);
+        let mut sample = Sample::new(NUM_BITS, 100 as ScoreType);
+        lonely.write_sample(&sample);
+        sample.set_bit(0, true);
+        let (score, uncertainty) = lonely.predict_score(&sample.bytes);
+        assert_eq!(score, 100 as ScoreType);
+        assert!(uncertainty.is_finite());
+        sample.set_bit(0, false);
+        for index in 0..NUM_BITS {
+            sample.set_bit(index, true); // now the opposite of the only sample
+        }
+        assert_eq!(
+            lonely.predict_score(&sample.bytes),
+            (100 as ScoreType, f64::INFINITY)
+        );
+    }
+
+    #[test]
+    fn test_sample_weighting() {
+        const NUM_BITS: usize = 16;
+        let query = Sample::new(NUM_BITS, ZERO_SCORE);
+        let mut memory = MhdMemory::new(NUM_BITS);
+
+        // A mediocre old sample at distance 1...
+        let mut old = Sample::new(NUM_BITS, 10 as ScoreType);
+        old.set_bit(0, true);
+        memory.write_sample(&old);
+        // ... many far away samples (too far to count) ...
+        for index in 0..NUM_BITS {
+            let mut far = Sample::new_ones(NUM_BITS, 500 as ScoreType);
+            far.set_bit(index, false);
+            memory.write_sample(&far);
+        }
+        // ... and an excellent new sample at distance 2
+        let mut new = Sample::new(NUM_BITS, 1000 as ScoreType);
+        new.set_bit(1, true);
+        new.set_bit(2, true);
+        memory.write_sample(&new);
+
+        // By default, only the kernel counts
+        assert_eq!(memory.weighting, SampleWeighting::default());
+        for index in 0..memory.num_samples() {
+            assert_eq!(memory.sample_weight(index, 0.5), 0.5);
+        }
+        let (kernel_only, _) = memory.predict_score(&query.bytes);
+
+        memory.weighting.recency_half_life = Some(4.0);
+        assert_eq!(memory.sample_weight(0, 1.0), 0.5f64.powf(17.0 / 4.0));
+        assert_eq!(memory.sample_weight(17, 1.0), 1.0);
+        let (recency, _) = memory.predict_score(&query.bytes);
+        assert!(kernel_only < recency);
+
+        memory.weighting = SampleWeighting {
+            recency_half_life: None,
+            score_importance: 2.0,
+        };
+        assert_eq!(memory.sample_weight(0, 1.0), 1.0);
+        assert_eq!(memory.sample_weight(17, 1.0), 4.0);
+        let (importance, _) = memory.predict_score(&query.bytes);
+        assert!(kernel_only < importance);
+
+        memory.weighting.recency_half_life = Some(4.0);
+        let (both, _) = memory.predict_score(&query.bytes);
+        assert!(recency < both);
+        assert!(importance < both);
+
+        // Samples 0 to 8 are older than two half lives (and the old one is gone)
+        memory.weighting.recency_half_life = Some(4.0);
+        assert_eq!(memory.recency_factor(9), 0.25);
+        assert!(memory.recency_factor(10) > 0.25);
+        assert_eq!(memory.prune_stale(0.25), 9);
+        assert_eq!(memory.num_samples(), 9);
+        assert!(memory
+            .samples
+            .iter()
+            .all(|sample| 10 as ScoreType != sample.score));
+        assert_eq!(memory.recency_factor(8), 1.0);
+        assert_eq!(memory.prune_stale(0.25), 0);
+        memory.weighting.recency_half_life = None;
+        assert_eq!(memory.prune_stale(0.99), 0);
+
+        // The weighting survives clearing the memory
+        memory.weighting.recency_half_life = Some(4.0);
+        memory.clear();
+        assert_eq!(memory.weighting.recency_half_life, Some(4.0));
+    }
+
+    #[test]
+    fn test_kernel_shape_and_threshold() {
+        const NUM_BITS: usize = 16;
+        let full_mask = vec![0xFF; 2];
+        let mut kernel = DistanceKernel::default();
+        assert_eq!(kernel.shape, KernelShape::Binomial);
+        assert_eq!(kernel.threshold(&full_mask), 8);
+        assert_eq!(kernel.weight(8, 0), 1.0);
+        assert_eq!(kernel.weight(8, 4), 0.5f64.powf(1.0 / 5.0));
+        assert_eq!(kernel.weight(8, 8), 0.0);
+        kernel.shape = KernelShape::Power;
+        assert_eq!(kernel.weight(8, 4), 0.2);
+        assert_eq!(kernel.weight(8, 8), 1.0 / 9.0);
+        assert_eq!(kernel.weight(8, 9), 0.0);
+        kernel.threshold = Some(3);
+        assert_eq!(kernel.threshold(&full_mask), 3);
+
+        // With threshold zero, only the exact hits count
+        let mut memory = MhdMemory::new(NUM_BITS);
+        let hit = Sample::new(NUM_BITS, 100 as ScoreType);
+        let mut near = Sample::new(NUM_BITS, 900 as ScoreType);
+        near.set_bit(0, true);
+        memory.write_sample(&hit);
+        memory.write_sample(&near);
+        let sums = memory.read_2_sums(&full_mask, &hit.bytes, 0);
+        assert_eq!((sums.weight_false, sums.weight_true), (1.0, 0.875f64.sqrt()));
+        memory.kernel.threshold = Some(0);
+        let sums = memory.read_2_sums(&full_mask, &hit.bytes, 0);
+        assert_eq!((sums.weight_false, sums.weight_true), (1.0, 0.0));
+        memory.kernel.threshold = Some(1);
+        let sums = memory.read_2_sums(&full_mask, &hit.bytes, 0);
+        assert_eq!(sums.weight_true, 0.0); // the binomial kernel is zero at the threshold
+        memory.kernel.shape = KernelShape::Power;
+        let sums = memory.read_2_sums(&full_mask, &hit.bytes, 0);
+        assert_eq!((sums.weight_false, sums.weight_true), (1.0, 0.5));
+    }
+
+    #[test]
+    fn test_fit_kernel_exponent() {
+        const NUM_BITS: usize = 16;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        assert_eq!(memory.kernel, DistanceKernel::default());
+        for _ in 0..64 {
+            let mut sample = Sample::random(NUM_BITS);
+            sample.score = (sample.bytes[0].count_ones() * 100) as ScoreType;
+            memory.write_sample(&sample);
+        }
+        let default_mae = memory.self_score().mae;
+        let exponent = memory.fit_kernel_exponent();
+        assert_eq!(exponent, memory.kernel.exponent);
+        assert!(KERNEL_EXPONENTS.contains(&exponent));
+        assert!(memory.self_score().mae <= default_mae);
+
+        // The exponent changes the reads
+        let query = Sample::random(NUM_BITS);
+        let mask = vec![0xFF; query.bytes.len()];
+        memory.kernel.exponent = 0.25;
+        let flat = memory.masked_read(&mask, &query.bytes);
+        memory.kernel.exponent = 16.0;
+        let sharp = memory.masked_read(&mask, &query.bytes);
+        assert_ne!(flat, sharp);
+
+        // Refitting every few samples, and the kernel survives clearing the memory
+        memory.kernel.refit_interval = Some(8);
+        memory.clear();
+        assert_eq!(memory.kernel.exponent, 16.0);
+        for _ in 0..16 {
+            // (not write_n_random_samples: 16 random bits repeat too often, with other scores)
+            let mut sample = Sample::random(NUM_BITS);
+            sample.score = (sample.bytes[0].count_ones() * 100) as ScoreType;
+            memory.write_sample(&sample);
+        }
+        assert_eq!(memory.kernel.refit_interval, Some(8));
+    }
+
+    #[test]
+    fn test_bit_sliced_reads() {
+        const NUM_BITS: usize = 44;
+        let mut rows = MhdMemory::new(NUM_BITS);
+        rows.write_n_random_samples(100);
+        let mut columns = rows.clone();
+        columns.use_bit_slices(true);
+        assert!(columns.uses_bit_slices() && !rows.uses_bit_slices());
+        // Samples written later are sliced, too
+        for _ in 0..100 {
+            let sample = Sample::random(NUM_BITS);
+            rows.write_sample(&sample);
+            columns.write_sample(&sample);
+        }
+
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-9 * a.abs().max(1.0);
+        for index in 0..NUM_BITS {
+            let mask = Sample::random(NUM_BITS).bytes;
+            let query = Sample::random(NUM_BITS).bytes;
+            let by_row = rows.read_2_priorities(&mask, &query, index);
+            let by_column = columns.read_2_priorities(&mask, &query, index);
+            assert!(close(by_row.0, by_column.0) && close(by_row.1, by_column.1));
+        }
+        // Several decisions at once, each bit from its column
+        let mask = Sample::random(NUM_BITS).bytes;
+        let query = Sample::random(NUM_BITS).bytes;
+        let indices: Vec<usize> = (0..NUM_BITS).collect();
+        let by_row = rows.read_priorities(&mask, &query, &indices);
+        let by_column = columns.read_priorities(&mask, &query, &indices);
+        for (row, column) in by_row.iter().zip(by_column.iter()) {
+            assert!(close(row.0, column.0) && close(row.1, column.1));
+        }
+        assert!(rows.bit_column(0).is_none());
+        assert_eq!(columns.bit_column(0).unwrap().len(), 200_usize.div_ceil(64));
+
+        columns.clear();
+        assert!(columns.uses_bit_slices());
+        columns.use_bit_slices(false);
+        assert!(!columns.uses_bit_slices());
+    }
+
+    #[test]
+    fn test_within() {
+        const NUM_BITS: usize = 30;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_n_random_samples(200);
+        let mut sliced = memory.clone();
+        sliced.use_bit_slices(true);
+        let mask = Sample::random(NUM_BITS).bytes;
+        let query = Sample::random(NUM_BITS).bytes;
+        for radius in 0..=weight(&mask) {
+            let expected: Vec<&Sample> = memory
+                .samples
+                .iter()
+                .filter(|s| distance(&mask, &query, &s.bytes) <= radius)
+                .collect();
+            assert_eq!(memory.within(&mask, &query, radius).collect::<Vec<_>>(), expected);
+            assert_eq!(sliced.within(&mask, &query, radius).collect::<Vec<_>>(), expected);
+        }
+        // Every sample is within its own radius zero
+        let sample = memory.samples[17].clone();
+        assert!(memory.within(&mask, &sample.bytes, 0).any(|s| *s == sample));
+    }
+
+    #[test]
+    fn test_sketch_filter() {
+        const NUM_BITS: usize = 200;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_n_random_samples(100);
+        let mut sketched = memory.clone();
+        sketched.use_sketches(true);
+        assert!(sketched.uses_sketches() && !memory.uses_sketches());
+        // Samples written later are sketched, too; duplicates are still found
+        let copy = memory.samples[42].clone();
+        assert!(!sketched.write_sample(&copy));
+        for _ in 0..100 {
+            let sample = Sample::random(NUM_BITS);
+            assert!(memory.write_sample(&sample));
+            assert!(sketched.write_sample(&sample));
+        }
+        assert_eq!(sketched.num_samples(), memory.num_samples());
+        assert!(sketched.search(&copy).is_some());
+
+        // Near neighbours of a stored sample: the sketches may miss some, but (almost) never
+        let mask = vec![0xFF; copy.size_in_bytes()];
+        let mut query = copy.clone();
+        query.bytes[0] ^= 0x0F; // 4 bits away from copy
+        for radius in [4, 10, 60].iter() {
+            let exact: Vec<&Sample> = memory.within(&mask, &query.bytes, *radius).collect();
+            let approximate: Vec<&Sample> = sketched.within(&mask, &query.bytes, *radius).collect();
+            assert!(approximate.iter().all(|s| exact.contains(s)));
+            assert!(approximate.contains(&&copy));
+        }
+        // Far away: random samples are about 100 bits apart
+        assert_eq!(sketched.within(&mask, &query.bytes, 10).count(), 1);
+
+        // Sketches cost 8 bytes per sample (at least)
+        let with_sketches = sketched.memory_bytes();
+        sketched.use_sketches(false);
+        assert!(sketched.memory_bytes() + 8 * sketched.num_samples() <= with_sketches);
+        sketched.use_sketches(true);
+
+        sketched.clear();
+        assert!(sketched.uses_sketches());
+        sketched.use_sketches(false);
+        assert!(!sketched.uses_sketches());
+    }
+
+    #[test]
+    fn test_write_samples() {
+        let mut memory = MhdMemory::new(64);
+        let samples: Vec<Sample> = (0..10).map(|_| Sample::random(64)).collect();
+        assert_eq!(memory.write_samples(&samples), 10);
+        // Nothing new the second time
+        assert_eq!(memory.write_samples(&samples[..5]), 0);
+        assert_eq!(memory.num_samples(), 10);
+    }
+
+    #[test]
+    fn test_iter_by_distance() {
+        const NUM_BITS: usize = 32;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        assert_eq!(memory.iter_by_distance(&[0xFF; 4], &[0; 4]).count(), 0);
+        memory.write_n_random_samples(200);
+        let query = Sample::random(NUM_BITS);
+        let mask = [0xFF, 0xFF, 0x0F, 0x00];
+        let ordered: Vec<(u64, &Sample)> = memory.iter_by_distance(&mask, &query.bytes).collect();
+        assert_eq!(ordered.len(), memory.num_samples());
+        assert!(ordered.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        for (dist, sample) in ordered.iter() {
+            assert_eq!(*dist, distance(&mask, &query.bytes, &sample.bytes));
+        }
+        // Nothing is closer than the closest
+        let (closest, _) = ordered[0];
+        let at_closest = ordered.iter().filter(|(dist, _)| *dist == closest).count();
+        assert_eq!(memory.within(&mask, &query.bytes, closest).count(), at_closest);
+
+        // Ties are in the order of writing
+        let mut ties = MhdMemory::new(NUM_BITS);
+        ties.write_n_random_samples(20);
+        let scores: Vec<ScoreType> = ties
+            .iter_by_distance(&[0x00; 4], &query.bytes)
+            .map(|(_, sample)| sample.score)
+            .collect();
+        let written: Vec<ScoreType> = ties.samples.iter().map(|sample| sample.score).collect();
+        assert_eq!(scores, written);
+    }
+
+    #[test]
+    fn test_duplicate_positions() {
+        let mut memory = MhdMemory::new(64);
+        let samples: Vec<Sample> = (0..100).map(|_| Sample::random(64)).collect();
+        memory.write_samples(&samples);
+        assert_eq!(memory.search_index(&samples[42]), Some(42));
+        assert_eq!(memory.search_index(&Sample::new(64, ZERO_SCORE)), None);
+
+        // Removing a sample moves the later ones up
+        memory.remove_sample(10);
+        assert_eq!(memory.search_index(&samples[10]), None);
+        assert_eq!(memory.search_index(&samples[42]), Some(41));
+        assert!(!memory.write_sample(&samples[99]));
+        assert!(memory.write_sample(&samples[10]));
+        assert_eq!(memory.search_index(&samples[10]), Some(99));
+
+        // Samples pushed directly are still found (by scanning)
+        let direct = Sample::random(64);
+        memory.samples.push(direct.clone());
+        assert!(memory.search(&direct).is_some());
+        assert_eq!(memory.search_index(&samples[42]), Some(41));
+        memory.clear();
+        assert!(memory.search(&direct).is_none());
+    }
+
+    #[test]
+    fn test_write_masked_sample() {
+        const NUM_BITS: usize = 32;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_n_random_samples(20);
+        let complete = Sample::random(NUM_BITS);
+        let known = [0xFF, 0xFF, 0x00, 0x00]; // the first half
+        assert!(memory.write_masked_sample(&known, &complete.bytes, 500 as ScoreType));
+        let partial_index = memory.num_samples() - 1;
+        assert_eq!(memory.samples[partial_index].bytes[2..], [0x00, 0x00]);
+
+        // The same partial sample again (another completion): stored once, no panic
+        assert!(!memory.write_masked_sample(&known, &complete.bytes, 600 as ScoreType));
+        assert_eq!(memory.samples[partial_index].score, 500 as ScoreType);
+        // But the complete sample with the same bits is another one
+        let mut cleared = complete.clone();
+        cleared.bytes[2] = 0x00;
+        cleared.bytes[3] = 0x00;
+        assert_eq!(memory.search_index(&cleared), None);
+        // ... and with a full mask, a complete one is written
+        assert!(memory.write_masked_sample(&[0xFF; 4], &complete.bytes, complete.score));
+        assert!(!memory.samples.last().unwrap().is_partial());
+
+        // Reads ignore the unknown bits
+        let query = Sample::random(NUM_BITS);
+        let mask = Sample::random(NUM_BITS);
+        let distances = memory.distances(&mask.bytes, &query.bytes);
+        let both: Vec<u8> = mask.bytes.iter().zip(known.iter()).map(|(m, k)| m & k).collect();
+        assert_eq!(
+            distances[partial_index],
+            distance(&both, &query.bytes, &complete.bytes)
+        );
+        memory.use_bit_slices(true);
+        assert_eq!(memory.distances(&mask.bytes, &query.bytes), distances);
+        let (prio_false, prio_true) = memory.read_2_priorities(&mask.bytes, &query.bytes, 20);
+        assert!(0.0 < prio_false && 0.0 < prio_true);
+
+        // Removing the partial sample keeps the positions straight
+        memory.remove_sample(partial_index);
+        assert!(!memory.has_partial_samples());
+        assert_eq!(memory.search_index(&complete), Some(partial_index));
+    }
+
+    #[test]
+    fn test_normalize_scores() {
+        const NUM_BITS: usize = 16;
+        // Samples with bit 0 set score 100 more, on top of a huge offset
+        let write = |memory: &mut MhdMemory, offset: ScoreType| {
+            for value in 0..64u8 {
+                let mut sample = Sample::new(NUM_BITS, offset + value as ScoreType);
+                sample.bytes[1] = value;
+                sample.set_bit(0, 0 == value % 2);
+                if sample.get_bit(0) {
+                    sample.score += 100 as ScoreType;
+                };
+                memory.write_sample(&sample);
+            }
+        };
+        let mut small = MhdMemory::new(NUM_BITS);
+        let mut large = MhdMemory::new(NUM_BITS);
+        write(&mut small, ZERO_SCORE);
+        write(&mut large, 1_000_000 as ScoreType);
+        assert!((large.score_std_dev() - small.score_std_dev()).abs() < 1e-6);
+        assert_eq!(large.score_mean(), small.score_mean() + 1_000_000.0);
+
+        let (mask, query) = ([0x00, 0x00], [0x00, 0x00]);
+        let contrast = |memory: &MhdMemory| {
+            let (sums, max_score) = memory.scaled_sums(&memory.read_2_sums(&mask, &query, 0));
+            let (prio_false, prio_true) = sums.priorities(max_score);
+            prio_true - prio_false
+        };
+        // Raw, the offset all but hides the difference...
+        assert!(0.2 < contrast(&small) && contrast(&large) < 0.001);
+        // ... normalized, it is the same for both
+        small.config.normalize_scores = true;
+        large.config.normalize_scores = true;
+        assert!(0.2 < contrast(&small));
+        assert!((contrast(&small) - contrast(&large)).abs() < 1e-6);
+
+        // The running standard deviation follows removals
+        large.remove_sample(0);
+        let scores: Vec<f64> = large.samples.iter().map(|s| s.score.to_f64()).collect();
+        let mean = scores.iter().sum::<f64>() / scores.len() as f64;
+        let squares: f64 = scores.iter().map(|s| (s - mean).powi(2)).sum();
+        assert!((large.score_std_dev() - (squares / scores.len() as f64).sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_overwrite_dominated() {
+        const NUM_BITS: usize = 16;
+        let known = [0xFF, 0x00];
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_sample(&Sample::new_ones(NUM_BITS, 50 as ScoreType));
+        assert!(memory.write_masked_sample(&known, &[0x0F, 0x01], 10 as ScoreType));
+
+        // By default, a better score for the same bits is ignored...
+        assert!(!memory.write_masked_sample(&known, &[0x0F, 0x02], 30 as ScoreType));
+        assert_eq!(memory.samples[1].score, 10 as ScoreType);
+        // ... with overwrite_dominated, it replaces the stored one (but a worse one doesn't)
+        memory.config.overwrite_dominated = true;
+        assert!(!memory.write_masked_sample(&known, &[0x0F, 0x03], 30 as ScoreType));
+        assert_eq!(memory.samples[1].score, 30 as ScoreType);
+        assert!(!memory.write_masked_sample(&known, &[0x0F, 0x04], 20 as ScoreType));
+        assert_eq!(memory.samples[1].score, 30 as ScoreType);
+        assert_eq!(
+            (memory.min_score, memory.max_score),
+            (30 as ScoreType, 50 as ScoreType)
+        );
+        assert_eq!(memory.total_score, 80 as ScoreType);
+        assert!((memory.score_std_dev() - 10.0).abs() < 1e-9);
+
+        // Complete samples, too (which would otherwise have to score the same)
+        assert!(!memory.write_sample(&Sample::new_ones(NUM_BITS, 70 as ScoreType)));
+        assert_eq!(
+            (memory.min_score, memory.max_score),
+            (30 as ScoreType, 70 as ScoreType)
+        );
+        assert_eq!(memory.num_samples(), 2);
+    }
+
+    #[test]
+    fn test_remap() {
+        const NUM_BITS: usize = 24;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_n_random_samples(100);
+
+        // The identity changes nothing
+        let identity: Vec<Option<usize>> = (0..NUM_BITS).map(Some).collect();
+        assert_eq!(memory.remap(&identity).samples, memory.samples);
+
+        // A permutation keeps every bit, elsewhere
+        let permutation: Vec<Option<usize>> = (0..NUM_BITS).map(|b| Some((b * 5) % 24)).collect();
+        let permuted = memory.remap(&permutation);
+        assert_eq!(permuted.num_samples(), 100);
+        for (old, new) in memory.samples.iter().zip(permuted.samples.iter()) {
+            let moved = |b: usize| get_bit(&new.bytes, b) == get_bit(&old.bytes, (b * 5) % 24);
+            assert!((0..NUM_BITS).all(moved));
+            assert_eq!(new.score, old.score);
+        }
+        assert_eq!(permuted.max_score, memory.max_score);
+
+        // Fewer bits, and some new ones: partial samples, the first of any duplicates kept
+        let mapping: Vec<Option<usize>> = (0..4).map(Some).chain(vec![None; 4]).collect();
+        let narrow = memory.remap(&mapping);
+        assert_eq!(narrow.width(), 8);
+        assert!(narrow.num_samples() <= 16);
+        assert!(narrow.samples.iter().all(|s| s.is_partial()));
+        let first = &narrow.samples[0];
+        assert_eq!(first.score, memory.samples[0].score);
+        assert_eq!(first.bytes[0] & 0x0F, memory.samples[0].bytes[0] & 0x0F);
+        // ... and remapped again, the unknown bits stay unknown
+        let rotation: Vec<Option<usize>> = (0..8).map(|b| Some((b + 7) % 8)).collect();
+        let back = narrow.remap(&rotation);
+        assert_eq!(back.samples[0].known.as_ref().unwrap()[..], [0x1E]);
+    }
+
+    #[test]
+    fn test_search_masked() {
+        const NUM_BITS: usize = 40; // wide enough that 300 random samples are all different
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_n_random_samples(300);
+        let query = Sample::random(NUM_BITS);
+        let mask = [0xF0, 0x81, 0x00, 0x00, 0x00];
+        let expected: Vec<&Sample> = memory
+            .samples
+            .iter()
+            .filter(|s| 0 == distance(&mask, &query.bytes, &s.bytes))
+            .collect();
+        assert_eq!(memory.search_masked(&mask, &query.bytes).collect::<Vec<_>>(), expected);
+
+        // A full mask finds just what search finds
+        let sample = memory.samples[42].clone();
+        let found: Vec<&Sample> = memory.search_masked(&[0xFF; 5], &sample.bytes).collect();
+        assert_eq!(found, vec![memory.search(&sample).unwrap()]);
+
+        // A partial sample agrees wherever it doesn't know
+        let mut memory = MhdMemory::new(24);
+        memory.write_masked_sample(&[0xFF, 0x00, 0x00], &[0x12, 0x34, 0x56], 7 as ScoreType);
+        assert_eq!(memory.search_masked(&[0xFF; 3], &[0x12, 0xAB, 0xCD]).count(), 1);
+        assert_eq!(memory.search_masked(&[0xFF; 3], &[0x13, 0x34, 0x56]).count(), 0);
+    }
+
+    #[test]
+    fn test_masked_read_with_confidence() {
+        const NUM_BITS: usize = 16;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        let nothing = memory.masked_read_with_confidence(&[0xFF; 2], &[0x00; 2]);
+        assert_eq!((nothing.effective_samples, nothing.variance), (0.0, f64::INFINITY));
+
+        // One sample: no variance, and only one sample
+        memory.write_sample(&Sample::new(NUM_BITS, 10 as ScoreType));
+        let one = memory.masked_read_with_confidence(&[0xFF; 2], &[0x00; 2]);
+        assert_eq!((one.score, one.effective_samples), (10 as ScoreType, 1.0));
+        assert!(one.variance < 1e-9);
+
+        // Masked out, all samples weigh the same
+        memory.write_sample(&Sample::new_ones(NUM_BITS, 30 as ScoreType));
+        let both = memory.masked_read_with_confidence(&[0x00; 2], &[0xFF; 2]);
+        assert_eq!((both.variance, both.effective_samples), (100.0, 2.0));
+        assert_eq!(both.standard_error(), 50.0f64.sqrt());
+
+        // The closer sample counts more
+        let closer = memory.masked_read_with_confidence(&[0xFF; 2], &[0xFF; 2]);
+        assert!(1.0 < closer.effective_samples && closer.effective_samples < 2.0);
+        assert!(closer.variance < both.variance);
+        assert_eq!(closer.score, memory.masked_read(&[0xFF; 2], &[0xFF; 2]));
+    }
+
+    #[test]
+    fn test_read_priorities() {
+        const NUM_BITS: usize = 48;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_n_random_samples(300);
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-9 * a.abs().max(1.0);
+        let indices = [0, 7, 8, 30, 47, 7];
+        for use_lsh in [false, true].iter() {
+            memory.use_lsh(*use_lsh);
+            let mask = Sample::random(NUM_BITS);
+            let query = Sample::random(NUM_BITS);
+            let batched = memory.read_priorities(&mask.bytes, &query.bytes, &indices);
+            assert_eq!(batched.len(), indices.len());
+            for (index, (prio_false, prio_true)) in indices.iter().zip(batched) {
+                let one = memory.read_2_priorities(&mask.bytes, &query.bytes, *index);
+                assert!(close(one.0, prio_false) && close(one.1, prio_true));
+            }
+        }
+        assert!(memory.read_priorities(&[0xFF; 6], &[0x00; 6], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_top_samples_and_bit_importance() {
+        const NUM_BITS: usize = 16;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        assert!(memory.top_samples(3).is_empty());
+        assert_eq!(memory.bit_importance(), vec![0.0; NUM_BITS]);
+
+        // Samples with bit 0 set score (about) 100 more than those without
+        for _ in 0..40 {
+            let mut sample = Sample::random(NUM_BITS);
+            put_bit(&mut sample.bytes, NUM_BITS - 1, true); // always set: importance zero
+            // (the noise comes from the bits, so duplicates score the same)
+            let noise = (sample.bytes[1] % 10) as ScoreType;
+            sample.score = if get_bit(&sample.bytes, 0) {
+                100 as ScoreType
+            } else {
+                ZERO_SCORE
+            } + noise;
+            memory.write_sample(&sample);
+        }
+        let top = memory.top_samples(3);
+        assert_eq!(top.len(), 3);
+        assert!(top[0].score >= top[1].score && top[1].score >= top[2].score);
+        assert_eq!(top[0].score, memory.max_score);
+        assert_eq!(memory.top_samples(1000).len(), memory.num_samples());
+        assert!(memory.top_k_by_score(0).is_empty());
+
+        // The same as sorting them all (stably)
+        let mut sorted: Vec<&Sample> = memory.samples.iter().collect();
+        sorted.sort_by(|a, b| b.score.cmp_scores(&a.score));
+        for k in 1..=memory.num_samples() {
+            assert_eq!(memory.top_k_by_score(k), sorted[..k].to_vec());
+        }
+        let good = memory.samples_in_score_range(100 as ScoreType, memory.max_score);
+        assert!(good.iter().all(|s| get_bit(&s.bytes, 0)));
+        assert_eq!(
+            good.len()
+                + memory
+                    .samples_in_score_range(ZERO_SCORE, 99 as ScoreType)
+                    .len(),
+            memory.num_samples()
+        );
+
+        let importance = memory.bit_importance();
+        assert_eq!(importance.len(), NUM_BITS);
+        assert!(90.0 < importance[0]);
+        assert_eq!(importance[NUM_BITS - 1], 0.0);
+        assert!(importance[1..].iter().all(|i| i.abs() < importance[0]));
+    }
+
+    #[test]
+    fn test_iterators() {
+        const NUM_BITS: usize = 32;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_n_random_samples(20);
+        assert!(memory.iter().eq(memory.samples.iter()));
+        assert_eq!((&memory).into_iter().count(), 20);
+        let mut total = ZERO_SCORE;
+        for sample in &memory {
+            total += sample.score;
+        }
+        assert_eq!(total, memory.total_score);
+        let best = memory.par_iter().filter(|sample| sample.score == memory.max_score);
+        assert!(0 < best.count());
+
+        // collected again (with a duplicate, which is skipped)
+        let mut samples = memory.samples.clone();
+        samples.push(samples[3].clone());
+        let collected: MhdMemory = samples.into_iter().collect();
+        assert_eq!(collected.width(), NUM_BITS);
+        assert_eq!(collected.samples, memory.samples);
+        assert_eq!(collected.total_score, memory.total_score);
+        let empty: MhdMemory = std::iter::empty().collect();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_sample_weighted() {
+        const NUM_BITS: usize = 16;
+        let mut rng = StdRng::seed_from_u64(2039);
+        let mut memory = MhdMemory::new(NUM_BITS);
+        for score in 0..4 {
+            let mut sample = Sample::new(NUM_BITS, (score * 10) as ScoreType);
+            sample.bytes[0] = score as u8;
+            memory.write_sample(&sample);
+        }
+        // Scores 10, 20 and 30 above the lowest: drawn 1/6, 2/6 and 3/6 of the time
+        let mut counts = [0usize; 4];
+        for _ in 0..6000 {
+            counts[memory.sample_weighted(&mut rng).bytes[0] as usize] += 1;
+        }
+        assert_eq!(counts[0], 0);
+        assert!(800 < counts[1] && counts[1] < 1200, "{:?}", counts);
+        assert!(1800 < counts[2] && counts[2] < 2200, "{:?}", counts);
+        assert!(2800 < counts[3] && counts[3] < 3200, "{:?}", counts);
+
+        // All the same score: all equally likely
+        memory.clear();
+        memory.write_sample(&Sample::new(NUM_BITS, 5 as ScoreType));
+        memory.write_sample(&Sample::new_ones(NUM_BITS, 5 as ScoreType));
+        let ones = (0..1000)
+            .filter(|_| 0 != memory.sample_weighted(&mut rng).bytes[0])
+            .count();
+        assert!(400 < ones && ones < 600, "{}", ones);
+    }
+
+    #[test]
+    fn test_eviction() {
+        const NUM_BITS: usize = 40; // wide enough for 300 random samples without duplicates
+        let sample_with = |score: u32| {
+            let mut sample = Sample::random(NUM_BITS);
+            sample.score = score as ScoreType;
+            sample
+        };
+        let (a, b, c, d) = (sample_with(5), sample_with(1), sample_with(9), sample_with(7));
+
+        // Least recently used: writing a again saves it, so b goes
+        let config = MhdMemoryConfig {
+            max_samples: Some(3),
+            eviction: EvictionPolicy::LeastRecentlyUsed,
+            ..MhdMemoryConfig::default()
+        };
+        let mut memory = MhdMemory::with_config(NUM_BITS, config);
+        assert_eq!(memory.write_samples(vec![&a, &b, &c]), 3);
+        assert!(!memory.write_sample(&a));
+        assert!(memory.write_sample(&d));
+        assert_eq!(memory.samples, vec![a.clone(), c.clone(), d.clone()]);
+        assert_eq!(memory.num_evictions(), 1);
+        assert_eq!(memory.total_score, (5 + 9 + 7) as ScoreType);
+        assert_eq!(
+            (memory.min_score, memory.max_score),
+            (5 as ScoreType, 9 as ScoreType)
+        );
+
+        // Lowest score first: b goes (and the minimum score changes)
+        memory.clear();
+        assert_eq!(memory.config, config);
+        memory.config.eviction = EvictionPolicy::LowestScore;
+        memory.write_samples(vec![&a, &b, &c, &d]);
+        assert_eq!(memory.samples, vec![a.clone(), c.clone(), d.clone()]);
+        assert_eq!(
+            (memory.min_score, memory.max_score),
+            (5 as ScoreType, 9 as ScoreType)
+        );
+
+        // Random: the transposed copy and the sketches stay in step with the samples
+        let config = MhdMemoryConfig {
+            max_samples: Some(50),
+            eviction: EvictionPolicy::Random,
+            ..MhdMemoryConfig::default()
+        };
+        let mut memory = MhdMemory::with_config(NUM_BITS, config);
+        memory.use_bit_slices(true);
+        memory.use_sketches(true);
+        memory.write_n_random_samples(300);
+        assert_eq!(memory.num_samples(), 50);
+        assert_eq!(memory.num_evictions(), 250);
+        let mut plain = MhdMemory::new(NUM_BITS);
+        plain.write_samples(memory.samples.iter());
+        let mask = Sample::random(NUM_BITS).bytes;
+        let query = Sample::random(NUM_BITS).bytes;
+        assert_eq!(
+            memory.read_2_priorities(&mask, &query, 3),
+            plain.read_2_priorities(&mask, &query, 3)
+        );
+        for sample in plain.samples.iter() {
+            assert!(memory.search(sample).is_some());
+        }
+        assert_eq!(memory.total_score, plain.total_score);
+    }
+
+    #[test]
+    fn test_parallel_threshold() {
+        const NUM_BITS: usize = 40;
+        let mut sequential = MhdMemory::new(NUM_BITS);
+        sequential.config.parallel_threshold = usize::MAX;
+        sequential.write_n_random_samples(2000);
+        let mut parallel = sequential.clone();
+        parallel.config.parallel_threshold = 0; // i.e. chunks of one sample
+
+        // Both paths agree (up to the order floating point sums are added up in)
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-9 * a.abs().max(1.0);
+        for _ in 0..10 {
+            let mask = Sample::random(NUM_BITS).bytes;
+            let query = Sample::random(NUM_BITS).bytes;
+            let read = sequential.masked_read(&mask, &query);
+            let parallel_read = parallel.masked_read(&mask, &query);
+            assert!((parallel_read.to_f64() - read.to_f64()).abs() <= 1.0);
+            let sums = sequential.read_2_sums(&mask, &query, 7);
+            let other_sums = parallel.read_2_sums(&mask, &query, 7);
+            assert!(close(sums.score_false, other_sums.score_false));
+            assert!(close(sums.weight_true, other_sums.weight_true));
+            assert_eq!(sums.hits_true, other_sums.hits_true);
+            let (score, uncertainty) = sequential.predict_score(&query);
+            let (other_score, other_uncertainty) = parallel.predict_score(&query);
+            assert!((score.to_f64() - other_score.to_f64()).abs() <= 1.0);
+            assert!(close(uncertainty, other_uncertainty));
+        }
+    }
+
+    #[test]
+    fn test_distances() {
+        const NUM_BITS: usize = 40;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_n_random_samples(300);
+        let mask = Sample::random(NUM_BITS).bytes;
+        let query = Sample::random(NUM_BITS).bytes;
+        let distances = memory.distances(&mask, &query);
+        for (sample, dist) in memory.samples.iter().zip(distances.iter()) {
+            assert_eq!(*dist, distance(&mask, &query, &sample.bytes));
+        }
+        // The same from the bit slices
+        memory.use_bit_slices(true);
+        assert_eq!(memory.distances(&mask, &query), distances);
+    }
+
+    #[test]
+    fn test_lsh_reads() {
+        const NUM_BITS: usize = 64;
+        let mut memory = MhdMemory::with_config(
+            NUM_BITS,
+            MhdMemoryConfig {
+                max_samples: Some(400),
+                ..MhdMemoryConfig::default()
+            },
+        );
+        memory.use_lsh(true);
+        memory.write_n_random_samples(500); // (with 100 evictions, to keep the index busy)
+        assert!(memory.uses_lsh());
+        let mut plain = MhdMemory::new(NUM_BITS);
+        plain.write_samples(memory.samples.iter());
+
+        // An exact hit is (practically) always found, and counts just the same
+        let mask = vec![0xFF; 8];
+        for index in [0, 99, 399].iter() {
+            let query = &memory.samples[*index].bytes;
+            let candidates = memory.lsh_candidates(&mask, query).unwrap();
+            assert!(candidates.contains(index));
+            assert!(candidates.len() < memory.num_samples() / 4);
+            let sums = memory.read_2_sums(&mask, query, 3);
+            let plain_sums = plain.read_2_sums(&mask, query, 3);
+            assert_eq!(sums.hits_false + sums.hits_true, 1);
+            assert_eq!(
+                (sums.hits_false, sums.hits_true),
+                (plain_sums.hits_false, plain_sums.hits_true)
+            );
+            assert!(ZERO_SCORE < memory.masked_read(&mask, query));
+        }
+
+        // With nothing decided, the index can't help, so all samples are read
+        let nothing = vec![0x00; 8];
+        let query = &memory.samples[7].bytes;
+        assert!(memory.lsh_candidates(&nothing, query).is_none());
+        assert_eq!(
+            memory.masked_read(&nothing, query),
+            plain.masked_read(&nothing, query)
+        );
+
+        // The index grows with the samples, and is kept (empty) by clear
+        assert!(plain.memory_bytes() < memory.memory_bytes());
+        memory.clear();
+        assert!(memory.uses_lsh());
+        memory.write_n_random_samples(10);
+        let query = memory.samples[5].bytes.clone();
+        assert!(memory.lsh_candidates(&mask, &query).unwrap().contains(&5));
+        memory.use_lsh(false);
+        assert!(memory.lsh_candidates(&mask, &query).is_none());
+    }
+
+    #[test]
+    fn test_read_subsample() {
+        const NUM_BITS: usize = 32;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_n_random_samples(1000);
+        let mut approximate = memory.clone();
+        approximate.config.read_subsample = Some(100);
+        let mask = Sample::random(NUM_BITS).bytes;
+        let query = Sample::random(NUM_BITS).bytes;
+
+        // Each read visits another 100 samples (in order)
+        let candidates = approximate.read_candidates(&mask, &query).unwrap();
+        assert_eq!(candidates.len(), 100);
+        assert!(candidates.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_ne!(approximate.read_candidates(&mask, &query).unwrap(), candidates);
+        let (prio_false, prio_true) = approximate.read_2_priorities(&mask, &query, 5);
+        assert!(0.0 < prio_false && 0.0 < prio_true);
+
+        // A subsample as large as the memory is exact
+        approximate.config.read_subsample = Some(1000);
+        assert!(approximate.read_candidates(&mask, &query).is_none());
+        assert_eq!(
+            approximate.read_2_sums(&mask, &query, 5),
+            memory.read_2_sums(&mask, &query, 5)
+        );
+        assert_eq!(
+            approximate.masked_read(&mask, &query),
+            memory.masked_read(&mask, &query)
+        );
+    }
+
+    #[test]
+    fn test_seeded_read_subsample() {
+        const NUM_BITS: usize = 32;
+        let config = MhdMemoryConfig {
+            seed: Some(2033),
+            read_subsample: Some(50),
+            ..MhdMemoryConfig::default()
+        };
+        let mut first = MhdMemory::with_config(NUM_BITS, config);
+        first.write_n_random_samples(500);
+        let mut second = MhdMemory::with_config(NUM_BITS, config);
+        second.write_n_random_samples(500);
+        assert_eq!(first.samples, second.samples);
+
+        // The same seed, the same subsamples, read after read
+        let mask = Sample::random(NUM_BITS).bytes;
+        for _ in 0..10 {
+            let query = Sample::random(NUM_BITS).bytes;
+            assert_eq!(
+                first.read_candidates(&mask, &query),
+                second.read_candidates(&mask, &query)
+            );
+            assert_eq!(
+                first.masked_read(&mask, &query),
+                second.masked_read(&mask, &query)
+            );
+            assert_eq!(
+                first.read_2_sums(&mask, &query, 3),
+                second.read_2_sums(&mask, &query, 3)
+            );
+        }
+        // ... and a clone goes on with the same random numbers
+        let clone = first.clone();
+        let query = Sample::random(NUM_BITS).bytes;
+        assert_eq!(
+            first.read_candidates(&mask, &query),
+            clone.read_candidates(&mask, &query)
+        );
+    }
+
+    #[test]
+    fn test_cluster_reads() {
+        const NUM_BITS: usize = 64;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_n_random_samples(400);
+        memory.use_clusters(true);
+        assert!(memory.uses_clusters());
+        memory.write_n_random_samples(100); // (they join the clusters)
+        let mut plain = MhdMemory::new(NUM_BITS);
+        plain.write_samples(memory.samples.iter());
+
+        // An exact hit is always found, and counts just the same
+        let mask = vec![0xFF; 8];
+        for index in [0, 99, 450].iter() {
+            let query = &memory.samples[*index].bytes;
+            let candidates = memory.cluster_candidates(&mask, query).unwrap();
+            assert!(candidates.contains(index));
+            assert!(candidates.len() < memory.num_samples() / 2);
+            let sums = memory.read_2_sums(&mask, query, 3);
+            let plain_sums = plain.read_2_sums(&mask, query, 3);
+            assert_eq!(
+                (sums.hits_false, sums.hits_true),
+                (plain_sums.hits_false, plain_sums.hits_true)
+            );
+        }
+
+        // With nothing decided, all samples are read
+        let nothing = vec![0x00; 8];
+        let query = &memory.samples[7].bytes;
+        assert!(memory.cluster_candidates(&nothing, query).is_none());
+        assert_eq!(
+            memory.masked_read(&nothing, query),
+            plain.masked_read(&nothing, query)
+        );
+
+        // Removing a sample keeps the clusters straight; clear keeps them (empty)
+        memory.remove_sample(3);
+        let query = memory.samples[3].bytes.clone();
+        assert!(memory.cluster_candidates(&mask, &query).unwrap().contains(&3));
+        assert!(plain.memory_bytes() < memory.memory_bytes());
+        memory.clear();
+        assert!(memory.uses_clusters());
+        memory.write_n_random_samples(10);
+        let query = memory.samples[5].bytes.clone();
+        assert!(memory.cluster_candidates(&mask, &query).unwrap().contains(&5));
+        memory.use_clusters(false);
+        assert!(memory.cluster_candidates(&mask, &query).is_none());
+    }
+
+    #[test]
+    fn test_masked_read_multi() {
+        const NUM_BITS: usize = 32;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        assert_eq!(memory.num_objectives(), 0);
+        memory.write_n_random_samples(200);
+        assert_eq!(memory.num_objectives(), 1);
+        let mask = Sample::random(NUM_BITS).bytes;
+        let query = Sample::random(NUM_BITS).bytes;
+        // Single objective: the same as masked_read
+        let single = memory.masked_read_multi(&mask, &query);
+        assert_eq!(single, vec![memory.masked_read(&mask, &query)]);
+
+        // Two objectives: each one predicted as masked_read would predict it alone
+        memory.clear();
+        let mut second_only = MhdMemory::new(NUM_BITS);
+        for _ in 0..200 {
+            let sample = Sample::random(NUM_BITS);
+            let other = Sample::random(NUM_BITS).score;
+            second_only.write_sample(&Sample { score: other, ..sample.clone() });
+            let objectives = vec![sample.score, other];
+            memory.write_sample(&sample.with_objectives(objectives));
+        }
+        assert_eq!(memory.num_objectives(), 2);
+        let both = memory.masked_read_multi(&mask, &query);
+        assert_eq!(both[0], memory.masked_read(&mask, &query));
+        assert_eq!(both[1], second_only.masked_read(&mask, &query));
+
+        // A sample without the second objective only counts for the first
+        memory.write_sample(&Sample::random(NUM_BITS));
+        assert_eq!(memory.num_objectives(), 2);
+        assert_eq!(memory.masked_read_multi(&mask, &query).len(), 2);
+    }
+
+    #[test]
+    fn test_metric_choice() {
+        use metric::{MaskedJaccard, WeightedHamming};
+        const NUM_BITS: usize = 32;
+        let mut hamming = MhdMemory::new(NUM_BITS);
+        hamming.write_n_random_samples(50);
+        let mut explicit = MhdMemory::with_metric(NUM_BITS, MaskedHamming);
+        let mut weighted = MhdMemory::with_metric(NUM_BITS, WeightedHamming::new(vec![1.0; 32]));
+        let mut jaccard = MhdMemory::with_metric(NUM_BITS, MaskedJaccard);
+        for sample in hamming.samples.iter() {
+            explicit.write_sample(sample);
+            weighted.write_sample(sample);
+            jaccard.write_sample(sample);
+        }
+        assert_eq!(hamming.metric_name(), "MaskedHamming");
+        assert_eq!(jaccard.metric_name(), "MaskedJaccard");
+
+        // The default, explicit masked hamming, and weighted hamming (all weights one) agree
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-9 * a.abs().max(1.0);
+        let mask = vec![0xFF; 4];
+        for _ in 0..10 {
+            let query = Sample::random(NUM_BITS).bytes;
+            let expected = hamming.read_2_priorities(&mask, &query, 3);
+            for memory in [&explicit, &weighted].iter() {
+                let priorities = memory.read_2_priorities(&mask, &query, 3);
+                assert!(close(priorities.0, expected.0) && close(priorities.1, expected.1));
+            }
+            // Jaccard reads differently, but still reads something
+            let priorities = jaccard.read_2_priorities(&mask, &query, 3);
+            assert!(priorities.0.is_finite() && priorities.1.is_finite());
+        }
+
+        // Range queries in the metric
+        let query = Sample::random(NUM_BITS).bytes;
+        let near: Vec<&Sample> = jaccard.within(&mask, &query, 20).collect();
+        for sample in jaccard.samples.iter() {
+            let is_near = MaskedJaccard.distance(&mask, &query, &sample.bytes) <= 20;
+            assert_eq!(is_near, near.contains(&sample));
+        }
+
+        // The metric survives clearing
+        jaccard.clear();
+        assert_eq!(jaccard.metric_name(), "MaskedJaccard");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        const NUM_BITS: usize = 48;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.kernel.exponent = 2.0;
+        memory.weighting.recency_half_life = Some(10.0);
+        memory.use_bit_slices(true);
+        memory.write_n_random_samples(30);
+
+        let json = serde_json::to_string(&memory).expect("could not serialize");
+        let mut copy: MhdMemory = serde_json::from_str(&json).expect("could not deserialize");
+        assert_eq!(copy.width(), NUM_BITS);
+        assert_eq!(copy.samples, memory.samples);
+        assert_eq!(copy.kernel, memory.kernel);
+        assert_eq!(copy.weighting, memory.weighting);
+        assert_eq!(copy.total_score, memory.total_score);
+        // The transposed copy has to be turned on again
+        assert!(!copy.uses_bit_slices());
+        copy.use_bit_slices(true);
+
+        // Same memory, same answers
+        let mask = vec![0xFF; memory.samples[0].size_in_bytes()];
+        let query = Sample::random(NUM_BITS).bytes;
+        assert_eq!(
+            copy.masked_read(&mask, &query),
+            memory.masked_read(&mask, &query)
+        );
+        assert_eq!(
+            copy.read_2_priorities(&mask, &query, 5),
+            memory.read_2_priorities(&mask, &query, 5)
+        );
+
+        let sample = Sample::random(NUM_BITS);
+        let copy: Sample = serde_json::from_str(&serde_json::to_string(&sample).unwrap()).unwrap();
+        assert_eq!(copy, sample);
+    }
 } // end mod tests
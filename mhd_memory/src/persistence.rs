@@ -0,0 +1,306 @@
+/// # Persistence -- saving and loading MHD Memories
+///
+/// A memory is written as CSV text, one row per sample: the sample's bytes in hex
+/// (byte 0 first), and its score. A comment line before the header holds the width in bits,
+/// so e.g. pandas reads it with `pd.read_csv(path, comment="#")`:
+///
+/// ```text
+/// # mhd_memory width 12
+/// bits,score
+/// a30f,42
+/// 07f1,17
+/// ```
+///
+/// ```rust
+/// use mhd_memory::{MhdMemory, Sample};
+///
+/// let mut memory = MhdMemory::new(40);
+/// memory.write_n_random_samples(10);
+///
+/// let mut file = Vec::new(); // or a std::fs::File, etc.
+/// memory.export_csv(&mut file).unwrap();
+/// let copy = MhdMemory::import_csv(&file[..]).unwrap();
+/// assert_eq!(copy.width(), 40);
+/// assert_eq!(copy.samples, memory.samples);
+/// ```
+///
+/// `export_binary` writes the same more compactly, and with partial samples and
+/// objectives, too: the magic bytes `MHDBIN01`, the width in bits and the number of samples
+/// (little endian `u64`s), then for every sample its score (a little endian `f64`), a flags
+/// byte (1 = partial, 2 = with objectives), its bytes, then the known bytes if partial, and
+/// the number of objectives (a `u32`) and their values (`f64`s) if it has any.
+///
+/// ```rust
+/// use mhd_memory::MhdMemory;
+///
+/// let mut memory = MhdMemory::new(40);
+/// memory.write_n_random_samples(10);
+///
+/// let mut file = Vec::new();
+/// memory.export_binary(&mut file).unwrap();
+/// assert_eq!(file.len(), 24 + 10 * (8 + 1 + 5));
+/// let copy = MhdMemory::import_binary(&file[..]).unwrap();
+/// assert_eq!(copy.samples, memory.samples);
+/// ```
+///
+/// With the feature "serde", memories (and samples) can be serialized in any serde format
+/// instead, along with their settings (weighting, kernel) and write history. Not saved are
+/// what can be rebuilt -- the transposed copy, the sketches and the LSH index (call
+/// `use_bit_slices`, `use_sketches` or `use_lsh` again after loading) -- and a custom metric
+/// (use `with_metric` again).
+/// So a memory trained in one run can warm-start a later one, instead of random samples.
+use std::io::{self, BufRead, Read, Write};
+
+use mhdmemory::MhdMemory;
+use sample::{Sample, ScoreType, ZERO_SCORE};
+use score::Score;
+
+const WIDTH_COMMENT: &str = "# mhd_memory width ";
+const CSV_HEADER: &str = "bits,score";
+const BINARY_MAGIC: &[u8; 8] = b"MHDBIN01";
+const FLAG_PARTIAL: u8 = 1;
+const FLAG_OBJECTIVES: u8 = 2;
+
+fn invalid_data(line_number: usize, message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line_number, message),
+    )
+}
+
+fn invalid_binary(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f64<R: Read>(reader: &mut R) -> io::Result<f64> {
+    Ok(f64::from_bits(read_u64(reader)?))
+}
+
+impl MhdMemory {
+    /// Write all samples as CSV, see above -- all complete samples, that is:
+    /// CSV has no column for the known bits of partial ones (see `write_masked_sample`)
+    pub fn export_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{}{}", WIDTH_COMMENT, self.width)?;
+        writeln!(writer, "{}", CSV_HEADER)?;
+        for sample in self.samples.iter().filter(|s| !s.is_partial()) {
+            for byte in sample.bytes.iter() {
+                write!(writer, "{:02x}", byte)?;
+            }
+            writeln!(writer, ",{}", sample.score)?;
+        }
+        writer.flush()
+    }
+
+    /// Read a memory written by `export_csv` (the samples are written to a new memory,
+    /// in the order they are read -- so duplicates are dropped)
+    pub fn import_csv<R: BufRead>(reader: R) -> io::Result<MhdMemory> {
+        let mut memory: Option<MhdMemory> = None;
+        for (line_index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            let line_number = line_index + 1;
+            if let Some(width) = line.strip_prefix(WIDTH_COMMENT) {
+                let width = width
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|e| invalid_data(line_number, format!("bad width: {}", e)))?;
+                memory = Some(MhdMemory::new(width));
+                continue;
+            };
+            if line.is_empty() || line.starts_with('#') || line == CSV_HEADER {
+                continue;
+            };
+            let memory = memory
+                .as_mut()
+                .ok_or_else(|| invalid_data(line_number, String::from("width not given")))?;
+            let (bits, score) = line.split_once(',').ok_or_else(|| {
+                invalid_data(line_number, format!("expected bits,score: {}", line))
+            })?;
+            let mut sample = Sample::new(memory.width, ZERO_SCORE);
+            if !bits.is_ascii() || bits.len() != 2 * sample.size_in_bytes() {
+                return Err(invalid_data(
+                    line_number,
+                    format!(
+                        "expected {} hex digits: {}",
+                        2 * sample.size_in_bytes(),
+                        bits
+                    ),
+                ));
+            };
+            for (index, byte) in sample.bytes.iter_mut().enumerate() {
+                *byte = u8::from_str_radix(&bits[2 * index..2 * index + 2], 16)
+                    .map_err(|e| invalid_data(line_number, format!("bad bits: {}", e)))?;
+            }
+            sample.score = score
+                .trim()
+                .parse::<ScoreType>()
+                .map_err(|e| invalid_data(line_number, format!("bad score: {}", e)))?;
+            memory.write_sample(&sample);
+        } // end for all lines
+        memory.ok_or_else(|| invalid_data(0, String::from("empty file")))
+    }
+
+    /// Write all samples in the binary format, see above
+    pub fn export_binary<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(BINARY_MAGIC)?;
+        writer.write_all(&(self.width as u64).to_le_bytes())?;
+        writer.write_all(&(self.num_samples() as u64).to_le_bytes())?;
+        for sample in self.samples.iter() {
+            let mut flags = 0;
+            if sample.is_partial() {
+                flags |= FLAG_PARTIAL;
+            };
+            if !sample.objectives.is_empty() {
+                flags |= FLAG_OBJECTIVES;
+            };
+            writer.write_all(&sample.score.to_f64().to_le_bytes())?;
+            writer.write_all(&[flags])?;
+            writer.write_all(&sample.bytes)?;
+            if let Some(known) = sample.known.as_ref() {
+                writer.write_all(known)?;
+            };
+            if !sample.objectives.is_empty() {
+                writer.write_all(&(sample.objectives.len() as u32).to_le_bytes())?;
+                for objective in sample.objectives.iter() {
+                    writer.write_all(&objective.to_f64().to_le_bytes())?;
+                }
+            };
+        }
+        writer.flush()
+    }
+
+    /// Read a memory written by `export_binary` (the samples are written to a new memory,
+    /// in the order they are read -- so duplicates are dropped)
+    pub fn import_binary<R: Read>(mut reader: R) -> io::Result<MhdMemory> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != BINARY_MAGIC {
+            return Err(invalid_binary(String::from("not a binary mhd_memory")));
+        };
+        let width = read_u64(&mut reader)? as usize;
+        if !Sample::size_is_legal(width) {
+            return Err(invalid_binary(format!("illegal sample width {}", width)));
+        };
+        let num_samples = read_u64(&mut reader)?;
+        let mut memory = MhdMemory::new(width);
+        for sample_index in 0..num_samples {
+            let mut sample = Sample::new(width, ScoreType::from_f64(read_f64(&mut reader)?));
+            let mut flags = [0; 1];
+            reader.read_exact(&mut flags)?;
+            if 0 != flags[0] & !(FLAG_PARTIAL | FLAG_OBJECTIVES) {
+                return Err(invalid_binary(format!(
+                    "sample {}: bad flags {}",
+                    sample_index, flags[0]
+                )));
+            };
+            reader.read_exact(&mut sample.bytes)?;
+            if 0 != flags[0] & FLAG_PARTIAL {
+                let mut known = vec![0; sample.bytes.len()];
+                reader.read_exact(&mut known)?;
+                sample = sample.with_known(&known);
+            };
+            if 0 != flags[0] & FLAG_OBJECTIVES {
+                let mut count = [0; 4];
+                reader.read_exact(&mut count)?;
+                sample.objectives = (0..u32::from_le_bytes(count))
+                    .map(|_| read_f64(&mut reader).map(ScoreType::from_f64))
+                    .collect::<io::Result<_>>()?;
+            };
+            memory.write_sample(&sample);
+        }
+        Ok(memory)
+    }
+} // end impl MhdMemory (persistence)
+
+///////////////////// TESTs for persistence /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_round_trip() {
+        const NUM_BITS: usize = 21; // not a multiple of 8
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_n_random_samples(50);
+        let mut file = Vec::new();
+        memory.export_csv(&mut file).unwrap();
+
+        let text = String::from_utf8(file.clone()).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("# mhd_memory width 21"));
+        assert_eq!(lines.next(), Some("bits,score"));
+        assert_eq!(lines.count(), memory.num_samples());
+
+        let copy = MhdMemory::import_csv(&file[..]).unwrap();
+        assert_eq!(copy.width(), NUM_BITS);
+        assert_eq!(copy.samples, memory.samples);
+        assert_eq!(copy.max_score, memory.max_score);
+        assert_eq!(copy.total_score, memory.total_score);
+
+        // An empty memory survives, too
+        let mut file = Vec::new();
+        MhdMemory::new(NUM_BITS).export_csv(&mut file).unwrap();
+        assert!(MhdMemory::import_csv(&file[..]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        const NUM_BITS: usize = 21;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_n_random_samples(50);
+        let objectives = [3, 1, 4].iter().map(|&o| o as ScoreType).collect();
+        let mut sample = Sample::random(NUM_BITS).with_objectives(objectives);
+        sample.score = 3 as ScoreType;
+        memory.write_sample(&sample);
+        memory.write_masked_sample(&[0xFF, 0x0F, 0x00], &[0x12, 0x34, 0x56], 9 as ScoreType);
+        assert!(memory.samples.last().unwrap().is_partial());
+
+        let mut file = Vec::new();
+        memory.export_binary(&mut file).unwrap();
+        let copy = MhdMemory::import_binary(&file[..]).unwrap();
+        assert_eq!(copy.width(), NUM_BITS);
+        assert_eq!(copy.samples, memory.samples);
+        assert_eq!(copy.max_score, memory.max_score);
+        assert_eq!(copy.total_score, memory.total_score);
+
+        // Truncated, or not binary at all
+        assert!(MhdMemory::import_binary(&file[..file.len() - 1]).is_err());
+        let mut csv = Vec::new();
+        memory.export_csv(&mut csv).unwrap();
+        assert!(MhdMemory::import_binary(&csv[..]).is_err());
+        let mut bad_flags = Vec::new();
+        MhdMemory::new(NUM_BITS)
+            .export_binary(&mut bad_flags)
+            .unwrap();
+        bad_flags[16] = 1; // one sample...
+        bad_flags.extend_from_slice(&[0; 8]); // ... with score 0.0 ...
+        bad_flags.extend_from_slice(&[4, 0, 0, 0]); // ... and unknown flags
+        assert!(MhdMemory::import_binary(&bad_flags[..]).is_err());
+    }
+
+    #[test]
+    fn test_csv_errors() {
+        let bad_files = [
+            "",                                             // no width
+            "bits,score\n0102,3\n",                         // no width
+            "# mhd_memory width 16\nbits,score\n010,3\n",   // too few hex digits
+            "# mhd_memory width 16\nbits,score\nxx02,3\n",  // not hex
+            "# mhd_memory width 16\nbits,score\n0102\n",    // no score
+            "# mhd_memory width 16\nbits,score\n0102,3x\n", // bad score
+        ];
+        for bad_file in bad_files.iter() {
+            assert!(MhdMemory::import_csv(bad_file.as_bytes()).is_err());
+        }
+        let good = "# mhd_memory width 16\nbits,score\n0102,3\n\nff00, 7\n";
+        let memory = MhdMemory::import_csv(good.as_bytes()).unwrap();
+        assert_eq!(memory.num_samples(), 2);
+        assert_eq!(memory.samples[1].bytes, vec![0xff, 0x00]);
+        assert_eq!(memory.max_score, 7 as ScoreType);
+    }
+}
@@ -0,0 +1,200 @@
+/// # Decision policies -- from a read to a decision
+///
+/// `MhdMemory::read_and_decide` reads the memory for one open decision (see `read_2_sums`)
+/// and then decides for true or false. How it decides is a `DecisionPolicy`; four are
+/// provided, and `MhdMemory::read_and_decide_with` takes any, so new ones can be tried out
+/// without changing the memory (or the solvers, see e.g. `MhdMonteCarloSolver::with_policy`).
+///
+/// * `UcbPolicy`: the priorities with UCB exploration (`read_2_priorities`), as ever:
+///   the higher one wins, or, `full_monte`, they are the odds. The default.
+/// * `EpsilonGreedy`: the higher (plain) priority, but a coin toss with probability `epsilon`
+/// * `Softmax`: at random, the odds growing exponentially with the priorities over `temperature`
+/// * `ThompsonSampling`: draw a plausible (normalized) score for each alternative from a Beta
+///   distribution around its weighted average score -- the more weight, the narrower -- and
+///   take the better draw
+///
+/// ```rust
+/// use mhd_memory::{EpsilonGreedy, MhdMemory, Sample, ScoreType};
+/// use mhd_memory::{Softmax, ThompsonSampling, UcbPolicy};
+///
+/// let mut memory = MhdMemory::new(16);
+/// memory.write_sample(&Sample::new(16, 10 as ScoreType));
+/// memory.write_sample(&Sample::new_ones(16, 1000 as ScoreType));
+/// let (mask, query) = ([0x00; 2], [0x00; 2]); // nothing decided yet
+///
+/// // The sample with bit 0 set scores much better: every policy should (mostly) see that
+/// assert!(memory.read_and_decide_with(&UcbPolicy::default(), &mask, &query, 0));
+/// assert!(memory.read_and_decide_with(&EpsilonGreedy { epsilon: 0.0 }, &mask, &query, 0));
+/// assert!(memory.read_and_decide_with(&Softmax { temperature: 0.01 }, &mask, &query, 0));
+/// let trues = (0..100)
+///     .filter(|_| memory.read_and_decide_with(&ThompsonSampling, &mask, &query, 0))
+///     .count();
+/// assert!(50 < trues);
+/// ```
+use rand::Rng;
+use rand_distr::{Beta, Distribution};
+use std::fmt::Debug;
+
+use mhdmemory::{MhdMemory, PrioritySums};
+use score::Score;
+
+pub trait DecisionPolicy: Debug + Send + Sync {
+    /// A short name, for traces and reports
+    fn name(&self) -> &'static str;
+
+    /// Decide for true or false, given the sums `memory` read for the decision
+    fn decide(&self, memory: &MhdMemory, sums: &PrioritySums) -> bool;
+}
+
+// The alternative with the higher priority, a coin toss if they are equal
+fn higher(priorities: (f64, f64)) -> bool {
+    match priorities.0.partial_cmp(&priorities.1) {
+        Some(std::cmp::Ordering::Less) => true,
+        Some(std::cmp::Ordering::Greater) => false,
+        _ => rand::thread_rng().gen::<bool>(),
+    }
+}
+
+/// The priorities with UCB exploration, see `MhdMemory::decide` (the default policy)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct UcbPolicy {
+    /// Decide at random, in proportion to the priorities (otherwise for the higher one)
+    pub full_monte: bool,
+}
+
+impl DecisionPolicy for UcbPolicy {
+    fn name(&self) -> &'static str {
+        "UCB"
+    }
+
+    fn decide(&self, memory: &MhdMemory, sums: &PrioritySums) -> bool {
+        MhdMemory::decide(memory.priorities_from(sums), self.full_monte)
+    }
+}
+
+/// The higher priority (without exploration), except with probability `epsilon`:
+/// then a coin toss
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EpsilonGreedy {
+    pub epsilon: f64,
+}
+
+impl DecisionPolicy for EpsilonGreedy {
+    fn name(&self) -> &'static str {
+        "EpsilonGreedy"
+    }
+
+    fn decide(&self, memory: &MhdMemory, sums: &PrioritySums) -> bool {
+        let mut rng = rand::thread_rng();
+        if rng.gen_bool(self.epsilon.clamp(0.0, 1.0)) {
+            rng.gen::<bool>()
+        } else {
+            let (sums, max_score) = memory.scaled_sums(sums);
+            higher(sums.priorities(max_score))
+        }
+    }
+}
+
+/// True with probability exp(prio_true / T) / (exp(prio_false / T) + exp(prio_true / T)),
+/// for the priorities without exploration (which are between 0.0 and 1.0) and the
+/// `temperature` T: the hotter, the closer to a coin toss
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Softmax {
+    pub temperature: f64,
+}
+
+impl DecisionPolicy for Softmax {
+    fn name(&self) -> &'static str {
+        "Softmax"
+    }
+
+    fn decide(&self, memory: &MhdMemory, sums: &PrioritySums) -> bool {
+        let (sums, max_score) = memory.scaled_sums(sums);
+        let (prio_false, prio_true) = sums.priorities(max_score);
+        if 0.0 >= self.temperature {
+            return higher((prio_false, prio_true));
+        };
+        let probability = 1.0 / (1.0 + ((prio_false - prio_true) / self.temperature).exp());
+        rand::thread_rng().gen_bool(probability.clamp(0.0, 1.0))
+    }
+}
+
+/// For each alternative, draw from Beta(1 + w * s, 1 + w * (1 - s)), where s is its
+/// weighted average score over the best score (between 0.0 and 1.0) and w its weight;
+/// decide for the larger draw
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ThompsonSampling;
+
+impl ThompsonSampling {
+    fn draw<R: Rng>(rng: &mut R, score: f64, weight: f64, max_score: f64) -> f64 {
+        let average = if 0.0 < weight && 0.0 < max_score {
+            (score / weight / max_score).clamp(0.0, 1.0)
+        } else {
+            0.5 // nothing known
+        };
+        let weight = weight.max(0.0);
+        match Beta::new(1.0 + weight * average, 1.0 + weight * (1.0 - average)) {
+            Ok(beta) => beta.sample(rng),
+            Err(_) => rng.gen(), // (only for weights too large to be finite)
+        }
+    }
+}
+
+impl DecisionPolicy for ThompsonSampling {
+    fn name(&self) -> &'static str {
+        "ThompsonSampling"
+    }
+
+    fn decide(&self, memory: &MhdMemory, sums: &PrioritySums) -> bool {
+        let mut rng = rand::thread_rng();
+        let (sums, max_score) = memory.scaled_sums(sums);
+        let max_score = max_score.to_f64();
+        let draw_false = Self::draw(&mut rng, sums.score_false, sums.weight_false, max_score);
+        let draw_true = Self::draw(&mut rng, sums.score_true, sums.weight_true, max_score);
+        higher((draw_false, draw_true))
+    }
+}
+
+///////////////////// TESTs for DecisionPolicy /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sample::{Sample, ScoreType};
+
+    #[test]
+    fn test_policies() {
+        const NUM_BITS: usize = 8;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        memory.write_sample(&Sample::new(NUM_BITS, 100 as ScoreType));
+        memory.write_sample(&Sample::new_ones(NUM_BITS, 100 as ScoreType));
+        let (mask, query) = ([0x00], [0x00]);
+        let count_trues = |memory: &MhdMemory, policy: &dyn DecisionPolicy| {
+            (0..1000)
+                .filter(|_| memory.read_and_decide_with(policy, &mask, &query, 3))
+                .count()
+        };
+        // The same scores: every policy tosses a coin (more or less)
+        let policies: Vec<Box<dyn DecisionPolicy>> = vec![
+            Box::new(UcbPolicy::default()),
+            Box::new(UcbPolicy { full_monte: true }),
+            Box::new(EpsilonGreedy { epsilon: 0.1 }),
+            Box::new(Softmax { temperature: 1.0 }),
+            Box::new(ThompsonSampling),
+        ];
+        for policy in policies.iter() {
+            let trues = count_trues(&memory, policy.as_ref());
+            assert!(350 < trues && trues < 650, "{}: {}", policy.name(), trues);
+        }
+
+        // Bit 3 set scores better: the greedy ones always see it, the others mostly
+        memory.clear();
+        memory.write_sample(&Sample::new(NUM_BITS, 10 as ScoreType));
+        memory.write_sample(&Sample::new_ones(NUM_BITS, 1000 as ScoreType));
+        assert_eq!(count_trues(&memory, &EpsilonGreedy { epsilon: 0.0 }), 1000);
+        assert_eq!(count_trues(&memory, &Softmax { temperature: 0.0 }), 1000);
+        assert!(900 < count_trues(&memory, &EpsilonGreedy { epsilon: 0.1 }));
+        assert!(500 < count_trues(&memory, &Softmax { temperature: 0.5 }));
+        assert!(count_trues(&memory, &Softmax { temperature: 100.0 }) < 600);
+        assert!(500 < count_trues(&memory, &ThompsonSampling));
+    }
+}
@@ -2,9 +2,7 @@
 // pub const NUM_BITS: usize = 1024; // Kilobit, not yet a kilobyte....
 // pub const NUM_BYTES: usize = NUM_BITS / 8; // 8 is not really a magic number, is it?
 
-pub type ScoreType = u32; // that can change at any time, so we give it a name
-
-pub const ZERO_SCORE: ScoreType = 0;
+pub use score::{Score, ScoreType, ZERO_SCORE}; // see there: the type can be chosen by a feature
 
 /// # The `Sample` Trait (Generic?)
 ///
@@ -66,14 +64,25 @@ pub const ZERO_SCORE: ScoreType = 0;
 /// ```
 ///
 #[derive(Default, Clone, PartialEq)] // Debug implemented by hand, see below
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Sample {
     // pub bytes:  [u8; NUM_BYTES],
     pub width: usize,
-    pub bytes: Vec<u8>,   // initially empty
-    pub score: ScoreType, // we will probably change that ...
+    pub bytes: SampleBytes, // initially empty; word aligned, see there
+    pub score: ScoreType,   // we will probably change that ...
+    /// For multi-objective problems: all the scores (empty = just `score`), see `objectives`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub objectives: Vec<ScoreType>,
+    /// For partial samples: which bits are known (None = all), see `with_known`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub known: Option<SampleBytes>,
 } // end struct Sample
 
 use rand::prelude::*;
+use sample_bytes::SampleBytes;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use util::put_bit;
 
 impl Sample {
     // calculate ceil( size_in_bits / 8 ) without floating point cast...
@@ -95,7 +104,7 @@ impl Sample {
     }
 
     #[inline]
-    fn size_is_legal(size_in_bits: usize) -> bool {
+    pub(crate) fn size_is_legal(size_in_bits: usize) -> bool {
         let size_in_bytes = Self::bits_to_bytes(size_in_bits);
         (3 < size_in_bits) && (size_in_bytes <= 1024 * 1024) // this is subject to change
     }
@@ -106,8 +115,10 @@ impl Sample {
         Self {
             width: 0,
             // bytes : [0;  Self::NUM_BYTES ],
-            bytes: Vec::with_capacity(DEFAULT_CAPACITY),
+            bytes: SampleBytes::with_capacity(DEFAULT_CAPACITY),
             score: ZERO_SCORE,
+            objectives: Vec::new(),
+            known: None,
         }
     }
 
@@ -117,7 +128,9 @@ impl Sample {
         Self {
             width: size_in_bits,
             score: starting_score,
-            bytes: vec![0x0; Self::bits_to_bytes(size_in_bits)], // start with an empty vector of bytes
+            objectives: Vec::new(),
+            known: None,
+            bytes: SampleBytes::from_elem(0x0, Self::bits_to_bytes(size_in_bits)), // start with an empty vector of bytes
         }
     }
 
@@ -127,18 +140,25 @@ impl Sample {
         Self {
             width: size_in_bits,
             score: starting_score,
-            bytes: vec![0xFF; Self::bits_to_bytes(size_in_bits)], // start with an empty vector of bytes
+            objectives: Vec::new(),
+            known: None,
+            bytes: SampleBytes::from_elem(0xFF, Self::bits_to_bytes(size_in_bits)), // start with an empty vector of bytes
         }
     }
 
     #[inline]
     pub fn randomize(&mut self) {
+        self.randomize_with(&mut rand::thread_rng());
+    }
+
+    /// `randomize`, with the random numbers from `rng` (e.g. a seeded one)
+    pub fn randomize_with<R: Rng + ?Sized>(&mut self, rng: &mut R) {
         // First a random score
-        const MAX_RANDOM_SCORE: ScoreType = 1000; // seems to work out OK....
-        self.score = rand::thread_rng().gen_range(0..=MAX_RANDOM_SCORE);
+        const MAX_RANDOM_SCORE: u32 = 1000; // seems to work out OK....
+        self.score = ScoreType::from_f64(rng.gen_range(0..=MAX_RANDOM_SCORE) as f64);
         // Then some random bytes
         // Note -- length of bytes vector is not changed!
-        rand::thread_rng().fill_bytes(&mut self.bytes);
+        rng.fill_bytes(&mut self.bytes);
     }
 
     #[inline]
@@ -149,6 +169,128 @@ impl Sample {
         result
     }
 
+    /// A sample with the bits from `bits`, in order (as `set_bit` numbers them),
+    /// as many bits as there are
+    ///
+    /// ```rust
+    /// use mhd_memory::{Sample, ScoreType};
+    ///
+    /// let sample = Sample::from_bits((0..12).map(|index| 0 == index % 3), 7 as ScoreType);
+    /// assert_eq!(sample.size(), 12);
+    /// assert_eq!(sample.score, 7 as ScoreType);
+    /// assert_eq!(&sample.bytes[..], &[0b1001_0010, 0b0100_0000]);
+    /// assert!(sample.iter_bits().eq((0..12).map(|index| 0 == index % 3)));
+    /// ```
+    pub fn from_bits<I: Iterator<Item = bool>>(bits: I, score: ScoreType) -> Self {
+        let mut bytes = SampleBytes::with_capacity(bits.size_hint().0 / 8 + 1);
+        let mut width = 0;
+        for bit in bits {
+            if 0 == width % 8 {
+                bytes.push(0x0);
+            };
+            if bit {
+                bytes[width / 8] |= 128 >> (width % 8);
+            };
+            width += 1;
+        }
+        debug_assert!(Self::size_is_legal(width));
+        Self {
+            width,
+            score,
+            objectives: Vec::new(),
+            known: None,
+            bytes,
+        }
+    }
+
+    /// A sample with a solution's decisions, in the solutions' bit order
+    /// (as `util::put_bit` numbers them, like `Problem::sample_from_solution`),
+    /// which is *not* the order of `from_bits` and `set_bit`
+    ///
+    /// ```rust
+    /// use mhd_memory::util::get_bit;
+    /// use mhd_memory::{Sample, ScoreType};
+    ///
+    /// let decisions: Vec<bool> = (0..12).map(|index| 0 == index % 3).collect();
+    /// let sample = Sample::from_solution_bits(&decisions, 7 as ScoreType);
+    /// assert_eq!(sample.size(), 12);
+    /// assert_eq!(&sample.bytes[..], &[0b0100_1001, 0b0000_0010]);
+    /// assert!((0..12).all(|index| get_bit(&sample.bytes, index) == decisions[index]));
+    /// ```
+    pub fn from_solution_bits(bits: &[bool], score: ScoreType) -> Self {
+        let mut result = Self::new(bits.len(), score);
+        for (index, &bit) in bits.iter().enumerate() {
+            put_bit(&mut result.bytes, index, bit);
+        }
+        result
+    }
+
+    /// The bits, in order (`size()` of them, without the padding of the last byte)
+    #[inline]
+    pub fn iter_bits(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.width).map(move |index| self.get_bit(index))
+    }
+
+    /// The same sample, with a score per objective (e.g. profit and volume, in a bi-objective
+    /// knapsack problem). `score` stays the one score everything else uses (the memory's
+    /// min, max and total, the priorities...), so set it to the first objective, say,
+    /// or a weighted sum. `MhdMemory::masked_read_multi` predicts all objectives at once.
+    ///
+    /// ```rust
+    /// use mhd_memory::{MhdMemory, Sample, ScoreType};
+    ///
+    /// let (ten, three) = (10 as ScoreType, 3 as ScoreType);
+    /// let sample = Sample::new(16, ten).with_objectives(vec![ten, three]);
+    /// assert_eq!(sample.objectives(), &[ten, three]);
+    /// assert_eq!(Sample::new(16, ten).objectives(), &[ten]); // single objective
+    ///
+    /// let mut memory = MhdMemory::new(16);
+    /// memory.write_sample(&sample);
+    /// let mask = vec![0xFF; 2];
+    /// assert_eq!(memory.masked_read_multi(&mask, &sample.bytes), vec![ten, three]);
+    /// ```
+    pub fn with_objectives(mut self, objectives: Vec<ScoreType>) -> Self {
+        self.objectives = objectives;
+        self
+    }
+
+    /// The same sample, but only partially known: only the bits set in `known` are
+    /// (e.g. the decisions made so far in a partial solution). The unknown bits are cleared,
+    /// and reads ignore them, as if they were masked out. See `MhdMemory::write_masked_sample`.
+    ///
+    /// ```rust
+    /// use mhd_memory::{Sample, ScoreType};
+    ///
+    /// let partial = Sample::new_ones(16, 10 as ScoreType).with_known(&[0xFF, 0x0F]);
+    /// assert!(partial.is_partial());
+    /// assert_eq!(&partial.bytes[..], &[0xFF, 0x0F]);
+    /// assert!(!partial.get_bit(8) && partial.get_bit(15));
+    /// ```
+    pub fn with_known(mut self, known: &[u8]) -> Self {
+        debug_assert_eq!(known.len(), self.bytes.len());
+        for (byte, known_byte) in self.bytes.iter_mut().zip(known) {
+            *byte &= known_byte;
+        }
+        self.known = Some(SampleBytes::from(known));
+        self
+    }
+
+    /// Are some of the bits unknown? (see `with_known`)
+    #[inline]
+    pub fn is_partial(&self) -> bool {
+        self.known.is_some()
+    }
+
+    /// All the scores of this sample -- or just `score`, if it has no `objectives`
+    #[inline]
+    pub fn objectives(&self) -> &[ScoreType] {
+        if self.objectives.is_empty() {
+            std::slice::from_ref(&self.score)
+        } else {
+            &self.objectives
+        }
+    }
+
     #[inline]
     pub fn byte_index(bit_index: usize) -> usize {
         bit_index / 8
@@ -179,7 +321,27 @@ impl Sample {
 impl std::fmt::Debug for Sample {
     #[inline]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "[Sample: score {}, bytes{:x?}]", self.score, self.bytes)
+        if self.objectives.is_empty() {
+            write!(f, "[Sample: score {}, bytes{:x?}", self.score, self.bytes)?;
+        } else {
+            write!(
+                f,
+                "[Sample: score {}, objectives {:?}, bytes{:x?}",
+                self.score, self.objectives, self.bytes
+            )?;
+        };
+        if let Some(known) = &self.known {
+            write!(f, ", known{:x?}", known)?;
+        };
+        write!(f, "]")
+    }
+}
+
+/// The sample's bytes, e.g. for `distance_many`
+impl AsRef<[u8]> for Sample {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
     }
 }
 
@@ -210,7 +372,7 @@ mod tests {
         assert_eq!(r.score, ZERO_SCORE); // should be 0
         assert_eq!(r.size(), NUM_TEST_BITS);
 
-        let s = Sample::new(NUM_TEST_BITS, 42);
+        let s = Sample::new(NUM_TEST_BITS, 42 as ScoreType);
         assert_eq!(s.score, 42 as ScoreType); // should NOT be 0
         assert!(r.score != s.score);
         // assert!(r.bytes == s.bytes);
@@ -243,6 +405,32 @@ mod tests {
         assert!(!row1.get_bit(62));
     } // end test_methods
 
+    #[test]
+    fn test_from_bits() {
+        use util::get_bit;
+
+        // Whole bytes (random padding wouldn't survive the round trip)
+        let random = Sample::random(80);
+        let bits: Vec<bool> = random.iter_bits().collect();
+        assert_eq!(bits.len(), 80);
+        let copy = Sample::from_bits(bits.into_iter(), random.score);
+        assert_eq!(copy, random);
+
+        // Solution order: the same round trip through `util::get_bit`
+        let decisions: Vec<bool> = (0..80).map(|index| get_bit(&random.bytes, index)).collect();
+        assert_eq!(Sample::from_solution_bits(&decisions, random.score), random);
+
+        // The same as setting the bits one by one
+        const NUM_TEST_BITS: usize = 77;
+        let mut by_hand = Sample::new(NUM_TEST_BITS, ZERO_SCORE);
+        for index in (0..NUM_TEST_BITS).filter(|index| 0 == index % 5) {
+            by_hand.set_bit(index, true);
+        }
+        let from_bits = Sample::from_bits((0..NUM_TEST_BITS).map(|i| 0 == i % 5), ZERO_SCORE);
+        assert_eq!(from_bits, by_hand);
+        assert_eq!(from_bits.bytes.words(), by_hand.bytes.words());
+    }
+
     #[test]
     fn test_randomization() {
         // Note: This test could fail due to dumb luck.
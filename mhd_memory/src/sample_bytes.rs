@@ -0,0 +1,231 @@
+/// # The bytes of a sample, stored in words
+///
+/// `Sample::bytes` used to be a plain `Vec<u8>`, and a `Vec<u8>` is only guaranteed to be
+/// byte aligned. `distance_fast` compares whole (8 byte) words, and has to give up when its
+/// arguments are aligned differently -- which, for short samples, happened often.
+/// `SampleBytes` keeps the bytes in a `Vec<u64>` (the last word padded with zeros), so they
+/// always start on a word boundary, and can also be read as words (see `words`).
+/// The byte view is the standard `slice::align_to`, as in `util::as_words` -- no pointer
+/// casts of our own.
+///
+/// Otherwise it behaves like the `Vec<u8>` it replaces: it dereferences to `[u8]`
+/// (so `&sample.bytes` is a `&[u8]`, `sample.bytes[3]` a byte, etc.),
+/// compares with byte vectors and slices, and serializes as a byte vector.
+///
+/// ```rust
+/// use mhd_memory::{Sample, SampleBytes, ZERO_SCORE};
+///
+/// let mut bytes = SampleBytes::from(vec![0x0F; 12]);
+/// assert_eq!(bytes.len(), 12);
+/// assert_eq!(bytes.words().len(), 2); // 16 bytes, the last 4 unused
+/// bytes[11] = 0xFF;
+/// assert_eq!(bytes, vec![0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0xFF]);
+///
+/// let sample = Sample::new_ones(20, ZERO_SCORE);
+/// assert_eq!(&sample.bytes[..], &[0xFF, 0xFF, 0xFF]);
+/// assert_eq!(sample.bytes.as_ptr() as usize % 8, 0); // word aligned
+/// ```
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, DerefMut};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const WORD_BYTES: usize = std::mem::size_of::<u64>();
+
+#[derive(Default, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(from = "Vec<u8>", into = "Vec<u8>")
+)]
+pub struct SampleBytes {
+    words: Vec<u64>,
+    len: usize, // in bytes, at most WORD_BYTES * words.len()
+}
+
+impl SampleBytes {
+    #[inline]
+    fn words_for(num_bytes: usize) -> usize {
+        num_bytes.div_ceil(WORD_BYTES)
+    }
+
+    /// No bytes yet, but room for `capacity` of them
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            words: Vec::with_capacity(Self::words_for(capacity)),
+            len: 0,
+        }
+    }
+
+    /// `len` bytes, all equal to `value`
+    pub fn from_elem(value: u8, len: usize) -> Self {
+        let mut result = Self {
+            words: vec![0; Self::words_for(len)],
+            len,
+        };
+        if 0 != value {
+            result.fill(value);
+        };
+        result
+    }
+
+    /// The bytes as words, including the padding (zeros) at the end of the last word
+    #[inline]
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// The bytes as words, to change them a word at a time. Keep the padding at the end of
+    /// the last word zero: `words` (and so `distance_words`) reads it, too.
+    #[inline]
+    pub fn words_mut(&mut self) -> &mut [u64] {
+        &mut self.words
+    }
+
+    /// How many bytes fit without reallocating
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        WORD_BYTES * self.words.capacity()
+    }
+
+    /// Append a byte, as `Vec::push` does
+    pub fn push(&mut self, byte: u8) {
+        if self.len == WORD_BYTES * self.words.len() {
+            self.words.push(0);
+        };
+        self.len += 1;
+        let last = self.len - 1;
+        self[last] = byte;
+    }
+} // end impl SampleBytes
+
+impl Deref for SampleBytes {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        // SAFETY: the standard `align_to` (as in `util::as_words`), the other way round --
+        // which always works out: bytes need no alignment, and every word is 8 valid bytes
+        let (_, bytes, _) = unsafe { self.words.align_to::<u8>() };
+        &bytes[..self.len]
+    }
+}
+
+impl DerefMut for SampleBytes {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // SAFETY: see deref (and any 8 bytes written make a valid word again)
+        let (_, bytes, _) = unsafe { self.words.align_to_mut::<u8>() };
+        &mut bytes[..self.len]
+    }
+}
+
+impl AsRef<[u8]> for SampleBytes {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Borrow<[u8]> for SampleBytes {
+    #[inline]
+    fn borrow(&self) -> &[u8] {
+        self
+    }
+}
+
+impl From<&[u8]> for SampleBytes {
+    fn from(bytes: &[u8]) -> Self {
+        let mut result = Self::from_elem(0, bytes.len());
+        result.copy_from_slice(bytes);
+        result
+    }
+}
+
+impl From<Vec<u8>> for SampleBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::from(&bytes[..])
+    }
+}
+
+impl From<SampleBytes> for Vec<u8> {
+    fn from(bytes: SampleBytes) -> Self {
+        bytes.to_vec()
+    }
+}
+
+// Equality and hashing go by the bytes (not the padding), as for a Vec<u8>
+impl PartialEq for SampleBytes {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for SampleBytes {}
+
+impl PartialEq<[u8]> for SampleBytes {
+    #[inline]
+    fn eq(&self, other: &[u8]) -> bool {
+        **self == *other
+    }
+}
+
+impl<'a> PartialEq<&'a [u8]> for SampleBytes {
+    #[inline]
+    fn eq(&self, other: &&'a [u8]) -> bool {
+        **self == **other
+    }
+}
+
+impl PartialEq<Vec<u8>> for SampleBytes {
+    #[inline]
+    fn eq(&self, other: &Vec<u8>) -> bool {
+        **self == other[..]
+    }
+}
+
+impl Hash for SampleBytes {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl fmt::Debug for SampleBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+///////////////////// TESTs for SampleBytes /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_bytes() {
+        for len in 0..20usize {
+            let bytes: Vec<u8> = (0..len as u8).map(|b| b.wrapping_mul(37)).collect();
+            let sample_bytes = SampleBytes::from(bytes.clone());
+            assert_eq!(sample_bytes, bytes);
+            assert_eq!(sample_bytes.words().len(), len.div_ceil(8));
+            assert_eq!(sample_bytes.as_ptr() as usize % WORD_BYTES, 0);
+            assert_eq!(Vec::from(sample_bytes.clone()), bytes);
+            assert_eq!(format!("{:x?}", sample_bytes), format!("{:x?}", bytes));
+
+            // Built byte by byte: the same, padding and all
+            let mut pushed = SampleBytes::with_capacity(4);
+            for byte in bytes.iter() {
+                pushed.push(*byte);
+            }
+            assert_eq!(pushed, sample_bytes);
+            assert_eq!(pushed.words(), sample_bytes.words());
+        }
+        let ones = SampleBytes::from_elem(0xFF, 9);
+        assert_eq!(ones.words()[0], u64::MAX);
+        assert_eq!(ones.words()[1].count_ones(), 8); // one byte set, the padding not
+    }
+}
@@ -0,0 +1,207 @@
+/// # Scores
+///
+/// Every sample (and every solution, in the sister crate) has a score, of type `ScoreType`.
+/// That is `u32` by default, which suits most combinatorial problems -- but not problems
+/// with fractional or negative objective values, which would have to be scaled and shifted
+/// (and lose precision) to fit. So the score type can be chosen with a feature instead:
+///
+/// | feature | `ScoreType` |
+/// |--|--|
+/// | (none) | `u32` |
+/// | `score-i64` | `i64` |
+/// | `score-f64` | `f64` |
+///
+/// (The features are forwarded by `mhd_optimization`. They add up, like all features should:
+/// with both on, e.g. because two crates in one build ask for different ones, `f64` wins --
+/// it holds every `i64` score, if not always exactly.)
+/// Code which has to work with all of them uses the `Score` trait: it has no `Ord`
+/// (floats don't), so compare with `Score::cmp_scores`, and convert with `to_f64` and
+/// `from_f64` rather than `as`.
+///
+/// ```rust
+/// use mhd_memory::{Score, ScoreType, ZERO_SCORE};
+///
+/// let mut scores: Vec<ScoreType> = vec![ScoreType::from_f64(3.0), ZERO_SCORE, ScoreType::MAX];
+/// scores.sort_by(Score::cmp_scores);
+/// assert_eq!(scores[0], ZERO_SCORE);
+/// assert_eq!(scores[1].to_f64(), 3.0);
+/// assert_eq!(Score::max_of(scores.iter().cloned()), Some(ScoreType::MAX));
+/// ```
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::str::FromStr;
+
+#[cfg(not(any(feature = "score-i64", feature = "score-f64")))]
+pub type ScoreType = u32; // that can change at any time, so we give it a name
+#[cfg(all(feature = "score-i64", not(feature = "score-f64")))]
+pub type ScoreType = i64;
+#[cfg(feature = "score-f64")]
+pub type ScoreType = f64;
+
+pub const ZERO_SCORE: ScoreType = <ScoreType as Score>::ZERO;
+
+/// What every score type can do
+pub trait Score:
+    Copy
+    + Default
+    + PartialEq
+    + PartialOrd
+    + Debug
+    + Display
+    + FromStr
+    + Send
+    + Sync
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + AddAssign
+    + SubAssign
+    + Sum
+    + 'static
+{
+    const ZERO: Self;
+    const ONE: Self;
+    /// The largest score (infinity, for floats)
+    const MAX: Self;
+    /// The smallest score (zero, for unsigned types)
+    const MIN: Self;
+
+    fn to_f64(self) -> f64;
+
+    /// The closest score (rounded and saturated, for integer types)
+    fn from_f64(value: f64) -> Self;
+
+    /// A total order, also for floats (where NaN is larger than everything)
+    fn cmp_scores(&self, other: &Self) -> Ordering;
+
+    /// `self + other`, but at most `MAX` (instead of overflowing)
+    fn saturating_add(self, other: Self) -> Self;
+
+    /// The largest of `scores`, if there are any
+    fn max_of<I: Iterator<Item = Self>>(scores: I) -> Option<Self> {
+        scores.max_by(Self::cmp_scores)
+    }
+
+    /// The smallest of `scores`, if there are any
+    fn min_of<I: Iterator<Item = Self>>(scores: I) -> Option<Self> {
+        scores.min_by(Self::cmp_scores)
+    }
+}
+
+impl Score for u32 {
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+    const MAX: Self = u32::MAX;
+    const MIN: Self = 0;
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> Self {
+        value.round() as Self // (`as` saturates, and turns NaN into zero)
+    }
+
+    #[inline]
+    fn cmp_scores(&self, other: &Self) -> Ordering {
+        self.cmp(other)
+    }
+
+    #[inline]
+    fn saturating_add(self, other: Self) -> Self {
+        u32::saturating_add(self, other)
+    }
+}
+
+impl Score for i64 {
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+    const MAX: Self = i64::MAX;
+    const MIN: Self = i64::MIN;
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> Self {
+        value.round() as Self // (`as` saturates, and turns NaN into zero)
+    }
+
+    #[inline]
+    fn cmp_scores(&self, other: &Self) -> Ordering {
+        self.cmp(other)
+    }
+
+    #[inline]
+    fn saturating_add(self, other: Self) -> Self {
+        i64::saturating_add(self, other)
+    }
+}
+
+impl Score for f64 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+    const MAX: Self = f64::INFINITY;
+    const MIN: Self = f64::NEG_INFINITY;
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    #[inline]
+    fn cmp_scores(&self, other: &Self) -> Ordering {
+        self.total_cmp(other)
+    }
+
+    #[inline]
+    fn saturating_add(self, other: Self) -> Self {
+        self + other // (infinity is as far as it goes)
+    }
+}
+
+///////////////////// TESTs for Score /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_score<S: Score>() {
+        assert_eq!(S::ZERO + S::ONE, S::ONE);
+        assert_eq!(S::from_f64(2.0), S::ONE + S::ONE);
+        assert_eq!(S::from_f64(2.0).to_f64(), 2.0);
+        assert_eq!(S::ONE.cmp_scores(&S::ZERO), Ordering::Greater);
+        assert_eq!(S::MIN.cmp_scores(&S::MAX), Ordering::Less);
+        let scores = [S::ONE, S::MAX, S::ZERO];
+        assert_eq!(S::max_of(scores.iter().cloned()), Some(S::MAX));
+        assert_eq!(S::min_of(scores.iter().cloned()), Some(S::ZERO));
+        assert_eq!(S::max_of(Vec::new().into_iter()), None);
+        assert_eq!(S::MAX.saturating_add(S::ONE), S::MAX);
+        assert_eq!(
+            [S::ONE, S::ZERO, S::ONE].iter().cloned().sum::<S>(),
+            S::from_f64(2.0)
+        );
+    }
+
+    #[test]
+    fn test_score_types() {
+        check_score::<u32>();
+        check_score::<i64>();
+        check_score::<f64>();
+        check_score::<ScoreType>();
+        // Rounded and saturated
+        assert_eq!(u32::from_f64(-3.0), 0);
+        assert_eq!(u32::from_f64(2.6), 3);
+        assert_eq!(i64::from_f64(-2.6), -3);
+        assert_eq!(f64::from_f64(-2.6), -2.6);
+    }
+}
@@ -0,0 +1,251 @@
+/// # The Sharded MHD Memory
+///
+/// A `ShardedMemory` splits its samples into several `MhdMemory` shards: new samples go to
+/// the shards in turn (round robin), and a read adds up the partial sums of all shards
+/// (`MhdMemory::read_2_sums`, in parallel) before turning them into priorities.
+///
+/// Every shard has its own lock, so several threads can write (and read) at the same time,
+/// through a shared reference -- a writer only ever blocks one shard.
+///
+/// Note: the recency and score weighting (`SampleWeighting`) of each shard is relative to the
+/// samples in that shard. Without weighting, reads give the same priorities as one big memory.
+///
+/// ```rust
+/// use mhd_memory::{MhdMemory, Sample, ShardedMemory, ZERO_SCORE};
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let memory = Arc::new(ShardedMemory::new(64, 4));
+/// let writers: Vec<_> = (0..4)
+///     .map(|_| {
+///         let memory = Arc::clone(&memory);
+///         thread::spawn(move || {
+///             for _ in 0..25 {
+///                 memory.write_sample(&Sample::random(64));
+///             }
+///         })
+///     })
+///     .collect();
+/// for writer in writers {
+///     writer.join().unwrap();
+/// }
+/// assert_eq!(memory.num_samples(), 100); // (random duplicates are practically impossible)
+///
+/// let query = Sample::random(64);
+/// let mask = Sample::new_ones(64, ZERO_SCORE);
+/// let (prio_false, prio_true) = memory.read_2_priorities(&mask.bytes, &query.bytes, 0);
+/// assert!(0.0 <= prio_false && 0.0 <= prio_true);
+/// ```
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{RwLock, RwLockReadGuard};
+
+use rayon::prelude::*;
+
+use mhdmemory::{MhdMemory, PrioritySums};
+use sample::{Sample, Score, ScoreType, ZERO_SCORE};
+
+#[derive(Debug)]
+pub struct ShardedMemory {
+    width: usize,
+    shards: Vec<RwLock<MhdMemory>>,
+    next_shard: AtomicUsize, // round robin
+}
+
+impl ShardedMemory {
+    /// A memory for samples of `width` bits, split into `num_shards` shards
+    pub fn new(width: usize, num_shards: usize) -> Self {
+        assert!(0 < num_shards, "A sharded memory needs at least one shard");
+        Self {
+            width,
+            shards: (0..num_shards)
+                .map(|_| RwLock::new(MhdMemory::new(width)))
+                .collect(),
+            next_shard: AtomicUsize::new(0),
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub fn num_shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Read access to one shard (e.g. to look at its samples)
+    pub fn shard(&self, index: usize) -> RwLockReadGuard<'_, MhdMemory> {
+        self.shards[index].read().expect("Shard lock poisoned")
+    }
+
+    /// The number of samples in each shard
+    pub fn shard_sizes(&self) -> Vec<usize> {
+        (0..self.num_shards())
+            .map(|index| self.shard(index).num_samples())
+            .collect()
+    }
+
+    pub fn num_samples(&self) -> usize {
+        self.shard_sizes().iter().sum()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        0 == self.num_samples()
+    }
+
+    /// The best score in any shard
+    pub fn max_score(&self) -> ScoreType {
+        let max_scores = (0..self.num_shards())
+            .map(|index| self.shard(index))
+            .filter(|shard| !shard.is_empty())
+            .map(|shard| shard.max_score);
+        Score::max_of(max_scores).unwrap_or(ZERO_SCORE)
+    }
+
+    // Read access to all shards at once. The locks are taken here, and *not* in a rayon job:
+    // a rayon worker waiting for a lock could starve the writer holding it (which may itself
+    // be waiting for a rayon worker).
+    fn read_all(&self) -> Vec<RwLockReadGuard<'_, MhdMemory>> {
+        (0..self.num_shards())
+            .map(|index| self.shard(index))
+            .collect()
+    }
+
+    /// Is this sample (pattern) stored in any shard?
+    pub fn contains(&self, query: &Sample) -> bool {
+        self.read_all()
+            .par_iter()
+            .any(|shard| shard.search(query).is_some())
+    }
+
+    /// Store a new sample in the next shard (round robin); returns false if it was already
+    /// stored. Only one shard is locked for writing.
+    /// (Two threads writing the *same* new sample at the same time may both store it.)
+    pub fn write_sample(&self, new_sample: &Sample) -> bool {
+        if self.contains(new_sample) {
+            return false;
+        };
+        let index = self.next_shard.fetch_add(1, Ordering::Relaxed) % self.num_shards();
+        self.shards[index]
+            .write()
+            .expect("Shard lock poisoned")
+            .write_sample(new_sample)
+    }
+
+    /// Add up the partial sums of all shards, see `MhdMemory::read_2_sums`
+    pub fn read_2_sums(&self, mask: &[u8], query: &[u8], index: usize) -> PrioritySums {
+        self.read_all()
+            .par_iter() // RAYON!
+            .map(|shard| shard.read_2_sums(mask, query, index))
+            .reduce(PrioritySums::default, |a, b| a + b)
+    }
+
+    /// Just like `MhdMemory::read_2_priorities`, over all shards
+    pub fn read_2_priorities(&self, mask: &[u8], query: &[u8], index: usize) -> (f64, f64) {
+        self.read_2_sums(mask, query, index)
+            .priorities(self.max_score())
+    }
+
+    /// Just like `MhdMemory::read_sums`, over all shards
+    pub fn read_sums(&self, mask: &[u8], query: &[u8], indices: &[usize]) -> Vec<PrioritySums> {
+        self.read_all()
+            .par_iter() // RAYON!
+            .map(|shard| shard.read_sums(mask, query, indices))
+            .reduce(
+                || vec![PrioritySums::default(); indices.len()],
+                |a, b| a.into_iter().zip(b).map(|(a, b)| a + b).collect(),
+            )
+    }
+
+    /// Just like `MhdMemory::read_priorities`, over all shards
+    pub fn read_priorities(&self, mask: &[u8], query: &[u8], indices: &[usize]) -> Vec<(f64, f64)> {
+        let max_score = self.max_score();
+        self.read_sums(mask, query, indices)
+            .iter()
+            .map(|sums| sums.priorities(max_score))
+            .collect()
+    }
+
+    /// Just like `MhdMemory::read_and_decide`, over all shards
+    pub fn read_and_decide(
+        &self,
+        mask: &[u8],
+        query: &[u8],
+        index: usize,
+        full_monte: bool,
+    ) -> bool {
+        MhdMemory::decide(self.read_2_priorities(mask, query, index), full_monte)
+    }
+
+    /// Empty every shard (keeping each shard's configuration)
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.write().expect("Shard lock poisoned").clear();
+        }
+    }
+} // end impl ShardedMemory
+
+///////////////////// TESTs for ShardedMemory /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_robin_writes() {
+        const NUM_BITS: usize = 32;
+        let memory = ShardedMemory::new(NUM_BITS, 3);
+        assert!(memory.is_empty());
+        assert_eq!(memory.max_score(), ZERO_SCORE);
+
+        let samples: Vec<Sample> = (0..10).map(|_| Sample::random(NUM_BITS)).collect();
+        for sample in samples.iter() {
+            assert!(memory.write_sample(sample));
+        }
+        assert_eq!(memory.shard_sizes(), vec![4, 3, 3]);
+        // Duplicates are found in any shard
+        for sample in samples.iter() {
+            assert!(memory.contains(sample));
+            assert!(!memory.write_sample(sample));
+        }
+        assert_eq!(memory.num_samples(), 10);
+        let max_score = Score::max_of(samples.iter().map(|s| s.score)).unwrap();
+        assert_eq!(memory.max_score(), max_score);
+
+        memory.clear();
+        assert!(memory.is_empty());
+    }
+
+    #[test]
+    fn test_sharded_reads_match_one_memory() {
+        const NUM_BITS: usize = 40;
+        let sharded = ShardedMemory::new(NUM_BITS, 4);
+        let mut single = MhdMemory::new(NUM_BITS);
+        for _ in 0..200 {
+            let sample = Sample::random(NUM_BITS);
+            sharded.write_sample(&sample);
+            single.write_sample(&sample);
+        }
+        assert_eq!(sharded.num_samples(), single.num_samples());
+
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-9 * a.abs().max(1.0);
+        for index in 0..NUM_BITS {
+            let mask = Sample::random(NUM_BITS).bytes;
+            let query = Sample::random(NUM_BITS).bytes;
+            let one = single.read_2_priorities(&mask, &query, index);
+            let many = sharded.read_2_priorities(&mask, &query, index);
+            assert!(close(one.0, many.0) && close(one.1, many.1));
+        }
+
+        // All decisions at once
+        let mask = Sample::random(NUM_BITS).bytes;
+        let query = Sample::random(NUM_BITS).bytes;
+        let indices: Vec<usize> = (0..NUM_BITS).collect();
+        let batched = sharded.read_priorities(&mask, &query, &indices);
+        for (index, many) in indices.iter().zip(batched) {
+            let one = single.read_2_priorities(&mask, &query, *index);
+            assert!(close(one.0, many.0) && close(one.1, many.1));
+        }
+    }
+}
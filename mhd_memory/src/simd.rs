@@ -0,0 +1,302 @@
+/// # SIMD Backends for the Masked Hamming Distance
+///
+/// `distance` is the hot loop of every memory read. `distance_fast` counts bits with the
+/// scalar tree-merging trick (see `weight`), which is portable, but leaves most of a modern
+/// CPU idle. The backends here use its vector units instead:
+///
+/// * `Avx2`: 32 bytes at a time, counting the bits of each nibble with a table lookup
+///   (`vpshufb`), and adding up the bytes with `vpsadbw` (Muła's algorithm),
+/// * `Avx512`: 64 bytes at a time, with a native 64 bit popcount (AVX-512 VPOPCNTDQ),
+/// * `Neon`: 16 bytes at a time, with a native 8 bit popcount (`cnt`),
+/// * `Scalar`: the portable fallback, i.e. `distance_fast` (or the naive loop, if the
+///   arguments are not aligned alike).
+///
+/// `SimdBackend::best` detects the fastest backend the CPU supports at runtime (once), and
+/// `distance` uses it. The SIMD backends don't care about alignment. What doesn't fill a
+/// whole vector (the tail, or a short sample) is counted 64 bits at a time.
+///
+/// ```rust
+/// use mhd_memory::SimdBackend;
+///
+/// let mask = vec![0xF0; 1000];
+/// let x = vec![0xFF; 1000];
+/// let y = vec![0; 1000];
+/// for backend in SimdBackend::available() {
+///     assert_eq!(backend.distance(&mask, &x, &y), 4 * 1000);
+/// }
+/// assert!(SimdBackend::best().is_available());
+/// println!("distance uses {}", SimdBackend::best().name());
+/// ```
+use log::*;
+use std::convert::TryInto;
+use std::sync::OnceLock;
+
+use distance_::scalar_distance;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SimdBackend {
+    Scalar,
+    Avx2,
+    Avx512,
+    Neon,
+}
+
+impl SimdBackend {
+    /// All backends, slowest first
+    pub const ALL: [SimdBackend; 4] = [
+        SimdBackend::Scalar,
+        SimdBackend::Neon,
+        SimdBackend::Avx2,
+        SimdBackend::Avx512,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SimdBackend::Scalar => "scalar",
+            SimdBackend::Avx2 => "AVX2",
+            SimdBackend::Avx512 => "AVX-512 VPOPCNTDQ",
+            SimdBackend::Neon => "NEON",
+        }
+    }
+
+    /// Does this CPU support this backend? (`Scalar` is always available)
+    pub fn is_available(self) -> bool {
+        match self {
+            SimdBackend::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            SimdBackend::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(target_arch = "x86_64")]
+            SimdBackend::Avx512 => {
+                is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("avx512vpopcntdq")
+            }
+            #[cfg(target_arch = "aarch64")]
+            SimdBackend::Neon => std::arch::is_aarch64_feature_detected!("neon"),
+            #[allow(unreachable_patterns)] // (on some architectures, all are covered above)
+            _ => false,
+        }
+    }
+
+    /// The backends this CPU supports, slowest first
+    pub fn available() -> Vec<SimdBackend> {
+        Self::ALL
+            .iter()
+            .cloned()
+            .filter(|backend| backend.is_available())
+            .collect()
+    }
+
+    /// The fastest backend this CPU supports (detected once, at the first call)
+    #[inline]
+    pub fn best() -> SimdBackend {
+        static BEST: OnceLock<SimdBackend> = OnceLock::new();
+        *BEST.get_or_init(|| {
+            let best = *Self::available().last().unwrap_or(&SimdBackend::Scalar);
+            debug!("masked hamming distance uses the {} backend", best.name());
+            best
+        })
+    }
+
+    /// The masked hamming distance between `x` and `y`, computed by this backend
+    /// (or by `Scalar`, if this one is not available).
+    ///
+    /// # Panics
+    ///
+    /// Arguments must have the same length, or else `distance` panics.
+    pub fn distance(self, mask: &[u8], x: &[u8], y: &[u8]) -> u64 {
+        // The SIMD versions rely on this -- they read all three slices up to mask.len()
+        assert_eq!(x.len(), y.len());
+        assert_eq!(mask.len(), y.len());
+        if !self.is_available() {
+            return scalar_distance(mask, x, y);
+        };
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            SimdBackend::Avx2 => unsafe { x86::distance_avx2(mask, x, y) },
+            #[cfg(target_arch = "x86_64")]
+            SimdBackend::Avx512 => unsafe { x86::distance_avx512(mask, x, y) },
+            #[cfg(target_arch = "aarch64")]
+            SimdBackend::Neon => unsafe { neon::distance_neon(mask, x, y) },
+            _ => scalar_distance(mask, x, y),
+        }
+    }
+} // end impl SimdBackend
+
+// The tail, which doesn't fill a whole vector: 64 bits at a time, then byte by byte.
+// (Inlined, so it gets the target features -- e.g. popcnt -- of the SIMD function calling it.)
+#[inline(always)]
+#[allow(dead_code)] // (not used on architectures without a SIMD backend)
+fn tail_distance(mask: &[u8], x: &[u8], y: &[u8]) -> u64 {
+    let words = mask
+        .chunks_exact(8)
+        .zip(x.chunks_exact(8).zip(y.chunks_exact(8)));
+    let mut count: u64 = words
+        .map(|(m, (a, b))| {
+            let word = |bytes: &[u8]| u64::from_ne_bytes(bytes.try_into().unwrap());
+            (word(m) & (word(a) ^ word(b))).count_ones() as u64
+        })
+        .sum();
+    let rest = mask.len() - mask.len() % 8;
+    for index in rest..mask.len() {
+        count += (mask[index] & (x[index] ^ y[index])).count_ones() as u64;
+    }
+    count
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::tail_distance;
+
+    // A byte counts at most 8 bits, so 31 rounds of byte-wise sums can't overflow (248 < 256)
+    const ROUNDS_PER_SUM: usize = 31;
+
+    /// # Safety
+    ///
+    /// The CPU must support AVX2, and all three slices must have the same length.
+    #[target_feature(enable = "avx2,popcnt")]
+    pub(super) unsafe fn distance_avx2(mask: &[u8], x: &[u8], y: &[u8]) -> u64 {
+        const WIDTH: usize = 32;
+        let num_vectors = mask.len() / WIDTH;
+        #[rustfmt::skip]
+        let lookup = _mm256_setr_epi8(
+            0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4,
+            0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4,
+        );
+        let low_nibbles = _mm256_set1_epi8(0x0F);
+        let mut sums = _mm256_setzero_si256(); // four 64 bit sums
+
+        let mut vector = 0;
+        while vector < num_vectors {
+            let end = num_vectors.min(vector + ROUNDS_PER_SUM);
+            let mut byte_counts = _mm256_setzero_si256();
+            while vector < end {
+                let offset = vector * WIDTH;
+                let m = _mm256_loadu_si256(mask.as_ptr().add(offset) as *const __m256i);
+                let a = _mm256_loadu_si256(x.as_ptr().add(offset) as *const __m256i);
+                let b = _mm256_loadu_si256(y.as_ptr().add(offset) as *const __m256i);
+                let bits = _mm256_and_si256(m, _mm256_xor_si256(a, b));
+                let low = _mm256_and_si256(bits, low_nibbles);
+                let high = _mm256_and_si256(_mm256_srli_epi16(bits, 4), low_nibbles);
+                let counts = _mm256_add_epi8(
+                    _mm256_shuffle_epi8(lookup, low),
+                    _mm256_shuffle_epi8(lookup, high),
+                );
+                byte_counts = _mm256_add_epi8(byte_counts, counts);
+                vector += 1;
+            }
+            sums = _mm256_add_epi64(sums, _mm256_sad_epu8(byte_counts, _mm256_setzero_si256()));
+        }
+
+        let mut lanes = [0u64; 4];
+        _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sums);
+        let done = num_vectors * WIDTH;
+        lanes.iter().sum::<u64>() + tail_distance(&mask[done..], &x[done..], &y[done..])
+    }
+
+    /// # Safety
+    ///
+    /// The CPU must support AVX-512F and AVX-512 VPOPCNTDQ,
+    /// and all three slices must have the same length.
+    #[target_feature(enable = "avx512f,avx512vpopcntdq,popcnt")]
+    pub(super) unsafe fn distance_avx512(mask: &[u8], x: &[u8], y: &[u8]) -> u64 {
+        const WIDTH: usize = 64;
+        let num_vectors = mask.len() / WIDTH;
+        let mut sums = _mm512_setzero_si512(); // eight 64 bit sums
+        for vector in 0..num_vectors {
+            let offset = vector * WIDTH;
+            let m = _mm512_loadu_si512(mask.as_ptr().add(offset) as *const _);
+            let a = _mm512_loadu_si512(x.as_ptr().add(offset) as *const _);
+            let b = _mm512_loadu_si512(y.as_ptr().add(offset) as *const _);
+            let bits = _mm512_and_si512(m, _mm512_xor_si512(a, b));
+            sums = _mm512_add_epi64(sums, _mm512_popcnt_epi64(bits));
+        }
+        let done = num_vectors * WIDTH;
+        _mm512_reduce_add_epi64(sums) as u64 + tail_distance(&mask[done..], &x[done..], &y[done..])
+    }
+} // end mod x86
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use std::arch::aarch64::*;
+
+    use super::tail_distance;
+
+    // A byte counts at most 8 bits, so 31 rounds of byte-wise sums can't overflow (248 < 256)
+    const ROUNDS_PER_SUM: usize = 31;
+
+    /// # Safety
+    ///
+    /// The CPU must support NEON, and all three slices must have the same length.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn distance_neon(mask: &[u8], x: &[u8], y: &[u8]) -> u64 {
+        const WIDTH: usize = 16;
+        let num_vectors = mask.len() / WIDTH;
+        let mut sum: u64 = 0;
+
+        let mut vector = 0;
+        while vector < num_vectors {
+            let end = num_vectors.min(vector + ROUNDS_PER_SUM);
+            let mut byte_counts = vdupq_n_u8(0);
+            while vector < end {
+                let offset = vector * WIDTH;
+                let m = vld1q_u8(mask.as_ptr().add(offset));
+                let a = vld1q_u8(x.as_ptr().add(offset));
+                let b = vld1q_u8(y.as_ptr().add(offset));
+                let bits = vandq_u8(m, veorq_u8(a, b));
+                byte_counts = vaddq_u8(byte_counts, vcntq_u8(bits));
+                vector += 1;
+            }
+            sum += vaddlvq_u8(byte_counts) as u64;
+        }
+
+        let done = num_vectors * WIDTH;
+        sum + tail_distance(&mask[done..], &x[done..], &y[done..])
+    }
+} // end mod neon
+
+///////////////////// TESTs for SimdBackend /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use distance_::naive;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_backends_against_naive() {
+        let mut rng = thread_rng();
+        let available = SimdBackend::available();
+        assert_eq!(available[0], SimdBackend::Scalar);
+        assert_eq!(Some(&SimdBackend::best()), available.last());
+        // (Short and long slices, not multiples of any vector width, and misaligned)
+        for &len in [0, 1, 7, 8, 31, 33, 64, 100, 257, 2000, 10_007].iter() {
+            let mut random_bytes =
+                |len: usize| -> Vec<u8> { (0..len).map(|_| rng.gen()).collect() };
+            let mask = random_bytes(len + 3);
+            let x = random_bytes(len + 3);
+            let y = random_bytes(len + 3);
+            for offset in 0..4 {
+                let end = offset + len.saturating_sub(offset);
+                let (m, a, b) = (
+                    &mask[offset..end],
+                    &x[1..1 + end - offset],
+                    &y[..end - offset],
+                );
+                let expected = naive(m, a, b);
+                for backend in SimdBackend::ALL.iter() {
+                    // (backends which are not available fall back on Scalar)
+                    assert_eq!(backend.distance(m, a, b), expected, "{:?}", backend);
+                }
+            }
+        }
+        // Enough for byte-wise sums to overflow, if they were not added up in time
+        let ones = vec![0xFF; 64 * 1024];
+        let zeros = vec![0x00; ones.len()];
+        for backend in available.iter() {
+            assert_eq!(
+                backend.distance(&ones, &ones, &zeros),
+                8 * ones.len() as u64
+            );
+        }
+    }
+}
@@ -0,0 +1,129 @@
+/// # SimHash Sketches -- 64 bit fingerprints of samples
+///
+/// A sketch condenses a sample into one `u64`, so that similar samples have similar sketches:
+/// each sketch bit is the sign of a random ±1 projection of the sample's bits (read as ±1,
+/// too). Two samples which differ in `d` of `n` bits differ in about `64·θ/π` sketch bits,
+/// where `cos θ = 1 - 2d/n`. So one popcount compares two sketches, instead of a distance
+/// over all bytes -- an approximate, but very fast, filter for large memories
+/// (see `MhdMemory::use_sketches`). Identical samples always have identical sketches.
+///
+/// The projections are fixed (derived from the bit positions by a hash function),
+/// so sketches of the same width can be compared across memories and runs.
+/// Bit positions are the raw positions in the bytes (bit `p % 8` of byte `p / 8`).
+///
+/// ```rust
+/// use mhd_memory::sketch::{simhash, sketch_distance, sketch_radius};
+/// use mhd_memory::Sample;
+///
+/// let sample = Sample::random(256);
+/// let mut close = sample.clone();
+/// close.bytes[0] ^= 0b101; // two bits differ
+/// let far = Sample::random(256);
+///
+/// let sketch = simhash(&sample.bytes, 256);
+/// assert_eq!(sketch, simhash(&sample.clone().bytes, 256));
+/// assert!(sketch_distance(sketch, simhash(&close.bytes, 256)) <= sketch_radius(2, 256));
+/// let far_distance = sketch_distance(sketch, simhash(&far.bytes, 256));
+/// assert!(sketch_distance(sketch, simhash(&close.bytes, 256)) < far_distance);
+/// ```
+use std::f64::consts::PI;
+
+/// The projections' signs at one bit position: bit k is the sign of projection k there
+/// (SplitMix64, a cheap and well mixed hash)
+#[inline]
+fn projection_signs(position: usize) -> u64 {
+    let mut z = (position as u64)
+        .wrapping_add(1)
+        .wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// The sketch of the first `width` bits of `bytes`
+pub fn simhash(bytes: &[u8], width: usize) -> u64 {
+    assert!(width <= 8 * bytes.len());
+    let mut sums = [0i64; 64]; // one projection per sketch bit
+    for position in 0..width {
+        let bit = 0 != bytes[position / 8] & (1 << (position % 8));
+        // where the projection's sign matches the bit, add one; elsewhere subtract one
+        let agree = if bit {
+            projection_signs(position)
+        } else {
+            !projection_signs(position)
+        };
+        for (k, sum) in sums.iter_mut().enumerate() {
+            *sum += if 0 != agree & (1 << k) { 1 } else { -1 };
+        }
+    }
+    sums.iter()
+        .enumerate()
+        .filter(|(_, sum)| 0 < **sum)
+        .fold(0, |sketch, (k, _)| sketch | (1 << k))
+}
+
+/// The number of sketch bits which differ
+#[inline]
+pub fn sketch_distance(left: u64, right: u64) -> u32 {
+    (left ^ right).count_ones()
+}
+
+/// The largest sketch distance to expect between samples (of `width` bits) at most `radius`
+/// bits apart: the expected sketch distance plus three standard deviations.
+/// Samples farther apart in their sketches are (very probably) farther apart in their bits.
+pub fn sketch_radius(radius: u64, width: usize) -> u32 {
+    if width as u64 <= radius {
+        return 64;
+    };
+    let fraction = radius as f64 / width as f64;
+    let probability = (1.0 - 2.0 * fraction).acos() / PI;
+    let expected = 64.0 * probability;
+    let deviation = (64.0 * probability * (1.0 - probability)).sqrt();
+    (expected + 3.0 * deviation + 1.0).ceil().min(64.0) as u32
+}
+
+///////////////////// TESTs for SimHash Sketches /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use distance_::distance;
+    use sample::{Sample, ZERO_SCORE};
+
+    #[test]
+    fn test_sketches() {
+        const NUM_BITS: usize = 500;
+        let mask = vec![0xFF; Sample::new(NUM_BITS, ZERO_SCORE).size_in_bytes()];
+        assert_eq!(sketch_radius(0, NUM_BITS), 1);
+        assert_eq!(sketch_radius(NUM_BITS as u64, NUM_BITS), 64);
+        assert!(sketch_radius(10, NUM_BITS) < sketch_radius(100, NUM_BITS));
+
+        let sample = Sample::random(NUM_BITS);
+        let sketch = simhash(&sample.bytes, NUM_BITS);
+        // Random samples differ in about half their sketch bits
+        let far: u32 = (0..20)
+            .map(|_| sketch_distance(sketch, simhash(&Sample::random(NUM_BITS).bytes, NUM_BITS)))
+            .sum();
+        assert!(15 * 20 < far && far < 49 * 20);
+
+        // Close samples are (almost always) within the sketch radius
+        let mut misses = 0;
+        for flips in 1..=50 {
+            let mut close = sample.clone();
+            for position in 0..flips {
+                close.bytes[position / 8] ^= 1 << (position % 8);
+            }
+            let dist = distance(&mask, &sample.bytes, &close.bytes);
+            assert_eq!(dist, flips as u64);
+            let sketch_dist = sketch_distance(sketch, simhash(&close.bytes, NUM_BITS));
+            if sketch_radius(dist, NUM_BITS) < sketch_dist {
+                misses += 1;
+            };
+        }
+        assert!(misses <= 2);
+
+        // Bits beyond the width don't count
+        let mut padded = sample.clone();
+        padded.bytes.push(0xFF);
+        assert_eq!(simhash(&padded.bytes, NUM_BITS), sketch);
+    }
+}
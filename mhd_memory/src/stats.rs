@@ -0,0 +1,237 @@
+/// # Memory statistics
+///
+/// Is a memory any good? `MhdMemory::stats` collects what tells: how its scores are spread,
+/// how far apart its samples are (from a sample of the pairs, for large memories), how often
+/// each bit is set (a bit which is always or never set tells the memory nothing), and how
+/// many samples are duplicates. Print it for a summary.
+///
+/// ```rust
+/// use mhd_memory::MhdMemory;
+///
+/// let mut memory = MhdMemory::new(32);
+/// memory.write_n_random_samples(100);
+/// let stats = memory.stats();
+/// assert_eq!(stats.num_samples, 100);
+/// assert_eq!(stats.score_histogram.iter().sum::<usize>(), 100);
+/// assert_eq!(stats.bit_fill.len(), 32);
+/// assert_eq!(stats.duplicates, 0);
+/// println!("{}", stats);
+/// ```
+use rand::Rng;
+use std::collections::HashSet;
+use std::fmt;
+
+use mhdmemory::MhdMemory;
+use sample::{Sample, ScoreType, ZERO_SCORE};
+use score::Score;
+
+/// Number of bins of `MemoryStats::score_histogram`
+pub const SCORE_BINS: usize = 10;
+/// The most pairs of samples whose distances `MemoryStats::distance_histogram` counts
+/// (with more samples, the pairs are chosen at random)
+pub const MAX_DISTANCE_PAIRS: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryStats {
+    pub width: usize,
+    pub num_samples: usize,
+    pub min_score: ScoreType,
+    pub max_score: ScoreType,
+    /// Number of samples per score bin: `SCORE_BINS` bins of equal width,
+    /// from `min_score` to `max_score`
+    pub score_histogram: Vec<usize>,
+    /// Number of pairs of samples at each distance (0 to `width`),
+    /// counted over `num_pairs` pairs
+    pub distance_histogram: Vec<usize>,
+    pub num_pairs: usize,
+    /// The fraction of samples with each bit set
+    pub bit_fill: Vec<f64>,
+    /// Number of samples with the same bits as an earlier one
+    /// (`write_sample` never stores those, but they can be pushed into `samples` directly)
+    pub duplicates: usize,
+}
+
+impl MemoryStats {
+    /// The average distance between two samples (of the pairs counted)
+    pub fn mean_distance(&self) -> f64 {
+        if 0 == self.num_pairs {
+            return 0.0;
+        };
+        let sum: usize = self
+            .distance_histogram
+            .iter()
+            .enumerate()
+            .map(|(distance, count)| distance * count)
+            .sum();
+        sum as f64 / self.num_pairs as f64
+    }
+
+    /// Number of bits which are set in all samples, or in none
+    pub fn constant_bits(&self) -> usize {
+        self.bit_fill
+            .iter()
+            .filter(|fill| 0.0 == **fill || 1.0 == **fill)
+            .count()
+    }
+}
+
+impl fmt::Display for MemoryStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} samples of {} bits, {} duplicates",
+            self.num_samples, self.width, self.duplicates
+        )?;
+        if 0 == self.num_samples {
+            return Ok(());
+        };
+        writeln!(
+            f,
+            "scores {} to {}: {:?}",
+            self.min_score, self.max_score, self.score_histogram
+        )?;
+        writeln!(
+            f,
+            "distances (of {} pairs) average {:.2}: {:?}",
+            self.num_pairs,
+            self.mean_distance(),
+            self.distance_histogram
+        )?;
+        let fill: Vec<String> = self
+            .bit_fill
+            .iter()
+            .map(|fill| format!("{:.2}", fill))
+            .collect();
+        write!(
+            f,
+            "bit fill ({} constant): [{}]",
+            self.constant_bits(),
+            fill.join(", ")
+        )
+    }
+}
+
+impl MhdMemory {
+    /// Statistics about the samples, see `MemoryStats`
+    pub fn stats(&self) -> MemoryStats {
+        let num_samples = self.num_samples();
+
+        let mut score_histogram = vec![0; SCORE_BINS];
+        let range = (self.max_score - self.min_score).to_f64();
+        for sample in self.samples.iter() {
+            let offset = (sample.score - self.min_score).to_f64();
+            let bin = if 0.0 < range {
+                ((offset / range) * SCORE_BINS as f64) as usize
+            } else {
+                0
+            };
+            score_histogram[bin.min(SCORE_BINS - 1)] += 1;
+        }
+
+        // Distances over all bits (and only those, not the padding)
+        let mask = Sample::from_bits((0..self.width).map(|_| true), ZERO_SCORE);
+        let all_pairs = num_samples * num_samples.saturating_sub(1) / 2;
+        let pairs: Vec<(usize, usize)> = if all_pairs <= MAX_DISTANCE_PAIRS {
+            (0..num_samples)
+                .flat_map(|i| (0..i).map(move |j| (i, j)))
+                .collect()
+        } else {
+            let mut rng = rand::thread_rng();
+            (0..MAX_DISTANCE_PAIRS)
+                .map(|_| {
+                    let i = rng.gen_range(1..num_samples);
+                    (i, rng.gen_range(0..i))
+                })
+                .collect()
+        };
+        let mut distance_histogram = vec![0; self.width + 1];
+        for (i, j) in pairs.iter() {
+            let distance = self.metric_distance(
+                &mask.bytes,
+                &self.samples[*i].bytes,
+                &self.samples[*j].bytes,
+            );
+            distance_histogram[(distance as usize).min(self.width)] += 1;
+        }
+
+        let bit_fill = (0..self.width)
+            .map(|bit| {
+                let set = self.samples.iter().filter(|s| s.get_bit(bit)).count();
+                set as f64 / num_samples.max(1) as f64
+            })
+            .collect();
+
+        let mut seen = HashSet::new();
+        let duplicates = self
+            .samples
+            .iter()
+            .filter(|sample| !seen.insert(&sample.bytes))
+            .count();
+
+        MemoryStats {
+            width: self.width,
+            num_samples,
+            min_score: self.min_score,
+            max_score: self.max_score,
+            score_histogram,
+            distance_histogram,
+            num_pairs: pairs.len(),
+            bit_fill,
+            duplicates,
+        }
+    }
+} // end impl MhdMemory
+
+///////////////////// TESTs for MemoryStats /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        const NUM_BITS: usize = 12;
+        let mut memory = MhdMemory::new(NUM_BITS);
+        let empty = memory.stats();
+        assert_eq!((empty.num_samples, empty.num_pairs), (0, 0));
+        assert_eq!(empty.mean_distance(), 0.0);
+        assert!(!empty.to_string().is_empty());
+
+        // Three samples: all zeros, all ones, and the first bit set
+        let mut first = Sample::new(NUM_BITS, 50 as ScoreType);
+        first.set_bit(0, true);
+        memory.write_sample(&Sample::new(NUM_BITS, ZERO_SCORE));
+        memory.write_sample(&Sample::new_ones(NUM_BITS, 100 as ScoreType));
+        memory.write_sample(&first);
+        let stats = memory.stats();
+        assert_eq!(stats.score_histogram[0], 1);
+        assert_eq!(stats.score_histogram[SCORE_BINS / 2], 1);
+        assert_eq!(stats.score_histogram[SCORE_BINS - 1], 1);
+        assert_eq!(stats.num_pairs, 3);
+        let mut distances = vec![0; NUM_BITS + 1];
+        distances[1] = 1; // zeros and first
+        distances[NUM_BITS - 1] = 1; // ones and first
+        distances[NUM_BITS] = 1; // zeros and ones
+        assert_eq!(stats.distance_histogram, distances);
+        assert_eq!(stats.mean_distance(), (1 + 11 + 12) as f64 / 3.0);
+        assert_eq!(stats.bit_fill[0], 2.0 / 3.0);
+        assert_eq!(stats.bit_fill[1], 1.0 / 3.0);
+        assert_eq!(stats.constant_bits(), 0);
+        assert_eq!(stats.duplicates, 0);
+
+        // A duplicate pushed directly
+        memory.samples.push(first.clone());
+        assert_eq!(memory.stats().duplicates, 1);
+
+        // Many samples: only some of the pairs
+        let mut large = MhdMemory::new(64);
+        large.write_n_random_samples(200);
+        let stats = large.stats();
+        assert_eq!(stats.num_pairs, MAX_DISTANCE_PAIRS);
+        assert_eq!(
+            stats.distance_histogram.iter().sum::<usize>(),
+            MAX_DISTANCE_PAIRS
+        );
+        assert!(20.0 < stats.mean_distance() && stats.mean_distance() < 44.0);
+        assert!(stats.to_string().contains("200 samples of 64 bits"));
+    }
+}
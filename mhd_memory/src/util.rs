@@ -1,5 +1,3 @@
-use core::{mem, slice};
-
 #[inline]
 pub fn get_bit(bytes: &[u8], bit_index: usize) -> bool {
     let byte_index = bit_index / 8;
@@ -25,44 +23,71 @@ pub fn put_bit(bytes: &mut [u8], bit_index: usize, new_value: bool) {
     } // bitwise OR -- sets bit, maybe
 }
 
-/// Reinterpret as much of `x` as a slice of (correctly aligned) `U`s
-/// as possible. (Same as `slice::align_to` but available in earlier
-/// compilers.)
-/// # Safety
-/// Tricky, this...
-#[inline(never)] // critical for autovectorization in `weight`.
-pub unsafe fn align_to<T, U>(x: &[T]) -> (&[T], &[U], &[T]) {
-    let orig_size = mem::size_of::<T>();
-    let size = mem::size_of::<U>();
-
-    debug_assert!(orig_size < size);
-    debug_assert!(size % orig_size == 0);
-    let size_ratio = size / orig_size;
-
-    let alignment = mem::align_of::<U>();
-
-    let ptr = x.as_ptr() as usize;
-    // round up to the nearest multiple
-    let aligned = (ptr + alignment - 1) / alignment * alignment;
-    let byte_distance = aligned - ptr;
-
-    // can't fit a single U in
-    if mem::size_of_val(x) < size + byte_distance {
-        return (x, &[], &[]);
-    }
+/// The indices of the bits which are set in `bytes` (numbered as by `get_bit`), in ascending
+/// order. The bytes are scanned a word at a time, so long runs of clear bits cost next to
+/// nothing.
+///
+/// ```rust
+/// use mhd_memory::util::iter_set_bits;
+///
+/// let bits: Vec<usize> = iter_set_bits(&[0x81, 0x00, 0x04]).collect();
+/// assert_eq!(bits, vec![0, 7, 18]);
+/// ```
+#[inline]
+pub fn iter_set_bits(bytes: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    iter_bits(bytes, 0)
+}
 
-    let (head, middle) = x.split_at(byte_distance / orig_size);
+/// The indices of the bits which are clear in `mask`, in ascending order -- e.g. the open
+/// decisions of a solution, whose mask has a bit set for every decision made. That includes
+/// any padding bits after the last decision, so stop at the number of decisions.
+///
+/// ```rust
+/// use mhd_memory::util::iter_open_bits;
+///
+/// let open: Vec<usize> = iter_open_bits(&[0xFE, 0xFF]).collect();
+/// assert_eq!(open, vec![0]);
+/// assert_eq!(iter_open_bits(&[0xFF, 0x7F]).next(), Some(15));
+/// ```
+#[inline]
+pub fn iter_open_bits(mask: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    iter_bits(mask, !0)
+}
 
-    debug_assert!(middle.as_ptr() as usize % alignment == 0);
-    let cast_middle = slice::from_raw_parts(middle.as_ptr() as *const U, middle.len() / size_ratio);
-    let tail = &middle[cast_middle.len() * size_ratio..];
+// The indices of the set bits of `bytes` xor `flip`, a word at a time
+fn iter_bits(bytes: &[u8], flip: u64) -> impl Iterator<Item = usize> + '_ {
+    bytes.chunks(8).enumerate().flat_map(move |(index, chunk)| {
+        let mut word_bytes = [0u8; 8];
+        word_bytes[..chunk.len()].copy_from_slice(chunk);
+        let mut word = u64::from_le_bytes(word_bytes) ^ flip;
+        if chunk.len() < 8 {
+            word &= (1u64 << (8 * chunk.len())) - 1; // not beyond the last byte
+        };
+        std::iter::from_fn(move || {
+            if 0 == word {
+                return None;
+            };
+            let bit = word.trailing_zeros() as usize;
+            word &= word - 1; // clear the lowest one
+            Some(index * 64 + bit)
+        })
+    })
+}
 
-    (head, cast_middle, tail)
+/// Split `x` into as many (correctly aligned) 64-bit words as possible, and the bytes
+/// before and after them -- that is, `slice::align_to::<u64>`, which is safe for words:
+/// any eight bytes make a valid `u64`. Two slices with the same 8-byte alignment (and
+/// length) are split alike.
+#[inline]
+pub fn as_words(x: &[u8]) -> (&[u8], &[u64], &[u8]) {
+    // SAFETY: every bit pattern is a valid u64 (and align_to respects its alignment)
+    unsafe { x.align_to::<u64>() }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use sample_bytes::SampleBytes;
 
     #[test]
     fn test_get_and_put_bit() {
@@ -88,89 +113,60 @@ mod tests {
         assert!(!get_bit(&buncha_bits, 63));
     }
 
-    fn align_to_test(
-        from: usize,
-        to: usize,
-        true_head: &[u8],
-        true_le_middle: &[u32],
-        true_tail: &[u8],
-    ) {
-        let true_middle = true_le_middle
-            .iter()
-            .map(|x| u32::from_le(*x))
-            .collect::<Vec<_>>();
-
-        let array_and_tuple = (0u64, [0u8, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
-        let array = &array_and_tuple.1;
-        // the array should be aligned appropriately
-        assert!((array.as_ptr() as usize) % 4 == 0);
-
-        let (head, middle, tail) = unsafe { align_to::<_, u32>(&array[from..to]) };
-        assert_eq!(head, true_head);
-        assert_eq!(middle, true_middle.as_slice());
-        assert_eq!(tail, true_tail);
-    }
-
-    #[test]
-    fn align_to_empty() {
-        align_to_test(0, 0, &[], &[], &[]);
-        align_to_test(1, 1, &[], &[], &[]);
-        align_to_test(2, 2, &[], &[], &[]);
-        align_to_test(3, 3, &[], &[], &[]);
-    }
-
     #[test]
-    fn align_to_short() {
-        align_to_test(0, 1, &[0], &[], &[]);
-        align_to_test(1, 2, &[1], &[], &[]);
-        align_to_test(2, 3, &[2], &[], &[]);
-        align_to_test(3, 4, &[3], &[], &[]);
-
-        align_to_test(0, 2, &[0, 1], &[], &[]);
-        align_to_test(1, 3, &[1, 2], &[], &[]);
-        align_to_test(2, 4, &[2, 3], &[], &[]);
-        align_to_test(3, 5, &[3, 4], &[], &[]);
-
-        align_to_test(0, 3, &[0, 1, 2], &[], &[]);
-        align_to_test(1, 4, &[1, 2, 3], &[], &[]);
-        align_to_test(2, 5, &[2, 3, 4], &[], &[]);
-        align_to_test(3, 6, &[3, 4, 5], &[], &[]);
+    fn test_iter_bits() {
+        let bytes: Vec<u8> = (0..19).map(|i| (i * 37 + 11) as u8).collect();
+        let set: Vec<usize> = (0..8 * bytes.len())
+            .filter(|bit| get_bit(&bytes, *bit))
+            .collect();
+        let clear: Vec<usize> = (0..8 * bytes.len())
+            .filter(|bit| !get_bit(&bytes, *bit))
+            .collect();
+        assert_eq!(iter_set_bits(&bytes).collect::<Vec<_>>(), set);
+        assert_eq!(iter_open_bits(&bytes).collect::<Vec<_>>(), clear);
+        assert_eq!(iter_set_bits(&[]).count(), 0);
+        assert_eq!(iter_open_bits(&[0xFF; 9]).count(), 0);
+        assert_eq!(iter_open_bits(&[0x00; 3]).count(), 24);
     }
 
-    #[test]
-    fn align_to_exact() {
-        align_to_test(0, 4, &[], &[0x03020100], &[]);
-        align_to_test(0, 8, &[], &[0x03020100, 0x07060504], &[]);
+    // Split bytes from..to of sixteen word-aligned bytes (0, 1, ..., 15)
+    fn as_words_test(from: usize, to: usize, true_head: &[u8], num_words: usize, true_tail: &[u8]) {
+        let bytes: Vec<u8> = (0..16).collect();
+        let array = SampleBytes::from(&bytes[..]);
+        let (head, middle, tail) = as_words(&array[from..to]);
+        assert_eq!(head, true_head);
+        assert_eq!(middle.len(), num_words);
+        assert_eq!(tail, true_tail);
+        let middle_bytes: Vec<u8> = middle.iter().flat_map(|w| w.to_ne_bytes()).collect();
+        assert_eq!(
+            &middle_bytes[..],
+            &bytes[from + head.len()..to - tail.len()]
+        );
     }
 
     #[test]
-    fn align_to_offset() {
-        align_to_test(1, 5, &[1, 2, 3, 4], &[], &[]);
-        align_to_test(2, 6, &[2, 3, 4, 5], &[], &[]);
-        align_to_test(3, 7, &[3, 4, 5, 6], &[], &[]);
-        align_to_test(1, 7, &[1, 2, 3, 4, 5, 6], &[], &[]);
+    fn as_words_empty() {
+        as_words_test(0, 0, &[], 0, &[]);
+        as_words_test(3, 3, &[], 0, &[]);
     }
 
     #[test]
-    fn align_to_overlap() {
-        align_to_test(0, 10, &[], &[0x03020100, 0x07060504], &[8, 9]);
-        align_to_test(0, 5, &[], &[0x03020100], &[4]);
-
-        align_to_test(1, 8, &[1, 2, 3], &[0x07060504], &[]);
-        align_to_test(3, 9, &[3], &[0x07060504], &[8]);
+    fn as_words_short() {
+        as_words_test(0, 3, &[], 0, &[0, 1, 2]);
+        as_words_test(1, 7, &[1, 2, 3, 4, 5, 6], 0, &[]);
+        as_words_test(1, 9, &[1, 2, 3, 4, 5, 6, 7], 0, &[8]);
     }
 
-    #[cfg_attr(not(debug_assertions), ignore)]
     #[test]
-    #[should_panic]
-    fn align_to_smaller() {
-        let _ = unsafe { align_to::<u64, u8>(&[]) };
+    fn as_words_exact() {
+        as_words_test(0, 8, &[], 1, &[]);
+        as_words_test(0, 16, &[], 2, &[]);
     }
 
-    #[cfg_attr(not(debug_assertions), ignore)]
     #[test]
-    #[should_panic]
-    fn align_to_nondivisible() {
-        let _ = unsafe { align_to::<[u8; 2], [u8; 3]>(&[]) };
+    fn as_words_overlap() {
+        as_words_test(0, 10, &[], 1, &[8, 9]);
+        as_words_test(3, 16, &[3, 4, 5, 6, 7], 1, &[]);
+        as_words_test(7, 15, &[7], 0, &[8, 9, 10, 11, 12, 13, 14]);
     }
 }
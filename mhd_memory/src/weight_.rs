@@ -1,6 +1,58 @@
+use std::convert::TryFrom;
+
+use util::as_words;
+
 fn naive(x: &[u8]) -> u64 {
     x.iter().fold(0, |a, b| a + b.count_ones() as u64)
 }
+
+fn masked_naive(mask: &[u8], x: &[u8]) -> u64 {
+    mask.iter()
+        .zip(x)
+        .fold(0, |a, (m, b)| a + (*m & *b).count_ones() as u64)
+}
+
+const M1: u64 = 0x5555555555555555;
+const M2: u64 = 0x3333333333333333;
+const M4: u64 = 0x0F0F0F0F0F0F0F0F;
+const M8: u64 = 0x00FF00FF00FF00FF;
+
+type T30 = [u64; 30];
+
+// The words in groups of thirty, and the words left over
+#[inline]
+pub(crate) fn thirties(words: &[u64]) -> (impl Iterator<Item = &T30>, &[u64]) {
+    let chunks = words.chunks_exact(30);
+    let rest = chunks.remainder();
+    (chunks.map(|chunk| <&T30>::try_from(chunk).unwrap()), rest)
+}
+
+// The population count of thirty words (word(0) to word(29)), tree-merged, see `weight`
+#[inline(always)]
+pub(crate) fn weight_of_thirty<F: Fn(usize) -> u64>(word: F) -> u64 {
+    let mut acc = 0;
+    for j_ in 0..10 {
+        let j = j_ * 3;
+        let mut count1 = word(j);
+        let mut count2 = word(j + 1);
+        let mut half1 = word(j + 2);
+        let mut half2 = half1;
+        half1 &= M1;
+        half2 = (half2 >> 1) & M1;
+        count1 -= (count1 >> 1) & M1;
+        count2 -= (count2 >> 1) & M1;
+        count1 += half1;
+        count2 += half2;
+        count1 = (count1 & M2) + ((count1 >> 2) & M2);
+        count1 += (count2 & M2) + ((count2 >> 2) & M2);
+        acc += (count1 & M4) + ((count1 >> 4) & M4);
+    }
+    acc = (acc & M8) + ((acc >> 8) & M8);
+    acc = acc + (acc >> 16);
+    acc = acc + (acc >> 32);
+    acc & 0xFFFF
+}
+
 /// Computes the [Hamming
 /// weight](https://en.wikipedia.org/wiki/Hamming_weight) of `x`, that
 /// is, the population count, or number of 1.
@@ -37,37 +89,60 @@ fn naive(x: &[u8]) -> u64 {
 /// assert_eq!( mhd_memory::weight(&[1, 0xFF, 1, 0xFF]), 1 + 8 + 1 + 8);
 /// ```
 pub fn weight(x: &[u8]) -> u64 {
-    const M1: u64 = 0x5555555555555555;
-    const M2: u64 = 0x3333333333333333;
-    const M4: u64 = 0x0F0F0F0F0F0F0F0F;
-    const M8: u64 = 0x00FF00FF00FF00FF;
-
-    type T30 = [u64; 30];
-    let (head, thirty, tail) = unsafe { super::util::align_to::<_, T30>(x) };
+    let (head, words, tail) = as_words(x);
+    let (thirty, rest) = thirties(words);
 
     let mut count = naive(head) + naive(tail);
+    count += rest
+        .iter()
+        .map(|word| word.count_ones() as u64)
+        .sum::<u64>();
     for array in thirty {
-        let mut acc = 0;
-        for j_ in 0..10 {
-            let j = j_ * 3;
-            let mut count1 = array[j];
-            let mut count2 = array[j + 1];
-            let mut half1 = array[j + 2];
-            let mut half2 = half1;
-            half1 &= M1;
-            half2 = (half2 >> 1) & M1;
-            count1 -= (count1 >> 1) & M1;
-            count2 -= (count2 >> 1) & M1;
-            count1 += half1;
-            count2 += half2;
-            count1 = (count1 & M2) + ((count1 >> 2) & M2);
-            count1 += (count2 & M2) + ((count2 >> 2) & M2);
-            acc += (count1 & M4) + ((count1 >> 4) & M4);
-        }
-        acc = (acc & M8) + ((acc >> 8) & M8);
-        acc = acc + (acc >> 16);
-        acc = acc + (acc >> 32);
-        count += acc & 0xFFFF;
+        count += weight_of_thirty(|j| array[j]);
+    }
+    count
+}
+
+/// Computes the Hamming weight of `x` under the mask, that is, the number of bits
+/// which are 1 in both `x` and `mask` -- e.g. how many of the decisions made so far
+/// (the mask) were decided for true (`x`).
+///
+/// This is as fast as `weight` if `mask` and `x` have the same 8-byte alignment (as the
+/// bytes of two `Sample`s always have, see `SampleBytes`), and falls back to the naive
+/// version otherwise:
+///
+/// ```rust
+/// fn naive(mask: &[u8], x: &[u8]) -> u64 {
+///     mask.iter().zip(x).fold(0, |a, (m, b)| a + (*m & *b).count_ones() as u64)
+/// }
+/// ```
+///
+/// # Panics
+///
+/// `mask` and `x` must have the same length, or else `masked_weight` panics.
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(mhd_memory::masked_weight(&[0x0F, 0xFF], &[0xFF, 0x81]), 4 + 2);
+/// ```
+pub fn masked_weight(mask: &[u8], x: &[u8]) -> u64 {
+    assert_eq!(mask.len(), x.len());
+    let (head0, words0, tail0) = as_words(mask);
+    let (head1, words1, tail1) = as_words(x);
+    if head0.len() != head1.len() {
+        return masked_naive(mask, x);
+    };
+    let ((thirty0, rest0), (thirty1, rest1)) = (thirties(words0), thirties(words1));
+
+    let mut count = masked_naive(head0, head1) + masked_naive(tail0, tail1);
+    count += rest0
+        .iter()
+        .zip(rest1)
+        .map(|(m, word)| (m & word).count_ones() as u64)
+        .sum::<u64>();
+    for (mask_array, array) in thirty0.zip(thirty1) {
+        count += weight_of_thirty(|j| mask_array[j] & array[j]);
     }
     count
 }
@@ -106,6 +181,25 @@ mod tests {
     }
     **************************************************************/
 
+    #[test]
+    fn masked_weight_smoke() {
+        let mask: Vec<u8> = (0..1000).map(|i| (i * 37) as u8).collect();
+        let x: Vec<u8> = (0..1000).map(|i| (i * 101 + 7) as u8).collect();
+        let expected = super::masked_naive(&mask, &x);
+        assert_eq!(super::masked_weight(&mask, &x), expected);
+        // misaligned against each other, or both alike
+        assert_eq!(
+            super::masked_weight(&mask[1..], &x[..999]),
+            super::masked_naive(&mask[1..], &x[..999])
+        );
+        assert_eq!(
+            super::masked_weight(&mask[3..], &x[3..]),
+            super::masked_naive(&mask[3..], &x[3..])
+        );
+        assert_eq!(super::masked_weight(&[0xFF; 1000], &x), super::weight(&x));
+        assert_eq!(super::masked_weight(&[0x00; 1000], &x), 0);
+    }
+
     #[test]
     fn weight_big() {
         let v = vec![0b1001_1101; 12791]; // 12791 is a prime, 14 bit number (according to openssl)
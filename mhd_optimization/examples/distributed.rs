@@ -0,0 +1,128 @@
+//! Distributed solving of one knapsack over TCP: one coordinator, many worker processes.
+//!
+//! All processes read the same instance file (the first problem in it), e.g.
+//!
+//! ```text
+//! cargo run --release --example distributed -- --workers 2 --port 7878 data/hardinstances_pisinger/knapPI_11_10000_1000.csv
+//! cargo run --release --example distributed -- --connect localhost:7878 data/hardinstances_pisinger/knapPI_11_10000_1000.csv
+//! cargo run --release --example distributed -- --connect localhost:7878 data/hardinstances_pisinger/knapPI_11_10000_1000.csv
+//! ```
+
+extern crate structopt;
+use structopt::StructOpt;
+
+extern crate log;
+extern crate simplelog;
+use log::*;
+use simplelog::*;
+
+extern crate mhd_optimization;
+use mhd_optimization::implementations::{parse_dot_csv_stream, parse_dot_dat_stream};
+use mhd_optimization::implementations::{BestFirstSolver, Problem01Knapsack};
+use mhd_optimization::optimizer::{run_coordinator, run_worker, MhdError, TcpChannel};
+use mhd_optimization::optimizer::{Problem, Solution};
+
+use std::fs::File;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "distributed")]
+struct Opt {
+    /// Verbose mode (-v or -vv)
+    #[structopt(short, long, parse(from_occurrences))]
+    verbose: u8,
+
+    /// Coordinator: wait for this many workers, then start
+    #[structopt(short, long, default_value = "0")]
+    workers: usize,
+
+    /// Coordinator: port to listen on
+    #[structopt(short, long, default_value = "7878")]
+    port: u16,
+
+    /// Coordinator: how many decisions are fixed per subtree (there are up to 2^depth subtrees)
+    #[structopt(short, long, default_value = "8")]
+    depth: usize,
+
+    /// Worker: address of the coordinator (host:port)
+    #[structopt(short, long)]
+    connect: Option<String>,
+
+    /// Worker: time limit per subtree in seconds (floating point; defines convergence)
+    #[structopt(short, long, default_value = "1.0")]
+    time: f32,
+
+    /// Instance file (.dat or .csv); the first problem in it is solved.
+    #[structopt(name = "FILE", parse(from_os_str))]
+    file: PathBuf,
+} // end struct Opt
+
+fn read_problem(file_name: &Path) -> Result<Problem01Knapsack, MhdError> {
+    let mut input = io::BufReader::new(File::open(file_name)?);
+    match file_name.extension().and_then(|ext| ext.to_str()) {
+        Some("dat") => parse_dot_dat_stream(&mut input),
+        Some("csv") => parse_dot_csv_stream(&mut input),
+        _ => Err(MhdError::InvalidConfig(
+            "Unknown file extension (not dat, not csv)".to_string(),
+        )),
+    }
+}
+
+/********************************* MAIN *****************************/
+fn main() -> io::Result<()> {
+    let opt = Opt::from_args();
+    assert!(opt.verbose < 4, "Too verbose: Maximum verbosity is vvv");
+    let level = match opt.verbose {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    TermLogger::init(
+        level,
+        Config::default(),
+        TerminalMode::Mixed,
+        ColorChoice::Auto,
+    )
+    .unwrap();
+
+    let knapsack = read_problem(&opt.file)?;
+    assert!(knapsack.is_legal(), "Illegal knapsack in {:?}", opt.file);
+
+    if let Some(address) = opt.connect.as_ref() {
+        // WORKER
+        let channel = TcpChannel::new(TcpStream::connect(address)?)?;
+        let mut solver = BestFirstSolver::new(knapsack.problem_size());
+        let time_limit = Duration::from_secs_f32(opt.time);
+        let num_subtrees = run_worker(&knapsack, &mut solver, channel, time_limit)?;
+        println!("Worker searched {} subtrees", num_subtrees);
+    } else {
+        // COORDINATOR
+        assert!(
+            0 < opt.workers,
+            "A coordinator needs at least one worker (--workers)"
+        );
+        let listener = TcpListener::bind(("0.0.0.0", opt.port))?;
+        println!("Waiting for {} workers on port {}", opt.workers, opt.port);
+        let mut channels = Vec::new();
+        while channels.len() < opt.workers {
+            let (stream, peer) = listener.accept()?;
+            println!("Worker {} connected from {}", channels.len() + 1, peer);
+            channels.push(TcpChannel::new(stream)?);
+        } // end while waiting for workers
+
+        let start_time = Instant::now();
+        let the_best = run_coordinator(&knapsack, channels, opt.depth)?;
+        println!(
+            "Found best score {} in knapsack with dim {} after {:?}",
+            the_best.get_score(),
+            knapsack.problem_size(),
+            start_time.elapsed()
+        );
+        info!("best is {}", the_best.readable());
+    }; // end if worker or coordinator
+    Ok(())
+}
@@ -1,4 +1,3 @@
-use std::cmp::max;
 extern crate structopt;
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -27,6 +26,13 @@ struct Opt {
     #[structopt(short, long, default_value = "1.0")]
     time: f32,
 
+    /// Target score (e.g. the known optimum, the "z" line of a Pisinger file)
+    ///
+    /// If given, each solver stops as soon as it reaches this score,
+    /// and the time it took ("time to target") is reported.
+    #[structopt(long)]
+    target: Option<ScoreType>,
+
     /// Algorithms (solvers) : 1 = depth first, 2 = best first, 4 = MCTS, 8 = MHD, 16 = BF MHD MCTS, 31 = 0x11111 = all of them ...
     #[structopt(short, long, default_value = "31")]
     algorithms: u8,
@@ -51,6 +57,14 @@ struct Opt {
     /// dat (rust crate format)
     #[structopt(name = "FILE", parse(from_os_str))]
     files: Vec<PathBuf>,
+
+    /// Directory to watch (daemon mode)
+    ///
+    /// Instead of processing FILEs once, keep watching this directory:
+    /// every new .dat or .csv file that appears is solved (with the chosen algorithms),
+    /// and the results are written next to it, into a file with the extension ".result" added.
+    #[structopt(short, long, parse(from_os_str))]
+    watch: Option<PathBuf>,
 } // end struct Opt
 
 const DEPTH_FIRST_BIT: u8 = 1;
@@ -59,22 +73,20 @@ const MCTS_BIT: u8 = 4;
 const MHD_BIT: u8 = 8;
 const BF_MHD_BIT: u8 = 16;
 
-use std::time::{Duration, Instant};
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime};
 
 extern crate mhd_memory;
 use mhd_memory::ScoreType; // used implicitly (only)
 
 extern crate mhd_optimization;
-use mhd_optimization::implementations::BestfirstMhdMonteCarloSolver;
-use mhd_optimization::implementations::{BestFirstSolver, DepthFirstSolver};
-use mhd_optimization::implementations::{MhdMonteCarloSolver, MonteCarloTreeSolver};
-use mhd_optimization::implementations::{Problem01Knapsack, ZeroOneKnapsackSolution};
-use mhd_optimization::optimizer::{Problem, Solution, Solver};
+use mhd_optimization::implementations::{Problem01Knapsack, SolverKind};
+use mhd_optimization::optimizer::{DynSolver, MhdError, Problem, Solution};
 
 fn run_one_problem_one_solver(
     opt: &Opt,
     knapsack: &Problem01Knapsack,
-    solver: &mut impl Solver<ZeroOneKnapsackSolution>,
+    solver: &mut DynSolver<Problem01Knapsack>,
 ) -> ScoreType {
     if !knapsack.is_legal() {
         println!("Not optimizing ILLEGAL Knapsack! {:?}", knapsack);
@@ -90,9 +102,31 @@ fn run_one_problem_one_solver(
     let time_limit = Duration::from_secs_f32(opt.time);
     let start_time = Instant::now();
 
-    let the_best = solver
-        .find_best_solution(knapsack, time_limit)
-        .expect("Optimization fails?!?");
+    let result = match opt.target {
+        None => solver
+            .find_best_solution(knapsack, time_limit)
+            .map(|report| {
+                info!("{}", report);
+                report.into_best()
+            }),
+        Some(target) => solver
+            .find_target_solution(knapsack, time_limit, target)
+            .map(|(the_best, time_to_target)| {
+                match time_to_target {
+                    Some(time) => print!("target {} reached after {:?}, ", target, time),
+                    None => print!("target {} NOT reached, ", target),
+                };
+                the_best
+            }),
+    };
+    let the_best = match result {
+        Ok(the_best) => the_best,
+        Err(e) => {
+            println!("with {}, optimization failed: {}", solver.name(), e);
+            warn!("Optimization with {} fails: {}", solver.name(), e);
+            return ZERO_SCORE;
+        }
+    };
 
     println!(
         "with {}, found best score {} in knapsack with dim {} after {:?}",
@@ -105,116 +139,225 @@ fn run_one_problem_one_solver(
     the_best.get_score()
 }
 
-const DFS_INDEX: usize = 0;
-const BFS_INDEX: usize = 1;
-const MCTS_INDEX: usize = 2;
-const MCTS_MONTE_INDEX: usize = 3;
-const MHD_INDEX: usize = 4;
-const MHD_MONTE_INDEX: usize = 5;
-const BF_MHD_SCORE: usize = 6;
-const NUM_SCORES: usize = 7;
-
 fn run_one_problem(opt: &Opt, knapsack: &mut Problem01Knapsack, prob_num: u16) {
     if 0.0 != opt.capacity {
         knapsack.basis.capacity =
             (knapsack.weights_sum() as f32 * (opt.capacity / 100.0)) as ScoreType;
     }; // else, leave capacity alone remain what the random constructor figured out.
 
-    let mut scores = [0 as ScoreType; NUM_SCORES];
-
     println!(" "); // blank line seperator -> output
-    if 0 != (opt.algorithms & DEPTH_FIRST_BIT) {
-        print!("Knapsack {}: ", prob_num + 1);
-        scores[DFS_INDEX] =
-            run_one_problem_one_solver(&opt, &knapsack, &mut DepthFirstSolver::new(opt.size));
-    }; // endif depth first
-
-    if 0 != (opt.algorithms & BEST_FIRST_BIT) {
-        print!("Knapsack {}: ", prob_num + 1);
-        scores[BFS_INDEX] =
-            run_one_problem_one_solver(&opt, &knapsack, &mut BestFirstSolver::new(opt.size));
-    }; // end if best first
-
-    if 0 != (opt.algorithms & MCTS_BIT) {
-        print!("Knapsack {}: ", prob_num + 1);
-        let mut solver = MonteCarloTreeSolver::builder(knapsack);
-        scores[MCTS_INDEX] = run_one_problem_one_solver(&opt, &knapsack, &mut solver);
-
-        // Do it again, but full monte
-        solver.clear();
-        solver.full_monte = true;
-        print!("FullMonte{}: ", prob_num + 1);
-        scores[MCTS_MONTE_INDEX] = run_one_problem_one_solver(&opt, &knapsack, &mut solver);
-    }; // end if best first
-
-    if 0 != (opt.algorithms & MHD_BIT) {
-        let mut solver = MhdMonteCarloSolver::builder(knapsack);
-        let start_size = solver.mhd_memory.num_samples();
-        print!("Knapsack {}: ", prob_num + 1);
-        scores[MHD_INDEX] = run_one_problem_one_solver(&opt, &knapsack, &mut solver);
-        println!("SIZES: MHD had {}, has {} samples", start_size, solver.mhd_memory.num_samples());
-        // Do it again, but full monte
-        solver.clear();
-        solver.full_monte = true;
-        let start_size = solver.mhd_memory.num_samples();
-        print!("MHDMonte {}: ", prob_num + 1);
-        scores[MHD_MONTE_INDEX] = run_one_problem_one_solver(&opt, &knapsack, &mut solver);
-        println!("SIZES: MHD had {}, has {} samples", start_size, solver.mhd_memory.num_samples());
-    }; // end if best first
-
-    if 0 != (opt.algorithms & BF_MHD_BIT) {
-        print!("Knapsack {}: ", prob_num + 1);
-        let mut solver = BestfirstMhdMonteCarloSolver::builder(knapsack);
-        let start_size = solver.mhd_memory.num_samples();
-        scores[BF_MHD_SCORE] = run_one_problem_one_solver(&opt, &knapsack, &mut solver);
-        println!("SIZES: MHD had {}, has {} samples", start_size, solver.mhd_memory.num_samples());
-    }; // end if best first
-
-    let best_score = scores.iter().fold(ZERO_SCORE, |s0, s1| max(s0, *s1));
-    assert_ne!(best_score, 0);
+    let mut scores = Vec::new();
+    for kind in selected_solvers(opt) {
+        print!("Knapsack {} ({}): ", prob_num + 1, kind);
+        let mut solver = kind.dyn_solver(knapsack);
+        scores.push(run_one_problem_one_solver(opt, knapsack, &mut solver));
+    } // end for all selected solvers
+
+    let best_score = Score::max_of(scores.iter().cloned()).unwrap_or(ZERO_SCORE);
+    if ZERO_SCORE == best_score {
+        warn!("No solver found a solution for knapsack {}", prob_num + 1);
+        return;
+    };
     print!("Dim {}, Best score {}, ", knapsack.problem_size(), best_score);
     let fbest: f32 = best_score as f32;
     let ratios: Vec<f32> = scores.iter().map(|s| *s as f32 / fbest).collect();
     println!("ratios {:?}", ratios);
 } // end run_one_problem
 
+// Read (up to opt.num_problems) knapsacks from a .dat or .csv file
+fn read_problems(opt: &Opt, file_name: &Path) -> io::Result<Vec<Problem01Knapsack>> {
+    let mut result = Vec::new();
+    let file = std::fs::File::open(file_name)?;
+    let mut input = io::BufReader::new(file);
+    let parser: fn(&mut io::BufReader<File>) -> Result<Problem01Knapsack, MhdError> =
+        match file_name.extension().and_then(|ext| ext.to_str()) {
+            Some("dat") => |input| parse_dot_dat_stream(input),
+            Some("csv") => |input| parse_dot_csv_stream(input),
+            _ => {
+                warn!("Unknown file extension (not dat, not csv");
+                return Ok(result);
+            }
+        }; // end match file name extension
+    for _ in 0..opt.num_problems {
+        // or end of file
+        match parser(&mut input) {
+            Err(MhdError::IoError(_)) => break,
+            Err(e) => {
+                warn!("Error {} in {:?}", e, file_name);
+                break;
+            }
+            Ok(knapsack) => result.push(knapsack),
+        }; // end match parser
+    } // end for  problems in file
+    Ok(result)
+} // end read_problems
+
 fn run_one_file(opt: &Opt, file_name: &PathBuf) -> usize {
     println!("\nProcessing Filename: {:?}", file_name);
-    let mut counter: usize = 0;
-    let file = std::fs::File::open(file_name).unwrap();
-    let mut input = io::BufReader::new(file);
-    match file_name
-        .extension()
-        .expect("Need a file name with an extension")
-        .to_str()
-        .expect("Need a simple file name extension")
-    {
-        "dat" => {
-            for prob_num in 0..opt.num_problems {
-                // or end of file
-                match parse_dot_dat_stream(&mut input) {
-                    Err(_) => break,
-                    Ok(mut knapsack) => run_one_problem(&opt, &mut knapsack, prob_num),
-                }; // end match parse_dot_dat
-                counter += 1;
-            } // end for  problems in file
-        } // end for all problems
-        "csv" => {
-            for prob_num in 0..opt.num_problems {
-                // or end of file
-                match parse_dot_csv_stream(&mut input) {
-                    Err(_) => break,
-                    Ok(mut knapsack) => run_one_problem(&opt, &mut knapsack, prob_num),
-                }; // end match parse_dot_dat
-                counter += 1;
-            } // end for  problems in file
-        } // end for all problems
-        _ => warn!("Unknown file extension (not dat, not csv"),
-    }; // end match file name extension
-       // Done!
-    counter
+    let knapsacks = match read_problems(opt, file_name) {
+        Ok(knapsacks) => knapsacks,
+        Err(e) => {
+            warn!("Error {} reading {:?}", e, file_name);
+            return 0;
+        }
+    };
+    for (prob_num, mut knapsack) in knapsacks.iter().cloned().enumerate() {
+        run_one_problem(opt, &mut knapsack, prob_num as u16);
+    } // end for all problems
+      // Done!
+    knapsacks.len()
 } // end run_one_file
 
+// The solvers selected by opt.algorithms
+fn selected_solvers(opt: &Opt) -> Vec<SolverKind> {
+    let mut result = Vec::new();
+    if 0 != (opt.algorithms & DEPTH_FIRST_BIT) {
+        result.push(SolverKind::DepthFirst);
+    };
+    if 0 != (opt.algorithms & BEST_FIRST_BIT) {
+        result.push(SolverKind::BestFirst);
+    };
+    if 0 != (opt.algorithms & MCTS_BIT) {
+        result.push(SolverKind::MonteCarloTree);
+        result.push(SolverKind::FullMonteCarloTree);
+    };
+    if 0 != (opt.algorithms & MHD_BIT) {
+        result.push(SolverKind::MhdMonteCarlo);
+        result.push(SolverKind::FullMhdMonteCarlo);
+    };
+    if 0 != (opt.algorithms & BF_MHD_BIT) {
+        result.push(SolverKind::BestfirstMhdMonteCarlo);
+    };
+    result
+} // end selected_solvers
+
+/********************************* WATCH MODE *****************************/
+fn result_file_name(file_name: &Path) -> PathBuf {
+    let mut result = file_name.as_os_str().to_owned();
+    result.push(".result");
+    PathBuf::from(result)
+}
+
+// Solve every problem in one file, and write one line per problem and solver into the result file
+fn solve_one_file_to_results(opt: &Opt, file_name: &Path) -> io::Result<usize> {
+    let knapsacks = read_problems(opt, file_name)?;
+    let mut output = io::BufWriter::new(File::create(result_file_name(file_name))?);
+    writeln!(
+        output,
+        "problem; solver; score; weight; capacity; seconds; decisions"
+    )?;
+    let time_limit = Duration::from_secs_f32(opt.time);
+    for (prob_num, knapsack) in knapsacks.iter().enumerate() {
+        if !knapsack.is_legal() {
+            writeln!(
+                output,
+                "{}; ILLEGAL KNAPSACK; ; ; {}; ; ",
+                prob_num + 1,
+                knapsack.capacity()
+            )?;
+            continue;
+        };
+        for kind in selected_solvers(opt) {
+            let start_time = Instant::now();
+            let the_best = match kind.find_best_solution(knapsack, time_limit) {
+                Ok(the_best) => the_best,
+                Err(e) => {
+                    warn!("Error {} solving {:?} with {}", e, file_name, kind);
+                    writeln!(output, "{}; {}; ERROR: {}; ; ; ; ", prob_num + 1, kind, e)?;
+                    continue;
+                }
+            };
+            let decisions: String = (0..knapsack.problem_size())
+                .map(|d| {
+                    if Some(true) == the_best.get_decision(d) {
+                        '1'
+                    } else {
+                        '0'
+                    }
+                })
+                .collect();
+            let weight: ScoreType = (0..knapsack.problem_size())
+                .filter(|&d| Some(true) == the_best.get_decision(d))
+                .map(|d| knapsack.basis.weights[d])
+                .sum();
+            writeln!(
+                output,
+                "{}; {}; {}; {}; {}; {:.3}; {}",
+                prob_num + 1,
+                kind,
+                the_best.get_score(),
+                weight,
+                knapsack.capacity(),
+                start_time.elapsed().as_secs_f32(),
+                decisions
+            )?;
+        } // end for all solvers
+    } // end for all problems
+    output.flush()?;
+    Ok(knapsacks.len())
+} // end solve_one_file_to_results
+
+// When was this file last modified (if we can tell)?
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+// Is this a (finished) instance file we have not solved yet?
+// (Files which failed are only tried again once they have changed.)
+fn needs_solving(path: &Path, failed: &HashMap<PathBuf, SystemTime>) -> bool {
+    let is_instance = matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("dat") | Some("csv")
+    );
+    let modified = modified(path);
+    // Files still being written (modified less than a second ago) are left for the next round
+    let is_settled = modified
+        .map(|time| WATCH_INTERVAL < time.elapsed().unwrap_or_default())
+        .unwrap_or(false);
+    let has_failed = modified.is_some() && failed.get(path) == modified.as_ref();
+    is_instance && is_settled && !has_failed && !result_file_name(path).exists()
+}
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+fn watch_directory(opt: &Opt, dir: &Path) {
+    assert!(dir.is_dir(), "Watch directory {:?} is not a directory", dir);
+    println!(
+        "Watching directory {:?} for new .dat and .csv files...",
+        dir
+    );
+    // The files which could not be solved, with their modification times
+    let mut failed = HashMap::new();
+    loop {
+        match dir.read_dir() {
+            Ok(entries) => {
+                for entry in entries.filter_map(|e| e.ok()) {
+                    let path = entry.path();
+                    if path.is_file() && needs_solving(&path, &failed) {
+                        println!("Solving {:?}", path);
+                        match solve_one_file_to_results(opt, &path) {
+                            Ok(count) => {
+                                failed.remove(&path);
+                                println!("Wrote {} results to {:?}", count, result_file_name(&path))
+                            }
+                            Err(e) => {
+                                warn!("Error {:?} while solving {:?}", e, path);
+                                if let Some(time) = modified(&path) {
+                                    failed.insert(path, time);
+                                };
+                            }
+                        };
+                    };
+                } // end for all entries in directory
+            }
+            Err(e) => warn!("Error {:?} reading directory {:?}", e, dir),
+        };
+        std::thread::sleep(WATCH_INTERVAL);
+    } // end loop (forever)
+} // end watch_directory
+
 fn run_one_directory(opt: &Opt, path: &PathBuf) -> usize {
     let mut num_tests: usize = 0;
     for entry_result in path.read_dir().expect("read_dir call failed") {
@@ -235,8 +378,10 @@ use log::*;
 use simplelog::*;
 use std::fs::File;
 use std::io;
+use std::io::Write;
+use std::path::Path;
 // use mhd_mem::mhd_memory::ScoreType; -- already imported above
-use mhd_memory::ZERO_SCORE;
+use mhd_memory::{Score, ZERO_SCORE};
 use mhd_optimization::implementations::{parse_dot_csv_stream, parse_dot_dat_stream};
 
 /********************************* MAIN *****************************/
@@ -279,6 +424,11 @@ fn main() {
         .unwrap();
     }; // end if verbose
 
+    if let Some(dir) = opt.watch.clone() {
+        // THIRD USE CASE : Daemon, watching a directory (never returns)
+        watch_directory(&opt, &dir);
+    }; // end if watching
+
     let mut num_tests: usize = 0;
     if opt.files.is_empty() {
         // FIRST USE CASE : No files, random data
@@ -0,0 +1,221 @@
+//! A small HTTP solve service, so other languages can use our solvers without FFI.
+//!
+//! Start it with `cargo run --example solve_service -- --port 8080`, then POST a
+//! 0-1 knapsack instance (and, optionally, a solver and a time limit) to `/solve`:
+//!
+//! ```text
+//! curl -d '{"weights": [3, 4, 5], "values": [4, 5, 6], "capacity": 8,
+//!           "solver": "bfs", "time": 0.5}' http://localhost:8080/solve
+//! ```
+//!
+//! The answer contains the decisions (which items are in the knapsack), the score,
+//! and a short report. Solvers are named as in `SolverKind` ("dfs", "bfs", "mcts", ...).
+
+extern crate structopt;
+use structopt::StructOpt;
+
+#[macro_use]
+extern crate serde;
+extern crate serde_json;
+extern crate tiny_http;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+extern crate log;
+extern crate simplelog;
+use log::*;
+use simplelog::*;
+
+extern crate mhd_memory;
+use mhd_memory::ScoreType;
+
+extern crate mhd_optimization;
+use mhd_optimization::implementations::{Problem01Knapsack, ProblemSubsetSum, SolverKind};
+use mhd_optimization::optimizer::{Problem, Solution};
+
+use std::time::{Duration, Instant};
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "solve_service")]
+struct Opt {
+    /// Verbose mode (-v or -vv)
+    #[structopt(short, long, parse(from_occurrences))]
+    verbose: u8,
+
+    /// Port to listen on
+    #[structopt(short, long, default_value = "8080")]
+    port: u16,
+
+    /// Maximum time limit in seconds a client may ask for
+    #[structopt(short, long, default_value = "10.0")]
+    max_time: f32,
+} // end struct Opt
+
+/// What a client POSTs to /solve
+#[derive(Deserialize, Debug)]
+struct SolveRequest {
+    weights: Vec<ScoreType>,
+    values: Vec<ScoreType>,
+    capacity: ScoreType,
+    #[serde(default = "default_solver")]
+    solver: String,
+    /// Time limit in seconds (floating point; defines convergence)
+    #[serde(default = "default_time")]
+    time: f32,
+}
+
+fn default_solver() -> String {
+    SolverKind::BestFirst.to_string()
+}
+
+fn default_time() -> f32 {
+    1.0
+}
+
+/// What a client gets back
+#[derive(Serialize, Debug)]
+struct SolveResponse {
+    solver: String,
+    score: ScoreType,
+    decisions: Vec<bool>,
+    report: SolveReport,
+}
+
+#[derive(Serialize, Debug)]
+struct SolveReport {
+    problem_size: usize,
+    capacity: ScoreType,
+    weight: ScoreType,
+    values_sum: ScoreType,
+    elapsed_ms: u128,
+}
+
+#[derive(Serialize, Debug)]
+struct ErrorResponse {
+    error: String,
+}
+
+fn knapsack_from_request(request: &SolveRequest) -> Result<Problem01Knapsack, String> {
+    if request.weights.len() != request.values.len() {
+        return Err(format!(
+            "{} weights but {} values",
+            request.weights.len(),
+            request.values.len()
+        ));
+    };
+    let knapsack = Problem01Knapsack {
+        basis: ProblemSubsetSum {
+            weights: request.weights.clone(),
+            capacity: request.capacity,
+        },
+        values: request.values.clone(),
+    };
+    if knapsack.is_legal() {
+        Ok(knapsack)
+    } else {
+        Err(format!("Illegal knapsack: {}", knapsack.short_description()))
+    }
+}
+
+fn solve(opt: &Opt, body: &str) -> Result<SolveResponse, String> {
+    let request: SolveRequest = serde_json::from_str(body).map_err(|e| e.to_string())?;
+    let kind: SolverKind = request.solver.parse()?;
+    let knapsack = knapsack_from_request(&request)?;
+    let time_limit = Duration::from_secs_f32(request.time.max(0.0).min(opt.max_time));
+
+    let start_time = Instant::now();
+    let best = kind
+        .find_best_solution(&knapsack, time_limit)
+        .map_err(|e| e.to_string())?;
+    let elapsed = start_time.elapsed();
+    info!(
+        "{} found score {} in knapsack with dim {} after {:?}",
+        kind,
+        best.get_score(),
+        knapsack.problem_size(),
+        elapsed
+    );
+
+    let decisions: Vec<bool> = (0..knapsack.problem_size())
+        .map(|index| best.get_decision(index).unwrap_or(false))
+        .collect();
+    let weight = decisions
+        .iter()
+        .zip(knapsack.basis.weights.iter())
+        .filter(|(&decision, _)| decision)
+        .map(|(_, &weight)| weight)
+        .sum();
+    Ok(SolveResponse {
+        solver: kind.to_string(),
+        score: best.get_score(),
+        decisions,
+        report: SolveReport {
+            problem_size: knapsack.problem_size(),
+            capacity: knapsack.capacity(),
+            weight,
+            values_sum: knapsack.values_sum(),
+            elapsed_ms: elapsed.as_millis(),
+        },
+    })
+}
+
+fn respond_json<T: serde::Serialize>(request: Request, status: u16, body: &T) {
+    let json = serde_json::to_string(body).expect("Responses always serialize");
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+        .expect("Static header is valid");
+    let response = Response::from_string(json)
+        .with_status_code(status)
+        .with_header(header);
+    if let Err(e) = request.respond(response) {
+        warn!("Could not send response: {}", e);
+    };
+}
+
+fn handle_request(opt: &Opt, mut request: Request) {
+    debug!("{} {}", request.method(), request.url());
+    if *request.method() != Method::Post || request.url() != "/solve" {
+        let error = ErrorResponse {
+            error: "Only POST /solve is supported".to_string(),
+        };
+        return respond_json(request, 404, &error);
+    };
+    let mut body = String::new();
+    if let Err(e) = request.as_reader().read_to_string(&mut body) {
+        let error = ErrorResponse {
+            error: e.to_string(),
+        };
+        return respond_json(request, 400, &error);
+    };
+    match solve(opt, &body) {
+        Ok(answer) => respond_json(request, 200, &answer),
+        Err(error) => respond_json(request, 400, &ErrorResponse { error }),
+    };
+}
+
+/********************************* MAIN *****************************/
+fn main() {
+    let opt = Opt::from_args();
+    assert!(opt.verbose < 4, "Too verbose: Maximum verbosity is vvv");
+
+    let level = match opt.verbose {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    TermLogger::init(
+        level,
+        Config::default(),
+        TerminalMode::Mixed,
+        ColorChoice::Auto,
+    )
+    .unwrap();
+
+    let address = format!("0.0.0.0:{}", opt.port);
+    let server = Server::http(&address).expect("Could not start server");
+    println!("Listening on http://{}/solve", address);
+
+    // One request at a time: each solve uses the whole time limit anyway.
+    for request in server.incoming_requests() {
+        handle_request(&opt, request);
+    } // end for all requests
+}
@@ -0,0 +1,122 @@
+//! # mhd-mem-inspect
+//!
+//! Look inside a persisted MHD memory (see `MhdMemory::export_csv`):
+//! summary statistics, the best samples, and which bits matter most for the score.
+//!
+//! ```text
+//! mhd-mem-inspect memory.csv --top 5 --bits 10
+//! mhd-mem-inspect memory.csv --csv importance.csv
+//! ```
+extern crate structopt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+extern crate mhd_memory;
+use mhd_memory::util::get_bit;
+use mhd_memory::{MhdMemory, Sample};
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "mhd-mem-inspect")]
+struct Opt {
+    /// Number of best samples to print
+    #[structopt(short, long, default_value = "10")]
+    top: usize,
+
+    /// Number of most important bits to print
+    #[structopt(short, long, default_value = "10")]
+    bits: usize,
+
+    /// Export the importance of every bit to this CSV file (columns bit,importance,rank)
+    #[structopt(short, long, parse(from_os_str))]
+    csv: Option<PathBuf>,
+
+    /// The memory file (as written by MhdMemory::export_csv)
+    #[structopt(name = "FILE", parse(from_os_str))]
+    file: PathBuf,
+} // end struct Opt
+
+fn bit_string(sample: &Sample) -> String {
+    (0..sample.size())
+        .map(|bit| {
+            if get_bit(&sample.bytes, bit) {
+                '1'
+            } else {
+                '0'
+            }
+        })
+        .collect()
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+    let memory = MhdMemory::import_csv(BufReader::new(File::open(&opt.file)?))?;
+
+    // Summary
+    println!("Memory {}:", opt.file.display());
+    println!("  width     {} bits", memory.width());
+    println!("  samples   {}", memory.num_samples());
+    if memory.is_empty() {
+        return Ok(());
+    };
+    println!(
+        "  scores    min {}, avg {}, max {}",
+        memory.min_score,
+        memory.avg_score(),
+        memory.max_score
+    );
+    let stats = memory.stats();
+    println!(
+        "  density   {:.3} (fraction of bits set), {} constant bits",
+        stats.bit_fill.iter().sum::<f64>() / memory.width() as f64,
+        stats.constant_bits()
+    );
+    println!(
+        "  distances average {:.2} (of {} pairs), {} duplicates",
+        stats.mean_distance(),
+        stats.num_pairs,
+        stats.duplicates
+    );
+    println!("  scores    {:?} (histogram)", stats.score_histogram);
+
+    // Top k samples
+    println!("Top {} samples:", opt.top);
+    for (rank, sample) in memory.top_samples(opt.top).iter().enumerate() {
+        println!(
+            "  {:>4}. {:>10} {}",
+            rank + 1,
+            sample.score,
+            bit_string(sample)
+        );
+    }
+
+    // Bit importance ranking
+    let importance = memory.bit_importance();
+    let mut ranking: Vec<usize> = (0..memory.width()).collect();
+    ranking.sort_by(|&a, &b| importance[b].abs().total_cmp(&importance[a].abs()));
+    println!("Most important bits (average score with bit set minus without):");
+    for (rank, &bit) in ranking.iter().take(opt.bits).enumerate() {
+        println!(
+            "  {:>4}. bit {:>5} {:>+12.2}",
+            rank + 1,
+            bit,
+            importance[bit]
+        );
+    }
+
+    if let Some(path) = opt.csv {
+        let mut writer = BufWriter::new(File::create(&path)?);
+        writeln!(writer, "bit,importance,rank")?;
+        let mut ranks = vec![0; memory.width()];
+        for (rank, &bit) in ranking.iter().enumerate() {
+            ranks[bit] = rank + 1;
+        }
+        for (bit, value) in importance.iter().enumerate() {
+            writeln!(writer, "{},{},{}", bit, value, ranks[bit])?;
+        }
+        writer.flush()?;
+        println!("Bit importance written to {}", path.display());
+    };
+    Ok(())
+} // end main
@@ -1,20 +1,69 @@
+use log::*;
+
 /// # Example Implementations
 ///
 ///
 ///
-use optimizer::{PriorityType, Solution, Solver};
+use optimizer::{
+    BucketQueue, EliteArchive, FrontierEntry, MemoryPruning, MemoryUsage, PriorityType, Problem,
+    Solution, Solver, SolverBuilder, VisitedSet, VisitedStats,
+};
 
 /// ## Example Solver Implementation: Best First Search
 ///
 ///
-use mhd_memory::ZERO_SCORE; // ScoreType not needed (?!?)
+use mhd_memory::{Score, ScoreType, ZERO_SCORE};
 use std::collections::BinaryHeap;
 // use num::NumCast;
 
+/// How the frontier (the open solutions) is ordered. Either way, the solution with the highest
+/// priority (score plus best possible score) is popped next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PriorityStrategy {
+    /// A `BinaryHeap`, ordered by the solutions' (floating point) priorities: O(log n)
+    #[default]
+    BinaryHeap,
+    /// A `BucketQueue` of integer priorities: O(1), but memory grows with the scores
+    BucketQueue,
+}
+
+/// What a solution's priority is, i.e. which open solution is popped first.
+/// Whichever it is, the search is exhaustive: only the order changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PriorityFunction {
+    /// Score plus best possible score, A* style (the classic one)
+    #[default]
+    ScoreAndBound,
+    /// The score so far: greedy, the best partial solution first
+    Score,
+    /// The best possible score (the bound): the most promising solution first
+    Bound,
+    /// The score the memory of `pruning` predicts for the solution's completions
+    /// (score plus best possible score while there's no such memory, or it's empty).
+    /// For a memory which predicts without pruning, see `MemoryPruning::new`'s confidence.
+    MemoryPredicted,
+}
+
 #[derive(Debug, Clone)]
 pub struct BestFirstSolver<Sol: Solution> {
     pub solutions: BinaryHeap<Sol>,
+    /// The frontier if the priority strategy is `PriorityStrategy::BucketQueue`
+    pub buckets: BucketQueue<Sol>,
+    priority_strategy: PriorityStrategy,
+    priority_function: PriorityFunction,
     best_solution: Sol,
+    /// Optional archive of good and diverse solutions, see `EliteArchive`
+    pub elites: Option<EliteArchive<Sol>>,
+    /// Optional memory-guided pruning (None = classical, exact search)
+    pub pruning: Option<MemoryPruning>,
+    /// Optional duplicate-state pruning, see `VisitedSet` (None = expand every state)
+    pub visited: Option<VisitedSet>,
+    /// Optional limit on the open solutions, see `with_max_open` (None = no limit)
+    pub max_open: Option<usize>,
+    /// How many open solutions were dropped, to stay within `max_open`
+    pub num_dropped: usize,
+    /// The best bound of a dropped solution which might have beaten the best one, if any
+    pub dropped_bound: Option<ScoreType>,
 }
 
 impl<Sol: Solution> Solver<Sol> for BestFirstSolver<Sol> {
@@ -29,51 +78,109 @@ impl<Sol: Solution> Solver<Sol> for BestFirstSolver<Sol> {
             "{} holding {} solutions, best score {}",
             self.name(),
             self.number_of_solutions(),
-            match self.solutions.peek() {
+            match self.solutions.peek().or(self.buckets.peek()) {
                 None => ZERO_SCORE,
                 Some(sol) => sol.get_score(),
             }
         )
     }
 
+    // Methods used by the Unified Optimization Algorithm (identified above)
+
+    #[inline]
+    fn number_of_solutions(&self) -> usize {
+        self.solutions.len() + self.buckets.len()
+    }
+
     #[inline]
-    fn new(size: usize) -> Self {
-        Self {
-            solutions: BinaryHeap::new(),
-            best_solution: Sol::new(size),
+    fn memory_usage(&self) -> MemoryUsage {
+        let frontier = MemoryUsage::of_frontier::<Sol>(self.number_of_solutions(), self.width());
+        match self.visited.as_ref() {
+            None => frontier,
+            Some(visited) => frontier + MemoryUsage::of_tree::<u64>(visited.len()),
         }
     }
 
-    // Methods used by the Unified Optimization Algorithm (identified above)
+    /// The highest priority first
+    fn snapshot_frontier(&self, limit: usize) -> Vec<FrontierEntry> {
+        match self.priority_strategy {
+            PriorityStrategy::BinaryHeap => {
+                let mut entries: Vec<FrontierEntry> =
+                    self.solutions.iter().map(FrontierEntry::of).collect();
+                entries.sort_by(|a, b| b.priority.total_cmp(&a.priority));
+                entries.truncate(limit);
+                entries
+            }
+            PriorityStrategy::BucketQueue => self
+                .buckets
+                .iter()
+                .take(limit)
+                .map(FrontierEntry::of)
+                .collect(),
+        }
+    }
 
+    /// Unless it dropped solutions which might have beaten the best one (see `with_max_open`)
     #[inline]
-    fn number_of_solutions(&self) -> usize {
-        self.solutions.len()
+    fn is_exhaustive(&self) -> bool {
+        match self.dropped_bound {
+            None => true,
+            Some(bound) => bound <= self.best_score(),
+        }
+    }
+
+    #[inline]
+    fn dropped_bound(&self) -> Option<ScoreType> {
+        self.dropped_bound
     }
 
     #[inline]
     fn is_empty(&self) -> bool {
-        self.solutions.is_empty()
+        self.solutions.is_empty() && self.buckets.is_empty()
     }
 
     #[inline]
     fn clear(&mut self) {
         self.solutions.clear();
+        self.buckets.clear();
         let size = self.best_solution.size();
         self.best_solution = Sol::new(size);
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
+        if let Some(pruning) = self.pruning.as_mut() {
+            pruning.clear();
+        };
+        if let Some(visited) = self.visited.as_mut() {
+            visited.clear();
+        };
+        self.num_dropped = 0;
+        self.dropped_bound = None;
     }
 
     #[inline]
     fn push(&mut self, mut solution: Sol) {
-        let p: PriorityType = (solution.get_score() + solution.get_best_score()) as PriorityType;
+        let priority = self.priority_of(&solution);
         // let mut new_solution = solution.clone(); clippy sez we don't need this (?)
-        solution.set_priority(p);
-        self.solutions.push(solution);
+        solution.set_priority(priority as PriorityType);
+        match self.priority_strategy {
+            PriorityStrategy::BinaryHeap => self.solutions.push(solution),
+            PriorityStrategy::BucketQueue => self.buckets.push(priority as usize, solution),
+        };
+        if let Some(max_open) = self.max_open {
+            if max_open < self.number_of_solutions() {
+                // Make some room, not just enough for one more
+                self.drop_worst((max_open - max_open / 4).max(1));
+            };
+        };
     }
 
     #[inline]
     fn pop(&mut self) -> Option<Sol> {
-        self.solutions.pop()
+        match self.priority_strategy {
+            PriorityStrategy::BinaryHeap => self.solutions.pop(),
+            PriorityStrategy::BucketQueue => self.buckets.pop(),
+        }
     }
 
     #[inline]
@@ -81,6 +188,11 @@ impl<Sol: Solution> Solver<Sol> for BestFirstSolver<Sol> {
         &self.best_solution
     }
 
+    #[inline]
+    fn elite_archive(&mut self) -> Option<&mut EliteArchive<Sol>> {
+        self.elites.as_mut()
+    }
+
     #[inline]
     fn store_best_solution(&mut self, solution: Sol) {
         // we'd like to check for completion, but can't use proble.solution_is_complete( s )
@@ -89,8 +201,176 @@ impl<Sol: Solution> Solver<Sol> for BestFirstSolver<Sol> {
         // debug_assert!(self.best_score() <= solution.get_score());
         self.best_solution = solution;
     }
+
+    /// The two frontiers are melded into one heap (or bucket queue).
+    fn merge(&mut self, mut other: Self) {
+        self.merge_best_solutions(&mut other);
+        self.num_dropped += other.num_dropped;
+        self.dropped_bound =
+            ScoreType::max_of(self.dropped_bound.into_iter().chain(other.dropped_bound));
+        if self.priority_strategy == other.priority_strategy {
+            self.solutions.append(&mut other.solutions);
+            self.buckets.append(&mut other.buckets);
+        } else {
+            while let Some(solution) = other.pop() {
+                self.push(solution);
+            }
+        };
+    }
+
+    // With memory pruning, learn complete children and drop hopeless incomplete ones;
+    // with a visited set, drop the children whose states were seen before
+    fn children_of_solution<Prob: Problem<Sol = Sol>>(
+        &mut self,
+        parent: &Sol,
+        problem: &Prob,
+    ) -> Vec<Sol> {
+        let children = problem.children_of_solution(parent);
+        let children = match self.pruning.as_mut() {
+            None => children,
+            Some(pruning) => {
+                pruning.filter_children(problem, children, self.best_solution.get_score())
+            }
+        };
+        match self.visited.as_mut() {
+            None => children,
+            Some(visited) => visited.filter_children(problem, children),
+        }
+    }
+
+    #[inline]
+    fn visited_stats(&self) -> Option<VisitedStats> {
+        self.visited.as_ref().map(|visited| visited.stats)
+    }
 } // end imp Solver for BestFirstSolver
 
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> SolverBuilder<Prob> for BestFirstSolver<Sol> {
+    #[inline]
+    fn from_problem(problem: &Prob) -> Self {
+        Self::new(problem.problem_size())
+    }
+}
+
+impl<Sol: Solution> BestFirstSolver<Sol> {
+    /// A solver for problems with `size` decisions (which is all it needs to know)
+    #[inline]
+    pub fn new(size: usize) -> Self {
+        Self {
+            solutions: BinaryHeap::new(),
+            buckets: BucketQueue::new(),
+            priority_strategy: PriorityStrategy::default(),
+            priority_function: PriorityFunction::default(),
+            best_solution: Sol::new(size),
+            elites: None,
+            pruning: None,
+            visited: None,
+            max_open: None,
+            num_dropped: 0,
+            dropped_bound: None,
+        }
+    }
+
+    #[inline]
+    pub fn priority_strategy(&self) -> PriorityStrategy {
+        self.priority_strategy
+    }
+
+    /// Choose how the frontier is ordered (the open solutions are moved over, if any)
+    pub fn set_priority_strategy(&mut self, strategy: PriorityStrategy) {
+        if strategy == self.priority_strategy {
+            return;
+        };
+        let open = self.take_frontier();
+        self.priority_strategy = strategy;
+        for solution in open {
+            self.push(solution);
+        }
+    }
+
+    #[inline]
+    pub fn priority_function(&self) -> PriorityFunction {
+        self.priority_function
+    }
+
+    /// Choose what is popped first, see `PriorityFunction`
+    pub fn with_priority_function(mut self, function: PriorityFunction) -> Self {
+        self.set_priority_function(function);
+        self
+    }
+
+    /// Choose what is popped first (the open solutions get new priorities, if any)
+    pub fn set_priority_function(&mut self, function: PriorityFunction) {
+        if function == self.priority_function {
+            return;
+        };
+        let open = self.take_frontier();
+        self.priority_function = function;
+        for solution in open {
+            self.push(solution);
+        }
+    }
+
+    /// The priority of `solution`, see `PriorityFunction`
+    pub fn priority_of(&self, solution: &Sol) -> ScoreType {
+        let score_and_bound = solution.get_score() + solution.get_best_score();
+        match self.priority_function {
+            PriorityFunction::ScoreAndBound => score_and_bound,
+            PriorityFunction::Score => solution.get_score(),
+            PriorityFunction::Bound => solution.get_best_score(),
+            PriorityFunction::MemoryPredicted => match self
+                .pruning
+                .as_ref()
+                .filter(|pruning| !pruning.memory.is_empty())
+            {
+                Some(pruning) => {
+                    let (prediction, _) = pruning
+                        .memory
+                        .predict_masked_score(solution.mask(), solution.query());
+                    prediction
+                }
+                None => score_and_bound,
+            },
+        }
+    }
+
+    /// SMA* style, a memory-bounded search: keep at most `max_open` open solutions.
+    /// When there are more, the ones with the lowest priority are dropped -- and the best bound
+    /// among them is kept (see `dropped_bound`), so the optimality lost is reported.
+    pub fn with_max_open(mut self, max_open: usize) -> Self {
+        self.max_open = Some(max_open);
+        self
+    }
+
+    // Keep the `keep` open solutions with the highest priority, drop the others
+    fn drop_worst(&mut self, keep: usize) {
+        let mut open = self.take_frontier(); // (highest priority first)
+        let best_score = self.best_score();
+        for dropped in open.drain(keep.min(open.len())..) {
+            self.num_dropped += 1;
+            let bound = dropped.get_best_score();
+            if best_score < bound && self.dropped_bound.is_none_or(|worst| worst < bound) {
+                self.dropped_bound = Some(bound);
+            };
+        }
+        debug!(
+            "Best first search: {} solutions dropped so far, best dropped bound {:?}",
+            self.num_dropped, self.dropped_bound
+        );
+        for solution in open {
+            self.push(solution);
+        }
+    }
+
+    // Empty the frontier, returning the open solutions
+    fn take_frontier(&mut self) -> Vec<Sol> {
+        let mut open = Vec::with_capacity(self.number_of_solutions());
+        while let Some(solution) = self.pop() {
+            open.push(solution);
+        }
+        open
+    }
+} // end other methods
+
 ///////////////////// TESTs for ProblemSubsetSum with  BestFirstSolver /////////////////////
 #[cfg(test)]
 mod more_tests {
@@ -146,4 +426,155 @@ mod more_tests {
         assert_eq!(knapsack.solution_score(&the_best), knapsack.capacity);
         assert_eq!(the_best.get_score(), knapsack.capacity);
     }
+
+    #[test]
+    fn test_best_first_merge() {
+        const FEW_DECISIONS: usize = 8;
+        let knapsack = ProblemSubsetSum::random(FEW_DECISIONS);
+        let mut solver = BestFirstSolver::<MinimalSolution>::new(FEW_DECISIONS);
+        let mut worker = BestFirstSolver::<MinimalSolution>::new(FEW_DECISIONS);
+        solver.start_search(&knapsack);
+        worker.start_search(&knapsack);
+        for _ in 0..3 {
+            solver.step(&knapsack);
+            worker.step(&knapsack);
+        }
+        let num_open = solver.number_of_solutions() + worker.number_of_solutions();
+        let best_score = solver.best_score().max(worker.best_score());
+        solver.merge(worker);
+        assert_eq!(solver.number_of_solutions(), num_open);
+        assert_eq!(solver.best_score(), best_score);
+        // The heap still pops the most promising solution first
+        if let Some(top) = solver.pop() {
+            while let Some(next) = solver.pop() {
+                assert!(next.priority() <= top.priority());
+            }
+        };
+    }
+
+    #[test]
+    fn test_bucket_queue_strategy() {
+        use implementations::{ExhaustiveSolver, Problem01Knapsack};
+        use std::time::Duration;
+
+        const FEW_DECISIONS: usize = 12;
+        let knapsack = Problem01Knapsack::random(FEW_DECISIONS);
+        let optimum = ExhaustiveSolver::builder(&knapsack).solve().get_score();
+        let mut solver = BestFirstSolver::new(FEW_DECISIONS);
+        assert_eq!(solver.priority_strategy(), PriorityStrategy::BinaryHeap);
+        solver.set_priority_strategy(PriorityStrategy::BucketQueue);
+        let the_best = solver
+            .find_best_solution(&knapsack, Duration::from_secs(1))
+            .expect("could not find best solution");
+        assert_eq!(the_best.get_score(), optimum);
+
+        // Switching moves the frontier, and it is popped in the same order
+        let problem = ProblemSubsetSum::random(FEW_DECISIONS);
+        let mut solver = BestFirstSolver::<MinimalSolution>::new(FEW_DECISIONS);
+        solver.start_search(&problem);
+        for _ in 0..5 {
+            solver.step(&problem);
+        }
+        let num_open = solver.number_of_solutions();
+        let mut buckets = solver.clone();
+        buckets.set_priority_strategy(PriorityStrategy::BucketQueue);
+        assert_eq!(buckets.number_of_solutions(), num_open);
+        assert!(buckets.solutions.is_empty());
+        assert_eq!(
+            solver.snapshot_frontier(usize::MAX).len(),
+            buckets.snapshot_frontier(usize::MAX).len()
+        );
+        for snapshot in [solver.snapshot_frontier(3), buckets.snapshot_frontier(3)].iter() {
+            assert!(snapshot.len() <= 3);
+            assert!(snapshot.windows(2).all(|w| w[1].priority <= w[0].priority));
+        }
+        while let Some(from_heap) = solver.pop() {
+            let from_buckets = buckets.pop().expect("same number of solutions");
+            assert_eq!(from_heap.priority(), from_buckets.priority());
+        }
+        assert!(buckets.is_empty());
+    }
+
+    #[test]
+    fn test_max_open() {
+        use implementations::{ExhaustiveSolver, Problem01Knapsack};
+        use std::time::Duration;
+
+        const NUM_DECISIONS: usize = 16;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let optimum = ExhaustiveSolver::builder(&knapsack).solve().get_score();
+
+        // Never more than 8 open solutions; and the bound still holds
+        let mut solver = BestFirstSolver::new(NUM_DECISIONS).with_max_open(8);
+        solver.start_search(&knapsack);
+        for _ in 0..100 {
+            solver.step(&knapsack);
+            assert!(solver.number_of_solutions() <= 8);
+        }
+        let report = solver
+            .find_best_solution(&knapsack, Duration::from_secs(1))
+            .expect("could not find best solution");
+        assert!(knapsack.solution_is_legal(&report.best));
+        assert!(report.get_score() <= optimum);
+        assert!(optimum <= report.final_bound);
+        if solver.is_exhaustive() {
+            assert_eq!(report.get_score(), optimum); // nothing that mattered was dropped
+        } else {
+            assert!(report.get_score() < solver.dropped_bound().unwrap());
+        };
+
+        // Room enough: nothing lost
+        let mut solver = BestFirstSolver::new(NUM_DECISIONS).with_max_open(1 << NUM_DECISIONS);
+        let report = solver
+            .find_best_solution(&knapsack, Duration::from_secs(1))
+            .expect("could not find best solution");
+        assert_eq!(report.get_score(), optimum);
+        assert_eq!(report.final_bound, optimum);
+        assert_eq!(solver.num_dropped, 0);
+    }
+
+    #[test]
+    fn test_priority_functions() {
+        use implementations::{ExhaustiveSolver, Problem01Knapsack};
+        use optimizer::MemoryPruning;
+        use std::time::Duration;
+
+        const FEW_DECISIONS: usize = 12;
+        let knapsack = Problem01Knapsack::random(FEW_DECISIONS);
+        let optimum = ExhaustiveSolver::builder(&knapsack).solve().get_score();
+        for function in [
+            PriorityFunction::ScoreAndBound,
+            PriorityFunction::Score,
+            PriorityFunction::Bound,
+            PriorityFunction::MemoryPredicted,
+        ]
+        .iter()
+        {
+            // Still exhaustive, whatever the order
+            let mut solver = BestFirstSolver::new(FEW_DECISIONS).with_priority_function(*function);
+            assert_eq!(solver.priority_function(), *function);
+            // (a memory which never prunes, only predicts)
+            solver.pruning = Some(MemoryPruning::new(FEW_DECISIONS, f64::INFINITY));
+            let the_best = solver
+                .find_best_solution(&knapsack, Duration::from_secs(1))
+                .expect("could not find best solution");
+            assert_eq!(the_best.get_score(), optimum, "{:?}", function);
+            assert_eq!(solver.pruning.unwrap().num_pruned, 0);
+        }
+
+        // Changing the function re-orders the frontier
+        let mut solver = BestFirstSolver::new(FEW_DECISIONS);
+        solver.start_search(&knapsack);
+        for _ in 0..5 {
+            solver.step(&knapsack);
+        }
+        let num_open = solver.number_of_solutions();
+        solver.set_priority_function(PriorityFunction::Bound);
+        assert_eq!(solver.number_of_solutions(), num_open);
+        let mut last_bound = ScoreType::MAX;
+        while let Some(solution) = solver.pop() {
+            assert!(solution.get_best_score() <= last_bound);
+            last_bound = solution.get_best_score();
+        }
+    }
 }
@@ -1,7 +1,9 @@
 use log::*;
 
 use mhd_memory::*;
-use optimizer::{PriorityType, Problem, Solution, Solver};
+use optimizer::{
+    EliteArchive, MemoryUsage, PriorityType, Problem, Solution, Solver, SolverBuilder,
+};
 use std::collections::BinaryHeap;
 
 /// # Example Implementations
@@ -14,6 +16,8 @@ pub struct BestfirstMhdMonteCarloSolver<Sol: Solution, Prob: Problem<Sol = Sol>>
     pub mhd_memory: MhdMemory,
     pub solutions: BinaryHeap<Sol>,
     pub best_solution: Sol,
+    /// Optional archive of good and diverse solutions, see `EliteArchive`
+    pub elites: Option<EliteArchive<Sol>>,
     pub problem: Prob,
 }
 
@@ -44,6 +48,7 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> BestfirstMhdMonteCarloSolver<Sol,
             mhd_memory: MhdMemory::new(problem.problem_size()),
             solutions: BinaryHeap::new(),
             best_solution: problem.random_solution(),
+            elites: None,
             problem: problem.clone(),
         };
         // bootstrap the memory with random samples (but legal ones!)
@@ -51,6 +56,13 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> BestfirstMhdMonteCarloSolver<Sol,
         // Finished! Return what we've built!
         product
     }
+
+    /// Read the memory with `kernel` (see `DistanceKernel`), e.g. with a smaller threshold
+    /// for a large problem
+    pub fn with_kernel(mut self, kernel: DistanceKernel) -> Self {
+        self.mhd_memory.kernel = kernel;
+        self
+    }
 } // end private Methods
 
 /// Here are the public methods needed to implement Solver<Sol>
@@ -73,11 +85,6 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol>
         )
     }
 
-    #[inline]
-    fn new(_: usize) -> Self {
-        panic!("New(size) not defined for BestfirstMhdMonteCarloSolver!");
-    }
-
     // Methods used by the Unified Optimization Algorithm (identified above)
 
     #[inline]
@@ -85,6 +92,12 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol>
         self.solutions.len()
     }
 
+    #[inline]
+    fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::of_frontier::<Sol>(self.solutions.len(), self.width())
+            + MemoryUsage::of_memory(&self.mhd_memory)
+    }
+
     #[inline]
     fn is_empty(&self) -> bool {
         self.solutions.is_empty()
@@ -99,6 +112,9 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol>
         self.mhd_memory.clear();
         self.bootstrap_memory();
         self.best_solution = Sol::new(width);
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
     }
 
     #[inline]
@@ -169,6 +185,11 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol>
         &self.best_solution
     }
 
+    #[inline]
+    fn elite_archive(&mut self) -> Option<&mut EliteArchive<Sol>> {
+        self.elites.as_mut()
+    }
+
     #[inline]
     fn store_best_solution(&mut self, solution: Sol) {
         // we'd like to check for completion, but can't use proble.solution_is_complete( s )
@@ -179,6 +200,15 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol>
     } //end store_best_solution
 } // end imp Solver for BestfirstMhdMonteCarloSolver
 
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> SolverBuilder<Prob>
+    for BestfirstMhdMonteCarloSolver<Sol, Prob>
+{
+    #[inline]
+    fn from_problem(problem: &Prob) -> Self {
+        Self::builder(problem)
+    }
+}
+
 /**************************************************************************************/
 //////////////// TESTs for ProblemSubsetSum with  MonteCarloTreeSolver /////////////////
 #[cfg(test)]
@@ -1,7 +1,10 @@
 /// # Example Implementations
 ///
 ///
-use optimizer::{Solution, Solver};
+use optimizer::{
+    EliteArchive, FrontierEntry, MemoryPruning, MemoryUsage, Problem, Solution, Solver,
+    SolverBuilder, VisitedSet, VisitedStats,
+};
 
 /// ## Example Solver Implementation: Depth First Search
 ///
@@ -9,6 +12,8 @@ use optimizer::{Solution, Solver};
 /// ```rust
 /// use mhd_optimization::optimizer::{ Solution, MinimalSolution, Solver };
 /// use mhd_optimization::implementations::DepthFirstSolver;
+/// # extern crate mhd_memory;
+/// use mhd_memory::{ScoreType, ZERO_SCORE};
 ///
 /// let mut my_solver = DepthFirstSolver::< MinimalSolution >::new( 8 );
 ///
@@ -18,8 +23,8 @@ use optimizer::{Solution, Solver};
 /// let sol0 = MinimalSolution::new( 8 );
 /// let sol1 = MinimalSolution::random( 8 );
 ///
-/// assert_eq!( sol0.get_score(), 0 );
-/// assert_eq!( sol0.get_best_score(), 0 );
+/// assert_eq!( sol0.get_score(), ZERO_SCORE );
+/// assert_eq!( sol0.get_best_score(), ZERO_SCORE );
 /// assert_eq!( sol0.get_decision( 0 ), None );
 ///
 /// let mut sol2 = MinimalSolution::new( 4 );
@@ -31,10 +36,10 @@ use optimizer::{Solution, Solver};
 /// assert!(   sol2.get_decision( 2 ).unwrap( ) );
 /// assert_eq!( sol0.get_decision( 3 ), None );
 ///
-/// sol2.put_score(      42  );
-/// sol2.put_best_score( 88  );
-/// assert_eq!( sol2.get_score(),      42  );
-/// assert_eq!( sol2.get_best_score(), 88  );
+/// sol2.put_score(      42 as ScoreType );
+/// sol2.put_best_score( 88 as ScoreType );
+/// assert_eq!( sol2.get_score(),      42 as ScoreType );
+/// assert_eq!( sol2.get_best_score(), 88 as ScoreType );
 ///
 /// my_solver.push( sol0 );
 /// my_solver.push( sol1 );
@@ -52,8 +57,28 @@ use optimizer::{Solution, Solver};
 pub struct DepthFirstSolver<Sol: Solution> {
     pub solutions: Vec<Sol>,
     best_solution: Sol,
+    /// Optional archive of good and diverse solutions, see `EliteArchive`
+    pub elites: Option<EliteArchive<Sol>>,
+    /// Optional memory-guided pruning (None = classical, exact search)
+    pub pruning: Option<MemoryPruning>,
+    /// Optional duplicate-state pruning, see `VisitedSet` (None = expand every state)
+    pub visited: Option<VisitedSet>,
 }
 
+impl<Sol: Solution> DepthFirstSolver<Sol> {
+    /// A solver for problems with `size` decisions (which is all it needs to know)
+    #[inline]
+    pub fn new(size: usize) -> Self {
+        Self {
+            solutions: Vec::new(),
+            best_solution: Sol::new(size),
+            elites: None,
+            pruning: None,
+            visited: None,
+        }
+    }
+} // end private Methods
+
 impl<Sol: Solution> Solver<Sol> for DepthFirstSolver<Sol> {
     // type Sol = TwoSampleSolution;
 
@@ -72,19 +97,35 @@ impl<Sol: Solution> Solver<Sol> for DepthFirstSolver<Sol> {
         )
     }
 
+    // Methods used by the Unified Optimization Algorithm (identified above)
+
     #[inline]
-    fn new(size: usize) -> Self {
-        Self {
-            solutions: Vec::new(),
-            best_solution: Sol::new(size),
+    fn number_of_solutions(&self) -> usize {
+        self.solutions.len()
+    }
+
+    #[inline]
+    fn memory_usage(&self) -> MemoryUsage {
+        let frontier = MemoryUsage::of_frontier::<Sol>(self.number_of_solutions(), self.width());
+        match self.visited.as_ref() {
+            None => frontier,
+            Some(visited) => frontier + MemoryUsage::of_tree::<u64>(visited.len()),
         }
     }
 
-    // Methods used by the Unified Optimization Algorithm (identified above)
+    /// The top of the stack first
+    fn snapshot_frontier(&self, limit: usize) -> Vec<FrontierEntry> {
+        self.solutions
+            .iter()
+            .rev()
+            .take(limit)
+            .map(FrontierEntry::of)
+            .collect()
+    }
 
     #[inline]
-    fn number_of_solutions(&self) -> usize {
-        self.solutions.len()
+    fn is_exhaustive(&self) -> bool {
+        true
     }
 
     #[inline]
@@ -97,6 +138,15 @@ impl<Sol: Solution> Solver<Sol> for DepthFirstSolver<Sol> {
         self.solutions.clear();
         let size = self.best_solution.size();
         self.best_solution = Sol::new(size);
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
+        if let Some(pruning) = self.pruning.as_mut() {
+            pruning.clear();
+        };
+        if let Some(visited) = self.visited.as_mut() {
+            visited.clear();
+        };
     }
 
     #[inline]
@@ -114,6 +164,11 @@ impl<Sol: Solution> Solver<Sol> for DepthFirstSolver<Sol> {
         &self.best_solution
     }
 
+    #[inline]
+    fn elite_archive(&mut self) -> Option<&mut EliteArchive<Sol>> {
+        self.elites.as_mut()
+    }
+
     #[inline]
     fn store_best_solution(&mut self, solution: Sol) {
         // we'd like to check for completion, but can't use proble.solution_is_complete( s )
@@ -123,13 +178,52 @@ impl<Sol: Solution> Solver<Sol> for DepthFirstSolver<Sol> {
         self.best_solution = solution;
     }
 
+    /// The other's open solutions are stacked on top of these (and so are searched next).
+    fn merge(&mut self, mut other: Self) {
+        self.merge_best_solutions(&mut other);
+        self.solutions.append(&mut other.solutions);
+    }
+
+    // With memory pruning, learn complete children and drop hopeless incomplete ones;
+    // with a visited set, drop the children whose states were seen before
+    fn children_of_solution<Prob: Problem<Sol = Sol>>(
+        &mut self,
+        parent: &Sol,
+        problem: &Prob,
+    ) -> Vec<Sol> {
+        let children = problem.children_of_solution(parent);
+        let children = match self.pruning.as_mut() {
+            None => children,
+            Some(pruning) => {
+                pruning.filter_children(problem, children, self.best_solution.get_score())
+            }
+        };
+        match self.visited.as_mut() {
+            None => children,
+            Some(visited) => visited.filter_children(problem, children),
+        }
+    }
+
+    #[inline]
+    fn visited_stats(&self) -> Option<VisitedStats> {
+        self.visited.as_ref().map(|visited| visited.stats)
+    }
+
     // take default new_best_soluiton() method
 }
 
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> SolverBuilder<Prob> for DepthFirstSolver<Sol> {
+    #[inline]
+    fn from_problem(problem: &Prob) -> Self {
+        Self::new(problem.problem_size())
+    }
+}
+
 ///////////////////// TESTs for DepthFirstSolver /////////////////////
 #[cfg(test)]
 mod more_tests {
     use super::*;
+    use mhd_memory::{Score, ScoreType};
     use optimizer::{MinimalSolution, Solution};
 
     const NUM_DECISIONS: usize = 64; // for a start
@@ -160,4 +254,206 @@ mod more_tests {
         solver.clear();
         assert!(solver.is_empty());
     }
+
+    #[test]
+    fn test_depth_first_stepping() {
+        use implementations::ProblemSubsetSum;
+        use optimizer::{Problem, StepEvent};
+
+        const FEW_DECISIONS: usize = 8; // so we can be sure to find THE optimum!
+        let problem = ProblemSubsetSum::random(FEW_DECISIONS);
+        let mut solver = DepthFirstSolver::<MinimalSolution>::new(FEW_DECISIONS);
+
+        // Before start_search, there is nothing to pop
+        assert_eq!(StepEvent::Finished, solver.step(&problem).event);
+
+        solver.start_search(&problem);
+        assert_eq!(solver.number_of_solutions(), 1); // the starting solution
+
+        // The root is never complete, so the first step must branch (or bound)
+        let first_event = solver.step(&problem).event;
+        assert_ne!(first_event, StepEvent::Finished);
+        assert_ne!(first_event, StepEvent::Popped);
+
+        let mut num_steps = 1;
+        let mut num_new_bests = 0;
+        loop {
+            match solver.step(&problem).event {
+                StepEvent::Finished => break,
+                StepEvent::NewBest => num_new_bests += 1,
+                StepEvent::Pushed(n) => assert!(0 < n && n <= 2),
+                _ => {}
+            };
+            num_steps += 1;
+            assert!(num_steps < (1 << (FEW_DECISIONS + 1)));
+        }
+        assert!(solver.is_empty());
+        assert!(num_new_bests <= num_steps);
+        assert_eq!(solver.best_score(), problem.capacity);
+        assert!(problem.solution_is_complete(solver.best_solution()));
+    }
+
+    #[test]
+    fn test_depth_first_with_memory_pruning() {
+        use implementations::Problem01Knapsack;
+        use std::time::Duration;
+
+        const SOME_DECISIONS: usize = 20;
+        let knapsack = Problem01Knapsack::random(SOME_DECISIONS);
+        let mut solver = DepthFirstSolver::new(SOME_DECISIONS);
+        solver.pruning = Some(MemoryPruning::new(SOME_DECISIONS, 2.0));
+
+        let the_best = solver
+            .find_best_solution(&knapsack, Duration::from_millis(500))
+            .expect("could not find best solution");
+        assert!(knapsack.solution_is_legal(&the_best));
+        assert!(knapsack.solution_is_complete(&the_best));
+        assert_eq!(the_best.get_score(), knapsack.solution_score(&the_best));
+
+        let pruning = solver.pruning.as_ref().unwrap();
+        assert!(!pruning.memory.is_empty());
+
+        solver.clear();
+        assert!(solver.pruning.as_ref().unwrap().memory.is_empty());
+    }
+
+    #[test]
+    fn test_depth_first_merge() {
+        use implementations::ProblemSubsetSum;
+        use optimizer::{Problem, StepEvent};
+        use std::time::Duration;
+
+        const FEW_DECISIONS: usize = 10;
+        let problem = ProblemSubsetSum::random(FEW_DECISIONS);
+        let mut solver = DepthFirstSolver::<MinimalSolution>::new(FEW_DECISIONS);
+        solver.start_search(&problem);
+        for _ in 0..4 {
+            solver.step(&problem);
+        }
+        // Fork: a second worker takes half the frontier...
+        let mut worker = DepthFirstSolver::<MinimalSolution>::new(FEW_DECISIONS);
+        let half = solver.number_of_solutions() / 2;
+        worker.solutions = solver.solutions.split_off(half);
+        worker.store_best_solution(solver.best_solution().clone());
+        for _ in 0..3 {
+            worker.step(&problem);
+        }
+        // ... and joins again
+        let num_open = solver.number_of_solutions() + worker.number_of_solutions();
+        let best_score = solver.best_score().max(worker.best_score());
+        solver.merge(worker);
+        assert_eq!(solver.number_of_solutions(), num_open);
+        assert_eq!(solver.best_score(), best_score);
+
+        // Nothing was lost: the merged search still finds the optimum
+        let the_best = solver
+            .continue_search(&problem, Duration::from_secs(1))
+            .expect("could not find best solution");
+        assert!(problem.solution_is_complete(&the_best));
+        assert_eq!(the_best.get_score(), problem.capacity);
+        assert_eq!(StepEvent::Finished, solver.step(&problem).event);
+    }
+
+    #[test]
+    fn test_find_all_best_solutions() {
+        use implementations::ProblemSubsetSum;
+        use optimizer::{Problem, SolverConfig};
+        use std::time::Duration;
+
+        const FEW_DECISIONS: usize = 6;
+        let mut problem = ProblemSubsetSum::new(FEW_DECISIONS);
+        problem.weights = vec![1 as ScoreType; FEW_DECISIONS];
+        problem.capacity = 3 as ScoreType;
+        let mut solver = DepthFirstSolver::<MinimalSolution>::new(FEW_DECISIONS);
+        let time_limit = Duration::from_secs(1);
+
+        // Any 3 of the 6 items: 20 optimal solutions
+        let optima = solver
+            .find_all_best_solutions(&problem, time_limit, 0 as ScoreType)
+            .expect("could not find solutions");
+        assert_eq!(optima.len(), 20);
+        for solution in optima.iter() {
+            assert!(problem.solution_is_legal(solution));
+            assert!(problem.solution_is_complete(solution));
+            assert_eq!(solution.get_score(), 3 as ScoreType);
+        }
+
+        // ... and 15 more with only 2 items
+        solver.clear();
+        let near_optima = solver
+            .find_all_best_solutions(&problem, time_limit, 1 as ScoreType)
+            .expect("could not find solutions");
+        assert_eq!(near_optima.len(), 20 + 15);
+        assert_eq!(near_optima[0].get_score(), 3 as ScoreType);
+        assert_eq!(near_optima[34].get_score(), 2 as ScoreType);
+
+        // ... and within the limits of the config, like any search
+        solver.clear();
+        let config = SolverConfig {
+            node_limit: Some(5),
+            ..SolverConfig::from(time_limit)
+        };
+        let some_optima = solver
+            .find_all_best_solutions(&problem, config, 0 as ScoreType)
+            .expect("could not find solutions");
+        assert!(!some_optima.is_empty() && some_optima.len() < 20);
+    }
+
+    #[test]
+    fn test_snapshot_frontier() {
+        use implementations::ProblemSubsetSum;
+        use optimizer::{FrontierEntry, Problem};
+
+        const FEW_DECISIONS: usize = 10;
+        let problem = ProblemSubsetSum::random(FEW_DECISIONS);
+        let mut solver = DepthFirstSolver::<MinimalSolution>::new(FEW_DECISIONS);
+        assert!(solver.snapshot_frontier(10).is_empty());
+        solver.start_search(&problem);
+        for _ in 0..4 {
+            solver.step(&problem);
+        }
+        let snapshot = solver.snapshot_frontier(usize::MAX);
+        assert_eq!(snapshot.len(), solver.number_of_solutions());
+        assert_eq!(solver.snapshot_frontier(1).len(), 1.min(snapshot.len()));
+        for entry in snapshot.iter() {
+            assert!(entry.depth <= FEW_DECISIONS);
+            assert!(entry.score <= entry.bound);
+        }
+        // The next one to be popped comes first
+        if let Some(next) = solver.pop() {
+            assert_eq!(snapshot[0], FrontierEntry::of(&next));
+            assert_eq!(
+                snapshot[0].depth,
+                problem.first_open_decision(&next).unwrap()
+            );
+        };
+    }
+
+    #[test]
+    fn test_find_target_solution() {
+        use implementations::{ExhaustiveSolver, Problem01Knapsack};
+        use std::time::Duration;
+
+        const FEW_DECISIONS: usize = 12;
+        let knapsack = Problem01Knapsack::random(FEW_DECISIONS);
+        let optimum = ExhaustiveSolver::builder(&knapsack).solve().get_score();
+        let mut solver = DepthFirstSolver::new(FEW_DECISIONS);
+        let time_limit = Duration::from_secs(1);
+
+        let half = ScoreType::from_f64(optimum.to_f64() / 2.0);
+        for target in [1 as ScoreType, half, optimum].iter() {
+            let (the_best, time_to_target) = solver
+                .find_target_solution(&knapsack, time_limit, *target)
+                .expect("could not find solution");
+            assert!(*target <= the_best.get_score());
+            assert!(time_to_target.expect("target not reached") <= time_limit);
+            solver.clear();
+        }
+        // Beyond the optimum: never reached, so the search runs until it's finished
+        let (the_best, time_to_target) = solver
+            .find_target_solution(&knapsack, time_limit, optimum + 1 as ScoreType)
+            .expect("could not find solution");
+        assert_eq!(the_best.get_score(), optimum);
+        assert_eq!(time_to_target, None);
+    }
 }
@@ -0,0 +1,270 @@
+use log::*;
+
+/// # Example Implementations
+///
+/// ## Example Solver Implementation: Diverse Solutions
+///
+/// Sometimes the best solution isn't enough: near-optimal, but structurally different
+/// alternatives matter, too. The `DiverseSolver` wraps any other solver (the "inner" solver)
+/// and searches as it does, for any problem. But every complete solution the search comes
+/// across (see `Solver::new_best_solution`) is also offered to an `EliteArchive` of its own,
+/// which keeps (up to) `k` of the best solutions, pairwise at least `d` decisions apart
+/// (masked hamming distance, see `EliteArchive::distance_between`): a solution too close
+/// to a better one is filtered out, one better than those it is too close to replaces them.
+/// The inner solver's incumbent is not filtered, so an exhaustive inner solver still prunes
+/// with the best score -- and so it sees fewer alternatives than a heuristic would.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{EliteArchive, Problem, Solution, Solver};
+/// use mhd_optimization::implementations::{DiverseSolver, EvolutionarySolver, Problem01Knapsack};
+/// use std::time::Duration;
+///
+/// let knapsack = Problem01Knapsack::random(24);
+/// let mut solver = DiverseSolver::wrap(EvolutionarySolver::builder(&knapsack), 3, 4);
+/// let best = solver.find_best_solution(&knapsack, Duration::from_millis(100)).unwrap();
+///
+/// let alternatives = solver.solutions();
+/// assert!(!alternatives.is_empty() && alternatives.len() <= 3);
+/// assert_eq!(alternatives[0].get_score(), best.get_score());
+/// for (index, first) in alternatives.iter().enumerate() {
+///     for second in alternatives[index + 1..].iter() {
+///         assert!(4 <= EliteArchive::distance_between(first, second));
+///     }
+/// }
+/// ```
+use optimizer::{
+    EliteArchive, FrontierEntry, MemoryUsage, Problem, Solution, Solver, SolverBuilder,
+    VisitedStats,
+};
+
+use mhd_memory::ScoreType;
+
+/// Number of solutions a `DiverseSolver` looks for, unless told otherwise
+pub const DEFAULT_NUM_DIVERSE_SOLUTIONS: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct DiverseSolver<Sol: Solution, Inner: Solver<Sol>> {
+    pub inner: Inner,
+    /// The diverse solutions found so far, see `solutions`
+    pub diversity: EliteArchive<Sol>,
+}
+
+impl<Sol: Solution, Inner: Solver<Sol>> DiverseSolver<Sol, Inner> {
+    /// Search with `inner`, for up to `k` (at least one) complete solutions,
+    /// pairwise at least `d` decisions apart
+    pub fn wrap(inner: Inner, k: usize, d: u64) -> Self {
+        Self {
+            inner,
+            // (the archive's elites are *more* than its min_distance apart)
+            diversity: EliteArchive::new(k.max(1), d.saturating_sub(1)),
+        }
+    }
+
+    /// The diverse solutions, best first
+    #[inline]
+    pub fn solutions(&self) -> &[Sol] {
+        self.diversity.elites()
+    }
+} // end private Methods
+
+impl<Sol: Solution, Inner: Solver<Sol>> Solver<Sol> for DiverseSolver<Sol, Inner> {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "DiverseSolver"
+    }
+
+    #[inline]
+    fn short_description(&self) -> String {
+        format!(
+            "{} holding {} of {} solutions, best score is {}, inner solver: {}",
+            self.name(),
+            self.diversity.len(),
+            self.diversity.capacity,
+            self.best_score(),
+            self.inner.short_description()
+        )
+    }
+
+    // Methods used by the Unified Optimization Algorithm (identified above)
+
+    #[inline]
+    fn number_of_solutions(&self) -> usize {
+        self.inner.number_of_solutions()
+    }
+
+    #[inline]
+    fn memory_usage(&self) -> MemoryUsage {
+        self.inner.memory_usage()
+    }
+
+    #[inline]
+    fn snapshot_frontier(&self, limit: usize) -> Vec<FrontierEntry> {
+        self.inner.snapshot_frontier(limit)
+    }
+
+    #[inline]
+    fn is_exhaustive(&self) -> bool {
+        self.inner.is_exhaustive()
+    }
+
+    #[inline]
+    fn dropped_bound(&self) -> Option<ScoreType> {
+        self.inner.dropped_bound()
+    }
+
+    #[inline]
+    fn visited_stats(&self) -> Option<VisitedStats> {
+        self.inner.visited_stats()
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    #[inline]
+    fn is_finished(&self) -> bool {
+        self.inner.is_finished()
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.inner.clear();
+        self.diversity.clear();
+    }
+
+    #[inline]
+    fn push(&mut self, solution: Sol) {
+        self.inner.push(solution);
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<Sol> {
+        self.inner.pop()
+    }
+
+    #[inline]
+    fn best_solution(&self) -> &Sol {
+        self.inner.best_solution()
+    }
+
+    /// The archive of diverse solutions: `new_best_solution` offers every complete solution
+    #[inline]
+    fn elite_archive(&mut self) -> Option<&mut EliteArchive<Sol>> {
+        Some(&mut self.diversity)
+    }
+
+    /// The diverse solutions, not just the best distinct ones
+    fn best_solutions(&mut self, k: usize) -> Vec<Sol> {
+        self.solutions().iter().take(k).cloned().collect()
+    }
+
+    #[inline]
+    fn store_best_solution(&mut self, solution: Sol) {
+        self.inner.store_best_solution(solution);
+    }
+
+    #[inline]
+    fn reseed(&mut self, seed: u64) {
+        self.inner.reseed(seed);
+    }
+
+    #[inline]
+    fn children_of_solution<Prob: Problem<Sol = Sol>>(
+        &mut self,
+        parent: &Sol,
+        problem: &Prob,
+    ) -> Vec<Sol> {
+        self.inner.children_of_solution(parent, problem)
+    }
+
+    /// Start a new search, with no diverse solutions yet (but the random starting one)
+    fn start_search_from<Prob: Problem<Sol = Sol>>(&mut self, problem: &Prob, root: Sol) {
+        self.diversity.clear();
+        self.inner.start_search_from(problem, root);
+        let first = self.inner.best_solution().clone();
+        self.diversity.offer(&first);
+        debug!(
+            "{} looks for {} solutions, more than {} apart",
+            self.name(),
+            self.diversity.capacity,
+            self.diversity.min_distance
+        );
+    }
+} // end impl Solver for DiverseSolver
+
+impl<Sol, Prob, Inner> SolverBuilder<Prob> for DiverseSolver<Sol, Inner>
+where
+    Sol: Solution,
+    Prob: Problem<Sol = Sol>,
+    Inner: SolverBuilder<Prob>,
+{
+    /// `DEFAULT_NUM_DIVERSE_SOLUTIONS`, a quarter of the decisions apart
+    #[inline]
+    fn from_problem(problem: &Prob) -> Self {
+        Self::wrap(
+            Inner::from_problem(problem),
+            DEFAULT_NUM_DIVERSE_SOLUTIONS,
+            (problem.problem_size() / 4) as u64,
+        )
+    }
+}
+
+/**************************************************************************************/
+//////////////// TESTs for DiverseSolver /////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{DepthFirstSolver, ExhaustiveSolver, MhdMonteCarloSolver};
+    use implementations::{Problem01Knapsack, ProblemSubsetSum};
+    use std::time::Duration;
+
+    fn assert_diverse<Sol: Solution>(solutions: &[Sol], d: u64) {
+        for (index, first) in solutions.iter().enumerate() {
+            for second in solutions[index + 1..].iter() {
+                assert!(second.get_score() <= first.get_score());
+                assert!(d <= EliteArchive::distance_between(first, second));
+            }
+        }
+    }
+
+    #[test]
+    fn test_diverse_solver() {
+        const NUM_DECISIONS: usize = 12;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let optimum = ExhaustiveSolver::builder(&knapsack).solve().get_score();
+
+        // An exhaustive inner solver: still the optimum, and some alternatives
+        let mut solver = DiverseSolver::wrap(DepthFirstSolver::new(NUM_DECISIONS), 3, 2);
+        let best = solver
+            .find_best_solution(&knapsack, Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(best.get_score(), optimum);
+        assert!(solver.is_exhaustive());
+        let solutions = solver.solutions().to_vec();
+        assert_eq!(solutions[0].get_score(), optimum);
+        assert!(solutions.len() <= 3);
+        for solution in solutions.iter() {
+            assert!(knapsack.solution_is_complete(solution));
+            assert!(knapsack.solution_is_legal(solution));
+        }
+        assert_diverse(&solutions, 2);
+        assert_eq!(
+            solver.best_solutions(2),
+            solutions[..solutions.len().min(2)]
+        );
+
+        // A heuristic, on another problem
+        const MORE_DECISIONS: usize = 24;
+        let problem = ProblemSubsetSum::random(MORE_DECISIONS);
+        let mut solver = DiverseSolver::wrap(MhdMonteCarloSolver::builder(&problem), 4, 6);
+        solver
+            .find_best_solution(&problem, Duration::from_millis(100))
+            .unwrap();
+        assert!(!solver.solutions().is_empty());
+        assert_diverse(solver.solutions(), 6);
+
+        solver.clear();
+        assert!(solver.solutions().is_empty());
+    }
+}
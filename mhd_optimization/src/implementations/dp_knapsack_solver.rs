@@ -0,0 +1,365 @@
+use log::*;
+
+use mhd_memory::{Score, ScoreType, ZERO_SCORE};
+
+/// # Example Implementations
+///
+/// ## Example Solver Implementation: Dynamic Programming for the 0/1 Knapsack
+///
+/// An exact solver for `Problem01Knapsack`, to certify the optimum of a knapsack benchmark
+/// (so the heuristic solvers' true optimality gaps can be computed). Two classics:
+///
+/// * The pseudo-polynomial DP over the capacities 0..=C: after each item, the best value of
+///   any subset of the items so far weighing at most `c`, for every `c` (O(n·C) time).
+///   To rebuild the best subset, it remembers which items improved which capacities
+///   (one bit each, so memory is O(n·C) bits -- see `max_dp_cells`).
+/// * For large capacities, Horowitz and Sahni's meet in the middle: all 2^(n/2) subsets of
+///   each half of the items, the second half's sorted by weight and thinned out to those
+///   which are better than all lighter ones, so that each subset of the first half finds the
+///   best partner by binary search (O(2^(n/2)·n) time, O(2^(n/2)) memory, n at most
+///   `MAX_MEET_IN_THE_MIDDLE_DECISIONS`).
+///
+/// `best_items` chooses whichever fits.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, Solution, Solver};
+/// use mhd_optimization::implementations::{DpKnapsackSolver, Problem01Knapsack};
+///
+/// let knapsack = Problem01Knapsack::random(40);
+/// let mut solver = DpKnapsackSolver::builder(&knapsack);
+/// let optimum = solver.solve();
+/// assert!(knapsack.solution_is_legal(&optimum));
+/// assert!(knapsack.solution_is_complete(&optimum));
+///
+/// // Both methods agree
+/// let items = solver.meet_in_the_middle_items(); // (the first and the last 20 items)
+/// let value: u64 = items.iter().map(|&item| knapsack.values[item] as u64).sum();
+/// assert_eq!(value, optimum.get_score() as u64);
+/// ```
+use implementations::{Problem01Knapsack, ZeroOneKnapsackSolution};
+use optimizer::{EliteArchive, Problem, Solution, Solver, SolverBuilder};
+
+/// Largest table (items times capacities) the DP may use, unless told otherwise: 32 MiB
+pub const DEFAULT_MAX_DP_CELLS: usize = 1 << 28;
+
+/// 2^20 subsets per half is already more than enough...
+pub const MAX_MEET_IN_THE_MIDDLE_DECISIONS: usize = 40;
+
+#[derive(Debug, Clone)]
+pub struct DpKnapsackSolver {
+    /// Largest DP table (in bits, one per item and capacity) before meeting in the middle
+    pub max_dp_cells: usize,
+    /// Has the optimum been found (and popped)?
+    pub solved: bool,
+    pub best_solution: ZeroOneKnapsackSolution,
+    /// Optional archive of good and diverse solutions, see `EliteArchive`
+    pub elites: Option<EliteArchive<ZeroOneKnapsackSolution>>,
+    pub problem: Problem01Knapsack,
+}
+
+// A subset of half the items: its weight, its value, and the items (bit i: item first + i)
+#[derive(Debug, Clone, Copy)]
+struct HalfSubset {
+    weight: u64,
+    value: ScoreType,
+    items: u64,
+}
+
+impl DpKnapsackSolver {
+    // a replacement for Self::new( size )
+    #[inline]
+    pub fn builder(problem: &Problem01Knapsack) -> Self {
+        Self {
+            max_dp_cells: DEFAULT_MAX_DP_CELLS,
+            solved: false,
+            best_solution: problem.random_solution(),
+            elites: None,
+            problem: problem.clone(),
+        }
+    }
+
+    /// Would the DP table fit (see `max_dp_cells`)?
+    #[inline]
+    pub fn dp_fits(&self) -> bool {
+        let capacities = self.problem.capacity() as usize + 1;
+        capacities
+            .checked_mul(self.problem.problem_size())
+            .is_some_and(|cells| cells <= self.max_dp_cells)
+    }
+
+    /// The items (indices) of a best knapsack -- by DP if it fits, else by meeting in the middle
+    pub fn best_items(&self) -> Vec<usize> {
+        if self.dp_fits() {
+            self.dp_items()
+        } else {
+            self.meet_in_the_middle_items()
+        }
+    }
+
+    /// The items (indices) of a best knapsack, by DP over the capacities
+    pub fn dp_items(&self) -> Vec<usize> {
+        let weights = &self.problem.basis.weights;
+        let capacity = self.problem.capacity() as usize;
+        let num_words = capacity / 64 + 1;
+        let mut best = vec![ZERO_SCORE; capacity + 1]; // best value weighing at most c
+        let mut improved = Vec::with_capacity(self.problem.problem_size()); // bit c, per item
+
+        for (&weight, &value) in weights.iter().zip(self.problem.values.iter()) {
+            let weight = weight as usize;
+            let mut bits = vec![0u64; num_words];
+            if weight <= capacity {
+                // Walk downwards, so each item is taken at most once
+                for c in (weight..=capacity).rev() {
+                    let with_item = best[c - weight] + value;
+                    if best[c] < with_item {
+                        best[c] = with_item;
+                        bits[c / 64] |= 1 << (c % 64);
+                    };
+                }
+            };
+            improved.push(bits);
+        } // end for all items
+        debug!("{} finds best value {} by DP", self.name(), best[capacity]);
+
+        // The last item which improved the capacity left is in, the rest is up to the others
+        let mut result = Vec::new();
+        let mut left = capacity;
+        for (item, bits) in improved.iter().enumerate().rev() {
+            if 0 != bits[left / 64] & (1 << (left % 64)) {
+                result.push(item);
+                left -= weights[item] as usize;
+            };
+        }
+        result.reverse();
+        result
+    }
+
+    // All subsets of the items first..last (which fit)
+    fn half_subsets(&self, first: usize, last: usize) -> Vec<HalfSubset> {
+        let capacity = self.problem.capacity() as u64;
+        let mut result = vec![HalfSubset {
+            weight: 0,
+            value: ZERO_SCORE,
+            items: 0,
+        }];
+        for item in first..last {
+            let weight = self.problem.basis.weights[item] as u64;
+            let value = self.problem.values[item];
+            let bit = 1 << (item - first);
+            for index in 0..result.len() {
+                let subset = result[index];
+                if subset.weight + weight <= capacity {
+                    result.push(HalfSubset {
+                        weight: subset.weight + weight,
+                        value: subset.value + value,
+                        items: subset.items | bit,
+                    });
+                };
+            }
+        }
+        result
+    }
+
+    /// The items (indices) of a best knapsack, by meeting in the middle (Horowitz & Sahni)
+    pub fn meet_in_the_middle_items(&self) -> Vec<usize> {
+        let size = self.problem.problem_size();
+        assert!(
+            size <= MAX_MEET_IN_THE_MIDDLE_DECISIONS,
+            "DpKnapsackSolver: {} decisions are too many to meet in the middle",
+            size
+        );
+        let capacity = self.problem.capacity() as u64;
+        let middle = size / 2;
+        let firsts = self.half_subsets(0, middle);
+        let mut seconds = self.half_subsets(middle, size);
+
+        // Lightest first, and of those the best; then drop all which a lighter one beats
+        seconds.sort_by(|a, b| {
+            a.weight
+                .cmp(&b.weight)
+                .then_with(|| b.value.cmp_scores(&a.value))
+        });
+        let mut frontier: Vec<HalfSubset> = Vec::with_capacity(seconds.len());
+        for subset in seconds {
+            if frontier.last().is_none_or(|last| last.value < subset.value) {
+                frontier.push(subset);
+            };
+        }
+
+        // Each subset of the first half, with the best of the second which still fits
+        let mut best = (ZERO_SCORE, 0, 0);
+        for first in firsts.iter() {
+            let room = capacity - first.weight;
+            let fitting = frontier.partition_point(|second| second.weight <= room);
+            if let Some(second) = fitting.checked_sub(1).map(|index| &frontier[index]) {
+                if best.0 < first.value + second.value {
+                    best = (first.value + second.value, first.items, second.items);
+                };
+            };
+        }
+        debug!("{} finds best value {} in the middle", self.name(), best.0);
+        (0..middle)
+            .filter(|item| 0 != best.1 & (1 << item))
+            .chain((middle..size).filter(|item| 0 != best.2 & (1 << (item - middle))))
+            .collect()
+    }
+
+    /// Find the best items, and return (and store) the best solution
+    pub fn solve(&mut self) -> ZeroOneKnapsackSolution {
+        let items = self.best_items();
+        let mut result = self.problem.starting_solution();
+        while let Some(index) = self.problem.first_open_decision(&result) {
+            result.make_decision(index, items.contains(&index));
+            self.problem.apply_rules(&mut result);
+        }
+        debug_assert!(self.problem.solution_is_legal(&result));
+        debug_assert!(
+            items
+                .iter()
+                .map(|&item| self.problem.values[item])
+                .sum::<ScoreType>()
+                <= result.get_score()
+        );
+        self.solved = true;
+        self.store_best_solution(result.clone());
+        result
+    }
+} // end private Methods
+
+impl Solver<ZeroOneKnapsackSolution> for DpKnapsackSolver {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "DpKnapsackSolver"
+    }
+
+    #[inline]
+    fn short_description(&self) -> String {
+        format!(
+            "{} ({}), best score is {}",
+            self.name(),
+            if self.solved { "solved" } else { "not solved" },
+            self.best_score()
+        )
+    }
+
+    // Methods used by the Unified Optimization Algorithm (identified above)
+
+    #[inline]
+    fn number_of_solutions(&self) -> usize {
+        self.solved as usize
+    }
+
+    #[inline]
+    fn is_finished(&self) -> bool {
+        self.solved
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.solved = false;
+        let size = self.best_solution.size();
+        self.best_solution = ZeroOneKnapsackSolution::new(size);
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
+    }
+
+    /// The DP needs no partial solutions; better complete ones are stored, though.
+    #[inline]
+    fn push(&mut self, solution: ZeroOneKnapsackSolution) {
+        if self.problem.solution_is_complete(&solution) && self.best_score() < solution.get_score()
+        {
+            self.store_best_solution(solution);
+        }
+    }
+
+    /// The first pop finds the optimum and returns it, then there is nothing left.
+    #[inline]
+    fn pop(&mut self) -> Option<ZeroOneKnapsackSolution> {
+        if self.solved {
+            None
+        } else {
+            Some(self.solve())
+        }
+    }
+
+    #[inline]
+    fn best_solution(&self) -> &ZeroOneKnapsackSolution {
+        &self.best_solution
+    }
+
+    #[inline]
+    fn elite_archive(&mut self) -> Option<&mut EliteArchive<ZeroOneKnapsackSolution>> {
+        self.elites.as_mut()
+    }
+
+    #[inline]
+    fn store_best_solution(&mut self, solution: ZeroOneKnapsackSolution) {
+        // we'd like to check for completion, but can't use proble.solution_is_complete( s )
+        debug_assert_eq!(solution.get_score(), solution.get_best_score());
+        self.best_solution = solution;
+    }
+} // end impl Solver for DpKnapsackSolver
+
+impl SolverBuilder<Problem01Knapsack> for DpKnapsackSolver {
+    #[inline]
+    fn from_problem(problem: &Problem01Knapsack) -> Self {
+        Self::builder(problem)
+    }
+}
+
+/**************************************************************************************/
+//////////////// TESTs for DpKnapsackSolver /////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{DepthFirstSolver, ExhaustiveSolver};
+    use std::time::Duration;
+
+    #[test]
+    fn test_dp_knapsack() {
+        let mut knapsack = Problem01Knapsack::new(4);
+        knapsack.basis.weights = [5, 4, 6, 3].iter().map(|&x| x as ScoreType).collect();
+        knapsack.values = [10, 40, 30, 50].iter().map(|&x| x as ScoreType).collect();
+        knapsack.basis.capacity = 10 as ScoreType; // 40 + 50 = 90 (weight 7), or 30 + 50 = 80 (weight 9)...
+        let mut solver = DpKnapsackSolver::builder(&knapsack);
+        assert!(solver.dp_fits());
+        assert_eq!(solver.dp_items(), vec![1, 3]);
+        assert_eq!(solver.meet_in_the_middle_items(), vec![1, 3]);
+
+        knapsack.basis.capacity = 13 as ScoreType; // 40 + 30 + 50 = 120 (weight 13)
+        solver = DpKnapsackSolver::builder(&knapsack);
+        solver.max_dp_cells = 0; // meet in the middle
+        assert!(!solver.dp_fits());
+        let optimum = solver.solve();
+        assert_eq!(optimum.get_score(), 120 as ScoreType);
+        assert!(solver.is_finished());
+        assert!(solver.pop().is_none());
+        solver.clear();
+        assert!(!solver.is_finished());
+    }
+
+    #[test]
+    fn test_dp_knapsack_is_an_oracle() {
+        const FEW_DECISIONS: usize = 12;
+        for _ in 0..5 {
+            let knapsack = Problem01Knapsack::random(FEW_DECISIONS);
+            let oracle = ExhaustiveSolver::builder(&knapsack).solve();
+            let mut solver = DpKnapsackSolver::builder(&knapsack);
+            let optimum = solver.solve();
+            assert_eq!(optimum.get_score(), oracle.get_score());
+            let in_the_middle: ScoreType = solver
+                .meet_in_the_middle_items()
+                .iter()
+                .map(|&item| knapsack.values[item])
+                .sum();
+            assert_eq!(in_the_middle, oracle.get_score());
+
+            let mut solver = DepthFirstSolver::new(FEW_DECISIONS);
+            let the_best = solver
+                .find_best_solution(&knapsack, Duration::from_secs(1))
+                .expect("could not find best solution");
+            assert_eq!(the_best.get_score(), optimum.get_score());
+        }
+    }
+}
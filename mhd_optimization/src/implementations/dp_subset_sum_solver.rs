@@ -0,0 +1,261 @@
+use log::*;
+
+use mhd_memory::ScoreType;
+
+/// # Example Implementations
+///
+/// ## Example Solver Implementation: Dynamic Programming for Subset Sum
+///
+/// An exact, pseudo-polynomial solver for `ProblemSubsetSum`: the classical DP over the set of
+/// reachable sums, kept as a bitset, so adding an item is one shift-and-or over C/64 words
+/// (O(n·C/64) in all, for n items and capacity C).
+/// To rebuild the best subset, each sum also remembers the item which first reached it.
+///
+/// It certifies the optimum of a subset sum benchmark, and is a correctness oracle for the
+/// heuristic solvers (as long as C is not too large -- memory is O(C)).
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, Solution, Solver};
+/// use mhd_optimization::implementations::{DpSubsetSumSolver, ProblemSubsetSum};
+///
+/// let problem = ProblemSubsetSum::random(64);
+/// let mut solver = DpSubsetSumSolver::builder(&problem);
+/// let optimum = solver.solve();
+/// assert!(problem.solution_is_legal(&optimum));
+/// assert!(problem.solution_is_complete(&optimum));
+/// // random capacities are always the sum of some of the weights
+/// assert_eq!(optimum.get_score(), problem.capacity);
+/// ```
+use implementations::ProblemSubsetSum;
+use optimizer::{EliteArchive, MinimalSolution, Problem, Solution, Solver, SolverBuilder};
+
+#[derive(Debug, Clone)]
+pub struct DpSubsetSumSolver {
+    /// Has the DP been run (and its solution been popped)?
+    pub solved: bool,
+    pub best_solution: MinimalSolution,
+    /// Optional archive of good and diverse solutions, see `EliteArchive`
+    pub elites: Option<EliteArchive<MinimalSolution>>,
+    pub problem: ProblemSubsetSum,
+}
+
+impl DpSubsetSumSolver {
+    // a replacement for Self::new( size )
+    #[inline]
+    pub fn builder(problem: &ProblemSubsetSum) -> Self {
+        Self {
+            solved: false,
+            best_solution: problem.random_solution(),
+            elites: None,
+            problem: problem.clone(),
+        }
+    }
+
+    /// The items (indices) of a best subset, i.e. one with the largest sum not above capacity
+    pub fn best_subset(&self) -> Vec<usize> {
+        let capacity = self.problem.capacity as usize;
+        let num_words = capacity / 64 + 1;
+        let mut reachable = vec![0u64; num_words]; // bit s: is the sum s reachable?
+        reachable[0] = 1;
+        let mut first_item = vec![usize::MAX; capacity + 1]; // the item which first reached s
+        let top_mask = u64::MAX >> (63 - capacity % 64); // bits 0..=capacity of the last word
+
+        for (item, &weight) in self.problem.weights.iter().enumerate() {
+            let weight = weight as usize;
+            if 0 == weight || capacity < weight {
+                continue;
+            };
+            let (word_shift, bit_shift) = (weight / 64, weight % 64);
+            // Walk downwards, so every word is read before it is changed
+            for word in (word_shift..num_words).rev() {
+                let mut shifted = reachable[word - word_shift] << bit_shift;
+                if 0 < bit_shift && word_shift < word {
+                    shifted |= reachable[word - word_shift - 1] >> (64 - bit_shift);
+                };
+                if word + 1 == num_words {
+                    shifted &= top_mask;
+                };
+                let mut new_sums = shifted & !reachable[word];
+                while 0 != new_sums {
+                    let bit = new_sums.trailing_zeros() as usize;
+                    first_item[64 * word + bit] = item;
+                    new_sums &= new_sums - 1;
+                }
+                reachable[word] |= shifted;
+            }
+            if 0 != reachable[capacity / 64] & (1 << (capacity % 64)) {
+                break; // can't do better than full
+            };
+        } // end for all items
+
+        // The best sum, and the items which add up to it
+        let mut sum = (0..=capacity)
+            .rev()
+            .find(|&s| 0 != reachable[s / 64] & (1 << (s % 64)))
+            .unwrap_or(0);
+        debug!("{} finds best sum {}", self.name(), sum);
+        let mut result = Vec::new();
+        while 0 < sum {
+            let item = first_item[sum];
+            result.push(item);
+            sum -= self.problem.weights[item] as usize;
+        }
+        result.reverse();
+        result
+    }
+
+    /// Run the DP, and return (and store) the best solution
+    pub fn solve(&mut self) -> MinimalSolution {
+        let subset = self.best_subset();
+        let mut result = self.problem.starting_solution();
+        while let Some(index) = self.problem.first_open_decision(&result) {
+            result.make_decision(index, subset.contains(&index));
+            self.problem.apply_rules(&mut result);
+        }
+        debug_assert!(self.problem.solution_is_legal(&result));
+        debug_assert_eq!(
+            result.get_score(),
+            subset
+                .iter()
+                .map(|&item| self.problem.weights[item])
+                .sum::<ScoreType>()
+        );
+        self.solved = true;
+        self.store_best_solution(result.clone());
+        result
+    }
+} // end private Methods
+
+impl Solver<MinimalSolution> for DpSubsetSumSolver {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "DpSubsetSumSolver"
+    }
+
+    #[inline]
+    fn short_description(&self) -> String {
+        format!(
+            "{} ({}), best score is {}",
+            self.name(),
+            if self.solved { "solved" } else { "not solved" },
+            self.best_score()
+        )
+    }
+
+    // Methods used by the Unified Optimization Algorithm (identified above)
+
+    #[inline]
+    fn number_of_solutions(&self) -> usize {
+        self.solved as usize
+    }
+
+    #[inline]
+    fn is_finished(&self) -> bool {
+        self.solved
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.solved = false;
+        let size = self.best_solution.size();
+        self.best_solution = MinimalSolution::new(size);
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
+    }
+
+    /// The DP needs no partial solutions; better complete ones are stored, though.
+    #[inline]
+    fn push(&mut self, solution: MinimalSolution) {
+        if self.problem.solution_is_complete(&solution) && self.best_score() < solution.get_score()
+        {
+            self.store_best_solution(solution);
+        }
+    }
+
+    /// The first pop runs the DP and returns the optimum, then there is nothing left.
+    #[inline]
+    fn pop(&mut self) -> Option<MinimalSolution> {
+        if self.solved {
+            None
+        } else {
+            Some(self.solve())
+        }
+    }
+
+    #[inline]
+    fn best_solution(&self) -> &MinimalSolution {
+        &self.best_solution
+    }
+
+    #[inline]
+    fn elite_archive(&mut self) -> Option<&mut EliteArchive<MinimalSolution>> {
+        self.elites.as_mut()
+    }
+
+    #[inline]
+    fn store_best_solution(&mut self, solution: MinimalSolution) {
+        // we'd like to check for completion, but can't use proble.solution_is_complete( s )
+        debug_assert_eq!(solution.get_score(), solution.get_best_score());
+        self.best_solution = solution;
+    }
+} // end impl Solver for DpSubsetSumSolver
+
+impl SolverBuilder<ProblemSubsetSum> for DpSubsetSumSolver {
+    #[inline]
+    fn from_problem(problem: &ProblemSubsetSum) -> Self {
+        Self::builder(problem)
+    }
+}
+
+/**************************************************************************************/
+//////////////// TESTs for DpSubsetSumSolver /////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{DepthFirstSolver, ExhaustiveSolver};
+    use std::time::Duration;
+
+    #[test]
+    fn test_dp_subset_sum() {
+        let mut problem = ProblemSubsetSum::new(5);
+        problem.weights = [70, 30, 45, 64, 5]
+            .iter()
+            .map(|&x| x as ScoreType)
+            .collect();
+        problem.capacity = 100 as ScoreType; // 70 + 30, or 30 + 64 + 5 = 99, ...
+        let solver = DpSubsetSumSolver::builder(&problem);
+        assert_eq!(solver.best_subset(), vec![0, 1]);
+
+        problem.capacity = 99 as ScoreType; // only 30 + 64 + 5 = 99
+        let mut solver = DpSubsetSumSolver::builder(&problem);
+        assert_eq!(solver.best_subset(), vec![1, 3, 4]);
+
+        problem.capacity = 98 as ScoreType; // 30 + 64 = 94 is best
+        solver = DpSubsetSumSolver::builder(&problem);
+        let optimum = solver.solve();
+        assert_eq!(optimum.get_score(), 94 as ScoreType);
+        assert!(solver.is_finished());
+        assert!(solver.pop().is_none());
+        solver.clear();
+        assert!(!solver.is_finished());
+    }
+
+    #[test]
+    fn test_dp_is_an_oracle() {
+        const FEW_DECISIONS: usize = 12;
+        for _ in 0..5 {
+            let mut problem = ProblemSubsetSum::random(FEW_DECISIONS);
+            problem.capacity -= 1 as ScoreType; // usually no longer reachable
+            let optimum = DpSubsetSumSolver::builder(&problem).solve();
+            let oracle = ExhaustiveSolver::builder(&problem).solve();
+            assert_eq!(optimum.get_score(), oracle.get_score());
+
+            let mut solver = DepthFirstSolver::new(FEW_DECISIONS);
+            let the_best = solver
+                .find_best_solution(&problem, Duration::from_secs(1))
+                .expect("could not find best solution");
+            assert_eq!(the_best.get_score(), optimum.get_score());
+        }
+    }
+}
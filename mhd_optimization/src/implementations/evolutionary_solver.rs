@@ -0,0 +1,279 @@
+use log::*;
+use rand::prelude::*;
+
+use mhd_memory::ScoreType;
+
+/// # Example Implementations
+///
+/// ## Example Solver Implementation: a (1+λ) Evolution Strategy
+///
+/// The simplest evolutionary algorithm there is, and a cheap, strong baseline:
+/// Each `pop` mutates the best solution so far (the parent) by flipping every decision
+/// with probability `flip_rate`, and repairs the result by making the (mutated) decisions
+/// one after the other, applying the rules after each one (`Problem::apply_rules`).
+/// The best of `lambda` offspring replaces the parent, if it is better.
+/// After every generation, the flip rate adapts itself: it grows after a generation which
+/// improved the parent, and shrinks (more slowly) after one which didn't.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, Solution, Solver};
+/// use mhd_optimization::implementations::{EvolutionarySolver, Problem01Knapsack};
+/// use std::time::Duration;
+///
+/// let knapsack = Problem01Knapsack::random(32);
+/// let mut solver = EvolutionarySolver::builder(&knapsack);
+/// solver.lambda = 8;
+///
+/// let best = solver.find_best_solution(&knapsack, Duration::from_millis(200))
+///                  .expect("could not find best solution");
+/// assert!(knapsack.solution_is_legal(&best));
+/// assert!(knapsack.solution_is_complete(&best));
+/// assert!(0 < solver.num_generations());
+/// ```
+use optimizer::{EliteArchive, Problem, Solution, Solver, SolverBuilder};
+
+// The flip rate is multiplied by this after a successful generation, and divided by its
+// fourth root after an unsuccessful one (a version of the "1/5 success rule").
+const ADAPTATION_FACTOR: f64 = 2.0;
+const MAX_FLIP_RATE: f64 = 0.5;
+
+#[derive(Debug, Clone)]
+pub struct EvolutionarySolver<Sol: Solution, Prob: Problem<Sol = Sol>> {
+    /// Number of offspring per generation (the λ in (1+λ))
+    pub lambda: usize,
+    /// Probability of flipping each decision (self-adapted, between 1/width and 1/2)
+    pub flip_rate: f64,
+    /// Number of offspring produced so far
+    pub num_offspring: usize,
+    pub best_solution: Sol,
+    /// Optional archive of good and diverse solutions, see `EliteArchive`
+    pub elites: Option<EliteArchive<Sol>>,
+    pub problem: Prob,
+    generation_score: ScoreType, // the parent's score at the start of this generation
+}
+
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> EvolutionarySolver<Sol, Prob> {
+    // a replacement for Self::new( size )
+    #[inline]
+    pub fn builder(problem: &Prob) -> Self {
+        let best_solution = problem.random_solution();
+        Self {
+            lambda: 4,
+            flip_rate: Self::min_flip_rate(problem.problem_size()),
+            num_offspring: 0,
+            generation_score: best_solution.get_score(),
+            best_solution,
+            elites: None,
+            problem: problem.clone(),
+        }
+    }
+
+    #[inline]
+    fn min_flip_rate(width: usize) -> f64 {
+        1.0 / (width.max(2) as f64)
+    }
+
+    /// Number of generations started so far
+    #[inline]
+    pub fn num_generations(&self) -> usize {
+        self.num_offspring.div_ceil(self.lambda.max(1))
+    }
+
+    // At the start of each generation, adapt the flip rate to the success of the last one.
+    fn adapt_flip_rate(&mut self) {
+        let width = self.problem.problem_size();
+        if self.generation_score < self.best_score() {
+            self.flip_rate = (self.flip_rate * ADAPTATION_FACTOR).min(MAX_FLIP_RATE);
+        } else {
+            self.flip_rate =
+                (self.flip_rate / ADAPTATION_FACTOR.powf(0.25)).max(Self::min_flip_rate(width));
+        };
+        self.generation_score = self.best_score();
+        trace!(
+            "Generation {}: flip rate now {}",
+            self.num_generations(),
+            self.flip_rate
+        );
+    }
+
+    /// A mutation of the best solution so far, repaired so it is legal (and complete).
+    pub fn mutant(&self) -> Sol {
+        let mut generator = thread_rng();
+        let width = self.problem.problem_size();
+        let mut flips: Vec<bool> = (0..width)
+            .map(|_| generator.gen_bool(self.flip_rate))
+            .collect();
+        if !flips.iter().any(|&flip| flip) {
+            flips[generator.gen_range(0..width)] = true; // don't waste an evaluation
+        };
+        let mut result = self.problem.starting_solution();
+        while let Some(index) = self.problem.first_open_decision(&result) {
+            let parent_decision = self.best_solution.get_decision(index).unwrap_or(false);
+            let decision = parent_decision != flips[index];
+            result.make_decision(index, decision);
+            if !self.problem.solution_is_legal(&result) {
+                result.make_decision(index, !decision); // repair
+            };
+            self.problem.apply_rules(&mut result);
+            debug_assert!(self.problem.rules_audit_passed(&result));
+        } // end while there are open decisions
+        result
+    }
+} // end private Methods
+
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for EvolutionarySolver<Sol, Prob> {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "EvolutionarySolver"
+    }
+
+    #[inline]
+    fn short_description(&self) -> String {
+        format!(
+            "{} after {} offspring, flip rate {}, best score is {}",
+            self.name(),
+            self.num_offspring,
+            self.flip_rate,
+            self.best_score()
+        )
+    }
+
+    // Methods used by the Unified Optimization Algorithm (identified above)
+
+    #[inline]
+    fn number_of_solutions(&self) -> usize {
+        self.num_offspring
+    }
+
+    /// An evolution strategy never runs out of offspring; it stops when it converges.
+    #[inline]
+    fn is_finished(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.num_offspring = 0;
+        self.flip_rate = Self::min_flip_rate(self.problem.problem_size());
+        let size = self.best_solution.size();
+        self.best_solution = Sol::new(size);
+        self.generation_score = self.best_solution.get_score();
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
+    }
+
+    /// Only complete solutions are of interest (better ones become the parent);
+    /// the root pushed by `start_search` is ignored.
+    #[inline]
+    fn push(&mut self, solution: Sol) {
+        if self.problem.solution_is_complete(&solution) && self.best_score() < solution.get_score()
+        {
+            self.store_best_solution(solution);
+        }
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<Sol> {
+        if self.num_offspring.is_multiple_of(self.lambda.max(1)) {
+            self.adapt_flip_rate();
+        };
+        self.num_offspring += 1;
+        Some(self.mutant())
+    }
+
+    #[inline]
+    fn best_solution(&self) -> &Sol {
+        &self.best_solution
+    }
+
+    #[inline]
+    fn elite_archive(&mut self) -> Option<&mut EliteArchive<Sol>> {
+        self.elites.as_mut()
+    }
+
+    #[inline]
+    fn store_best_solution(&mut self, solution: Sol) {
+        // we'd like to check for completion, but can't use proble.solution_is_complete( s )
+        debug_assert_eq!(solution.get_score(), solution.get_best_score());
+        self.best_solution = solution;
+    }
+} // end impl Solver for EvolutionarySolver
+
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> SolverBuilder<Prob>
+    for EvolutionarySolver<Sol, Prob>
+{
+    #[inline]
+    fn from_problem(problem: &Prob) -> Self {
+        Self::builder(problem)
+    }
+}
+
+/**************************************************************************************/
+//////////////// TESTs for EvolutionarySolver /////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{Problem01Knapsack, ProblemSubsetSum};
+    use std::time::Duration;
+
+    #[test]
+    fn test_mutants_are_legal() {
+        const NUM_DECISIONS: usize = 32;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let mut solver = EvolutionarySolver::builder(&knapsack);
+        for flip_rate in [0.0, 0.1, 0.5, 1.0].iter() {
+            solver.flip_rate = *flip_rate;
+            for _ in 0..10 {
+                let mutant = solver.mutant();
+                assert!(knapsack.solution_is_legal(&mutant));
+                assert!(knapsack.solution_is_complete(&mutant));
+                assert_eq!(mutant.get_score(), knapsack.solution_score(&mutant));
+            }
+        }
+    }
+
+    #[test]
+    fn test_flip_rate_adapts() {
+        const NUM_DECISIONS: usize = 16;
+        let problem = ProblemSubsetSum::random(NUM_DECISIONS);
+        let mut solver = EvolutionarySolver::builder(&problem);
+        let min_rate = solver.flip_rate;
+        solver.lambda = 3;
+
+        // No improvement: the rate stays at its minimum
+        for _ in 0..7 {
+            solver.pop();
+        }
+        assert_eq!(solver.num_generations(), 3);
+        assert_eq!(solver.flip_rate, min_rate);
+
+        // An improvement: the rate goes up at the start of the next generation
+        let mut better = solver.best_solution().clone();
+        let better_score = solver.best_score() + 1 as ScoreType;
+        better.put_score(better_score);
+        better.put_best_score(better_score);
+        solver.store_best_solution(better);
+        for _ in 0..3 {
+            solver.pop();
+        }
+        assert!(min_rate < solver.flip_rate);
+        assert!(solver.flip_rate <= MAX_FLIP_RATE);
+    }
+
+    #[test]
+    fn test_evolutionary_find_solution() {
+        const NUM_DECISIONS: usize = 16;
+        let problem = ProblemSubsetSum::random(NUM_DECISIONS);
+        let mut solver = EvolutionarySolver::builder(&problem);
+        let start = problem.random_solution();
+        let best = solver
+            .find_best_solution_from(&problem, start.clone(), Duration::from_millis(100))
+            .expect("could not find best solution");
+        assert!(problem.solution_is_legal(&best));
+        assert!(problem.solution_is_complete(&best));
+        assert!(start.get_score() <= best.get_score());
+        assert!(best.get_score() <= problem.capacity);
+        assert!(0 < solver.number_of_solutions());
+    }
+}
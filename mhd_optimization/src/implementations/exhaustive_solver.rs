@@ -0,0 +1,242 @@
+use log::*;
+
+/// # Example Implementations
+///
+/// ## Example Solver Implementation: Exhaustive Enumeration
+///
+/// For small problems only: `pop` returns the next of all 2^n assignments of the n decisions
+/// (counting in binary, decision `i` is bit `i` of the counter), skipping the illegal ones.
+/// Each assignment is made decision by decision, applying the rules after each one,
+/// so the solutions are complete and scored. No bounds, no heuristics, no surprises:
+/// once `is_finished`, the best solution is THE optimum -- an oracle for testing other solvers.
+///
+/// Note that `find_best_solution` stops when it converges (like every solver), which may be
+/// before all assignments have been enumerated; `solve` never stops before that.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, Solution, Solver};
+/// use mhd_optimization::implementations::{DepthFirstSolver, ExhaustiveSolver, Problem01Knapsack};
+/// use std::time::Duration;
+///
+/// let knapsack = Problem01Knapsack::random(10);
+/// let optimum = ExhaustiveSolver::builder(&knapsack).solve();
+/// assert!(knapsack.solution_is_legal(&optimum));
+///
+/// let mut solver = DepthFirstSolver::new(10);
+/// let the_best = solver.find_best_solution(&knapsack, Duration::from_secs(1)).unwrap();
+/// assert_eq!(the_best.get_score(), optimum.get_score());
+/// ```
+use optimizer::{EliteArchive, Problem, Solution, Solver, SolverBuilder};
+
+/// 2^30 assignments is already more than enough...
+pub const MAX_EXHAUSTIVE_DECISIONS: usize = 30;
+
+#[derive(Debug, Clone)]
+pub struct ExhaustiveSolver<Sol: Solution, Prob: Problem<Sol = Sol>> {
+    /// The next assignment to try (and the number tried so far)
+    pub next_assignment: u64,
+    pub best_solution: Sol,
+    /// Optional archive of good and diverse solutions, see `EliteArchive`
+    pub elites: Option<EliteArchive<Sol>>,
+    pub problem: Prob,
+}
+
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> ExhaustiveSolver<Sol, Prob> {
+    // a replacement for Self::new( size )
+    #[inline]
+    pub fn builder(problem: &Prob) -> Self {
+        assert!(
+            problem.problem_size() <= MAX_EXHAUSTIVE_DECISIONS,
+            "ExhaustiveSolver: {} decisions are too many",
+            problem.problem_size()
+        );
+        Self {
+            next_assignment: 0,
+            best_solution: problem.random_solution(),
+            elites: None,
+            problem: problem.clone(),
+        }
+    }
+
+    /// Number of assignments in all
+    #[inline]
+    pub fn num_assignments(&self) -> u64 {
+        1 << self.problem.problem_size()
+    }
+
+    /// The solution for one assignment, if it is legal
+    pub fn solution_of_assignment(&self, assignment: u64) -> Option<Sol> {
+        let mut result = self.problem.starting_solution();
+        while let Some(index) = self.problem.first_open_decision(&result) {
+            result.make_decision(index, 0 != (assignment >> index) & 1);
+            if !self.problem.solution_is_legal(&result) {
+                return None;
+            };
+            self.problem.apply_rules(&mut result);
+            debug_assert!(self.problem.rules_audit_passed(&result));
+        } // end while there are open decisions
+        Some(result)
+    }
+
+    /// Enumerate all (remaining) assignments, and return the best solution
+    pub fn solve(&mut self) -> Sol {
+        while let Some(solution) = self.pop() {
+            if self.problem.better_than(&solution, &self.best_solution) {
+                self.store_best_solution(solution);
+            };
+        }
+        info!(
+            "{} enumerated {} assignments",
+            self.name(),
+            self.number_of_solutions()
+        );
+        self.best_solution.clone()
+    }
+} // end private Methods
+
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for ExhaustiveSolver<Sol, Prob> {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "ExhaustiveSolver"
+    }
+
+    #[inline]
+    fn short_description(&self) -> String {
+        format!(
+            "{} after {} of {} assignments, best score is {}",
+            self.name(),
+            self.next_assignment,
+            self.num_assignments(),
+            self.best_score()
+        )
+    }
+
+    // Methods used by the Unified Optimization Algorithm (identified above)
+
+    #[inline]
+    fn number_of_solutions(&self) -> usize {
+        self.next_assignment as usize
+    }
+
+    #[inline]
+    fn is_finished(&self) -> bool {
+        self.num_assignments() <= self.next_assignment
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.next_assignment = 0;
+        let size = self.best_solution.size();
+        self.best_solution = Sol::new(size);
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
+    }
+
+    /// Only complete solutions are of interest (better ones are stored);
+    /// the root pushed by `start_search` is ignored.
+    #[inline]
+    fn push(&mut self, solution: Sol) {
+        if self.problem.solution_is_complete(&solution) && self.best_score() < solution.get_score()
+        {
+            self.store_best_solution(solution);
+        }
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<Sol> {
+        while !self.is_finished() {
+            let assignment = self.next_assignment;
+            self.next_assignment += 1;
+            if let Some(solution) = self.solution_of_assignment(assignment) {
+                return Some(solution);
+            };
+        }
+        None
+    }
+
+    #[inline]
+    fn best_solution(&self) -> &Sol {
+        &self.best_solution
+    }
+
+    #[inline]
+    fn elite_archive(&mut self) -> Option<&mut EliteArchive<Sol>> {
+        self.elites.as_mut()
+    }
+
+    #[inline]
+    fn store_best_solution(&mut self, solution: Sol) {
+        // we'd like to check for completion, but can't use proble.solution_is_complete( s )
+        debug_assert_eq!(solution.get_score(), solution.get_best_score());
+        self.best_solution = solution;
+    }
+} // end impl Solver for ExhaustiveSolver
+
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> SolverBuilder<Prob> for ExhaustiveSolver<Sol, Prob> {
+    #[inline]
+    fn from_problem(problem: &Prob) -> Self {
+        Self::builder(problem)
+    }
+}
+
+/**************************************************************************************/
+//////////////// TESTs for ExhaustiveSolver /////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{BestFirstSolver, Problem01Knapsack, ProblemSubsetSum};
+    use mhd_memory::ScoreType;
+    use optimizer::MinimalSolution;
+    use std::time::Duration;
+
+    #[test]
+    fn test_exhaustive_enumeration() {
+        const NUM_DECISIONS: usize = 6;
+        let mut problem = ProblemSubsetSum::new(NUM_DECISIONS);
+        problem.weights = vec![1 as ScoreType; NUM_DECISIONS];
+        problem.capacity = NUM_DECISIONS as ScoreType; // everything fits, everything is legal
+        let mut solver = ExhaustiveSolver::builder(&problem);
+        assert_eq!(solver.num_assignments(), 64);
+
+        let mut num_legal = 0;
+        while let Some(solution) = solver.pop() {
+            assert!(problem.solution_is_complete(&solution));
+            assert_eq!(solution.get_score(), problem.solution_score(&solution));
+            num_legal += 1;
+        }
+        assert_eq!(num_legal, 64);
+        assert!(solver.is_finished());
+
+        // Now only 3 items fit: the assignments with more are skipped
+        problem.capacity = 3 as ScoreType;
+        let mut solver = ExhaustiveSolver::builder(&problem);
+        let optimum = solver.solve();
+        assert_eq!(optimum.get_score(), 3 as ScoreType);
+        assert_eq!(solver.number_of_solutions(), 64);
+        solver.clear();
+        assert!(!solver.is_finished());
+    }
+
+    #[test]
+    fn test_exhaustive_is_an_oracle() {
+        const FEW_DECISIONS: usize = 12;
+        let knapsack = Problem01Knapsack::random(FEW_DECISIONS);
+        let optimum = ExhaustiveSolver::builder(&knapsack).solve();
+        assert!(knapsack.solution_is_legal(&optimum));
+        assert!(knapsack.solution_is_complete(&optimum));
+
+        let mut solver = BestFirstSolver::new(FEW_DECISIONS);
+        let the_best = solver
+            .find_best_solution(&knapsack, Duration::from_secs(1))
+            .expect("could not find best solution");
+        assert_eq!(the_best.get_score(), optimum.get_score());
+
+        let subset_sum = ProblemSubsetSum::random(FEW_DECISIONS);
+        let mut solver = ExhaustiveSolver::<MinimalSolution, _>::builder(&subset_sum);
+        let the_best = solver
+            .find_best_solution(&subset_sum, Duration::from_secs(1))
+            .expect("could not find best solution");
+        assert_eq!(the_best.get_score(), subset_sum.capacity);
+    }
+}
@@ -0,0 +1,348 @@
+use log::*;
+use rand::prelude::*;
+
+use mhd_memory::{MhdMemory, Score};
+
+/// # Example Implementations
+///
+/// ## Example Solver Implementation: a (Steady State) Genetic Algorithm
+///
+/// A population-based baseline: the solver keeps a population of `population_size`
+/// complete solutions (random ones, to begin with). Each `pop` breeds one child: two parents
+/// are chosen by tournament selection (the best of `tournament_size` random members),
+/// and the child inherits each decision from one of them, at random (uniform crossover).
+/// Then each decision is flipped with probability `mutation_rate`. Like the mutants of the
+/// `EvolutionarySolver`, the child is repaired by making its decisions one after the other,
+/// applying the rules after each one. It replaces the worst member of the population,
+/// if it is better (and not there already).
+///
+/// Optionally (see `with_memory`), the solver learns every child in an MHD memory, and where
+/// the parents disagree (at the "crossover points"), the memory decides which parent's gene
+/// the child inherits -- reading it the way the `MhdMonteCarloSolver` does.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, Solution, Solver};
+/// use mhd_optimization::implementations::{GeneticSolver, Problem01Knapsack};
+/// use std::time::Duration;
+///
+/// let knapsack = Problem01Knapsack::random(32);
+/// let mut solver = GeneticSolver::builder(&knapsack).with_memory();
+/// solver.tournament_size = 3;
+///
+/// let best = solver.find_best_solution(&knapsack, Duration::from_millis(200))
+///                  .expect("could not find best solution");
+/// assert!(knapsack.solution_is_legal(&best));
+/// assert!(knapsack.solution_is_complete(&best));
+/// assert!(0 < solver.num_offspring);
+/// ```
+use optimizer::{EliteArchive, MemoryUsage, Problem, Solution, Solver, SolverBuilder};
+
+pub const DEFAULT_POPULATION_SIZE: usize = 16;
+pub const DEFAULT_TOURNAMENT_SIZE: usize = 2;
+
+#[derive(Debug, Clone)]
+pub struct GeneticSolver<Sol: Solution, Prob: Problem<Sol = Sol>> {
+    pub population_size: usize,
+    /// Number of random members competing to become a parent
+    pub tournament_size: usize,
+    /// Probability of flipping each decision of a child
+    pub mutation_rate: f64,
+    /// The population: complete solutions, no two the same
+    pub population: Vec<Sol>,
+    /// Number of children bred so far
+    pub num_offspring: usize,
+    /// Optional memory deciding the genes at the crossover points, see `with_memory`
+    pub memory: Option<MhdMemory>,
+    pub best_solution: Sol,
+    /// Optional archive of good and diverse solutions, see `EliteArchive`
+    pub elites: Option<EliteArchive<Sol>>,
+    pub problem: Prob,
+}
+
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> GeneticSolver<Sol, Prob> {
+    // a replacement for Self::new( size )
+    #[inline]
+    pub fn builder(problem: &Prob) -> Self {
+        let width = problem.problem_size();
+        let mut product = Self {
+            population_size: DEFAULT_POPULATION_SIZE,
+            tournament_size: DEFAULT_TOURNAMENT_SIZE,
+            mutation_rate: 1.0 / (width.max(2) as f64),
+            population: Vec::with_capacity(DEFAULT_POPULATION_SIZE),
+            num_offspring: 0,
+            memory: None,
+            best_solution: problem.random_solution(),
+            elites: None,
+            problem: problem.clone(),
+        };
+        let first = product.best_solution.clone();
+        product.join_population(first);
+        product
+    }
+
+    /// Let an MHD memory (of every child so far) choose the genes at the crossover points
+    pub fn with_memory(mut self) -> Self {
+        let mut memory = MhdMemory::new(self.problem.problem_size());
+        for member in self.population.iter() {
+            memory.write_sample(&self.problem.sample_from_solution(member));
+        }
+        self.memory = Some(memory);
+        self
+    }
+
+    // Choose a parent: the best of `tournament_size` random members of the population
+    fn tournament(&self, generator: &mut ThreadRng) -> &Sol {
+        (0..self.tournament_size.max(1))
+            .filter_map(|_| self.population.choose(generator))
+            .max_by(|a, b| a.get_score().cmp_scores(&b.get_score()))
+            .expect("the population is never empty")
+    }
+
+    /// A child of two parents chosen by tournament: uniform crossover, then mutation,
+    /// repaired so it is legal (and complete).
+    pub fn offspring(&self) -> Sol {
+        let mut generator = thread_rng();
+        let mother = self.tournament(&mut generator);
+        let father = self.tournament(&mut generator);
+        let mut result = self.problem.starting_solution();
+        while let Some(index) = self.problem.first_open_decision(&result) {
+            let from_mother = mother.get_decision(index).unwrap_or(false);
+            let from_father = father.get_decision(index).unwrap_or(false);
+            let mut decision = if from_mother == from_father {
+                from_mother
+            } else {
+                // A crossover point: ask the memory (if any), or flip a coin
+                match self.memory.as_ref().filter(|memory| !memory.is_empty()) {
+                    Some(memory) => {
+                        memory.read_and_decide(result.mask(), result.query(), index, true)
+                    }
+                    None => generator.gen(),
+                }
+            };
+            if generator.gen_bool(self.mutation_rate.clamp(0.0, 1.0)) {
+                decision = !decision;
+            };
+            result.make_decision(index, decision);
+            if !self.problem.solution_is_legal(&result) {
+                result.make_decision(index, !decision); // repair
+            };
+            self.problem.apply_rules(&mut result);
+            debug_assert!(self.problem.rules_audit_passed(&result));
+        } // end while there are open decisions
+        result
+    }
+
+    // Let `child` into the population (if there's room, or it's better than the worst member)
+    fn join_population(&mut self, child: Sol) {
+        if self.population.contains(&child) {
+            return;
+        };
+        if self.population.len() < self.population_size.max(1) {
+            self.population.push(child);
+        } else if let Some((worst, _)) = self
+            .population
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.get_score().cmp_scores(&b.get_score()))
+        {
+            if self.population[worst].get_score() < child.get_score() {
+                self.population[worst] = child;
+            };
+        };
+    }
+} // end private Methods
+
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for GeneticSolver<Sol, Prob> {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "GeneticSolver"
+    }
+
+    #[inline]
+    fn short_description(&self) -> String {
+        format!(
+            "{} after {} offspring, population {}, best score is {}",
+            self.name(),
+            self.num_offspring,
+            self.population.len(),
+            self.best_score()
+        )
+    }
+
+    // Methods used by the Unified Optimization Algorithm (identified above)
+
+    #[inline]
+    fn number_of_solutions(&self) -> usize {
+        self.population.len()
+    }
+
+    #[inline]
+    fn memory_usage(&self) -> MemoryUsage {
+        let population = MemoryUsage::of_frontier::<Sol>(self.population.len(), self.width());
+        match self.memory.as_ref() {
+            None => population,
+            Some(memory) => population + MemoryUsage::of_memory(memory),
+        }
+    }
+
+    /// A genetic algorithm never runs out of children; it stops when it converges.
+    #[inline]
+    fn is_finished(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.num_offspring = 0;
+        self.population.clear();
+        if let Some(memory) = self.memory.as_mut() {
+            memory.clear();
+        };
+        let size = self.best_solution.size();
+        self.best_solution = Sol::new(size);
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
+    }
+
+    /// Only complete solutions are of interest (they join the population, e.g. a warm start);
+    /// the root pushed by `start_search` is ignored.
+    #[inline]
+    fn push(&mut self, solution: Sol) {
+        if self.problem.solution_is_complete(&solution) {
+            self.join_population(solution);
+        }
+    }
+
+    /// The next child -- or, while the population is still growing, a random solution
+    fn pop(&mut self) -> Option<Sol> {
+        let child = if self.population.len() < self.population_size.max(1) {
+            self.problem.random_solution()
+        } else {
+            self.offspring()
+        };
+        self.num_offspring += 1;
+        if let Some(memory) = self.memory.as_mut() {
+            memory.write_sample(&self.problem.sample_from_solution(&child));
+        };
+        trace!(
+            "Child {} with score {}",
+            self.num_offspring,
+            child.get_score()
+        );
+        self.join_population(child.clone());
+        Some(child)
+    }
+
+    #[inline]
+    fn best_solution(&self) -> &Sol {
+        &self.best_solution
+    }
+
+    #[inline]
+    fn elite_archive(&mut self) -> Option<&mut EliteArchive<Sol>> {
+        self.elites.as_mut()
+    }
+
+    #[inline]
+    fn reseed(&mut self, seed: u64) {
+        if let Some(memory) = self.memory.as_mut() {
+            memory.reseed(seed);
+        };
+    }
+
+    /// The best solution joins the population, too (e.g. an incumbent from elsewhere)
+    #[inline]
+    fn store_best_solution(&mut self, solution: Sol) {
+        // we'd like to check for completion, but can't use proble.solution_is_complete( s )
+        debug_assert_eq!(solution.get_score(), solution.get_best_score());
+        self.join_population(solution.clone());
+        self.best_solution = solution;
+    }
+} // end impl Solver for GeneticSolver
+
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> SolverBuilder<Prob> for GeneticSolver<Sol, Prob> {
+    #[inline]
+    fn from_problem(problem: &Prob) -> Self {
+        Self::builder(problem)
+    }
+}
+
+/**************************************************************************************/
+//////////////// TESTs for GeneticSolver /////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{Problem01Knapsack, ProblemSubsetSum};
+    use optimizer::MinimalSolution;
+    use std::time::Duration;
+
+    #[test]
+    fn test_offspring_are_legal() {
+        const NUM_DECISIONS: usize = 32;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        for solver in [
+            GeneticSolver::builder(&knapsack),
+            GeneticSolver::builder(&knapsack).with_memory(),
+        ]
+        .iter_mut()
+        {
+            for mutation_rate in [0.0, 0.1, 1.0].iter() {
+                solver.mutation_rate = *mutation_rate;
+                for _ in 0..40 {
+                    let child = solver.pop().unwrap();
+                    assert!(knapsack.solution_is_legal(&child));
+                    assert!(knapsack.solution_is_complete(&child));
+                    assert_eq!(child.get_score(), knapsack.solution_score(&child));
+                }
+            }
+            assert_eq!(solver.num_offspring, 120);
+            assert!(solver.population.len() <= solver.population_size);
+        }
+    }
+
+    #[test]
+    fn test_population() {
+        const NUM_DECISIONS: usize = 40;
+        let problem = ProblemSubsetSum::random(NUM_DECISIONS);
+        let mut solver = GeneticSolver::builder(&problem);
+        solver.population_size = 4;
+        for _ in 0..100 {
+            solver.pop();
+        }
+        // Full, no duplicates, and never worse than it was when it was first full
+        assert_eq!(solver.population.len(), 4);
+        let mut members = solver.population.clone();
+        members.sort();
+        members.dedup();
+        assert_eq!(members.len(), 4);
+        let worst = |population: &[MinimalSolution]| {
+            Score::min_of(population.iter().map(|s| s.get_score())).unwrap()
+        };
+        let worst_before = worst(&solver.population);
+        for _ in 0..100 {
+            solver.pop();
+        }
+        assert!(worst_before <= worst(&solver.population));
+
+        solver.clear();
+        assert!(solver.population.is_empty());
+        assert_eq!(solver.num_offspring, 0);
+    }
+
+    #[test]
+    fn test_genetic_find_solution() {
+        const NUM_DECISIONS: usize = 16;
+        let problem = ProblemSubsetSum::random(NUM_DECISIONS);
+        let mut solver = GeneticSolver::builder(&problem).with_memory();
+        let start = problem.random_solution();
+        let best = solver
+            .find_best_solution_from(&problem, start.clone(), Duration::from_millis(100))
+            .expect("could not find best solution");
+        assert!(problem.solution_is_legal(&best));
+        assert!(problem.solution_is_complete(&best));
+        assert!(start.get_score() <= best.get_score());
+        assert!(best.get_score() <= problem.capacity);
+        assert!(0 < solver.memory.as_ref().unwrap().num_samples());
+    }
+}
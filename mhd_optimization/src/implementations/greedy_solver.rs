@@ -0,0 +1,262 @@
+use log::*;
+
+/// # Example Implementations
+///
+/// ## Example Solver Implementation: Greedy Construction
+///
+/// One complete solution, quickly: the decisions are made in the problem's `greedy_order`
+/// (for knapsacks: the most value per weight first; for subset sums: the heaviest first),
+/// each one true if that is legal (i.e. if the item still fits), applying the rules after
+/// each one. Not optimal, but usually good -- much better than the random solution a search
+/// starts with. So besides the `GreedySolver` (whose first `pop` is the greedy solution,
+/// and then it is finished), there is `warm_start`, for any solver: it makes the greedy
+/// solution the solver's best solution (unless that is better already), so the search
+/// prunes with a good bound from the very first step.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, Solution, Solver};
+/// use mhd_optimization::implementations::{greedy_solution, warm_start};
+/// use mhd_optimization::implementations::{DepthFirstSolver, GreedySolver, Problem01Knapsack};
+/// use std::time::Duration;
+///
+/// let knapsack = Problem01Knapsack::random(32);
+/// let greedy = GreedySolver::builder(&knapsack).solve();
+/// assert!(knapsack.solution_is_legal(&greedy));
+/// assert!(knapsack.solution_is_complete(&greedy));
+///
+/// // Search, starting from the greedy solution...
+/// let mut solver = DepthFirstSolver::new(32);
+/// solver.start_search(&knapsack);
+/// warm_start(&mut solver, &knapsack);
+/// assert!(greedy.get_score() <= solver.best_score());
+/// let best = solver.continue_search(&knapsack, Duration::from_millis(100)).unwrap();
+/// assert!(greedy.get_score() <= best.get_score());
+///
+/// // ...or, which is the same
+/// let start = greedy_solution(&knapsack);
+/// let best = solver
+///     .find_best_solution_from(&knapsack, start, Duration::from_millis(100))
+///     .unwrap();
+/// assert!(greedy.get_score() <= best.get_score());
+/// ```
+use optimizer::{EliteArchive, Problem, Solution, Solver, SolverBuilder};
+
+/// The greedy solution of `problem`: decisions in `greedy_order`, each true if legal
+pub fn greedy_solution<Prob: Problem>(problem: &Prob) -> Prob::Sol {
+    let mut result = problem.starting_solution();
+    for index in problem.greedy_order() {
+        if result.get_decision(index).is_some() {
+            continue; // the rules made it already
+        };
+        result.make_decision(index, true);
+        if !problem.solution_is_legal(&result) {
+            result.make_decision(index, false); // doesn't fit
+        };
+        problem.apply_rules(&mut result);
+        debug_assert!(problem.rules_audit_passed(&result));
+    } // end for all decisions, in greedy order
+    debug_assert!(problem.solution_is_complete(&result));
+    result
+}
+
+/// Make the greedy solution `solver`'s best solution, if it is better
+/// (call it after `start_search`, which starts with a random one).
+/// Returns true if it was better.
+pub fn warm_start<Sol, S, Prob>(solver: &mut S, problem: &Prob) -> bool
+where
+    Sol: Solution,
+    S: Solver<Sol> + ?Sized,
+    Prob: Problem<Sol = Sol>,
+{
+    let greedy = greedy_solution(problem);
+    debug!(
+        "{} warm starts with greedy score {}",
+        solver.name(),
+        greedy.get_score()
+    );
+    solver.new_best_solution(problem, greedy)
+}
+
+#[derive(Debug, Clone)]
+pub struct GreedySolver<Sol: Solution, Prob: Problem<Sol = Sol>> {
+    /// Has the greedy solution been constructed (and popped)?
+    pub solved: bool,
+    pub best_solution: Sol,
+    /// Optional archive of good and diverse solutions, see `EliteArchive`
+    pub elites: Option<EliteArchive<Sol>>,
+    pub problem: Prob,
+}
+
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> GreedySolver<Sol, Prob> {
+    // a replacement for Self::new( size )
+    #[inline]
+    pub fn builder(problem: &Prob) -> Self {
+        Self {
+            solved: false,
+            best_solution: problem.random_solution(),
+            elites: None,
+            problem: problem.clone(),
+        }
+    }
+
+    /// Construct the greedy solution, and return (and store) it -- or the best solution,
+    /// if that is better
+    pub fn solve(&mut self) -> Sol {
+        let greedy = greedy_solution(&self.problem);
+        self.solved = true;
+        if self.problem.better_than(&greedy, &self.best_solution) {
+            self.store_best_solution(greedy);
+        };
+        self.best_solution.clone()
+    }
+} // end private Methods
+
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for GreedySolver<Sol, Prob> {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "GreedySolver"
+    }
+
+    #[inline]
+    fn short_description(&self) -> String {
+        format!(
+            "{} ({}), best score is {}",
+            self.name(),
+            if self.solved { "solved" } else { "not solved" },
+            self.best_score()
+        )
+    }
+
+    // Methods used by the Unified Optimization Algorithm (identified above)
+
+    #[inline]
+    fn number_of_solutions(&self) -> usize {
+        self.solved as usize
+    }
+
+    #[inline]
+    fn is_finished(&self) -> bool {
+        self.solved
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.solved = false;
+        let size = self.best_solution.size();
+        self.best_solution = Sol::new(size);
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
+    }
+
+    /// The construction needs no partial solutions; better complete ones are stored, though.
+    #[inline]
+    fn push(&mut self, solution: Sol) {
+        if self.problem.solution_is_complete(&solution)
+            && self.problem.better_than(&solution, &self.best_solution)
+        {
+            self.store_best_solution(solution);
+        }
+    }
+
+    /// The first pop constructs the greedy solution, then there is nothing left.
+    #[inline]
+    fn pop(&mut self) -> Option<Sol> {
+        if self.solved {
+            None
+        } else {
+            self.solved = true;
+            Some(greedy_solution(&self.problem))
+        }
+    }
+
+    #[inline]
+    fn best_solution(&self) -> &Sol {
+        &self.best_solution
+    }
+
+    #[inline]
+    fn elite_archive(&mut self) -> Option<&mut EliteArchive<Sol>> {
+        self.elites.as_mut()
+    }
+
+    #[inline]
+    fn store_best_solution(&mut self, solution: Sol) {
+        // we'd like to check for completion, but can't use proble.solution_is_complete( s )
+        debug_assert_eq!(solution.get_score(), solution.get_best_score());
+        self.best_solution = solution;
+    }
+} // end impl Solver for GreedySolver
+
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> SolverBuilder<Prob> for GreedySolver<Sol, Prob> {
+    #[inline]
+    fn from_problem(problem: &Prob) -> Self {
+        Self::builder(problem)
+    }
+}
+
+/**************************************************************************************/
+//////////////// TESTs for GreedySolver /////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{DepthFirstSolver, Problem01Knapsack, ProblemSubsetSum};
+    use mhd_memory::ScoreType;
+    use optimizer::SolverConfig;
+    use std::time::Duration;
+
+    #[test]
+    fn test_greedy_solution() {
+        let mut knapsack = Problem01Knapsack::new(4);
+        knapsack.basis.weights = [5, 4, 6, 3].iter().map(|&x| x as ScoreType).collect();
+        knapsack.values = [10, 40, 30, 50].iter().map(|&x| x as ScoreType).collect();
+        knapsack.basis.capacity = 10 as ScoreType;
+        // densities 2, 10, 5, 16.7: item 3, item 1 (weight 7), item 2 doesn't fit, nor 0
+        assert_eq!(knapsack.greedy_order(), vec![3, 1, 2, 0]);
+        let greedy = greedy_solution(&knapsack);
+        assert_eq!(greedy.get_score(), 90 as ScoreType);
+        assert_eq!(
+            (0..4).map(|i| greedy.get_decision(i)).collect::<Vec<_>>(),
+            vec![Some(false), Some(true), Some(false), Some(true)]
+        );
+
+        let mut problem = ProblemSubsetSum::new(4);
+        problem.weights = [30, 70, 5, 45].iter().map(|&x| x as ScoreType).collect();
+        problem.capacity = 99 as ScoreType;
+        assert_eq!(problem.greedy_order(), vec![1, 3, 0, 2]);
+        assert_eq!(greedy_solution(&problem).get_score(), 75 as ScoreType); // 70 + 5, missing 45 + 30 + 5
+
+        // The solver finds it once, then it's finished
+        let mut solver = GreedySolver::builder(&knapsack);
+        let report = solver
+            .find_best_solution(&knapsack, Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(report.get_score(), 90 as ScoreType);
+        assert!(solver.is_finished());
+        assert!(solver.pop().is_none());
+        solver.clear();
+        assert!(!solver.is_finished());
+    }
+
+    #[test]
+    fn test_warm_start() {
+        const NUM_DECISIONS: usize = 40;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let greedy = greedy_solution(&knapsack);
+        assert!(knapsack.solution_is_legal(&greedy));
+
+        let mut solver = DepthFirstSolver::new(NUM_DECISIONS);
+        solver.start_search(&knapsack);
+        warm_start(&mut solver, &knapsack);
+        assert!(greedy.get_score() <= solver.best_score());
+        assert!(!warm_start(&mut solver, &knapsack)); // (no better the second time)
+
+        // Even a search of a few steps is at least as good as greedy
+        let config = SolverConfig {
+            node_limit: Some(3),
+            ..Default::default()
+        };
+        let best = solver.continue_search(&knapsack, config).unwrap();
+        assert!(greedy.get_score() <= best.get_score());
+    }
+}
@@ -0,0 +1,434 @@
+use log::*;
+use rand::prelude::*;
+use std::sync::Arc;
+
+use mhd_memory::DecisionPolicy as MemoryPolicy;
+use mhd_memory::{DistanceKernel, MhdMemory, Score};
+
+/// # Example Implementations
+///
+/// ## Example Solver Implementation: a Hyper-Heuristic
+///
+/// Instead of committing to one way of making decisions, the `HyperHeuristicSolver` has
+/// several (see `DecisionPolicy`), and lets a multi-armed bandit (`PolicyBandit`) choose
+/// one of them at every decision of every rollout. When a rollout is complete, every policy
+/// used in it is rewarded with the (normalized) score of the complete solution -- so the
+/// bandit learns which policies work for this problem, while still trying the others now
+/// and then (UCB1). Every complete solution is also written to the MHD memory, which
+/// `DecisionPolicy::MemoryUcb` reads.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, Solution, Solver};
+/// use mhd_optimization::implementations::{DecisionPolicy, HyperHeuristicSolver, Problem01Knapsack};
+/// use std::time::Duration;
+///
+/// let knapsack = Problem01Knapsack::random(24);
+/// let mut solver = HyperHeuristicSolver::builder(&knapsack);
+///
+/// let best = solver.find_best_solution(&knapsack, Duration::from_millis(200))
+///                  .expect("could not find best solution");
+/// assert!(knapsack.solution_is_legal(&best));
+/// assert!(knapsack.solution_is_complete(&best));
+///
+/// // Which policy did best?
+/// let favorite = solver.bandit.favorite();
+/// assert!(0 < solver.bandit.count(favorite));
+/// ```
+use optimizer::{EliteArchive, MemoryUsage, Problem, Solution, Solver, SolverBuilder};
+
+/// The ways to make one decision, which `PolicyBandit` chooses from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecisionPolicy {
+    /// Ask the MHD memory (`MhdMemory::read_and_decide`, or the solver's `memory_policy`)
+    MemoryUcb,
+    /// Ask the problem (`Problem::greedy_decision`, e.g. value density for knapsacks)
+    Greedy,
+    /// Toss a coin
+    Random,
+}
+
+impl DecisionPolicy {
+    pub const ALL: [DecisionPolicy; 3] = [
+        DecisionPolicy::MemoryUcb,
+        DecisionPolicy::Greedy,
+        DecisionPolicy::Random,
+    ];
+
+    #[inline]
+    fn index(&self) -> usize {
+        match self {
+            DecisionPolicy::MemoryUcb => 0,
+            DecisionPolicy::Greedy => 1,
+            DecisionPolicy::Random => 2,
+        }
+    }
+}
+
+/// A UCB1 multi-armed bandit, with one arm per `DecisionPolicy`.
+/// Arms are counted when pulled, and rewarded later (once the rollout is complete),
+/// so the choices within one rollout vary, too.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyBandit {
+    /// The UCB1 exploration constant
+    pub exploration: f64,
+    counts: [usize; 3],
+    total_rewards: [f64; 3],
+}
+
+impl Default for PolicyBandit {
+    fn default() -> Self {
+        Self {
+            exploration: std::f64::consts::SQRT_2,
+            counts: [0; 3],
+            total_rewards: [0.0; 3],
+        }
+    }
+}
+
+impl PolicyBandit {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget all statistics (but keep the exploration constant)
+    pub fn clear(&mut self) {
+        self.counts = [0; 3];
+        self.total_rewards = [0.0; 3];
+    }
+
+    /// How often `policy` was chosen so far
+    #[inline]
+    pub fn count(&self, policy: DecisionPolicy) -> usize {
+        self.counts[policy.index()]
+    }
+
+    /// The average reward of `policy` so far (zero if never chosen)
+    #[inline]
+    pub fn mean_reward(&self, policy: DecisionPolicy) -> f64 {
+        let count = self.count(policy);
+        if 0 == count {
+            0.0
+        } else {
+            self.total_rewards[policy.index()] / count as f64
+        }
+    }
+
+    /// The policy with the best average reward so far
+    pub fn favorite(&self) -> DecisionPolicy {
+        *DecisionPolicy::ALL
+            .iter()
+            .max_by(|a, b| {
+                self.mean_reward(**a)
+                    .partial_cmp(&self.mean_reward(**b))
+                    .expect("Not NaN")
+            })
+            .expect("Not empty")
+    }
+
+    // The UCB1 value of `policy` (infinite if never chosen, so each is tried at least once)
+    fn ucb(&self, policy: DecisionPolicy) -> f64 {
+        let count = self.count(policy);
+        if 0 == count {
+            return f64::INFINITY;
+        };
+        let total: usize = self.counts.iter().sum();
+        let bonus = ((total as f64).ln() / count as f64).sqrt();
+        self.mean_reward(policy) + self.exploration * bonus
+    }
+
+    /// Choose a policy (the one with the highest UCB1 value), and count it
+    pub fn pull(&mut self) -> DecisionPolicy {
+        let policy = *DecisionPolicy::ALL
+            .iter()
+            .max_by(|a, b| self.ucb(**a).partial_cmp(&self.ucb(**b)).expect("Not NaN"))
+            .expect("Not empty");
+        self.counts[policy.index()] += 1;
+        policy
+    }
+
+    /// Reward a pulled policy (rewards should be between 0 and 1)
+    #[inline]
+    pub fn reward(&mut self, policy: DecisionPolicy, reward: f64) {
+        debug_assert!(0 < self.count(policy), "Reward for a policy never pulled");
+        self.total_rewards[policy.index()] += reward;
+    }
+} // end impl PolicyBandit
+
+/**************************************************************************************/
+#[derive(Debug, Clone)]
+pub struct HyperHeuristicSolver<Sol: Solution, Prob: Problem<Sol = Sol>> {
+    pub mhd_memory: MhdMemory,
+    pub bandit: PolicyBandit,
+    /// How `DecisionPolicy::MemoryUcb` decides, given what the memory reads
+    /// (None = `mhd_memory::UcbPolicy`)
+    pub memory_policy: Option<Arc<dyn MemoryPolicy>>,
+    pub best_solution: Sol,
+    /// Optional archive of good and diverse solutions, see `EliteArchive`
+    pub elites: Option<EliteArchive<Sol>>,
+    pub problem: Prob,
+    /// Number of rollouts (complete solutions produced) so far
+    pub num_rollouts: usize,
+}
+
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> HyperHeuristicSolver<Sol, Prob> {
+    // a replacement for Self::new( size )
+    #[inline]
+    pub fn builder(problem: &Prob) -> Self {
+        Self {
+            mhd_memory: MhdMemory::new(problem.problem_size()),
+            bandit: PolicyBandit::new(),
+            memory_policy: None,
+            best_solution: problem.random_solution(),
+            elites: None,
+            problem: problem.clone(),
+            num_rollouts: 0,
+        }
+    }
+
+    /// Read the memory with `kernel` (see `DistanceKernel`), e.g. with a smaller threshold
+    /// for a large problem
+    pub fn with_kernel(mut self, kernel: DistanceKernel) -> Self {
+        self.mhd_memory.kernel = kernel;
+        self
+    }
+
+    /// Let `DecisionPolicy::MemoryUcb` decide with `policy` (see `mhd_memory::DecisionPolicy`)
+    pub fn with_memory_policy<P: MemoryPolicy + 'static>(mut self, policy: P) -> Self {
+        self.memory_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Make decision `index` of `solution` the way `policy` says to
+    pub fn decide(&self, policy: DecisionPolicy, solution: &Sol, index: usize) -> bool {
+        match policy {
+            DecisionPolicy::MemoryUcb => {
+                // (an empty memory has no opinion, and decides at random)
+                let (mask, query) = (solution.mask(), solution.query());
+                match self.memory_policy.as_ref() {
+                    Some(policy) => {
+                        self.mhd_memory
+                            .read_and_decide_with(policy.as_ref(), mask, query, index)
+                    }
+                    None => self.mhd_memory.read_and_decide(mask, query, index, false),
+                }
+            }
+            DecisionPolicy::Greedy => self.problem.greedy_decision(solution, index),
+            DecisionPolicy::Random => thread_rng().gen(),
+        }
+    }
+
+    /// One rollout: make every decision with a policy the bandit chooses,
+    /// then reward the policies used, and learn the complete solution.
+    pub fn rollout(&mut self) -> Sol {
+        let mut result = self.problem.starting_solution();
+        let mut used = Vec::with_capacity(self.problem.problem_size());
+        while let Some(index) = self.problem.first_open_decision(&result) {
+            let policy = self.bandit.pull();
+            used.push(policy);
+            let decision = self.decide(policy, &result, index);
+            result.make_decision(index, decision);
+            if !self.problem.solution_is_legal(&result) {
+                result.make_decision(index, !decision); // repair
+            };
+            self.problem.apply_rules(&mut result);
+            debug_assert!(self.problem.rules_audit_passed(&result));
+        } // end while there are open decisions
+
+        let reward = result.get_score().to_f64() / self.best_score().to_f64().max(1.0);
+        for policy in used {
+            self.bandit.reward(policy, reward.min(1.0));
+        }
+        self.mhd_memory
+            .write_sample(&self.problem.sample_from_solution(&result));
+        self.num_rollouts += 1;
+        trace!(
+            "Hyper-heuristic rollout {}, score {}, favorite policy {:?}",
+            self.num_rollouts,
+            result.get_score(),
+            self.bandit.favorite()
+        );
+        result
+    }
+} // end private Methods
+
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for HyperHeuristicSolver<Sol, Prob> {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "HyperHeuristicSolver"
+    }
+
+    #[inline]
+    fn short_description(&self) -> String {
+        format!(
+            "{} after {} rollouts, favorite policy {:?}, best score is {}",
+            self.name(),
+            self.num_rollouts,
+            self.bandit.favorite(),
+            self.best_score()
+        )
+    }
+
+    // Methods used by the Unified Optimization Algorithm (identified above)
+
+    #[inline]
+    fn number_of_solutions(&self) -> usize {
+        self.mhd_memory.num_samples()
+    }
+
+    #[inline]
+    fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::of_memory(&self.mhd_memory)
+    }
+
+    /// Rollouts never run out; the search stops when it converges.
+    #[inline]
+    fn is_finished(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.mhd_memory.clear();
+        self.bandit.clear();
+        self.num_rollouts = 0;
+        let size = self.best_solution.size();
+        self.best_solution = Sol::new(size);
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
+    }
+
+    /// Only complete solutions are of interest (better ones are stored);
+    /// the root pushed by `start_search` is ignored.
+    #[inline]
+    fn push(&mut self, solution: Sol) {
+        if self.problem.solution_is_complete(&solution) && self.best_score() < solution.get_score()
+        {
+            self.store_best_solution(solution);
+        }
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<Sol> {
+        Some(self.rollout())
+    }
+
+    #[inline]
+    fn best_solution(&self) -> &Sol {
+        &self.best_solution
+    }
+
+    #[inline]
+    fn elite_archive(&mut self) -> Option<&mut EliteArchive<Sol>> {
+        self.elites.as_mut()
+    }
+
+    #[inline]
+    fn store_best_solution(&mut self, solution: Sol) {
+        // we'd like to check for completion, but can't use proble.solution_is_complete( s )
+        debug_assert_eq!(solution.get_score(), solution.get_best_score());
+        self.best_solution = solution;
+    }
+} // end impl Solver for HyperHeuristicSolver
+
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> SolverBuilder<Prob>
+    for HyperHeuristicSolver<Sol, Prob>
+{
+    #[inline]
+    fn from_problem(problem: &Prob) -> Self {
+        Self::builder(problem)
+    }
+}
+
+/**************************************************************************************/
+//////////////// TESTs for HyperHeuristicSolver /////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{Problem01Knapsack, ProblemSubsetSum};
+    use mhd_memory::ScoreType;
+    use std::time::Duration;
+
+    #[test]
+    fn test_policy_bandit() {
+        let mut bandit = PolicyBandit::new();
+        // Every policy is tried once first
+        let mut first_three: Vec<DecisionPolicy> = (0..3).map(|_| bandit.pull()).collect();
+        first_three.sort_by_key(|policy| policy.index());
+        assert_eq!(first_three, DecisionPolicy::ALL.to_vec());
+
+        // Then the one rewarded most is chosen most
+        bandit.reward(DecisionPolicy::Greedy, 1.0);
+        bandit.reward(DecisionPolicy::Random, 0.1);
+        for _ in 0..100 {
+            let policy = bandit.pull();
+            let reward = if DecisionPolicy::Greedy == policy {
+                1.0
+            } else {
+                0.1
+            };
+            bandit.reward(policy, reward);
+        }
+        assert_eq!(bandit.favorite(), DecisionPolicy::Greedy);
+        assert!(bandit.count(DecisionPolicy::Random) < bandit.count(DecisionPolicy::Greedy));
+        assert!(0 < bandit.count(DecisionPolicy::MemoryUcb)); // still explored
+        assert_eq!(bandit.mean_reward(DecisionPolicy::Greedy), 1.0);
+
+        bandit.clear();
+        assert_eq!(bandit.count(DecisionPolicy::Greedy), 0);
+        assert_eq!(bandit.mean_reward(DecisionPolicy::Greedy), 0.0);
+    }
+
+    #[test]
+    fn test_greedy_decisions() {
+        let mut knapsack = Problem01Knapsack::new(3);
+        knapsack.basis.weights = [10, 10, 30].iter().map(|&x| x as ScoreType).collect();
+        knapsack.basis.capacity = 25 as ScoreType;
+        knapsack.values = [30, 5, 60].iter().map(|&x| x as ScoreType).collect();
+        let root = knapsack.starting_solution(); // item 2 is too heavy, so already decided
+        assert!(knapsack.greedy_decision(&root, 0)); // density 3 > 35 / 20
+        assert!(!knapsack.greedy_decision(&root, 1)); // density 0.5 < 35 / 20
+        assert!(!knapsack.greedy_decision(&root, 2)); // too heavy
+
+        // The default: take it, if it fits
+        let mut problem = ProblemSubsetSum::new(2);
+        problem.weights = [3, 5].iter().map(|&x| x as ScoreType).collect();
+        problem.capacity = 4 as ScoreType;
+        let root = problem.starting_solution();
+        assert!(problem.greedy_decision(&root, 0));
+        assert!(!problem.greedy_decision(&root, 1));
+    }
+
+    #[test]
+    fn test_hyper_heuristic_find_solution() {
+        const NUM_DECISIONS: usize = 16;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let mut solver = HyperHeuristicSolver::builder(&knapsack);
+        let best = solver
+            .find_best_solution(&knapsack, Duration::from_millis(100))
+            .expect("could not find best solution");
+        assert!(knapsack.solution_is_legal(&best));
+        assert!(knapsack.solution_is_complete(&best));
+        assert!(0 < solver.num_rollouts);
+        assert!(0 < solver.number_of_solutions());
+        let pulls: usize = DecisionPolicy::ALL
+            .iter()
+            .map(|policy| solver.bandit.count(*policy))
+            .sum();
+        assert!(solver.num_rollouts <= pulls);
+
+        solver.clear();
+        assert_eq!(solver.num_rollouts, 0);
+        assert!(solver.mhd_memory.is_empty());
+
+        // The memory's decisions by another policy
+        let mut solver = HyperHeuristicSolver::builder(&knapsack)
+            .with_memory_policy(mhd_memory::EpsilonGreedy { epsilon: 0.2 });
+        let best = solver
+            .find_best_solution(&knapsack, Duration::from_millis(50))
+            .expect("could not find best solution");
+        assert!(knapsack.solution_is_legal(&best));
+        assert!(0 < solver.bandit.count(DecisionPolicy::MemoryUcb));
+    }
+}
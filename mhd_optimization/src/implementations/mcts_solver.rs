@@ -1,13 +1,14 @@
 use log::*;
 use rand::prelude::*; // for info, trace, warn, etc.
+use std::collections::HashMap;
 
-use mhd_memory::{ScoreType, ZERO_SCORE}; // ScoreType not needed (?!?)
+use mhd_memory::{MhdMemory, ScoreType, ZERO_SCORE}; // ScoreType not needed (?!?)
 
 /// # Example Implementations
 ///
 ///
 ///
-use optimizer::{Problem, Solution, Solver};
+use optimizer::{DecisionMap, EliteArchive, MemoryUsage, Problem, Solution, Solver, SolverBuilder};
 
 /**************************************************************************************/
 // Helper Struct -- the MCTS Tree Node Struct
@@ -18,6 +19,8 @@ pub struct MonteTreeNode {
     pub max_score: ScoreType,
     pub true_branch: Option<Box<MonteTreeNode>>,
     pub false_branch: Option<Box<MonteTreeNode>>,
+    /// The `Problem::transposition_key` of this node's state (only with a transposition table)
+    pub key: Option<u64>,
 }
 
 type UcbType = f64;
@@ -37,6 +40,7 @@ impl MonteTreeNode {
             counter: 0,
             true_branch: None,
             false_branch: None,
+            key: None,
         }
     }
 
@@ -115,21 +119,132 @@ impl MonteTreeNode {
         self.counter = 0;
     }
 
+    /// Cut off every branch below `depth` (the root is depth 0), and forget which nodes were
+    /// exhausted -- used after the problem changed, see `MonteCarloTreeSolver::adapt_to`.
+    /// The counters and max scores of the remaining nodes stay, as hints for the new search.
+    pub fn truncate(&mut self, depth: usize) {
+        self.exhausted = false;
+        if 0 == depth {
+            self.true_branch = None;
+            self.false_branch = None;
+        } else {
+            if let Some(true_box) = &mut self.true_branch {
+                true_box.truncate(depth - 1);
+            };
+            if let Some(false_box) = &mut self.false_branch {
+                false_box.truncate(depth - 1);
+            };
+        };
+    }
+
+    /// Check this (sub)tree against `problem`, a changed version of the problem it was grown
+    /// for (of the same size), replaying the decisions from `state` (this node's solution)
+    /// down -- see `MonteCarloTreeSolver::reuse_tree`. What's stale is dropped or reset:
+    /// subtrees below states which are complete now, max scores above a node's (new) bound,
+    /// and which nodes were exhausted. With `keys`, the transposition keys are recomputed.
+    /// Returns the number of nodes whose subtrees were dropped.
+    /// (A loop, not a recursion, like `grow_tree`.)
+    pub fn revalidate<Sol: Solution, Prob: Problem<Sol = Sol>>(
+        &mut self,
+        problem: &Prob,
+        state: Sol,
+        keys: bool,
+    ) -> usize {
+        let mut num_stale = 0;
+        let mut stack: Vec<(&mut MonteTreeNode, Sol)> = vec![(self, state)];
+        while let Some((node, state)) = stack.pop() {
+            // the rules make open decisions legal both ways, so the replay stays legal
+            debug_assert!(problem.solution_is_legal(&state));
+            node.exhausted = false;
+            node.key = if keys {
+                Some(problem.transposition_key(&state))
+            } else {
+                None
+            };
+            if state.get_best_score() < node.max_score {
+                node.max_score = state.get_best_score();
+            };
+            let index = match problem.first_open_decision(&state) {
+                Some(index) => index,
+                None => {
+                    // complete now (e.g. nothing fits any more): a leaf
+                    if node.true_branch.is_some() || node.false_branch.is_some() {
+                        node.true_branch = None;
+                        node.false_branch = None;
+                        num_stale += 1;
+                    };
+                    continue;
+                }
+            };
+            let branches = [
+                (true, node.true_branch.as_deref_mut()),
+                (false, node.false_branch.as_deref_mut()),
+            ];
+            for (decision, branch) in branches {
+                if let Some(child) = branch {
+                    let mut child_state = state.clone();
+                    child_state.make_decision(index, decision);
+                    problem.apply_rules(&mut child_state);
+                    stack.push((child, child_state));
+                };
+            }
+        } // end while there are nodes to check
+        num_stale
+    }
+
+    /// Drop both subtrees of an exhausted node: they will never be visited again, and this
+    /// node's counter and max score already sum them up. Keeps long searches from holding
+    /// on to (lots of) dead nodes until `clear()`.
+    #[inline]
+    pub fn collapse(&mut self) {
+        debug_assert!(self.exhausted);
+        self.true_branch = None;
+        self.false_branch = None;
+    }
+
+    /// Number of nodes in this (sub)tree
+    pub fn num_nodes(&self) -> usize {
+        let branch_nodes = |branch: &Option<Box<MonteTreeNode>>| match branch {
+            None => 0,
+            Some(node) => node.num_nodes(),
+        };
+        1 + branch_nodes(&self.true_branch) + branch_nodes(&self.false_branch)
+    }
+
+    /// This node's counter and max score -- or, with a transposition table, those of its state
+    /// (summed up over all the nodes with the same state)
+    #[inline]
+    pub fn statistics(&self, table: Option<&TranspositionTable>) -> (usize, ScoreType) {
+        match (table, self.key) {
+            (Some(table), Some(key)) => match table.get(key) {
+                Some(entry) => (entry.counter, entry.max_score),
+                None => (self.counter, self.max_score), // (the table was full)
+            },
+            _ => (self.counter, self.max_score),
+        }
+    }
+
     #[inline]
-    pub fn ucts_value(&self, parent_counter: usize, high_score: ScoreType) -> UcbType {
+    pub fn ucts_value(
+        &self,
+        table: Option<&TranspositionTable>,
+        parent_counter: usize,
+        high_score: ScoreType,
+    ) -> UcbType {
+        let (counter, max_score) = self.statistics(table);
         // avoid dividing by zero
         if self.exhausted {
             UCB_ZERO
-        } else if 0 == self.counter {
+        } else if 0 == counter {
             UCB_MAX
         } else {
-            // if 0 < self.counter and not exhausted
-            let n_j = self.counter as UcbType;
+            // if 0 < counter and not exhausted
+            let n_j = counter as UcbType;
             assert!(0 != parent_counter);
             let parent_n = parent_counter as UcbType;
 
             // left term -- the exploitation term
-            let exploitation = (self.max_score as UcbType) / (high_score as UcbType);
+            let exploitation = (max_score as UcbType) / (high_score as UcbType);
 
             // right summand -- the exploration term
             let exploration = (parent_n.ln() / n_j).sqrt() * UCB_C_P;
@@ -142,18 +257,26 @@ impl MonteTreeNode {
     #[inline]
     fn ucts_branch_ucb(
         branch: &Option<Box<MonteTreeNode>>,
+        table: Option<&TranspositionTable>,
         parent_counter: usize,
         high_score: ScoreType,
     ) -> UcbType {
         match branch {
             None => UCB_MAX,
-            Some(boxed_node) => boxed_node.ucts_value(parent_counter, high_score),
+            Some(boxed_node) => boxed_node.ucts_value(table, parent_counter, high_score),
         }
     } // end ucts_branch_value
 
-    fn best_ucb_branch(&self, full_monte: bool, high_score: ScoreType) -> bool {
-        let true_subtree_ucb = Self::ucts_branch_ucb(&self.true_branch, self.counter, high_score);
-        let false_subtree_ucb = Self::ucts_branch_ucb(&self.false_branch, self.counter, high_score);
+    fn best_ucb_branch(
+        &self,
+        table: Option<&TranspositionTable>,
+        full_monte: bool,
+        high_score: ScoreType,
+    ) -> bool {
+        let (counter, _) = self.statistics(table);
+        let true_subtree_ucb = Self::ucts_branch_ucb(&self.true_branch, table, counter, high_score);
+        let false_subtree_ucb =
+            Self::ucts_branch_ucb(&self.false_branch, table, counter, high_score);
         assert!(UCB_ZERO != true_subtree_ucb || UCB_ZERO != false_subtree_ucb);
         if UCB_ZERO == true_subtree_ucb {
             return false;
@@ -187,38 +310,89 @@ impl MonteTreeNode {
         }
     } // end best_ucb_branch
 
+    #[inline]
+    fn branch_mut(&mut self, decision: bool) -> &mut Option<Box<MonteTreeNode>> {
+        match decision {
+            true => &mut self.true_branch,
+            false => &mut self.false_branch,
+        }
+    }
+
+    // After a descent through this node found `new_score`: update the max score,
+    // and check for exhaustion
+    fn back_up(&mut self, new_score: ScoreType, table: Option<&mut TranspositionTable>) {
+        if self.max_score < new_score {
+            self.max_score = new_score;
+        };
+        if let (Some(table), Some(key)) = (table, self.key) {
+            table.record(key, new_score);
+        };
+
+        // check for exhaustion
+        //self.exhausted = match ( &self.true_branch, &self.false_branch ) {
+        //    ( Some( true_box ), Some( false_box) ) => { true_box.exhausted && false_box.exhausted },
+        //    _ => { self.exhausted }, // i.e. NOP, Do Nothing
+        //};
+        if let Some(true_box) = &self.true_branch {
+            if let Some(false_box) = &self.false_branch {
+                self.exhausted = true_box.exhausted && false_box.exhausted;
+            }; // end if unbox false branch
+        }; // endif unbox true branch
+        if self.exhausted {
+            self.collapse(); // only the aggregate max_score and counter are left
+        };
+    }
+
     ///////////////////////// GROW TREE ////////////////////////////////
+    /// One descent from this node (the root), making decisions for `solution` as it goes,
+    /// then the backup, from the node it stopped at up to here. Returns the score found.
+    /// A loop, not a recursion (the tree can be as deep as there are decisions): the nodes
+    /// on the path are taken out of the tree on the way down, and put back on the way up.
     fn grow_tree<Sol: Solution, Prob: Problem<Sol = Sol>>(
         &mut self,
         problem: &Prob,
         solution: &mut Sol,
         full_monte: bool,
         high_score: ScoreType,
+        mut table: Option<&mut TranspositionTable>,
+        mut rollout: Option<&mut RolloutPolicy>,
     ) -> ScoreType {
-        assert!(problem.solution_is_legal(solution)); // !!!
-        assert!(!self.exhausted); // logic above should make that impossible
-        if problem.solution_is_complete(solution) {
-            trace!(
-                "Top of grow_tree, COMPLETE solution score {} (high score {})",
-                solution.get_score(),
-                high_score
-            );
-            // complete and legal
-            self.exhausted = true;
-            let new_score = problem.solution_score(solution);
-            self.max_score = std::cmp::max(self.max_score, new_score);
-
-            // we could call self.store_best_solution now already, but...
-            // we won't need it until later!
-            debug_assert!(problem.rules_audit_passed(solution));
-            new_score
-        } else {
-            // end if solution is incomplete but legal and self NOT exhausted
-
-            self.counter += 1;
+        // The nodes below this one on the path, and the decision leading to each
+        let mut path: Vec<(Box<MonteTreeNode>, bool)> = Vec::new();
+
+        // Descend...
+        let new_score = loop {
+            let node: &mut MonteTreeNode = match path.last_mut() {
+                Some((node, _)) => node,
+                None => &mut *self,
+            };
+            assert!(problem.solution_is_legal(solution)); // !!!
+            assert!(!node.exhausted); // logic above should make that impossible
+            if problem.solution_is_complete(solution) {
+                trace!(
+                    "Top of grow_tree, COMPLETE solution score {} (high score {})",
+                    solution.get_score(),
+                    high_score
+                );
+                // complete and legal
+                node.exhausted = true;
+                // we could call self.store_best_solution now already, but...
+                // we won't need it until later!
+                debug_assert!(problem.rules_audit_passed(solution));
+                break problem.solution_score(solution);
+            };
+            // solution is incomplete but legal and node NOT exhausted
+
+            node.counter += 1;
+            if let Some(table) = table.as_deref_mut() {
+                let key = *node
+                    .key
+                    .get_or_insert_with(|| problem.transposition_key(solution));
+                table.visit(key);
+            };
 
             // decide on a branch!
-            let decision = self.best_ucb_branch(full_monte, high_score);
+            let decision = node.best_ucb_branch(table.as_deref(), full_monte, high_score);
 
             // Fix solution ... compare Problem::produce_children()
             debug_assert!(problem.solution_is_legal(solution));
@@ -229,7 +403,7 @@ impl MonteTreeNode {
             trace!(
                 "Grow_tree: depth {}, counter = {}, solution score {} (high score {}) => {}",
                 index,
-                self.counter,
+                node.counter,
                 solution.get_score(),
                 high_score,
                 decision
@@ -241,62 +415,193 @@ impl MonteTreeNode {
             debug_assert!(problem.rules_audit_passed(solution));
 
             // We do NOT check legality or completeness here,
-            // those will be tesed on the recursive call.
+            // those will be tesed on the next time round.
+
+            // unbox the choosen node (if the choosen branch is not there, put it there)
+            let choosen_branch = node.branch_mut(decision);
+            let expanded = choosen_branch.is_none();
+            let mut child = match choosen_branch.take() {
+                Some(child) => child,
+                None => {
+                    let mut child = MonteTreeNode::new();
+                    if let Some(table) = table.as_deref_mut() {
+                        let key = problem.transposition_key(solution);
+                        if table.get(key).is_some() {
+                            table.num_transpositions += 1; // been there, by another way
+                        };
+                        child.key = Some(key);
+                    };
+                    Box::new(child)
+                }
+            };
+            assert!(!child.exhausted); // if it was, we shouldn't be here...
+
+            // BOUND:
+            // we COULD call problem.could_be_better than, but we'd need access to the current
+            // best solution.  We use high_score instead.
+            if solution.get_best_score() <= high_score || problem.solution_is_complete(solution) {
+                child.exhausted = true;
+                child.collapse();
+                *choosen_branch = Some(child);
+                break solution.get_score();
+            };
+            // a new  best solution is possible, but solution is incomplete
+            // A new node? Then (with a rollout policy) it's time for a rollout...
+            if let Some(policy) = rollout.as_deref_mut().filter(|_| expanded) {
+                policy.complete(problem, solution);
+                *choosen_branch = Some(child);
+                break solution.get_score();
+            };
+            // so...               Down we go!
+            path.push((child, decision));
+        }; // end descent
+
+        // We don't have to update self.best_solution here -- we do that when this method
+        // is finished.
+
+        // ...and back up, putting the path back into the tree
+        while let Some((mut node, decision)) = path.pop() {
+            node.back_up(new_score, table.as_deref_mut());
+            let parent: &mut MonteTreeNode = match path.last_mut() {
+                Some((parent, _)) => parent,
+                None => &mut *self,
+            };
+            *parent.branch_mut(decision) = Some(node);
+        }
+        self.back_up(new_score, table);
+        new_score
+    } // end grow_tree
+} // end impl MonteTreeNode
+
+/**************************************************************************************/
+// Helper Enum -- the Rollout Policy
+
+/// How to complete a solution, from a node just added to the tree, see
+/// `MonteCarloTreeSolver::with_rollout`: each open decision in turn, as the policy says
+/// (or the other way, if that is illegal), applying the rules after each one.
+#[derive(Debug, Clone)]
+pub enum RolloutPolicy {
+    /// Flip a coin
+    Random,
+    /// Ask the problem, see `Problem::greedy_decision`
+    Greedy,
+    /// Read an MHD memory (of all rollouts so far; a coin flip while it is empty),
+    /// as the `MhdMonteCarloSolver` does
+    Memory(Box<MhdMemory>),
+}
+
+impl RolloutPolicy {
+    /// A memory guided rollout policy, for a problem with `width` decisions
+    pub fn memory(width: usize) -> Self {
+        RolloutPolicy::Memory(Box::new(MhdMemory::new(width)))
+    }
 
-            let choosen_branch = match decision {
-                true => &mut self.true_branch,
-                false => &mut self.false_branch,
+    /// Complete `solution` (in place), see `RolloutPolicy`
+    pub fn complete<Sol: Solution, Prob: Problem<Sol = Sol>>(
+        &mut self,
+        problem: &Prob,
+        solution: &mut Sol,
+    ) {
+        let mut generator = thread_rng();
+        while let Some(index) = problem.first_open_decision(solution) {
+            let decision = match self {
+                RolloutPolicy::Random => generator.gen(),
+                RolloutPolicy::Greedy => problem.greedy_decision(solution, index),
+                RolloutPolicy::Memory(memory) if memory.is_empty() => generator.gen(),
+                RolloutPolicy::Memory(memory) => {
+                    memory.read_and_decide(solution.mask(), solution.query(), index, true)
+                }
             };
-            // if the choosen branch is not there, put it there.
-            if choosen_branch.is_none() {
-                *choosen_branch = Some(Box::new(MonteTreeNode::new()));
+            solution.make_decision(index, decision);
+            if !problem.solution_is_legal(solution) {
+                solution.make_decision(index, !decision);
             };
+            problem.apply_rules(solution);
+            debug_assert!(problem.rules_audit_passed(solution));
+        } // end while there are open decisions
+        if let RolloutPolicy::Memory(memory) = self {
+            memory.write_sample(&problem.sample_from_solution(solution));
+        };
+    }
+} // end impl RolloutPolicy
 
-            // unbox the choosen node
-            assert!(choosen_branch.is_some());
-            if let Some(boxed_node) = choosen_branch {
-                assert!(!boxed_node.exhausted); // if it was, we shouldn't be here...
-
-                // BOUND:
-                // we COULD call problem.could_be_better than, but we'd need access to the current
-                // best solution.  We use high_score instead.
-                let new_score: ScoreType;
-                if solution.get_best_score() <= high_score
-                    || problem.solution_is_complete(&solution)
-                {
-                    boxed_node.exhausted = true;
-                    new_score = solution.get_score();
-                } else {
-                    // a new  best solution is possible, but solution is incomplete
-                    // so...               Recursion!
-                    new_score = boxed_node.grow_tree(problem, solution, full_monte, high_score);
-                };
+/**************************************************************************************/
+// Helper Struct -- the Transposition Table
 
-                self.max_score = std::cmp::max(self.max_score, new_score);
+/// What all the nodes with the same state have seen, see `TranspositionTable`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TranspositionEntry {
+    pub counter: usize,
+    pub max_score: ScoreType,
+}
 
-                // We don't have to update self.best_solution here -- we do that when this method
-                // is finished (after unrolling all the recursion.
+/// Different paths through the tree can reach the same state (for a subset sum, e.g.,
+/// all partial solutions with the same sum and the same decisions still open, see
+/// `Problem::transposition_key`), but each path has nodes of its own. A transposition table
+/// lets them share their statistics: the UCB values are computed from the counter and max
+/// score of the state, i.e. of all its nodes together. At most `max_entries` states are
+/// kept; when the table is full, new states just use their nodes' own statistics.
+#[derive(Debug, Default, Clone)]
+pub struct TranspositionTable {
+    entries: HashMap<u64, TranspositionEntry>,
+    pub max_entries: usize,
+    /// How often a new node found its state in the table already
+    pub num_transpositions: usize,
+}
 
-                // check for exhaustion
-                //self.exhausted = match ( &self.true_branch, &self.false_branch ) {
-                //    ( Some( true_box ), Some( false_box) ) => { true_box.exhausted && false_box.exhausted },
-                //    _ => { self.exhausted }, // i.e. NOP, Do Nothing
-                //};
-                if let Some(true_box) = &self.true_branch {
-                    if let Some(false_box) = &self.false_branch {
-                        self.exhausted = true_box.exhausted && false_box.exhausted;
-                    }; // end if unbox false branch
-                }; // endif unbox true branch
+impl TranspositionTable {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            max_entries,
+            num_transpositions: 0,
+        }
+    }
 
-                // return
-                new_score
-            } else {
-                // if let Some( boxed_node ) didn't work
-                panic!("Rust is broken! Found 'None' after explicitly getting rid of it!");
-            } // end if rust is broken
-        } // end if solution incomplete
-    } // end grow_tree
-} // end impl MonteTreeNode
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    #[inline]
+    pub fn get(&self, key: u64) -> Option<&TranspositionEntry> {
+        self.entries.get(&key)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.num_transpositions = 0;
+    }
+
+    // The entry of state `key` (a new one, if there is room)
+    fn entry(&mut self, key: u64) -> Option<&mut TranspositionEntry> {
+        if self.max_entries <= self.entries.len() && !self.entries.contains_key(&key) {
+            return None;
+        };
+        Some(self.entries.entry(key).or_default())
+    }
+
+    // A node of state `key` was visited
+    fn visit(&mut self, key: u64) {
+        if let Some(entry) = self.entry(key) {
+            entry.counter += 1;
+        };
+    }
+
+    // A node of state `key` led to a (complete) solution with score `score`
+    fn record(&mut self, key: u64, score: ScoreType) {
+        if let Some(entry) = self.entry(key) {
+            if entry.max_score < score {
+                entry.max_score = score;
+            };
+        };
+    }
+} // end impl TranspositionTable
 
 /**************************************************************************************/
 /// ## Example Solver Implementation: MCTS, Monte Carlo Tree Search
@@ -309,6 +614,12 @@ pub struct MonteCarloTreeSolver<Sol: Solution, Prob: Problem<Sol = Sol>> {
     pub full_monte: bool,
     pub mcts_root: MonteTreeNode,
     pub best_solution: Sol,
+    /// Optional archive of good and diverse solutions, see `EliteArchive`
+    pub elites: Option<EliteArchive<Sol>>,
+    /// Optional statistics shared by nodes of the same state, see `with_transpositions`
+    pub transpositions: Option<TranspositionTable>,
+    /// Optional rollouts from new nodes, see `with_rollout`
+    pub rollout: Option<RolloutPolicy>,
     pub problem: Prob,
 }
 
@@ -320,9 +631,88 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> MonteCarloTreeSolver<Sol, Prob> {
             full_monte: false, // until overwritten with true
             mcts_root: MonteTreeNode::root(),
             best_solution: problem.random_solution(),
+            elites: None,
+            transpositions: None,
+            rollout: None,
             problem: problem.clone(), // = problem, note rust syntatic sugar
         }
     }
+
+    /// Classic MCTS: each descent adds (at most) one node to the tree, and completes the
+    /// solution from there with a rollout (see `RolloutPolicy`), whose score backs up the tree.
+    /// (Without a rollout policy, each descent adds nodes all the way down, until the bound
+    /// prunes it, or the solution is complete.)
+    pub fn with_rollout(mut self, policy: RolloutPolicy) -> Self {
+        self.rollout = Some(policy);
+        self
+    }
+
+    /// Share statistics between nodes of the same state, in a transposition table of
+    /// at most `max_entries` states, see `TranspositionTable`
+    pub fn with_transpositions(mut self, max_entries: usize) -> Self {
+        self.transpositions = Some(TranspositionTable::new(max_entries));
+        self
+    }
+
+    /// Adapt this solver to `problem`, a changed version of its old problem (see `DecisionMap`),
+    /// keeping the tree down to the depth where decisions still mean the same thing,
+    /// and carrying the best solution over (if it is still legal).
+    /// Then `find_best_solution_from` the carried over best solution re-solves quickly.
+    pub fn adapt_to(&mut self, problem: &Prob, map: &DecisionMap) -> Option<Sol> {
+        self.mcts_root.truncate(map.stable_prefix());
+        if let Some(table) = self.transpositions.as_mut() {
+            table.clear(); // the states have changed
+        };
+        let warm_start = map.carry_over_solution(problem, &self.best_solution);
+        self.best_solution = match &warm_start {
+            Some(solution) => solution.clone(),
+            None => problem.random_solution(),
+        };
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
+        self.problem = problem.clone();
+        warm_start
+    }
+
+    /// Keep the tree for `problem`, a changed version of the old problem with the same
+    /// decisions (e.g. a knapsack with a new capacity), instead of starting from scratch:
+    /// the tree is revalidated (see `MonteTreeNode::revalidate`), and the best solution is
+    /// carried over, if it is still legal (with its new score). If the number of decisions
+    /// changed, the tree is stale as a whole, and cleared. (For added or removed decisions,
+    /// see `adapt_to`.) Returns the carried over best solution, as `adapt_to` does,
+    /// for `find_best_solution_from`.
+    pub fn reuse_tree(&mut self, problem: &Prob) -> Option<Sol> {
+        let size = problem.problem_size();
+        if let Some(table) = self.transpositions.as_mut() {
+            table.clear(); // the states may have changed
+        };
+        let warm_start = if size == self.problem.problem_size() {
+            let num_stale = self.mcts_root.revalidate(
+                problem,
+                problem.starting_solution(),
+                self.transpositions.is_some(),
+            );
+            debug!("Reusing the MCTS tree, {} stale subtrees dropped", num_stale);
+            DecisionMap::identity(size).carry_over_solution(problem, &self.best_solution)
+        } else {
+            debug!("Not reusing the MCTS tree, {} decisions now", size);
+            self.mcts_root.clear();
+            if let Some(RolloutPolicy::Memory(memory)) = self.rollout.as_mut() {
+                **memory = MhdMemory::new(size);
+            };
+            None
+        };
+        self.best_solution = match &warm_start {
+            Some(solution) => solution.clone(),
+            None => problem.random_solution(),
+        };
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
+        self.problem = problem.clone();
+        warm_start
+    }
 } // end private Methods
 
 /**************************************************************************************/
@@ -344,11 +734,6 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for MonteCarloTreeSolv
         )
     }
 
-    #[inline]
-    fn new(_: usize) -> Self {
-        panic!("New(size) not define for MonteCarloTreeSolver!");
-    }
-
     // Methods used by the Unified Optimization Algorithm (identified above)
 
     #[inline]
@@ -356,6 +741,18 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for MonteCarloTreeSolv
         self.mcts_root.counter
     }
 
+    #[inline]
+    fn memory_usage(&self) -> MemoryUsage {
+        let mut result = MemoryUsage::of_tree::<MonteTreeNode>(self.mcts_root.num_nodes());
+        if let Some(table) = self.transpositions.as_ref() {
+            result = result + MemoryUsage::of_tree::<(u64, TranspositionEntry)>(table.len());
+        };
+        if let Some(RolloutPolicy::Memory(memory)) = self.rollout.as_ref() {
+            result = result + MemoryUsage::of_memory(memory);
+        };
+        result
+    }
+
     #[inline]
     fn is_empty(&self) -> bool {
         0 == self.mcts_root.counter
@@ -369,8 +766,17 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for MonteCarloTreeSolv
     #[inline]
     fn clear(&mut self) {
         self.mcts_root.clear();
+        if let Some(table) = self.transpositions.as_mut() {
+            table.clear();
+        };
+        if let Some(RolloutPolicy::Memory(memory)) = self.rollout.as_mut() {
+            memory.clear();
+        };
         let size = self.best_solution.size();
         self.best_solution = Sol::new(size);
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
     }
 
     #[inline]
@@ -384,11 +790,14 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for MonteCarloTreeSolv
     #[inline]
     fn pop(&mut self) -> Option<Sol> {
         let mut result = self.problem.starting_solution();
+        let high_score = self.best_score();
         let score = self.mcts_root.grow_tree(
             &self.problem,
             &mut result,
             self.full_monte,
-            self.best_score(),
+            high_score,
+            self.transpositions.as_mut(),
+            self.rollout.as_mut(),
         );
         debug!("Pop called grow_tree, got back {}", score);
         Some(result)
@@ -399,6 +808,11 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for MonteCarloTreeSolv
         &self.best_solution
     }
 
+    #[inline]
+    fn elite_archive(&mut self) -> Option<&mut EliteArchive<Sol>> {
+        self.elites.as_mut()
+    }
+
     #[inline]
     fn store_best_solution(&mut self, solution: Sol) {
         // we'd like to check for completion, but can't use proble.solution_is_complete( s )
@@ -409,13 +823,22 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for MonteCarloTreeSolv
     }
 } // end imp Solver for MonteCarloTreeSolver
 
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> SolverBuilder<Prob>
+    for MonteCarloTreeSolver<Sol, Prob>
+{
+    #[inline]
+    fn from_problem(problem: &Prob) -> Self {
+        Self::builder(problem)
+    }
+}
+
 /**************************************************************************************/
 //////////////// TESTs for ProblemSubsetSum with  MonteCarloTreeSolver /////////////////
 #[cfg(test)]
 mod more_tests {
     use super::*;
     use implementations::*;
-    use optimizer::{MinimalSolution, Problem, Solution, Solver};
+    use optimizer::{MinimalSolution, Problem, Solution, Solver, SolverConfig};
 
     const NUM_DECISIONS: usize = 64; // for a start
 
@@ -426,14 +849,18 @@ mod more_tests {
         let solver = MonteCarloTreeSolver::<MinimalSolution, ProblemSubsetSum>::builder(&problem);
         assert_eq!(solver.mcts_root.max_score, ZERO_SCORE);
 
-        assert_eq!(solver.mcts_root.ucts_value(0, solver.best_score()), UCB_MAX);
+        assert_eq!(
+            solver.mcts_root.ucts_value(None, 0, solver.best_score()),
+            UCB_MAX
+        );
 
+        let root = &solver.mcts_root;
         assert_eq!(
-            MonteTreeNode::ucts_branch_ucb(&solver.mcts_root.true_branch, 0, solver.best_score()),
+            MonteTreeNode::ucts_branch_ucb(&root.true_branch, None, 0, solver.best_score()),
             UCB_MAX
         );
         assert_eq!(
-            MonteTreeNode::ucts_branch_ucb(&solver.mcts_root.false_branch, 0, solver.best_score()),
+            MonteTreeNode::ucts_branch_ucb(&root.false_branch, None, 0, solver.best_score()),
             UCB_MAX
         );
     }
@@ -487,8 +914,12 @@ mod more_tests {
         // assert!(!solver.is_empty());
         assert_eq!(solver.mcts_root.counter, 2);
 
-        // One or both subtrees are not none, i.e. are some...
-        assert!(solver.mcts_root.true_branch.is_some() || solver.mcts_root.false_branch.is_some());
+        // One or both subtrees are not none, i.e. are some... (unless all are exhausted already)
+        assert!(
+            solver.mcts_root.exhausted
+                || solver.mcts_root.true_branch.is_some()
+                || solver.mcts_root.false_branch.is_some()
+        );
 
         assert!(solver.problem.rules_audit_passed(&solution2));
 
@@ -532,6 +963,8 @@ mod more_tests {
 
         assert!(solver.mcts_root.exhausted);
         assert!(solver.mcts_root.counter <= MAX_COUNTER);
+        // The exhausted tree has been collapsed into its root
+        assert_eq!(solver.mcts_root.num_nodes(), 1);
 
         assert!(solver.problem.solution_is_legal(&the_best));
         assert!(solver.problem.solution_is_complete(&the_best));
@@ -542,6 +975,117 @@ mod more_tests {
         assert!(the_best.get_score() <= solver.problem.capacity);
     }
 
+    #[test]
+    fn test_mcts_rollouts() {
+        const FEW_DECISIONS: usize = 8; // so we can be sure to find THE optimum!
+        let knapsack = Problem01Knapsack::random(FEW_DECISIONS);
+        let optimum = ExhaustiveSolver::builder(&knapsack).solve();
+        for policy in [
+            RolloutPolicy::Random,
+            RolloutPolicy::Greedy,
+            RolloutPolicy::memory(FEW_DECISIONS),
+        ]
+        .iter()
+        {
+            let mut solver =
+                MonteCarloTreeSolver::<ZeroOneKnapsackSolution, Problem01Knapsack>::builder(
+                    &knapsack,
+                )
+                .with_rollout(policy.clone());
+            // One new node per descent (at most)
+            let mut popper = solver.clone();
+            for pops in 1..=4 {
+                let solution = popper.pop().expect("pop() should return Some(sol)");
+                assert!(knapsack.solution_is_legal(&solution));
+                assert!(popper.mcts_root.num_nodes() <= 1 + pops);
+            }
+            // Still exhaustive, in the end
+            let the_best = solver
+                .find_best_solution(&knapsack, std::time::Duration::from_secs(1))
+                .expect("could not find best solution");
+            assert!(solver.mcts_root.exhausted, "{:?}", policy);
+            assert_eq!(the_best.get_score(), optimum.get_score(), "{:?}", policy);
+        }
+        let solver = MonteCarloTreeSolver::<ZeroOneKnapsackSolution, Problem01Knapsack>::builder(
+            &knapsack,
+        )
+        .with_rollout(RolloutPolicy::memory(FEW_DECISIONS));
+        match solver.rollout {
+            Some(RolloutPolicy::Memory(memory)) => assert!(memory.is_empty()),
+            _ => panic!("a memory guided rollout policy"),
+        };
+    }
+
+    #[test]
+    fn test_mcts_deep_tree() {
+        // One node per decision on the way down: deeper than a recursion would like to go
+        const MANY_DECISIONS: usize = 2000;
+        let problem = ProblemSubsetSum::random(MANY_DECISIONS);
+        let mut solver =
+            MonteCarloTreeSolver::<MinimalSolution, ProblemSubsetSum>::builder(&problem);
+        // The empty knapsack is the best solution so far, so nothing gets pruned
+        let mut empty = problem.starting_solution();
+        while let Some(index) = problem.first_open_decision(&empty) {
+            empty.make_decision(index, false);
+            problem.apply_rules(&mut empty);
+        }
+        solver.store_best_solution(empty);
+        let first = solver.pop().expect("pop() should return Some(sol)");
+        assert!(problem.solution_is_legal(&first));
+        assert!(problem.solution_is_complete(&first));
+        assert_eq!(solver.mcts_root.counter, 1);
+        assert!(MANY_DECISIONS / 2 < solver.mcts_root.num_nodes());
+        assert_eq!(solver.mcts_root.max_score, first.get_score());
+        solver.pop();
+        assert_eq!(solver.mcts_root.counter, 2);
+    }
+
+    #[test]
+    fn test_mcts_transpositions() {
+        const FEW_DECISIONS: usize = 10;
+        // All the same weight: many ways to the same sum
+        let mut problem = ProblemSubsetSum::new(FEW_DECISIONS);
+        problem.weights = vec![3 as ScoreType; FEW_DECISIONS];
+        problem.capacity = 16 as ScoreType;
+        let first = problem.produce_child(&problem.starting_solution(), 0, true);
+        let both = problem.produce_child(&first, 1, false);
+        let other = problem.produce_child(&problem.starting_solution(), 0, false);
+        let other = problem.produce_child(&other, 1, true);
+        assert_eq!(
+            problem.transposition_key(&both),
+            problem.transposition_key(&other)
+        );
+        assert_ne!(
+            problem.transposition_key(&first),
+            problem.transposition_key(&both)
+        );
+
+        let mut solver =
+            MonteCarloTreeSolver::<MinimalSolution, ProblemSubsetSum>::builder(&problem)
+                .with_transpositions(1000);
+        let the_best = solver
+            .find_best_solution(&problem, std::time::Duration::from_secs(1))
+            .expect("could not find best solution");
+        assert_eq!(the_best.get_score(), 15 as ScoreType);
+        let table = solver.transpositions.as_ref().expect("a table");
+        assert!(0 < table.num_transpositions);
+        assert!(!table.is_empty() && table.len() <= 1000);
+        let (counter, max_score) = solver.mcts_root.statistics(Some(table));
+        assert!(solver.mcts_root.counter <= counter);
+        assert_eq!(max_score, 15 as ScoreType);
+
+        // A full table takes no more states
+        let mut solver =
+            MonteCarloTreeSolver::<MinimalSolution, ProblemSubsetSum>::builder(&problem)
+                .with_transpositions(4);
+        for _ in 0..20 {
+            solver.pop();
+        }
+        assert_eq!(solver.transpositions.as_ref().unwrap().len(), 4);
+        solver.clear();
+        assert!(solver.transpositions.as_ref().unwrap().is_empty());
+    }
+
     #[test]
     fn test_mcts_find_01knapsack_solution() {
         const FEW_DECISIONS: usize = 8; // so we can be sure to find THE optimum!
@@ -575,6 +1119,9 @@ mod more_tests {
             solver.problem.solution_score(&the_best),
             the_best.get_score()
         );
+        // THE optimum, see the oracle
+        let optimum = ExhaustiveSolver::builder(&knapsack).solve();
+        assert_eq!(the_best.get_score(), optimum.get_score());
     }
 
     #[test]
@@ -629,4 +1176,91 @@ mod more_tests {
             second_best.get_score()
         );
     }
+
+    #[test]
+    fn test_mcts_adapt_to_changed_problem() {
+        const FEW_DECISIONS: usize = 10;
+        let mut knapsack = Problem01Knapsack::random(FEW_DECISIONS);
+        let mut solver = MonteCarloTreeSolver::builder(&knapsack);
+
+        use std::time::Duration;
+        let time_limit = Duration::from_millis(200);
+        let the_best = solver
+            .find_best_solution(&knapsack, time_limit)
+            .expect("could not find best solution");
+        let num_nodes = solver.mcts_root.num_nodes();
+
+        // Remove the last item: only the tree below it is cut off
+        let map = knapsack.remove_item(FEW_DECISIONS - 1);
+        let warm_start = solver.adapt_to(&knapsack, &map);
+        assert!(solver.mcts_root.num_nodes() <= num_nodes);
+        assert!(!solver.mcts_root.exhausted);
+        if the_best.get_decision(FEW_DECISIONS - 1) == Some(false) {
+            assert_eq!(warm_start.as_ref().unwrap().get_score(), the_best.get_score());
+        };
+
+        let new_best = match warm_start {
+            Some(solution) => solver.find_best_solution_from(&knapsack, solution, time_limit),
+            None => solver.find_best_solution(&knapsack, time_limit),
+        }
+        .expect("could not re-solve");
+        assert!(knapsack.solution_is_legal(&new_best));
+        assert!(knapsack.solution_is_complete(&new_best));
+        assert_eq!(knapsack.solution_score(&new_best), new_best.get_score());
+
+        let mut root = MonteTreeNode::root();
+        root.true_branch = Some(Box::new(MonteTreeNode::new()));
+        assert_eq!(root.num_nodes(), 2);
+        root.truncate(0);
+        assert_eq!(root.num_nodes(), 1);
+    }
+
+    #[test]
+    fn test_mcts_reuse_tree() {
+        const FEW_DECISIONS: usize = 12;
+        let mut knapsack = Problem01Knapsack::random(FEW_DECISIONS);
+        let mut solver = MonteCarloTreeSolver::builder(&knapsack).with_transpositions(1000);
+        let few_steps = SolverConfig {
+            node_limit: Some(40),
+            ..Default::default()
+        };
+        let the_best = solver
+            .find_best_solution(&knapsack, few_steps.clone())
+            .expect("could not find best solution");
+        let num_nodes = solver.mcts_root.num_nodes();
+
+        // More room: the tree is kept (with its counters), and so is the best solution
+        let capacity = knapsack.capacity();
+        knapsack.set_capacity(capacity + capacity / 2 as ScoreType);
+        let warm_start = solver.reuse_tree(&knapsack).expect("still legal");
+        assert_eq!(warm_start.get_score(), the_best.get_score());
+        assert_eq!(solver.mcts_root.num_nodes(), num_nodes);
+        assert!(!solver.mcts_root.exhausted);
+        assert!(0 < solver.mcts_root.counter);
+
+        // Nothing fits any more: every state is complete, the subtrees are stale
+        knapsack.set_capacity(ZERO_SCORE);
+        solver.reuse_tree(&knapsack);
+        assert_eq!(solver.mcts_root.num_nodes(), 1);
+        assert_eq!(solver.mcts_root.max_score, ZERO_SCORE);
+
+        // Still exhaustive, from a reused tree
+        knapsack.set_capacity(capacity);
+        let optimum = ExhaustiveSolver::builder(&knapsack).solve();
+        solver
+            .find_best_solution(&knapsack, few_steps)
+            .expect("could not find best solution");
+        let warm_start = solver.reuse_tree(&knapsack).expect("the same problem");
+        let new_best = solver
+            .find_best_solution_from(&knapsack, warm_start, std::time::Duration::from_secs(1))
+            .expect("could not re-solve");
+        assert!(solver.is_finished());
+        assert_eq!(new_best.get_score(), optimum.get_score());
+
+        // A new item: the tree is stale as a whole
+        knapsack.add_item(10 as ScoreType, 10 as ScoreType);
+        assert!(solver.reuse_tree(&knapsack).is_none());
+        assert_eq!(solver.mcts_root.num_nodes(), 1);
+        assert_eq!(solver.mcts_root.counter, 0);
+    }
 }
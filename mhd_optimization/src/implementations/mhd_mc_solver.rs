@@ -1,7 +1,8 @@
 use log::*;
+use std::sync::Arc;
 
 use mhd_memory::*;
-use optimizer::{Problem, Solution, Solver};
+use optimizer::{DecisionMap, EliteArchive, MemoryUsage, Problem, Solution, Solver, SolverBuilder};
 
 /// # Example Implementations
 ///
@@ -11,8 +12,24 @@ use optimizer::{Problem, Solution, Solver};
 pub struct MhdMonteCarloSolver<Sol: Solution, Prob: Problem<Sol = Sol>> {
     pub mhd_memory: MhdMemory,
     pub best_solution: Sol,
+    /// Optional archive of good and diverse solutions, see `EliteArchive`
+    pub elites: Option<EliteArchive<Sol>>,
     pub problem: Prob,
     pub full_monte: bool,
+    /// Restart (keeping the memory) after this many pops without a new best (None = never);
+    /// the limit doubles with every restart, so that searches still converge.
+    /// (The incumbent is kept across these restarts.)
+    pub restart_after: Option<usize>,
+    /// How often the solver restarted (see `restart_keep_memory`)
+    pub num_restarts: usize,
+    /// How to decide, given what the memory reads (None = `UcbPolicy`, with `full_monte`)
+    pub policy: Option<Arc<dyn DecisionPolicy>>,
+    /// Improve each new solution by local search (flipping up to this many decisions)
+    /// before the memory learns it (None = don't), see `with_local_search`
+    pub local_search: Option<usize>,
+    /// How often the local search improved a solution
+    pub num_local_improvements: usize,
+    stagnation: usize, // pops without a new best solution
 }
 
 impl<Sol: Solution, Prob: Problem<Sol = Sol>> MhdMonteCarloSolver<Sol, Prob> {
@@ -35,10 +52,14 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> MhdMonteCarloSolver<Sol, Prob> {
         // };
         // Version 1: (since 30 May 2021)
         let target = if self.problem.problem_size() < 16 { 4 } else { 16 };
-        while self.mhd_memory.num_samples() < target {
+        // Some (small) problems have fewer than target legal solutions, so don't try forever
+        let max_attempts = 64 * target;
+        let mut attempts = 0;
+        while self.mhd_memory.num_samples() < target && attempts < max_attempts {
             let solution = self.problem.random_solution();
             self.mhd_memory
                 .write_sample(&self.problem.sample_from_solution(&solution));
+            attempts += 1;
         };
         debug!("MHD MCSolver Builder -- Size goal was {}, build size {}",
                  target, self.mhd_memory.num_samples() );
@@ -51,8 +72,15 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> MhdMonteCarloSolver<Sol, Prob> {
         let mut product = Self {
             mhd_memory: MhdMemory::new(problem.problem_size()),
             best_solution: problem.random_solution(),
+            elites: None,
             problem: problem.clone(),
             full_monte: false, // until overwritten with true
+            restart_after: None,
+            num_restarts: 0,
+            policy: None,
+            local_search: None,
+            num_local_improvements: 0,
+            stagnation: 0,
         };
         // bootstrap the memory with random samples (but legal ones!)
         product.bootstrap_memory();
@@ -60,6 +88,102 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> MhdMonteCarloSolver<Sol, Prob> {
         product
     }
 
+    /// Read the memory with `kernel` (see `DistanceKernel`), e.g. with a smaller threshold
+    /// for a large problem
+    pub fn with_kernel(mut self, kernel: DistanceKernel) -> Self {
+        self.mhd_memory.kernel = kernel;
+        self
+    }
+
+    /// Decide with `policy` (see `DecisionPolicy`) instead of the memory's UCB priorities
+    pub fn with_policy<P: DecisionPolicy + 'static>(mut self, policy: P) -> Self {
+        self.policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// The hybrid: improve each solution the memory constructs by local search (first
+    /// improvement: the first better neighbor within `max_flips` flipped decisions, until there
+    /// is none, see `Problem::neighbors`), and let the memory learn the local optimum instead.
+    /// So the memory learns from good solutions only, and reads better.
+    /// (If it knows the local optimum already, it learns the solution it constructed.)
+    pub fn with_local_search(mut self, max_flips: usize) -> Self {
+        self.local_search = Some(max_flips);
+        self
+    }
+
+    /// First improvement local search from `solution`, see `with_local_search`
+    pub fn climb(&mut self, solution: &Sol, max_flips: usize) -> Sol {
+        let mut current = solution.clone();
+        while let Some(better) = self
+            .problem
+            .neighbors(&current, max_flips)
+            .find(|neighbor| self.problem.better_than(neighbor, &current))
+        {
+            current = better;
+            self.num_local_improvements += 1;
+        }
+        current
+    }
+
+    /// Restart the search, but keep everything the memory has learned so far
+    /// (unlike `clear()`, which wipes and re-bootstraps the memory).
+    /// The rollouts always start at the root, so the only other state is the incumbent:
+    /// it is replaced by a random solution (as at the start of a search), and returned,
+    /// so the caller can keep it -- or offer it again as a warm start.
+    /// The elite archive (if any) and the stagnation count are reset, too.
+    pub fn restart_keep_memory(&mut self) -> Sol {
+        let previous_best =
+            std::mem::replace(&mut self.best_solution, self.problem.random_solution());
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
+        self.stagnation = 0;
+        self.num_restarts += 1;
+        debug!(
+            "MHD MCSolver restart {}, keeping {} samples, previous best score {}",
+            self.num_restarts,
+            self.mhd_memory.num_samples(),
+            previous_best.get_score()
+        );
+        previous_best
+    }
+
+    /// Adapt this solver to `problem`, a changed version of its old problem (see `DecisionMap`):
+    /// Every sample in the memory is carried over (with its new score), except those which
+    /// are not legal any more, and so is the best solution (if it is still legal).
+    /// Then `find_best_solution_from` the carried over best solution re-solves quickly.
+    pub fn adapt_to(&mut self, problem: &Prob, map: &DecisionMap) -> Option<Sol> {
+        let mut memory = MhdMemory::new(map.new_size());
+        memory.weighting = self.mhd_memory.weighting;
+        memory.kernel = self.mhd_memory.kernel;
+        for sample in self.mhd_memory.samples.iter() {
+            if let Some(carried) = map.carry_over_sample(problem, sample) {
+                memory.write_sample(&carried);
+            };
+        }
+        debug!(
+            "MHD MCSolver adapted {} of {} samples to the changed problem",
+            memory.num_samples(),
+            self.mhd_memory.num_samples()
+        );
+        self.mhd_memory = memory;
+        self.problem = problem.clone();
+        if self.mhd_memory.is_empty() {
+            self.bootstrap_memory();
+        };
+
+        let warm_start = map.carry_over_solution(problem, &self.best_solution);
+        self.best_solution = match &warm_start {
+            Some(solution) => solution.clone(),
+            None => problem.random_solution(),
+        };
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
+        self.stagnation = 0;
+        warm_start
+    }
+
     /// **The whole magic is _here!_**
     ///
     /// `find_new_solution()` is a recursive utility function that uses MCTS
@@ -67,6 +191,17 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> MhdMonteCarloSolver<Sol, Prob> {
     /// knows how to react should it find a solution which is already in the memory.
     fn find_new_solution(&mut self, solution: &Sol) -> Option<Sol> {
         if self.problem.solution_is_complete(solution) {
+            if let Some(max_flips) = self.local_search {
+                let optimum = self.climb(solution, max_flips);
+                if optimum != *solution
+                    && self
+                        .mhd_memory
+                        .write_sample(&self.problem.sample_from_solution(&optimum))
+                {
+                    trace!("find_new_solution, returning new local optimum!");
+                    return Some(optimum);
+                };
+            };
             if self
                 .mhd_memory
                 .write_sample(&self.problem.sample_from_solution(solution))
@@ -85,12 +220,20 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> MhdMonteCarloSolver<Sol, Prob> {
                 .expect("Should have an open decision");
             // Decide whether to set the next open bit to true or false, 1 or 0
             // First, query the mhd memory
-            let decision = self.mhd_memory.read_and_decide(
-                solution.mask(),
-                solution.query(),
-                open_decision,
-                self.full_monte,
-            );
+            let decision = match self.policy.as_ref() {
+                Some(policy) => self.mhd_memory.read_and_decide_with(
+                    policy.as_ref(),
+                    solution.mask(),
+                    solution.query(),
+                    open_decision,
+                ),
+                None => self.mhd_memory.read_and_decide(
+                    solution.mask(),
+                    solution.query(),
+                    open_decision,
+                    self.full_monte,
+                ),
+            };
 
             // Now, try this solution and see if it's usable...
             let mut child = solution.clone();
@@ -156,11 +299,6 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for MhdMonteCarloSolve
         )
     }
 
-    #[inline]
-    fn new(_: usize) -> Self {
-        panic!("New(size) not define for MhdMonteCarloSolver!");
-    }
-
     // Methods used by the Unified Optimization Algorithm (identified above)
 
     #[inline]
@@ -168,6 +306,11 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for MhdMonteCarloSolve
         self.mhd_memory.num_samples()
     }
 
+    #[inline]
+    fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::of_memory(&self.mhd_memory)
+    }
+
     #[inline]
     fn is_empty(&self) -> bool {
         self.mhd_memory.is_empty()
@@ -175,27 +318,12 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for MhdMonteCarloSolve
 
     #[inline]
     fn is_finished(&self) -> bool {
-        // Max size should be about a gigabyte.
-        // Problem: Small problems lead to many, many solutions (max)....
-        // Alternative = 2 ^width. So, where is the threshold?
-        // width      width in bytes     max_solutions in 1 GB  2^width
-        // 256        32                 32 Mega = 2^25   <     2^256
-        // 64         8                 128 Mega = 2^27   <     2^64
-        // 32         4                 256 Mega = 2^28   <     2^32
-        // 28         4 (!)             256 Mega = 2^28   =     2^28
-        // 24         3                 341 Mega = 2^28ish >  2^24
-        // 16         2                 512 Mega = 2^29    >  2^16
-        let max_solutions: usize = if self.mhd_memory.width <= 28 {
-            1 << self.mhd_memory.width() // 2 ^ width
-        } else {
-            // if 28 < self.mhd_memory.width
-            const MAX_MEMORY: usize = 1 << 30;
-            let width_in_bytes = (self.mhd_memory.width() + 7) / 8;
-            MAX_MEMORY / width_in_bytes
-        };
-        // now, return true, finished, exhausted when...
-        // ( self.mhd_memory.width() <= self.repitition_count ) || ...
-        max_solutions < self.number_of_solutions()
+        // Finished when every possible solution has been seen (small problems),
+        // or when the memory would grow beyond a gigabyte (see MemoryUsage).
+        const MAX_MEMORY_BYTES: usize = 1 << 30;
+        let all_seen = self.mhd_memory.width <= 28
+            && (1 << self.mhd_memory.width()) < self.number_of_solutions(); // 2 ^ width
+        all_seen || MAX_MEMORY_BYTES < self.memory_usage().total_bytes()
     }
 
     #[inline]
@@ -204,7 +332,13 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for MhdMonteCarloSolve
         self.mhd_memory.clear();
         self.bootstrap_memory();
         self.best_solution = Sol::new(width);
-        // Leave full_monte as it is (?!?)
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
+        self.stagnation = 0;
+        self.num_restarts = 0;
+        self.num_local_improvements = 0;
+        // Leave full_monte (and restart_after, local_search) as it is (?!?)
     }
 
     #[inline]
@@ -216,6 +350,14 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for MhdMonteCarloSolve
     }
 
     fn pop(&mut self) -> Option<Sol> {
+        if let Some(limit) = self.restart_after {
+            if limit << self.num_restarts.min(32) <= self.stagnation {
+                // Stagnation! Restart, but keep the previous best as the incumbent of the
+                // new search, so it is not lost (and not reported as a new best, either).
+                let previous_best = self.restart_keep_memory();
+                self.best_solution = previous_best;
+            };
+        };
         match self.find_new_solution(&self.problem.starting_solution()) {
             None => {
                 debug!("MHD MCTS POP Returns NONE!!");
@@ -224,6 +366,11 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for MhdMonteCarloSolve
             Some(solution) => {
                 assert!(self.problem.solution_is_complete(&solution));
                 // assert_ne!(solution, self.best_solution); Unlikely but not illegal
+                if self.best_score() < solution.get_score() {
+                    self.stagnation = 0;
+                } else {
+                    self.stagnation += 1;
+                };
                 debug!(
                     "MHD MCTS POP: Returns solution with score {}",
                     solution.get_score()
@@ -238,6 +385,18 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for MhdMonteCarloSolve
         &self.best_solution
     }
 
+    #[inline]
+    fn elite_archive(&mut self) -> Option<&mut EliteArchive<Sol>> {
+        self.elites.as_mut()
+    }
+
+    /// Only the memory's own random numbers (random samples, random eviction) are seeded;
+    /// its reads, and the problem's random solutions, still use `rand::thread_rng`
+    #[inline]
+    fn reseed(&mut self, seed: u64) {
+        self.mhd_memory.reseed(seed);
+    }
+
     #[inline]
     fn store_best_solution(&mut self, solution: Sol) {
         // we'd like to check for completion, but can't use proble.solution_is_complete( s )
@@ -248,6 +407,15 @@ impl<Sol: Solution, Prob: Problem<Sol = Sol>> Solver<Sol> for MhdMonteCarloSolve
     } //end store_best_solution
 } // end imp Solver for MhdMonteCarloSolver
 
+impl<Sol: Solution, Prob: Problem<Sol = Sol>> SolverBuilder<Prob>
+    for MhdMonteCarloSolver<Sol, Prob>
+{
+    #[inline]
+    fn from_problem(problem: &Prob) -> Self {
+        Self::builder(problem)
+    }
+}
+
 /**************************************************************************************/
 //////////////// TESTs for ProblemSubsetSum with  MonteCarloTreeSolver /////////////////
 #[cfg(test)]
@@ -255,7 +423,7 @@ mod more_tests {
 
     use super::*;
     use implementations::*;
-    use optimizer::{MinimalSolution, Problem, Solution, Solver};
+    use optimizer::{MinimalSolution, Problem, Solution, Solver, StepEvent};
 
     #[test]
     fn test_mc_mhd_solver() {
@@ -307,6 +475,77 @@ mod more_tests {
         };
     }
 
+    #[test]
+    fn test_with_kernel() {
+        const NUM_DECISIONS: usize = 32;
+        let knapsack = ProblemSubsetSum::random(NUM_DECISIONS);
+        let kernel = DistanceKernel {
+            shape: KernelShape::Power,
+            exponent: 2.0,
+            threshold: Some(4),
+            ..Default::default()
+        };
+        let mut solver = MhdMonteCarloSolver::builder(&knapsack).with_kernel(kernel);
+        let the_best = solver
+            .find_best_solution(&knapsack, std::time::Duration::from_millis(100))
+            .expect("could not find best solution");
+        assert!(knapsack.solution_is_legal(&the_best));
+        // The kernel survives clearing the solver
+        solver.clear();
+        assert_eq!(solver.mhd_memory.kernel, kernel);
+    }
+
+    #[test]
+    fn test_with_policy() {
+        const NUM_DECISIONS: usize = 24;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let policies: Vec<Arc<dyn mhd_memory::DecisionPolicy>> = vec![
+            Arc::new(EpsilonGreedy { epsilon: 0.1 }),
+            Arc::new(Softmax { temperature: 0.1 }),
+            Arc::new(ThompsonSampling),
+        ];
+        for policy in policies {
+            let mut solver = MhdMonteCarloSolver::builder(&knapsack);
+            solver.policy = Some(policy.clone());
+            let the_best = solver
+                .find_best_solution(&knapsack, std::time::Duration::from_millis(50))
+                .expect("could not find best solution");
+            assert!(knapsack.solution_is_legal(&the_best), "{}", policy.name());
+            assert!(knapsack.solution_is_complete(&the_best), "{}", policy.name());
+        }
+        let solver = MhdMonteCarloSolver::builder(&knapsack).with_policy(ThompsonSampling);
+        assert_eq!(solver.policy.expect("a policy").name(), "ThompsonSampling");
+    }
+
+    #[test]
+    fn test_with_local_search() {
+        const NUM_DECISIONS: usize = 40;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let mut solver = MhdMonteCarloSolver::builder(&knapsack).with_local_search(2);
+        assert_eq!(solver.local_search, Some(2));
+
+        // A local optimum: no neighbor is better
+        let start = knapsack.random_solution();
+        let optimum = solver.climb(&start, 1);
+        assert!(start.get_score() <= optimum.get_score());
+        assert!(knapsack.solution_is_legal(&optimum));
+        assert!(knapsack
+            .neighbors(&optimum, 1)
+            .all(|neighbor| neighbor.get_score() <= optimum.get_score()));
+
+        let num_samples = solver.number_of_solutions();
+        let the_best = solver
+            .find_best_solution(&knapsack, std::time::Duration::from_millis(100))
+            .expect("could not find best solution");
+        assert!(knapsack.solution_is_legal(&the_best));
+        assert!(knapsack.solution_is_complete(&the_best));
+        assert!(num_samples < solver.number_of_solutions());
+        assert!(0 < solver.num_local_improvements);
+        solver.clear();
+        assert_eq!(solver.num_local_improvements, 0);
+        assert_eq!(solver.local_search, Some(2));
+    }
+
     #[test]
     fn test_mcts_find_solution() {
         const FEW_DECISIONS: usize = 8; // so we can be sure to find THE optimum!
@@ -360,6 +599,64 @@ mod more_tests {
         );
     }
 
+    #[test]
+    fn test_builder_with_few_legal_solutions() {
+        // At most one item fits, and only two of them ever do: just three legal solutions.
+        // (Eight items, so the samples have no random padding bits to tell them apart.)
+        let mut knapsack = Problem01Knapsack::random(8);
+        knapsack.basis.weights = [10, 10, 100, 100, 100, 100, 100, 100]
+            .iter()
+            .map(|&x| x as ScoreType)
+            .collect();
+        knapsack.basis.capacity = 15 as ScoreType;
+        let solver =
+            MhdMonteCarloSolver::<ZeroOneKnapsackSolution, Problem01Knapsack>::builder(&knapsack);
+        assert!(!solver.is_empty());
+        assert!(solver.number_of_solutions() < 4);
+    }
+
+    #[test]
+    fn test_restart_keep_memory() {
+        const SOME_DECISIONS: usize = 12;
+        let knapsack = Problem01Knapsack::random(SOME_DECISIONS);
+        let mut solver =
+            MhdMonteCarloSolver::<ZeroOneKnapsackSolution, Problem01Knapsack>::builder(&knapsack);
+        solver.restart_after = Some(4);
+
+        use std::time::Duration;
+        let the_best = solver
+            .find_best_solution(&knapsack, Duration::from_millis(200))
+            .expect("could not find best solution");
+        assert!(knapsack.solution_is_complete(&the_best));
+        assert!(0 < solver.num_restarts);
+
+        // An explicit restart keeps every sample, and hands back the incumbent
+        let num_samples = solver.number_of_solutions();
+        let num_restarts = solver.num_restarts;
+        let previous_best = solver.restart_keep_memory();
+        assert_eq!(previous_best.get_score(), the_best.get_score());
+        assert_eq!(solver.number_of_solutions(), num_samples);
+        assert_eq!(solver.num_restarts, num_restarts + 1);
+        assert!(knapsack.solution_is_complete(solver.best_solution()));
+
+        // Automatic restarts keep the incumbent: every new best really is better
+        solver.restart_after = Some(1);
+        solver.start_search(&knapsack);
+        for _ in 0..100 {
+            let best_score = solver.best_score();
+            if StepEvent::NewBest == solver.step(&knapsack).event {
+                assert!(best_score < solver.best_score());
+            } else {
+                assert_eq!(best_score, solver.best_score());
+            };
+        }
+        assert!(1 < solver.num_restarts);
+
+        // clear() is different: it starts over with a new memory
+        solver.clear();
+        assert_eq!(solver.num_restarts, 0);
+    }
+
     #[test]
     fn test_mcts_solve_mutliple_knapsacks() {
         const FEW_DECISIONS: usize = 8; // so we can be sure to find THE optimum!
@@ -401,4 +698,44 @@ mod more_tests {
             second_best.get_score()
         );
     }
+
+    #[test]
+    fn test_mhd_adapt_to_changed_problem() {
+        const SOME_DECISIONS: usize = 12;
+        let mut knapsack = Problem01Knapsack::random(SOME_DECISIONS);
+        let mut solver =
+            MhdMonteCarloSolver::<ZeroOneKnapsackSolution, Problem01Knapsack>::builder(&knapsack);
+
+        use std::time::Duration;
+        let time_limit = Duration::from_millis(200);
+        let the_best = solver
+            .find_best_solution(&knapsack, time_limit)
+            .expect("could not find best solution");
+        let num_samples = solver.number_of_solutions();
+
+        // A smaller knapsack: the samples that are too heavy now are dropped
+        let capacity = ScoreType::from_f64(knapsack.capacity().to_f64() * 0.75);
+        let map = knapsack.set_capacity(capacity);
+        let warm_start = solver.adapt_to(&knapsack, &map);
+        assert!(!solver.is_empty());
+        assert!(solver.number_of_solutions() <= num_samples);
+        assert_eq!(
+            warm_start.is_some(),
+            knapsack.basis.solution_score(&the_best.basis) <= capacity
+        );
+
+        // A new item: every sample is kept (without the new item)
+        let num_samples = solver.number_of_solutions();
+        let map = knapsack.add_item(1 as ScoreType, 1000 as ScoreType);
+        solver.adapt_to(&knapsack, &map);
+        assert_eq!(solver.number_of_solutions(), num_samples);
+        assert_eq!(solver.mhd_memory.width(), SOME_DECISIONS + 1);
+
+        let new_best = solver
+            .find_best_solution(&knapsack, time_limit)
+            .expect("could not re-solve");
+        assert!(knapsack.solution_is_legal(&new_best));
+        assert!(knapsack.solution_is_complete(&new_best));
+        assert_eq!(knapsack.solution_score(&new_best), new_best.get_score());
+    }
 }
@@ -35,13 +35,83 @@ use optimizer::Problem;
 //                      -- More than one problem per file
 //                      -- Optima solution supplied in third column (!) ... also time for comparison
 
-// use std::error::Error;
 use log::*;
+use optimizer::MhdError;
 use std::io;
+use std::str::FromStr;
 
-/// This parser reads one line from a "dot dat" file -- since each line is a problem --
-/// and returns one problem -- or nothing, if no problem could be read.
+/// One line of input, numbered from one (from where the parser started reading),
+/// so that a `MhdError::ParseError` can say where the problem is
+struct Line {
+    number: usize,
+    text: String,
+}
+
+impl Line {
+    /// Read the next line -- an empty input is an (unexpected) end of file
+    fn read<R: io::BufRead>(input: &mut R, number: usize) -> Result<Line, MhdError> {
+        let mut text = String::new();
+        // Note that read_line return Ok(0) on EOF
+        if 0 == input.read_line(&mut text)? {
+            return Err(MhdError::IoError(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "End of File? Empty Line",
+            )));
+        };
+        Ok(Line { number, text })
+    }
+
+    /// The blank separated tokens
+    fn tokens(&self) -> Vec<&str> {
+        self.text.split_whitespace().collect()
+    }
+
+    /// An error at `token` (which must be a slice of this line), or at its end if there's none
+    fn error(&self, token: Option<&str>, message: &str) -> MhdError {
+        let offset = match token {
+            Some(token) => token.as_ptr() as usize - self.text.as_ptr() as usize,
+            None => self.text.trim_end().len(),
+        };
+        MhdError::ParseError {
+            line: self.number,
+            col: offset + 1,
+            message: message.to_string(),
+        }
+    }
 
+    /// Exactly `count` tokens (or fields), or an error
+    fn expect_count(&self, tokens: &[&str], count: usize, message: &str) -> Result<(), MhdError> {
+        if tokens.len() == count {
+            Ok(())
+        } else {
+            Err(self.error(tokens.get(count).cloned(), message))
+        }
+    }
+
+    /// The value of a "key value" line, or an error if the line is something else
+    fn value_of(&self, key: &str) -> Result<&str, MhdError> {
+        let tokens = self.tokens();
+        let message = format!("expected {} <value> (only)", key);
+        self.expect_count(&tokens, 2, &message)?;
+        if key != tokens[0] {
+            return Err(self.error(Some(tokens[0]), &message));
+        };
+        Ok(tokens[1])
+    }
+
+    /// `token` (a slice of this line) as a number, or an error
+    fn parse<T: FromStr>(&self, token: &str, message: &str) -> Result<T, MhdError> {
+        token
+            .trim()
+            .parse()
+            .map_err(|_| self.error(Some(token), message))
+    }
+} // end impl Line
+
+/// This parser reads one line from a "dot dat" file -- since each line is a problem --
+/// and returns one problem -- or an error, if no problem could be read
+/// (`MhdError::IoError` at the end of the file, `MhdError::ParseError` for bad lines).
+//
 // The file format is as follows:
 // >    Format of instance file lines (fields)
 // >    0 = ID    [parsed but discarded!]
@@ -53,12 +123,11 @@ use std::io;
 // >    5 = weight 1
 // >    6 = cost 1
 // >    ... and so on
-pub fn parse_dot_dat_stream<R: io::BufRead>(mut input: R) -> io::Result<Problem01Knapsack> {
-    let mut line = String::new();
-    input.read_line(&mut line)?;
-    debug!("Parser read line {}", line);
+pub fn parse_dot_dat_stream<R: io::BufRead>(mut input: R) -> Result<Problem01Knapsack, MhdError> {
+    let line = Line::read(&mut input, 1)?;
+    debug!("Parser read line {}", line.text);
     // Split line into tokens
-    let tokens: Vec<String> = line.split_whitespace().map(|tok| tok.to_owned()).collect();
+    let tokens = line.tokens();
     let num_tokens = tokens.len();
     debug!(
         "Parser split line into {} tokens (empty? {})",
@@ -66,45 +135,42 @@ pub fn parse_dot_dat_stream<R: io::BufRead>(mut input: R) -> io::Result<Problem0
         tokens.is_empty()
     );
     if tokens.is_empty() {
-        // return this Err....
-        Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "End of File? Empty Line".to_string(),
-        ))
-    } else {
-        // if not empty
-        assert!(
-            (7 <= num_tokens) && (1 == num_tokens % 2),
-            "Illegal number of token"
-        );
-        let id: usize = tokens[0].parse().expect("Expect id");
-        let size: usize = tokens[1].parse().expect("Expect dimension");
-        let capacity: ScoreType = tokens[2].parse().expect("Expect capacity");
-        assert!(2 * size + 3 == num_tokens);
+        return Err(MhdError::IoError(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "End of File? Empty Line",
+        )));
+    };
+    if num_tokens < 7 || 0 == num_tokens % 2 {
+        return Err(line.error(None, "Illegal number of token"));
+    };
+    let id: usize = line.parse(tokens[0], "Expect id")?;
+    let size: usize = line.parse(tokens[1], "Expect dimension")?;
+    let capacity: ScoreType = line.parse(tokens[2], "Expect capacity")?;
+    line.expect_count(&tokens, 2 * size + 3, "Number of tokens =/= dimension")?;
+    debug!(
+        " Parsing Knapsack id {}, size {}, capacity {}:",
+        id, size, capacity
+    );
+    // id now not an unused variable, I hope...
+    let mut result = Problem01Knapsack::new(size);
+    result.basis.capacity = capacity;
+    for dim in 0..size {
+        result.basis.weights[dim] = line.parse(tokens[3 + 2 * dim], "Expect weight")?;
+        result.values[dim] = line.parse(tokens[4 + 2 * dim], "Expect cost")?;
         debug!(
-            " Parsing Knapsack id {}, size {}, capacity {}:",
-            id, size, capacity
+            " Just parsed dim {}, weight {}, cost {} ",
+            dim, result.basis.weights[dim], result.values[dim]
         );
-        // id now not an unused variable, I hope...
-        let mut result = Problem01Knapsack::new(size);
-        result.basis.capacity = capacity;
-        for dim in 0..size {
-            result.basis.weights[dim] = tokens[3 + 2 * dim].parse().expect("Expect weight");
-            result.values[dim] = tokens[4 + 2 * dim].parse().expect("Expect cost");
-            debug!(
-                " Just parsed dim {}, weight {}, cost {} ",
-                dim, result.basis.weights[dim], result.values[dim]
-            );
-        } // end loop over weight-cost pairs
-        debug!(" About to return Knapsack {:?} ", result);
-        Ok(result)
-    } // end if non-empty line
+    } // end loop over weight-cost pairs
+    debug!(" About to return Knapsack {:?} ", result);
+    Ok(result)
 }
 
 /// This parser reads one problem from a "dot csv" file -- taken to be in "Pisinger format,
 /// where each file contains 100 knapsack problems --
-/// and returns one problem -- or nothing, if no problem could be read.
-
+/// and returns one problem -- or an error, if no problem could be read
+/// (`MhdError::IoError` at the end of the file, `MhdError::ParseError` for bad lines).
+//
 // The file format is as follows:
 // >   The format of each instance is
 // >
@@ -119,100 +185,85 @@ pub fn parse_dot_dat_stream<R: io::BufRead>(mut input: R) -> io::Result<Problem0
 // >  n p[n] w[n] x[n]
 // >  -----                     <-- 5 dashes
 // >  (and then a blank line)
-
-// extern crate log;
-// use log::*;
-
-pub fn parse_dot_csv_stream<R: io::BufRead>(mut input: R) -> io::Result<Problem01Knapsack> {
+pub fn parse_dot_csv_stream<R: io::BufRead>(mut input: R) -> Result<Problem01Knapsack, MhdError> {
     // Line 1 = instance-name
-    let mut line = String::new();
-    trace!("Parser - ID line 1={}", line);
-    let mut tokens: Vec<String> = Vec::new();
     // skip blank lines until we get a non-blank line...
-    while tokens.is_empty() {
-        line.clear(); // to prevent next  read_line appending on old one...
-                      // Note that read_line return Ok(0) on EOF
-        let num_bytes_read = input.read_line(&mut line)?;
-        if 0 == num_bytes_read {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "End of File? Empty Line".to_string(),
-            ));
-        }; // end if we read zero bytes
-           // else we have a string
-        tokens = line.split_whitespace().map(|tok| tok.to_owned()).collect();
-    } // end while tokens empty
-    assert_eq!(1, tokens.len(), "Expected problem name (only)"); // Discard name if OK
+    let mut number = 0;
+    let line = loop {
+        number += 1;
+        let line = Line::read(&mut input, number)?;
+        if !line.tokens().is_empty() {
+            break line;
+        };
+    }; // end loop while tokens empty
+    trace!("Parser - ID line 1={}", line.text);
+    line.expect_count(&line.tokens(), 1, "Expected problem name (only)")?; // Discard name if OK
 
     // line 2 = n <int>
-    line.clear(); // make a new line, because...
-    input.read_line(&mut line)?; // ...this appends the new line otherwise.
-    trace!("Parser - n int line 2 ={}", line);
-    tokens = line.split_whitespace().map(|tok| tok.to_owned()).collect();
-    assert_eq!(2, tokens.len(), "expected n <int> (only)");
-    assert_eq!("n", tokens[0], "expected n <int> (did not find n)");
-    let size: usize = tokens[1].parse().expect("Expect dimension");
+    number += 1;
+    let line = Line::read(&mut input, number)?;
+    trace!("Parser - n int line 2 ={}", line.text);
+    let size: usize = line.parse(line.value_of("n")?, "Expect dimension")?;
 
     // line 3 = c <int>
-    let mut line = String::new();
-    input.read_line(&mut line)?;
-    trace!("Parser - c int line 3 ={}", line);
-    tokens = line.split_whitespace().map(|tok| tok.to_owned()).collect();
-    assert_eq!(2, tokens.len(), "expected c <int> (only)");
-    assert_eq!("c", tokens[0], "expected c <int> (did not find c)");
-    let capacity: ScoreType = tokens[1].parse().expect("Expect dimension");
+    number += 1;
+    let line = Line::read(&mut input, number)?;
+    trace!("Parser - c int line 3 ={}", line.text);
+    let capacity: ScoreType = line.parse(line.value_of("c")?, "Expect capacity")?;
 
     // line 4 = z <int>
-    let mut line = String::new();
-    input.read_line(&mut line)?;
-    trace!("Parser - z int line 4 ={}", line);
-    tokens = line.split_whitespace().map(|tok| tok.to_owned()).collect();
-    assert_eq!(2, tokens.len(), "expected c <int> (only)");
-    assert_eq!("z", tokens[0], "expected z <int> (did not find z)");
-    let goal: ScoreType = tokens[1].parse().expect("Expect Ziel (goal score)");
-    println!("\n                                        GOAL = {}", goal);
+    number += 1;
+    let line = Line::read(&mut input, number)?;
+    trace!("Parser - z int line 4 ={}", line.text);
+    let goal: ScoreType = line.parse(line.value_of("z")?, "Expect Ziel (goal score)")?;
+    debug!("Parser - goal (z) = {}", goal);
 
     // line 5 = time <int>
-    let mut line = String::new();
-    input.read_line(&mut line)?;
-    trace!("Parser - time int line 5 ={}", line);
-    tokens = line.split_whitespace().map(|tok| tok.to_owned()).collect();
-    assert_eq!(2, tokens.len(), "expected time <int> (only)");
-    assert_eq!("time", tokens[0], "expected time <int> (did not find time)");
+    number += 1;
+    let line = Line::read(&mut input, number)?;
+    trace!("Parser - time int line 5 ={}", line.text);
+    line.value_of("time")?;
+
+    // lines 6, 7, ... up to the "-----" line -- collected before we build the result,
+    // so that a bogus n in the header is an error, not a huge allocation
+    const DASHES: &str = "-----";
+    let mut values: Vec<ScoreType> = Vec::new();
+    let mut weights: Vec<ScoreType> = Vec::new();
+    let mut reference: Vec<u8> = Vec::new();
+    let line = loop {
+        number += 1;
+        let line = Line::read(&mut input, number)?;
+        if DASHES == line.text.trim_end() {
+            break line;
+        };
+        if values.len() == size {
+            return Err(line.error(Some(line.text.as_str()), "expected 5 dashes"));
+        };
+        trace!("Parser - data line ={}", line.text);
+        let fields: Vec<&str> = line.text.split(',').collect();
+        line.expect_count(
+            &fields,
+            4,
+            "expected <index>,<profit>,<weight>,<solution> (only)",
+        )?;
+
+        let index: usize = line.parse(fields[0], "Expect index")?;
+        if index != values.len() + 1 {
+            return Err(line.error(Some(fields[0]), "read index =/= dim"));
+        };
+        values.push(line.parse(fields[1], "Expect profit")?);
+        weights.push(line.parse(fields[2], "Expect weight")?);
+        reference.push(line.parse(fields[3], "Expect Solution count (0|1)")?);
+    }; // loop until the dashes
+    if values.len() != size {
+        return Err(line.error(Some(line.text.as_str()), "fewer items than n"));
+    };
 
     // We can now build the result object...
     let mut result = Problem01Knapsack::new(size);
     result.basis.capacity = capacity;
-    let mut reference = vec![255u8; size];
-
-    // lines 6, 7, ...
-    // Note: Clippy doesn't like this line. I do....
-    #[allow(clippy::needless_range_loop)]
-    for dim in 0..size {
-        let mut line = String::new();
-        input.read_line(&mut line)?;
-        trace!("Parser - data line ={}", line);
-        tokens = line.split(',').map(|tok| tok.to_owned()).collect();
-        assert_eq!(
-            4,
-            tokens.len(),
-            "expected <index>,<profit>,<weight>,<solution> (only)"
-        );
-
-        let index: usize = tokens[0].parse().expect("Expect index");
-        assert_eq!(index - 1, dim, "read index =/= dim");
-        result.values[dim] = tokens[1].parse().expect("Expect profit");
-        result.basis.weights[dim] = tokens[2].parse().expect("Expect weight");
-        tokens[3].pop(); // line.split leaves a \n on last token!?!?!
-        let dummy: u8 = tokens[3].parse().expect("Expect Solution count (0|1)");
-        reference[dim] = dummy;
-    } // for 0 <= dim < size
-
-    // Next to last line = "-----\n"
-    const DASHES: &str = "-----\n";
-    let mut line = String::new();
-    input.read_line(&mut line)?;
-    assert_eq!(line, DASHES, "expected 5 dashes");
+    result.values = values;
+    result.basis.weights = weights;
 
     // Last line should be blank, but that will be skipped above
 
@@ -220,3 +271,63 @@ pub fn parse_dot_csv_stream<R: io::BufRead>(mut input: R) -> io::Result<Problem0
     info!("Reference Solution (score {}) = {:?}", goal, reference);
     Ok(result)
 }
+
+/**************************************************************************************/
+//////////////// TESTs for Parsers /////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PISINGER: &str =
+        "\nknapPI_1_3_1000_1\nn 3\nc 10\nz 13\ntime 0.00\n1,5,4,1\n2,8,6,1\n3,3,5,0\n-----\n\n";
+
+    fn scores(values: &[u32]) -> Vec<ScoreType> {
+        values.iter().map(|&value| value as ScoreType).collect()
+    }
+
+    #[test]
+    fn test_parsers() {
+        let mut input = io::BufReader::new(PISINGER.as_bytes());
+        let knapsack = parse_dot_csv_stream(&mut input).unwrap();
+        assert_eq!(knapsack.capacity(), 10 as ScoreType);
+        assert_eq!(knapsack.values, scores(&[5, 8, 3]));
+        assert_eq!(knapsack.basis.weights, scores(&[4, 6, 5]));
+        match parse_dot_csv_stream(&mut input) {
+            Err(MhdError::IoError(error)) => assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof),
+            other => panic!("expected the end of the file, got {:?}", other),
+        };
+
+        // Where it goes wrong: line 7 (counting the blank line), the profit
+        let broken = PISINGER.replace("1,5,4,1", "1,five,4,1");
+        match parse_dot_csv_stream(broken.as_bytes()) {
+            Err(MhdError::ParseError { line, col, .. }) => assert_eq!((line, col), (7, 3)),
+            other => panic!("expected a parse error, got {:?}", other),
+        };
+        let broken = PISINGER.replace("c 10", "capacity 10");
+        match parse_dot_csv_stream(broken.as_bytes()) {
+            Err(MhdError::ParseError { line, col, .. }) => assert_eq!((line, col), (4, 1)),
+            other => panic!("expected a parse error, got {:?}", other),
+        };
+        // A header n that doesn't match the items: an error, not an allocation of n items
+        let broken = PISINGER.replace("n 3", "n 3000000000000");
+        match parse_dot_csv_stream(broken.as_bytes()) {
+            Err(MhdError::ParseError { line, col, .. }) => assert_eq!((line, col), (10, 1)),
+            other => panic!("expected a parse error, got {:?}", other),
+        };
+        let broken = PISINGER.replace("n 3", "n 2");
+        match parse_dot_csv_stream(broken.as_bytes()) {
+            Err(MhdError::ParseError { line, col, .. }) => assert_eq!((line, col), (9, 1)),
+            other => panic!("expected a parse error, got {:?}", other),
+        };
+
+        let mut input = "1 2 10 5 3 6 4\n2 1 7 3 3 9\n".as_bytes();
+        assert_eq!(
+            parse_dot_dat_stream(&mut input).unwrap().values,
+            scores(&[3, 4])
+        );
+        match parse_dot_dat_stream(&mut input) {
+            Err(MhdError::ParseError { line, col, .. }) => assert_eq!((line, col), (1, 12)),
+            other => panic!("expected a parse error, got {:?}", other),
+        };
+    }
+}
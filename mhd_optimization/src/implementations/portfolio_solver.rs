@@ -0,0 +1,274 @@
+use log::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use mhd_memory::{Score, ScoreType};
+
+/// # Example Implementations
+///
+/// ## Example Solver Implementation: A Portfolio, Racing
+///
+/// Which solver is best depends on the problem, and nobody knows in advance. A
+/// `PortfolioSolver` doesn't have to: it runs several kinds of solvers (see `SolverKind`)
+/// at the same time, each in a thread of its own, on a clone of its own of the problem.
+/// They share their incumbents: a solver which finds a new best solution offers it to the
+/// others, and every `share_interval` steps each solver takes over the best one offered,
+/// if it is better than its own (via `new_best_solution`, so e.g. an MHD solver learns it).
+/// As soon as an exhaustive solver (see `Solver::is_exhaustive`) finishes, its best solution
+/// is optimal, and the race is over; otherwise each solver runs within the limits of the
+/// `SolverConfig`, as usual. The best solution of them all wins.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, Solution};
+/// use mhd_optimization::implementations::{PortfolioSolver, Problem01Knapsack, SolverKind};
+/// use std::time::Duration;
+///
+/// let knapsack = Problem01Knapsack::random(16);
+/// let portfolio =
+///     PortfolioSolver::new(vec![SolverKind::DepthFirst, SolverKind::MhdMonteCarlo]);
+/// let reports = portfolio.race(&knapsack, Duration::from_secs(1)).unwrap();
+/// assert_eq!(reports.len(), 2);
+///
+/// let the_best = portfolio.find_best_solution(&knapsack, Duration::from_secs(1)).unwrap();
+/// assert!(knapsack.solution_is_legal(&the_best));
+/// assert_eq!(the_best.final_bound, the_best.get_score()); // (the DFS proved it)
+/// ```
+use implementations::{SolverJob, SolverKind};
+use optimizer::{MhdError, Problem, SearchDriver, Solution, SolveReport, Solver, SolverConfig};
+use optimizer::{StepEvent, StopReason};
+
+/// Steps a solver makes between two looks at the shared incumbent, unless told otherwise
+pub const DEFAULT_SHARE_INTERVAL: u64 = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortfolioSolver {
+    /// The solvers to race, one thread each
+    pub kinds: Vec<SolverKind>,
+    /// Steps between two looks at the shared incumbent; fewer means more sharing, more locking
+    pub share_interval: u64,
+}
+
+impl Default for PortfolioSolver {
+    /// DFS, BFS, MCTS and MHD-MC
+    fn default() -> Self {
+        Self::new(vec![
+            SolverKind::DepthFirst,
+            SolverKind::BestFirst,
+            SolverKind::MonteCarloTree,
+            SolverKind::MhdMonteCarlo,
+        ])
+    }
+}
+
+// What the racers share
+struct Race<Sol> {
+    incumbent: Mutex<Option<Sol>>,
+    proven: AtomicBool, // an exhaustive solver finished, the race is over
+    share_interval: u64,
+}
+
+impl<Sol: Clone> Race<Sol> {
+    // Offer `solution`: keep it, if it's better than the incumbent
+    fn offer<Prob: Problem<Sol = Sol>>(&self, problem: &Prob, solution: &Sol) {
+        let mut incumbent = self
+            .incumbent
+            .lock()
+            .expect("no racer panics holding the lock");
+        let better = match incumbent.as_ref() {
+            None => true,
+            Some(best) => problem.better_than(solution, best),
+        };
+        if better {
+            *incumbent = Some(solution.clone());
+        };
+    }
+
+    // The incumbent, if it's better than `solution`
+    fn better_than<Prob: Problem<Sol = Sol>>(&self, problem: &Prob, solution: &Sol) -> Option<Sol> {
+        let incumbent = self
+            .incumbent
+            .lock()
+            .expect("no racer panics holding the lock");
+        incumbent
+            .as_ref()
+            .filter(|best| problem.better_than(best, solution))
+            .cloned()
+    }
+}
+
+// One racer's job: search, sharing incumbents on the way
+struct RaceJob<'a, Prob: 'a + Problem> {
+    problem: &'a Prob,
+    config: SolverConfig,
+    race: &'a Race<Prob::Sol>,
+}
+
+impl<'a, Prob: Problem> SolverJob<Prob> for RaceJob<'a, Prob> {
+    type Output = SolveReport<Prob::Sol>;
+
+    fn run<S: Solver<Prob::Sol> + 'static>(self, mut solver: S) -> Self::Output {
+        let RaceJob {
+            problem,
+            config,
+            race,
+        } = self;
+        if let Some(seed) = config.seed {
+            solver.reseed(seed);
+        };
+        solver.start_search(problem);
+        let mut driver = SearchDriver::new(&solver, config, None);
+        while driver.stop_reason().is_none() {
+            if race.proven.load(Ordering::Relaxed) {
+                driver.stop(StopReason::Cancelled);
+                break;
+            };
+            if StepEvent::NewBest == driver.step(&mut solver, problem).event {
+                race.offer(problem, solver.best_solution());
+            } else if driver.num_steps().is_multiple_of(race.share_interval) {
+                if let Some(better) = race.better_than(problem, solver.best_solution()) {
+                    solver.new_best_solution(problem, better);
+                };
+            };
+        } // end while not stopped
+        race.offer(problem, solver.best_solution());
+        if Some(StopReason::Finished) == driver.stop_reason() && solver.is_exhaustive() {
+            race.proven.store(true, Ordering::Relaxed);
+        };
+        let report = driver.report(&solver, problem);
+        debug!("{} in the race: {}", solver.name(), report);
+        report
+    }
+}
+
+impl PortfolioSolver {
+    /// A portfolio of these `kinds` of solvers
+    pub fn new(kinds: Vec<SolverKind>) -> Self {
+        Self {
+            kinds,
+            share_interval: DEFAULT_SHARE_INTERVAL,
+        }
+    }
+
+    /// Race all the solvers on `problem` (each within the limits of `config`, with a seed of
+    /// its own, see `SolverConfig::for_run`), and report how each one did, in the order of
+    /// `kinds`.
+    pub fn race<Prob: Problem + Send + 'static, C: Into<SolverConfig>>(
+        &self,
+        problem: &Prob,
+        config: C,
+    ) -> Result<Vec<SolveReport<Prob::Sol>>, MhdError> {
+        let config = config.into();
+        let race = Race {
+            incumbent: Mutex::new(None),
+            proven: AtomicBool::new(false),
+            share_interval: self.share_interval.max(1),
+        };
+        let race = &race;
+        thread::scope(|scope| {
+            let racers: Vec<_> = self
+                .kinds
+                .iter()
+                .enumerate()
+                .map(|(index, kind)| {
+                    let problem = problem.clone();
+                    let config = config.for_run(index, 1);
+                    scope.spawn(move || {
+                        let job = RaceJob {
+                            problem: &problem,
+                            config,
+                            race,
+                        };
+                        kind.run(&problem, job)
+                    })
+                })
+                .collect();
+            racers
+                .into_iter()
+                .zip(self.kinds.iter())
+                .map(|(racer, kind)| {
+                    racer
+                        .join()
+                        .map_err(|_| MhdError::Panicked(format!("{} in the race", kind)))
+                })
+                .collect()
+        })
+    }
+
+    /// Race all the solvers on `problem` (see `race`), and report the winner: the best
+    /// solution of them all -- with the tightest bound any of them found.
+    pub fn find_best_solution<Prob: Problem + Send + 'static, C: Into<SolverConfig>>(
+        &self,
+        problem: &Prob,
+        config: C,
+    ) -> Result<SolveReport<Prob::Sol>, MhdError> {
+        let reports = self.race(problem, config)?;
+        let tightest_bound = ScoreType::min_of(reports.iter().map(|report| report.final_bound));
+        let mut winner: Option<(SolverKind, SolveReport<Prob::Sol>)> = None;
+        for (kind, report) in self.kinds.iter().zip(reports) {
+            let better = match winner.as_ref() {
+                None => true,
+                Some((_, best)) => problem.better_than(&report.best, &best.best),
+            };
+            if better {
+                winner = Some((*kind, report));
+            };
+        }
+        let (kind, mut report) = winner.ok_or_else(|| {
+            MhdError::InvalidConfig("an empty portfolio can't find anything".to_string())
+        })?;
+        let best_score = report.get_score();
+        report.final_bound = ScoreType::max_of(tightest_bound.into_iter().chain(Some(best_score)))
+            .unwrap_or(best_score);
+        info!("Portfolio: {} wins, {}", kind, report);
+        Ok(report)
+    }
+} // end impl PortfolioSolver
+
+/**************************************************************************************/
+//////////////// TESTs for PortfolioSolver /////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{ExhaustiveSolver, Problem01Knapsack};
+    use optimizer::Solution;
+    use std::time::Duration;
+
+    #[test]
+    fn test_portfolio() {
+        const NUM_DECISIONS: usize = 14;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let optimum = ExhaustiveSolver::builder(&knapsack).solve().get_score();
+
+        // The default portfolio: the exhaustive solvers find the optimum, and prove it
+        let portfolio = PortfolioSolver::default();
+        let reports = portfolio.race(&knapsack, Duration::from_secs(10)).unwrap();
+        assert_eq!(reports.len(), portfolio.kinds.len());
+        for report in reports.iter() {
+            assert!(knapsack.solution_is_legal(&report.best));
+            assert!(report.get_score() <= optimum);
+        }
+        let the_best = portfolio
+            .find_best_solution(&knapsack, Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(the_best.get_score(), optimum);
+        assert_eq!(the_best.final_bound, optimum);
+
+        // Heuristics only: nobody proves anything, but they all share
+        let mut portfolio =
+            PortfolioSolver::new(vec![SolverKind::MhdMonteCarlo, SolverKind::Evolutionary]);
+        portfolio.share_interval = 1;
+        let the_best = portfolio
+            .find_best_solution(&knapsack, Duration::from_millis(100))
+            .unwrap();
+        assert!(knapsack.solution_is_legal(&the_best));
+        assert!(the_best.get_score() <= the_best.final_bound);
+
+        match PortfolioSolver::new(Vec::new())
+            .find_best_solution(&knapsack, Duration::from_millis(10))
+        {
+            Err(MhdError::InvalidConfig(_)) => {}
+            other => panic!("expected an invalid configuration, got {:?}", other),
+        };
+    }
+}
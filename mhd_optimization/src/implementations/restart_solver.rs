@@ -0,0 +1,485 @@
+use log::*;
+use std::time::{Duration, Instant};
+
+use mhd_memory::{Score, ScoreType};
+
+/// # Example Implementations
+///
+/// ## Example Solver Implementation: Multiple Restarts
+///
+/// The randomized solvers' results vary a lot from run to run. The `RestartSolver` wraps
+/// any other solver (the "inner" solver) and amortizes that: `find_best_solution` splits the
+/// time limit into `num_restarts` independent runs of the inner solver, and returns the best
+/// of them all. Each run starts from its own random incumbent -- or, if the `RestartSolver`
+/// keeps an elite archive, from the next of its elites (collected from the earlier runs),
+/// so that later runs start from good but diverse places.
+///
+/// Alternatively, a `RestartSchedule` (`Luby` or `Geometric`) limits each run to a number of
+/// steps instead: short runs first, and the occasional longer one, for as long as the limits
+/// of the `SolverConfig` allow (and the best solution keeps improving, see its `time_limit`).
+/// Each such run starts from the best solution so far (the incumbent), so stochastic solvers
+/// which stagnate get a fresh start without losing what they found. With `keep_state`,
+/// the inner solver isn't even cleared between runs (e.g. an MHD solver keeps its memory).
+///
+/// Otherwise (e.g. when driven by `step`), the inner solver just does its job.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{EliteArchive, Problem, Solution, Solver};
+/// use mhd_optimization::implementations::{MhdMonteCarloSolver, Problem01Knapsack};
+/// use mhd_optimization::implementations::{RestartSchedule, RestartSolver};
+/// use std::time::Duration;
+///
+/// let knapsack = Problem01Knapsack::random(32);
+/// let mut solver = RestartSolver::wrap(MhdMonteCarloSolver::builder(&knapsack), 4);
+/// solver.elites = Some(EliteArchive::new(4, 2));
+///
+/// let best = solver.find_best_solution(&knapsack, Duration::from_millis(200))
+///                  .expect("could not find best solution");
+/// assert!(knapsack.solution_is_legal(&best));
+/// assert_eq!(solver.run_scores.len(), 4);
+/// assert!(solver.run_scores.iter().all(|score| *score <= best.get_score()));
+///
+/// // Restarts on a Luby schedule, keeping the memory
+/// solver.schedule = RestartSchedule::Luby { unit: 16 };
+/// solver.keep_state = true;
+/// let best = solver.find_best_solution(&knapsack, Duration::from_millis(200)).unwrap();
+/// assert!(knapsack.solution_is_legal(&best));
+/// assert!(1 < solver.run_scores.len());
+/// ```
+use optimizer::{
+    EliteArchive, FrontierEntry, MemoryUsage, MhdError, Problem, Solution, SolveReport, Solver,
+    SolverBuilder, SolverConfig, StopReason, VisitedStats,
+};
+
+pub const DEFAULT_NUM_RESTARTS: usize = 4;
+
+/// The n-th (from zero) number of the Luby sequence: 1, 1, 2, 1, 1, 2, 4, 1, 1, 2, ...
+/// (Luby, Sinclair & Zuckerman's universal restart strategy for Las Vegas algorithms)
+pub fn luby(n: usize) -> u64 {
+    let mut index = n as u64 + 1; // the sequence is defined from one
+    loop {
+        // the smallest k with index <= 2^k - 1
+        let k = 64 - index.leading_zeros();
+        if index == (1 << k) - 1 {
+            return 1 << (k - 1);
+        };
+        index -= (1 << (k - 1)) - 1;
+    }
+}
+
+/// How the `RestartSolver` limits its runs
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RestartSchedule {
+    /// `num_restarts` independent runs, each with an equal part of the limits
+    #[default]
+    EqualParts,
+    /// Run `n` (from zero) makes at most `unit * luby(n)` steps
+    Luby { unit: u64 },
+    /// Run `n` (from zero) makes at most `first * factor^n` steps
+    Geometric { first: u64, factor: f64 },
+}
+
+impl RestartSchedule {
+    /// The number of steps run `run` may make (None: no limit of its own)
+    pub fn run_length(&self, run: usize) -> Option<u64> {
+        match *self {
+            RestartSchedule::EqualParts => None,
+            RestartSchedule::Luby { unit } => Some(unit.saturating_mul(luby(run)).max(1)),
+            RestartSchedule::Geometric { first, factor } => {
+                // (`as` saturates)
+                Some(((first as f64) * factor.powi(run.min(i32::MAX as usize) as i32)) as u64)
+                    .map(|length| length.max(1))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RestartSolver<Sol: Solution, Inner: Solver<Sol>> {
+    pub inner: Inner,
+    /// Number of independent runs of the inner solver per `find_best_solution`
+    /// (with `RestartSchedule::EqualParts`; the other schedules run as long as the limits allow)
+    pub num_restarts: usize,
+    pub schedule: RestartSchedule,
+    /// Don't clear the inner solver between runs (e.g. to keep an MHD solver's memory)
+    pub keep_state: bool,
+    /// The best score of each run of the last `find_best_solution`
+    pub run_scores: Vec<ScoreType>,
+    pub best_solution: Sol,
+    /// Optional archive of good and diverse solutions (of all runs), see `EliteArchive`.
+    /// If there is one, runs after the first start from its elites.
+    pub elites: Option<EliteArchive<Sol>>,
+}
+
+impl<Sol: Solution, Inner: Solver<Sol>> RestartSolver<Sol, Inner> {
+    /// Restart `inner` `num_restarts` times (at least once) per search
+    pub fn wrap(inner: Inner, num_restarts: usize) -> Self {
+        Self {
+            best_solution: Sol::new(inner.width()),
+            inner,
+            num_restarts: num_restarts.max(1),
+            schedule: RestartSchedule::default(),
+            keep_state: false,
+            run_scores: Vec::new(),
+            elites: None,
+        }
+    }
+
+    // The incumbent to start run number `run` from (if not a random one)
+    fn seed_of_run(&self, run: usize) -> Option<Sol> {
+        match &self.elites {
+            Some(archive) if 0 < run && !archive.is_empty() => {
+                Some(archive.elites()[(run - 1) % archive.len()].clone())
+            }
+            _ if 0 < run && RestartSchedule::EqualParts != self.schedule => {
+                Some(self.best_solution.clone())
+            }
+            _ => None,
+        }
+    }
+
+    // The limits of run number `run`, which starts after `steps` steps and `elapsed` time
+    fn config_of_run(
+        &self,
+        config: &SolverConfig,
+        run: usize,
+        steps: u64,
+        elapsed: Duration,
+    ) -> SolverConfig {
+        match self.schedule.run_length(run) {
+            None => config.for_run(run, self.num_restarts),
+            Some(length) => {
+                let mut run_config = config.for_run(run, 1);
+                let steps_left = config.node_limit.map(|limit| limit.saturating_sub(steps));
+                run_config.node_limit = Some(steps_left.map_or(length, |left| left.min(length)));
+                run_config.total_time_limit = config
+                    .total_time_limit
+                    .map(|limit| limit.saturating_sub(elapsed));
+                run_config
+            }
+        }
+    }
+} // end private Methods
+
+impl<Sol: Solution, Inner: Solver<Sol>> Solver<Sol> for RestartSolver<Sol, Inner> {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "RestartSolver"
+    }
+
+    #[inline]
+    fn short_description(&self) -> String {
+        format!(
+            "{} after {} of {} runs, best score is {}, inner solver: {}",
+            self.name(),
+            self.run_scores.len(),
+            self.num_restarts,
+            self.best_score(),
+            self.inner.short_description()
+        )
+    }
+
+    // Methods used by the Unified Optimization Algorithm (identified above)
+
+    #[inline]
+    fn number_of_solutions(&self) -> usize {
+        self.inner.number_of_solutions()
+    }
+
+    #[inline]
+    fn memory_usage(&self) -> MemoryUsage {
+        self.inner.memory_usage()
+    }
+
+    #[inline]
+    fn snapshot_frontier(&self, limit: usize) -> Vec<FrontierEntry> {
+        self.inner.snapshot_frontier(limit)
+    }
+
+    #[inline]
+    fn is_exhaustive(&self) -> bool {
+        self.inner.is_exhaustive()
+    }
+
+    #[inline]
+    fn dropped_bound(&self) -> Option<ScoreType> {
+        self.inner.dropped_bound()
+    }
+
+    #[inline]
+    fn visited_stats(&self) -> Option<VisitedStats> {
+        self.inner.visited_stats()
+    }
+
+    #[inline]
+    fn is_finished(&self) -> bool {
+        self.inner.is_finished()
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.inner.clear();
+        self.run_scores.clear();
+        let size = self.best_solution.size();
+        self.best_solution = Sol::new(size);
+        if let Some(elites) = self.elites.as_mut() {
+            elites.clear();
+        };
+    }
+
+    #[inline]
+    fn push(&mut self, solution: Sol) {
+        self.inner.push(solution);
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<Sol> {
+        self.inner.pop()
+    }
+
+    #[inline]
+    fn best_solution(&self) -> &Sol {
+        &self.best_solution
+    }
+
+    #[inline]
+    fn elite_archive(&mut self) -> Option<&mut EliteArchive<Sol>> {
+        self.elites.as_mut()
+    }
+
+    #[inline]
+    fn store_best_solution(&mut self, solution: Sol) {
+        // we'd like to check for completion, but can't use proble.solution_is_complete( s )
+        debug_assert_eq!(solution.get_score(), solution.get_best_score());
+        self.best_solution = solution;
+    }
+
+    #[inline]
+    fn reseed(&mut self, seed: u64) {
+        self.inner.reseed(seed);
+    }
+
+    /// `num_restarts` runs of the inner solver, each with (an equal) part of the limits
+    /// of `config` (and a seed of its own, see `SolverConfig::for_run`),
+    /// and the best of them all -- or as many runs as the `schedule` and `config` allow.
+    /// The report adds up the runs' reports: the improvements are those of the best of them
+    /// all, and the final bound is the tightest any run found.
+    fn find_best_solution<Prob: Problem<Sol = Sol>, C: Into<SolverConfig>>(
+        &mut self,
+        problem: &Prob,
+        config: C,
+    ) -> Result<SolveReport<Sol>, MhdError> {
+        let config = config.into();
+        let start_time = Instant::now();
+        self.store_best_solution(problem.random_solution());
+        self.run_scores.clear();
+        let mut reports: Vec<SolveReport<Sol>> = Vec::with_capacity(self.num_restarts);
+        let mut improvements = 0;
+        let mut time_to_best = start_time.elapsed();
+        let mut last_best_time = start_time;
+        let mut steps = 0;
+        let mut stop_reason = StopReason::Finished;
+        for run in 0.. {
+            if RestartSchedule::EqualParts == self.schedule && self.num_restarts <= run {
+                break;
+            };
+            if 0 == run || !self.keep_state {
+                self.inner.clear();
+            };
+            let run_config = self.config_of_run(&config, run, steps, start_time.elapsed());
+            let run_best = match self.seed_of_run(run) {
+                None => self.inner.find_best_solution(problem, run_config)?,
+                Some(seed) => self
+                    .inner
+                    .find_best_solution_from(problem, seed, run_config)?,
+            };
+            debug!(
+                "{}: run {} finds best score {} ({})",
+                self.name(),
+                run + 1,
+                run_best.get_score(),
+                run_best.stop_reason
+            );
+            // Collect the run's elites, too (if both keep an archive)
+            let inner_elites: Vec<Sol> = match self.inner.elite_archive() {
+                None => Vec::new(),
+                Some(archive) => archive.elites().to_vec(),
+            };
+            if let Some(archive) = self.elite_archive() {
+                for elite in inner_elites.iter() {
+                    archive.offer(elite);
+                }
+            };
+            self.run_scores.push(run_best.get_score());
+            if self.new_best_solution(problem, run_best.best.clone()) {
+                improvements += 1;
+                // (the run started its elapsed time ago)
+                time_to_best = start_time.elapsed() - run_best.elapsed + run_best.time_to_best;
+                last_best_time = Instant::now();
+            };
+            steps += run_best.nodes_visited;
+            stop_reason = run_best.stop_reason;
+            let proved = StopReason::Finished == stop_reason && self.inner.is_exhaustive();
+            reports.push(run_best);
+            if RestartSchedule::EqualParts != self.schedule {
+                // Go on (with the next run) until the limits of the whole search are reached
+                if proved {
+                    break;
+                };
+                match config.limit_reached(self, start_time, last_best_time, steps, 0) {
+                    Some(reason) => {
+                        stop_reason = reason;
+                        break;
+                    }
+                    None if StopReason::MemoryLimit == stop_reason => break,
+                    None => {}
+                };
+            };
+        } // end for all runs
+        info!(
+            "{} finds best score {} in {} runs (scores {:?})",
+            self.name(),
+            self.best_score(),
+            self.run_scores.len(),
+            self.run_scores
+        );
+        let best_score = self.best_score();
+        let tightest_bound = ScoreType::min_of(reports.iter().map(|report| report.final_bound));
+        Ok(SolveReport {
+            best: self.best_solution.clone(),
+            nodes_visited: reports.iter().map(|report| report.nodes_visited).sum(),
+            max_depth: reports
+                .iter()
+                .map(|report| report.max_depth)
+                .max()
+                .unwrap_or(0),
+            time_to_best,
+            improvements,
+            elapsed: start_time.elapsed(),
+            stop_reason,
+            final_bound: ScoreType::max_of(tightest_bound.into_iter().chain(Some(best_score)))
+                .unwrap_or(best_score),
+            visited: reports
+                .iter()
+                .filter_map(|report| report.visited)
+                .reduce(|all, run| all + run),
+        })
+    }
+} // end impl Solver for RestartSolver
+
+impl<Sol, Prob, Inner> SolverBuilder<Prob> for RestartSolver<Sol, Inner>
+where
+    Sol: Solution,
+    Prob: Problem<Sol = Sol>,
+    Inner: SolverBuilder<Prob>,
+{
+    /// `DEFAULT_NUM_RESTARTS` runs of the inner solver
+    #[inline]
+    fn from_problem(problem: &Prob) -> Self {
+        Self::wrap(Inner::from_problem(problem), DEFAULT_NUM_RESTARTS)
+    }
+}
+
+/**************************************************************************************/
+//////////////// TESTs for RestartSolver /////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{
+        DepthFirstSolver, EvolutionarySolver, ExhaustiveSolver, Problem01Knapsack,
+    };
+    use optimizer::MinimalSolution;
+    use std::time::Duration;
+
+    #[test]
+    fn test_restarts() {
+        const FEW_DECISIONS: usize = 12;
+        let knapsack = Problem01Knapsack::random(FEW_DECISIONS);
+        let optimum = ExhaustiveSolver::builder(&knapsack).solve().get_score();
+
+        // Every run of an exact solver finds the optimum
+        let mut solver = RestartSolver::<_, DepthFirstSolver<_>>::from_problem(&knapsack);
+        assert_eq!(solver.num_restarts, DEFAULT_NUM_RESTARTS);
+        let best = solver
+            .find_best_solution(&knapsack, Duration::from_secs(1))
+            .expect("could not find best solution");
+        assert_eq!(best.get_score(), optimum);
+        assert_eq!(solver.run_scores, vec![optimum; DEFAULT_NUM_RESTARTS]);
+
+        // A randomized one, seeded from the elites after the first run
+        let mut solver = RestartSolver::wrap(EvolutionarySolver::builder(&knapsack), 3);
+        solver.elites = Some(EliteArchive::new(3, 1));
+        assert!(solver.seed_of_run(1).is_none());
+        let best = solver
+            .find_best_solution(&knapsack, Duration::from_millis(300))
+            .expect("could not find best solution");
+        assert!(knapsack.solution_is_legal(&best));
+        assert!(best.get_score() <= optimum);
+        assert_eq!(solver.run_scores.len(), 3);
+        assert!(solver
+            .run_scores
+            .iter()
+            .all(|score| *score <= best.get_score()));
+        assert!(!solver.elites.as_ref().unwrap().is_empty());
+        assert!(solver.seed_of_run(0).is_none());
+        assert!(solver.seed_of_run(1).is_some());
+
+        solver.clear();
+        assert!(solver.run_scores.is_empty());
+        assert!(solver.elites.as_ref().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_restart_schedules() {
+        let sequence: Vec<u64> = (0..15).map(luby).collect();
+        assert_eq!(sequence, vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+        let schedule = RestartSchedule::Luby { unit: 10 };
+        assert_eq!(schedule.run_length(6), Some(40));
+        let schedule = RestartSchedule::Geometric {
+            first: 10,
+            factor: 1.5,
+        };
+        let lengths: Vec<_> = (0..4).filter_map(|run| schedule.run_length(run)).collect();
+        assert_eq!(lengths, vec![10, 15, 22, 33]);
+        assert_eq!(RestartSchedule::EqualParts.run_length(3), None);
+
+        const NUM_DECISIONS: usize = 40;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+
+        // Many short runs, each starting from the incumbent, until the node limit
+        let mut solver = RestartSolver::wrap(EvolutionarySolver::builder(&knapsack), 1);
+        solver.schedule = RestartSchedule::Luby { unit: 8 };
+        let config = SolverConfig {
+            node_limit: Some(200),
+            ..Duration::from_secs(5).into()
+        };
+        let report = solver.find_best_solution(&knapsack, config).unwrap();
+        assert!(knapsack.solution_is_legal(&report));
+        assert_eq!(report.stop_reason, StopReason::NodeLimit);
+        assert_eq!(report.nodes_visited, 200);
+        assert!(10 < solver.run_scores.len());
+        // (each run starts from the incumbent, so it never gets worse)
+        assert!(solver.run_scores.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        // An exhaustive solver stops restarting as soon as it has finished
+        let mut solver = RestartSolver::wrap(DepthFirstSolver::new(12), 1);
+        solver.schedule = RestartSchedule::Geometric {
+            first: 4,
+            factor: 2.0,
+        };
+        let knapsack = Problem01Knapsack::random(12);
+        let optimum = ExhaustiveSolver::builder(&knapsack).solve().get_score();
+        let report = solver
+            .find_best_solution(&knapsack, Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(report.get_score(), optimum);
+        assert_eq!(report.stop_reason, StopReason::Finished);
+    }
+
+    #[test]
+    fn test_restart_at_least_once() {
+        let solver = RestartSolver::wrap(DepthFirstSolver::<MinimalSolution>::new(8), 0);
+        assert_eq!(solver.num_restarts, 1);
+        assert_eq!(solver.width(), 8);
+    }
+}
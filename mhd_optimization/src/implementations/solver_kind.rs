@@ -0,0 +1,211 @@
+/// # Example Implementations
+///
+/// ## Solver Kinds -- choosing a solver at run time
+///
+/// The `Solver` trait is generic (and not object safe), so we cannot just hand out a
+/// `Box<dyn Solver>`. Instead, a `SolverKind` names one of the solvers in this module,
+/// and builds and runs it for a given problem -- so command line tools, services, etc.
+/// can pick their solver from a string. (To keep the solver, see `dyn_solver`.)
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, Solution};
+/// use mhd_optimization::implementations::{ProblemSubsetSum, SolverKind};
+/// use std::time::Duration;
+///
+/// let problem = ProblemSubsetSum::random(8);
+/// let kind: SolverKind = "bfs".parse().expect("known solver");
+/// assert_eq!(kind, SolverKind::BestFirst);
+///
+/// let best = kind.find_best_solution(&problem, Duration::from_secs(1))
+///                .expect("could not find best solution");
+/// assert!(problem.solution_is_complete(&best));
+/// assert_eq!(best.get_score(), problem.capacity);
+/// ```
+use std::fmt;
+use std::str::FromStr;
+
+use implementations::{BestFirstSolver, DepthFirstSolver};
+use implementations::{EvolutionarySolver, GeneticSolver, HyperHeuristicSolver};
+use implementations::{BestfirstMhdMonteCarloSolver, MhdMonteCarloSolver, MonteCarloTreeSolver};
+use optimizer::{DynSolver, MhdError, Problem, SolveReport, Solver, SolverBuilder};
+use optimizer::SolverConfig;
+
+/// What to do with a solver of whatever kind, see `SolverKind::run`
+/// (a closure can't be generic over the solver's type, a trait method can).
+pub trait SolverJob<Prob: Problem> {
+    type Output;
+
+    fn run<S: Solver<Prob::Sol> + 'static>(self, solver: S) -> Self::Output;
+}
+
+// The job of `SolverKind::find_best_solution`
+struct FindBest<'a, Prob: 'a>(SolverConfig, &'a Prob);
+
+impl<'a, Prob: Problem> SolverJob<Prob> for FindBest<'a, Prob> {
+    type Output = Result<SolveReport<Prob::Sol>, MhdError>;
+
+    fn run<S: Solver<Prob::Sol> + 'static>(self, mut solver: S) -> Self::Output {
+        let FindBest(config, problem) = self;
+        solver.find_best_solution(problem, config)
+    }
+}
+
+// The job of `SolverKind::dyn_solver`
+struct Erase;
+
+impl<Prob: Problem> SolverJob<Prob> for Erase {
+    type Output = DynSolver<Prob>;
+
+    fn run<S: Solver<Prob::Sol> + 'static>(self, solver: S) -> Self::Output {
+        DynSolver::wrap(solver)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SolverKind {
+    DepthFirst,
+    BestFirst,
+    MonteCarloTree,
+    FullMonteCarloTree,
+    MhdMonteCarlo,
+    FullMhdMonteCarlo,
+    BestfirstMhdMonteCarlo,
+    Evolutionary,
+    HyperHeuristic,
+    Genetic,
+    MhdLocalSearch,
+}
+
+impl SolverKind {
+    /// Every kind of solver, in the same order as the `knapsacks` example runs them.
+    pub const ALL: [SolverKind; 11] = [
+        SolverKind::DepthFirst,
+        SolverKind::BestFirst,
+        SolverKind::MonteCarloTree,
+        SolverKind::FullMonteCarloTree,
+        SolverKind::MhdMonteCarlo,
+        SolverKind::FullMhdMonteCarlo,
+        SolverKind::BestfirstMhdMonteCarlo,
+        SolverKind::Evolutionary,
+        SolverKind::HyperHeuristic,
+        SolverKind::Genetic,
+        SolverKind::MhdLocalSearch,
+    ];
+
+    /// The short name, as accepted by `from_str` (and printed by `Display`).
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            SolverKind::DepthFirst => "dfs",
+            SolverKind::BestFirst => "bfs",
+            SolverKind::MonteCarloTree => "mcts",
+            SolverKind::FullMonteCarloTree => "full-mcts",
+            SolverKind::MhdMonteCarlo => "mhd",
+            SolverKind::FullMhdMonteCarlo => "full-mhd",
+            SolverKind::BestfirstMhdMonteCarlo => "bf-mhd",
+            SolverKind::Evolutionary => "es",
+            SolverKind::HyperHeuristic => "hh",
+            SolverKind::Genetic => "ga",
+            SolverKind::MhdLocalSearch => "mhd-ls",
+        }
+    }
+
+    /// Build a solver of this kind for `problem`, and let it find the best solution it can
+    /// (within the limits of `config`, see `SolverConfig`), and report how, see `SolveReport`.
+    pub fn find_best_solution<Prob: Problem + 'static, C: Into<SolverConfig>>(
+        &self,
+        problem: &Prob,
+        config: C,
+    ) -> Result<SolveReport<Prob::Sol>, MhdError> {
+        self.run(problem, FindBest(config.into(), problem))
+    }
+
+    /// Build a solver of this kind for `problem`, to keep (type erased, see `DynSolver`).
+    pub fn dyn_solver<Prob: Problem + 'static>(&self, problem: &Prob) -> DynSolver<Prob> {
+        self.run(problem, Erase)
+    }
+
+    /// Build a solver of this kind for `problem`, and hand it to `job` (see `SolverJob`).
+    pub fn run<Prob, J>(&self, problem: &Prob, job: J) -> J::Output
+    where
+        Prob: Problem + 'static,
+        J: SolverJob<Prob>,
+    {
+        match self {
+            SolverKind::DepthFirst => job.run(DepthFirstSolver::from_problem(problem)),
+            SolverKind::BestFirst => job.run(BestFirstSolver::from_problem(problem)),
+            SolverKind::MonteCarloTree | SolverKind::FullMonteCarloTree => {
+                let mut solver = MonteCarloTreeSolver::from_problem(problem);
+                solver.full_monte = SolverKind::FullMonteCarloTree == *self;
+                job.run(solver)
+            }
+            SolverKind::MhdMonteCarlo | SolverKind::FullMhdMonteCarlo => {
+                let mut solver = MhdMonteCarloSolver::from_problem(problem);
+                solver.full_monte = SolverKind::FullMhdMonteCarlo == *self;
+                job.run(solver)
+            }
+            SolverKind::BestfirstMhdMonteCarlo => {
+                job.run(BestfirstMhdMonteCarloSolver::from_problem(problem))
+            }
+            SolverKind::Evolutionary => job.run(EvolutionarySolver::from_problem(problem)),
+            SolverKind::HyperHeuristic => job.run(HyperHeuristicSolver::from_problem(problem)),
+            SolverKind::Genetic => job.run(GeneticSolver::from_problem(problem)),
+            SolverKind::MhdLocalSearch => {
+                job.run(MhdMonteCarloSolver::builder(problem).with_local_search(1))
+            }
+        } // end match self
+    }
+} // end impl SolverKind
+
+impl fmt::Display for SolverKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.short_name())
+    }
+}
+
+impl FromStr for SolverKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let name = name.trim().to_lowercase();
+        SolverKind::ALL
+            .iter()
+            .find(|kind| kind.short_name() == name)
+            .cloned()
+            .ok_or_else(|| {
+                let known: Vec<&str> = SolverKind::ALL.iter().map(|k| k.short_name()).collect();
+                format!("Unknown solver '{}' (known: {})", name, known.join(", "))
+            })
+    }
+}
+
+///////////////////// TESTs for SolverKind /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::Problem01Knapsack;
+    use optimizer::Solution;
+    use std::time::Duration;
+
+    #[test]
+    fn test_solver_kind_names() {
+        for kind in SolverKind::ALL.iter() {
+            assert_eq!(*kind, kind.short_name().parse().unwrap());
+            assert_eq!(*kind, kind.to_string().to_uppercase().parse().unwrap());
+        }
+        assert!("no-such-solver".parse::<SolverKind>().is_err());
+    }
+
+    #[test]
+    fn test_every_kind_solves() {
+        const NUM_DECISIONS: usize = 8;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        for kind in SolverKind::ALL.iter() {
+            let best = kind
+                .find_best_solution(&knapsack, Duration::from_millis(200))
+                .expect("could not find best solution");
+            assert!(knapsack.solution_is_legal(&best));
+            assert!(knapsack.solution_is_complete(&best));
+            assert_eq!(best.get_score(), knapsack.solution_score(&best));
+        }
+    }
+}
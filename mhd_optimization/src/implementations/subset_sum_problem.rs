@@ -17,9 +17,12 @@ use log::*;
 extern crate rand_distr;
 
 use rand_distr::{Bernoulli, Distribution, Gamma}; // formerly used: Exp
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 
-use mhd_memory::{ScoreType, ZERO_SCORE}; // Not used: NUM_BYTES
-use optimizer::{MinimalSolution, Problem, Solution};
+use mhd_memory::util::iter_open_bits;
+use mhd_memory::{Score, ScoreType, ZERO_SCORE}; // Not used: NUM_BYTES
+use optimizer::{DecisionMap, MinimalSolution, Problem, Solution};
 
 #[derive(Debug, Clone)]
 pub struct ProblemSubsetSum {
@@ -32,6 +35,33 @@ impl ProblemSubsetSum {
     pub fn weights_sum(&self) -> ScoreType {
         self.weights.iter().sum()
     }
+
+    // Changing a problem (to re-solve it). Each change returns a DecisionMap,
+    // which carries solutions & samples of the old problem over to the new one.
+
+    /// Change the capacity
+    pub fn set_capacity(&mut self, capacity: ScoreType) -> DecisionMap {
+        self.capacity = capacity;
+        DecisionMap::identity(self.problem_size())
+    }
+
+    /// Add an item where its weight belongs: the weights stay sorted, heaviest first,
+    /// as `randomize` sorts them (and greedy and bounding expect). Lighter items move down.
+    pub fn add_item(&mut self, weight: ScoreType) -> DecisionMap {
+        let index = self
+            .weights
+            .iter()
+            .position(|&other| other < weight)
+            .unwrap_or(self.problem_size());
+        self.weights.insert(index, weight);
+        DecisionMap::inserted(self.problem_size() - 1, index)
+    }
+
+    /// Remove the item `index` (later items move up one)
+    pub fn remove_item(&mut self, index: usize) -> DecisionMap {
+        self.weights.remove(index);
+        DecisionMap::removed(self.problem_size() + 1, index)
+    }
 }
 
 // Problem Trait Methods
@@ -57,7 +87,7 @@ impl Problem for ProblemSubsetSum {
     fn new(size: usize) -> Self {
         ProblemSubsetSum {
             weights: vec![ZERO_SCORE; size],
-            capacity: 0,
+            capacity: ZERO_SCORE,
         }
     }
 
@@ -79,24 +109,25 @@ impl Problem for ProblemSubsetSum {
         let mut rng = rand::thread_rng();
         // The parameters shape=2.0 and scale=1000.0 were arrived at by playing around in a
         // Jupyter Notebook but remain failry arbitrary
-        let distr = Gamma::new(2.0, 1000.0).unwrap();
+        let distr = Gamma::<f64>::new(2.0, 1000.0).unwrap();
 
+        // (whole numbers, also for float scores -- so their sums are exact)
         self.weights = (0..num_bits)
-            .map(|_| (distr.sample(&mut rng) + 1.0) as ScoreType)
+            .map(|_| (distr.sample(&mut rng) + 1.0).floor() as ScoreType)
             .collect();
 
         ///// The next two lines are optional. Experimentation still going on to see if they help.
         ////  They are not independant: The 2nd makes no sense without the first, so either none,
         ////  just the first or both. See below for experimental results.
         // Sort weights
-        self.weights.sort_unstable();
+        self.weights.sort_unstable_by(Score::cmp_scores);
         self.weights.reverse();
         debug_assert!(
             num_bits == self.problem_size(),
             "Problem size changed in sort?!?"
         );
-        debug_assert!(0 < self.weights[0]);
-        debug_assert!(0 < self.weights[num_bits - 1]);
+        debug_assert!(ZERO_SCORE < self.weights[0]);
+        debug_assert!(ZERO_SCORE < self.weights[num_bits - 1]);
         debug_assert!(self.weights[num_bits - 1] <= self.weights[0]); // Change if not reversing sort
 
         // Choose Capacity as the sum of a random selection of the weights
@@ -128,7 +159,7 @@ impl Problem for ProblemSubsetSum {
         // Note: By definition, the default knapsack is ILLEGAL since all weights are zero, etc.
         //
         // Revision: We're going to allow overly large capacity after all...
-        let legal = (0 < self.problem_size()) && (0 < self.capacity);
+        let legal = (0 < self.problem_size()) && (ZERO_SCORE < self.capacity);
         if !legal || (self.weights_sum() <= self.capacity) {
             warn!(
                 "Funky Subset Sum Proble: dim {}, weight sum {} <= capacity {}",
@@ -143,37 +174,25 @@ impl Problem for ProblemSubsetSum {
     // first, methods not defined previously, but which arose while implemeneting the others (see below)
     #[inline]
     fn solution_score(&self, solution: &Self::Sol) -> ScoreType {
-        let mut result = ZERO_SCORE;
-        // Note to self -- later we can be faster here by doing this byte-wise
-        for index in 0..self.problem_size() {
-            if let Some(decision) = solution.get_decision(index) {
-                if decision {
-                    result += self.weights[index];
-                }
-            }
-        } // end for all bits
-        result as ScoreType
+        solution
+            .true_decisions()
+            .take_while(|index| *index < self.problem_size())
+            .map(|index| self.weights[index])
+            .sum()
     } // end solution_is_legal
 
     fn solution_best_score(&self, solution: &Self::Sol) -> ScoreType {
         // add up all weights which are either open or not set to zero,
-        // stopping if we get past capacity
-        let mut result = ZERO_SCORE;
-        for index in 0..self.problem_size() {
-            match solution.get_decision(index) {
-                // open decision! So we COULD put this item in the knapsack...
-                None => result += self.weights[index],
-                Some(decision) => {
-                    if decision {
-                        result += self.weights[index]
-                    }
-                }
-            }; // end match
-            if self.capacity < result {
-                return self.capacity;
-            }; // end if over capacity
-        } // end for all bits
-          // if we're here, then upper_bound is less than capacity
+        // but no more than the capacity
+        let open: ScoreType = iter_open_bits(solution.mask())
+            .take_while(|index| *index < self.problem_size())
+            .map(|index| self.weights[index])
+            .sum();
+        let result = self.solution_score(solution) + open;
+        if self.capacity < result {
+            return self.capacity;
+        }; // end if over capacity
+           // if we're here, then upper_bound is less than capacity
         debug_assert!(result <= self.capacity);
         debug_assert!(self.solution_score(&solution) <= result);
         // next assert fails if solution is complete and best_score != score
@@ -249,29 +268,32 @@ impl Problem for ProblemSubsetSum {
         result
     }
 
+    /// The heaviest first (the weights are sorted that way, unless items were added since)
+    fn greedy_order(&self) -> Vec<usize> {
+        let mut result: Vec<usize> = (0..self.problem_size()).collect();
+        result.sort_by(|&a, &b| self.weights[b].cmp_scores(&self.weights[a]));
+        result
+    }
+
+    /// The decisions still open, and the sum so far: how the sum got there doesn't matter
+    fn transposition_key(&self, solution: &Self::Sol) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        solution.mask().hash(&mut hasher);
+        solution.get_score().to_f64().to_bits().hash(&mut hasher);
+        hasher.finish()
+    }
+
     //Use the default implementation of better_than()
     //Use the default implementation of can_be_better_than()
 
     fn first_open_decision(&self, solution: &Self::Sol) -> Option<usize> {
-        // Note to self -- later we can be faster here by doing this byte-wise
-        for index in 0..self.problem_size() {
-            if solution.get_decision(index).is_none() {
-                return Some(index);
-            };
-        } // end for all bits
-          // if we get here, return....
-        None
+        solution
+            .first_open_decision()
+            .filter(|index| *index < self.problem_size())
     }
 
     fn last_closed_decision(&self, solution: &Self::Sol) -> Option<usize> {
-        // Note to self -- later we can be faster here by doing this byte-wise
-        for index in self.problem_size()..0 {
-            if None != solution.get_decision(index) {
-                return Some(index);
-            };
-        } // end for all bits
-          // if we get here, return....
-        None
+        solution.last_closed_decision()
     }
 
     fn apply_rules(&self, sol: &mut Self::Sol) {
@@ -355,23 +377,23 @@ mod tests {
 
         assert!(!rand_sack_a.is_legal());
         assert_eq!(rand_sack_a.problem_size(), TEST_SIZE);
-        assert_eq!(rand_sack_a.weights_sum(), 0);
-        assert_eq!(rand_sack_a.capacity, 0);
+        assert_eq!(rand_sack_a.weights_sum(), 0 as ScoreType);
+        assert_eq!(rand_sack_a.capacity, 0 as ScoreType);
 
         rand_sack_a.randomize();
 
         assert!(rand_sack_a.is_legal());
         assert_eq!(rand_sack_a.problem_size(), TEST_SIZE);
 
-        assert_ne!(rand_sack_a.weights_sum(), 0);
-        assert_ne!(rand_sack_a.capacity, 0);
+        assert_ne!(rand_sack_a.weights_sum(), ZERO_SCORE);
+        assert_ne!(rand_sack_a.capacity, ZERO_SCORE);
 
         let rand_sack_b = ProblemSubsetSum::random(TEST_SIZE);
 
         assert!(rand_sack_b.is_legal());
         assert_eq!(rand_sack_b.problem_size(), TEST_SIZE);
-        assert_ne!(rand_sack_b.weights_sum(), 0);
-        assert_ne!(rand_sack_b.capacity, 0);
+        assert_ne!(rand_sack_b.weights_sum(), ZERO_SCORE);
+        assert_ne!(rand_sack_b.capacity, ZERO_SCORE);
 
         let starter = rand_sack_b.starting_solution();
         assert!(rand_sack_b.is_legal());
@@ -488,4 +510,30 @@ mod tests {
         assert_eq!(the_best.get_score(), little_knapsack.capacity);
         assert_eq!(the_best.get_best_score(), little_knapsack.capacity);
     }
+
+    #[test]
+    fn test_parallel_children() {
+        use optimizer::problem::PARALLEL_CHILDREN_MIN_SIZE;
+
+        assert!(!ProblemSubsetSum::random(16).parallel_children());
+        let big_knapsack = ProblemSubsetSum::random(PARALLEL_CHILDREN_MIN_SIZE);
+        assert!(big_knapsack.parallel_children());
+
+        // Same children, in the same order, as one after the other
+        let mut parent = big_knapsack.starting_solution();
+        while let Some(index) = big_knapsack.first_open_decision(&parent) {
+            let children = big_knapsack.children_of_solution(&parent);
+            assert_eq!(
+                children,
+                vec![
+                    big_knapsack.produce_child(&parent, index, true),
+                    big_knapsack.produce_child(&parent, index, false)
+                ]
+            );
+            if 64 < index {
+                break;
+            };
+            parent = children[1].clone();
+        }
+    }
 } // end mod tests
@@ -13,10 +13,13 @@ extern crate rand_distr;
 
 use rand::prelude::*;
 use rand_distr::{Distribution, Gamma};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 
 use implementations::ProblemSubsetSum;
-use mhd_memory::{ScoreType, ZERO_SCORE}; // Not used: NUM_BYTES
-use optimizer::{MinimalSolution, PriorityType, Problem, Solution};
+use mhd_memory::util::{get_bit, iter_open_bits, iter_set_bits};
+use mhd_memory::{Score, ScoreType, ZERO_SCORE}; // Not used: NUM_BYTES
+use optimizer::{DecisionMap, MinimalSolution, PriorityType, Problem, Solution};
 
 /********************************************************************************************/
 ///## Customized Solution Type for the 0/1 Knapsack
@@ -188,6 +191,120 @@ impl Problem01Knapsack {
         debug_assert!(self.rules_audit_passed(&result));
         result
     }
+
+    // Changing a problem (to re-solve it), as for ProblemSubsetSum
+
+    /// Change the capacity
+    pub fn set_capacity(&mut self, capacity: ScoreType) -> DecisionMap {
+        self.basis.set_capacity(capacity)
+    }
+
+    /// Add an item where its weight belongs, see `ProblemSubsetSum::add_item`
+    pub fn add_item(&mut self, weight: ScoreType, value: ScoreType) -> DecisionMap {
+        let map = self.basis.add_item(weight);
+        let index = map
+            .old_indices()
+            .iter()
+            .position(Option::is_none)
+            .expect("one new item");
+        self.values.insert(index, value);
+        map
+    }
+
+    /// Remove the item `index` (later items move up one)
+    pub fn remove_item(&mut self, index: usize) -> DecisionMap {
+        self.values.remove(index);
+        self.basis.remove_item(index)
+    }
+
+    /// Sensitivity analysis: for each item, what if it were forced into (or out of) the
+    /// knapsack, i.e. the other way than in `best`? The rest is re-optimized greedily:
+    /// items are dropped (worst value per weight first) until the knapsack fits again,
+    /// then the freed capacity is filled (best value per weight first).
+    /// So `forced_score` is always achievable, but may underestimate the best possible.
+    pub fn sensitivity(&self, best: &ZeroOneKnapsackSolution) -> Vec<ItemSensitivity> {
+        assert!(self.solution_is_complete(best));
+        let in_best: Vec<bool> = (0..self.problem_size())
+            .map(|index| best.get_decision(index).expect("complete"))
+            .collect();
+        (0..self.problem_size())
+            .map(|index| {
+                let mut forced = in_best.clone();
+                forced[index] = !in_best[index];
+                ItemSensitivity {
+                    index,
+                    in_best: in_best[index],
+                    best_score: best.get_score(),
+                    forced_score: self.greedy_repair(&mut forced, index),
+                }
+            })
+            .collect()
+    }
+
+    // Make `items` fit (without touching item `fixed`), then fill it up greedily.
+    // Returns the resulting score, or None if item `fixed` alone doesn't fit.
+    fn greedy_repair(&self, items: &mut [bool], fixed: usize) -> Option<ScoreType> {
+        let weights = &self.basis.weights;
+        let capacity = self.capacity() as u64;
+        // Sort by value per weight, best first (cross multiplied, to stay exact)
+        let mut by_ratio: Vec<usize> = (0..items.len()).filter(|&i| i != fixed).collect();
+        by_ratio.sort_by(|&a, &b| {
+            let a_ratio = self.values[a] as u64 * weights[b] as u64;
+            let b_ratio = self.values[b] as u64 * weights[a] as u64;
+            b_ratio.cmp(&a_ratio)
+        });
+        let mut weight: u64 = (0..items.len())
+            .filter(|&i| items[i])
+            .map(|i| weights[i] as u64)
+            .sum();
+        for &index in by_ratio.iter().rev() {
+            if weight <= capacity {
+                break;
+            };
+            if items[index] {
+                items[index] = false;
+                weight -= weights[index] as u64;
+            }
+        }
+        if capacity < weight {
+            return None; // item `fixed` is too heavy on its own
+        };
+        for &index in by_ratio.iter() {
+            if !items[index] && weight + weights[index] as u64 <= capacity {
+                items[index] = true;
+                weight += weights[index] as u64;
+            }
+        }
+        Some(
+            (0..items.len())
+                .filter(|&i| items[i])
+                .map(|i| self.values[i])
+                .sum(),
+        )
+    }
+}
+
+/// The result of `Problem01Knapsack::sensitivity` for one item
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ItemSensitivity {
+    /// The item (decision) index
+    pub index: usize,
+    /// Is the item in the knapsack in the analysed (best) solution?
+    pub in_best: bool,
+    /// The score of the analysed solution
+    pub best_score: ScoreType,
+    /// The (greedily re-optimized) score with the item forced the other way,
+    /// or None if that is impossible (the item alone is heavier than the capacity)
+    pub forced_score: Option<ScoreType>,
+}
+
+impl ItemSensitivity {
+    /// How much the score changes when the item is forced the other way (usually a loss)
+    #[inline]
+    pub fn impact(&self) -> Option<i64> {
+        self.forced_score
+            .map(|forced| forced as i64 - self.best_score as i64)
+    }
 }
 
 // Problem Trait Methods
@@ -231,10 +348,11 @@ impl Problem for Problem01Knapsack {
         let mut rng = rand::thread_rng();
         // The parameters shape=2.0 and scale=1000.0 were arrived at by playing around in a
         // Jupyter Notebook but remain failry arbitrary
-        let distr = Gamma::new(2.0, 1000.0).unwrap();
+        let distr = Gamma::<f64>::new(2.0, 1000.0).unwrap();
 
+        // (whole numbers, also for float scores -- so their sums are exact)
         self.values = (0..num_bits)
-            .map(|_| (distr.sample(&mut rng) + 1.0) as ScoreType)
+            .map(|_| (distr.sample(&mut rng) + 1.0).floor() as ScoreType)
             .collect();
 
         // This has been removed to not make the problem TOO easy...
@@ -255,33 +373,23 @@ impl Problem for Problem01Knapsack {
 
     // first, methods not defined previously, but which arose while implemeneting the others (see below)
     fn solution_score(&self, solution: &Self::Sol) -> ScoreType {
-        let mut result = ZERO_SCORE;
-        // Note to self -- later we can be faster here by doing this byte-wise
-        for index in 0..self.problem_size() {
-            if let Some(decision) = solution.get_decision(index) {
-                if decision {
-                    result += self.values[index];
-                }
-            }
-        } // end for all bits
-        result as ScoreType
+        // the decisions made, and made for true -- a word at a time
+        let mask = solution.mask();
+        iter_set_bits(solution.query())
+            .take_while(|index| *index < self.problem_size())
+            .filter(|index| get_bit(mask, *index))
+            .map(|index| self.values[index])
+            .sum()
     } // end solution_is_legal
 
     fn solution_best_score(&self, solution: &Self::Sol) -> ScoreType {
         debug_assert!(self.solution_is_legal(solution));
         // add up all values which are either open or not set to zero,
-        let mut result = ZERO_SCORE;
-        for index in 0..self.problem_size() {
-            match solution.get_decision(index) {
-                // open decision! So we COULD put this item in the knapsack...
-                None => result += self.values[index],
-                Some(decision) => {
-                    if decision {
-                        result += self.values[index]
-                    } // else add zero, i.e. do nothing
-                }
-            }; // end match
-        } // end for all bits
+        let open: ScoreType = iter_open_bits(solution.mask())
+            .take_while(|index| *index < self.problem_size())
+            .map(|index| self.values[index])
+            .sum();
+        let result = self.solution_score(solution) + open;
         debug_assert!(self.solution_score(&solution) <= result);
         // next assert fails if solution is complete and best_score != score
         debug_assert!(
@@ -313,6 +421,43 @@ impl Problem for Problem01Knapsack {
         self.solution_from_basis(&self.basis.starting_solution())
     }
 
+    /// Value density greedy: take the item if it fits, and if its value per weight is at
+    /// least that of all the open items together.
+    fn greedy_decision(&self, solution: &Self::Sol, index: usize) -> bool {
+        let weights = &self.basis.weights;
+        if self.capacity() < self.basis.solution_score(&solution.basis) + weights[index] {
+            return false; // does not fit
+        };
+        let open: Vec<usize> = (0..self.problem_size())
+            .filter(|&i| solution.get_decision(i).is_none())
+            .collect();
+        let open_values: u64 = open.iter().map(|&i| self.values[i] as u64).sum();
+        let open_weights: u64 = open.iter().map(|&i| weights[i] as u64).sum();
+        // value / weight >= open_values / open_weights, cross multiplied
+        open_values * weights[index] as u64 <= self.values[index] as u64 * open_weights
+    }
+
+    /// Value density: the most value per weight first (cross multiplied, to stay exact)
+    fn greedy_order(&self) -> Vec<usize> {
+        let weights = &self.basis.weights;
+        let mut result: Vec<usize> = (0..self.problem_size()).collect();
+        result.sort_by(|&a, &b| {
+            let a_ratio = self.values[a] as u64 * weights[b] as u64;
+            let b_ratio = self.values[b] as u64 * weights[a] as u64;
+            b_ratio.cmp(&a_ratio)
+        });
+        result
+    }
+
+    /// The decisions still open, the weight so far and the value so far
+    fn transposition_key(&self, solution: &Self::Sol) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        solution.mask().hash(&mut hasher);
+        solution.basis.get_score().to_f64().to_bits().hash(&mut hasher);
+        solution.get_score().to_f64().to_bits().hash(&mut hasher);
+        hasher.finish()
+    }
+
     // Take the default better_than() method
     // Take the default can_be_better_than() method
 
@@ -396,7 +541,7 @@ mod tests {
 
         assert!(!rand_sack_a.is_legal());
         assert_eq!(rand_sack_a.problem_size(), TEST_SIZE);
-        assert_eq!(rand_sack_a.weights_sum(), 0);
+        assert_eq!(rand_sack_a.weights_sum(), ZERO_SCORE);
 
         trace!("Testing randomized 01Knapsack...");
         rand_sack_a.randomize();
@@ -404,18 +549,18 @@ mod tests {
         assert!(rand_sack_a.is_legal());
         assert_eq!(rand_sack_a.problem_size(), TEST_SIZE);
 
-        assert_ne!(rand_sack_a.weights_sum(), 0);
-        assert_ne!(rand_sack_a.values_sum(), 0);
-        assert_ne!(rand_sack_a.capacity(), 0);
+        assert_ne!(rand_sack_a.weights_sum(), ZERO_SCORE);
+        assert_ne!(rand_sack_a.values_sum(), ZERO_SCORE);
+        assert_ne!(rand_sack_a.capacity(), ZERO_SCORE);
 
         debug!("Testing random 01Knapsack...");
         let rand_sack_b = Problem01Knapsack::random(TEST_SIZE);
 
         assert!(rand_sack_b.is_legal());
         assert_eq!(rand_sack_b.problem_size(), TEST_SIZE);
-        assert_ne!(rand_sack_b.weights_sum(), 0);
-        assert_ne!(rand_sack_b.values_sum(), 0);
-        assert_ne!(rand_sack_b.capacity(), 0);
+        assert_ne!(rand_sack_b.weights_sum(), ZERO_SCORE);
+        assert_ne!(rand_sack_b.values_sum(), ZERO_SCORE);
+        assert_ne!(rand_sack_b.capacity(), ZERO_SCORE);
 
         debug!("Testing random 01Knapsack's starting solution...");
         let starter = rand_sack_b.starting_solution();
@@ -522,4 +667,34 @@ mod tests {
         assert_eq!(best_score, little_knapsack.solution_score(&the_best));
         assert_eq!(best_score, little_knapsack.solution_best_score(&the_best));
     }
+
+    #[test]
+    fn test_sensitivity() {
+        let mut knapsack = Problem01Knapsack::new(4);
+        knapsack.basis.weights = [5, 4, 3, 20].iter().map(|&x| x as ScoreType).collect();
+        knapsack.basis.capacity = 9 as ScoreType;
+        knapsack.values = [10, 7, 2, 100].iter().map(|&x| x as ScoreType).collect();
+        let mut best = knapsack.starting_solution();
+        for (index, &decision) in [true, true, false, false].iter().enumerate() {
+            best.make_decision(index, decision);
+        }
+        knapsack.fix_scores(&mut best);
+        assert_eq!(best.get_score(), 17 as ScoreType);
+
+        let report = knapsack.sensitivity(&best);
+        assert_eq!(report.len(), 4);
+        // Without item 0, items 1 and 2 are the best we can do
+        assert!(report[0].in_best);
+        assert_eq!(report[0].forced_score, Some(9 as ScoreType));
+        assert_eq!(report[0].impact(), Some(-8));
+        // Without item 1, item 2 fills the gap
+        assert_eq!(report[1].forced_score, Some(12 as ScoreType));
+        // With item 2, item 1 (the worse per weight) has to go
+        assert!(!report[2].in_best);
+        assert_eq!(report[2].forced_score, Some(12 as ScoreType));
+        assert_eq!(report[2].impact(), Some(-5));
+        // Item 3 can never fit
+        assert_eq!(report[3].forced_score, None);
+        assert_eq!(report[3].impact(), None);
+    }
 } // end mod tests
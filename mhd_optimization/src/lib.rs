@@ -14,6 +14,8 @@
 //! ```rust
 //! use mhd_optimization::optimizer::*;
 //! use mhd_optimization::implementations::*;
+//! # extern crate mhd_memory;
+//! use mhd_memory::ZERO_SCORE;
 //!
 //! const NUM_DECISIONS: usize = 4; // for a start
 //!
@@ -31,7 +33,7 @@
 //! assert!(knapsack.solution_is_complete(&the_best));
 //
 //! let best_score = the_best.get_score();
-//! assert!(0  < best_score);
+//! assert!(ZERO_SCORE < best_score);
 //! assert_eq!(best_score, knapsack.solution_score(&the_best));
 //! ```
 
@@ -43,9 +45,23 @@ extern crate hamming;
 extern crate log;
 extern crate rand;
 extern crate rand_distr;
+extern crate rayon;
 extern crate simplelog;
 extern crate structopt;
 
+#[cfg(feature = "plots")]
+extern crate plotters;
+
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(feature = "serde", test))]
+extern crate serde_json;
+
+#[cfg(feature = "async")]
+extern crate futures_channel;
+#[cfg(all(feature = "async", test))]
+extern crate futures_executor;
+
 extern crate mhd_memory;
 
 pub mod optimizer {
@@ -53,11 +69,70 @@ pub mod optimizer {
     pub use self::solution::{MinimalSolution, PriorityType, Solution};
 
     pub mod solver;
-    pub use self::solver::Solver;
+    pub use self::solver::{FrontierEntry, Solver, SolverBuilder, StepEvent, StepOutcome};
+
+    pub mod solver_config;
+    pub use self::solver_config::{CancelHandle, ConvergenceMode, SolverConfig, StopReason};
+
+    pub mod mhd_error;
+    pub use self::mhd_error::MhdError;
+
+    pub mod observer;
+    pub use self::observer::{IterationStats, SearchObserver};
+
+    pub mod solve_iter;
+    pub use self::solve_iter::SolveIter;
+
+    pub mod solve_report;
+    pub use self::solve_report::SolveReport;
+
+    pub mod search_driver;
+    pub use self::search_driver::SearchDriver;
 
     pub mod problem;
     pub use self::problem::Problem;
 
+    pub mod dynamic;
+    pub use self::dynamic::{DynProblem, DynSolver};
+
+    pub mod decision_map;
+    pub use self::decision_map::DecisionMap;
+
+    pub mod neighborhood;
+    pub use self::neighborhood::Neighborhood;
+
+    pub mod search_trace;
+    pub use self::search_trace::{SearchTrace, TraceEvent};
+
+    pub mod elite_archive;
+    pub use self::elite_archive::EliteArchive;
+
+    pub mod bucket_queue;
+    pub use self::bucket_queue::BucketQueue;
+
+    pub mod memory_usage;
+    pub use self::memory_usage::MemoryUsage;
+
+    pub mod memory_pruning;
+    pub use self::memory_pruning::MemoryPruning;
+
+    pub mod visited_set;
+    pub use self::visited_set::{VisitedSet, VisitedStats};
+
+    pub mod distributed;
+    pub use self::distributed::{run_coordinator, run_worker};
+    pub use self::distributed::{Channel, Message, MpscChannel, TcpChannel};
+
+    #[cfg(feature = "plots")]
+    pub mod plots;
+    #[cfg(feature = "plots")]
+    pub use self::plots::plot_convergence;
+
+    #[cfg(feature = "async")]
+    pub mod async_search;
+    #[cfg(feature = "async")]
+    pub use self::async_search::{AsyncSearch, Progress, ProgressStream};
+
     // pub mod permutations;
     // pub use self::permutations::{ Permutation,  }; // PermutationSolution
 
@@ -68,16 +143,18 @@ pub mod implementations {
     pub use self::subset_sum_problem::ProblemSubsetSum;
 
     pub mod zero_one_knapsack_problem;
-    pub use self::zero_one_knapsack_problem::{Problem01Knapsack, ZeroOneKnapsackSolution};
+    pub use self::zero_one_knapsack_problem::{
+        ItemSensitivity, Problem01Knapsack, ZeroOneKnapsackSolution,
+    };
 
     pub mod depth_first_solver;
     pub use self::depth_first_solver::DepthFirstSolver;
 
     pub mod best_first_solver;
-    pub use self::best_first_solver::BestFirstSolver;
+    pub use self::best_first_solver::{BestFirstSolver, PriorityFunction, PriorityStrategy};
 
     pub mod mcts_solver;
-    pub use self::mcts_solver::MonteCarloTreeSolver;
+    pub use self::mcts_solver::{MonteCarloTreeSolver, RolloutPolicy, TranspositionTable};
 
     pub mod mhd_mc_solver;
     pub use self::mhd_mc_solver::*;
@@ -85,9 +162,42 @@ pub mod implementations {
     pub mod bf_mhd_mc_solver;
     pub use self::bf_mhd_mc_solver::*;
 
+    pub mod evolutionary_solver;
+    pub use self::evolutionary_solver::EvolutionarySolver;
+
+    pub mod genetic_solver;
+    pub use self::genetic_solver::GeneticSolver;
+
+    pub mod hyper_heuristic_solver;
+    pub use self::hyper_heuristic_solver::{DecisionPolicy, HyperHeuristicSolver, PolicyBandit};
+
+    pub mod exhaustive_solver;
+    pub use self::exhaustive_solver::ExhaustiveSolver;
+
+    pub mod dp_subset_sum_solver;
+    pub use self::dp_subset_sum_solver::DpSubsetSumSolver;
+
+    pub mod dp_knapsack_solver;
+    pub use self::dp_knapsack_solver::DpKnapsackSolver;
+
+    pub mod greedy_solver;
+    pub use self::greedy_solver::{greedy_solution, warm_start, GreedySolver};
+
+    pub mod restart_solver;
+    pub use self::restart_solver::{luby, RestartSchedule, RestartSolver};
+
+    pub mod diverse_solver;
+    pub use self::diverse_solver::DiverseSolver;
+
 //    pub mod mcts_mhd_solver;
 //    pub use self::mcts_mhd_solver::*;
 
+    pub mod solver_kind;
+    pub use self::solver_kind::{SolverJob, SolverKind};
+
+    pub mod portfolio_solver;
+    pub use self::portfolio_solver::PortfolioSolver;
+
     pub mod parsers;
     pub use self::parsers::{parse_dot_csv_stream, parse_dot_dat_stream};
 
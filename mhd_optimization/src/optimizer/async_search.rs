@@ -0,0 +1,228 @@
+/// # Async Search (feature "async")
+///
+/// `find_best_solution` runs until it is done -- fine in a thread of its own, but inside an
+/// async service (tokio, async-std, ...) it would block a worker thread of the runtime, and
+/// starve every other task there. `Solver::find_best_solution_async` returns an `AsyncSearch`
+/// instead: a future which makes `steps_per_poll` steps each time it is polled, and then
+/// yields (after waking itself up again), until the search is finished or has converged,
+/// just like `find_best_solution`. It needs no particular runtime.
+///
+/// To cancel a search, drop the future (the async way), or call `cancel` on its `CancelHandle`
+/// (see `SolverConfig::cancel`) from anywhere else; either way, the solver keeps the best
+/// solution found so far.
+/// `progress` gives a stream of `Progress` reports, one per poll.
+///
+/// ```rust
+/// extern crate futures_executor;
+/// # extern crate mhd_optimization;
+/// use futures_executor::{block_on, block_on_stream};
+/// use mhd_optimization::optimizer::{Problem, Solution, Solver};
+/// use mhd_optimization::implementations::{DepthFirstSolver, Problem01Knapsack};
+/// use std::time::Duration;
+///
+/// # fn main() {
+/// let knapsack = Problem01Knapsack::random(16);
+/// let mut solver = DepthFirstSolver::new(16);
+///
+/// let mut search = solver.find_best_solution_async(&knapsack, Duration::from_secs(1));
+/// let progress = search.progress();
+/// let the_best = block_on(search); // or .await, in an async fn
+/// assert!(knapsack.solution_is_complete(&the_best));
+///
+/// let reports: Vec<_> = block_on_stream(progress).collect();
+/// assert!(reports.last().unwrap().finished);
+/// assert_eq!(reports.last().unwrap().best_score, the_best.get_score());
+/// # }
+/// ```
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+
+use mhd_memory::ScoreType;
+use optimizer::{CancelHandle, Problem, SearchDriver, Solver, SolverConfig, StopReason};
+
+/// How many steps to make per poll, unless told otherwise
+pub const DEFAULT_STEPS_PER_POLL: usize = 64;
+
+/// ## Progress Reports
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// Steps made so far
+    pub steps: u64,
+    pub elapsed: Duration,
+    pub best_score: ScoreType,
+    /// Number of open solutions (see `Solver::number_of_solutions`)
+    pub frontier: usize,
+    /// Whether this is the last report (the search is finished, converged, or cancelled)
+    pub finished: bool,
+}
+
+/// The receiving end of the progress reports of an `AsyncSearch` (a `Stream`)
+pub type ProgressStream = UnboundedReceiver<Progress>;
+
+/// ## The Async Search Future
+pub struct AsyncSearch<'a, S: 'a, Prob: 'a + Problem>
+where
+    S: Solver<Prob::Sol>,
+{
+    solver: &'a mut S,
+    problem: &'a Prob,
+    config: SolverConfig,
+    /// Steps made between two yields; fewer means a more responsive runtime, but more overhead
+    pub steps_per_poll: usize,
+    cancel_handle: CancelHandle,
+    progress: Option<UnboundedSender<Progress>>,
+    driver: Option<SearchDriver>, // once the search started
+}
+
+impl<'a, S, Prob: Problem> AsyncSearch<'a, S, Prob>
+where
+    S: Solver<Prob::Sol>,
+{
+    /// A search of `problem` with `solver`, like `find_best_solution`
+    /// (which calls `start_search` at the first poll, so don't call it yourself)
+    pub fn new<C: Into<SolverConfig>>(solver: &'a mut S, problem: &'a Prob, config: C) -> Self {
+        let mut config = config.into();
+        // (the config's handle, if it has one, cancels this search, too)
+        let cancel_handle = config
+            .cancel
+            .get_or_insert_with(CancelHandle::default)
+            .clone();
+        Self {
+            solver,
+            problem,
+            config,
+            steps_per_poll: DEFAULT_STEPS_PER_POLL,
+            cancel_handle,
+            progress: None,
+            driver: None,
+        }
+    }
+
+    /// A handle to cancel this search with
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel_handle.clone()
+    }
+
+    /// A stream of progress reports, one per poll. The stream ends when the search ends
+    /// (or is dropped). Only the stream of the last call gets reports.
+    pub fn progress(&mut self) -> ProgressStream {
+        let (sender, receiver) = unbounded();
+        self.progress = Some(sender);
+        receiver
+    }
+
+    fn report(&self, driver: &SearchDriver, finished: bool) {
+        if let Some(sender) = &self.progress {
+            // (if nobody is listening anymore, that's fine with us)
+            let _ = sender.unbounded_send(Progress {
+                steps: driver.num_steps(),
+                elapsed: driver.elapsed(),
+                best_score: self.solver.best_score(),
+                frontier: self.solver.number_of_solutions(),
+                finished,
+            });
+        };
+    }
+}
+
+impl<'a, S, Prob: Problem> Future for AsyncSearch<'a, S, Prob>
+where
+    S: Solver<Prob::Sol>,
+{
+    /// The best solution found
+    type Output = Prob::Sol;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        let search = self.get_mut();
+        let mut driver = match search.driver.take() {
+            Some(driver) => driver,
+            None => {
+                if let Some(seed) = search.config.seed {
+                    search.solver.reseed(seed);
+                };
+                search.solver.start_search(search.problem);
+                SearchDriver::new(&*search.solver, search.config.clone(), None)
+            }
+        };
+
+        if search.cancel_handle.is_cancelled() {
+            driver.stop(StopReason::Cancelled);
+        };
+        for _ in 0..search.steps_per_poll.max(1) {
+            if driver.stop_reason().is_some() {
+                break;
+            };
+            driver.step(search.solver, search.problem);
+        } // end for all steps of this poll
+        let finished = driver.stop_reason().is_some();
+        search.report(&driver, finished);
+        search.driver = Some(driver);
+
+        if finished {
+            search.progress = None; // ends the progress stream
+            Poll::Ready(search.solver.best_solution().clone())
+        } else {
+            // Give the others a chance, but come back to us soon
+            context.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+///////////////////// TESTs for AsyncSearch /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_executor::{block_on, block_on_stream};
+    use implementations::{DepthFirstSolver, ExhaustiveSolver, Problem01Knapsack};
+    use optimizer::Solution;
+
+    #[test]
+    fn test_async_search() {
+        const FEW_DECISIONS: usize = 12;
+        let knapsack = Problem01Knapsack::random(FEW_DECISIONS);
+        let optimum = ExhaustiveSolver::builder(&knapsack).solve().get_score();
+
+        let mut solver = DepthFirstSolver::new(FEW_DECISIONS);
+        let mut search = solver.find_best_solution_async(&knapsack, Duration::from_secs(1));
+        search.steps_per_poll = 5;
+        let progress = search.progress();
+        let the_best = block_on(search);
+        assert_eq!(the_best.get_score(), optimum);
+        assert_eq!(solver.best_score(), optimum);
+
+        // Several polls, so several reports, and only the last one is final
+        let reports: Vec<Progress> = block_on_stream(progress).collect();
+        assert!(1 < reports.len());
+        assert!(reports.windows(2).all(|w| w[0].steps + 5 >= w[1].steps));
+        assert!(reports
+            .windows(2)
+            .all(|w| w[0].best_score <= w[1].best_score));
+        assert!(reports.iter().rev().skip(1).all(|report| !report.finished));
+        assert!(reports.last().unwrap().finished);
+        assert_eq!(reports.last().unwrap().best_score, optimum);
+    }
+
+    #[test]
+    fn test_cancel_search() {
+        const MANY_DECISIONS: usize = 100;
+        let knapsack = Problem01Knapsack::random(MANY_DECISIONS);
+        let mut solver = DepthFirstSolver::new(MANY_DECISIONS);
+        let mut search = solver.find_best_solution_async(&knapsack, Duration::from_secs(100));
+        let progress = search.progress();
+        let handle = search.cancel_handle();
+        assert!(!handle.is_cancelled());
+        handle.cancel();
+        let the_best = block_on(search);
+        // Cancelled at once, after the start (which finds a random solution)
+        assert!(knapsack.solution_is_complete(&the_best));
+        let reports: Vec<Progress> = block_on_stream(progress).collect();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].steps, 0);
+        assert!(reports[0].finished);
+    }
+}
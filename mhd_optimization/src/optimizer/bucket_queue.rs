@@ -0,0 +1,190 @@
+/// # The Bucket Queue
+///
+/// A priority queue for small integer priorities: one bucket (a stack) per priority, and
+/// an index of the highest bucket which may be non-empty. `push` is O(1), and so is `pop`,
+/// amortized, as long as the priorities popped mostly go down -- which is the case in
+/// branch and bound, where children are never more promising than their parents.
+/// No comparisons at all, let alone floating point ones.
+///
+/// Items of equal priority come out last in, first out (like a depth first search).
+/// Memory grows with the highest priority pushed (one empty `Vec` per priority below),
+/// so this is for priorities derived from (moderate) integer scores,
+/// see `PriorityStrategy::BucketQueue` in `BestFirstSolver`.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::BucketQueue;
+///
+/// let mut queue = BucketQueue::new();
+/// queue.push(3, "three");
+/// queue.push(7, "seven");
+/// queue.push(3, "another three");
+/// assert_eq!(queue.len(), 3);
+/// assert_eq!(queue.peek(), Some(&"seven"));
+/// assert_eq!(queue.pop(), Some("seven"));
+/// assert_eq!(queue.pop(), Some("another three"));
+/// assert_eq!(queue.pop(), Some("three"));
+/// assert_eq!(queue.pop(), None);
+/// ```
+
+#[derive(Debug, Clone)]
+pub struct BucketQueue<T> {
+    buckets: Vec<Vec<T>>, // buckets[p]: the items with priority p
+    top: usize,           // no bucket above this one holds an item
+    len: usize,
+}
+
+impl<T> Default for BucketQueue<T> {
+    fn default() -> Self {
+        Self {
+            buckets: Vec::new(),
+            top: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<T> BucketQueue<T> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        0 == self.len
+    }
+
+    /// Remove all items (but keep the buckets, for reuse)
+    pub fn clear(&mut self) {
+        for bucket in self.buckets.iter_mut() {
+            bucket.clear();
+        }
+        self.top = 0;
+        self.len = 0;
+    }
+
+    #[inline]
+    pub fn push(&mut self, priority: usize, item: T) {
+        if self.buckets.len() <= priority {
+            self.buckets.resize_with(priority + 1, Vec::new);
+        };
+        self.buckets[priority].push(item);
+        self.top = self.top.max(priority);
+        self.len += 1;
+    }
+
+    /// Remove (one of) the item(s) with the highest priority
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        };
+        while self.buckets[self.top].is_empty() {
+            self.top -= 1; // can't underflow: some bucket is not empty
+        }
+        self.len -= 1;
+        self.buckets[self.top].pop()
+    }
+
+    /// The item `pop` would return next
+    pub fn peek(&self) -> Option<&T> {
+        self.buckets[..self.buckets.len().min(self.top + 1)]
+            .iter()
+            .rev()
+            .find_map(|bucket| bucket.last())
+    }
+
+    /// The highest priority of any item
+    pub fn top_priority(&self) -> Option<usize> {
+        (0..self.buckets.len().min(self.top + 1))
+            .rev()
+            .find(|&priority| !self.buckets[priority].is_empty())
+    }
+
+    /// All items, in the order `pop` would return them
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.buckets[..self.buckets.len().min(self.top + 1)]
+            .iter()
+            .rev()
+            .flat_map(|bucket| bucket.iter().rev())
+    }
+
+    /// Move all items of `other` into this queue, leaving `other` empty
+    pub fn append(&mut self, other: &mut Self) {
+        for (priority, bucket) in other.buckets.iter_mut().enumerate() {
+            for item in bucket.drain(..) {
+                self.push(priority, item);
+            }
+        }
+        other.clear();
+    }
+
+    /// All items, highest priority first
+    pub fn drain(&mut self) -> Vec<T> {
+        let mut result = Vec::with_capacity(self.len);
+        while let Some(item) = self.pop() {
+            result.push(item);
+        }
+        result
+    }
+} // end impl BucketQueue
+
+///////////////////// TESTs for BucketQueue /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_bucket_queue_against_sorting() {
+        let mut rng = thread_rng();
+        let mut queue = BucketQueue::new();
+        let mut priorities: Vec<usize> = (0..500).map(|_| rng.gen_range(0..100)).collect();
+        for (index, priority) in priorities.iter().enumerate() {
+            queue.push(*priority, (*priority, index));
+        }
+        assert_eq!(queue.len(), 500);
+        assert_eq!(queue.top_priority(), priorities.iter().max().cloned());
+
+        priorities.sort_unstable_by(|a, b| b.cmp(a));
+        let mut popped = Vec::new();
+        while let Some((priority, _)) = queue.pop() {
+            popped.push(priority);
+            // (push something less promising now and then, as branch and bound does)
+            if 0 < priority && 0 == popped.len() % 7 {
+                queue.push(priority - 1, (priority - 1, 0));
+                priorities.push(priority - 1);
+            };
+        }
+        priorities.sort_unstable_by(|a, b| b.cmp(a));
+        assert_eq!(popped, priorities);
+        assert!(queue.is_empty());
+        assert_eq!(queue.peek(), None);
+        assert_eq!(queue.top_priority(), None);
+    }
+
+    #[test]
+    fn test_bucket_queue_append() {
+        let mut first = BucketQueue::new();
+        let mut second = BucketQueue::new();
+        first.push(5, 'a');
+        first.push(1, 'b');
+        second.push(9, 'c');
+        second.push(5, 'd');
+        first.append(&mut second);
+        assert!(second.is_empty());
+        assert_eq!(first.len(), 4);
+        let in_order: Vec<char> = first.iter().cloned().collect();
+        assert_eq!(in_order, vec!['c', 'd', 'a', 'b']);
+        assert_eq!(first.drain(), vec!['c', 'd', 'a', 'b']);
+        first.push(2, 'e');
+        first.clear();
+        assert!(first.is_empty());
+        assert_eq!(first.pop(), None);
+    }
+}
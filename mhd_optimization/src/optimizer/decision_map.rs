@@ -0,0 +1,261 @@
+/// # Decision Maps -- re-solving after a problem changed
+///
+/// When a problem is changed a little (e.g. a knapsack gets a new capacity, or an item is
+/// added or removed -- see `Problem01Knapsack::add_item` etc.), most of what a solver
+/// learned about the old problem is still useful. A `DecisionMap` says which decision of
+/// the *old* problem each decision of the *new* problem corresponds to, so that solutions
+/// and samples can be carried over (and those which became illegal dropped).
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, Solution, Solver};
+/// use mhd_optimization::implementations::{BestFirstSolver, Problem01Knapsack};
+/// # extern crate mhd_memory;
+/// use mhd_memory::ScoreType;
+/// use std::time::Duration;
+///
+/// let mut knapsack = Problem01Knapsack::random(12);
+/// let mut solver = BestFirstSolver::new(12);
+/// let best = solver.find_best_solution(&knapsack, Duration::from_millis(100)).unwrap();
+///
+/// // A new item arrives: re-solve, starting from the old best solution (a warm start)
+/// let map = knapsack.add_item(100 as ScoreType, 50 as ScoreType);
+/// let warm_start = map.carry_over_solution(&knapsack, &best).expect("still legal");
+/// assert_eq!(warm_start.get_score(), best.get_score());
+///
+/// let mut solver = BestFirstSolver::new(13);
+/// let new_best = solver
+///     .find_best_solution_from(&knapsack, warm_start, Duration::from_millis(100))
+///     .unwrap();
+/// assert!(best.get_score() <= new_best.get_score());
+/// ```
+use mhd_memory::util::get_bit;
+use mhd_memory::Sample;
+use optimizer::{Problem, Solution};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecisionMap {
+    /// The number of decisions in the old problem
+    pub old_size: usize,
+    old_indices: Vec<Option<usize>>, // for each new decision, the old one (if any)
+}
+
+impl DecisionMap {
+    /// Nothing moved (e.g. only the capacity changed)
+    pub fn identity(size: usize) -> Self {
+        Self {
+            old_size: size,
+            old_indices: (0..size).map(Some).collect(),
+        }
+    }
+
+    /// A new decision was appended at the end
+    pub fn appended(old_size: usize) -> Self {
+        Self::inserted(old_size, old_size)
+    }
+
+    /// A new decision was inserted at `inserted`, and all later decisions move down by one
+    pub fn inserted(old_size: usize, inserted: usize) -> Self {
+        assert!(inserted <= old_size);
+        let mut result = Self::identity(old_size);
+        result.old_indices.insert(inserted, None);
+        result
+    }
+
+    /// The decision `removed` was dropped, and all later decisions move up by one
+    pub fn removed(old_size: usize, removed: usize) -> Self {
+        assert!(removed < old_size);
+        Self {
+            old_size,
+            old_indices: (0..old_size)
+                .filter(|&index| index != removed)
+                .map(Some)
+                .collect(),
+        }
+    }
+
+    /// The number of decisions in the new problem
+    #[inline]
+    pub fn new_size(&self) -> usize {
+        self.old_indices.len()
+    }
+
+    /// Which old decision does new decision `new_index` correspond to? (None if it is new)
+    #[inline]
+    pub fn old_index(&self, new_index: usize) -> Option<usize> {
+        self.old_indices[new_index]
+    }
+
+    /// For each new decision, the old one (if any) -- e.g. to `MhdMemory::remap` a memory
+    #[inline]
+    pub fn old_indices(&self) -> &[Option<usize>] {
+        &self.old_indices
+    }
+
+    /// How many of the first decisions kept their index?
+    /// Search tree levels up to this depth can be kept.
+    pub fn stable_prefix(&self) -> usize {
+        self.old_indices
+            .iter()
+            .enumerate()
+            .take_while(|(new_index, old_index)| Some(*new_index) == **old_index)
+            .count()
+    }
+
+    /// Carry a complete solution of the old problem over to the (new) `problem`:
+    /// New decisions are set to false (e.g. a new item is left out of the knapsack).
+    /// Returns None if the result is not legal in the new problem.
+    pub fn carry_over_solution<Sol: Solution, Prob: Problem<Sol = Sol>>(
+        &self,
+        problem: &Prob,
+        old: &Sol,
+    ) -> Option<Sol> {
+        self.carry_over(problem, |old_index| old.get_decision(old_index))
+    }
+
+    /// Like `carry_over_solution`, for a sample from an MHD memory: the new sample gets the
+    /// new problem's score for its decisions (or None, if it is not legal any more).
+    pub fn carry_over_sample<Prob: Problem>(&self, problem: &Prob, old: &Sample) -> Option<Sample> {
+        assert_eq!(old.size(), self.old_size);
+        // Samples hold a solution's query bytes, so use the solutions' bit order
+        self.carry_over(problem, |old_index| Some(get_bit(&old.bytes, old_index)))
+            .map(|solution| problem.sample_from_solution(&solution))
+    }
+
+    fn carry_over<Prob: Problem, F: Fn(usize) -> Option<bool>>(
+        &self,
+        problem: &Prob,
+        old_decision: F,
+    ) -> Option<Prob::Sol> {
+        assert_eq!(problem.problem_size(), self.new_size());
+        let mut result = Prob::Sol::new(self.new_size());
+        for new_index in 0..self.new_size() {
+            let decision = match self.old_index(new_index) {
+                Some(old_index) => old_decision(old_index)?, // only complete solutions carry over
+                None => false,
+            };
+            result.make_decision(new_index, decision);
+        }
+        if !problem.solution_is_legal(&result) {
+            return None;
+        };
+        problem.fix_scores(&mut result);
+        debug_assert!(problem.rules_audit_passed(&result));
+        Some(result)
+    }
+} // end impl DecisionMap
+
+///////////////////// TESTs for DecisionMap /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::ProblemSubsetSum;
+    use mhd_memory::{MhdMemory, ScoreType};
+    use optimizer::MinimalSolution;
+
+    #[test]
+    fn test_decision_maps() {
+        let identity = DecisionMap::identity(4);
+        assert_eq!(identity.new_size(), 4);
+        assert_eq!(identity.stable_prefix(), 4);
+
+        let appended = DecisionMap::appended(4);
+        assert_eq!(appended.new_size(), 5);
+        assert_eq!(appended.old_index(4), None);
+        assert_eq!(appended.stable_prefix(), 4);
+
+        let removed = DecisionMap::removed(4, 1);
+        assert_eq!(removed.new_size(), 3);
+        assert_eq!(removed.old_index(0), Some(0));
+        assert_eq!(removed.old_index(1), Some(2));
+        assert_eq!(removed.stable_prefix(), 1);
+        assert_eq!(removed.old_indices(), &[Some(0), Some(2), Some(3)]);
+
+        let inserted = DecisionMap::inserted(4, 1);
+        assert_eq!(inserted.new_size(), 5);
+        assert_eq!(inserted.old_index(1), None);
+        assert_eq!(inserted.old_index(2), Some(1));
+        assert_eq!(inserted.stable_prefix(), 1);
+        assert_eq!(DecisionMap::inserted(4, 4), appended);
+    }
+
+    #[test]
+    fn test_carry_over() {
+        let mut problem = ProblemSubsetSum::random(8);
+        problem.weights = [8, 7, 6, 5, 4, 3, 2, 1]
+            .iter()
+            .map(|&x| x as ScoreType)
+            .collect();
+        problem.capacity = 10 as ScoreType;
+        let mut old = MinimalSolution::new(8);
+        for index in 0..8 {
+            old.make_decision(index, index == 1 || index == 5); // weight 7 + 3
+        }
+        problem.fix_scores(&mut old);
+
+        // Remove item 0: the solution stays the same (with shifted indices)
+        let map = problem.remove_item(0);
+        let carried = map
+            .carry_over_solution(&problem, &old)
+            .expect("still legal");
+        assert_eq!(carried.get_score(), 10 as ScoreType);
+        assert_eq!(carried.get_decision(0), Some(true));
+        assert_eq!(carried.get_decision(4), Some(true));
+
+        // Lower the capacity: now it is illegal
+        let map = problem.set_capacity(9 as ScoreType);
+        assert!(map.carry_over_solution(&problem, &carried).is_none());
+
+        // Samples carry over, too
+        let sample = problem.sample_from_solution(&carried);
+        let map = problem.add_item(1 as ScoreType);
+        assert_eq!(map, DecisionMap::appended(7));
+        assert!(map.carry_over_sample(&problem, &sample).is_none()); // still too heavy
+        problem.set_capacity(12 as ScoreType);
+        let carried_sample = map
+            .carry_over_sample(&problem, &sample)
+            .expect("legal again");
+        assert_eq!(carried_sample.size(), 8);
+        assert_eq!(carried_sample.score, 10 as ScoreType);
+        assert!(!get_bit(&carried_sample.bytes, 7)); // the new item is left out
+
+        // A heavy item goes in front (the weights stay sorted), and the rest move down
+        let map = problem.add_item(9 as ScoreType);
+        assert_eq!(map, DecisionMap::inserted(8, 0));
+        assert_eq!(problem.weights[0], 9 as ScoreType);
+        let moved = map
+            .carry_over_sample(&problem, &carried_sample)
+            .expect("still legal");
+        assert_eq!(moved.score, 10 as ScoreType);
+        assert!(!get_bit(&moved.bytes, 0) && get_bit(&moved.bytes, 1));
+    }
+
+    #[test]
+    fn test_remap_memory() {
+        let mut problem = ProblemSubsetSum::random(12);
+        let mut memory = MhdMemory::new(12);
+        for _ in 0..20 {
+            memory.write_sample(&problem.sample_from_solution(&problem.random_solution()));
+        }
+
+        // Removing an item keeps every solution legal: the remapped memory has them all
+        let map = problem.remove_item(3);
+        let remapped = memory.remap(map.old_indices());
+        assert_eq!(remapped.width(), 11);
+        for old in memory.samples.iter() {
+            let carried = map.carry_over_sample(&problem, old).expect("still legal");
+            assert!(remapped
+                .samples
+                .iter()
+                .any(|new| new.bytes == carried.bytes));
+        }
+
+        // A new item is unknown in every remapped sample, the other decisions aren't
+        let map = problem.add_item(1 as ScoreType);
+        let inserted = (0..map.new_size()).find(|&index| map.old_index(index).is_none());
+        let remapped = remapped.remap(map.old_indices());
+        for sample in remapped.samples.iter() {
+            let known = sample.known.as_ref().expect("partial");
+            assert!((0..12).all(|index| get_bit(known, index) != (Some(index) == inserted)));
+        }
+    }
+}
@@ -0,0 +1,746 @@
+/// # Distributed Solving (Coordinator and Workers)
+///
+/// One search tree, many processes: the coordinator splits the tree into subtrees --
+/// solutions whose first few decisions are already made ("prefixes") -- and farms them out
+/// to workers. Before each subtree, a worker gets the best solution found so far anywhere
+/// (the "incumbent"), so it can bound its own search with it -- and whenever the incumbent
+/// improves, the busy workers get it again, in the middle of their searches.
+/// A worker which fails (hangs up, sends nonsense) is dropped, and its subtree goes back
+/// into the queue for the others.
+///
+/// Coordinator and workers talk over a `Channel`: either an in-process `MpscChannel`
+/// (threads) or a `TcpChannel` (processes, machines). The messages are simple lines of text,
+/// and solutions are sent as their decisions ('1', '0', or '?' for open).
+///
+/// Note: Only solvers which really search the subtree below the root they are given
+/// (i.e. which use `start_search_from`'s root), such as `DepthFirstSolver` and
+/// `BestFirstSolver`, make sense as workers.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{run_coordinator, run_worker, MpscChannel};
+/// use mhd_optimization::optimizer::{Problem, Solution, Solver};
+/// use mhd_optimization::implementations::{DepthFirstSolver, Problem01Knapsack};
+/// use std::time::Duration;
+///
+/// let knapsack = Problem01Knapsack::random(12);
+/// let time_limit = Duration::from_secs(1);
+///
+/// let (coordinator_end, worker_end) = MpscChannel::pair();
+/// let worker_knapsack = knapsack.clone();
+/// let worker = std::thread::spawn(move || {
+///     let mut solver = DepthFirstSolver::new(12);
+///     run_worker(&worker_knapsack, &mut solver, worker_end, time_limit)
+/// });
+///
+/// let best = run_coordinator(&knapsack, vec![coordinator_end], 3)
+///     .expect("coordinator failed");
+/// assert!(0 < worker.join().unwrap().expect("worker failed"));
+/// assert!(knapsack.solution_is_complete(&best));
+/// ```
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::*;
+
+use mhd_memory::ScoreType;
+use optimizer::{Problem, Solution, Solver, StepEvent};
+
+/// ## The Messages
+///
+/// Solutions travel as strings of decisions (see `decisions_of`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// Coordinator to worker: the best (complete) solution found so far.
+    Incumbent(ScoreType, String),
+    /// Coordinator to worker: please search the subtree below this (incomplete) solution.
+    Work(usize, String),
+    /// Worker to coordinator: the best solution found in subtree `id` (score, decisions).
+    Result(usize, ScoreType, String),
+    /// Coordinator to worker: no more work, please quit.
+    Done,
+}
+
+impl Message {
+    /// One line of text (without the newline).
+    pub fn to_line(&self) -> String {
+        match self {
+            Message::Incumbent(score, decisions) => format!("BEST {} {}", score, decisions),
+            Message::Work(id, decisions) => format!("WORK {} {}", id, decisions),
+            Message::Result(id, score, decisions) => {
+                format!("RESULT {} {} {}", id, score, decisions)
+            }
+            Message::Done => "DONE".to_string(),
+        }
+    }
+
+    /// Parse one line of text, as written by `to_line`.
+    pub fn from_line(line: &str) -> io::Result<Self> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let number = |index: usize| -> io::Result<usize> {
+            tokens
+                .get(index)
+                .and_then(|token| token.parse().ok())
+                .ok_or_else(|| invalid_data(format!("Expected a number in message '{}'", line)))
+        };
+        let score = |index: usize| -> io::Result<ScoreType> {
+            tokens
+                .get(index)
+                .and_then(|token| token.parse().ok())
+                .ok_or_else(|| invalid_data(format!("Expected a score in message '{}'", line)))
+        };
+        let text = |index: usize| -> io::Result<String> {
+            tokens
+                .get(index)
+                .map(|token| token.to_string())
+                .ok_or_else(|| invalid_data(format!("Expected decisions in message '{}'", line)))
+        };
+        match tokens.first() {
+            Some(&"BEST") => Ok(Message::Incumbent(score(1)?, text(2)?)),
+            Some(&"WORK") => Ok(Message::Work(number(1)?, text(2)?)),
+            Some(&"RESULT") => Ok(Message::Result(number(1)?, score(2)?, text(3)?)),
+            Some(&"DONE") => Ok(Message::Done),
+            _ => Err(invalid_data(format!("Unknown message '{}'", line))),
+        }
+    }
+} // end impl Message
+
+fn invalid_data(text: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, text)
+}
+
+/// ## The Channel Trait
+///
+/// How coordinator and workers exchange messages. Both ends of a channel are `Channel`s.
+pub trait Channel {
+    fn send(&mut self, message: &Message) -> io::Result<()>;
+
+    /// Blocks until the next message arrives.
+    fn receive(&mut self) -> io::Result<Message>;
+
+    /// The next message, if one has arrived already (`None` otherwise, without blocking).
+    fn try_receive(&mut self) -> io::Result<Option<Message>>;
+}
+
+fn hung_up() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "Other end hung up")
+}
+
+/// A channel between threads of one process.
+#[derive(Debug)]
+pub struct MpscChannel {
+    sender: Sender<Message>,
+    receiver: Receiver<Message>,
+}
+
+impl MpscChannel {
+    /// Both ends of a new channel: one for the coordinator, one for a worker.
+    pub fn pair() -> (Self, Self) {
+        let (to_worker, from_coordinator) = channel();
+        let (to_coordinator, from_worker) = channel();
+        (
+            Self {
+                sender: to_worker,
+                receiver: from_worker,
+            },
+            Self {
+                sender: to_coordinator,
+                receiver: from_coordinator,
+            },
+        )
+    }
+}
+
+impl Channel for MpscChannel {
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        self.sender.send(message.clone()).map_err(|_| hung_up())
+    }
+
+    fn receive(&mut self) -> io::Result<Message> {
+        self.receiver.recv().map_err(|_| hung_up())
+    }
+
+    fn try_receive(&mut self) -> io::Result<Option<Message>> {
+        match self.receiver.try_recv() {
+            Ok(message) => Ok(Some(message)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(hung_up()),
+        }
+    }
+}
+
+/// A channel over TCP, one message per line.
+#[derive(Debug)]
+pub struct TcpChannel {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl TcpChannel {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+}
+
+impl Channel for TcpChannel {
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        writeln!(self.writer, "{}", message.to_line())?;
+        self.writer.flush()
+    }
+
+    fn receive(&mut self) -> io::Result<Message> {
+        let mut line = String::new();
+        if 0 == self.reader.read_line(&mut line)? {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Connection closed",
+            ));
+        };
+        Message::from_line(line.trim())
+    }
+
+    // Only peeks without blocking: once the first bytes of a line are there,
+    // the rest of it (written in one go, see `send`) is read as usual.
+    fn try_receive(&mut self) -> io::Result<Option<Message>> {
+        if self.reader.buffer().is_empty() {
+            self.reader.get_ref().set_nonblocking(true)?;
+            let arrived = self.reader.fill_buf().map(|bytes| !bytes.is_empty());
+            self.reader.get_ref().set_nonblocking(false)?;
+            match arrived {
+                Ok(true) => {}
+                Ok(false) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Connection closed",
+                    ))
+                }
+                Err(ref error) if io::ErrorKind::WouldBlock == error.kind() => return Ok(None),
+                Err(error) => return Err(error),
+            };
+        };
+        self.receive().map(Some)
+    }
+}
+
+/*******************************************************************************/
+// Solutions <-> Strings
+
+/// The decisions of `solution` as a string: '1' (true), '0' (false) or '?' (open).
+pub fn decisions_of<Sol: Solution>(solution: &Sol) -> String {
+    (0..solution.size())
+        .map(|index| match solution.get_decision(index) {
+            Some(true) => '1',
+            Some(false) => '0',
+            None => '?',
+        })
+        .collect()
+}
+
+/// Rebuild a solution from its decisions (see `decisions_of`), by making them one by one
+/// (and applying the problem's rules on the way) -- so scores etc. are all correct.
+pub fn solution_from_decisions<Prob: Problem>(
+    problem: &Prob,
+    decisions: &str,
+) -> io::Result<Prob::Sol> {
+    if decisions.len() != problem.problem_size() {
+        return Err(invalid_data(format!(
+            "Expected {} decisions, got {}",
+            problem.problem_size(),
+            decisions.len()
+        )));
+    };
+    let mut result = problem.starting_solution();
+    for (index, code) in decisions.chars().enumerate() {
+        let decision = match code {
+            '1' => true,
+            '0' => false,
+            _ => continue,
+        };
+        if result.get_decision(index).is_none() {
+            result.make_decision(index, decision);
+            problem.apply_rules(&mut result);
+        };
+    } // end for all decisions
+    if decisions_of(&result) != decisions || !problem.solution_is_legal(&result) {
+        return Err(invalid_data(format!(
+            "Decisions {} are not a legal solution of {}",
+            decisions,
+            problem.short_description()
+        )));
+    };
+    Ok(result)
+}
+
+/// Split the search tree into subtrees: expand the tree breadth first, `depth` levels deep.
+/// Returns the roots of the subtrees (at most 2^depth of them).
+pub fn split_search_tree<Prob: Problem>(problem: &Prob, depth: usize) -> Vec<Prob::Sol> {
+    let mut frontier = vec![problem.starting_solution()];
+    for _ in 0..depth {
+        let mut next_frontier = Vec::new();
+        for solution in frontier {
+            if problem.solution_is_complete(&solution) {
+                next_frontier.push(solution);
+            } else {
+                next_frontier.extend(problem.children_of_solution(&solution));
+            };
+        } // end for all solutions in frontier
+        frontier = next_frontier;
+    } // end for all levels
+    frontier
+}
+
+/*******************************************************************************/
+/// ## The Coordinator
+///
+/// Split the search tree into subtrees (`depth` decisions deep), hand them out to the workers
+/// at the other ends of `channels`, collect their results, and return the best solution.
+/// Every new incumbent goes out to all busy workers at once; a worker which fails is
+/// dropped and its subtree handed to another one. Fails only if every worker did.
+/// Every (remaining) worker gets a `Done` at the end.
+pub fn run_coordinator<Prob, Chan>(
+    problem: &Prob,
+    channels: Vec<Chan>,
+    depth: usize,
+) -> io::Result<Prob::Sol>
+where
+    Prob: Problem,
+    Chan: Channel,
+{
+    let mut queue: Vec<(usize, String)> = split_search_tree(problem, depth)
+        .iter()
+        .map(decisions_of)
+        .enumerate()
+        .collect();
+    info!(
+        "Coordinator: {} subtrees for {} workers, problem {}",
+        queue.len(),
+        channels.len(),
+        problem.short_description()
+    );
+    let mut incumbent = problem.random_solution();
+    let mut workers: Vec<Worker<Chan>> = channels
+        .into_iter()
+        .map(|channel| Worker {
+            channel,
+            work: None,
+            failed: false,
+        })
+        .collect();
+
+    loop {
+        // Hand out subtrees to the idle workers
+        for worker in workers.iter_mut().filter(|worker| worker.work.is_none()) {
+            let (id, root) = match queue.pop() {
+                None => break,
+                Some(work) => work,
+            };
+            worker.work = Some((id, root.clone()));
+            let sent = worker
+                .channel
+                .send(&Message::Incumbent(
+                    incumbent.get_score(),
+                    decisions_of(&incumbent),
+                ))
+                .and_then(|_| worker.channel.send(&Message::Work(id, root)));
+            if let Err(error) = sent {
+                worker.fail(&mut queue, error);
+            };
+        } // end for all idle workers
+        workers.retain(|worker| !worker.failed);
+        if workers.iter().all(|worker| worker.work.is_none()) {
+            if queue.is_empty() {
+                break;
+            };
+            if workers.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    format!("All workers failed, {} subtrees left", queue.len()),
+                ));
+            };
+            continue;
+        };
+
+        // Collect the results which have arrived
+        let mut improved = false;
+        let mut any_news = false;
+        for worker in workers.iter_mut().filter(|worker| worker.work.is_some()) {
+            let id = worker.work.as_ref().map_or(0, |work| work.0);
+            let solution = match worker.channel.try_receive() {
+                Ok(None) => continue,
+                Ok(Some(Message::Result(result_id, score, decisions))) if result_id == id => {
+                    debug!("Coordinator: subtree {} has best score {}", id, score);
+                    solution_from_decisions(problem, &decisions)
+                }
+                Ok(Some(other)) => Err(invalid_data(format!(
+                    "Expected result of subtree {}, got {:?}",
+                    id, other
+                ))),
+                Err(error) => Err(error),
+            };
+            any_news = true;
+            match solution {
+                Ok(solution) => {
+                    worker.work = None;
+                    if problem.solution_is_complete(&solution)
+                        && problem.better_than(&solution, &incumbent)
+                    {
+                        info!("Coordinator: new incumbent {}", solution.get_score());
+                        incumbent = solution;
+                        improved = true;
+                    };
+                }
+                Err(error) => worker.fail(&mut queue, error),
+            }; // end match answer
+        } // end for all busy workers
+
+        // ... and tell the others about a new incumbent
+        if improved {
+            let message = Message::Incumbent(incumbent.get_score(), decisions_of(&incumbent));
+            for worker in workers.iter_mut().filter(|worker| worker.work.is_some()) {
+                if let Err(error) = worker.channel.send(&message) {
+                    worker.fail(&mut queue, error);
+                };
+            } // end for all busy workers
+        };
+        if !any_news {
+            thread::sleep(POLL_INTERVAL);
+        };
+    } // end loop
+
+    for worker in workers.iter_mut() {
+        if let Err(error) = worker.channel.send(&Message::Done) {
+            warn!("Coordinator: could not say goodbye to a worker: {}", error);
+        };
+    } // end for all workers
+    Ok(incumbent)
+} // end run_coordinator
+
+// How long the coordinator (and a busy worker) waits before looking for messages again
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// The coordinator's end of one worker, and what it is working on (if anything)
+struct Worker<Chan> {
+    channel: Chan,
+    work: Option<(usize, String)>,
+    failed: bool,
+}
+
+impl<Chan> Worker<Chan> {
+    // Give up on this worker, but not on its subtree
+    fn fail(&mut self, queue: &mut Vec<(usize, String)>, error: io::Error) {
+        warn!("Coordinator: worker failed ({}), dropping it", error);
+        if let Some(work) = self.work.take() {
+            queue.push(work);
+        };
+        self.failed = true;
+    }
+}
+
+/// ## The Worker
+///
+/// Search every subtree the coordinator sends (bounded by the incumbent it sent before,
+/// and by every better one it sends during the search),
+/// with `solver`, until the coordinator says `Done`. Returns the number of subtrees searched.
+/// As in `find_best_solution`, the search of one subtree stops when no better solution
+/// was found for `time_limit`.
+pub fn run_worker<Sol, Prob, Solv, Chan>(
+    problem: &Prob,
+    solver: &mut Solv,
+    mut channel: Chan,
+    time_limit: Duration,
+) -> io::Result<usize>
+where
+    Sol: Solution,
+    Prob: Problem<Sol = Sol>,
+    Solv: Solver<Sol>,
+    Chan: Channel,
+{
+    let mut incumbent: Option<Sol> = None;
+    let mut num_subtrees: usize = 0;
+    loop {
+        match channel.receive()? {
+            Message::Incumbent(_, decisions) => {
+                incumbent = Some(solution_from_decisions(problem, &decisions)?);
+            }
+            Message::Work(id, decisions) => {
+                let root = solution_from_decisions(problem, &decisions)?;
+                solver.clear();
+                solver.start_search_from(problem, root);
+                if let Some(best) = incumbent.take() {
+                    // bound the search with the best solution found anywhere else
+                    if problem.better_than(&best, solver.best_solution()) {
+                        solver.store_best_solution(best);
+                    };
+                };
+                let mut start_time = Instant::now();
+                let mut poll_time = Instant::now();
+                loop {
+                    match solver.step(problem).event {
+                        StepEvent::Finished => break,
+                        StepEvent::NewBest => start_time = Instant::now(),
+                        _ => {}
+                    };
+                    if solver.is_finished() || time_limit < start_time.elapsed() {
+                        break;
+                    };
+                    if POLL_INTERVAL < poll_time.elapsed() {
+                        poll_time = Instant::now();
+                        match channel.try_receive()? {
+                            None => {}
+                            Some(Message::Incumbent(_, decisions)) => {
+                                let best = solution_from_decisions(problem, &decisions)?;
+                                if problem.better_than(&best, solver.best_solution()) {
+                                    debug!("Worker: new incumbent {}", best.get_score());
+                                    solver.store_best_solution(best);
+                                };
+                            }
+                            Some(other) => {
+                                return Err(invalid_data(format!(
+                                    "Worker did not expect {:?} during a search",
+                                    other
+                                )))
+                            }
+                        }; // end match message
+                    };
+                } // end search loop
+                let best = solver.best_solution();
+                debug!("Worker: subtree {} has best score {}", id, best.get_score());
+                channel.send(&Message::Result(id, best.get_score(), decisions_of(best)))?;
+                num_subtrees += 1;
+            }
+            Message::Done => return Ok(num_subtrees),
+            other => return Err(invalid_data(format!("Worker did not expect {:?}", other))),
+        }; // end match message
+    } // end loop
+} // end run_worker
+
+///////////////////// TESTs for distributed solving /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{BestFirstSolver, DepthFirstSolver, Problem01Knapsack};
+    use std::net::TcpListener;
+
+    const NUM_DECISIONS: usize = 12;
+
+    #[test]
+    fn test_message_lines() {
+        let messages = vec![
+            Message::Incumbent(42 as ScoreType, "10?1".to_string()),
+            Message::Work(7, "1???".to_string()),
+            Message::Result(7, 99 as ScoreType, "1001".to_string()),
+            Message::Done,
+        ];
+        for message in messages {
+            assert_eq!(message, Message::from_line(&message.to_line()).unwrap());
+        }
+        assert!(Message::from_line("HELLO").is_err());
+        assert!(Message::from_line("WORK seven 1???").is_err());
+        assert!(Message::from_line("BEST lots 1001").is_err());
+    }
+
+    #[test]
+    fn test_try_receive() {
+        let (mut coordinator_end, mut worker_end) = MpscChannel::pair();
+        assert_eq!(worker_end.try_receive().unwrap(), None);
+        coordinator_end.send(&Message::Done).unwrap();
+        assert_eq!(worker_end.try_receive().unwrap(), Some(Message::Done));
+        drop(coordinator_end);
+        assert!(worker_end.try_receive().is_err());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut worker_end =
+            TcpChannel::new(TcpStream::connect(listener.local_addr().unwrap()).unwrap()).unwrap();
+        let mut coordinator_end = TcpChannel::new(listener.accept().unwrap().0).unwrap();
+        assert_eq!(worker_end.try_receive().unwrap(), None);
+        coordinator_end.send(&Message::Done).unwrap();
+        let message = loop {
+            if let Some(message) = worker_end.try_receive().unwrap() {
+                break message;
+            };
+        };
+        assert_eq!(message, Message::Done);
+        // Still blocking as usual, too
+        coordinator_end
+            .send(&Message::Work(1, "1?".to_string()))
+            .unwrap();
+        assert_eq!(
+            worker_end.receive().unwrap(),
+            Message::Work(1, "1?".to_string())
+        );
+    }
+
+    #[test]
+    fn test_decisions_round_trip() {
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        for solution in split_search_tree(&knapsack, 3)
+            .into_iter()
+            .chain(vec![knapsack.random_solution()])
+        {
+            let decisions = decisions_of(&solution);
+            let copy = solution_from_decisions(&knapsack, &decisions).unwrap();
+            assert_eq!(decisions, decisions_of(&copy));
+            assert_eq!(solution.get_score(), copy.get_score());
+        }
+        assert!(solution_from_decisions(&knapsack, "1").is_err());
+    }
+
+    #[test]
+    fn test_split_search_tree() {
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        assert_eq!(1, split_search_tree(&knapsack, 0).len());
+        let subtrees = split_search_tree(&knapsack, 3);
+        assert!(!subtrees.is_empty() && subtrees.len() <= 8);
+        for root in subtrees {
+            assert!(knapsack.solution_is_legal(&root));
+        }
+    }
+
+    #[test]
+    fn test_distributed_with_threads() {
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let time_limit = Duration::from_secs(1);
+
+        let mut coordinator_ends = Vec::new();
+        let mut workers = Vec::new();
+        for _ in 0..3 {
+            let (coordinator_end, worker_end) = MpscChannel::pair();
+            coordinator_ends.push(coordinator_end);
+            let worker_knapsack = knapsack.clone();
+            workers.push(thread::spawn(move || {
+                let mut solver = BestFirstSolver::new(NUM_DECISIONS);
+                run_worker(&worker_knapsack, &mut solver, worker_end, time_limit)
+            }));
+        }
+        let best = run_coordinator(&knapsack, coordinator_ends, 4).unwrap();
+        let num_searched: usize = workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap().unwrap())
+            .sum();
+        assert_eq!(num_searched, split_search_tree(&knapsack, 4).len());
+
+        // Same as a single depth first search?
+        let mut solver = DepthFirstSolver::new(NUM_DECISIONS);
+        let single = solver.find_best_solution(&knapsack, time_limit).unwrap();
+        assert!(knapsack.solution_is_complete(&best));
+        assert_eq!(best.get_score(), single.get_score());
+    }
+
+    #[test]
+    fn test_failed_worker() {
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let time_limit = Duration::from_secs(1);
+
+        // One worker hangs up before it even starts, the other one has to do all the work
+        let (failing_end, worker_end) = MpscChannel::pair();
+        drop(worker_end);
+        let (coordinator_end, worker_end) = MpscChannel::pair();
+        let worker_knapsack = knapsack.clone();
+        let worker = thread::spawn(move || {
+            let mut solver = DepthFirstSolver::new(NUM_DECISIONS);
+            run_worker(&worker_knapsack, &mut solver, worker_end, time_limit)
+        });
+        let best = run_coordinator(&knapsack, vec![failing_end, coordinator_end], 3).unwrap();
+        assert_eq!(
+            worker.join().unwrap().unwrap(),
+            split_search_tree(&knapsack, 3).len()
+        );
+        let mut solver = DepthFirstSolver::new(NUM_DECISIONS);
+        let single = solver.find_best_solution(&knapsack, time_limit).unwrap();
+        assert_eq!(best.get_score(), single.get_score());
+
+        // Without any (working) worker, the coordinator gives up
+        let (failing_end, worker_end) = MpscChannel::pair();
+        drop(worker_end);
+        assert!(run_coordinator(&knapsack, vec![failing_end], 3).is_err());
+    }
+
+    #[test]
+    fn test_incumbent_broadcast() {
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let mut solver = DepthFirstSolver::new(NUM_DECISIONS);
+        let optimum = solver
+            .find_best_solution(&knapsack, Duration::from_secs(1))
+            .unwrap()
+            .into_best();
+
+        // Two scripted workers: the first one "finds" the optimum right away...
+        let (coordinator_end, mut finder) = MpscChannel::pair();
+        let (other_end, mut waiter) = MpscChannel::pair();
+        let (score, decisions) = (optimum.get_score(), decisions_of(&optimum));
+        let finder = thread::spawn(move || {
+            let first_incumbent = match finder.receive().unwrap() {
+                Message::Incumbent(score, _) => score,
+                other => panic!("expected an incumbent, got {:?}", other),
+            };
+            let id = match finder.receive().unwrap() {
+                Message::Work(id, _) => id,
+                other => panic!("expected work, got {:?}", other),
+            };
+            finder.send(&Message::Result(id, score, decisions)).unwrap();
+            assert_eq!(finder.receive().unwrap(), Message::Done);
+            first_incumbent
+        });
+        // ... and the second one, still busy, hears about it (unless it was known before)
+        let waiter = thread::spawn(move || {
+            let first_incumbent = match waiter.receive().unwrap() {
+                Message::Incumbent(score, _) => score,
+                other => panic!("expected an incumbent, got {:?}", other),
+            };
+            let (id, root) = match waiter.receive().unwrap() {
+                Message::Work(id, root) => (id, root),
+                other => panic!("expected work, got {:?}", other),
+            };
+            let news = if first_incumbent < score {
+                Some(waiter.receive().unwrap())
+            } else {
+                None // nothing new to hear about
+            };
+            waiter
+                .send(&Message::Result(id, first_incumbent, root))
+                .unwrap();
+            news
+        });
+
+        let best = run_coordinator(&knapsack, vec![coordinator_end, other_end], 1).unwrap();
+        assert_eq!(best.get_score(), optimum.get_score());
+        assert!(finder.join().unwrap() <= best.get_score());
+        if let Some(news) = waiter.join().unwrap() {
+            assert_eq!(
+                news,
+                Message::Incumbent(best.get_score(), decisions_of(&best))
+            );
+        };
+    }
+
+    #[test]
+    fn test_distributed_over_tcp() {
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let worker_knapsack = knapsack.clone();
+        let worker = thread::spawn(move || {
+            let channel = TcpChannel::new(TcpStream::connect(address).unwrap()).unwrap();
+            let mut solver = DepthFirstSolver::new(NUM_DECISIONS);
+            run_worker(
+                &worker_knapsack,
+                &mut solver,
+                channel,
+                Duration::from_secs(1),
+            )
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let channels = vec![TcpChannel::new(stream).unwrap()];
+        let best = run_coordinator(&knapsack, channels, 2).unwrap();
+        assert!(0 < worker.join().unwrap().unwrap());
+        assert!(knapsack.solution_is_complete(&best));
+        assert!(knapsack.solution_is_legal(&best));
+    }
+}
@@ -0,0 +1,671 @@
+/// # Choosing Solvers and Problems at Run Time
+///
+/// The `Solver` and `Problem` traits are generic (and not object safe): there is no
+/// `Box<dyn Solver>`, and no `Box<dyn Problem>`. An application which reads its solver and
+/// problem from a configuration file would have to match every combination. Instead, two
+/// adapters erase the types (behind a box):
+///
+/// * a `DynSolver<Prob>` is any solver for problems of type `Prob`, with the searches of the
+///   `Solver` trait (`find_best_solution` and friends) -- see `SolverKind::dyn_solver`
+///   for a solver by name;
+/// * a `DynProblem<Sol>` is any problem with solutions of type `Sol`, and a `Problem` itself,
+///   so every solver can solve it (e.g. a `ProblemSubsetSum`, or one of the application's
+///   own problems with `MinimalSolution`s, whichever the configuration file asks for).
+///
+/// The price is a virtual call per method. (And `DynProblem::new`, with no type to make
+/// a problem of, only makes an empty placeholder -- `DynProblem::wrap` a real problem.)
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{DynProblem, DynSolver, Problem, Solution};
+/// use mhd_optimization::implementations::{ProblemSubsetSum, SolverKind};
+/// use std::time::Duration;
+///
+/// let problem = DynProblem::wrap(ProblemSubsetSum::random(12));
+/// for name in ["dfs", "bfs", "mhd"].iter() {
+///     let kind: SolverKind = name.parse().expect("known solver");
+///     let mut solver: DynSolver<_> = kind.dyn_solver(&problem);
+///     let best = solver.find_best_solution(&problem, Duration::from_millis(100)).unwrap();
+///     assert!(problem.solution_is_legal(&best));
+///     assert_eq!(solver.best_score(), best.get_score());
+/// }
+/// ```
+use std::fmt;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use mhd_memory::{Sample, ScoreType, ZERO_SCORE};
+use optimizer::SolverConfig;
+use optimizer::{MemoryUsage, MhdError, Problem, SearchObserver, Solution, SolveReport, Solver};
+
+/**************************************************************************************/
+// The object safe part of the `Solver` trait, for one type of problem
+
+trait SolverObject<Prob: Problem> {
+    fn name(&self) -> &'static str;
+    fn short_description(&self) -> String;
+    fn memory_usage(&self) -> MemoryUsage;
+    fn is_exhaustive(&self) -> bool;
+    fn clear(&mut self);
+    fn reseed(&mut self, seed: u64);
+    fn best_solution(&self) -> &Prob::Sol;
+    fn best_score(&self) -> ScoreType;
+    fn find_best_solution(
+        &mut self,
+        problem: &Prob,
+        config: SolverConfig,
+    ) -> Result<SolveReport<Prob::Sol>, MhdError>;
+    fn find_best_solution_observed(
+        &mut self,
+        problem: &Prob,
+        config: SolverConfig,
+        observer: &mut dyn SearchObserver<Prob::Sol>,
+    ) -> Result<SolveReport<Prob::Sol>, MhdError>;
+    fn find_best_solution_from(
+        &mut self,
+        problem: &Prob,
+        incumbent: Prob::Sol,
+        config: SolverConfig,
+    ) -> Result<SolveReport<Prob::Sol>, MhdError>;
+    fn find_target_solution(
+        &mut self,
+        problem: &Prob,
+        config: SolverConfig,
+        target: ScoreType,
+    ) -> Result<(Prob::Sol, Option<Duration>), MhdError>;
+}
+
+impl<Prob: Problem, S: Solver<Prob::Sol>> SolverObject<Prob> for S {
+    fn name(&self) -> &'static str {
+        Solver::name(self)
+    }
+    fn short_description(&self) -> String {
+        Solver::short_description(self)
+    }
+    fn memory_usage(&self) -> MemoryUsage {
+        Solver::memory_usage(self)
+    }
+    fn is_exhaustive(&self) -> bool {
+        Solver::is_exhaustive(self)
+    }
+    fn clear(&mut self) {
+        Solver::clear(self)
+    }
+    fn reseed(&mut self, seed: u64) {
+        Solver::reseed(self, seed)
+    }
+    fn best_solution(&self) -> &Prob::Sol {
+        Solver::best_solution(self)
+    }
+    fn best_score(&self) -> ScoreType {
+        Solver::best_score(self)
+    }
+    fn find_best_solution(
+        &mut self,
+        problem: &Prob,
+        config: SolverConfig,
+    ) -> Result<SolveReport<Prob::Sol>, MhdError> {
+        Solver::find_best_solution(self, problem, config)
+    }
+    fn find_best_solution_observed(
+        &mut self,
+        problem: &Prob,
+        config: SolverConfig,
+        observer: &mut dyn SearchObserver<Prob::Sol>,
+    ) -> Result<SolveReport<Prob::Sol>, MhdError> {
+        Solver::find_best_solution_observed(self, problem, config, observer)
+    }
+    fn find_best_solution_from(
+        &mut self,
+        problem: &Prob,
+        incumbent: Prob::Sol,
+        config: SolverConfig,
+    ) -> Result<SolveReport<Prob::Sol>, MhdError> {
+        Solver::find_best_solution_from(self, problem, incumbent, config)
+    }
+    fn find_target_solution(
+        &mut self,
+        problem: &Prob,
+        config: SolverConfig,
+        target: ScoreType,
+    ) -> Result<(Prob::Sol, Option<Duration>), MhdError> {
+        Solver::find_target_solution(self, problem, config, target)
+    }
+} // end impl SolverObject for every Solver
+
+/// A solver, any solver, for problems of type `Prob`, chosen at run time
+pub struct DynSolver<Prob: Problem> {
+    solver: Box<dyn SolverObject<Prob>>,
+}
+
+impl<Prob: Problem> DynSolver<Prob> {
+    /// Erase the type of `solver`
+    pub fn wrap<S: Solver<Prob::Sol> + 'static>(solver: S) -> Self {
+        Self {
+            solver: Box::new(solver),
+        }
+    }
+
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        self.solver.name()
+    }
+
+    #[inline]
+    pub fn short_description(&self) -> String {
+        self.solver.short_description()
+    }
+
+    #[inline]
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.solver.memory_usage()
+    }
+
+    /// See `Solver::is_exhaustive`
+    #[inline]
+    pub fn is_exhaustive(&self) -> bool {
+        self.solver.is_exhaustive()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.solver.clear()
+    }
+
+    #[inline]
+    pub fn reseed(&mut self, seed: u64) {
+        self.solver.reseed(seed)
+    }
+
+    #[inline]
+    pub fn best_solution(&self) -> &Prob::Sol {
+        self.solver.best_solution()
+    }
+
+    #[inline]
+    pub fn best_score(&self) -> ScoreType {
+        self.solver.best_score()
+    }
+
+    /// See `Solver::find_best_solution`
+    pub fn find_best_solution<C: Into<SolverConfig>>(
+        &mut self,
+        problem: &Prob,
+        config: C,
+    ) -> Result<SolveReport<Prob::Sol>, MhdError> {
+        self.solver.find_best_solution(problem, config.into())
+    }
+
+    /// See `Solver::find_best_solution_observed`
+    pub fn find_best_solution_observed<C: Into<SolverConfig>>(
+        &mut self,
+        problem: &Prob,
+        config: C,
+        observer: &mut dyn SearchObserver<Prob::Sol>,
+    ) -> Result<SolveReport<Prob::Sol>, MhdError> {
+        self.solver
+            .find_best_solution_observed(problem, config.into(), observer)
+    }
+
+    /// See `Solver::find_best_solution_from`
+    pub fn find_best_solution_from<C: Into<SolverConfig>>(
+        &mut self,
+        problem: &Prob,
+        incumbent: Prob::Sol,
+        config: C,
+    ) -> Result<SolveReport<Prob::Sol>, MhdError> {
+        self.solver
+            .find_best_solution_from(problem, incumbent, config.into())
+    }
+
+    /// See `Solver::find_target_solution`
+    pub fn find_target_solution<C: Into<SolverConfig>>(
+        &mut self,
+        problem: &Prob,
+        config: C,
+        target: ScoreType,
+    ) -> Result<(Prob::Sol, Option<Duration>), MhdError> {
+        self.solver
+            .find_target_solution(problem, config.into(), target)
+    }
+} // end impl DynSolver
+
+impl<Prob: Problem> fmt::Debug for DynSolver<Prob> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DynSolver({})", self.short_description())
+    }
+}
+
+/**************************************************************************************/
+// The object safe part of the `Problem` trait, for one type of solution
+
+trait ProblemObject<Sol>: fmt::Debug + Send + Sync {
+    fn box_clone(&self) -> Box<dyn ProblemObject<Sol>>;
+    fn name(&self) -> &'static str;
+    fn short_description(&self) -> String;
+    fn problem_size(&self) -> usize;
+    fn randomize(&mut self);
+    fn is_legal(&self) -> bool;
+    fn solution_score(&self, solution: &Sol) -> ScoreType;
+    fn solution_best_score(&self, solution: &Sol) -> ScoreType;
+    fn fix_scores(&self, solution: &mut Sol);
+    fn solution_is_legal(&self, solution: &Sol) -> bool;
+    fn solution_is_complete(&self, solution: &Sol) -> bool;
+    fn random_solution(&self) -> Sol;
+    fn starting_solution(&self) -> Sol;
+    fn better_than(&self, new_solution: &Sol, old_solution: &Sol) -> bool;
+    fn can_be_better_than(&self, new_solution: &Sol, old_solution: &Sol) -> bool;
+    fn first_open_decision(&self, solution: &Sol) -> Option<usize>;
+    fn last_closed_decision(&self, solution: &Sol) -> Option<usize>;
+    fn apply_rules(&self, solution: &mut Sol);
+    fn rules_audit_passed(&self, solution: &Sol) -> bool;
+    fn produce_child(&self, parent: &Sol, index: usize, decision: bool) -> Sol;
+    fn children_of_solution(&self, parent: &Sol) -> Vec<Sol>;
+    fn parallel_children(&self) -> bool;
+    fn random_completion(&self, solution: &Sol, index: usize, decision: bool) -> Sol;
+    fn greedy_decision(&self, solution: &Sol, index: usize) -> bool;
+    fn greedy_order(&self) -> Vec<usize>;
+    fn transposition_key(&self, solution: &Sol) -> u64;
+    fn sample_from_solution(&self, solution: &Sol) -> Sample;
+    fn solution_from_sample(&self, sample: &Sample) -> Sol;
+}
+
+impl<Prob: Problem + Send + 'static> ProblemObject<Prob::Sol> for Prob {
+    fn box_clone(&self) -> Box<dyn ProblemObject<Prob::Sol>> {
+        Box::new(self.clone())
+    }
+    fn name(&self) -> &'static str {
+        Problem::name(self)
+    }
+    fn short_description(&self) -> String {
+        Problem::short_description(self)
+    }
+    fn problem_size(&self) -> usize {
+        Problem::problem_size(self)
+    }
+    fn randomize(&mut self) {
+        Problem::randomize(self)
+    }
+    fn is_legal(&self) -> bool {
+        Problem::is_legal(self)
+    }
+    fn solution_score(&self, solution: &Prob::Sol) -> ScoreType {
+        Problem::solution_score(self, solution)
+    }
+    fn solution_best_score(&self, solution: &Prob::Sol) -> ScoreType {
+        Problem::solution_best_score(self, solution)
+    }
+    fn fix_scores(&self, solution: &mut Prob::Sol) {
+        Problem::fix_scores(self, solution)
+    }
+    fn solution_is_legal(&self, solution: &Prob::Sol) -> bool {
+        Problem::solution_is_legal(self, solution)
+    }
+    fn solution_is_complete(&self, solution: &Prob::Sol) -> bool {
+        Problem::solution_is_complete(self, solution)
+    }
+    fn random_solution(&self) -> Prob::Sol {
+        Problem::random_solution(self)
+    }
+    fn starting_solution(&self) -> Prob::Sol {
+        Problem::starting_solution(self)
+    }
+    fn better_than(&self, new_solution: &Prob::Sol, old_solution: &Prob::Sol) -> bool {
+        Problem::better_than(self, new_solution, old_solution)
+    }
+    fn can_be_better_than(&self, new_solution: &Prob::Sol, old_solution: &Prob::Sol) -> bool {
+        Problem::can_be_better_than(self, new_solution, old_solution)
+    }
+    fn first_open_decision(&self, solution: &Prob::Sol) -> Option<usize> {
+        Problem::first_open_decision(self, solution)
+    }
+    fn last_closed_decision(&self, solution: &Prob::Sol) -> Option<usize> {
+        Problem::last_closed_decision(self, solution)
+    }
+    fn apply_rules(&self, solution: &mut Prob::Sol) {
+        Problem::apply_rules(self, solution)
+    }
+    fn rules_audit_passed(&self, solution: &Prob::Sol) -> bool {
+        Problem::rules_audit_passed(self, solution)
+    }
+    fn produce_child(&self, parent: &Prob::Sol, index: usize, decision: bool) -> Prob::Sol {
+        Problem::produce_child(self, parent, index, decision)
+    }
+    fn children_of_solution(&self, parent: &Prob::Sol) -> Vec<Prob::Sol> {
+        Problem::children_of_solution(self, parent)
+    }
+    fn parallel_children(&self) -> bool {
+        Problem::parallel_children(self)
+    }
+    fn random_completion(&self, solution: &Prob::Sol, index: usize, decision: bool) -> Prob::Sol {
+        Problem::random_completion(self, solution, index, decision)
+    }
+    fn greedy_decision(&self, solution: &Prob::Sol, index: usize) -> bool {
+        Problem::greedy_decision(self, solution, index)
+    }
+    fn greedy_order(&self) -> Vec<usize> {
+        Problem::greedy_order(self)
+    }
+    fn transposition_key(&self, solution: &Prob::Sol) -> u64 {
+        Problem::transposition_key(self, solution)
+    }
+    fn sample_from_solution(&self, solution: &Prob::Sol) -> Sample {
+        Problem::sample_from_solution(self, solution)
+    }
+    fn solution_from_sample(&self, sample: &Sample) -> Prob::Sol {
+        Problem::solution_from_sample(self, sample)
+    }
+} // end impl ProblemObject for every Problem
+
+// What `DynProblem::new` makes: `size` decisions, but nothing to decide about --
+// every solution is legal, and every score is zero.
+#[derive(Debug, Clone)]
+struct EmptyProblem<Sol> {
+    size: usize,
+    solution_type: PhantomData<Sol>,
+}
+
+impl<Sol: Solution + Send + Sync> Problem for EmptyProblem<Sol> {
+    type Sol = Sol;
+
+    fn short_description(&self) -> String {
+        format!("EmptyProblem with {} decisions", self.size)
+    }
+
+    fn new(size: usize) -> Self {
+        Self {
+            size,
+            solution_type: PhantomData,
+        }
+    }
+
+    fn problem_size(&self) -> usize {
+        self.size
+    }
+
+    fn randomize(&mut self) {
+        // nothing to randomize
+    }
+
+    fn is_legal(&self) -> bool {
+        true
+    }
+
+    fn solution_score(&self, _: &Sol) -> ScoreType {
+        ZERO_SCORE
+    }
+
+    fn solution_best_score(&self, _: &Sol) -> ScoreType {
+        ZERO_SCORE
+    }
+
+    fn solution_is_legal(&self, _: &Sol) -> bool {
+        true
+    }
+
+    fn solution_is_complete(&self, solution: &Sol) -> bool {
+        Problem::first_open_decision(self, solution).is_none()
+    }
+
+    fn random_solution(&self) -> Sol {
+        let mut result = Sol::random(self.size);
+        Problem::fix_scores(self, &mut result);
+        result
+    }
+
+    fn starting_solution(&self) -> Sol {
+        Sol::new(self.size)
+    }
+
+    fn first_open_decision(&self, solution: &Sol) -> Option<usize> {
+        (0..self.size).find(|&index| solution.get_decision(index).is_none())
+    }
+
+    fn last_closed_decision(&self, solution: &Sol) -> Option<usize> {
+        (0..self.size)
+            .rev()
+            .find(|&index| solution.get_decision(index).is_some())
+    }
+
+    fn apply_rules(&self, _: &mut Sol) {
+        // no rules
+    }
+
+    fn rules_audit_passed(&self, _: &Sol) -> bool {
+        true
+    }
+} // end impl Problem for EmptyProblem
+
+/// A problem, any problem with solutions of type `Sol`, chosen at run time
+#[derive(Debug)]
+pub struct DynProblem<Sol> {
+    problem: Box<dyn ProblemObject<Sol>>,
+}
+
+impl<Sol: Solution + Send + Sync + 'static> DynProblem<Sol> {
+    /// Erase the type of `problem`
+    pub fn wrap<Prob: Problem<Sol = Sol> + Send + 'static>(problem: Prob) -> Self {
+        Self {
+            problem: Box::new(problem),
+        }
+    }
+}
+
+impl<Sol> Clone for DynProblem<Sol> {
+    fn clone(&self) -> Self {
+        Self {
+            problem: self.problem.box_clone(),
+        }
+    }
+}
+
+impl<Sol: Solution + Send + Sync + 'static> Problem for DynProblem<Sol> {
+    type Sol = Sol;
+
+    #[inline]
+    fn name(&self) -> &'static str {
+        self.problem.name()
+    }
+
+    #[inline]
+    fn short_description(&self) -> String {
+        self.problem.short_description()
+    }
+
+    /// There is no type to make a new problem of: this is an empty placeholder with `size`
+    /// decisions, where every solution is legal and scores zero (and so is `Problem::random`,
+    /// there is nothing to randomize). Use `DynProblem::wrap` for a real problem.
+    fn new(size: usize) -> Self {
+        Self::wrap(EmptyProblem::<Sol>::new(size))
+    }
+
+    #[inline]
+    fn problem_size(&self) -> usize {
+        self.problem.problem_size()
+    }
+
+    #[inline]
+    fn randomize(&mut self) {
+        self.problem.randomize()
+    }
+
+    #[inline]
+    fn is_legal(&self) -> bool {
+        self.problem.is_legal()
+    }
+
+    #[inline]
+    fn solution_score(&self, solution: &Sol) -> ScoreType {
+        self.problem.solution_score(solution)
+    }
+
+    #[inline]
+    fn solution_best_score(&self, solution: &Sol) -> ScoreType {
+        self.problem.solution_best_score(solution)
+    }
+
+    #[inline]
+    fn fix_scores(&self, solution: &mut Sol) {
+        self.problem.fix_scores(solution)
+    }
+
+    #[inline]
+    fn solution_is_legal(&self, solution: &Sol) -> bool {
+        self.problem.solution_is_legal(solution)
+    }
+
+    #[inline]
+    fn solution_is_complete(&self, solution: &Sol) -> bool {
+        self.problem.solution_is_complete(solution)
+    }
+
+    #[inline]
+    fn random_solution(&self) -> Sol {
+        self.problem.random_solution()
+    }
+
+    #[inline]
+    fn starting_solution(&self) -> Sol {
+        self.problem.starting_solution()
+    }
+
+    #[inline]
+    fn better_than(&self, new_solution: &Sol, old_solution: &Sol) -> bool {
+        self.problem.better_than(new_solution, old_solution)
+    }
+
+    #[inline]
+    fn can_be_better_than(&self, new_solution: &Sol, old_solution: &Sol) -> bool {
+        self.problem.can_be_better_than(new_solution, old_solution)
+    }
+
+    #[inline]
+    fn first_open_decision(&self, solution: &Sol) -> Option<usize> {
+        self.problem.first_open_decision(solution)
+    }
+
+    #[inline]
+    fn last_closed_decision(&self, solution: &Sol) -> Option<usize> {
+        self.problem.last_closed_decision(solution)
+    }
+
+    #[inline]
+    fn apply_rules(&self, solution: &mut Sol) {
+        self.problem.apply_rules(solution)
+    }
+
+    #[inline]
+    fn rules_audit_passed(&self, solution: &Sol) -> bool {
+        self.problem.rules_audit_passed(solution)
+    }
+
+    #[inline]
+    fn produce_child(&self, parent: &Sol, index: usize, decision: bool) -> Sol {
+        self.problem.produce_child(parent, index, decision)
+    }
+
+    #[inline]
+    fn children_of_solution(&self, parent: &Sol) -> Vec<Sol> {
+        self.problem.children_of_solution(parent)
+    }
+
+    #[inline]
+    fn parallel_children(&self) -> bool {
+        self.problem.parallel_children()
+    }
+
+    #[inline]
+    fn random_completion(&self, solution: &Sol, index: usize, decision: bool) -> Sol {
+        self.problem.random_completion(solution, index, decision)
+    }
+
+    #[inline]
+    fn greedy_decision(&self, solution: &Sol, index: usize) -> bool {
+        self.problem.greedy_decision(solution, index)
+    }
+
+    #[inline]
+    fn greedy_order(&self) -> Vec<usize> {
+        self.problem.greedy_order()
+    }
+
+    #[inline]
+    fn transposition_key(&self, solution: &Sol) -> u64 {
+        self.problem.transposition_key(solution)
+    }
+
+    #[inline]
+    fn sample_from_solution(&self, solution: &Sol) -> Sample {
+        self.problem.sample_from_solution(solution)
+    }
+
+    #[inline]
+    fn solution_from_sample(&self, sample: &Sample) -> Sol {
+        self.problem.solution_from_sample(sample)
+    }
+} // end impl Problem for DynProblem
+
+///////////////////// TESTs for DynSolver and DynProblem /////////////////////
+#[cfg(test)]
+mod tests {
+    // (not super::*, the object safe traits would hide the real ones)
+    use super::{DynProblem, DynSolver};
+    use implementations::{DepthFirstSolver, MhdMonteCarloSolver, Problem01Knapsack};
+    use implementations::{ProblemSubsetSum, ZeroOneKnapsackSolution};
+    use mhd_memory::ZERO_SCORE;
+    use optimizer::MinimalSolution;
+    use optimizer::{Problem, Solution, Solver};
+    use std::time::Duration;
+
+    #[test]
+    fn test_dyn_solver_and_problem() {
+        const NUM_DECISIONS: usize = 12;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let optimum = DepthFirstSolver::new(NUM_DECISIONS)
+            .find_best_solution(&knapsack, Duration::from_secs(1))
+            .unwrap()
+            .get_score();
+
+        // The same problem, type erased: the same optimum, with any solver
+        let problem: DynProblem<ZeroOneKnapsackSolution> = DynProblem::wrap(knapsack.clone());
+        assert_eq!(problem.problem_size(), NUM_DECISIONS);
+        assert_eq!(problem.name(), knapsack.name());
+        assert_eq!(problem.greedy_order(), knapsack.greedy_order());
+        let solvers: Vec<DynSolver<DynProblem<ZeroOneKnapsackSolution>>> = vec![
+            DynSolver::wrap(DepthFirstSolver::new(NUM_DECISIONS)),
+            DynSolver::wrap(MhdMonteCarloSolver::builder(&problem)),
+        ];
+        for mut solver in solvers {
+            let best = solver
+                .find_best_solution(&problem.clone(), Duration::from_millis(200))
+                .unwrap();
+            assert!(knapsack.solution_is_legal(&best));
+            assert!(best.get_score() <= optimum);
+            assert_eq!(solver.best_score(), best.get_score());
+            if solver.is_exhaustive() {
+                assert_eq!(best.get_score(), optimum);
+            };
+        }
+
+        // Another kind of problem (and solution)
+        let mut problem = DynProblem::<MinimalSolution>::wrap(ProblemSubsetSum::random(8));
+        problem.randomize();
+        assert!(problem.is_legal());
+        let mut solver = DynSolver::wrap(DepthFirstSolver::new(8));
+        let (best, time_to_target) = solver
+            .find_target_solution(&problem, Duration::from_secs(1), ZERO_SCORE)
+            .unwrap();
+        assert!(problem.solution_is_legal(&best));
+        assert!(time_to_target.is_some());
+
+        // No problem at all: a placeholder, solved with any solution
+        let problem = DynProblem::<MinimalSolution>::random(8);
+        assert_eq!(problem.problem_size(), 8);
+        assert!(problem.is_legal());
+        let mut solver = DynSolver::wrap(DepthFirstSolver::new(8));
+        let best = solver
+            .find_best_solution(&problem, Duration::from_millis(100))
+            .unwrap();
+        assert!(problem.solution_is_complete(&best));
+        assert_eq!(best.get_score(), ZERO_SCORE);
+    }
+}
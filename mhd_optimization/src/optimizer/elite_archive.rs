@@ -0,0 +1,237 @@
+/// # The Elite Archive
+///
+/// An `EliteArchive` keeps a bounded collection of good *and diverse* complete solutions --
+/// restarts and large neighbourhood searches need several different, high quality seeds,
+/// not just the single incumbent.
+///
+/// A solution is admitted only if its score is high enough (better than the worst elite,
+/// once the archive is full), and if its masked distance to every elite exceeds `min_distance`.
+/// If it is too close to some elites but better than all of them, it replaces them.
+/// With a `min_distance` of zero (see `top`), that's just a pool of the best distinct solutions.
+///
+/// Solvers offer every complete solution to their archive (if they have one) in
+/// `Solver::new_best_solution`:
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{EliteArchive, Problem, Solution, Solver};
+/// use mhd_optimization::implementations::{DepthFirstSolver, Problem01Knapsack};
+/// use std::time::Duration;
+///
+/// let knapsack = Problem01Knapsack::random(16);
+/// let mut solver = DepthFirstSolver::new(16);
+/// solver.elites = Some(EliteArchive::new(5, 2));
+///
+/// let best = solver.find_best_solution(&knapsack, Duration::from_secs(1))
+///                  .expect("could not find best solution");
+/// let elites = solver.elites.unwrap();
+/// assert!(!elites.is_empty() && elites.len() <= 5);
+/// assert!(elites.best().unwrap().get_score() <= best.get_score());
+/// ```
+use mhd_memory::distance;
+use optimizer::Solution;
+
+#[derive(Debug, Clone)]
+pub struct EliteArchive<Sol: Solution> {
+    /// Maximum number of elites
+    pub capacity: usize,
+    /// Elites must be further apart than this (masked hamming distance)
+    pub min_distance: u64,
+    elites: Vec<Sol>, // sorted, best first
+}
+
+impl<Sol: Solution> EliteArchive<Sol> {
+    pub fn new(capacity: usize, min_distance: u64) -> Self {
+        assert!(
+            0 < capacity,
+            "An elite archive needs room for at least one solution"
+        );
+        Self {
+            capacity,
+            min_distance,
+            elites: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// A pool of the `capacity` best solutions, all different, but not necessarily diverse
+    #[inline]
+    pub fn top(capacity: usize) -> Self {
+        Self::new(capacity, 0)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.elites.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.elites.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.elites.clear();
+    }
+
+    /// The elites, best first
+    #[inline]
+    pub fn elites(&self) -> &[Sol] {
+        &self.elites
+    }
+
+    #[inline]
+    pub fn best(&self) -> Option<&Sol> {
+        self.elites.first()
+    }
+
+    /// Masked hamming distance between two solutions (only decisions made in both count)
+    pub fn distance_between(first: &Sol, second: &Sol) -> u64 {
+        let mask: Vec<u8> = first
+            .mask()
+            .iter()
+            .zip(second.mask().iter())
+            .map(|(a, b)| a & b)
+            .collect();
+        distance(&mask, first.query(), second.query())
+    }
+
+    /// Offer a (complete) solution to the archive; returns true iff it was admitted.
+    pub fn offer(&mut self, solution: &Sol) -> bool {
+        let score = solution.get_score();
+        if self.elites.len() == self.capacity
+            && score <= self.elites.last().expect("Full, so not empty").get_score()
+        {
+            return false; // not good enough
+        };
+        let close: Vec<usize> = (0..self.elites.len())
+            .filter(|&index| {
+                Self::distance_between(solution, &self.elites[index]) <= self.min_distance
+            })
+            .collect();
+        if close
+            .iter()
+            .any(|&index| score <= self.elites[index].get_score())
+        {
+            return false; // not different enough from a better (or equal) elite
+        };
+        // Replace the (worse) elites it is too close to
+        for &index in close.iter().rev() {
+            self.elites.remove(index);
+        }
+        let position = self
+            .elites
+            .iter()
+            .position(|elite| elite.get_score() < score)
+            .unwrap_or(self.elites.len());
+        self.elites.insert(position, solution.clone());
+        self.elites.truncate(self.capacity);
+        true
+    } // end offer
+} // end impl EliteArchive
+
+///////////////////// TESTs for EliteArchive /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mhd_memory::{Score, ScoreType};
+    use optimizer::MinimalSolution;
+
+    const NUM_DECISIONS: usize = 16;
+
+    fn solution_with(score: u32, ones: &[usize]) -> MinimalSolution {
+        let mut solution = MinimalSolution::new(NUM_DECISIONS);
+        for index in 0..NUM_DECISIONS {
+            solution.make_decision(index, ones.contains(&index));
+        }
+        solution.put_score(score as ScoreType);
+        solution.put_best_score(score as ScoreType);
+        solution
+    }
+
+    #[test]
+    fn test_elite_archive() {
+        let mut archive = EliteArchive::new(3, 2);
+        assert!(archive.is_empty());
+        assert!(archive.best().is_none());
+
+        assert!(archive.offer(&solution_with(10, &[0, 1, 2, 3])));
+        assert!(!archive.offer(&solution_with(10, &[0, 1, 2, 3]))); // duplicate
+        assert!(!archive.offer(&solution_with(5, &[0, 1, 2]))); // close, and worse
+        assert!(archive.offer(&solution_with(20, &[8, 9, 10, 11]))); // far away
+        assert!(archive.offer(&solution_with(15, &[4, 5, 6, 7])));
+        assert_eq!(archive.len(), 3);
+        let scores: Vec<f64> = archive
+            .elites()
+            .iter()
+            .map(|s| s.get_score().to_f64())
+            .collect();
+        assert_eq!(scores, vec![20.0, 15.0, 10.0]);
+
+        // Full: must beat the worst elite
+        assert!(!archive.offer(&solution_with(9, &[12, 13, 14, 15])));
+        assert!(archive.offer(&solution_with(12, &[12, 13, 14, 15])));
+        let scores: Vec<f64> = archive
+            .elites()
+            .iter()
+            .map(|s| s.get_score().to_f64())
+            .collect();
+        assert_eq!(scores, vec![20.0, 15.0, 12.0]);
+
+        // Close to an elite, but better: replaces it
+        assert!(archive.offer(&solution_with(30, &[8, 9, 10])));
+        let scores: Vec<f64> = archive
+            .elites()
+            .iter()
+            .map(|s| s.get_score().to_f64())
+            .collect();
+        assert_eq!(scores, vec![30.0, 15.0, 12.0]);
+        assert_eq!(archive.best().unwrap().get_score(), 30 as ScoreType);
+
+        archive.clear();
+        assert!(archive.is_empty());
+    }
+
+    #[test]
+    fn test_best_solutions() {
+        use implementations::{DepthFirstSolver, Problem01Knapsack};
+        use optimizer::{Problem, Solver};
+        use std::time::Duration;
+
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let mut solver = DepthFirstSolver::new(NUM_DECISIONS);
+        let best = solver
+            .find_best_solution(&knapsack, Duration::from_secs(1))
+            .unwrap();
+        // No archive, no alternatives
+        assert_eq!(solver.best_solutions(3), vec![best.best.clone()]);
+        assert!(solver.best_solutions(0).is_empty());
+
+        solver.elites = Some(EliteArchive::top(5));
+        solver
+            .find_best_solution(&knapsack, Duration::from_secs(1))
+            .unwrap();
+        let top = solver.best_solutions(3);
+        assert_eq!(top.len(), 3);
+        assert_eq!(top[0].get_score(), best.get_score());
+        for (index, solution) in top.iter().enumerate() {
+            assert!(knapsack.solution_is_complete(solution));
+            assert!(knapsack.solution_is_legal(solution));
+            for other in top[index + 1..].iter() {
+                assert!(other.get_score() <= solution.get_score());
+                assert!(0 < EliteArchive::distance_between(solution, other));
+            }
+        }
+        assert_eq!(solver.best_solutions(10).len(), 5);
+    }
+
+    #[test]
+    fn test_distance_between() {
+        let first = solution_with(0, &[0, 1]);
+        let second = solution_with(0, &[1, 2]);
+        assert_eq!(EliteArchive::distance_between(&first, &second), 2);
+        assert_eq!(EliteArchive::distance_between(&first, &first), 0);
+
+        let partial = MinimalSolution::new(NUM_DECISIONS); // nothing decided
+        assert_eq!(EliteArchive::distance_between(&first, &partial), 0);
+    }
+}
@@ -0,0 +1,199 @@
+/// # Memory-Guided Pruning (for the classical solvers)
+///
+/// `MemoryPruning` lets a classical solver (`DepthFirstSolver`, `BestFirstSolver`) learn every
+/// complete solution it sees in an MHD memory, and skip a subtree when the memory predicts --
+/// with enough confidence -- that its completions cannot beat the best solution so far.
+/// Unlike the bound (`Problem::can_be_better_than`), this is a heuristic:
+/// with pruning, a solver may miss the optimum, but it gets to the good solutions sooner.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{MemoryPruning, Problem, Solution, Solver};
+/// use mhd_optimization::implementations::{DepthFirstSolver, Problem01Knapsack};
+/// use std::time::Duration;
+///
+/// let knapsack = Problem01Knapsack::random(24);
+/// let mut solver = DepthFirstSolver::new(24);
+/// solver.pruning = Some(MemoryPruning::new(24, 3.0));
+///
+/// let best = solver.find_best_solution(&knapsack, Duration::from_secs(1))
+///                  .expect("could not find best solution");
+/// assert!(knapsack.solution_is_complete(&best));
+/// assert!(!solver.pruning.unwrap().memory.is_empty());
+/// ```
+use log::*;
+
+use mhd_memory::{MhdMemory, Score, ScoreType};
+use optimizer::{Problem, Solution};
+
+#[derive(Debug, Clone)]
+pub struct MemoryPruning {
+    /// Every complete solution seen so far
+    pub memory: MhdMemory,
+    /// Prune only if even the prediction plus this many uncertainties cannot beat the best score
+    pub confidence: f64,
+    /// Never prune before the memory holds this many complete solutions
+    pub min_samples: usize,
+    /// How many subtrees were skipped so far
+    pub num_pruned: usize,
+}
+
+impl MemoryPruning {
+    /// Pruning for problems with `width` decisions
+    pub fn new(width: usize, confidence: f64) -> Self {
+        Self {
+            memory: MhdMemory::new(width),
+            confidence,
+            min_samples: width,
+            num_pruned: 0,
+        }
+    }
+
+    /// Forget everything learned (but keep the configuration)
+    pub fn clear(&mut self) {
+        self.memory.clear();
+        self.num_pruned = 0;
+    }
+
+    /// Remember a complete solution
+    #[inline]
+    pub fn learn<Prob: Problem>(&mut self, problem: &Prob, solution: &Prob::Sol) {
+        self.memory
+            .write_sample(&problem.sample_from_solution(solution));
+    }
+
+    /// Is the memory confident that no completion of `partial` beats `incumbent` (the best score)?
+    pub fn can_prune<Sol: Solution>(&self, partial: &Sol, incumbent: ScoreType) -> bool {
+        if self.memory.num_samples() < self.min_samples.max(1) {
+            return false;
+        };
+        let (prediction, uncertainty) = self
+            .memory
+            .predict_masked_score(partial.mask(), partial.query());
+        let optimistic = prediction.to_f64() + self.confidence * uncertainty;
+        trace!(
+            "Memory pruning: prediction {} + {} * {} vs. incumbent {}",
+            prediction,
+            self.confidence,
+            uncertainty,
+            incumbent
+        );
+        optimistic < incumbent.to_f64()
+    }
+
+    /// Learn the complete children, and drop the incomplete ones which `can_prune` says to.
+    /// This is what solvers call from their `Solver::children_of_solution`.
+    pub fn filter_children<Prob: Problem>(
+        &mut self,
+        problem: &Prob,
+        children: Vec<Prob::Sol>,
+        incumbent: ScoreType,
+    ) -> Vec<Prob::Sol> {
+        let mut result = Vec::with_capacity(children.len());
+        let mut complete = Vec::new();
+        for child in children {
+            if problem.solution_is_complete(&child) {
+                complete.push(problem.sample_from_solution(&child));
+                result.push(child);
+            } else if self.can_prune(&child, incumbent) {
+                self.num_pruned += 1;
+            } else {
+                result.push(child);
+            }
+        } // end for all children
+
+        // Learn the complete children all at once
+        self.memory.write_samples(&complete);
+        result
+    }
+} // end impl MemoryPruning
+
+///////////////////// TESTs for MemoryPruning /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::ProblemSubsetSum;
+    use optimizer::MinimalSolution;
+
+    const NUM_DECISIONS: usize = 16;
+
+    // Learn some completions of both children of the root
+    fn learn_completions(problem: &ProblemSubsetSum, pruning: &mut MemoryPruning) {
+        let root: MinimalSolution = problem.starting_solution();
+        for _ in 0..NUM_DECISIONS {
+            pruning.learn(problem, &problem.random_completion(&root, 0, true));
+            pruning.learn(problem, &problem.random_completion(&root, 0, false));
+        }
+    }
+
+    #[test]
+    fn test_can_prune() {
+        let problem = ProblemSubsetSum::random(NUM_DECISIONS);
+        let mut pruning = MemoryPruning::new(NUM_DECISIONS, 3.0);
+        // (Leaving out the first, heaviest item is always legal; putting it in may not be)
+        let partial = problem.produce_child(&problem.starting_solution(), 0, false);
+
+        // An empty memory knows nothing, so never prunes
+        assert!(!pruning.can_prune(&partial, 1_000_000 as ScoreType));
+
+        learn_completions(&problem, &mut pruning);
+        pruning.min_samples = 1;
+        assert!(pruning.memory.max_score <= problem.capacity);
+        // Nothing ever came close to a much higher score -- prune
+        let much_higher = (100 as ScoreType) * problem.capacity + 100 as ScoreType;
+        assert!(pruning.can_prune(&partial, much_higher));
+        // ...but nothing can be pruned when the best score so far is zero
+        assert!(!pruning.can_prune(&partial, 0 as ScoreType));
+        // ...and nothing before the memory holds enough samples
+        pruning.min_samples = pruning.memory.num_samples() + 1;
+        assert!(!pruning.can_prune(&partial, much_higher));
+
+        pruning.clear();
+        assert!(pruning.memory.is_empty());
+        assert_eq!(pruning.num_pruned, 0);
+    }
+
+    #[test]
+    fn test_filter_children() {
+        let problem = ProblemSubsetSum::random(NUM_DECISIONS);
+        let mut pruning = MemoryPruning::new(NUM_DECISIONS, 3.0);
+        learn_completions(&problem, &mut pruning);
+        pruning.min_samples = 1;
+
+        let root = problem.starting_solution();
+        let children = problem.children_of_solution(&root);
+        let num_children = children.len();
+        let num_incomplete = children
+            .iter()
+            .filter(|child| !problem.solution_is_complete(child))
+            .count();
+        let much_higher = (100 as ScoreType) * problem.capacity + 100 as ScoreType;
+        let kept = pruning.filter_children(&problem, children, much_higher);
+        assert_eq!(kept.len(), num_children - num_incomplete);
+        assert_eq!(pruning.num_pruned, num_incomplete);
+
+        let children = problem.children_of_solution(&root);
+        let kept = pruning.filter_children(&problem, children, 0 as ScoreType);
+        assert_eq!(kept.len(), num_children);
+    }
+
+    #[test]
+    fn test_predict_solution_samples() {
+        // Twelve decisions: the last four are in the second byte, next to its padding
+        let problem = ProblemSubsetSum::random(12);
+        let solution = problem.random_solution();
+        let sample = problem.sample_from_solution(&solution);
+        let mut opposite = sample.clone(); // (so that the memory's scores vary)
+        opposite.bytes.iter_mut().for_each(|byte| *byte = !*byte);
+        opposite.score = sample.score + 1 as ScoreType;
+        let mut memory = MhdMemory::new(12);
+        memory.write_sample(&sample);
+        memory.write_sample(&opposite);
+        assert_eq!(memory.predict_score(&sample.bytes), (sample.score, 0.0));
+
+        // Differing only in decision 9 is not an exact hit
+        let mut other = solution.clone();
+        other.make_decision(9, !solution.get_decision(9).expect("complete"));
+        let (_, uncertainty) = memory.predict_score(other.query());
+        assert!(0.0 < uncertainty);
+    }
+}
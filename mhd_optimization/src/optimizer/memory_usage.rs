@@ -0,0 +1,149 @@
+/// # Memory Usage
+///
+/// How much memory a solver holds (approximately, in bytes, see `Solver::memory_usage`):
+/// open solutions in its frontier, nodes of its search tree, and samples in its MHD memory.
+/// Large searches are limited by memory rather than time, so this is worth watching --
+/// `MhdMonteCarloSolver::is_finished` stops before its memory reaches a gigabyte, for example.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{MemoryUsage, Problem, Solver};
+/// use mhd_optimization::implementations::{DepthFirstSolver, ProblemSubsetSum};
+///
+/// let problem = ProblemSubsetSum::random(20);
+/// let mut solver = DepthFirstSolver::new(20);
+/// assert_eq!(solver.memory_usage(), MemoryUsage::default());
+///
+/// solver.start_search(&problem);
+/// solver.step(&problem);
+/// let usage = solver.memory_usage();
+/// assert_eq!(usage.frontier_solutions, solver.number_of_solutions());
+/// assert!(0 < usage.total_bytes());
+/// println!("{}", usage); // e.g. "frontier 2 solutions (160 bytes), ..."
+/// ```
+use std::fmt;
+use std::mem::size_of;
+use std::ops::Add;
+
+use optimizer::Solution;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Open solutions waiting to be popped
+    pub frontier_solutions: usize,
+    pub frontier_bytes: usize,
+    /// Nodes of a search tree (e.g. Monte Carlo)
+    pub tree_nodes: usize,
+    pub tree_bytes: usize,
+    /// Samples in an MHD memory
+    pub memory_samples: usize,
+    pub memory_bytes: usize,
+}
+
+impl MemoryUsage {
+    /// Approximate size of one solution with `width` decisions: the struct itself,
+    /// plus its mask and decisions (one bit per decision each)
+    #[inline]
+    pub fn bytes_per_solution<Sol: Solution>(width: usize) -> usize {
+        size_of::<Sol>() + 2 * width.div_ceil(8)
+    }
+
+    /// A frontier of `num_solutions` solutions, with `width` decisions each
+    pub fn of_frontier<Sol: Solution>(num_solutions: usize, width: usize) -> Self {
+        Self {
+            frontier_solutions: num_solutions,
+            frontier_bytes: num_solutions * Self::bytes_per_solution::<Sol>(width),
+            ..Self::default()
+        }
+    }
+
+    /// A search tree of `num_nodes` nodes of type `Node`
+    pub fn of_tree<Node>(num_nodes: usize) -> Self {
+        Self {
+            tree_nodes: num_nodes,
+            tree_bytes: num_nodes * size_of::<Node>(),
+            ..Self::default()
+        }
+    }
+
+    /// An MHD memory, see `MhdMemory::memory_bytes`
+    pub fn of_memory(memory: &mhd_memory::MhdMemory) -> Self {
+        Self {
+            memory_samples: memory.num_samples(),
+            memory_bytes: memory.memory_bytes(),
+            ..Self::default()
+        }
+    }
+
+    #[inline]
+    pub fn total_bytes(&self) -> usize {
+        self.frontier_bytes + self.tree_bytes + self.memory_bytes
+    }
+}
+
+impl Add for MemoryUsage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            frontier_solutions: self.frontier_solutions + other.frontier_solutions,
+            frontier_bytes: self.frontier_bytes + other.frontier_bytes,
+            tree_nodes: self.tree_nodes + other.tree_nodes,
+            tree_bytes: self.tree_bytes + other.tree_bytes,
+            memory_samples: self.memory_samples + other.memory_samples,
+            memory_bytes: self.memory_bytes + other.memory_bytes,
+        }
+    }
+}
+
+impl fmt::Display for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "frontier {} solutions ({} bytes), tree {} nodes ({} bytes), \
+             memory {} samples ({} bytes), total {} bytes",
+            self.frontier_solutions,
+            self.frontier_bytes,
+            self.tree_nodes,
+            self.tree_bytes,
+            self.memory_samples,
+            self.memory_bytes,
+            self.total_bytes()
+        )
+    }
+}
+
+///////////////////// TESTs for MemoryUsage /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use optimizer::MinimalSolution;
+
+    #[test]
+    fn test_memory_usage() {
+        let per_solution = MemoryUsage::bytes_per_solution::<MinimalSolution>(20);
+        assert_eq!(per_solution, size_of::<MinimalSolution>() + 6);
+        let frontier = MemoryUsage::of_frontier::<MinimalSolution>(10, 20);
+        assert_eq!(frontier.total_bytes(), 10 * per_solution);
+
+        let tree = MemoryUsage::of_tree::<[u64; 4]>(3);
+        assert_eq!(tree.tree_bytes, 3 * 32);
+
+        let mut memory = mhd_memory::MhdMemory::new(64);
+        memory.write_n_random_samples(100);
+        let memory = MemoryUsage::of_memory(&memory);
+        assert_eq!(memory.memory_samples, 100);
+        assert!(100 * 8 < memory.memory_bytes);
+
+        let sum = frontier + tree + memory;
+        assert_eq!(sum.frontier_solutions, 10);
+        assert_eq!(sum.tree_nodes, 3);
+        assert_eq!(sum.memory_samples, 100);
+        assert_eq!(
+            sum.total_bytes(),
+            frontier.total_bytes() + tree.total_bytes() + memory.total_bytes()
+        );
+        assert!(sum
+            .to_string()
+            .ends_with(&format!("total {} bytes", sum.total_bytes())));
+    }
+}
@@ -0,0 +1,175 @@
+/// # Errors
+///
+/// What can go wrong -- in a search (`Solver::find_best_solution` and friends), reading a
+/// problem (the parsers), or setting things up -- as one `MhdError`, so callers can tell
+/// the cases apart: no feasible solution is something else than a file which can't be read.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::MhdError;
+/// use mhd_optimization::implementations::parse_dot_dat_stream;
+/// # extern crate mhd_memory;
+/// use mhd_memory::ScoreType;
+///
+/// let knapsack = parse_dot_dat_stream("1 2 10 5 3 6 4".as_bytes()).unwrap();
+/// assert_eq!(knapsack.capacity(), 10 as ScoreType);
+///
+/// match parse_dot_dat_stream("1 2 10 5 x 6 4".as_bytes()) {
+///     Err(MhdError::ParseError { line, col, .. }) => assert_eq!((line, col), (1, 10)),
+///     other => panic!("expected a parse error, got {:?}", other),
+/// };
+/// ```
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use optimizer::StopReason;
+
+#[derive(Debug)]
+pub enum MhdError {
+    /// The search ran out of time before it found a feasible solution
+    Timeout,
+    /// The search stopped for another reason (a node, push or memory limit, a cancel...)
+    /// before it found a feasible solution -- which proves nothing
+    Interrupted(StopReason),
+    /// There is no feasible solution: an exhaustive search finished without one
+    Infeasible,
+    /// Input which doesn't make sense, at `line` and column `col` (both from one,
+    /// the line counted from where the parser started reading)
+    ParseError {
+        line: usize,
+        col: usize,
+        message: String,
+    },
+    /// Reading or writing failed (or the input ended early)
+    IoError(io::Error),
+    /// The solvers, problems or limits asked for don't fit together
+    InvalidConfig(String),
+    /// A solver panicked (e.g. in a thread of its own, see `PortfolioSolver`)
+    Panicked(String),
+}
+
+impl fmt::Display for MhdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MhdError::Timeout => write!(f, "timeout before a feasible solution was found"),
+            MhdError::Interrupted(reason) => {
+                write!(
+                    f,
+                    "stopped ({}) before a feasible solution was found",
+                    reason
+                )
+            }
+            MhdError::Infeasible => write!(f, "no feasible solution"),
+            MhdError::ParseError { line, col, message } => {
+                write!(
+                    f,
+                    "parse error at line {}, column {}: {}",
+                    line, col, message
+                )
+            }
+            MhdError::IoError(error) => write!(f, "I/O error: {}", error),
+            MhdError::InvalidConfig(message) => write!(f, "invalid configuration: {}", message),
+            MhdError::Panicked(message) => write!(f, "panicked: {}", message),
+        }
+    }
+}
+
+impl Error for MhdError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MhdError::IoError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MhdError {
+    fn from(error: io::Error) -> Self {
+        MhdError::IoError(error)
+    }
+}
+
+impl From<MhdError> for io::Error {
+    /// For callers which only deal in `io::Result`s: I/O errors come back as they were,
+    /// the others as invalid data
+    fn from(error: MhdError) -> Self {
+        match error {
+            MhdError::IoError(error) => error,
+            other => io::Error::new(io::ErrorKind::InvalidData, other),
+        }
+    }
+}
+
+///////////////////// TESTs for MhdError /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{DepthFirstSolver, ProblemSubsetSum};
+    use optimizer::{MinimalSolution, Problem, Solution, Solver, SolverConfig};
+    use std::time::Duration;
+
+    #[test]
+    fn test_mhd_error() {
+        let error = MhdError::ParseError {
+            line: 3,
+            col: 7,
+            message: "expected a weight".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "parse error at line 3, column 7: expected a weight"
+        );
+        assert!(error.source().is_none());
+
+        // I/O errors round trip, the others become invalid data
+        let error = MhdError::from(io::Error::new(io::ErrorKind::UnexpectedEof, "eof"));
+        assert!(error.source().is_some());
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::UnexpectedEof);
+        let error = io::Error::from(MhdError::Infeasible);
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "no feasible solution");
+    }
+
+    #[test]
+    fn test_no_legal_solution() {
+        const NUM_DECISIONS: usize = 10;
+        let mut problem = ProblemSubsetSum::random(NUM_DECISIONS);
+        let capacity = problem.weights_sum() - problem.weights[0];
+        problem.set_capacity(capacity);
+
+        // Every item in the knapsack: too heavy -- and nothing can beat it
+        let mut too_heavy = problem.starting_solution();
+        for index in 0..NUM_DECISIONS {
+            too_heavy.make_decision(index, true);
+        }
+        problem.fix_scores(&mut too_heavy);
+        let score = too_heavy.get_score();
+        too_heavy.put_best_score(score);
+        assert!(!problem.solution_is_legal(&too_heavy));
+        let start = |solver: &mut DepthFirstSolver<MinimalSolution>| {
+            solver.start_search(&problem);
+            solver.push(problem.starting_solution());
+            solver.store_best_solution(too_heavy.clone());
+        };
+
+        // Cut short by a node limit: nothing proven, so not infeasible
+        let mut solver = DepthFirstSolver::new(NUM_DECISIONS);
+        start(&mut solver);
+        let config = SolverConfig {
+            node_limit: Some(1),
+            ..Default::default()
+        };
+        match solver.continue_search(&problem, config) {
+            Err(MhdError::Interrupted(StopReason::NodeLimit)) => {}
+            other => panic!("expected an interrupted search, got {:?}", other),
+        };
+
+        // An exhaustive search, finished: that's a proof
+        let mut solver = DepthFirstSolver::new(NUM_DECISIONS);
+        start(&mut solver);
+        match solver.continue_search(&problem, Duration::from_secs(1)) {
+            Err(MhdError::Infeasible) => {}
+            other => panic!("expected infeasible, got {:?}", other),
+        };
+    }
+}
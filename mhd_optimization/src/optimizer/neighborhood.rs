@@ -0,0 +1,149 @@
+/// # Neighborhoods -- for local search
+///
+/// A `Neighborhood` lazily enumerates all *legal* solutions within (masked) hamming
+/// distance `k` of a given solution: first all solutions with one decision flipped,
+/// then all with two, and so on. Only decisions already made can be flipped;
+/// every neighbor has had the problem's rules applied (`Problem::apply_rules`),
+/// so its scores are correct.
+///
+/// Use `Problem::neighbors` to get one:
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, Solution};
+/// use mhd_optimization::implementations::Problem01Knapsack;
+///
+/// let knapsack = Problem01Knapsack::random(16);
+/// let start = knapsack.random_solution();
+///
+/// // Hill climbing: take the first better neighbor, until there is none
+/// let mut current = start.clone();
+/// while let Some(better) = knapsack
+///     .neighbors(&current, 2)
+///     .find(|neighbor| current.get_score() < neighbor.get_score())
+/// {
+///     current = better;
+/// }
+/// assert!(start.get_score() <= current.get_score());
+/// assert!(knapsack.solution_is_legal(&current));
+/// ```
+use optimizer::{Problem, Solution};
+
+pub struct Neighborhood<'a, Prob: Problem> {
+    problem: &'a Prob,
+    center: Prob::Sol,
+    decided: Vec<usize>,     // the indices of the decisions which may be flipped
+    max_flips: usize,        // the "k" in k-flip
+    combination: Vec<usize>, // which of the decided indices are flipped right now
+}
+
+impl<'a, Prob: Problem> Neighborhood<'a, Prob> {
+    pub fn new(problem: &'a Prob, center: &Prob::Sol, max_flips: usize) -> Self {
+        let decided = (0..problem.problem_size())
+            .filter(|&index| center.get_decision(index).is_some())
+            .collect();
+        Self {
+            problem,
+            center: center.clone(),
+            decided,
+            max_flips,
+            combination: Vec::with_capacity(max_flips),
+        }
+    }
+
+    // Move on to the next set of decisions to flip; returns false when there are no more.
+    fn advance(&mut self) -> bool {
+        let num_decided = self.decided.len();
+        let num_flips = self.combination.len();
+        // The next combination of the same size...
+        for position in (0..num_flips).rev() {
+            if self.combination[position] < num_decided - num_flips + position {
+                self.combination[position] += 1;
+                for later in position + 1..num_flips {
+                    self.combination[later] = self.combination[later - 1] + 1;
+                }
+                return true;
+            };
+        }
+        // ...or the first one with one more flip
+        if num_flips < self.max_flips && num_flips < num_decided {
+            self.combination = (0..=num_flips).collect();
+            return true;
+        };
+        false
+    }
+} // end impl Neighborhood
+
+impl<'a, Prob: Problem> Iterator for Neighborhood<'a, Prob> {
+    type Item = Prob::Sol;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.advance() {
+            let mut neighbor = self.center.clone();
+            for &position in self.combination.iter() {
+                let index = self.decided[position];
+                let decision = neighbor.get_decision(index).expect("decided");
+                neighbor.make_decision(index, !decision);
+            }
+            if self.problem.solution_is_legal(&neighbor) {
+                self.problem.apply_rules(&mut neighbor);
+                debug_assert!(self.problem.rules_audit_passed(&neighbor));
+                return Some(neighbor);
+            };
+        } // end while there are more combinations
+        None
+    }
+}
+
+///////////////////// TESTs for Neighborhood /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::ProblemSubsetSum;
+    use mhd_memory::{distance, ScoreType};
+
+    const NUM_DECISIONS: usize = 6;
+
+    #[test]
+    fn test_neighborhood_sizes() {
+        let mut problem = ProblemSubsetSum::new(NUM_DECISIONS);
+        problem.weights = vec![1 as ScoreType; NUM_DECISIONS];
+        problem.capacity = NUM_DECISIONS as ScoreType; // everything fits, everything is legal
+        let center = problem.random_solution();
+
+        assert_eq!(problem.neighbors(&center, 0).count(), 0);
+        assert_eq!(problem.neighbors(&center, 1).count(), 6);
+        assert_eq!(problem.neighbors(&center, 2).count(), 6 + 15);
+        assert_eq!(problem.neighbors(&center, 3).count(), 6 + 15 + 20);
+        assert_eq!(problem.neighbors(&center, 100).count(), 63); // all but the center
+
+        for neighbor in problem.neighbors(&center, 2) {
+            let flips = distance(center.mask(), center.query(), neighbor.query());
+            assert!(0 < flips && flips <= 2);
+            assert_eq!(neighbor.get_score(), problem.solution_score(&neighbor));
+        }
+
+        // Only decided decisions get flipped
+        let partial = problem.produce_child(&problem.starting_solution(), 0, true);
+        assert_eq!(problem.neighbors(&partial, 3).count(), 1);
+    }
+
+    #[test]
+    fn test_neighborhood_legality() {
+        let mut problem = ProblemSubsetSum::new(NUM_DECISIONS);
+        problem.weights = vec![1 as ScoreType; NUM_DECISIONS];
+        problem.capacity = 3 as ScoreType;
+        let mut center = problem.starting_solution();
+        for index in 0..NUM_DECISIONS {
+            center.make_decision(index, index < 3); // full: 3 items of weight 1
+        }
+        problem.fix_scores(&mut center);
+
+        // One flip: only taking something out is legal
+        assert_eq!(problem.neighbors(&center, 1).count(), 3);
+        // Two flips: take two out (3), or swap one in and one out (3 * 3)
+        assert_eq!(problem.neighbors(&center, 2).count(), 3 + 3 + 9);
+        assert!(problem
+            .neighbors(&center, 2)
+            .all(|neighbor| problem.solution_is_legal(&neighbor)));
+    }
+}
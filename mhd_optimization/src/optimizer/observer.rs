@@ -0,0 +1,149 @@
+/// # Watching a Search
+///
+/// A `SearchObserver` is told what happens while `Solver::find_best_solution_observed` runs:
+/// every new best solution (`on_new_best`), every iteration of the loop (`on_iteration`),
+/// and why the search stopped (`on_stop`), e.g. to stream live progress to a dashboard.
+/// They all do nothing by default, so an observer only implements what it is interested in.
+/// A closure `|solution, elapsed, visits| ...` is an observer of the new best solutions.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{IterationStats, MinimalSolution, Problem, SearchObserver};
+/// use mhd_optimization::optimizer::{Solution, Solver};
+/// use mhd_optimization::implementations::{DepthFirstSolver, ProblemSubsetSum};
+/// use std::time::Duration;
+///
+/// let problem = ProblemSubsetSum::random(12);
+/// let mut solver = DepthFirstSolver::new(12);
+///
+/// // A closure sees the new best solutions...
+/// let mut scores = Vec::new();
+/// let mut on_new_best = |best: &MinimalSolution, _elapsed: Duration, _visits: u64| {
+///     scores.push(best.get_score())
+/// };
+/// let best = solver
+///     .find_best_solution_observed(&problem, Duration::from_secs(1), &mut on_new_best)
+///     .unwrap();
+/// assert_eq!(scores.last(), Some(&best.get_score()));
+///
+/// // ...an observer of its own sees every iteration, too
+/// #[derive(Default)]
+/// struct Dashboard {
+///     iterations: u64,
+/// }
+/// impl<Sol> SearchObserver<Sol> for Dashboard {
+///     fn on_iteration(&mut self, stats: &IterationStats) {
+///         self.iterations = stats.visits;
+///     }
+/// }
+/// let mut dashboard = Dashboard::default();
+/// solver
+///     .find_best_solution_observed(&problem, Duration::from_secs(1), &mut dashboard)
+///     .unwrap();
+/// assert!(0 < dashboard.iterations);
+/// ```
+use std::time::Duration;
+
+use mhd_memory::ScoreType;
+use optimizer::{StepEvent, StopReason};
+
+/// What one iteration of the loop (one call to `Solver::step`) did, and where the search is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IterationStats {
+    /// Iterations so far (this one included)
+    pub visits: u64,
+    /// Time since the search started
+    pub elapsed: Duration,
+    pub event: StepEvent,
+    /// The best score so far, after this iteration
+    pub best_score: ScoreType,
+    /// Number of open solutions (see `Solver::number_of_solutions`), after this iteration
+    pub frontier: usize,
+}
+
+pub trait SearchObserver<Sol> {
+    /// A new best `solution` was found, `elapsed` after the start, in iteration `visits`
+    fn on_new_best(&mut self, _solution: &Sol, _elapsed: Duration, _visits: u64) {}
+
+    /// One more iteration of the loop is done
+    fn on_iteration(&mut self, _stats: &IterationStats) {}
+
+    /// The search stopped, for `reason`
+    fn on_stop(&mut self, _reason: StopReason) {}
+}
+
+impl<Sol, F: FnMut(&Sol, Duration, u64)> SearchObserver<Sol> for F {
+    fn on_new_best(&mut self, solution: &Sol, elapsed: Duration, visits: u64) {
+        self(solution, elapsed, visits)
+    }
+}
+
+///////////////////// TESTs for SearchObserver /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{DepthFirstSolver, ProblemSubsetSum};
+    use optimizer::{Problem, Solution, Solver};
+
+    #[derive(Default)]
+    struct Recorder {
+        new_bests: Vec<(ScoreType, u64)>,
+        iterations: Vec<IterationStats>,
+    }
+
+    impl<Sol: Solution> SearchObserver<Sol> for Recorder {
+        fn on_new_best(&mut self, solution: &Sol, _elapsed: Duration, visits: u64) {
+            self.new_bests.push((solution.get_score(), visits));
+        }
+
+        fn on_iteration(&mut self, stats: &IterationStats) {
+            self.iterations.push(*stats);
+        }
+    }
+
+    #[test]
+    fn test_observer() {
+        let problem = ProblemSubsetSum::random(10);
+        let mut solver = DepthFirstSolver::new(10);
+        let mut recorder = Recorder::default();
+        let best = solver
+            .find_best_solution_observed(&problem, Duration::from_secs(1), &mut recorder)
+            .unwrap();
+
+        // Every iteration, numbered, up to the end of the search
+        let visits: Vec<u64> = recorder.iterations.iter().map(|s| s.visits).collect();
+        assert_eq!(visits, (1..=visits.len() as u64).collect::<Vec<_>>());
+        let last = recorder.iterations.last().unwrap();
+        assert_eq!((last.frontier, last.best_score), (0, best.get_score()));
+
+        // Every new best, in the iteration it was found
+        assert_eq!(recorder.new_bests.last().unwrap().0, best.get_score());
+        for (score, visit) in recorder.new_bests.iter() {
+            let stats = recorder.iterations[*visit as usize - 1];
+            assert_eq!(
+                (stats.event, stats.best_score),
+                (StepEvent::NewBest, *score)
+            );
+        }
+    }
+
+    #[test]
+    fn test_observe_all_best() {
+        let problem = ProblemSubsetSum::random(10);
+        let mut solver = DepthFirstSolver::new(10);
+        let mut recorder = Recorder::default();
+        let optima = solver
+            .find_all_best_solutions_observed(
+                &problem,
+                Duration::from_secs(1),
+                0 as ScoreType,
+                &mut recorder,
+            )
+            .unwrap();
+
+        // The same loop, so the same iterations
+        let visits: Vec<u64> = recorder.iterations.iter().map(|s| s.visits).collect();
+        assert_eq!(visits, (1..=visits.len() as u64).collect::<Vec<_>>());
+        let last = recorder.iterations.last().unwrap();
+        assert_eq!((last.frontier, last.best_score), (0, optima[0].get_score()));
+    }
+}
@@ -0,0 +1,173 @@
+/// # Convergence Plots (feature "plots")
+///
+/// The standard comparison figure -- best score so far vs. elapsed time, one line per solver --
+/// rendered straight from a handful of `SearchTrace`s, without a detour through Python.
+/// Files ending in `.svg` get an SVG, anything else (e.g. `.png`) gets a bitmap.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{plot_convergence, Problem, SearchTrace, Solver};
+/// use mhd_optimization::implementations::{BestFirstSolver, DepthFirstSolver, Problem01Knapsack};
+/// use std::time::Duration;
+///
+/// let knapsack = Problem01Knapsack::random(10);
+/// let time_limit = Duration::from_millis(200);
+/// let traces = vec![
+///     SearchTrace::record(&mut DepthFirstSolver::new(10), &knapsack, time_limit),
+///     SearchTrace::record(&mut BestFirstSolver::new(10), &knapsack, time_limit),
+/// ];
+///
+/// let path = std::env::temp_dir().join("mhd_doctest_convergence.svg");
+/// plot_convergence(&traces, &path, "Knapsack, 10 decisions").expect("could not plot");
+/// assert!(path.exists());
+/// ```
+use std::error::Error;
+use std::path::Path;
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use mhd_memory::{Score, ScoreType, ZERO_SCORE};
+use optimizer::SearchTrace;
+
+const PLOT_SIZE: (u32, u32) = (1024, 768);
+
+/// Draw one line per trace (labelled with the solver's name) into `path`.
+pub fn plot_convergence<P: AsRef<Path>>(
+    traces: &[SearchTrace],
+    path: P,
+    caption: &str,
+) -> Result<(), Box<dyn Error>> {
+    let path = path.as_ref();
+    let is_svg = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"));
+    if is_svg {
+        let root = SVGBackend::new(path, PLOT_SIZE).into_drawing_area();
+        draw_convergence(&root, traces, caption)?;
+        root.present()?;
+    } else {
+        let root = BitMapBackend::new(path, PLOT_SIZE).into_drawing_area();
+        draw_convergence(&root, traces, caption)?;
+        root.present()?;
+    };
+    Ok(())
+}
+
+// The curves as (milliseconds, score) points, as step functions: from the best score the
+// search started with, continued to the end of each search so the lines don't just stop
+// at the last improvement.
+fn convergence_points(trace: &SearchTrace) -> Vec<(f64, ScoreType)> {
+    let mut result = Vec::new();
+    let last_event = match trace.events.last() {
+        Some(event) => event,
+        None => return result,
+    };
+    let mut last_score = trace.start_score;
+    result.push((0.0, last_score));
+    for (elapsed, score) in trace.incumbents() {
+        let millis = elapsed.as_secs_f64() * 1000.0;
+        result.push((millis, last_score));
+        result.push((millis, score));
+        last_score = score;
+    } // end for all improvements
+    result.push((last_event.elapsed.as_secs_f64() * 1000.0, last_score));
+    result
+}
+
+fn draw_convergence<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    traces: &[SearchTrace],
+    caption: &str,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let curves: Vec<Vec<(f64, ScoreType)>> = traces.iter().map(convergence_points).collect();
+    let max_millis = curves
+        .iter()
+        .flatten()
+        .map(|&(millis, _)| millis)
+        .fold(1.0, f64::max);
+    // (at least one, so the axis never collapses)
+    let scores = curves.iter().flatten().map(|&(_, score)| score);
+    let max_score = ScoreType::max_of(scores.chain(Some(ScoreType::ONE))).unwrap();
+
+    let mut chart = ChartBuilder::on(root)
+        .caption(caption, ("sans-serif", 24))
+        .margin(16)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(
+            0.0..max_millis * 1.05,
+            ZERO_SCORE..max_score + max_score / (20 as ScoreType),
+        )?;
+
+    chart
+        .configure_mesh()
+        .x_desc("elapsed time (ms)")
+        .y_desc("best score")
+        .draw()?;
+
+    for (index, (trace, curve)) in traces.iter().zip(curves).enumerate() {
+        let color = Palette99::pick(index).to_rgba();
+        chart
+            .draw_series(LineSeries::new(curve, color.stroke_width(2)))?
+            .label(trace.solver.clone())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    } // end for all traces
+
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .position(SeriesLabelPosition::LowerRight)
+        .draw()?;
+    Ok(())
+}
+
+///////////////////// TESTs for plots /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{DepthFirstSolver, ProblemSubsetSum};
+    use optimizer::{MinimalSolution, Problem};
+    use std::time::Duration;
+
+    #[test]
+    fn test_convergence_points() {
+        let problem = ProblemSubsetSum::random(8);
+        let mut solver = DepthFirstSolver::<MinimalSolution>::new(8);
+        let trace = SearchTrace::record(&mut solver, &problem, Duration::from_secs(1));
+
+        let points = convergence_points(&trace);
+        assert_eq!(points[0], (0.0, trace.start_score));
+        // A step function: time never goes backwards, score never goes down
+        for pair in points.windows(2) {
+            assert!(pair[0].0 <= pair[1].0);
+            assert!(pair[0].1 <= pair[1].1);
+        }
+        assert_eq!(points.last().unwrap().1, trace.final_score());
+
+        assert!(convergence_points(&SearchTrace::default()).is_empty());
+    }
+
+    #[test]
+    fn test_plot_svg_and_png() {
+        let problem = ProblemSubsetSum::random(8);
+        let mut solver = DepthFirstSolver::<MinimalSolution>::new(8);
+        let traces = vec![SearchTrace::record(
+            &mut solver,
+            &problem,
+            Duration::from_secs(1),
+        )];
+
+        for name in &["mhd_test_convergence.svg", "mhd_test_convergence.png"] {
+            let path = std::env::temp_dir().join(name);
+            plot_convergence(&traces, &path, "Subset Sum").expect("could not plot");
+            assert!(0 < std::fs::metadata(&path).unwrap().len());
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+}
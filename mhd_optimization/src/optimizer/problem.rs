@@ -1,15 +1,23 @@
 use rand::prelude::*;
+use rayon;
+use std::collections::hash_map::DefaultHasher;
 use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
 
-use mhd_memory::{Sample, ScoreType}; // Not used: NUM_BYTES
-use optimizer::Solution;
+use mhd_memory::util::get_bit;
+use mhd_memory::{Sample, SampleBytes, ScoreType}; // Not used: NUM_BYTES
+use optimizer::{Neighborhood, Solution};
 // use mhd_optimizer::Solver;
 
+/// From this many decisions on, `apply_rules` (which looks at every decision) takes longer
+/// than handing a child over to another thread, see `Problem::parallel_children`.
+pub const PARALLEL_CHILDREN_MIN_SIZE: usize = 2048;
+
 /// ## The Problem Trait
 ///
-pub trait Problem: Sized + Clone + Debug {
+pub trait Problem: Sized + Clone + Debug + Sync {
     // Every Problem will probably need it's own "associated" solution type
-    type Sol: Solution;
+    type Sol: Solution + Send + Sync;
 
     /// Every instance of this struct should have a descriptive name (for tracing, debugging).
     /// Default works, but is very long (override it to make it friendlier).
@@ -137,6 +145,8 @@ pub trait Problem: Sized + Clone + Debug {
     /// legality. Only legal children are returned (so there can be 0, 1 or 2).
     /// Actually, there should only be two, if make_implicit_decisions is correct.
     /// TODO: Fix this to return a pair, not a vector
+    /// If `parallel_children`, the two children are produced concurrently (but returned
+    /// in the same order), so the search is the same either way -- just faster.
     #[inline]
     fn children_of_solution(&self, parent: &Self::Sol) -> Vec<Self::Sol> {
         debug_assert!(self.rules_audit_passed(parent));
@@ -147,6 +157,16 @@ pub trait Problem: Sized + Clone + Debug {
             .first_open_decision(parent)
             .expect("There must be an open decision");
 
+        if self.parallel_children() {
+            let (true_child, false_child) = rayon::join(
+                || self.produce_child(parent, index, true),
+                || self.produce_child(parent, index, false),
+            );
+            result.push(true_child);
+            result.push(false_child);
+            return result;
+        };
+
         // The order of the next two operations is important!
         // Try deciding TRUE
         result.push(self.produce_child(parent, index, true));
@@ -157,6 +177,14 @@ pub trait Problem: Sized + Clone + Debug {
         result
     } // end children_of_solution
 
+    /// Whether `children_of_solution` should produce its two children concurrently.
+    /// Worth it only if `apply_rules` is expensive -- by default, for large problems.
+    /// Problems with (much) more expensive rules should override this.
+    #[inline]
+    fn parallel_children(&self) -> bool {
+        PARALLEL_CHILDREN_MIN_SIZE <= self.problem_size()
+    }
+
     fn random_completion(&self, solution: &Self::Sol, index: usize, decision: bool) -> Self::Sol {
         let mut generator = thread_rng();
         let mut result = solution.clone();
@@ -180,14 +208,82 @@ pub trait Problem: Sized + Clone + Debug {
         } // end loop
     } // end random_completion
 
+    /// A quick, greedy choice for decision `index` of `solution`, for heuristics to use.
+    /// Default: decide true if that is legal and does not lower the bound (the best score).
+    fn greedy_decision(&self, solution: &Self::Sol, index: usize) -> bool {
+        let mut with = solution.clone();
+        with.make_decision(index, true);
+        if !self.solution_is_legal(&with) {
+            return false;
+        };
+        let mut without = solution.clone();
+        without.make_decision(index, false);
+        self.solution_best_score(&without) <= self.solution_best_score(&with)
+    }
+
+    /// The order a greedy construction makes the decisions in, most promising first
+    /// (see `GreedySolver`). Default: in index order.
+    fn greedy_order(&self) -> Vec<usize> {
+        (0..self.problem_size()).collect()
+    }
+
+    /// A hash of the state of (partial) `solution`: two solutions with the same key can be
+    /// completed the same ways, to the same scores -- so a search may share what it learns
+    /// about them (e.g. a transposition table, see `MonteCarloTreeSolver::with_transpositions`).
+    /// Default: the decisions made (and which ones they are). Problems whose states are
+    /// coarser than that (e.g. only the sum so far matters) should override this.
+    fn transposition_key(&self, solution: &Self::Sol) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for (mask, query) in solution.mask().iter().zip(solution.query().iter()) {
+            (mask, query & mask).hash(&mut hasher); // (the open decisions' bits don't count)
+        }
+        hasher.finish()
+    }
+
+    /// All legal solutions within (masked) hamming distance `max_flips` of `solution`,
+    /// produced lazily, with the rules applied. See `Neighborhood`.
+    #[inline]
+    fn neighbors<'a>(&'a self, solution: &Self::Sol, max_flips: usize) -> Neighborhood<'a, Self> {
+        Neighborhood::new(self, solution, max_flips)
+    }
+
     #[inline]
     fn sample_from_solution(&self, solution: &Self::Sol) -> Sample {
         debug_assert!(self.solution_is_complete(solution));
         let result = Sample {
             width: self.problem_size(),
             score: self.solution_score(solution),
-            bytes: solution.query().to_vec(),
+            bytes: SampleBytes::from(solution.query()),
+            objectives: Vec::new(),
+            known: None,
         };
         result
     } // end sample_from_solution
+
+    /// The inverse of `sample_from_solution`: a complete solution with the sample's decisions,
+    /// and this problem's scores for them. (Whether it is *legal* is up to the caller to check:
+    /// the sample may come from an older version of the problem, e.g. from a persisted memory.)
+    ///
+    /// ```rust
+    /// use mhd_optimization::optimizer::{Problem, Solution};
+    /// use mhd_optimization::implementations::Problem01Knapsack;
+    ///
+    /// let knapsack = Problem01Knapsack::random(20);
+    /// let solution = knapsack.random_solution();
+    /// let sample = knapsack.sample_from_solution(&solution);
+    /// let copy = knapsack.solution_from_sample(&sample);
+    /// assert!(knapsack.solution_is_complete(&copy));
+    /// assert_eq!(copy.get_score(), solution.get_score());
+    /// assert_eq!(copy.readable(), solution.readable()); // same decisions, same score
+    /// ```
+    fn solution_from_sample(&self, sample: &Sample) -> Self::Sol {
+        debug_assert_eq!(sample.size(), self.problem_size());
+        let mut result = self.starting_solution();
+        for index in 0..self.problem_size() {
+            // Samples hold a solution's query bytes, so use the solutions' bit order
+            result.make_decision(index, get_bit(&sample.bytes, index));
+        }
+        self.fix_scores(&mut result);
+        result
+    } // end solution_from_sample
 } // end trait Problem
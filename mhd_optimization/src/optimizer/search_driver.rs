@@ -0,0 +1,272 @@
+/// # Driving a Search, Step by Step
+///
+/// `Solver::step` makes one iteration of the unified optimization loop; everything else
+/// `find_best_solution` does -- counting steps, timing, checking the limits of its
+/// `SolverConfig`, keeping the score for the `SolveReport` -- is up to a `SearchDriver`.
+/// `find_best_solution` (via `continue_search`), `SolveIter` and `AsyncSearch` all drive
+/// their searches with one. So can anyone who wants to interleave a search with their own
+/// logic -- UI updates, reprioritizing, a termination criterion of their own:
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, SearchDriver, Solution, Solver, StepEvent};
+/// use mhd_optimization::implementations::{DepthFirstSolver, ProblemSubsetSum};
+/// use std::time::Duration;
+///
+/// let problem = ProblemSubsetSum::random(16);
+/// let mut solver = DepthFirstSolver::new(16);
+/// solver.start_search(&problem);
+/// let mut driver = SearchDriver::new(&solver, Duration::from_secs(1), None);
+/// let mut new_bests = 0;
+/// while driver.stop_reason().is_none() {
+///     let outcome = driver.step(&mut solver, &problem);
+///     if StepEvent::NewBest == outcome.event {
+///         new_bests += 1; // e.g. update the UI
+///     };
+///     if 1000 <= driver.num_steps() {
+///         break; // had enough
+///     };
+/// }
+/// let report = driver.report(&solver, &problem);
+/// assert_eq!(report.improvements, new_bests);
+/// assert!(problem.solution_is_legal(&report));
+/// ```
+use std::time::{Duration, Instant};
+
+use log::*;
+
+use mhd_memory::{Score, ScoreType};
+use optimizer::{Problem, Solution, SolveReport, Solver, SolverConfig};
+use optimizer::{StepEvent, StepOutcome, StopReason};
+
+#[derive(Debug, Clone)]
+pub struct SearchDriver {
+    config: SolverConfig,
+    target: Option<ScoreType>,
+    start_time: Instant,
+    last_best_time: Instant,
+    time_to_best: Duration,
+    num_steps: u64,
+    num_pushed: u64,
+    num_improvements: u64,
+    max_depth: usize,
+    stop_reason: Option<StopReason>,
+}
+
+impl SearchDriver {
+    /// A driver for `solver`'s search (after `start_search`), within the limits of `config`,
+    /// which also stops as soon as the best score reaches `target` (if there is one).
+    /// The clock starts now.
+    pub fn new<Sol: Solution, S: Solver<Sol> + ?Sized, C: Into<SolverConfig>>(
+        solver: &S,
+        config: C,
+        target: Option<ScoreType>,
+    ) -> Self {
+        let now = Instant::now();
+        let mut driver = Self {
+            config: config.into(),
+            target,
+            start_time: now,
+            last_best_time: now,
+            time_to_best: Duration::ZERO,
+            num_steps: 0,
+            num_pushed: 0,
+            num_improvements: 0,
+            max_depth: 0,
+            stop_reason: None,
+        };
+        // (the random starting solution might already be good enough)
+        if driver.reached_target(solver) {
+            driver.stop_reason = Some(StopReason::TargetReached);
+        };
+        driver
+    }
+
+    fn reached_target<Sol: Solution, S: Solver<Sol> + ?Sized>(&self, solver: &S) -> bool {
+        self.target.is_some_and(|t| t <= solver.best_score())
+    }
+
+    /// Make one step of the search (see `Solver::step`), and check whether it is over.
+    /// Once it is (see `stop_reason`), the solver isn't asked for any more steps.
+    pub fn step<Sol, S, Prob>(&mut self, solver: &mut S, problem: &Prob) -> StepOutcome
+    where
+        Sol: Solution,
+        S: Solver<Sol> + ?Sized,
+        Prob: Problem<Sol = Sol>,
+    {
+        self.step_within(solver, problem, None, &mut |_: &Sol| {})
+    }
+
+    /// `step`, but with `Solver::step_within` (a bound within `epsilon` of the best score,
+    /// and `on_complete` shown every complete solution found)
+    pub fn step_within<Sol, S, Prob, F>(
+        &mut self,
+        solver: &mut S,
+        problem: &Prob,
+        epsilon: Option<ScoreType>,
+        on_complete: &mut F,
+    ) -> StepOutcome
+    where
+        Sol: Solution,
+        S: Solver<Sol> + ?Sized,
+        Prob: Problem<Sol = Sol>,
+        F: FnMut(&Sol),
+    {
+        if self.stop_reason.is_some() {
+            return StepOutcome::FINISHED;
+        };
+        self.num_steps += 1;
+        let outcome = solver.step_within(problem, epsilon, on_complete);
+        self.max_depth = self.max_depth.max(outcome.depth.unwrap_or(0));
+        match outcome.event {
+            StepEvent::NewBest => {
+                // Reset timer!
+                // That means we have converged if we go for time_limit without a new best!
+                self.last_best_time = Instant::now();
+                self.time_to_best = self.elapsed();
+                self.num_improvements += 1;
+                // new_best_solution already gave debug output,
+                // but without start_time or num_visitations...
+                debug!(
+                    "Solver found new BEST after {:?}, {} visitations, score = {} ",
+                    self.time_to_best,
+                    self.num_steps,
+                    solver.best_score()
+                );
+                if self.reached_target(solver) {
+                    self.stop_reason = Some(StopReason::TargetReached);
+                    info!(
+                        "Solver reaches target score {} after {:?}",
+                        solver.best_score(),
+                        self.time_to_best
+                    );
+                };
+            }
+            StepEvent::Pushed(num_children) => self.num_pushed += num_children as u64,
+            _ => {} // Finished, Popped or Pruned -- nothing to do here
+        };
+
+        // Terminate?
+        if self.stop_reason.is_none() {
+            self.stop_reason = if StepEvent::Finished == outcome.event || solver.is_finished() {
+                Some(StopReason::Finished)
+            } else {
+                self.config.limit_reached(
+                    solver,
+                    self.start_time,
+                    self.last_best_time,
+                    self.num_steps,
+                    self.num_pushed,
+                )
+            };
+        };
+        outcome
+    }
+
+    /// Stop the search (for `reason`), unless it has already stopped
+    pub fn stop(&mut self, reason: StopReason) {
+        self.stop_reason.get_or_insert(reason);
+    }
+
+    /// Why the search stopped (None while it is still running)
+    #[inline]
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        self.stop_reason
+    }
+
+    /// Steps made so far
+    #[inline]
+    pub fn num_steps(&self) -> u64 {
+        self.num_steps
+    }
+
+    /// Time since the search started
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    /// The best solution of `solver` (which searched `problem`), and how the search went.
+    /// If it is still running, it counts as stopped by the caller (`StopReason::Cancelled`).
+    pub fn report<Sol, S, Prob>(&self, solver: &S, problem: &Prob) -> SolveReport<Sol>
+    where
+        Sol: Solution,
+        S: Solver<Sol> + ?Sized,
+        Prob: Problem<Sol = Sol>,
+    {
+        // How good could a solution still be? Only an exhaustive solver's frontier can tell
+        // (and what it dropped, if anything).
+        let best_score = solver.best_score();
+        let dropped_bound = solver.dropped_bound();
+        let final_bound = if solver.is_exhaustive() || dropped_bound.is_some() {
+            let bounds = solver
+                .snapshot_frontier(usize::MAX)
+                .into_iter()
+                .map(|e| e.bound);
+            ScoreType::max_of(bounds.chain(dropped_bound).chain(Some(best_score)))
+                .unwrap_or(best_score)
+        } else {
+            let root_bound = problem.solution_best_score(&problem.starting_solution());
+            ScoreType::max_of([root_bound, best_score].iter().copied()).unwrap_or(best_score)
+        };
+
+        SolveReport {
+            best: solver.best_solution().clone(),
+            nodes_visited: self.num_steps,
+            max_depth: self.max_depth,
+            time_to_best: self.time_to_best,
+            improvements: self.num_improvements,
+            elapsed: self.elapsed(),
+            stop_reason: self.stop_reason.unwrap_or(StopReason::Cancelled),
+            final_bound,
+            visited: solver.visited_stats(),
+        }
+    }
+} // end impl SearchDriver
+
+///////////////////// TESTs for SearchDriver /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{DepthFirstSolver, ProblemSubsetSum};
+
+    #[test]
+    fn test_search_driver() {
+        const NUM_DECISIONS: usize = 12;
+        let problem = ProblemSubsetSum::random(NUM_DECISIONS);
+        let mut solver = DepthFirstSolver::new(NUM_DECISIONS);
+
+        // All the way, step by step
+        solver.start_search(&problem);
+        let mut driver = SearchDriver::new(&solver, Duration::from_secs(1), None);
+        let mut outcomes = Vec::new();
+        while driver.stop_reason().is_none() {
+            outcomes.push(driver.step(&mut solver, &problem));
+        }
+        assert_eq!(driver.stop_reason(), Some(StopReason::Finished));
+        assert_eq!(driver.num_steps(), outcomes.len() as u64);
+        // ...and not a step further
+        assert_eq!(driver.step(&mut solver, &problem), StepOutcome::FINISHED);
+        assert_eq!(driver.num_steps(), outcomes.len() as u64);
+        let report = driver.report(&solver, &problem);
+        assert_eq!(report.get_score(), problem.capacity);
+        let deepest = outcomes.iter().filter_map(|o| o.depth).max();
+        assert_eq!(Some(report.max_depth), deepest);
+
+        // Stopped by the caller
+        solver.start_search(&problem);
+        let mut driver = SearchDriver::new(&solver, Duration::from_secs(1), None);
+        driver.step(&mut solver, &problem);
+        assert_eq!(
+            driver.report(&solver, &problem).stop_reason,
+            StopReason::Cancelled
+        );
+        driver.stop(StopReason::NodeLimit);
+        driver.stop(StopReason::TimeLimit); // (too late)
+        assert_eq!(driver.stop_reason(), Some(StopReason::NodeLimit));
+
+        // A target reached before the first step
+        solver.start_search(&problem);
+        let driver = SearchDriver::new(&solver, Duration::from_secs(1), Some(ScoreType::ZERO));
+        assert_eq!(driver.stop_reason(), Some(StopReason::TargetReached));
+    }
+}
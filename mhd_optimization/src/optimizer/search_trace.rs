@@ -0,0 +1,205 @@
+/// # Recording a Search (for Notebooks)
+///
+/// A `SearchTrace` records every step of the unified optimization loop -- which event
+/// happened, when, how big the frontier was and what the best score so far was --
+/// and can write all that out as JSON, ready to be loaded into a Jupyter notebook
+/// (e.g. with `pandas.json_normalize(json.load(f)["events"])`) or an observable plot.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, SearchTrace, Solver};
+/// use mhd_optimization::implementations::{DepthFirstSolver, ProblemSubsetSum};
+/// use std::time::Duration;
+///
+/// let problem = ProblemSubsetSum::random(8);
+/// let mut solver = DepthFirstSolver::new(8);
+/// let trace = SearchTrace::record(&mut solver, &problem, Duration::from_secs(1));
+///
+/// // One event per step (a lucky start may need no branching at all)
+/// assert!(!trace.events.is_empty());
+/// assert!(trace.nodes_expanded() <= trace.events.len());
+/// assert_eq!(trace.final_score(), problem.capacity);
+/// assert!(trace.to_json().starts_with("{"));
+/// ```
+use std::io;
+use std::time::{Duration, Instant};
+
+use mhd_memory::{ScoreType, ZERO_SCORE};
+use optimizer::{Problem, Solution, Solver, StepEvent};
+
+/// One line in the trace: what one call to `Solver::step` did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEvent {
+    pub visit: usize,
+    pub elapsed: Duration,
+    pub event: StepEvent,
+    pub incumbent: ScoreType, // best score so far, AFTER the step
+    pub frontier: usize,      // number of solutions in the solver, AFTER the step
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchTrace {
+    pub solver: String,
+    pub problem: String,
+    pub problem_size: usize,
+    pub root_bound: ScoreType,  // best score of the starting solution
+    pub start_score: ScoreType, // best score so far BEFORE the first step
+    pub events: Vec<TraceEvent>,
+}
+
+impl SearchTrace {
+    /// Run a search -- just like `Solver::find_best_solution` does --
+    /// and record every step on the way.
+    pub fn record<Sol: Solution, Solv: Solver<Sol>, Prob: Problem<Sol = Sol>>(
+        solver: &mut Solv,
+        problem: &Prob,
+        time_limit: Duration,
+    ) -> Self {
+        let mut result = Self {
+            solver: solver.name().trim().to_string(),
+            problem: problem.name().to_string(),
+            problem_size: problem.problem_size(),
+            root_bound: problem.starting_solution().get_best_score(),
+            start_score: ZERO_SCORE,
+            events: Vec::new(),
+        };
+
+        let global_start_time = Instant::now();
+        let mut start_time = Instant::now();
+
+        solver.start_search(problem);
+        result.start_score = solver.best_score();
+        loop {
+            let event = solver.step(problem).event;
+            if StepEvent::NewBest == event {
+                start_time = Instant::now(); // reset convergence timer, see find_best_solution
+            };
+            result.events.push(TraceEvent {
+                visit: result.events.len() + 1,
+                elapsed: global_start_time.elapsed(),
+                event,
+                incumbent: solver.best_score(),
+                frontier: solver.number_of_solutions(),
+            });
+            if StepEvent::Finished == event
+                || solver.is_finished()
+                || time_limit < start_time.elapsed()
+            {
+                break;
+            };
+        } // end loop
+        result
+    }
+
+    /// How many solutions were branched, i.e. how many steps pushed children?
+    pub fn nodes_expanded(&self) -> usize {
+        self.events
+            .iter()
+            .filter(|e| matches!(e.event, StepEvent::Pushed(_)))
+            .count()
+    }
+
+    /// The best score at the end of the recorded search.
+    pub fn final_score(&self) -> ScoreType {
+        self.events.last().map_or(ZERO_SCORE, |e| e.incumbent)
+    }
+
+    /// The convergence curve: every time the best score improved, and the new best score.
+    pub fn incumbents(&self) -> Vec<(Duration, ScoreType)> {
+        self.events
+            .iter()
+            .filter(|e| StepEvent::NewBest == e.event)
+            .map(|e| (e.elapsed, e.incumbent))
+            .collect()
+    }
+
+    /// Write the trace as one JSON object, with one event per line.
+    pub fn write_json<W: io::Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"solver\": \"{}\",", json_escape(&self.solver))?;
+        writeln!(out, "  \"problem\": \"{}\",", json_escape(&self.problem))?;
+        writeln!(out, "  \"problem_size\": {},", self.problem_size)?;
+        writeln!(out, "  \"root_bound\": {},", self.root_bound)?;
+        writeln!(out, "  \"start_score\": {},", self.start_score)?;
+        writeln!(out, "  \"nodes_expanded\": {},", self.nodes_expanded())?;
+        writeln!(out, "  \"events\": [")?;
+        for (index, e) in self.events.iter().enumerate() {
+            let (name, children) = match e.event {
+                StepEvent::Popped => ("popped", 0),
+                StepEvent::Pushed(n) => ("pushed", n),
+                StepEvent::NewBest => ("new_best", 0),
+                StepEvent::Pruned => ("pruned", 0),
+                StepEvent::Finished => ("finished", 0),
+            };
+            let separator = if index + 1 < self.events.len() { "," } else { "" };
+            writeln!(
+                out,
+                "    {{\"visit\": {}, \"elapsed_us\": {}, \"event\": \"{}\", \"children\": {}, \"incumbent\": {}, \"frontier\": {}}}{}",
+                e.visit,
+                e.elapsed.as_micros(),
+                name,
+                children,
+                e.incumbent,
+                e.frontier,
+                separator
+            )?;
+        } // end for all events
+        writeln!(out, "  ]")?;
+        writeln!(out, "}}")
+    }
+
+    /// Same as `write_json`, but into a String
+    pub fn to_json(&self) -> String {
+        let mut buffer = Vec::new();
+        self.write_json(&mut buffer)
+            .expect("Writing to a Vec cannot fail");
+        String::from_utf8(buffer).expect("JSON is always valid UTF-8")
+    }
+} // end impl SearchTrace
+
+// Names are our own, but who knows what users call their solvers...
+fn json_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+///////////////////// TESTs for SearchTrace /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{BestFirstSolver, Problem01Knapsack};
+
+    #[test]
+    fn test_record_and_export() {
+        const NUM_DECISIONS: usize = 8;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let mut solver = BestFirstSolver::new(NUM_DECISIONS);
+
+        let trace = SearchTrace::record(&mut solver, &knapsack, Duration::from_secs(1));
+
+        assert_eq!(trace.problem, "Problem01Knapsack");
+        assert_eq!(trace.problem_size, NUM_DECISIONS);
+        assert!(!trace.events.is_empty());
+        assert_eq!(trace.final_score(), solver.best_score());
+        assert!(trace.final_score() <= trace.root_bound);
+        assert!(trace.start_score <= trace.final_score());
+
+        // The incumbent never gets worse
+        let incumbents = trace.incumbents();
+        for pair in incumbents.windows(2) {
+            assert!(pair[0].0 <= pair[1].0);
+            assert!(pair[0].1 < pair[1].1);
+        }
+
+        let json = trace.to_json();
+        assert!(json.starts_with("{\n"));
+        assert!(json.trim_end().ends_with('}'));
+        assert!(json.contains("\"solver\": \"BestFirstSolver\""));
+        // header (8 lines), one line per event, two closing lines
+        assert_eq!(json.lines().count(), 8 + trace.events.len() + 2);
+    }
+
+    #[test]
+    fn test_json_escape() {
+        assert_eq!(json_escape("plain"), "plain");
+        assert_eq!(json_escape("a \"quoted\" \\ b"), "a \\\"quoted\\\" \\\\ b");
+    }
+}
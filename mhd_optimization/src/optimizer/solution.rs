@@ -3,7 +3,7 @@
 ///
 use std::fmt::Debug; // or {Debug, Display}, if necessary ever again...
 
-use mhd_memory::{ScoreType, ZERO_SCORE};
+use mhd_memory::{Score, ScoreType, ZERO_SCORE};
 
 pub type PriorityType = f32; // that can change at any time, so we give it a name
 
@@ -119,13 +119,15 @@ pub trait Solution: Sized + Clone + Ord + Debug {
 /// Examples:
 /// ```rust
 /// use mhd_optimization::optimizer::{ Solution, PriorityType, MinimalSolution };
+/// # extern crate mhd_memory;
+/// use mhd_memory::{ScoreType, ZERO_SCORE};
 /// let sol0 = MinimalSolution::new( 8 );
 /// let sol1 = MinimalSolution::random( 8 );
 ///
 /// assert_eq!( sol0.name(), "MinimalSolution");
-/// assert_eq!( sol0.get_score(), 0 );
+/// assert_eq!( sol0.get_score(), ZERO_SCORE );
 /// // assert_eq!( sol0.get_score(), sol1.get_score() );
-/// assert_eq!( sol0.get_best_score(), 0 );
+/// assert_eq!( sol0.get_best_score(), ZERO_SCORE );
 /// // assert_eq!( sol0.get_best_score(), sol1.get_best_score() );
 /// assert_eq!( sol0.get_decision( 0 ), None );
 ///
@@ -142,10 +144,10 @@ pub trait Solution: Sized + Clone + Ord + Debug {
 /// assert!(   sol2.get_decision( 2 ).unwrap( ) );
 /// assert_eq!( sol0.get_decision( 3 ), None );
 ///
-/// sol2.put_score(      42  );
-/// sol2.put_best_score( 88  );
-/// assert_eq!( sol2.get_score(),      42  );
-/// assert_eq!( sol2.get_best_score(), 88  );
+/// sol2.put_score(      42 as ScoreType );
+/// sol2.put_best_score( 88 as ScoreType );
+/// assert_eq!( sol2.get_score(),      42 as ScoreType );
+/// assert_eq!( sol2.get_best_score(), 88 as ScoreType );
 ///
 /// let mut sol3 = MinimalSolution::new( 4 );
 /// sol2.set_priority( 42.00 as PriorityType );
@@ -155,17 +157,55 @@ pub trait Solution: Sized + Clone + Ord + Debug {
 /// assert!( sol2 < sol3 );
 /// assert!( ! (sol2 == sol3) );
 /// ```
+///
+/// Mask and decisions are stored in 64 bit words, so the bulk operations solvers need
+/// most -- randomizing, finding the first open decision, counting the decisions made,
+/// copying a solution to branch on it -- work a word (not a bit or byte) at a time:
+/// ```rust
+/// use mhd_optimization::optimizer::{ Solution, MinimalSolution };
+/// let mut sol = MinimalSolution::new( 100 );
+/// sol.make_decision( 0, true );
+/// sol.make_decision( 70, false );
+/// assert_eq!( sol.num_decisions_made(), 2 );
+/// assert_eq!( sol.first_open_decision(), Some( 1 ) );
+/// assert_eq!( sol.last_closed_decision(), Some( 70 ) );
+///
+/// let child = sol.branch( 1, true );
+/// assert_eq!( child.true_decisions().collect::<Vec<_>>(), vec![ 0, 1 ] );
+/// assert_eq!( child.mask().len(), 13 ); // still a byte slice, one bit per decision
+/// assert_eq!( MinimalSolution::random( 100 ).first_open_decision(), None );
+/// ```
 use rand::prelude::*;
 use std::cmp::Ordering;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use mhd_memory::util::*; // pub fn get_bit( bytes: &[u8], bit_index: usize ) -> bool
+use mhd_memory::{Sample, SampleBytes};
                          // use std::fmt::Display <-- Already imported, above
 
+const BITS_PER_WORD: usize = 64;
+
+// The word holding the bit of a decision, and the bit itself (in that word).
+// Words are stored little endian, so that their bytes are in the same order as their bits,
+// and `mask` and `query` can return the words (`SampleBytes`, as in the MHD memory's samples)
+// as the byte slices the MHD functions expect.
+#[inline]
+fn word_and_bit(decision_number: usize) -> (usize, u64) {
+    (
+        decision_number / BITS_PER_WORD,
+        (1u64 << (decision_number % BITS_PER_WORD)).to_le(),
+    )
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MinimalSolution {
     pub size: usize,
-    pub mask: Vec<u8>, // we could have used Vec<u8> (twice) here (and saved two scores),
-    pub decisions: Vec<u8>, // but we wouldn't have the get_bit and set_bit methods!
+    // One bit per decision (bits beyond `size` are always zero)
+    mask: SampleBytes,
+    decisions: SampleBytes,
     pub score: ScoreType,
     pub best_score: ScoreType, // best score possible
     pub priority: PriorityType,
@@ -193,11 +233,13 @@ impl Solution for MinimalSolution {
 
     #[inline]
     fn new(size: usize) -> Self {
-        let num_bytes = (size as f32 / 8.0).ceil() as usize;
+        let num_bytes = size.div_ceil(8);
         Self {
-            size,                            // idiomatic rust for "size: size"
-            mask: vec![0x0; num_bytes],      // all zeros == no decision made yet
-            decisions: vec![0x0; num_bytes], // all zeros == all decisions are false (zero)
+            size, // idiomatic rust for "size: size"
+            // all zeros == no decision made yet
+            mask: SampleBytes::from_elem(0x0, num_bytes),
+            // all zeros == all decisions are false (zero)
+            decisions: SampleBytes::from_elem(0x0, num_bytes),
             score: ZERO_SCORE,
             best_score: ZERO_SCORE,
             priority: 0.0,
@@ -206,12 +248,16 @@ impl Solution for MinimalSolution {
 
     #[inline]
     fn randomize(&mut self) {
-        const TOP_SCORE: ScoreType = 1000;
+        const TOP_SCORE: u32 = 1000;
         let mut generator = thread_rng();
-        self.mask = vec![0xFF; self.mask.len()];
-        generator.fill_bytes(&mut self.decisions);
-        self.score = generator.gen_range(1..=TOP_SCORE); //  as ScoreType;
-        self.best_score = self.score + generator.gen_range(1..=TOP_SCORE); // as ScoreType
+        for index in 0..self.mask.words().len() {
+            let used = self.used_bits(index);
+            self.mask.words_mut()[index] = used;
+            self.decisions.words_mut()[index] = generator.gen::<u64>() & used;
+        }
+        let mut random_score = || ScoreType::from_f64(generator.gen_range(1..=TOP_SCORE) as f64);
+        self.score = random_score();
+        self.best_score = self.score + random_score();
     }
 
     // Getters and Setters
@@ -262,21 +308,113 @@ impl Solution for MinimalSolution {
 
     #[inline]
     fn get_decision(&self, decision_number: usize) -> Option<bool> {
-        if !get_bit(&self.mask, decision_number) {
+        debug_assert!(decision_number < self.size);
+        let (word, bit) = word_and_bit(decision_number);
+        if 0 == self.mask.words()[word] & bit {
             None
         } else {
             // if bit is masked ==> Decision is made
-            Some(get_bit(&self.decisions, decision_number))
+            Some(0 != self.decisions.words()[word] & bit)
         }
     }
 
     #[inline]
     fn make_decision(&mut self, decision_number: usize, decision: bool) {
-        put_bit(&mut self.mask, decision_number, true);
-        put_bit(&mut self.decisions, decision_number, decision);
+        debug_assert!(decision_number < self.size);
+        let (word, bit) = word_and_bit(decision_number);
+        self.mask.words_mut()[word] |= bit;
+        if decision {
+            self.decisions.words_mut()[word] |= bit;
+        } else {
+            self.decisions.words_mut()[word] &= !bit;
+        }
     }
 } // end impl Soluton for MinimalSolution
 
+impl MinimalSolution {
+    // The bits of word `index` which stand for decisions (all but the padding of the last word)
+    #[inline]
+    fn used_bits(&self, index: usize) -> u64 {
+        let first = index * BITS_PER_WORD;
+        if self.size < first + BITS_PER_WORD {
+            ((1u64 << (self.size - first)) - 1).to_le()
+        } else {
+            !0
+        }
+    }
+
+    /// The number of decisions made (a popcount of the mask)
+    #[inline]
+    pub fn num_decisions_made(&self) -> usize {
+        self.mask
+            .words()
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// The smallest index of an open decision, if any
+    pub fn first_open_decision(&self) -> Option<usize> {
+        iter_open_bits(self.mask())
+            .next()
+            .filter(|index| *index < self.size)
+    }
+
+    /// The largest index of a decision made, if any
+    pub fn last_closed_decision(&self) -> Option<usize> {
+        self.mask
+            .words()
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, word)| 0 != **word)
+            .map(|(index, word)| {
+                (index + 1) * BITS_PER_WORD - 1 - u64::from_le(*word).leading_zeros() as usize
+            })
+    }
+
+    /// The indices of all decisions made and set to true, in ascending order
+    pub fn true_decisions(&self) -> impl Iterator<Item = usize> + '_ {
+        self.mask
+            .words()
+            .iter()
+            .zip(self.decisions.words().iter())
+            .enumerate()
+            .flat_map(|(index, (mask, decisions))| {
+                let mut ones = u64::from_le(mask & decisions);
+                std::iter::from_fn(move || {
+                    if 0 == ones {
+                        return None;
+                    };
+                    let bit = ones.trailing_zeros() as usize;
+                    ones &= ones - 1; // clear the lowest one
+                    Some(index * BITS_PER_WORD + bit)
+                })
+            })
+    }
+
+    /// A copy of this solution (to branch on) with one more decision made
+    #[inline]
+    pub fn branch(&self, decision_number: usize, decision: bool) -> Self {
+        let mut child = self.clone();
+        child.make_decision(decision_number, decision);
+        child
+    }
+
+    /// A complete solution with the decisions of an MHD memory sample, and its score
+    /// (as score and best score). See also `Problem::solution_from_sample`, which
+    /// (re)calculates the scores for a given problem.
+    pub fn from_sample(sample: &Sample) -> Self {
+        let mut result = Self::new(sample.size());
+        for index in 0..sample.size() {
+            result.make_decision(index, get_bit(&sample.bytes, index));
+        }
+        result.score = sample.score;
+        result.best_score = sample.score;
+        result
+    }
+}
+
 /// ## Default Sorting Implementations
 use std::cmp::*;
 
@@ -317,6 +455,8 @@ mod more_tests {
         assert_eq!(9, sol9.size());
         let sol23 = MinimalSolution::new(23);
         assert_eq!(23, sol23.size());
+        assert_eq!(3, sol23.mask().len());
+        assert_eq!(3, sol23.query().len());
 
         assert_eq!("MinimalSolution", sol23.name());
         assert_eq!(
@@ -329,13 +469,110 @@ mod more_tests {
         assert_eq!(Some(true), sol.get_decision(17));
         assert_eq!(None, sol.get_decision(41));
 
-        sol.put_score(42);
-        sol.put_best_score(4242);
-        assert_eq!(42, sol.get_score());
-        assert_eq!(4242, sol.get_best_score());
+        sol.put_score(42 as ScoreType);
+        sol.put_best_score(4242 as ScoreType);
+        assert_eq!(42 as ScoreType, sol.get_score());
+        assert_eq!(4242 as ScoreType, sol.get_best_score());
 
         assert_eq!(0.0, sol.priority());
         sol.set_priority(42.42);
         assert_eq!(42.42, sol.priority());
     }
+
+    #[test]
+    fn test_word_operations() {
+        for size in [1, 7, 63, 64, 65, 130].iter().cloned() {
+            let mut sol = MinimalSolution::new(size);
+            assert_eq!(0, sol.num_decisions_made());
+            assert_eq!(Some(0), sol.first_open_decision());
+            assert_eq!(None, sol.last_closed_decision());
+            for index in 0..size {
+                sol.make_decision(index, 0 == index % 3);
+                assert_eq!(index + 1, sol.num_decisions_made());
+                assert_eq!(Some(index), sol.last_closed_decision());
+                let next = if index + 1 < size {
+                    Some(index + 1)
+                } else {
+                    None
+                };
+                assert_eq!(next, sol.first_open_decision());
+            }
+            let expected: Vec<usize> = (0..size).step_by(3).collect();
+            assert_eq!(expected, sol.true_decisions().collect::<Vec<usize>>());
+            // The byte view agrees with the bits
+            for index in 0..size {
+                assert!(get_bit(sol.mask(), index));
+                assert_eq!(0 == index % 3, get_bit(sol.query(), index));
+            }
+
+            let random = MinimalSolution::random(size);
+            assert_eq!(size, random.num_decisions_made());
+            assert_eq!(None, random.first_open_decision());
+            assert_eq!(Some(size - 1), random.last_closed_decision());
+            assert_eq!(size, mhd_memory::weight(random.mask()) as usize);
+        }
+
+        // Branching copies, the parent stays as it was
+        let mut parent = MinimalSolution::new(100);
+        parent.make_decision(99, true);
+        let child = parent.branch(50, false);
+        assert_eq!(Some(false), child.get_decision(50));
+        assert_eq!(None, parent.get_decision(50));
+        assert_eq!(vec![99], child.true_decisions().collect::<Vec<usize>>());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut sol = MinimalSolution::random(70);
+        sol.set_priority(4.2);
+        let json = serde_json::to_string(&sol).expect("could not serialize");
+        let copy: MinimalSolution = serde_json::from_str(&json).expect("could not deserialize");
+        assert_eq!(copy, sol);
+        assert_eq!(copy.readable(), sol.readable());
+    }
+
+    #[test]
+    fn test_from_sample() {
+        use implementations::ProblemSubsetSum;
+        use optimizer::Problem;
+
+        let sample = Sample::random(23);
+        let sol = MinimalSolution::from_sample(&sample);
+        assert_eq!(23, sol.size());
+        for index in 0..23 {
+            assert_eq!(Some(get_bit(&sample.bytes, index)), sol.get_decision(index));
+        }
+        assert_eq!(sample.score, sol.get_score());
+        assert_eq!(sample.score, sol.get_best_score());
+
+        // Round trip through a problem, which recalculates the score
+        let problem = ProblemSubsetSum::random(23);
+        let original = problem.random_solution();
+        let sample = problem.sample_from_solution(&original);
+        let copy = problem.solution_from_sample(&sample);
+        assert!(problem.solution_is_complete(&copy));
+        assert_eq!(copy.get_score(), original.get_score());
+        assert_eq!(copy.get_best_score(), original.get_best_score());
+        // (the query bytes may differ in their padding bits)
+        assert_eq!(copy.readable(), original.readable());
+        let direct = MinimalSolution::from_sample(&sample);
+        assert!((0..23).all(|index| direct.get_decision(index) == original.get_decision(index)));
+    }
+
+    #[test]
+    fn test_from_solution_bits() {
+        use implementations::Problem01Knapsack;
+        use optimizer::Problem;
+
+        for size in [4, 7, 8, 12, 23, 64, 65] {
+            let problem = Problem01Knapsack::random(size);
+            let solution = problem.random_solution();
+            let decisions: Vec<bool> = (0..size)
+                .map(|index| solution.get_decision(index).expect("complete"))
+                .collect();
+            let sample = Sample::from_solution_bits(&decisions, solution.get_score());
+            assert_eq!(sample, problem.sample_from_solution(&solution));
+        }
+    }
 }
@@ -0,0 +1,136 @@
+/// # Anytime Search: an Iterator of Improving Solutions
+///
+/// `find_best_solution` blocks until the search stops. `Solver::solve_iter` returns a
+/// `SolveIter` instead: an iterator which yields the incumbent (the best solution so far)
+/// first, and then every new best solution as soon as it is found -- each one better than
+/// the one before. It searches only while it is asked for the next one, so the caller can
+/// take the last one it got whenever its own deadline hits, and simply stop asking.
+/// The iterator ends when the search stops (at the limits of its `SolverConfig`,
+/// see `stop_reason`).
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, Solution, Solver, StopReason};
+/// use mhd_optimization::implementations::{DepthFirstSolver, ProblemSubsetSum};
+/// use std::time::{Duration, Instant};
+///
+/// let problem = ProblemSubsetSum::random(16);
+/// let mut solver = DepthFirstSolver::new(16);
+///
+/// // Take the best solution found within 100 milliseconds (or sooner, if the search ends)
+/// let deadline = Instant::now() + Duration::from_millis(100);
+/// let mut solutions = solver.solve_iter(&problem, Duration::from_secs(1));
+/// let mut best = solutions.next().expect("the incumbent comes first");
+/// for better in solutions.by_ref() {
+///     assert!(best.get_score() < better.get_score());
+///     best = better;
+///     if deadline < Instant::now() {
+///         break;
+///     };
+/// }
+/// assert!(problem.solution_is_legal(&best));
+/// ```
+use optimizer::{Problem, SearchDriver, Solver, SolverConfig, StepEvent, StopReason};
+
+pub struct SolveIter<'a, S: 'a, Prob: 'a + Problem>
+where
+    S: Solver<Prob::Sol>,
+{
+    solver: &'a mut S,
+    problem: &'a Prob,
+    config: SolverConfig,
+    driver: Option<SearchDriver>, // once the search started
+}
+
+impl<'a, S, Prob: Problem> SolveIter<'a, S, Prob>
+where
+    S: Solver<Prob::Sol>,
+{
+    /// A search of `problem` with `solver`, like `find_best_solution`
+    /// (which calls `start_search` at the first `next`, so don't call it yourself)
+    pub fn new<C: Into<SolverConfig>>(solver: &'a mut S, problem: &'a Prob, config: C) -> Self {
+        Self {
+            solver,
+            problem,
+            config: config.into(),
+            driver: None,
+        }
+    }
+
+    /// Why the search stopped (None while it is still running)
+    #[inline]
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        self.driver.as_ref().and_then(SearchDriver::stop_reason)
+    }
+
+    /// Steps made so far
+    #[inline]
+    pub fn num_steps(&self) -> u64 {
+        self.driver.as_ref().map_or(0, SearchDriver::num_steps)
+    }
+}
+
+impl<'a, S, Prob: Problem> Iterator for SolveIter<'a, S, Prob>
+where
+    S: Solver<Prob::Sol>,
+{
+    type Item = Prob::Sol;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let driver = match self.driver.as_mut() {
+            Some(driver) => driver,
+            None => {
+                if let Some(seed) = self.config.seed {
+                    self.solver.reseed(seed);
+                };
+                self.solver.start_search(self.problem);
+                let driver = SearchDriver::new(&*self.solver, self.config.clone(), None);
+                self.driver = Some(driver);
+                return Some(self.solver.best_solution().clone()); // the incumbent
+            }
+        };
+
+        while driver.stop_reason().is_none() {
+            if StepEvent::NewBest == driver.step(self.solver, self.problem).event {
+                return Some(self.solver.best_solution().clone());
+            };
+        } // end while not stopped
+        None
+    }
+}
+
+///////////////////// TESTs for SolveIter /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{DepthFirstSolver, ExhaustiveSolver, Problem01Knapsack};
+    use optimizer::Solution;
+    use std::time::Duration;
+
+    #[test]
+    fn test_solve_iter() {
+        const NUM_DECISIONS: usize = 14;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let optimum = ExhaustiveSolver::builder(&knapsack).solve().get_score();
+        let mut solver = DepthFirstSolver::new(NUM_DECISIONS);
+
+        // Better and better, up to the optimum, then the search is finished
+        let mut solutions = solver.solve_iter(&knapsack, Duration::from_secs(10));
+        let scores: Vec<_> = solutions.by_ref().map(|s| s.get_score()).collect();
+        assert!(scores.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(scores.last(), Some(&optimum));
+        assert_eq!(solutions.stop_reason(), Some(StopReason::Finished));
+        assert!(0 < solutions.num_steps());
+        assert_eq!(solutions.next(), None);
+
+        // At the step limit: no further
+        let config = SolverConfig {
+            node_limit: Some(1),
+            ..Default::default()
+        };
+        let mut solutions = solver.solve_iter(&knapsack, config);
+        assert!(solutions.next().is_some()); // the incumbent, without a step
+        assert_eq!(solutions.num_steps(), 0);
+        while solutions.next().is_some() {}
+        assert_eq!(solutions.num_steps(), 1);
+    }
+}
@@ -0,0 +1,176 @@
+/// # What a Search Found, and How
+///
+/// `find_best_solution` returns a `SolveReport`: the best solution, and the story of the
+/// search behind it -- how many nodes it visited, how deep it got, when it found its best
+/// solution (after how many improvements), why it stopped, and how good a solution could
+/// still be (the final bound). A report dereferences to its best solution, so it can be
+/// used much like one.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, Solution, Solver, StopReason};
+/// use mhd_optimization::implementations::{DepthFirstSolver, ProblemSubsetSum};
+/// use std::time::Duration;
+///
+/// let problem = ProblemSubsetSum::random(12);
+/// let mut solver = DepthFirstSolver::new(12);
+/// let report = solver
+///     .find_best_solution(&problem, Duration::from_secs(1))
+///     .unwrap();
+/// assert!(problem.solution_is_legal(&report.best));
+/// assert_eq!(report.get_score(), problem.capacity); // (via Deref)
+/// assert_eq!(report.stop_reason, StopReason::Finished);
+/// assert!(0 < report.nodes_visited);
+/// assert!(report.max_depth <= 12);
+/// assert!(report.time_to_best <= report.elapsed);
+/// // An exhaustive search which finished has proved its best solution optimal
+/// assert_eq!(report.final_bound, report.get_score());
+/// assert_eq!(report.gap(), 0.0);
+/// ```
+use std::fmt;
+use std::ops::Deref;
+use std::time::Duration;
+
+use mhd_memory::{Score, ScoreType};
+use optimizer::{Solution, StopReason, VisitedStats};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolveReport<Sol> {
+    /// The best solution found
+    pub best: Sol,
+    /// Iterations of the loop (calls to `Solver::step`)
+    pub nodes_visited: u64,
+    /// The most decisions made in any solution popped
+    pub max_depth: usize,
+    /// When the best solution was found (zero, if it was there from the start)
+    pub time_to_best: Duration,
+    /// How often a better solution was found
+    pub improvements: u64,
+    /// How long the whole search took
+    pub elapsed: Duration,
+    pub stop_reason: StopReason,
+    /// The best score any solution could still have, as far as the solver knows:
+    /// the best score itself, if the search was exhaustive and finished
+    /// (see `Solver::is_exhaustive`), the bound of the starting solution at worst.
+    pub final_bound: ScoreType,
+    /// How many duplicate states were skipped, if the solver has a `VisitedSet`
+    pub visited: Option<VisitedStats>,
+}
+
+impl<Sol: Solution> SolveReport<Sol> {
+    /// The time the target score was reached -- None unless that is why the search stopped
+    #[inline]
+    pub fn time_to_target(&self) -> Option<Duration> {
+        if StopReason::TargetReached == self.stop_reason {
+            Some(self.time_to_best)
+        } else {
+            None
+        }
+    }
+
+    /// How far the best score might still be from the optimum, relative to the final bound:
+    /// zero if it is proved optimal
+    pub fn gap(&self) -> f64 {
+        let bound = self.final_bound.to_f64();
+        if bound <= 0.0 {
+            0.0
+        } else {
+            ((bound - self.best.get_score().to_f64()) / bound).max(0.0)
+        }
+    }
+
+    /// The best solution, without the rest of the report
+    #[inline]
+    pub fn into_best(self) -> Sol {
+        self.best
+    }
+}
+
+impl<Sol> Deref for SolveReport<Sol> {
+    type Target = Sol;
+
+    #[inline]
+    fn deref(&self) -> &Sol {
+        &self.best
+    }
+}
+
+impl<Sol: Solution> fmt::Display for SolveReport<Sol> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "best score {} (bound {}) after {} nodes, depth {}, {} improvements, \
+             best found after {:?} of {:?}, stopped: {}",
+            self.best.get_score(),
+            self.final_bound,
+            self.nodes_visited,
+            self.max_depth,
+            self.improvements,
+            self.time_to_best,
+            self.elapsed,
+            self.stop_reason
+        )?;
+        match self.visited {
+            Some(visited) => write!(f, ", {}", visited),
+            None => Ok(()),
+        }
+    }
+}
+
+///////////////////// TESTs for SolveReport /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{
+        DepthFirstSolver, MhdMonteCarloSolver, Problem01Knapsack, RestartSolver,
+    };
+    use optimizer::{Problem, Solver, SolverConfig};
+
+    #[test]
+    fn test_solve_report() {
+        const NUM_DECISIONS: usize = 14;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let root_bound = knapsack.solution_best_score(&knapsack.starting_solution());
+
+        // An exhaustive search: all the way down, and proved optimal
+        let mut solver = DepthFirstSolver::new(NUM_DECISIONS);
+        let report = solver
+            .find_best_solution(&knapsack, Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(report.stop_reason, StopReason::Finished);
+        assert_eq!(report.final_bound, report.get_score());
+        assert!(report.improvements <= report.nodes_visited);
+        assert!(0 < report.max_depth && report.max_depth < NUM_DECISIONS);
+        assert_eq!(report.time_to_target(), None);
+        assert!(!report.to_string().is_empty());
+
+        // Stopped early: the bound of the frontier is all it can promise
+        let config = SolverConfig {
+            node_limit: Some(3),
+            ..Default::default()
+        };
+        let report = solver.find_best_solution(&knapsack, config).unwrap();
+        assert_eq!(
+            (report.stop_reason, report.nodes_visited),
+            (StopReason::NodeLimit, 3)
+        );
+        assert!(report.get_score() <= report.final_bound && report.final_bound <= root_bound);
+
+        // A heuristic can't prove anything
+        let mut solver = MhdMonteCarloSolver::builder(&knapsack);
+        let report = solver
+            .find_best_solution(&knapsack, Duration::from_millis(50))
+            .unwrap();
+        assert_eq!(report.final_bound, root_bound);
+        assert!(report.time_to_best <= report.elapsed);
+
+        // Restarts add up their runs
+        let mut solver =
+            RestartSolver::<_, DepthFirstSolver<_>>::wrap(DepthFirstSolver::new(NUM_DECISIONS), 3);
+        let report = solver
+            .find_best_solution(&knapsack, Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(report.final_bound, report.get_score());
+        assert!(3 <= report.nodes_visited);
+        assert!(report.improvements <= 3);
+    }
+}
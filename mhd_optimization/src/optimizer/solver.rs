@@ -1,16 +1,78 @@
-use std::time::{Duration, Instant};
+use std::collections::HashSet;
+use std::time::Duration;
 
 use log::*; // for info, trace, warn, etc.
-use std::error::Error;
 // The next two imports are needed only for csv file writing (see bottom of fle)
 // use std::fs::OpenOptions; // and/or File, if we want to overwrite a file...
 // use std::io::prelude::*; // for writeln! (write_fmt)
 
-use mhd_memory::ScoreType;
-use optimizer::{Problem, Solution};
+use mhd_memory::{weight, Score, ScoreType};
+use optimizer::{EliteArchive, MemoryUsage, PriorityType, Problem, Solution, SolverConfig};
+use optimizer::{IterationStats, SearchDriver, SearchObserver, SolveIter, SolveReport};
+use optimizer::{MhdError, StopReason, VisitedStats};
 
-// Noe: "cargo test" expects tests to finish in less than 60 seconds
-static GLOBAL_TIME_LIMIT: Duration = Duration::from_secs(60); // can be changed
+#[cfg(feature = "async")]
+use optimizer::AsyncSearch;
+
+/// ## The Step Event Enum
+///
+/// What happened during one call of `Solver::step`,
+/// i.e. during one iteration of the unified optimization loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepEvent {
+    /// A complete solution was popped, but it was no better than the best solution so far.
+    Popped,
+    /// An incomplete solution was popped and branched, and this many children were pushed.
+    Pushed(usize),
+    /// A new best solution was found (and stored).
+    NewBest,
+    /// The popped solution -- or all of its children -- could not beat the best solution so far.
+    Pruned,
+    /// There was nothing left to pop (or the solver says it is finished): the search is over.
+    Finished,
+}
+
+/// ## The Step Outcome Struct
+///
+/// What `Solver::step` returns: what happened (see `StepEvent`), and how deep in the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepOutcome {
+    pub event: StepEvent,
+    /// Number of decisions made in the popped solution (None if nothing was popped)
+    pub depth: Option<usize>,
+}
+
+impl StepOutcome {
+    /// Nothing (left) to pop
+    pub const FINISHED: StepOutcome = StepOutcome {
+        event: StepEvent::Finished,
+        depth: None,
+    };
+}
+
+/// ## The Frontier Entry Struct
+///
+/// A summary of one open solution in a solver's frontier, see `Solver::snapshot_frontier`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrontierEntry {
+    /// Number of decisions made so far
+    pub depth: usize,
+    pub score: ScoreType,
+    /// The best score this solution could still reach (its "upper" bound)
+    pub bound: ScoreType,
+    pub priority: PriorityType,
+}
+
+impl FrontierEntry {
+    pub fn of<Sol: Solution>(solution: &Sol) -> Self {
+        Self {
+            depth: weight(solution.mask()) as usize,
+            score: solution.get_score(),
+            bound: solution.get_best_score(),
+            priority: solution.priority(),
+        }
+    }
+}
 
 /// ## The Solver Trait
 ///
@@ -26,11 +88,7 @@ pub trait Solver<Sol: Solution> {
     /// giving things like the number of solutions in the container, etc.
     fn short_description(&self) -> String;
 
-    // Constructors
-
-    /// Constructor for a "blank" solution (with no decisions made yet) where
-    /// size is the number of decisions to be made (free variables to assign values to).
-    fn new(size: usize) -> Self;
+    // Constructors: see `SolverBuilder`
 
     // Methods used by the Unified Optimization Algorithm (identified above)
 
@@ -82,6 +140,103 @@ pub trait Solver<Sol: Solution> {
     /// Store new best solution. Note, we take caller's word for it. Solution is not (re)tested.
     fn store_best_solution(&mut self, sol: Sol);
 
+    /// The archive of good and diverse solutions, if this solver keeps one (default: none).
+    /// `new_best_solution` offers every complete solution to it.
+    #[inline]
+    fn elite_archive(&mut self) -> Option<&mut EliteArchive<Sol>> {
+        None
+    }
+
+    /// The (at most) `k` best distinct solutions seen, best first: the best solution and the
+    /// elites of the archive -- for a pool of the k best, see `EliteArchive::top`.
+    /// Without an archive, there's only the best solution.
+    fn best_solutions(&mut self, k: usize) -> Vec<Sol> {
+        let mut pool = EliteArchive::top(k.max(1));
+        pool.offer(self.best_solution());
+        if let Some(archive) = self.elite_archive() {
+            for elite in archive.elites() {
+                pool.offer(elite);
+            }
+        };
+        pool.elites().iter().take(k).cloned().collect()
+    }
+
+    /// Seed the solver's own random numbers, see `SolverConfig::seed`. Default: nothing --
+    /// most solvers draw theirs from `rand::thread_rng`, which can't be seeded.
+    fn reseed(&mut self, _seed: u64) {}
+
+    /// A summary of (at most `limit` of) the open solutions, in the order they will be popped,
+    /// for debugging and visualizing the search. Default: none (no frontier to show);
+    /// `DepthFirstSolver` and `BestFirstSolver` show theirs.
+    fn snapshot_frontier(&self, _limit: usize) -> Vec<FrontierEntry> {
+        Vec::new()
+    }
+
+    /// Does this solver only ever drop solutions which can't beat the best one (by their bound),
+    /// and show all the others in `snapshot_frontier`? Then the bounds of its frontier bound
+    /// the optimum -- and once it is finished, its best solution is optimal
+    /// (see `SolveReport::final_bound`). Default: no -- heuristics aren't.
+    #[inline]
+    fn is_exhaustive(&self) -> bool {
+        false
+    }
+
+    /// The best bound of the open solutions this solver dropped although they might have
+    /// beaten its best solution (e.g. to save memory), if any: then it isn't exhaustive any more,
+    /// but its frontier and this still bound the optimum (see `SolveReport::final_bound`).
+    /// Default: None, nothing dropped.
+    #[inline]
+    fn dropped_bound(&self) -> Option<ScoreType> {
+        None
+    }
+
+    /// How often this solver's `VisitedSet` (if it has one) hit and missed, see
+    /// `SolveReport::visited`. Default: None, no visited set.
+    #[inline]
+    fn visited_stats(&self) -> Option<VisitedStats> {
+        None
+    }
+
+    /// Approximately how much memory this solver holds, see `MemoryUsage`.
+    /// Default: nothing worth mentioning (just the best solution, etc.);
+    /// solvers with a frontier, a tree or an MHD memory report them.
+    #[inline]
+    fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::default()
+    }
+
+    /// Take over the results of `other`, a solver of the same kind which searched (part of)
+    /// the same problem, e.g. in parallel: keep the better of the two best solutions, and offer
+    /// the other's elites to this solver's archive (if both keep one).
+    fn merge_best_solutions(&mut self, other: &mut Self)
+    where
+        Self: Sized,
+    {
+        if self.best_score() < other.best_score() {
+            self.store_best_solution(other.best_solution().clone());
+        };
+        let other_elites: Vec<Sol> = match other.elite_archive() {
+            None => Vec::new(),
+            Some(archive) => archive.elites().to_vec(),
+        };
+        if let Some(archive) = self.elite_archive() {
+            for elite in other_elites.iter() {
+                archive.offer(elite);
+            }
+        };
+    }
+
+    /// Recombine the partial search of `other` (e.g. a parallel worker) with this one.
+    /// Default: only the best solutions are merged (see `merge_best_solutions`), the other's
+    /// open solutions are dropped. Solvers with a frontier (`DepthFirstSolver`,
+    /// `BestFirstSolver`) take those over, too, so that the merged search can continue.
+    fn merge(&mut self, mut other: Self)
+    where
+        Self: Sized,
+    {
+        self.merge_best_solutions(&mut other);
+    }
+
     /// The next method looks at a complete solution and, if it is the best, remembers it
     /// (at the very least -- some form of "machine learning" may also take place).
     /// Every complete solution see so far should be sent through this method.
@@ -93,6 +248,9 @@ pub trait Solver<Sol: Solution> {
     ) -> bool {
         debug_assert!(problem.solution_is_complete(&solution));
         debug_assert!(problem.rules_audit_passed(&solution));
+        if let Some(archive) = self.elite_archive() {
+            archive.offer(&solution);
+        };
         let result = problem.better_than(&solution, self.best_solution());
         if result {
             // i.e. if solution is better than best_solution
@@ -121,24 +279,24 @@ pub trait Solver<Sol: Solution> {
     }
 
     /*******************************************************************************/
-    /// This is the crux of this whole project: The `find_best_solution` method.
-    /// It does what it says here.
-    /// Originally outside this (Problem) Trait, but the compiler is making this difficult...
-    #[allow(clippy::or_fun_call)]
-    fn find_best_solution<Prob: Problem<Sol = Sol>>(
-        &mut self,
-        problem: &Prob,
-        time_limit: Duration,
-    ) -> Result<Sol, Box<dyn Error>> {
-        let global_start_time = Instant::now();
-        let mut start_time = Instant::now();
+    /// Prepare this solver for a new search of `problem`:
+    /// A random (complete) solution becomes the best solution so far,
+    /// and the starting solution (the root of the search tree) is pushed.
+    /// `find_best_solution` calls this first; if you want to drive the search yourself
+    /// with `step`, call it yourself, too.
+    #[inline]
+    fn start_search<Prob: Problem<Sol = Sol>>(&mut self, problem: &Prob) {
+        self.start_search_from(problem, problem.starting_solution());
+    }
 
+    /// Just like `start_search`, but search only the subtree below `root`
+    /// (a solution with some decisions already made), instead of the whole tree.
+    /// Used e.g. to farm out subtrees to distributed workers.
+    fn start_search_from<Prob: Problem<Sol = Sol>>(&mut self, problem: &Prob, root: Sol) {
         // The best solution is currently defined, and randomized, but wrong.
         // Do it right.
         self.store_best_solution(problem.random_solution());
 
-        // define some solution to be "best-so-far"
-        let mut num_visitations: i64 = 0;
         debug_assert!(problem.solution_is_complete(self.best_solution()));
         debug_assert!(problem.solution_is_legal(self.best_solution()));
         info!("Optimizing Problem {}", problem.short_description());
@@ -147,110 +305,445 @@ pub trait Solver<Sol: Solution> {
             "First Random Solution (short) {}",
             self.best_solution().short_description()
         );
-        trace!("; visits; depth; score; complete; high score;");
+        trace!("; depth; score; complete; high score;");
         trace!(
-            "; {}; {}; {}; {}; {};",
-            num_visitations,
+            "; {}; {}; {}; {};",
             problem.problem_size(),
             self.best_solution().get_score(),
             true, // by definition
             self.best_solution().get_score()
         );
 
-        // start at the root of the tree
+        // start at the root of the (sub)tree
         // debug_assert!(self.is_empty()); <-- Doesn't hold for mcts_solver (etc.)
-        self.push(problem.starting_solution());
-
-        loop {
-            num_visitations += 1;
-
-            // Get a solution from the solver -- "pop" a solution
-            let pop_result = self.pop();
+        debug_assert!(problem.rules_audit_passed(&root));
+        self.push(root);
+    } // end start_search_from
+
+    /// Advance the search by exactly one iteration of the unified optimization loop:
+    /// pop one solution, and then either evaluate it (if complete) or branch and bound it.
+    /// The `StepOutcome` returned says what happened, and at which depth, see there.
+    /// `start_search` must have been called first. To step within the limits of a
+    /// `SolverConfig` (and get a `SolveReport` at the end), use a `SearchDriver`.
+    ///
+    /// ```rust
+    /// use mhd_optimization::optimizer::{Problem, Solver, StepEvent};
+    /// use mhd_optimization::implementations::{DepthFirstSolver, ProblemSubsetSum};
+    ///
+    /// let problem = ProblemSubsetSum::random(8);
+    /// let mut solver = DepthFirstSolver::new(8);
+    /// solver.start_search(&problem);
+    /// let mut steps = 0;
+    /// while StepEvent::Finished != solver.step(&problem).event {
+    ///     steps += 1;
+    /// }
+    /// assert!(0 < steps);
+    /// assert_eq!(solver.best_score(), problem.capacity);
+    /// ```
+    fn step<Prob: Problem<Sol = Sol>>(&mut self, problem: &Prob) -> StepOutcome {
+        self.step_within(problem, None, &mut |_: &Sol| {})
+    }
 
-            if pop_result.is_none() {
+    /// `step`, with a looser bound if there is an `epsilon`: then a partial solution is only
+    /// pruned if it can't even come within `epsilon` of the best score, so ties (and near ties)
+    /// are explored, too. `on_complete` is shown every complete solution the step finds,
+    /// better than the best or not (see `find_all_best_solutions`).
+    #[allow(clippy::or_fun_call)]
+    fn step_within<Prob, F>(
+        &mut self,
+        problem: &Prob,
+        epsilon: Option<ScoreType>,
+        on_complete: &mut F,
+    ) -> StepOutcome
+    where
+        Prob: Problem<Sol = Sol>,
+        F: FnMut(&Sol),
+    {
+        // BOUND: Can `solution` still beat the best (or come within epsilon of it)?
+        let promising = |solver: &Self, solution: &Sol| match epsilon {
+            None => problem.can_be_better_than(solution, solver.best_solution()),
+            Some(epsilon) => {
+                solver.best_score() <= problem.solution_best_score(solution).saturating_add(epsilon)
+            }
+        };
+        if self.is_finished() {
+            return StepOutcome::FINISHED;
+        };
+
+        // Get a solution from the solver -- "pop" a solution
+        let next_solution = match self.pop() {
+            None => {
                 debug!("Solver: Pop returns None, so we're done here!");
-                break;
-            };
+                return StepOutcome::FINISHED;
+            }
+            Some(solution) => solution,
+        };
+        let depth = Some(weight(next_solution.mask()) as usize);
 
-            let next_solution = pop_result.unwrap(); // must be unwrappable, see above....
-
-            trace!(
-                // CSV Fields: "; visits; depth; score; complete; high score;"
-                "; {}; {}; {}; {}; {};",
-                num_visitations,
-                problem
-                    .first_open_decision(&next_solution)
-                    .unwrap_or(problem.problem_size()),
-                next_solution.get_score(),
-                problem.solution_is_complete(&next_solution),
-                self.best_solution().get_score()
-            );
+        trace!(
+            // CSV Fields: "; depth; score; complete; high score;"
+            "; {}; {}; {}; {};",
+            problem
+                .first_open_decision(&next_solution)
+                .unwrap_or(problem.problem_size()),
+            next_solution.get_score(),
+            problem.solution_is_complete(&next_solution),
+            self.best_solution().get_score()
+        );
 
-            debug_assert!(problem.rules_audit_passed(&next_solution));
-
-            if problem.solution_is_complete(&next_solution) {
-                if self.new_best_solution(problem, next_solution) {
-                    // Reset timer!
-                    // That means we have converted if we go for time_limit without a new best solution!
-                    start_time = Instant::now();
-                    // new_best_solution already gave debug output,
-                    // but without start_time or num_visitations...
-                    debug!(
-                        // CSV Fiels: "; visits; depth; score; complete; high score;"
-                        "Solver found new BEST after {:?}, {} visitations, score = {} ",
-                        start_time,
-                        num_visitations,
+        debug_assert!(problem.rules_audit_passed(&next_solution));
+
+        let event = if problem.solution_is_complete(&next_solution) {
+            on_complete(&next_solution);
+            if self.new_best_solution(problem, next_solution) {
+                StepEvent::NewBest
+            } else {
+                StepEvent::Popped
+            }
+        } else if promising(self, &next_solution) {
+            // BOUND (above) and BRANCH (below)
+
+            // Get children
+            let children = self.children_of_solution(&next_solution, problem);
+
+            // Evaluate complete children, push (some) incomplete chldren
+            let mut num_pushed: usize = 0;
+            let mut found_new_best = false;
+            for child in children {
+                debug_assert!(problem.rules_audit_passed(&child));
+                if !problem.solution_is_complete(&child) {
+                    // child is incomplete
+                    if promising(self, &child) {
+                        self.push(child);
+                        num_pushed += 1;
+                    }
+                } else {
+                    // if solution IS complete
+                    // Compare above: "; depth; score; complete; high score;");
+                    trace!(
+                        "; {}; {}; {}; {};",
+                        problem.problem_size(),
+                        child.get_score(),
+                        true, // by definition
                         self.best_solution().get_score()
                     );
-                };
-                // do not push....
-            } else if problem.can_be_better_than(&next_solution, self.best_solution()) {
-                // BOUND (above) and BRANCH (below)
-
-                // Get children
-                let children = self.children_of_solution(&next_solution, problem);
-
-                // Evaluate complete children, push (some) incomplete chldren
-                for child in children {
-                    debug_assert!(problem.rules_audit_passed(&child));
-                    if !problem.solution_is_complete(&child) {
-                        // child is incomplete
-                        if problem.can_be_better_than(&child, self.best_solution()) {
-                            self.push(child); // clone because rustc says so...
-                        }
-                    } else {
-                        // if solution IS complete
-                        // Compare above: "; depth; score; complete; high score;");
-                        trace!(
-                            "; {}; {}; {}; {}; {};",
-                            num_visitations,
-                            problem.problem_size(),
-                            child.get_score(),
-                            true, // by definition
-                            self.best_solution().get_score()
-                        );
-                        // Learn the new complete solution, and test if it is the best so far
-                        if self.new_best_solution(problem, child) {
-                            // Reset timer!
-                            // That means we have converted if we go for time_limit without a new best solution!
-                            start_time = Instant::now();
-                        }
-                    } // end if complete
-                } // end for 0, 1 or 2 children
-            }; // end if complete or can be better than current best...
-
-            // Terminate out if loop?
-            if self.is_finished()
-                || (time_limit < start_time.elapsed())
-                || (GLOBAL_TIME_LIMIT < global_start_time.elapsed())
-            {
-                break;
-            }; // end if terminating
-        } // end loop
+                    // Learn the new complete solution, and test if it is the best so far
+                    on_complete(&child);
+                    if self.new_best_solution(problem, child) {
+                        found_new_best = true;
+                    }
+                } // end if complete
+            } // end for 0, 1 or 2 children
+
+            if found_new_best {
+                StepEvent::NewBest
+            } else if 0 < num_pushed {
+                StepEvent::Pushed(num_pushed)
+            } else {
+                StepEvent::Pruned
+            }
+        } else {
+            // next_solution cannot be better than the best so far
+            StepEvent::Pruned
+        }; // end if complete or can be better than current best...
+        StepOutcome { event, depth }
+    } // end step_within
 
-        // Done. Take a deep breath, print debug print, then return result.
+    /// This is the crux of this whole project: The `find_best_solution` method.
+    /// It does what it says here.
+    /// Originally outside this (Problem) Trait, but the compiler is making this difficult...
+    /// Now it is just a driver, calling `start_search` once and then `step` until done
+    /// (see `continue_search`). When it is done is up to `config` (see `SolverConfig`),
+    /// or just a time limit. Returns the best solution, and how the search went
+    /// (see `SolveReport`) -- or, if the best solution isn't even legal, an `MhdError`:
+    /// `Infeasible` if an exhaustive search finished, `Timeout` if the time ran out,
+    /// `Interrupted` if another limit (or a cancel) stopped it.
+    fn find_best_solution<Prob: Problem<Sol = Sol>, C: Into<SolverConfig>>(
+        &mut self,
+        problem: &Prob,
+        config: C,
+    ) -> Result<SolveReport<Sol>, MhdError> {
+        self.start_search(problem);
+        self.continue_search(problem, config)
+    }
+
+    /// `find_best_solution`, telling `observer` about every new best solution and every
+    /// iteration on the way, see `SearchObserver`.
+    fn find_best_solution_observed<Prob, C, O>(
+        &mut self,
+        problem: &Prob,
+        config: C,
+        observer: &mut O,
+    ) -> Result<SolveReport<Sol>, MhdError>
+    where
+        Prob: Problem<Sol = Sol>,
+        C: Into<SolverConfig>,
+        O: SearchObserver<Sol> + ?Sized,
+    {
+        self.start_search(problem);
+        self.continue_search_observed(problem, config, None, observer)
+    }
+
+    /// `find_best_solution` as an iterator of ever better solutions (the incumbent first),
+    /// which searches only while it is asked for the next one, see `SolveIter`.
+    fn solve_iter<'a, Prob: Problem<Sol = Sol>, C: Into<SolverConfig>>(
+        &'a mut self,
+        problem: &'a Prob,
+        config: C,
+    ) -> SolveIter<'a, Self, Prob>
+    where
+        Self: Sized,
+    {
+        SolveIter::new(self, problem, config)
+    }
+
+    /// `find_best_solution` as a future (feature "async"), which yields now and then,
+    /// so that it can run inside async services. See `AsyncSearch`.
+    #[cfg(feature = "async")]
+    fn find_best_solution_async<'a, Prob: Problem<Sol = Sol>, C: Into<SolverConfig>>(
+        &'a mut self,
+        problem: &'a Prob,
+        config: C,
+    ) -> AsyncSearch<'a, Self, Prob>
+    where
+        Self: Sized,
+    {
+        AsyncSearch::new(self, problem, config)
+    }
+
+    /// Like `find_best_solution`, but with a warm start: `incumbent` (a complete solution,
+    /// e.g. the best solution of a slightly different problem, see `DecisionMap`)
+    /// is the best solution so far -- unless the random starting solution happens to be better.
+    fn find_best_solution_from<Prob: Problem<Sol = Sol>, C: Into<SolverConfig>>(
+        &mut self,
+        problem: &Prob,
+        incumbent: Sol,
+        config: C,
+    ) -> Result<SolveReport<Sol>, MhdError> {
+        self.start_search(problem);
+        self.new_best_solution(problem, incumbent);
+        self.continue_search(problem, config)
+    }
 
-        let result = self.best_solution();
+    /// Like `find_best_solution`, but stop as soon as the best solution reaches `target`
+    /// (e.g. the known optimum of a benchmark problem). Returns the best solution, and the
+    /// time it took to reach the target -- `None` if the search finished or converged first.
+    /// Time-to-target, over many runs, is the standard way to compare randomized heuristics.
+    ///
+    /// ```rust
+    /// use mhd_optimization::optimizer::{Problem, Solution, Solver};
+    /// use mhd_optimization::implementations::{DepthFirstSolver, ProblemSubsetSum};
+    /// # extern crate mhd_memory;
+    /// use mhd_memory::ScoreType;
+    /// use std::time::Duration;
+    ///
+    /// let problem = ProblemSubsetSum::random(12);
+    /// let mut solver = DepthFirstSolver::new(12);
+    /// let (the_best, time_to_target) = solver
+    ///     .find_target_solution(&problem, Duration::from_secs(1), problem.capacity)
+    ///     .unwrap();
+    /// assert_eq!(the_best.get_score(), problem.capacity);
+    /// assert!(time_to_target.is_some());
+    ///
+    /// // An unreachable target: the search runs to the end, as usual
+    /// let (the_best, time_to_target) = solver
+    ///     .find_target_solution(
+    ///         &problem,
+    ///         Duration::from_secs(1),
+    ///         problem.capacity + 1 as ScoreType,
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(the_best.get_score(), problem.capacity);
+    /// assert_eq!(time_to_target, None);
+    /// ```
+    fn find_target_solution<Prob: Problem<Sol = Sol>, C: Into<SolverConfig>>(
+        &mut self,
+        problem: &Prob,
+        config: C,
+        target: ScoreType,
+    ) -> Result<(Sol, Option<Duration>), MhdError> {
+        self.start_search(problem);
+        self.continue_search_to_target(problem, config, Some(target))
+    }
+
+    /// Like `find_best_solution`, but return *every* complete solution found whose score is
+    /// within `epsilon` of the best score found, best first (and no two with the same decisions).
+    /// With `epsilon` zero, that is all the (equally) optimal solutions, ties included --
+    /// so partial solutions are only pruned if they can't even come within `epsilon` of the best.
+    /// The search runs the same loop as `find_best_solution`, just with a looser bound
+    /// (see `step_within`), so it stops at the same limits of `config` (see `SolverConfig`).
+    ///
+    /// ```rust
+    /// use mhd_optimization::optimizer::{MinimalSolution, Problem, Solution, Solver};
+    /// use mhd_optimization::implementations::{DepthFirstSolver, ProblemSubsetSum};
+    /// # extern crate mhd_memory;
+    /// use mhd_memory::{ScoreType, ZERO_SCORE};
+    /// use std::time::Duration;
+    ///
+    /// let mut problem = ProblemSubsetSum::new(5);
+    /// problem.weights = [1, 1, 1, 2, 3].iter().map(|&w| w as ScoreType).collect();
+    /// problem.capacity = 3 as ScoreType;
+    /// let mut solver = DepthFirstSolver::<MinimalSolution>::new(5);
+    /// let optima = solver
+    ///     .find_all_best_solutions(&problem, Duration::from_secs(1), ZERO_SCORE)
+    ///     .unwrap();
+    /// // 1+1+1, 1+2 (three ways) and 3
+    /// assert_eq!(optima.len(), 5);
+    /// assert!(optima.iter().all(|solution| solution.get_score() == 3 as ScoreType));
+    /// ```
+    fn find_all_best_solutions<Prob: Problem<Sol = Sol>, C: Into<SolverConfig>>(
+        &mut self,
+        problem: &Prob,
+        config: C,
+        epsilon: ScoreType,
+    ) -> Result<Vec<Sol>, MhdError> {
+        let mut no_observer = |_: &Sol, _: Duration, _: u64| {};
+        self.find_all_best_solutions_observed(problem, config, epsilon, &mut no_observer)
+    }
+
+    /// `find_all_best_solutions`, telling `observer` what happens, see `SearchObserver`.
+    fn find_all_best_solutions_observed<Prob, C, O>(
+        &mut self,
+        problem: &Prob,
+        config: C,
+        epsilon: ScoreType,
+        observer: &mut O,
+    ) -> Result<Vec<Sol>, MhdError>
+    where
+        Prob: Problem<Sol = Sol>,
+        C: Into<SolverConfig>,
+        O: SearchObserver<Sol> + ?Sized,
+    {
+        self.start_search(problem);
+        let mut found: Vec<Sol> = Vec::new();
+        let mut seen: HashSet<Vec<Option<bool>>> = HashSet::new();
+        let mut best_score = self.best_score();
+        // Keep a complete solution if it's near enough to the best (and new)
+        let mut keep = |solution: &Sol| {
+            let score = solution.get_score();
+            if score.cmp_scores(&best_score).is_gt() {
+                best_score = score;
+            };
+            let decisions = (0..solution.size())
+                .map(|index| solution.get_decision(index))
+                .collect();
+            if best_score <= score.saturating_add(epsilon) && seen.insert(decisions) {
+                found.push(solution.clone());
+            };
+        };
+        keep(self.best_solution());
+        let report =
+            self.continue_search_within(problem, config, None, Some(epsilon), &mut keep, observer)?;
+
+        // The best score may have risen since some were found
+        let best_score = report.get_score();
+        found.retain(|solution| best_score <= solution.get_score().saturating_add(epsilon));
+        found.sort_by(|a, b| b.get_score().cmp_scores(&a.get_score()));
+        info!(
+            "Optimizer finds {} solutions within {} of best score {}",
+            found.len(),
+            epsilon,
+            best_score
+        );
+        Ok(found)
+    } // end find_all_best_solutions_observed
+
+    /// Call `step` until the search is finished, or has converged, i.e. no new best solution
+    /// was found for the time limit -- or until another limit of `config` is reached,
+    /// see `SolverConfig`. Then return the best solution (and the rest of the `SolveReport`).
+    /// `start_search` must have been called first.
+    fn continue_search<Prob: Problem<Sol = Sol>, C: Into<SolverConfig>>(
+        &mut self,
+        problem: &Prob,
+        config: C,
+    ) -> Result<SolveReport<Sol>, MhdError> {
+        let mut no_observer = |_: &Sol, _: Duration, _: u64| {};
+        self.continue_search_observed(problem, config, None, &mut no_observer)
+    }
+
+    /// `continue_search`, but (if there is a `target`) stop as soon as the best score reaches
+    /// it, too. Returns the best solution and the time to target, see `find_target_solution`.
+    fn continue_search_to_target<Prob: Problem<Sol = Sol>, C: Into<SolverConfig>>(
+        &mut self,
+        problem: &Prob,
+        config: C,
+        target: Option<ScoreType>,
+    ) -> Result<(Sol, Option<Duration>), MhdError> {
+        let mut no_observer = |_: &Sol, _: Duration, _: u64| {};
+        let report = self.continue_search_observed(problem, config, target, &mut no_observer)?;
+        let time_to_target = report.time_to_target();
+        Ok((report.into_best(), time_to_target))
+    }
+
+    /// `continue_search_to_target`, telling `observer` what happens, see `SearchObserver`.
+    fn continue_search_observed<Prob, C, O>(
+        &mut self,
+        problem: &Prob,
+        config: C,
+        target: Option<ScoreType>,
+        observer: &mut O,
+    ) -> Result<SolveReport<Sol>, MhdError>
+    where
+        Prob: Problem<Sol = Sol>,
+        C: Into<SolverConfig>,
+        O: SearchObserver<Sol> + ?Sized,
+    {
+        self.continue_search_within(problem, config, target, None, &mut |_: &Sol| {}, observer)
+    }
+
+    /// `continue_search_observed`, stepping with `step_within` (with a bound within `epsilon`
+    /// of the best score, if there is one, and `on_complete` shown every complete solution).
+    /// This is the loop all the other searches (`find_best_solution`...) run,
+    /// a thin one: the `SearchDriver` does the bookkeeping.
+    fn continue_search_within<Prob, C, O, F>(
+        &mut self,
+        problem: &Prob,
+        config: C,
+        target: Option<ScoreType>,
+        epsilon: Option<ScoreType>,
+        on_complete: &mut F,
+        observer: &mut O,
+    ) -> Result<SolveReport<Sol>, MhdError>
+    where
+        Prob: Problem<Sol = Sol>,
+        C: Into<SolverConfig>,
+        O: SearchObserver<Sol> + ?Sized,
+        F: FnMut(&Sol),
+    {
+        let config = config.into();
+        if let Some(seed) = config.seed {
+            self.reseed(seed);
+        };
+        let mut driver = SearchDriver::new(self, config, target);
+        while driver.stop_reason().is_none() {
+            let StepOutcome { event, .. } = driver.step_within(self, problem, epsilon, on_complete);
+            if StepEvent::NewBest == event {
+                observer.on_new_best(self.best_solution(), driver.elapsed(), driver.num_steps());
+            };
+            observer.on_iteration(&IterationStats {
+                visits: driver.num_steps(),
+                elapsed: driver.elapsed(),
+                event,
+                best_score: self.best_score(),
+                frontier: self.number_of_solutions(),
+            });
+        } // end while not stopped
+
+        // Done. Take a deep breath, print debug print, then return result.
+        let report = driver.report(self, problem);
+        info!(
+            "Optimizer stops ({}) after {:?}, {} visitations",
+            report.stop_reason, report.elapsed, report.nodes_visited
+        );
+        observer.on_stop(report.stop_reason);
+        if !problem.solution_is_legal(&report.best) {
+            warn!("Optimizer stops ({}) without a legal solution", report.stop_reason);
+            return Err(match report.stop_reason {
+                StopReason::Finished if self.is_exhaustive() => MhdError::Infeasible,
+                StopReason::TimeLimit | StopReason::TotalTimeLimit => MhdError::Timeout,
+                reason => MhdError::Interrupted(reason),
+            });
+        };
 
         // ********************** CSV FILE TRACING ************
         // let mut macrotrace_file = OpenOptions::new()
@@ -261,21 +754,50 @@ pub trait Solver<Sol: Solution> {
         // writeln!(
         //     macrotrace_file,
         //     "\"{}\", \"{}\", \"{}\", {}; {}; {}; {}; {}", // EIGHT fields!
-        //     result.name(),
+        //     report.name(),
         //     self.name(),
         //     problem.name(),
-        //     start_time.elapsed().as_nanos(),
-        //     num_visitations,
+        //     report.elapsed.as_nanos(),
+        //     report.nodes_visited,
         //     self.number_of_solutions(),
-        //     result.get_score(),
-        //     result.get_best_score(),
+        //     report.get_score(),
+        //     report.get_best_score(),
         // )?;
         // ********************** CSV FILE TRACING ************
 
         debug!("Optimizer find best solution in {:?}", problem);
-        debug!("Optimizer converges on soution {:?}", result);
-        info!("Optimizer find best score {}", result.get_score());
+        debug!("Optimizer memory usage: {}", self.memory_usage());
+        debug!("Optimizer converges on soution {:?}", report.best);
+        info!("Optimizer find best score {}", report.get_score());
 
-        Ok(result.clone())
-    } // end default find_best_solution implementation
+        Ok(report)
+    } // end default continue_search_within implementation
 } // end Solver Problem
+
+/// ## The Solver Builder Trait
+///
+/// Every solver is built the same way, for the problem it is to solve:
+/// `S::from_problem(&problem)`. Some solvers need the problem itself (a clone of it,
+/// or its weights), some just its size -- e.g. `DepthFirstSolver::new(size)`,
+/// which is also their `from_problem`.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, Solver, SolverBuilder};
+/// use mhd_optimization::implementations::{DepthFirstSolver, MhdMonteCarloSolver};
+/// use mhd_optimization::implementations::ProblemSubsetSum;
+/// use std::time::Duration;
+///
+/// fn solve<S: SolverBuilder<ProblemSubsetSum>>(problem: &ProblemSubsetSum) -> bool {
+///     let mut solver = S::from_problem(problem);
+///     let best = solver.find_best_solution(problem, Duration::from_millis(50)).unwrap();
+///     problem.solution_is_legal(&best)
+/// }
+///
+/// let problem = ProblemSubsetSum::random(12);
+/// assert!(solve::<DepthFirstSolver<_>>(&problem));
+/// assert!(solve::<MhdMonteCarloSolver<_, _>>(&problem));
+/// ```
+pub trait SolverBuilder<Prob: Problem>: Solver<Prob::Sol> + Sized {
+    /// A new solver (with no search started yet) for `problem`
+    fn from_problem(problem: &Prob) -> Self;
+}
@@ -0,0 +1,381 @@
+/// # Solver Configurations
+///
+/// When should a search stop? `find_best_solution` (and its relatives) take a `SolverConfig`:
+///
+/// * `time_limit`: how long to search -- since the last new best solution (i.e. until the
+///   search has converged), or since the start, see `ConvergenceMode`
+/// * `total_time_limit`: the longest the whole search may take, come what may
+///   (None = no limit, e.g. for long overnight runs)
+/// * `node_limit`: the most steps (solutions popped, see `Solver::step`) the search may make
+/// * `push_limit`: the most solutions the search may push (into the frontier)
+/// * `memory_limit`: the most memory the solver may hold (see `Solver::memory_usage`)
+/// * `seed`: for the solver's own random numbers, see `Solver::reseed`
+/// * `cancel`: a `CancelHandle`, to stop the search from somewhere else (e.g. a GUI thread)
+///
+/// A search also stops when the solver is finished, whatever the limits.
+/// However it stops, the solver keeps (and returns) the best solution found so far,
+/// and says why it stopped (a `StopReason`, see `SearchObserver::on_stop`).
+/// The step, push and memory limits are the same on every machine -- unlike time limits,
+/// so they make for fair benchmark comparisons.
+/// A plain `Duration` is a `SolverConfig`, too: the time limit, and defaults for the rest.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{ConvergenceMode, Problem, Solver, SolverConfig};
+/// use mhd_optimization::implementations::{DepthFirstSolver, Problem01Knapsack};
+/// use std::time::Duration;
+///
+/// let knapsack = Problem01Knapsack::random(24);
+/// let mut solver = DepthFirstSolver::new(24);
+///
+/// // Converge for a second (at most a minute), as ever
+/// let best = solver.find_best_solution(&knapsack, Duration::from_secs(1)).unwrap();
+/// assert!(knapsack.solution_is_legal(&best));
+///
+/// // At most 100 steps (or 150 pushes), at most a second in all
+/// let config = SolverConfig {
+///     time_limit: Duration::from_secs(1),
+///     node_limit: Some(100),
+///     push_limit: Some(150),
+///     convergence_mode: ConvergenceMode::Deadline,
+///     ..Default::default()
+/// };
+/// let quick = solver.find_best_solution(&knapsack, config).unwrap();
+/// assert!(knapsack.solution_is_legal(&quick));
+/// ```
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use optimizer::{Solution, Solver};
+
+/// Default for `SolverConfig::time_limit`
+pub const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(1);
+
+/// Default for `SolverConfig::total_time_limit`
+/// (Note: "cargo test" expects tests to finish in less than 60 seconds)
+pub const DEFAULT_TOTAL_TIME_LIMIT: Duration = Duration::from_secs(60);
+
+/// What `SolverConfig::time_limit` is measured from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ConvergenceMode {
+    /// Stop when no new best solution was found for the time limit (the search converged)
+    #[default]
+    NoImprovement,
+    /// Stop when the time limit has passed since the start of the search
+    Deadline,
+}
+
+/// Why a search stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StopReason {
+    /// The solver is finished: nothing left to search (or the solver says so)
+    Finished,
+    /// The best score reached the target, see `Solver::find_target_solution`
+    TargetReached,
+    /// The time limit passed -- since the last new best solution (the search converged),
+    /// or since the start, see `ConvergenceMode`
+    TimeLimit,
+    TotalTimeLimit,
+    NodeLimit,
+    PushLimit,
+    MemoryLimit,
+    Cancelled,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            StopReason::Finished => "finished",
+            StopReason::TargetReached => "target reached",
+            StopReason::TimeLimit => "time limit",
+            StopReason::TotalTimeLimit => "total time limit",
+            StopReason::NodeLimit => "node limit",
+            StopReason::PushLimit => "push limit",
+            StopReason::MemoryLimit => "memory limit",
+            StopReason::Cancelled => "cancelled",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// ## Cancel Handles
+///
+/// Cancel a search from somewhere else (e.g. another task, or a thread).
+/// The search stops after its current step (or, if async, at its next poll).
+/// Clones share the flag -- and so does the `Arc<AtomicBool>` a handle is made `from`.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl From<Arc<AtomicBool>> for CancelHandle {
+    fn from(cancelled: Arc<AtomicBool>) -> Self {
+        Self { cancelled }
+    }
+}
+
+impl PartialEq for CancelHandle {
+    /// The same handle (or a clone of it)
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+}
+
+impl Eq for CancelHandle {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolverConfig {
+    /// How long to search, see `convergence_mode`
+    pub time_limit: Duration,
+    /// The longest the whole search may take (None = no limit)
+    pub total_time_limit: Option<Duration>,
+    /// The most steps the search may make (None = no limit)
+    pub node_limit: Option<u64>,
+    /// The most solutions the search may push, as `StepEvent::Pushed` counts them
+    /// (None = no limit)
+    pub push_limit: Option<u64>,
+    /// The most memory (in bytes, approximately, see `MemoryUsage`) the solver may hold
+    /// (None = no limit)
+    pub memory_limit: Option<usize>,
+    /// Seed for the solver's own random numbers, so that runs can be repeated
+    /// (None = a new seed every time), see `Solver::reseed`
+    pub seed: Option<u64>,
+    pub convergence_mode: ConvergenceMode,
+    /// Stop the search when this is cancelled (None = only at the limits)
+    pub cancel: Option<CancelHandle>,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            time_limit: DEFAULT_TIME_LIMIT,
+            total_time_limit: Some(DEFAULT_TOTAL_TIME_LIMIT),
+            node_limit: None,
+            push_limit: None,
+            memory_limit: None,
+            seed: None,
+            convergence_mode: ConvergenceMode::default(),
+            cancel: None,
+        }
+    }
+}
+
+impl From<Duration> for SolverConfig {
+    /// The time limit, and defaults for everything else
+    fn from(time_limit: Duration) -> Self {
+        Self {
+            time_limit,
+            ..Default::default()
+        }
+    }
+}
+
+impl SolverConfig {
+    /// Which limit (if any) stops a search with `solver`, which started at `start_time`,
+    /// found its last new best solution at `last_best_time`, made `steps` and pushed `pushed`
+    /// solutions? (Cancelled counts as a limit; finished doesn't, that's up to the caller.)
+    pub fn limit_reached<Sol: Solution, S: Solver<Sol> + ?Sized>(
+        &self,
+        solver: &S,
+        start_time: Instant,
+        last_best_time: Instant,
+        steps: u64,
+        pushed: u64,
+    ) -> Option<StopReason> {
+        let measured_from = match self.convergence_mode {
+            ConvergenceMode::NoImprovement => last_best_time,
+            ConvergenceMode::Deadline => start_time,
+        };
+        if self.cancel.as_ref().is_some_and(CancelHandle::is_cancelled) {
+            Some(StopReason::Cancelled)
+        } else if self.time_limit < measured_from.elapsed() {
+            Some(StopReason::TimeLimit)
+        } else if self
+            .total_time_limit
+            .is_some_and(|limit| limit < start_time.elapsed())
+        {
+            Some(StopReason::TotalTimeLimit)
+        } else if self.node_limit.is_some_and(|limit| limit <= steps) {
+            Some(StopReason::NodeLimit)
+        } else if self.push_limit.is_some_and(|limit| limit <= pushed) {
+            Some(StopReason::PushLimit)
+        } else if self
+            .memory_limit
+            .is_some_and(|limit| limit <= solver.memory_usage().total_bytes())
+        {
+            Some(StopReason::MemoryLimit)
+        } else {
+            None
+        }
+    }
+
+    /// The part of this config for one of `num_runs` runs (e.g. of a `RestartSolver`):
+    /// the limits divided evenly, and the seed (if any) different for every `run`
+    pub fn for_run(&self, run: usize, num_runs: usize) -> Self {
+        let num_runs = num_runs.max(1);
+        Self {
+            time_limit: self.time_limit / num_runs as u32,
+            total_time_limit: self.total_time_limit.map(|limit| limit / num_runs as u32),
+            node_limit: self.node_limit.map(|limit| limit / num_runs as u64),
+            push_limit: self.push_limit.map(|limit| limit / num_runs as u64),
+            memory_limit: self.memory_limit, // (one run at a time)
+            seed: self.seed.map(|seed| seed.wrapping_add(run as u64)),
+            convergence_mode: self.convergence_mode,
+            cancel: self.cancel.clone(),
+        }
+    }
+}
+
+///////////////////// TESTs for SolverConfig /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{DepthFirstSolver, Problem01Knapsack};
+    use optimizer::{MinimalSolution, Problem, SearchObserver};
+
+    // Remembers why the search stopped
+    #[derive(Default)]
+    struct Stop(Option<StopReason>);
+
+    impl<Sol> SearchObserver<Sol> for Stop {
+        fn on_stop(&mut self, reason: StopReason) {
+            self.0 = Some(reason);
+        }
+    }
+
+    #[test]
+    fn test_limit_reached() {
+        let solver = DepthFirstSolver::<MinimalSolution>::new(8);
+        let long_ago = Instant::now() - Duration::from_millis(500);
+        let now = Instant::now();
+        let limit = |config: &SolverConfig, start_time, last_best_time, steps, pushed| {
+            config.limit_reached(&solver, start_time, last_best_time, steps, pushed)
+        };
+        let config = SolverConfig::from(Duration::from_millis(100));
+        assert_eq!(config.total_time_limit, Some(DEFAULT_TOTAL_TIME_LIMIT));
+        // Converged: no new best since long ago
+        assert_eq!(
+            limit(&config, long_ago, long_ago, 0, 0),
+            Some(StopReason::TimeLimit)
+        );
+        assert_eq!(limit(&config, long_ago, now, 1_000_000, 1_000_000), None);
+
+        // Deadline: long ago is too long ago, even with a new best now
+        let deadline = SolverConfig {
+            convergence_mode: ConvergenceMode::Deadline,
+            ..config.clone()
+        };
+        assert_eq!(
+            limit(&deadline, long_ago, now, 0, 0),
+            Some(StopReason::TimeLimit)
+        );
+        assert_eq!(limit(&deadline, now, now, 0, 0), None);
+
+        // The total time, the steps, the pushes and the memory
+        let total = SolverConfig {
+            time_limit: Duration::from_secs(3600),
+            total_time_limit: Some(Duration::from_millis(100)),
+            ..config
+        };
+        assert_eq!(
+            limit(&total, long_ago, now, 0, 0),
+            Some(StopReason::TotalTimeLimit)
+        );
+        let unlimited = SolverConfig {
+            total_time_limit: None,
+            ..total
+        };
+        assert_eq!(limit(&unlimited, long_ago, now, 0, 0), None);
+        let nodes = SolverConfig {
+            node_limit: Some(10),
+            push_limit: Some(20),
+            ..unlimited.clone()
+        };
+        assert_eq!(limit(&nodes, now, now, 9, 19), None);
+        assert_eq!(limit(&nodes, now, now, 10, 0), Some(StopReason::NodeLimit));
+        assert_eq!(limit(&nodes, now, now, 0, 20), Some(StopReason::PushLimit));
+        let memory = SolverConfig {
+            memory_limit: Some(solver.memory_usage().total_bytes()),
+            ..unlimited
+        };
+        assert_eq!(
+            limit(&memory, now, now, 0, 0),
+            Some(StopReason::MemoryLimit)
+        );
+
+        // Split in runs
+        let run = SolverConfig {
+            seed: Some(7),
+            ..nodes
+        }
+        .for_run(2, 4);
+        assert_eq!(run.time_limit, Duration::from_secs(900));
+        assert_eq!((run.node_limit, run.seed), (Some(2), Some(9)));
+    }
+
+    #[test]
+    fn test_node_limit() {
+        const NUM_DECISIONS: usize = 20;
+        let knapsack = Problem01Knapsack::random(NUM_DECISIONS);
+        let mut solver = DepthFirstSolver::new(NUM_DECISIONS);
+        let config = SolverConfig {
+            node_limit: Some(1),
+            ..Default::default()
+        };
+        // One step: the root is popped, and (at most) its two children pushed
+        let mut stop = Stop::default();
+        let best = solver
+            .find_best_solution_observed(&knapsack, config, &mut stop)
+            .unwrap();
+        assert!(knapsack.solution_is_legal(&best));
+        assert!(solver.number_of_solutions() <= 2);
+        assert!(matches!(
+            stop.0,
+            Some(StopReason::NodeLimit) | Some(StopReason::Finished)
+        ));
+    }
+
+    #[test]
+    fn test_cancel() {
+        const MANY_DECISIONS: usize = 100;
+        let knapsack = Problem01Knapsack::random(MANY_DECISIONS);
+        let mut solver = DepthFirstSolver::new(MANY_DECISIONS);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let config = SolverConfig {
+            time_limit: Duration::from_secs(100),
+            total_time_limit: None,
+            cancel: Some(CancelHandle::from(cancelled.clone())),
+            ..Default::default()
+        };
+        // Cancelled from another thread, soon: the incumbent is returned
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            cancelled.store(true, Ordering::Relaxed);
+        });
+        let start_time = Instant::now();
+        let mut stop = Stop::default();
+        let best = solver
+            .find_best_solution_observed(&knapsack, config, &mut stop)
+            .unwrap();
+        canceller.join().unwrap();
+        assert!(start_time.elapsed() < Duration::from_secs(10));
+        assert!(knapsack.solution_is_legal(&best));
+        assert!(knapsack.solution_is_complete(&best));
+        assert_eq!(best.best, *solver.best_solution());
+        if !solver.is_finished() {
+            assert_eq!(stop.0, Some(StopReason::Cancelled));
+        };
+    }
+}
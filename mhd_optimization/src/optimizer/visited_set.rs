@@ -0,0 +1,183 @@
+/// # Duplicate-State Pruning (for the classical solvers)
+///
+/// With its rules applied, a problem can reach the same state (see
+/// `Problem::transposition_key`) by different ways -- e.g. two subsets of a subset sum
+/// problem with the same sum, which could be completed in exactly the same ways.
+/// A `VisitedSet` lets a classical solver (`DepthFirstSolver`, `BestFirstSolver`) expand
+/// each state only once: a child whose state was seen before is dropped. Unlike memory-guided
+/// pruning, this loses nothing (but a hash collision, now and then). How often it hits and
+/// misses is in the `SolveReport`.
+///
+/// ```rust
+/// use mhd_optimization::optimizer::{Problem, Solution, Solver, VisitedSet};
+/// use mhd_optimization::implementations::{DepthFirstSolver, ProblemSubsetSum};
+/// # extern crate mhd_memory;
+/// use mhd_memory::ScoreType;
+/// use std::time::Duration;
+///
+/// let mut problem = ProblemSubsetSum::new(12);
+/// problem.weights = vec![5 as ScoreType; 12]; // many ways to each sum
+/// problem.capacity = 31 as ScoreType;
+/// let mut solver = DepthFirstSolver::new(12);
+/// solver.visited = Some(VisitedSet::new(1 << 16));
+///
+/// let report = solver.find_best_solution(&problem, Duration::from_secs(1)).unwrap();
+/// assert_eq!(report.get_score(), 30 as ScoreType);
+/// assert!(0 < report.visited.unwrap().hits);
+/// ```
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Add;
+
+use optimizer::Problem;
+
+/// How often a `VisitedSet` was asked about a state: hits were seen before (and dropped),
+/// misses were new
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VisitedStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl Add for VisitedStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            hits: self.hits + other.hits,
+            misses: self.misses + other.misses,
+        }
+    }
+}
+
+impl fmt::Display for VisitedStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} duplicate states, {} new ones",
+            self.hits, self.misses
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VisitedSet {
+    // The transposition keys of the states seen so far
+    keys: HashSet<u64>,
+    /// Remember at most this many states (after that, new states are not dropped, ever)
+    pub max_entries: usize,
+    pub stats: VisitedStats,
+}
+
+impl VisitedSet {
+    /// A set of at most `max_entries` states
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            keys: HashSet::new(),
+            max_entries,
+            stats: VisitedStats::default(),
+        }
+    }
+
+    /// Number of states seen
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Forget every state (but keep the configuration)
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.stats = VisitedStats::default();
+    }
+
+    /// Was the state of `solution` seen before? If not, it has been now (if there's room).
+    pub fn visit<Prob: Problem>(&mut self, problem: &Prob, solution: &Prob::Sol) -> bool {
+        let key = problem.transposition_key(solution);
+        let seen = if self.keys.len() < self.max_entries {
+            !self.keys.insert(key)
+        } else {
+            self.keys.contains(&key)
+        };
+        if seen {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        };
+        seen
+    }
+
+    /// Drop the children whose states were seen before.
+    /// This is what solvers call from their `Solver::children_of_solution`.
+    pub fn filter_children<Prob: Problem>(
+        &mut self,
+        problem: &Prob,
+        children: Vec<Prob::Sol>,
+    ) -> Vec<Prob::Sol> {
+        children
+            .into_iter()
+            .filter(|child| !self.visit(problem, child))
+            .collect()
+    }
+} // end impl VisitedSet
+
+///////////////////// TESTs for VisitedSet /////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use implementations::{BestFirstSolver, DepthFirstSolver, ProblemSubsetSum};
+    use mhd_memory::ScoreType;
+    use optimizer::{Solution, Solver};
+    use std::time::Duration;
+
+    #[test]
+    fn test_visited_set() {
+        const NUM_DECISIONS: usize = 12;
+        let mut problem = ProblemSubsetSum::new(NUM_DECISIONS);
+        problem.weights = vec![3 as ScoreType; NUM_DECISIONS];
+        problem.capacity = 20 as ScoreType;
+
+        let mut visited = VisitedSet::new(2);
+        let root = problem.starting_solution();
+        let one = problem.produce_child(&root, 0, true);
+        let other = problem.produce_child(&problem.produce_child(&root, 0, false), 1, true);
+        assert!(!visited.visit(&problem, &root));
+        assert!(!visited.visit(&problem, &one));
+        assert!(visited.visit(&problem, &one));
+        assert_eq!(visited.len(), 2);
+        assert!(!visited.visit(&problem, &other)); // (full)
+        assert_eq!(visited.stats, VisitedStats { hits: 1, misses: 3 });
+        visited.clear();
+        assert!(visited.is_empty());
+
+        // The same optimum, with fewer nodes
+        let mut plain = DepthFirstSolver::new(NUM_DECISIONS);
+        let plain_report = plain
+            .find_best_solution(&problem, Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(plain_report.visited, None);
+        let mut solver = DepthFirstSolver::new(NUM_DECISIONS);
+        solver.visited = Some(VisitedSet::new(1 << 16));
+        let report = solver
+            .find_best_solution(&problem, Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(report.get_score(), 18 as ScoreType);
+        assert_eq!(report.get_score(), plain_report.get_score());
+        assert!(report.nodes_visited < plain_report.nodes_visited);
+        let stats = report.visited.unwrap();
+        assert!(0 < stats.hits && 0 < stats.misses);
+
+        let mut solver = BestFirstSolver::new(NUM_DECISIONS);
+        solver.visited = Some(VisitedSet::new(1 << 16));
+        let report = solver
+            .find_best_solution(&problem, Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(report.get_score(), 18 as ScoreType);
+        assert!(0 < report.visited.unwrap().hits);
+    }
+}